@@ -0,0 +1,32 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+#![no_main]
+
+use std::io::Cursor;
+
+use libfuzzer_sys::fuzz_target;
+
+use symphonia_core::formats::{FormatOptions, FormatReader};
+use symphonia_core::io::MediaSourceStream;
+use symphonia_bundle_flac::FlacReader;
+
+/// The maximum number of packets to read from a single input.
+const MAX_PACKETS: usize = 1024;
+
+fuzz_target!(|data: &[u8]| {
+    let source = Box::new(Cursor::new(data.to_vec()));
+    let mss = MediaSourceStream::new(source, Default::default());
+
+    if let Ok(mut reader) = FlacReader::try_new(mss, &FormatOptions::default()) {
+        for _ in 0..MAX_PACKETS {
+            if reader.next_packet().is_err() {
+                break;
+            }
+        }
+    }
+});
@@ -0,0 +1,19 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use symphonia_core::io::BufReader;
+use symphonia_core::meta::MetadataBuilder;
+use symphonia_metadata::id3v2::read_id3v2;
+
+fuzz_target!(|data: &[u8]| {
+    let mut builder = MetadataBuilder::new();
+    let _ = read_id3v2(&mut BufReader::new(data), &mut builder);
+});
@@ -234,7 +234,12 @@ impl Decoder for FlacDecoder {
     }
 
     fn reset(&mut self) {
-        // No state is stored between packets, therefore do nothing.
+        // No decoding state is stored between packets. However, the checksum of the stream can
+        // only be verified if all packets are decoded in order. Restart validation such that it
+        // may still succeed if decoding is restarted from the first packet.
+        if self.is_validating {
+            self.validator = Default::default();
+        }
     }
 
     fn codec_params(&self) -> &CodecParameters {
@@ -258,6 +263,15 @@ impl Decoder for FlacDecoder {
         if self.is_validating {
             // Try to get the expected MD5 checksum and compare it against the decoded checksum.
             if let Some(VerificationCheck::Md5(expected)) = self.params.verification_check {
+                // The checksum covers the entire stream. If the number of frames in the stream is
+                // known, verification is inconclusive unless all of them were decoded.
+                let n_decoded = self.validator.n_frames();
+
+                if let Some(n_frames) = self.params.n_frames.filter(|&n| n != n_decoded) {
+                    warn!("verification: only {} of {} frames were decoded", n_decoded, n_frames);
+                    return result;
+                }
+
                 let decoded = self.validator.md5();
 
                 // Only generate the expected and decoded MD5 checksum strings if logging is
@@ -699,3 +713,117 @@ fn lpc_predict<const N: usize>(order: usize, coeffs: &[i32; N], coeff_shift: u32
         buf[i] += (predicted >> coeff_shift) as i32;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::checksum::{Crc16Ansi, Crc8Ccitt, Md5};
+    use symphonia_core::codecs::{CodecParameters, Decoder, DecoderOptions, CODEC_TYPE_FLAC};
+    use symphonia_core::formats::Packet;
+    use symphonia_core::io::Monitor;
+
+    use super::FlacDecoder;
+
+    /// The number of samples in each frame.
+    const BLOCK_LEN: u8 = 16;
+
+    /// The constant sample value of each frame in the test stream.
+    const FRAME_VALUES: [i16; 3] = [100, -200, 300];
+
+    /// Make a frame of 16-bit mono samples with a constant value.
+    fn make_frame(frame_num: u8, value: i16) -> Vec<u8> {
+        // Fixed block size, an 8-bit block length, and the sample rate from the stream
+        // information. One channel of 16-bit samples.
+        let mut frame = vec![0xff, 0xf8, 0x60, 0x08, frame_num, BLOCK_LEN - 1];
+
+        let mut crc8 = Crc8Ccitt::new(0);
+        crc8.process_buf_bytes(&frame);
+        frame.push(crc8.crc());
+
+        // A constant subframe.
+        frame.push(0x00);
+        frame.extend_from_slice(&value.to_be_bytes());
+
+        let mut crc16 = Crc16Ansi::new(0);
+        crc16.process_buf_bytes(&frame);
+        frame.extend_from_slice(&crc16.crc().to_be_bytes());
+
+        frame
+    }
+
+    /// Get the MD5 checksum of the test stream.
+    fn stream_md5() -> [u8; 16] {
+        let mut md5 = Md5::default();
+
+        for value in FRAME_VALUES.iter() {
+            for _ in 0..BLOCK_LEN {
+                md5.process_buf_bytes(&value.to_le_bytes());
+            }
+        }
+
+        md5.md5()
+    }
+
+    /// Make a validating decoder for the test stream, given the stream's MD5 checksum.
+    fn make_decoder(md5: [u8; 16]) -> FlacDecoder {
+        let n_samples = (FRAME_VALUES.len() * usize::from(BLOCK_LEN)) as u64;
+
+        // The stream information block of a 44.1 kHz, mono, 16-bit stream.
+        let mut info = vec![0, BLOCK_LEN, 0, BLOCK_LEN, 0, 0, 0, 0, 0, 0];
+        info.extend_from_slice(&((44100 << 44) | (15 << 36) | n_samples).to_be_bytes());
+        info.extend_from_slice(&md5);
+
+        let mut params = CodecParameters::new();
+        params.for_codec(CODEC_TYPE_FLAC).with_extra_data(info.into_boxed_slice());
+
+        FlacDecoder::try_new(&params, &DecoderOptions { verify: true }).unwrap()
+    }
+
+    fn decode_frames(decoder: &mut FlacDecoder, frames: &[u8]) {
+        for &i in frames {
+            let frame = make_frame(i, FRAME_VALUES[usize::from(i)]);
+            let packet = Packet::new_from_slice(0, 0, u64::from(BLOCK_LEN), &frame);
+
+            decoder.decode(&packet).unwrap();
+        }
+    }
+
+    #[test]
+    fn verify_md5() {
+        let mut decoder = make_decoder(stream_md5());
+        decode_frames(&mut decoder, &[0, 1, 2]);
+        assert_eq!(decoder.finalize().verify_ok, Some(true));
+
+        // A checksum mismatch fails verification.
+        let mut md5 = stream_md5();
+        md5[0] ^= 1;
+
+        let mut decoder = make_decoder(md5);
+        decode_frames(&mut decoder, &[0, 1, 2]);
+        assert_eq!(decoder.finalize().verify_ok, Some(false));
+    }
+
+    #[test]
+    fn verify_md5_partial_decode() {
+        // Verification is inconclusive if the stream was not fully decoded.
+        let mut decoder = make_decoder(stream_md5());
+        decode_frames(&mut decoder, &[0, 1]);
+        assert_eq!(decoder.finalize().verify_ok, None);
+
+        // After seeking past the start of the stream, verification is inconclusive.
+        let mut decoder = make_decoder(stream_md5());
+        decode_frames(&mut decoder, &[0]);
+        decoder.reset();
+        decode_frames(&mut decoder, &[1, 2]);
+        assert_eq!(decoder.finalize().verify_ok, None);
+    }
+
+    #[test]
+    fn verify_md5_after_seek() {
+        // Decoding restarted from the start of the stream is verified.
+        let mut decoder = make_decoder(stream_md5());
+        decode_frames(&mut decoder, &[0, 1, 2, 1, 2]);
+        decoder.reset();
+        decode_frames(&mut decoder, &[0, 1, 2]);
+        assert_eq!(decoder.finalize().verify_ok, Some(true));
+    }
+}
@@ -18,6 +18,7 @@ use symphonia_core::io::Monitor;
 pub struct Validator {
     state: Md5,
     buf: Vec<u8>,
+    n_frames: u64,
 }
 
 impl Validator {
@@ -47,6 +48,8 @@ impl Validator {
         let n_channels = buf.spec().channels.count();
         let n_frames = buf.frames();
 
+        self.n_frames += n_frames as u64;
+
         // Calculate the total size of all the samples in bytes.
         let buf_len = n_channels * n_frames * bytes_per_sample;
 
@@ -69,6 +72,11 @@ impl Validator {
         self.state.process_buf_bytes(buf_slice);
     }
 
+    /// Get the number of frames processed by the validator.
+    pub fn n_frames(&self) -> u64 {
+        self.n_frames
+    }
+
     /// Get the checksum.
     pub fn md5(&mut self) -> [u8; 16] {
         self.state.md5()
@@ -121,3 +129,39 @@ macro_rules! copy_as {
 copy_as!(copy_as_i8, i8);
 copy_as!(copy_as_i16, i16);
 copy_as!(copy_as_i32, i32);
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::audio::{AudioBuffer, Channels, Signal, SignalSpec};
+    use symphonia_core::checksum::Md5;
+    use symphonia_core::io::Monitor;
+
+    use super::Validator;
+
+    #[test]
+    fn verify_validator() {
+        let spec = SignalSpec::new(44100, Channels::FRONT_LEFT | Channels::FRONT_RIGHT);
+
+        let mut buf = AudioBuffer::<i32>::new(4, spec);
+        buf.render_reserved(Some(3));
+        buf.chan_mut(0).copy_from_slice(&[1, -1, 0x12_3456]);
+        buf.chan_mut(1).copy_from_slice(&[2, -2, -0x12_3456]);
+
+        let mut validator = Validator::default();
+        validator.update(&buf, 24);
+        validator.update(&buf, 24);
+
+        assert_eq!(validator.n_frames(), 6);
+
+        // The checksum is of interleaved, little-endian, 24-bit samples.
+        let mut md5 = Md5::default();
+
+        for _ in 0..2 {
+            for sample in &[1i32, 2, -1, -2, 0x12_3456, -0x12_3456] {
+                md5.process_buf_bytes(&sample.to_le_bytes()[..3]);
+            }
+        }
+
+        assert_eq!(validator.md5(), md5.md5());
+    }
+}
@@ -102,6 +102,7 @@ pub struct FrameHeader {
     pub is_original: bool,
     pub has_padding: bool,
     pub has_crc: bool,
+    pub is_free_format: bool,
     pub frame_size: usize,
 }
 
@@ -119,6 +120,17 @@ impl FrameHeader {
         self.version == MpegVersion::Mpeg2p5
     }
 
+    /// Returns the length in bytes of the padding of the frame.
+    #[inline(always)]
+    pub fn padding_len(&self) -> usize {
+        match (self.has_padding, self.layer) {
+            (false, _) => 0,
+            // A layer 1 slot is 4 bytes.
+            (true, MpegLayer::Layer1) => 4,
+            (true, _) => 1,
+        }
+    }
+
     /// Returns the codec type for the frame.
     pub fn codec(&self) -> CodecType {
         match self.layer {
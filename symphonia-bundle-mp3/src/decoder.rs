@@ -6,11 +6,12 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 use symphonia_core::audio::{AsAudioBufferRef, AudioBuffer, AudioBufferRef, Signal};
+use symphonia_core::checksum::Crc16Ansi;
 use symphonia_core::codecs::{CodecDescriptor, CodecParameters, CodecType};
 use symphonia_core::codecs::{Decoder, DecoderOptions, FinalizeResult};
 use symphonia_core::errors::{decode_error, unsupported_error, Result};
 use symphonia_core::formats::Packet;
-use symphonia_core::io::FiniteStream;
+use symphonia_core::io::{FiniteStream, Monitor};
 use symphonia_core::support_codec;
 
 #[cfg(feature = "mp1")]
@@ -22,6 +23,8 @@ use symphonia_core::codecs::CODEC_TYPE_MP3;
 
 use super::{common::*, header};
 
+use log::warn;
+
 #[cfg(feature = "mp1")]
 use crate::layer1;
 #[cfg(feature = "mp2")]
@@ -52,24 +55,84 @@ impl State {
     }
 }
 
+/// Verify the CRC-16 of a frame, including the header. Returns `None` if the frame is not protected
+/// by a CRC, or if CRC verification is not supported for the frame's layer.
+///
+/// Only layer 3 frames are verified. For layer 3, the CRC covers the last 2 bytes of the frame
+/// header and the side information. For layers 1 and 2, the protected bits depend on the bit
+/// allocations, and are not verified.
+fn verify_crc(header: &FrameHeader, frame: &[u8]) -> Option<bool> {
+    if !header.has_crc || header.layer != MpegLayer::Layer3 {
+        return None;
+    }
+
+    // The CRC immediately follows the frame header, and is followed by the side information.
+    let side_info_end = header::MPEG_HEADER_LEN + 2 + header.side_info_len();
+
+    if frame.len() < side_info_end {
+        return Some(false);
+    }
+
+    let expected = u16::from_be_bytes([frame[4], frame[5]]);
+
+    let mut crc16 = Crc16Ansi::new(0xffff);
+    crc16.process_buf_bytes(&frame[2..4]);
+    crc16.process_buf_bytes(&frame[6..side_info_end]);
+
+    Some(crc16.crc() == expected)
+}
+
 /// MPEG1 and MPEG2 audio layer 1, 2, and 3 decoder.
+///
+/// If verification is enabled, protected layer 3 frames that fail CRC verification are rejected.
+/// The CRC of layer 1 and 2 frames is not verified.
 pub struct MpaDecoder {
     params: CodecParameters,
     state: State,
     buf: AudioBuffer<f32>,
+    /// If true, frames that fail CRC verification are discarded instead of decoded.
+    is_validating: bool,
+    /// The result of CRC verification for all protected frames decoded so far, if any.
+    verify_ok: Option<bool>,
 }
 
 impl MpaDecoder {
     fn decode_inner(&mut self, packet: &Packet) -> Result<()> {
         let mut reader = packet.as_buf_reader();
 
-        let header = header::read_frame_header(&mut reader)?;
+        let mut header = header::read_frame_header(&mut reader)?;
+
+        // The size of a free-format frame is not signalled in the header. Since a packet contains
+        // exactly one frame, derive the frame size from the packet length.
+        if header.is_free_format {
+            let frame_len = header::MPEG_HEADER_LEN + reader.bytes_available() as usize;
+            let unpadded_len = frame_len.saturating_sub(header.padding_len());
+
+            header::set_free_format_len(&mut header, unpadded_len)?;
+        }
 
         // The packet should be the size stated in the header.
         if header.frame_size != reader.bytes_available() as usize {
             return decode_error("mpa: invalid packet length");
         }
 
+        // Verify the CRC of protected frames. If validating, a corrupted frame is discarded,
+        // otherwise it is decoded regardless.
+        if let Some(is_ok) = verify_crc(&header, &packet.data) {
+            if !is_ok {
+                self.verify_ok = Some(false);
+
+                if self.is_validating {
+                    return decode_error("mpa: crc mismatch");
+                }
+
+                warn!("crc mismatch, decoding a possibly corrupted frame");
+            }
+            else if self.verify_ok.is_none() {
+                self.verify_ok = Some(true);
+            }
+        }
+
         // The audio buffer can only be created after the first frame is decoded.
         if self.buf.is_unused() {
             self.buf = AudioBuffer::new(1152, header.spec());
@@ -111,7 +174,7 @@ impl MpaDecoder {
 }
 
 impl Decoder for MpaDecoder {
-    fn try_new(params: &CodecParameters, _: &DecoderOptions) -> Result<Self> {
+    fn try_new(params: &CodecParameters, options: &DecoderOptions) -> Result<Self> {
         // This decoder only supports MP1, MP2, and MP3.
         match params.codec {
             #[cfg(feature = "mp1")]
@@ -126,7 +189,13 @@ impl Decoder for MpaDecoder {
         // Create decoder state.
         let state = State::new(params.codec);
 
-        Ok(MpaDecoder { params: params.clone(), state, buf: AudioBuffer::unused() })
+        Ok(MpaDecoder {
+            params: params.clone(),
+            state,
+            buf: AudioBuffer::unused(),
+            is_validating: options.verify,
+            verify_ok: None,
+        })
     }
 
     fn supported_codecs() -> &'static [CodecDescriptor] {
@@ -160,10 +229,93 @@ impl Decoder for MpaDecoder {
     }
 
     fn finalize(&mut self) -> FinalizeResult {
-        Default::default()
+        let mut result: FinalizeResult = Default::default();
+
+        // If verifying, report if all protected frames passed CRC verification. The result is
+        // inconclusive if no protected frames were decoded.
+        if self.is_validating {
+            result.verify_ok = self.verify_ok;
+        }
+
+        result
     }
 
     fn last_decoded(&self) -> AudioBufferRef<'_> {
         self.buf.as_audio_buffer_ref()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::checksum::Crc16Ansi;
+    use symphonia_core::codecs::{CodecParameters, Decoder, DecoderOptions, CODEC_TYPE_MP3};
+    use symphonia_core::formats::Packet;
+    use symphonia_core::io::Monitor;
+
+    use super::{verify_crc, MpaDecoder};
+    use crate::header::parse_frame_header;
+
+    /// The header, CRC, and side information of a protected MPEG2 layer 3 frame (64 kbps,
+    /// 22.05 kHz, mono, padded). The header and side information are from a frame encoded by LAME
+    /// 3.98.2, with the protection bit cleared. The CRC was computed independently with the
+    /// bit-serial algorithm of ISO/IEC 11172-3 (generator 0x8005, initial value 0xffff).
+    const PROTECTED_FRAME: [u8; 15] =
+        [0xff, 0xf2, 0x82, 0xc4, 0xd2, 0x52, 0xc3, 0x00, 0x00, 0x03, 0x48, 0x00, 0x00, 0x00, 0x00];
+
+    fn make_frame() -> Vec<u8> {
+        let mut frame = PROTECTED_FRAME.to_vec();
+        frame.resize(209, 0x55);
+        frame
+    }
+
+    #[test]
+    fn verify_crc16_check_value() {
+        // The check value of the CRC-16/CMS algorithm, which is used by MPEG audio.
+        let mut crc16 = Crc16Ansi::new(0xffff);
+        crc16.process_buf_bytes(b"123456789");
+        assert_eq!(crc16.crc(), 0xaee7);
+    }
+
+    #[test]
+    fn verify_layer3_crc() {
+        let header = parse_frame_header(0xfff2_82c4).unwrap();
+        let mut frame = make_frame();
+
+        assert_eq!(header.frame_size + 4, frame.len());
+        assert_eq!(verify_crc(&header, &frame), Some(true));
+
+        // The main data is not protected.
+        frame[15] ^= 0x1;
+        assert_eq!(verify_crc(&header, &frame), Some(true));
+
+        // A single bit error in the side information is detected.
+        frame[10] ^= 0x8;
+        assert_eq!(verify_crc(&header, &frame), Some(false));
+
+        // A single bit error in the protected part of the header is detected.
+        let mut frame = make_frame();
+        frame[3] ^= 0x1;
+        assert_eq!(verify_crc(&header, &frame), Some(false));
+
+        // An unprotected frame is not verified.
+        let header = parse_frame_header(0xfff3_82c4).unwrap();
+        assert_eq!(verify_crc(&header, &frame), None);
+    }
+
+    #[test]
+    fn verify_layer3_crc_rejected() {
+        let mut params = CodecParameters::new();
+        params.for_codec(CODEC_TYPE_MP3);
+
+        let options = DecoderOptions { verify: true };
+        let mut decoder = MpaDecoder::try_new(&params, &options).unwrap();
+
+        // A single bit error in the side information.
+        let mut frame = make_frame();
+        frame[10] ^= 0x8;
+
+        let packet = Packet::new_from_boxed_slice(0, 0, 0, frame.into_boxed_slice());
+        assert!(decoder.decode(&packet).is_err());
+        assert_eq!(decoder.finalize().verify_ok, Some(false));
+    }
+}
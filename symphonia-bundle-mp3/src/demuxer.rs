@@ -9,11 +9,15 @@ use symphonia_core::support_format;
 
 use symphonia_core::checksum::Crc16AnsiLe;
 use symphonia_core::codecs::CodecParameters;
-use symphonia_core::errors::{seek_error, Result, SeekErrorKind};
+use symphonia_core::errors::{
+    decode_error, end_of_stream_error, seek_error, Result, SeekErrorKind,
+};
 use symphonia_core::formats::prelude::*;
 use symphonia_core::io::*;
-use symphonia_core::meta::{Metadata, MetadataLog};
+use symphonia_core::meta::{Metadata, MetadataBuilder, MetadataLog};
 use symphonia_core::probe::{Descriptor, Instantiate, QueryDescriptor};
+use symphonia_metadata::id3v2;
+use symphonia_metadata::trailing::read_trailing_tags;
 
 use crate::common::{FrameHeader, MpegLayer};
 use crate::header::{self, MAX_MPEG_FRAME_SIZE, MPEG_HEADER_LEN};
@@ -22,6 +26,10 @@ use std::io::{Seek, SeekFrom};
 
 use log::{debug, info, warn};
 
+/// The encoder delay, in audio frames, assumed for layer 3 streams without a LAME tag: the 576
+/// frame delay of the reference encoder plus the 529 frame delay of the decoder.
+const LAYER3_ASSUMED_DELAY: u32 = 576 + 529;
+
 /// MPEG1 and MPEG2 audio elementary stream reader.
 ///
 /// `MpaReader` implements a demuxer for the MPEG1 and MPEG2 audio elementary stream.
@@ -32,6 +40,12 @@ pub struct MpaReader {
     metadata: MetadataLog,
     options: FormatOptions,
     first_packet_pos: u64,
+    /// The position of the end of the audio data, before any trailing tags, if known.
+    data_end_pos: Option<u64>,
+    /// The table of contents from a Xing or VBRI tag, if present.
+    toc: Option<Toc>,
+    /// The length of an unpadded frame, including the header, if the stream is free-format.
+    free_format_len: Option<usize>,
     next_packet_ts: u64,
 }
 
@@ -93,8 +107,35 @@ impl QueryDescriptor for MpaReader {
 
 impl FormatReader for MpaReader {
     fn try_new(mut source: MediaSourceStream, options: &FormatOptions) -> Result<Self> {
+        let mut metadata: MetadataLog = Default::default();
+
+        // If the source is seekable, read the tags at the end of the stream, and then return to
+        // the start of the audio data.
+        let data_end_pos = if source.is_seekable() {
+            let pos = source.pos();
+
+            let data_end_pos = match read_trailing_tags(&mut source, &mut metadata) {
+                Ok(data_end_pos) => data_end_pos,
+                Err(err) => {
+                    warn!("failed to read trailing tags: {}", err);
+                    None
+                }
+            };
+
+            source.seek(SeekFrom::Start(pos))?;
+
+            data_end_pos
+        }
+        else {
+            None
+        };
+
+        // The length of an unpadded free-format frame is measured from the first frame.
+        let mut free_format_len = None;
+
         // Try to read the first MPEG frame.
-        let (header, packet) = read_mpeg_frame_strict(&mut source)?;
+        let (header, packet) =
+            read_mpeg_frame_strict(&mut source, Some(&mut metadata), &mut free_format_len)?;
 
         // Use the header to populate the codec parameters.
         let mut params = CodecParameters::new();
@@ -105,40 +146,66 @@ impl FormatReader for MpaReader {
             .with_time_base(TimeBase::new(1, header.sample_rate))
             .with_channels(header.channel_mode.channels());
 
+        // The encoder delay and padding, if known.
+        let mut delay_padding = None;
+
+        // The total number of audio frames in the stream, including delay and padding, if known.
+        let mut num_frames = None;
+
+        // The table of contents for seeking, if known.
+        let mut toc = None;
+
+        // The position of the first frame. The byte positions in a Xing or VBRI tag are relative to
+        // the start of the frame containing the tag.
+        let tag_pos = source.pos() - packet.len() as u64;
+
         // Check if there is a Xing/Info tag contained in the first frame.
         if let Some(info_tag) = try_read_info_tag(&packet, &header) {
             // The LAME tag contains ReplayGain and padding information.
-            let (delay, padding) = if let Some(lame_tag) = info_tag.lame {
-                params.with_delay(lame_tag.enc_delay).with_padding(lame_tag.enc_padding);
-
-                (lame_tag.enc_delay, lame_tag.enc_padding)
-            }
-            else {
-                (0, 0)
-            };
+            delay_padding =
+                info_tag.lame.map(|lame_tag| (lame_tag.enc_delay, lame_tag.enc_padding));
 
             // The base Xing/Info tag may contain the number of frames.
             if let Some(num_mpeg_frames) = info_tag.num_frames {
                 info!("using xing header for duration");
 
-                let num_frames = u64::from(num_mpeg_frames) * header.duration();
+                let duration = u64::from(num_mpeg_frames) * header.duration();
 
-                // Adjust for gapless playback.
-                if options.enable_gapless {
-                    params.with_n_frames(num_frames - u64::from(delay) - u64::from(padding));
-                }
-                else {
-                    params.with_n_frames(num_frames);
+                // The length of the stream is required to use the table of contents if the tag
+                // does not provide it.
+                let num_bytes = info_tag.num_bytes.map(u64::from).or_else(|| {
+                    data_end_pos
+                        .or_else(|| source.byte_len())
+                        .map(|end| end.saturating_sub(tag_pos))
+                });
+
+                if let (Some(xing_toc), Some(num_bytes)) = (&info_tag.toc, num_bytes) {
+                    info!("using xing header for seeking");
+
+                    toc = Some(Toc::from_xing(xing_toc, duration, tag_pos, num_bytes));
                 }
+
+                num_frames = Some(duration);
             }
         }
         else if let Some(vbri_tag) = try_read_vbri_tag(&packet, &header) {
             info!("using vbri header for duration");
 
-            let num_frames = u64::from(vbri_tag.num_mpeg_frames) * header.duration();
+            let duration = u64::from(vbri_tag.num_mpeg_frames) * header.duration();
+
+            if !vbri_tag.toc.is_empty() {
+                info!("using vbri header for seeking");
+
+                toc = Some(Toc::from_vbri(
+                    &vbri_tag.toc,
+                    u64::from(vbri_tag.frames_per_entry) * header.duration(),
+                    duration,
+                    tag_pos,
+                    u64::from(vbri_tag.num_bytes),
+                ));
+            }
 
-            // Check if there is a VBRI tag.
-            params.with_n_frames(num_frames);
+            num_frames = Some(duration);
         }
         else {
             // The first frame was not a Xing/Info header, rewind back to the start of the frame so
@@ -149,9 +216,36 @@ impl FormatReader for MpaReader {
             if source.is_seekable() {
                 info!("estimating duration from bitrate, may be inaccurate for vbr files");
 
-                if let Some(n_mpeg_frames) = estimate_num_mpeg_frames(&mut source) {
-                    params.with_n_frames(n_mpeg_frames * header.duration());
-                }
+                num_frames =
+                    estimate_num_mpeg_frames(&mut source, data_end_pos, &mut free_format_len)
+                        .map(|n_mpeg_frames| n_mpeg_frames * header.duration());
+            }
+        }
+
+        // Without a LAME tag, the encoder delay is unknown. If allowed, assume the delay of the
+        // reference encoder. The padding depends on the length of the audio and cannot be assumed.
+        if delay_padding.is_none()
+            && options.enable_gapless
+            && options.estimate_gapless
+            && header.layer == MpegLayer::Layer3
+        {
+            info!("no lame tag, assuming an encoder delay of {} frames", LAYER3_ASSUMED_DELAY);
+
+            delay_padding = Some((LAYER3_ASSUMED_DELAY, 0));
+        }
+
+        if let Some((delay, padding)) = delay_padding {
+            params.with_delay(delay).with_padding(padding);
+        }
+
+        if let Some(num_frames) = num_frames {
+            // Adjust for gapless playback.
+            if options.enable_gapless {
+                let (delay, padding) = delay_padding.unwrap_or((0, 0));
+                params.with_n_frames(num_frames.saturating_sub(u64::from(delay + padding)));
+            }
+            else {
+                params.with_n_frames(num_frames);
             }
         }
 
@@ -161,9 +255,12 @@ impl FormatReader for MpaReader {
             reader: source,
             tracks: vec![Track::new(0, params)],
             cues: Vec::new(),
-            metadata: Default::default(),
+            metadata,
             options: *options,
             first_packet_pos,
+            data_end_pos,
+            toc,
+            free_format_len,
             next_packet_ts: 0,
         })
     }
@@ -171,7 +268,18 @@ impl FormatReader for MpaReader {
     fn next_packet(&mut self) -> Result<Packet> {
         let (header, packet) = loop {
             // Read the next MPEG frame.
-            let (header, packet) = read_mpeg_frame(&mut self.reader)?;
+            let (header, packet) = read_mpeg_frame(
+                &mut self.reader,
+                Some(&mut self.metadata),
+                &mut self.free_format_len,
+            )?;
+
+            // A frame synced to within the trailing tags is not audio data.
+            if let Some(data_end_pos) = self.data_end_pos {
+                if self.reader.pos() - packet.len() as u64 >= data_end_pos {
+                    return end_of_stream_error();
+                }
+            }
 
             // Check if the packet contains a Xing, Info, or VBRI tag.
             if is_maybe_info_tag(&packet, &header) {
@@ -198,7 +306,11 @@ impl FormatReader for MpaReader {
 
         self.next_packet_ts += duration;
 
-        let mut packet = Packet::new_from_boxed_slice(0, ts, duration, packet.into_boxed_slice());
+        // The frame, including its header, was read in its entirety.
+        let pos = self.reader.pos() - packet.len() as u64;
+
+        let mut packet =
+            Packet::new_from_boxed_slice(0, ts, duration, packet.into_boxed_slice()).with_pos(pos);
 
         if self.options.enable_gapless {
             symphonia_core::formats::util::trim_packet(
@@ -290,7 +402,9 @@ impl FormatReader for MpaReader {
 
         loop {
             // Parse the next frame header.
-            let header = header::parse_frame_header(header::sync_frame(&mut self.reader)?)?;
+            let mut header = header::parse_frame_header(sync_frame(&mut self.reader, None)?)?;
+
+            resolve_free_format_len(&mut self.reader, &mut header, &mut self.free_format_len)?;
 
             // Position of the frame header.
             let pos = self.reader.pos() - std::mem::size_of::<u32>() as u64;
@@ -385,6 +499,18 @@ impl MpaReader {
     /// Seeks the media source stream to a byte position roughly where the packet with the required
     /// timestamp should be located.
     fn preseek_coarse(&mut self, required_ts: u64, delay: u64) -> Result<()> {
+        // A table of contents accounts for variations in bitrate, prefer it if available.
+        if let Some(toc) = &self.toc {
+            self.next_packet_ts = preseek_toc(
+                &mut self.reader,
+                toc,
+                self.first_packet_pos,
+                &mut self.free_format_len,
+                required_ts,
+            )?;
+            return Ok(());
+        }
+
         // If gapless playback is enabled, get the padding.
         let padding = if self.options.enable_gapless {
             u64::from(self.tracks[0].codec_params.padding.unwrap_or(0))
@@ -393,8 +519,9 @@ impl MpaReader {
             0
         };
 
-        // Get the total byte length of the stream. It is not possible to seek without this.
-        let total_byte_len = match self.reader.byte_len() {
+        // Get the total byte length of the audio data, excluding any trailing tags. It is not
+        // possible to seek without this.
+        let total_byte_len = match self.data_end_pos.or_else(|| self.reader.byte_len()) {
             Some(byte_len) => byte_len,
             None => return seek_error(SeekErrorKind::Unseekable),
         };
@@ -422,7 +549,8 @@ impl MpaReader {
         self.reader.seek(SeekFrom::Start(seek_pos))?;
 
         // Resync to the start of the next packet.
-        let (header, _) = read_mpeg_frame_strict(&mut self.reader)?;
+        let (header, _) =
+            read_mpeg_frame_strict(&mut self.reader, None, &mut self.free_format_len)?;
 
         // Calculate, roughly, the timestamp of the packet based on the byte position after resync.
         let seeked_pos = self.reader.pos();
@@ -459,15 +587,127 @@ impl MpaReader {
     }
 }
 
+/// Seeks the media source stream to a byte position roughly where the packet with the required
+/// timestamp should be located using a table of contents. Returns the timestamp of the next packet.
+fn preseek_toc(
+    reader: &mut MediaSourceStream,
+    toc: &Toc,
+    first_packet_pos: u64,
+    free_format_len: &mut Option<usize>,
+    required_ts: u64,
+) -> Result<u64> {
+    // It is preferable to return a packet with a timestamp before the requested timestamp.
+    // Therefore, subtract the maximum packet size from the position found in the table.
+    let seek_pos =
+        toc.pos_of(required_ts).saturating_sub(MAX_MPEG_FRAME_SIZE).max(first_packet_pos);
+
+    reader.seek(SeekFrom::Start(seek_pos))?;
+
+    // Resync to the start of the next packet.
+    let (header, _) = read_mpeg_frame_strict(reader, None, free_format_len)?;
+
+    // Round the timestamp of the packet, found from the byte position after resync, to a multiple
+    // of the packet duration.
+    let packet_dur = header.duration();
+
+    Ok((toc.ts_of(reader.pos()) / packet_dur) * packet_dur)
+}
+
+/// A table of contents from a Xing or VBRI tag, mapping timestamps to byte positions in the stream
+/// for seeking in variable bitrate streams.
+struct Toc {
+    /// Pairs of timestamp and absolute byte position, in ascending order.
+    points: Vec<(u64, u64)>,
+}
+
+impl Toc {
+    /// Create a table of contents from a Xing tag. Entry `i` of the Xing table of contents is the
+    /// byte position at `i` percent of the duration, in units of 1/256th of the stream length.
+    fn from_xing(toc: &[u8; 100], duration: u64, start: u64, num_bytes: u64) -> Self {
+        let mut points: Vec<(u64, u64)> = toc
+            .iter()
+            .enumerate()
+            .map(|(i, &entry)| {
+                (i as u64 * duration / 100, start + u64::from(entry) * num_bytes / 256)
+            })
+            .collect();
+
+        points.push((duration, start + num_bytes));
+
+        Toc { points }
+    }
+
+    /// Create a table of contents from a VBRI tag. Each entry of the VBRI table of contents is the
+    /// length in bytes of a fixed duration segment of the stream.
+    fn from_vbri(toc: &[u32], entry_dur: u64, duration: u64, start: u64, num_bytes: u64) -> Self {
+        let mut points = Vec::with_capacity(toc.len() + 2);
+
+        let mut ts = 0;
+        let mut pos = start;
+
+        for &entry in toc {
+            if ts >= duration {
+                break;
+            }
+
+            points.push((ts, pos));
+
+            ts += entry_dur;
+            pos += u64::from(entry);
+        }
+
+        points.push((duration, start + num_bytes));
+
+        Toc { points }
+    }
+
+    /// Get the approximate byte position of the packet containing the given timestamp.
+    fn pos_of(&self, ts: u64) -> u64 {
+        interpolate(&self.points, ts)
+    }
+
+    /// Get the approximate timestamp of the packet starting at the given byte position.
+    fn ts_of(&self, pos: u64) -> u64 {
+        let points: Vec<(u64, u64)> = self.points.iter().map(|&(x, y)| (y, x)).collect();
+        interpolate(&points, pos)
+    }
+}
+
+/// Evaluate a piecewise linear function, given as a list of points in ascending order, at `x`.
+/// Values of `x` outside the range of the points are clamped.
+fn interpolate(points: &[(u64, u64)], x: u64) -> u64 {
+    // The index of the first point after x.
+    let i = points.iter().position(|&(px, _)| px > x).unwrap_or(points.len());
+
+    if i == 0 {
+        return points.first().map_or(0, |&(_, y)| y);
+    }
+
+    if i == points.len() {
+        return points[i - 1].1;
+    }
+
+    let (x0, y0) = points[i - 1];
+    let (x1, y1) = points[i];
+
+    y0 + ((u128::from(x - x0) * u128::from(y1.saturating_sub(y0))) / u128::from(x1 - x0)) as u64
+}
+
 /// Reads a MPEG frame and returns the header and buffer.
-fn read_mpeg_frame(reader: &mut MediaSourceStream) -> Result<(FrameHeader, Vec<u8>)> {
+fn read_mpeg_frame(
+    reader: &mut MediaSourceStream,
+    mut metadata: Option<&mut MetadataLog>,
+    free_format_len: &mut Option<usize>,
+) -> Result<(FrameHeader, Vec<u8>)> {
     let (header, header_word) = loop {
         // Sync to the next frame header.
-        let sync = header::sync_frame(reader)?;
+        let sync = sync_frame(reader, metadata.as_deref_mut())?;
 
-        // Parse the frame header fully.
-        if let Ok(header) = header::parse_frame_header(sync) {
-            break (header, sync);
+        // Parse the frame header fully, and find the frame size if the frame is free-format.
+        if let Ok(mut header) = header::parse_frame_header(sync) {
+            if resolve_free_format_len(reader, &mut header, free_format_len).is_ok() {
+                break (header, sync);
+            }
         }
 
         warn!("invalid mpeg audio header");
@@ -485,10 +725,14 @@ fn read_mpeg_frame(reader: &mut MediaSourceStream) -> Result<(FrameHeader, Vec<u
 }
 
 /// Reads a MPEG frame and checks if the next frame begins after the packet.
-fn read_mpeg_frame_strict(reader: &mut MediaSourceStream) -> Result<(FrameHeader, Vec<u8>)> {
+fn read_mpeg_frame_strict(
+    reader: &mut MediaSourceStream,
+    mut metadata: Option<&mut MetadataLog>,
+    free_format_len: &mut Option<usize>,
+) -> Result<(FrameHeader, Vec<u8>)> {
     loop {
         // Read the next MPEG frame.
-        let (header, packet) = read_mpeg_frame(reader)?;
+        let (header, packet) = read_mpeg_frame(reader, metadata.as_deref_mut(), free_format_len)?;
 
         // Get the position before trying to read the next header.
         let pos = reader.pos();
@@ -499,10 +743,22 @@ fn read_mpeg_frame_strict(reader: &mut MediaSourceStream) -> Result<(FrameHeader
             // If the stream is not synced to the next frame's sync word, or the next frame header
             // is not parseable or similar to the current frame header, then reject the current
             // packet since the stream likely synced to random data.
-            if !header::is_frame_header_word_synced(sync) || !is_frame_header_similar(&header, sync)
+            //
+            // A frame followed by zero padding is accepted, since the padding will be skipped when
+            // synchronizing to the next frame.
+            let is_padded = sync == 0;
+
+            if !is_padded
+                && (!header::is_frame_header_word_synced(sync)
+                    || !is_frame_header_similar(&header, sync))
             {
                 warn!("skipping junk at {} bytes", pos - packet.len() as u64);
 
+                // The free-format frame length may have been measured from junk.
+                if header.is_free_format {
+                    *free_format_len = None;
+                }
+
                 // Seek back to the second byte of the rejected packet to prevent syncing to the
                 // same spot again.
                 reader.seek_buffered_rev(packet.len() + MPEG_HEADER_LEN - 1);
@@ -517,6 +773,205 @@ fn read_mpeg_frame_strict(reader: &mut MediaSourceStream) -> Result<(FrameHeader
     }
 }
 
+/// Synchronize the reader to the end of the next frame header, and return the frame header as a
+/// `u32`.
+///
+/// Unlike `header::sync_frame`, any ID3v2 tag found while synchronizing is skipped. Stream rips
+/// often contain complete ID3v2 tags spliced between frames at song boundaries, and the contents
+/// of such tags (e.g., cover art) can easily contain false frame sync words. If a metadata log is
+/// provided, the skipped tag is also read and pushed onto it as a new revision.
+fn sync_frame(
+    reader: &mut MediaSourceStream,
+    mut metadata: Option<&mut MetadataLog>,
+) -> Result<u32> {
+    let mut sync = 0u32;
+
+    loop {
+        sync = (sync << 8) | u32::from(reader.read_u8()?);
+
+        // Random data can look like a sync word. Do a quick check to increase confidence that
+        // this may be the start of a frame.
+        if header::is_frame_header_word_synced(sync) && header::check_header(sync) {
+            break;
+        }
+
+        // The last 3 bytes read may be the "ID3" marker of an ID3v2 tag.
+        if sync & 0xff_ffff == 0x49_4433 && try_skip_id3v2_tag(reader, metadata.as_deref_mut())? {
+            sync = 0;
+        }
+
+        // The last 4 bytes read were 0. This may be the start of a long run of zero padding.
+        if sync == 0 {
+            skip_zero_run(reader)?;
+        }
+    }
+
+    Ok(sync)
+}
+
+/// Skip a run of zero bytes. Some rips contain long runs of zero padding between frames (e.g., a
+/// ripped CD pregap). Since a frame header cannot begin with a zero byte, the entire run can be
+/// skipped, and it is scanned a block at a time rather than byte-by-byte.
+fn skip_zero_run(reader: &mut MediaSourceStream) -> Result<()> {
+    let mut buf = [0u8; 1024];
+    let mut skipped = 0;
+
+    loop {
+        let len = reader.read_buf(&mut buf)?;
+
+        match buf[..len].iter().position(|&b| b != 0) {
+            Some(n) => {
+                // Rewind to the first non-zero byte.
+                reader.seek_buffered_rev(len - n);
+                skipped += n;
+                break;
+            }
+            None => skipped += len,
+        }
+    }
+
+    if skipped >= buf.len() {
+        debug!(
+            "skipped {} bytes of zero padding at {} bytes",
+            skipped,
+            reader.pos() - skipped as u64
+        );
+    }
+
+    Ok(())
+}
+
+/// Try to skip, and optionally read, an ID3v2 tag. The reader must be positioned immediately after
+/// the "ID3" marker. If the remainder of the tag header is invalid, the reader is rewound to its
+/// original position and `false` is returned.
+fn try_skip_id3v2_tag(
+    reader: &mut MediaSourceStream,
+    metadata: Option<&mut MetadataLog>,
+) -> Result<bool> {
+    // The remainder of the tag header: major version, minor version, flags, and a syncsafe size.
+    let mut buf = [0u8; 7];
+    reader.read_buf_exact(&mut buf)?;
+
+    // Only ID3v2.2 to ID3v2.4 are supported, versions must not be 0xff, and each byte of the
+    // syncsafe size must have the most-significant bit cleared.
+    let is_valid =
+        (2..=4).contains(&buf[0]) && buf[1] != 0xff && buf[3..].iter().all(|&b| b & 0x80 == 0);
+
+    if !is_valid {
+        reader.seek_buffered_rev(buf.len());
+        return Ok(false);
+    }
+
+    let size = buf[3..].iter().fold(0u64, |size, &b| (size << 7) | u64::from(b));
+
+    // ID3v2.4 tags may have a 10 byte footer following the tag body.
+    let footer_len = if buf[0] == 4 && buf[2] & 0x10 != 0 { 10 } else { 0 };
+
+    let tag_len = 10 + size + footer_len;
+
+    let tag_pos = reader.pos() - 10;
+
+    info!("found an id3v2 tag at {} bytes, length {} bytes", tag_pos, tag_len);
+
+    if let Some(metadata) = metadata {
+        // Rewind to the "ID3" marker so the entire tag can be read.
+        reader.seek_buffered_rev(10);
+
+        let mut scoped = ScopedStream::new(&mut *reader, tag_len);
+        let mut builder = MetadataBuilder::new();
+
+        match id3v2::read_id3v2(&mut scoped, &mut builder) {
+            Ok(_) => metadata.push(builder.metadata()),
+            Err(err) => warn!("failed to read id3v2 tag: {}", err),
+        }
+
+        // Skip any remaining data in the tag.
+        scoped.ignore()?;
+    }
+    else {
+        reader.ignore_bytes(tag_len - 10)?;
+    }
+
+    Ok(true)
+}
+
+/// Set the frame size of a free-format frame header. The reader must be positioned immediately
+/// after the frame header.
+///
+/// The unpadded length of all frames in a free-format stream is the same, but is not signalled in
+/// the frame header. If it is not yet known, it is measured from the distance between this frame
+/// header and the next free-format frame header, and then saved for all subsequent frames.
+fn resolve_free_format_len(
+    reader: &mut MediaSourceStream,
+    header: &mut FrameHeader,
+    free_format_len: &mut Option<usize>,
+) -> Result<()> {
+    if !header.is_free_format {
+        return Ok(());
+    }
+
+    match *free_format_len {
+        Some(unpadded_len) => header::set_free_format_len(header, unpadded_len),
+        None => {
+            let unpadded_len = match measure_free_format_len(reader, header) {
+                Some(unpadded_len) => unpadded_len,
+                None => return decode_error("mpa: could not find the free-format frame length"),
+            };
+
+            header::set_free_format_len(header, unpadded_len)?;
+
+            info!(
+                "free-format stream with a frame length of {} bytes (~{} kbps)",
+                unpadded_len,
+                header.bitrate / 1000
+            );
+
+            *free_format_len = Some(unpadded_len);
+
+            Ok(())
+        }
+    }
+}
+
+/// Measure the unpadded length of a free-format frame, including the header, by scanning for the
+/// header of the next free-format frame. The reader must be positioned immediately after the frame
+/// header, and is returned to that position afterwards.
+fn measure_free_format_len(reader: &mut MediaSourceStream, header: &FrameHeader) -> Option<usize> {
+    // The shortest possible frame contains the header and the side information.
+    let min_len = MPEG_HEADER_LEN + header.side_info_len() + header.padding_len();
+    let max_len = MAX_MPEG_FRAME_SIZE as usize;
+
+    let mut sync = 0u32;
+    let mut num_read = 0;
+    let mut unpadded_len = None;
+
+    while num_read < max_len {
+        match reader.read_u8() {
+            Ok(byte) => sync = (sync << 8) | u32::from(byte),
+            Err(_) => break,
+        }
+
+        num_read += 1;
+
+        // If the next frame header ends at the current position, then the length of this frame,
+        // including its header, is the number of bytes read.
+        if num_read < min_len || !header::is_frame_header_word_synced(sync) {
+            continue;
+        }
+
+        if is_frame_header_similar(header, sync)
+            && header::parse_frame_header(sync).map_or(false, |next| next.is_free_format)
+        {
+            unpadded_len = Some(num_read - header.padding_len());
+            break;
+        }
+    }
+
+    reader.seek_buffered_rev(num_read);
+
+    unpadded_len
+}
+
 /// Check if a sync word parses to a frame header that is similar to the one provided.
 fn is_frame_header_similar(header: &FrameHeader, sync: u32) -> bool {
     if let Ok(candidate) = header::parse_frame_header(sync) {
@@ -557,8 +1012,13 @@ fn read_main_data_begin<B: ReadBytes>(reader: &mut B, header: &FrameHeader) -> R
     Ok(main_data_begin)
 }
 
-/// Estimates the total number of MPEG frames in the media source stream.
-fn estimate_num_mpeg_frames(reader: &mut MediaSourceStream) -> Option<u64> {
+/// Estimates the total number of MPEG frames in the media source stream. If known, the end of the
+/// audio data is used to exclude trailing tags from the estimate.
+fn estimate_num_mpeg_frames(
+    reader: &mut MediaSourceStream,
+    data_end_pos: Option<u64>,
+    free_format_len: &mut Option<usize>,
+) -> Option<u64> {
     const MAX_FRAMES: u32 = 16;
     const MAX_LEN: usize = 16 * 1024;
 
@@ -577,8 +1037,8 @@ fn estimate_num_mpeg_frames(reader: &mut MediaSourceStream) -> Option<u64> {
     let mut total_frame_len = 0;
     let mut total_frames = 0;
 
-    let total_len = match reader.byte_len() {
-        Some(len) => len - start_pos,
+    let total_len = match data_end_pos.or_else(|| reader.byte_len()) {
+        Some(len) => len.saturating_sub(start_pos),
         _ => return None,
     };
 
@@ -587,7 +1047,9 @@ fn estimate_num_mpeg_frames(reader: &mut MediaSourceStream) -> Option<u64> {
         let header_val = break_on_err!(reader.read_be_u32());
 
         // Parse the frame header.
-        let header = break_on_err!(header::parse_frame_header(header_val));
+        let mut header = break_on_err!(header::parse_frame_header(header_val));
+
+        break_on_err!(resolve_free_format_len(reader, &mut header, free_format_len));
 
         // Tabulate the size.
         total_frame_len += MPEG_HEADER_LEN + header.frame_size;
@@ -596,9 +1058,12 @@ fn estimate_num_mpeg_frames(reader: &mut MediaSourceStream) -> Option<u64> {
         // Ignore the frame body.
         break_on_err!(reader.ignore_bytes(header.frame_size as u64));
 
-        // Read up-to 16 frames, or 16kB, then calculate the average MPEG frame length, and from
-        // that, the total number of MPEG frames.
-        if total_frames > MAX_FRAMES || total_frame_len > MAX_LEN {
+        // Read up-to 16 frames, 16kB, or all the audio data, then calculate the average MPEG frame
+        // length, and from that, the total number of MPEG frames.
+        if total_frames > MAX_FRAMES
+            || total_frame_len > MAX_LEN
+            || total_frame_len as u64 >= total_len
+        {
             let avg_mpeg_frame_len = total_frame_len as f64 / total_frames as f64;
             break Some((total_len as f64 / avg_mpeg_frame_len) as u64);
         }
@@ -851,6 +1316,10 @@ const VBRI_TAG_ID: [u8; 4] = *b"VBRI";
 struct VbriTag {
     num_bytes: u32,
     num_mpeg_frames: u32,
+    /// The number of MPEG frames per table of contents entry.
+    frames_per_entry: u32,
+    /// The table of contents, the length in bytes of each segment of the stream.
+    toc: Vec<u32>,
 }
 
 /// Try to read a VBRI tag from the provided MPEG frame.
@@ -892,7 +1361,42 @@ fn try_read_vbri_tag_inner(buf: &[u8], header: &FrameHeader) -> Result<Option<Vb
     let num_bytes = reader.read_be_u32()?;
     let num_mpeg_frames = reader.read_be_u32()?;
 
-    Ok(Some(VbriTag { num_bytes, num_mpeg_frames }))
+    // The table of contents is not required, discard it if it is invalid or truncated.
+    let (frames_per_entry, toc) = read_vbri_toc(&mut reader).unwrap_or_else(|_| {
+        warn!("vbri tag table of contents is invalid");
+        (0, Vec::new())
+    });
+
+    Ok(Some(VbriTag { num_bytes, num_mpeg_frames, frames_per_entry, toc }))
+}
+
+/// Read the table of contents of a VBRI tag. Returns the number of MPEG frames per entry, and the
+/// scaled entries.
+fn read_vbri_toc(reader: &mut BufReader<'_>) -> Result<(u32, Vec<u32>)> {
+    let num_entries = reader.read_be_u16()?;
+    let scale = u32::from(reader.read_be_u16()?);
+    let entry_size = reader.read_be_u16()?;
+    let frames_per_entry = u32::from(reader.read_be_u16()?);
+
+    if frames_per_entry == 0 {
+        return decode_error("mpa: invalid vbri frames per table of contents entry");
+    }
+
+    let mut toc = Vec::with_capacity(usize::from(num_entries));
+
+    for _ in 0..num_entries {
+        let entry = match entry_size {
+            1 => u32::from(reader.read_u8()?),
+            2 => u32::from(reader.read_be_u16()?),
+            3 => reader.read_be_u24()?,
+            4 => reader.read_be_u32()?,
+            _ => return decode_error("mpa: invalid vbri table of contents entry size"),
+        };
+
+        toc.push(entry.wrapping_mul(scale));
+    }
+
+    Ok((frames_per_entry, toc))
 }
 
 /// Perform a fast check to see if the packet contains a VBRI tag. If this returns true, the
@@ -921,3 +1425,281 @@ fn is_maybe_vbri_tag(buf: &[u8], header: &FrameHeader) -> bool {
     // The bytes preceeding the VBRI tag (mostly the side information) should be all 0.
     !buf[MPEG_HEADER_LEN..VBRI_TAG_OFFSET].iter().any(|&b| b != 0)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::io::Cursor;
+
+    use symphonia_core::errors::Error;
+    use symphonia_core::formats::{FormatOptions, FormatReader};
+    use symphonia_core::io::{MediaSourceStream, ReadBytes};
+    use symphonia_core::meta::StandardTagKey;
+
+    use super::{sync_frame, MpaReader, Toc, LAYER3_ASSUMED_DELAY};
+
+    #[test]
+    fn verify_sync_frame_skips_zero_padding() {
+        // A MPEG-1 layer 3 frame header (128 kbps, 44.1 kHz) following a long run of zero padding,
+        // and a header with a trailing zero byte immediately after.
+        let mut data = vec![0u8; 10_000];
+        data.extend_from_slice(&[0xff, 0xfb, 0x90, 0x64]);
+        data.extend_from_slice(&[0x00, 0x00, 0xff, 0xfb, 0x90, 0x00]);
+
+        let mut mss = MediaSourceStream::new(Box::new(Cursor::new(data)), Default::default());
+
+        assert_eq!(sync_frame(&mut mss, None).unwrap(), 0xfffb_9064);
+        assert_eq!(mss.pos(), 10_004);
+        assert_eq!(sync_frame(&mut mss, None).unwrap(), 0xfffb_9000);
+        assert_eq!(mss.pos(), 10_010);
+    }
+
+    #[test]
+    fn verify_estimated_gapless() {
+        // Ten MPEG-1 layer 3 frames (128 kbps, 44.1 kHz) without a Xing or LAME tag.
+        let mut data = Vec::new();
+
+        for _ in 0..10 {
+            data.extend_from_slice(&[0xff, 0xfb, 0x90, 0x64]);
+            data.resize(data.len() + 413, 0);
+        }
+
+        let open = |options: &FormatOptions| {
+            let mss =
+                MediaSourceStream::new(Box::new(Cursor::new(data.clone())), Default::default());
+            MpaReader::try_new(mss, options).unwrap()
+        };
+
+        let options = FormatOptions::default().with_enable_gapless(true);
+        assert_eq!(open(&options).tracks()[0].codec_params.delay, None);
+
+        let options = options.with_estimate_gapless(true);
+        let mut reader = open(&options);
+        assert_eq!(reader.tracks()[0].codec_params.delay, Some(LAYER3_ASSUMED_DELAY));
+        assert_eq!(reader.next_packet().unwrap().trim_start(), LAYER3_ASSUMED_DELAY);
+    }
+    #[test]
+    fn verify_trailing_id3v1_tag() {
+        // Ten MPEG-1 layer 3 frames (128 kbps, 44.1 kHz) followed by an ID3v1 tag. The comment of
+        // the tag contains a false frame sync word.
+        let mut data = Vec::new();
+
+        for _ in 0..10 {
+            data.extend_from_slice(&[0xff, 0xfb, 0x90, 0x64]);
+            data.resize(data.len() + 413, 0);
+        }
+
+        let mut tag = vec![0u8; 128];
+        tag[..3].copy_from_slice(b"TAG");
+        tag[3..8].copy_from_slice(b"Title");
+        tag[97..101].copy_from_slice(&[0xff, 0xfb, 0x90, 0x64]);
+        data.extend_from_slice(&tag);
+
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(data)), Default::default());
+        let mut reader = MpaReader::try_new(mss, &Default::default()).unwrap();
+
+        assert_eq!(reader.tracks()[0].codec_params.n_frames, Some(10 * 1152));
+
+        let metadata = reader.metadata();
+        let tags = metadata.current().unwrap().tags();
+        assert_eq!(tags[0].std_key, Some(StandardTagKey::TrackTitle));
+
+        for _ in 0..10 {
+            assert_eq!(reader.next_packet().unwrap().data.len(), 417);
+        }
+        assert!(reader.next_packet().is_err());
+    }
+
+    #[test]
+    fn verify_invalid_trailing_ape_tag() {
+        // Eight MPEG-1 layer 3 frames (128 kbps, 44.1 kHz) followed by an APEv2 footer flagged as
+        // having no footer, with a tag size equal to the length of the stream.
+        let mut data = Vec::new();
+
+        for _ in 0..8 {
+            data.extend_from_slice(&[0xff, 0xfb, 0x90, 0x64]);
+            data.resize(data.len() + 413, 0);
+        }
+
+        let tag_size = data.len() as u32 + 32;
+        data.extend_from_slice(b"APETAGEX");
+        data.extend_from_slice(&2000u32.to_le_bytes());
+        data.extend_from_slice(&tag_size.to_le_bytes());
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&0x4000_0000u32.to_le_bytes());
+        data.extend_from_slice(&[0; 8]);
+
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(data)), Default::default());
+        let mut reader = MpaReader::try_new(mss, &Default::default()).unwrap();
+
+        assert!(reader.metadata().current().is_none());
+
+        for _ in 0..8 {
+            assert_eq!(reader.next_packet().unwrap().data.len(), 417);
+        }
+        assert!(reader.next_packet().is_err());
+    }
+
+    fn make_id3v2_tag(title: &str) -> Vec<u8> {
+        // An ID3v2.3 tag with a title frame, and a private frame containing a false frame sync
+        // word.
+        let mut frames = Vec::new();
+
+        frames.extend_from_slice(b"TIT2");
+        frames.extend_from_slice(&(title.len() as u32 + 1).to_be_bytes());
+        frames.extend_from_slice(&[0, 0, 0]);
+        frames.extend_from_slice(title.as_bytes());
+
+        frames.extend_from_slice(b"PRIV");
+        frames.extend_from_slice(&6u32.to_be_bytes());
+        frames.extend_from_slice(&[0, 0, b'x', 0, 0xff, 0xfb, 0x90, 0x64]);
+
+        // The syncsafe tag size.
+        let mut tag = b"ID3\x03\x00\x00".to_vec();
+        tag.extend_from_slice(&[0, 0, 0, frames.len() as u8]);
+        tag.extend(frames);
+        tag
+    }
+
+    #[test]
+    fn verify_mid_stream_id3v2_tag() {
+        // Ten MPEG-1 layer 3 frames (128 kbps, 44.1 kHz) with an ID3v2 tag between the fifth and
+        // sixth frames.
+        let mut data = Vec::new();
+
+        for i in 0..10 {
+            if i == 5 {
+                data.extend(make_id3v2_tag("Second"));
+            }
+            data.extend_from_slice(&[0xff, 0xfb, 0x90, 0x64]);
+            data.resize(data.len() + 413, 0);
+        }
+
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(data)), Default::default());
+        let mut reader = MpaReader::try_new(mss, &Default::default()).unwrap();
+
+        // The tag is skipped without a gap in the packets.
+        for i in 0..10 {
+            let packet = reader.next_packet().unwrap();
+            assert_eq!(packet.data.len(), 417);
+            assert_eq!(packet.ts, i * 1152);
+            assert_eq!(packet.dur, 1152);
+        }
+        assert!(reader.next_packet().is_err());
+
+        // The tag is read as a new metadata revision.
+        let mut metadata = reader.metadata();
+        let tags = metadata.skip_to_latest().unwrap().tags();
+        let title = tags.iter().find(|tag| tag.std_key == Some(StandardTagKey::TrackTitle));
+        assert_eq!(title.unwrap().value.to_string(), "Second");
+    }
+
+    #[test]
+    fn verify_truncated_id3v2_tag() {
+        // Three MPEG-1 layer 3 frames (128 kbps, 44.1 kHz) followed by an ID3v2 tag that is
+        // truncated by the end of the stream.
+        let mut data = Vec::new();
+
+        for _ in 0..3 {
+            data.extend_from_slice(&[0xff, 0xfb, 0x90, 0x64]);
+            data.resize(data.len() + 413, 0);
+        }
+
+        // The tag claims to be 1000 bytes long.
+        let mut tag = make_id3v2_tag("Truncated");
+        tag[6..10].copy_from_slice(&[0, 0, 0x07, 0x68]);
+        data.extend(tag);
+
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(data)), Default::default());
+        let mut reader = MpaReader::try_new(mss, &Default::default()).unwrap();
+
+        for i in 0..3 {
+            assert_eq!(reader.next_packet().unwrap().ts, i * 1152);
+        }
+
+        // The end of the stream is reached inside the tag.
+        match reader.next_packet() {
+            Err(Error::IoError(err)) => assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof),
+            _ => panic!("expected the end of the stream"),
+        }
+    }
+
+    #[test]
+    fn verify_free_format_frames() {
+        // Ten free-format MPEG-1 layer 3 frames (44.1 kHz) with an unpadded length of 600 bytes.
+        // Every other frame is padded.
+        let mut data = Vec::new();
+
+        for i in 0..10 {
+            let padding = i % 2;
+            data.extend_from_slice(&[0xff, 0xfb, padding << 1, 0x64]);
+            data.resize(data.len() + 596 + usize::from(padding), 0);
+        }
+
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(data)), Default::default());
+        let mut reader = MpaReader::try_new(mss, &Default::default()).unwrap();
+
+        assert_eq!(reader.tracks()[0].codec_params.n_frames, Some(10 * 1152));
+
+        for i in 0..10 {
+            assert_eq!(reader.next_packet().unwrap().data.len(), 600 + i % 2);
+        }
+        assert!(reader.next_packet().is_err());
+    }
+
+    #[test]
+    fn verify_lame_tag_gapless() {
+        // An Info tag with a LAME extension, followed by ten MPEG-1 layer 3 frames (128 kbps,
+        // 44.1 kHz).
+        let mut data = vec![0xff, 0xfb, 0x90, 0x64];
+        data.resize(data.len() + 32, 0);
+        data.extend_from_slice(b"Info");
+        data.extend_from_slice(&1u32.to_be_bytes());
+        data.extend_from_slice(&10u32.to_be_bytes());
+        data.extend_from_slice(b"Lavc58.00");
+        data.resize(data.len() + 12, 0);
+        // An encoder delay of 576 frames, and 1000 frames of padding.
+        data.extend_from_slice(&((576u32 << 12) | 1000).to_be_bytes()[1..]);
+        data.resize(417, 0);
+
+        for _ in 0..10 {
+            data.extend_from_slice(&[0xff, 0xfb, 0x90, 0x64]);
+            data.resize(data.len() + 413, 0);
+        }
+
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(data)), Default::default());
+        let options = FormatOptions::default().with_enable_gapless(true);
+        let mut reader = MpaReader::try_new(mss, &options).unwrap();
+
+        // The decoder delay of 529 frames is added to the encoder delay, and removed from the
+        // padding.
+        let params = &reader.tracks()[0].codec_params;
+        assert_eq!((params.delay, params.padding), (Some(576 + 529), Some(1000 - 529)));
+        assert_eq!(params.n_frames, Some(10 * 1152 - 1000 - 576));
+
+        let packet = reader.next_packet().unwrap();
+        assert_eq!((packet.ts, packet.dur, packet.trim_start()), (0, 1152 - 1105, 1105));
+
+        let packet = (0..9).map(|_| reader.next_packet().unwrap()).last().unwrap();
+        assert_eq!((packet.ts, packet.trim_end()), (9 * 1152 - 1105, 1000 - 529));
+    }
+    #[test]
+    fn verify_xing_toc() {
+        // The first half of the duration is coded at a lower bitrate than the second half.
+        let mut xing_toc = [0u8; 100];
+
+        for (i, entry) in xing_toc.iter_mut().enumerate() {
+            *entry = if i < 50 { 2 * i as u8 } else { 100 + 3 * (i as u8 - 50) };
+        }
+
+        let toc = Toc::from_xing(&xing_toc, 100_000, 1000, 256_000);
+
+        assert_eq!(toc.pos_of(0), 1000);
+        assert_eq!(toc.pos_of(25_000), 51_000);
+        assert_eq!(toc.pos_of(25_500), 52_000);
+        assert_eq!(toc.pos_of(75_000), 176_000);
+        assert_eq!(toc.pos_of(200_000), 257_000);
+        assert_eq!(toc.ts_of(52_000), 25_500);
+        assert_eq!(toc.ts_of(500), 0);
+    }
+}
@@ -5,7 +5,7 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
-use symphonia_core::errors::{decode_error, unsupported_error, Result};
+use symphonia_core::errors::{decode_error, Result};
 use symphonia_core::io::ReadBytes;
 
 use crate::common::*;
@@ -126,10 +126,13 @@ pub fn parse_frame_header(header: u32) -> Result<FrameHeader> {
         _ => return decode_error("mpa: invalid MPEG layer"),
     };
 
+    let is_free_format = (header & 0xf000) == 0;
+
     let bitrate = match ((header & 0xf000) >> 12, version, layer) {
-        // "Free" bit-rate. Note, this is NOT variable bit-rate and is not a mandatory feature of
-        // MP3 decoders.
-        (0b0000, _, _) => return unsupported_error("mpa: free bit-rate is not supported"),
+        // "Free" bit-rate. Note, this is NOT variable bit-rate. The bit-rate, and therefore the
+        // frame size, is not signalled in the header and is only known once the frame length is
+        // found by other means.
+        (0b0000, _, _) => 0,
         // Invalid bit-rate.
         (0b1111, _, _) => return decode_error("mpa: invalid bit-rate"),
         // MPEG 1 bit-rates.
@@ -175,9 +178,9 @@ pub fn parse_frame_header(header: u32) -> Result<FrameHeader> {
         _ => unreachable!(),
     };
 
-    // Some layer 2 channel and bit-rate combinations are not allowed. Check that the frame does not
-    // use them.
-    if layer == MpegLayer::Layer2 {
+    // Some MPEG1 layer 2 channel and bit-rate combinations are not allowed. Check that the frame
+    // does not use them. MPEG2 and MPEG2.5 layer 2 do not have this restriction.
+    if version == MpegVersion::Mpeg1 && layer == MpegLayer::Layer2 && !is_free_format {
         if channel_mode == ChannelMode::Mono {
             if bitrate == 224_000 || bitrate == 256_000 || bitrate == 320_000 || bitrate == 384_000
             {
@@ -202,26 +205,19 @@ pub fn parse_frame_header(header: u32) -> Result<FrameHeader> {
 
     let has_crc = header & 0x1_0000 == 0;
 
-    // Constants provided for size calculation in section ISO-11172 section 2.4.3.1.
-    let factor = match layer {
-        MpegLayer::Layer1 => 12,
-        MpegLayer::Layer2 => 144,
-        MpegLayer::Layer3 if version == MpegVersion::Mpeg1 => 144,
-        MpegLayer::Layer3 => 72,
-    };
+    let (factor, slot_size) = slot_params(version, layer);
 
-    // The header specifies the total frame size in "slots". For layers 2 & 3 a slot is 1 byte,
-    // however for layer 1 a slot is 4 bytes.
-    let slot_size = match layer {
-        MpegLayer::Layer1 => 4,
-        _ => 1,
-    };
-
-    // Calculate the total frame size in number of slots.
-    let frame_size_slots = (factor * bitrate / sample_rate) as usize + usize::from(has_padding);
+    // Calculate the frame size in bytes, excluding the header. The size of a free-format frame is
+    // unknown until it is set with `set_free_format_len`.
+    let frame_size = if is_free_format {
+        0
+    }
+    else {
+        // Calculate the total frame size in number of slots.
+        let frame_size_slots = (factor * bitrate / sample_rate) as usize + usize::from(has_padding);
 
-    // Calculate the frame size in bytes, excluding the header.
-    let frame_size = (frame_size_slots * slot_size) - 4;
+        (frame_size_slots * slot_size) - 4
+    };
 
     Ok(FrameHeader {
         version,
@@ -235,10 +231,58 @@ pub fn parse_frame_header(header: u32) -> Result<FrameHeader> {
         is_original,
         has_padding,
         has_crc,
+        is_free_format,
         frame_size,
     })
 }
 
+/// Get the frame size factor, and the size in bytes of a slot, for a MPEG version and layer.
+fn slot_params(version: MpegVersion, layer: MpegLayer) -> (u32, usize) {
+    // Constants provided for size calculation in section ISO-11172 section 2.4.3.1.
+    let factor = match layer {
+        MpegLayer::Layer1 => 12,
+        MpegLayer::Layer2 => 144,
+        MpegLayer::Layer3 if version == MpegVersion::Mpeg1 => 144,
+        MpegLayer::Layer3 => 72,
+    };
+
+    // The header specifies the total frame size in "slots". For layers 2 & 3 a slot is 1 byte,
+    // however for layer 1 a slot is 4 bytes.
+    let slot_size = match layer {
+        MpegLayer::Layer1 => 4,
+        _ => 1,
+    };
+
+    (factor, slot_size)
+}
+
+/// Set the frame size and bit-rate of a free-format frame header.
+///
+/// All frames of a free-format stream have the same length, excluding padding. `unpadded_len` is
+/// that length in bytes, including the frame header.
+pub fn set_free_format_len(header: &mut FrameHeader, unpadded_len: usize) -> Result<()> {
+    debug_assert!(header.is_free_format);
+
+    let (factor, slot_size) = slot_params(header.version, header.layer);
+
+    let frame_len = unpadded_len + header.padding_len();
+
+    if unpadded_len % slot_size != 0
+        || unpadded_len < MPEG_HEADER_LEN + header.side_info_len()
+        || frame_len as u64 > MAX_MPEG_FRAME_SIZE
+    {
+        return decode_error("mpa: invalid free-format frame length");
+    }
+
+    // Derive the bit-rate from the number of slots in an unpadded frame.
+    let n_slots = (unpadded_len / slot_size) as u64;
+
+    header.bitrate = (n_slots * u64::from(header.sample_rate) / u64::from(factor)) as u32;
+    header.frame_size = frame_len - MPEG_HEADER_LEN;
+
+    Ok(())
+}
+
 /// Synchronize the stream to the start of the next MPEG audio frame header, then read and return
 /// the frame header or an error.
 #[inline]
@@ -254,3 +298,39 @@ pub fn read_frame_header<B: ReadBytes>(reader: &mut B) -> Result<FrameHeader> {
 pub fn read_frame_header_word_no_sync<B: ReadBytes>(reader: &mut B) -> Result<u32> {
     Ok(reader.read_be_u32()?)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_frame_header, set_free_format_len};
+    use crate::common::{ChannelMode, MpegVersion};
+
+    #[test]
+    fn verify_layer2_bitrate_mode_restrictions() {
+        // MPEG1 layer 2, 32 kbps, 44.1 kHz, stereo is not allowed.
+        assert!(parse_frame_header(0xfffd_1000).is_err());
+
+        // MPEG1 layer 2, 32 kbps, 44.1 kHz, mono is allowed.
+        let header = parse_frame_header(0xfffd_10c0).unwrap();
+        assert_eq!(header.channel_mode, ChannelMode::Mono);
+
+        // MPEG2 layer 2, 32 kbps, 22.05 kHz, stereo is allowed.
+        let header = parse_frame_header(0xfff5_4000).unwrap();
+        assert_eq!(header.version, MpegVersion::Mpeg2);
+        assert_eq!(header.bitrate, 32_000);
+        assert_eq!(header.channel_mode, ChannelMode::Stereo);
+    }
+
+    #[test]
+    fn verify_free_format_len() {
+        // MPEG1 layer 3, free-format, 44.1 kHz, joint stereo, padded.
+        let mut header = parse_frame_header(0xfffb_0264).unwrap();
+        assert!(header.is_free_format);
+
+        set_free_format_len(&mut header, 600).unwrap();
+        assert_eq!(header.frame_size, 597);
+        assert_eq!(header.bitrate, 183_750);
+
+        // A frame shorter than its side information is invalid.
+        assert!(set_free_format_len(&mut header, 32).is_err());
+    }
+}
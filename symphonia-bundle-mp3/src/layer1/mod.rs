@@ -97,9 +97,10 @@ impl Layer for Layer1 {
             _ => 32,
         };
 
-        // Read bit allocations for each non-intensity coded sub-bands.
+        // Read bit allocations for each non-intensity coded sub-bands. A bit allocation is only
+        // coded for the channels present in the frame.
         for sb in 0..bound {
-            for chan in &mut alloc {
+            for chan in &mut alloc[..num_channels] {
                 let bits = bs.read_bits_leq32(4)? as u8;
 
                 if bits > 0xe {
@@ -190,3 +191,33 @@ impl Layer for Layer1 {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::audio::{AudioBuffer, Signal};
+    use symphonia_core::io::BufReader;
+
+    use super::Layer1;
+    use crate::common::Layer;
+    use crate::header::parse_frame_header;
+
+    #[test]
+    fn verify_mono_bit_allocation() {
+        // A MPEG1 layer 1 frame (128 kbps, 44.1 kHz, mono). Only sub-band 0 is allocated 4 bits
+        // per sample, with a scalefactor index of 0, and 12 samples of 0b1111. If bit allocations
+        // were read for 2 channels, the samples would be read as an invalid bit allocation.
+        let header = parse_frame_header(0xffff_40c0).unwrap();
+
+        let mut body = vec![0u8; header.frame_size];
+        body[0] = 0x30;
+        body[16..23].copy_from_slice(&[0x03, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfc]);
+
+        let mut out = AudioBuffer::<f32>::new(1152, header.spec());
+        let mut layer = Layer1::new();
+
+        layer.decode(&mut BufReader::new(&body), &header, &mut out).unwrap();
+
+        assert_eq!(out.frames(), 384);
+        assert!(out.chan(0).iter().any(|&s| s.abs() > 0.1));
+    }
+}
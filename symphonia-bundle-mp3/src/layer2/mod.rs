@@ -139,7 +139,11 @@ fn find_sb_info(header: &FrameHeader) -> &'static SbInfo {
 
         let bitrate_per_channel = header.bitrate / num_channels;
 
-        if bitrate_per_channel <= 48_000 {
+        if header.is_free_format {
+            // Table 3-B.2a and 3-B.2b are used for free format.
+            usize::from(header.sample_rate != 48_000)
+        }
+        else if bitrate_per_channel <= 48_000 {
             // Table 3-B.2c and 3-B.2d are only used for bitrates <= 48 kbit/s.
             if header.sample_rate == 32_000 {
                 3
@@ -154,7 +158,6 @@ fn find_sb_info(header: &FrameHeader) -> &'static SbInfo {
         }
         else {
             // Table 3-B.2a and 3-B.2b as always used for bitrates > 80 kbit/s.
-            // TODO: Free format also used this case, but we don't support it.
             usize::from(header.sample_rate != 48_000)
         }
     }
@@ -108,8 +108,11 @@ fn read_granule_channel_side_info<B: ReadBitsLtr>(
             // For MPEG2.5, the number of scale-factor bands in region0 depends on the block type.
             // The standard indicates these values as 1 less than the actual value, therefore 1 is
             // added here to both values.
+            //
+            // Like MPEG2, mixed blocks use the same region0 as Short blocks. At 11.025 and 12 kHz
+            // this is 36 samples, the same as MPEG2, while at 8 kHz it is 72 samples.
             let region0_count = match channel.block_type {
-                BlockType::Short { is_mixed: false } => 5 + 1,
+                BlockType::Short { .. } => 5 + 1,
                 _ => 7 + 1,
             };
 
@@ -130,8 +133,6 @@ fn read_granule_channel_side_info<B: ReadBitsLtr>(
             // of sample and thus sum to 36 samples.
             //
             // In all cases, the region0_count is 36.
-            //
-            // TODO: This is not accurate for MPEG2.5 at 8kHz.
             channel.region1_start = 36;
         }
         // If MPEG version 2 AND the block type is not Short...
@@ -391,7 +392,7 @@ pub(super) fn read_scale_factors_mpeg2<B: ReadBitsLtr>(
                 ],
                 &SCALE_FACTOR_MPEG2_NSFB[4][block_index],
             ),
-            500..=512 => (
+            500..=511 => (
                 [
                     (sfc - 500) / 3, //
                     (sfc - 500) % 3, //
@@ -417,8 +418,65 @@ pub(super) fn read_scale_factors_mpeg2<B: ReadBitsLtr>(
             bits_read += slen * n_sfb as u32;
         }
 
+        // For the intensity stereo coded channel, the maximum value of a scale factor is an
+        // invalid intensity position. See ISO/IEC 13818-3 section 2.4.3.2.
+        if is_intensity_stereo {
+            for inv_pos in &mut channel.is_inv_pos[start..(start + n_sfb)] {
+                *inv_pos = ((1 << slen) - 1) as u8;
+            }
+        }
+
         start += n_sfb;
     }
 
     Ok(bits_read)
 }
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::io::BitReaderLtr;
+
+    use super::read_granule_channel_side_info;
+    use crate::header::parse_frame_header;
+    use crate::layer3::common::BlockType;
+    use crate::layer3::GranuleChannel;
+
+    /// Reads the side information of a window switched granule channel with Short blocks.
+    fn read_short_granule(header_word: u32, is_mixed: bool) -> GranuleChannel {
+        let header = parse_frame_header(header_word).unwrap();
+
+        // All fields are 0, except for the window switching flag, the block type (0b10), and the
+        // mixed block flag.
+        let mut side_info = [0u8; 8];
+        side_info[4] = 0b0000_0011;
+        side_info[5] = if is_mixed { 0b0100_0000 } else { 0 };
+
+        let mut channel = GranuleChannel::default();
+        let mut bs = BitReaderLtr::new(&side_info);
+        read_granule_channel_side_info(&mut bs, &mut channel, &header).unwrap();
+
+        assert_eq!(channel.block_type, BlockType::Short { is_mixed });
+        channel
+    }
+
+    #[test]
+    fn verify_mpeg2p5_short_regions() {
+        // MPEG2.5 layer 3, 8 kbps, 8 kHz, mono. Region0 spans the first 6 long bands, which are
+        // 12 samples wide at 8 kHz, for both mixed and non-mixed blocks.
+        for &is_mixed in &[true, false] {
+            let channel = read_short_granule(0xffe3_18c0, is_mixed);
+            assert_eq!(channel.region1_start, 72);
+            assert_eq!(channel.region2_start, 576);
+        }
+
+        // MPEG2.5 layer 3, 8 kbps, 11.025 kHz, mono. The bands are the same as MPEG2.
+        let channel = read_short_granule(0xffe3_10c0, true);
+        assert_eq!(channel.region1_start, 36);
+        assert_eq!(channel.region2_start, 576);
+
+        // MPEG2 layer 3, 8 kbps, 22.05 kHz, mono.
+        let channel = read_short_granule(0xfff3_10c0, true);
+        assert_eq!(channel.region1_start, 36);
+        assert_eq!(channel.region2_start, 576);
+    }
+}
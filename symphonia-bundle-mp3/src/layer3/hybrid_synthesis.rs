@@ -234,10 +234,14 @@ pub(super) fn antialias(channel: &mut GranuleChannel, samples: &mut [f32; 576])
     // are zeroed and do-not need anti-aliasing.
     let sb_rzero = channel.rzero / 18;
 
-    // The anti-aliasing filter must be applied up-to the last non-zero sub-band. After
-    // anti-aliasing, the first zeroed sub-band may have non-zero values "smeared" into it.
-    // Therefore, the rzero must be updated.
-    channel.rzero = 18 * sb_limit.min(sb_rzero + 2).min(32);
+    // The anti-aliasing filter must be applied up-to the last non-zero sub-band, but not past the
+    // sub-band limit.
+    let sb_end = sb_limit.min(sb_rzero + 2);
+
+    // After anti-aliasing, the first zeroed sub-band may have non-zero values "smeared" into it.
+    // Therefore, the rzero must be updated. For mixed blocks, the short block sub-bands past the
+    // sub-band limit remain non-zero.
+    channel.rzero = channel.rzero.max(18 * sb_end);
 
     // Anti-aliasing is performed using 8 butterfly calculations at the boundaries of ADJACENT
     // sub-bands. For each calculation, there are two samples: lower and upper. For each iteration,
@@ -264,7 +268,7 @@ pub(super) fn antialias(channel: &mut GranuleChannel, samples: &mut [f32; 576])
     //
     // Note that all butterfly calculations only involve two samples, and all iterations are
     // independant of each other. This lends itself well for SIMD processing.
-    for sb in (18..channel.rzero).step_by(18) {
+    for sb in (18..18 * sb_end).step_by(18) {
         for i in 0..8 {
             let li = sb - 1 - i;
             let ui = sb + i;
@@ -481,8 +485,10 @@ pub fn frequency_inversion(samples: &mut [f32; 576]) {
 
 #[cfg(test)]
 mod tests {
-    use super::imdct12_win;
     use super::IMDCT_WINDOWS;
+    use super::{antialias, imdct12_win};
+    use crate::layer3::common::BlockType;
+    use crate::layer3::GranuleChannel;
     use std::f64;
 
     fn imdct12_analytical(x: &[f32; 6]) -> [f32; 12] {
@@ -549,6 +555,28 @@ mod tests {
             assert!((expected[i + 18] - overlap[i]).abs() < 0.00001);
         }
     }
+
+    #[test]
+    fn verify_antialias_mixed() {
+        let mut channel = GranuleChannel::default();
+        channel.block_type = BlockType::Short { is_mixed: true };
+        channel.rzero = 300;
+
+        let mut samples = [1.0; 576];
+        antialias(&mut channel, &mut samples);
+
+        // Only the boundary between the 2 long block sub-bands is anti-aliased, and the short
+        // block sub-bands remain non-zero.
+        assert_eq!(channel.rzero, 300);
+        assert!(samples[10..26].iter().all(|&s| s != 1.0));
+        assert!(samples[..10].iter().chain(&samples[26..]).all(|&s| s == 1.0));
+
+        // Anti-aliasing smears the first long block sub-band into the second.
+        channel.rzero = 10;
+        antialias(&mut channel, &mut samples);
+
+        assert_eq!(channel.rzero, 36);
+    }
 }
 
 mod imdct36 {
@@ -199,6 +199,10 @@ struct GranuleChannel {
     /// in length (maximum value 15). For MPEG2 with intensity stereo, a scale factor will not
     /// exceed 5 bits (maximum value 31) in length.
     scalefacs: [u8; 39],
+    /// For the intensity stereo coded channel of a MPEG2/2.5 bitstream, the invalid intensity
+    /// position of each scale factor. A scale factor that is slen bits long has an invalid
+    /// intensity position of 2^slen - 1.
+    is_inv_pos: [u8; 39],
     /// The starting sample index of the rzero partition, or the count of big_values and count1
     /// samples.
     rzero: usize,
@@ -220,6 +224,7 @@ impl Default for GranuleChannel {
             scalefac_scale: false,
             count1table_select: 0,
             scalefacs: [0; 39],
+            is_inv_pos: [0; 39],
             rzero: 0,
         }
     }
@@ -246,6 +251,11 @@ impl fmt::Debug for GranuleChannel {
             write!(f, "{}, ", sf)?;
         }
         writeln!(f, "]")?;
+        write!(f, "\tis_inv_pos=[ ")?;
+        for pos in &self.is_inv_pos[..] {
+            write!(f, "{}, ", pos)?;
+        }
+        writeln!(f, "]")?;
         writeln!(f, "\trzero={}", self.rzero)?;
         writeln!(f, "}}")
     }
@@ -381,6 +391,7 @@ impl Layer for Layer3 {
         // frame.
         let mut frame_data: FrameData = Default::default();
 
+        // The CRC is verified by the decoder before the frame is decoded.
         let _crc = if header.has_crc { Some(reader.read_be_u16()?) } else { None };
 
         let buf = reader.read_buf_bytes_available_ref();
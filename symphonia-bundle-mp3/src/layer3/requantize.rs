@@ -375,7 +375,8 @@ pub(super) fn requantize(header: &FrameHeader, channel: &GranuleChannel, buf: &m
             let bands = SFB_MIXED_BANDS[header.sample_rate_idx];
             let switch = SFB_MIXED_SWITCH_POINT[header.sample_rate_idx];
 
-            requantize_long(channel, &bands[..switch], buf);
+            // The last long band ends where the first short band begins.
+            requantize_long(channel, &bands[..=switch], buf);
             requantize_short(channel, &bands[switch..], switch, buf);
         }
         _ => {
@@ -383,3 +384,46 @@ pub(super) fn requantize(header: &FrameHeader, channel: &GranuleChannel, buf: &m
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::requantize;
+    use crate::common::*;
+    use crate::layer3::common::BlockType;
+    use crate::layer3::GranuleChannel;
+
+    #[test]
+    fn verify_requantize_mixed() {
+        let header = FrameHeader {
+            version: MpegVersion::Mpeg1,
+            layer: MpegLayer::Layer3,
+            bitrate: 128_000,
+            sample_rate: 44_100,
+            sample_rate_idx: 0,
+            channel_mode: ChannelMode::Mono,
+            emphasis: Emphasis::None,
+            is_copyrighted: false,
+            is_original: false,
+            has_padding: false,
+            has_crc: false,
+            is_free_format: false,
+            frame_size: 417,
+        };
+
+        let mut channel = GranuleChannel::default();
+        channel.block_type = BlockType::Short { is_mixed: true };
+        channel.global_gain = 210;
+        channel.rzero = 576;
+
+        // A scale factor of 2 halves the samples of the last long band, 30..36. The first short
+        // band, 36..40, is not scaled.
+        channel.scalefacs[7] = 2;
+
+        let mut buf = [1.0; 576];
+        requantize(&header, &channel, &mut buf);
+
+        assert!(buf[..30].iter().all(|&s| s == 1.0));
+        assert!(buf[30..36].iter().all(|&s| s == 0.5));
+        assert!(buf[36..].iter().all(|&s| s == 1.0));
+    }
+}
@@ -19,15 +19,6 @@ use lazy_static::lazy_static;
 /// The invalid intensity position for MPEG1 bitstreams.
 const INTENSITY_INV_POS_MPEG1: u8 = 7;
 
-/// The invalid intensity position for MPEG2 and MPEG2.5 bitstreams.
-///
-/// NOTE: Some decoders also consider 7 to be an invalid intensity position in MPEG2 and MPEG2.5.
-/// However, this appears wrong. According to the standard, the /maximum/ value for the intensity
-/// position is considered invalid. For MPEG1, since the intensity ratios are only specified for
-/// is_pos < 7. For MPEG2 the ratios are specified for is_pos < 31. Therefore, it doesn't make sense
-/// to consider is_pos == 7 invalid for MPEG2 or 2.5.
-const INTENSITY_INV_POS_MPEG2: u8 = 31;
-
 lazy_static! {
     /// (Left, right) channel coefficients for decoding intensity stereo in MPEG2 bitstreams.
     ///
@@ -54,8 +45,9 @@ lazy_static! {
     /// ```
     ///
     /// The first dimension of this table is indexed by scalefac_compress & 1 to select i0. The
-    /// second dimension is indexed by is_pos to obtain the channel coefficients. Note that
-    /// is_pos == 31 is considered an invalid position, but IS included in the table.
+    /// second dimension is indexed by is_pos to obtain the channel coefficients. Note that the
+    /// invalid position of a scale factor band depends on the length of its scale factor, and may
+    /// be any is_pos < 32. Therefore, all positions are included in the table.
     static ref INTENSITY_STEREO_RATIOS_MPEG2: [[(f32, f32); 32]; 2] = {
         let is_scale: [f64; 2] = [
             1.0 / f64::sqrt(f64::consts::SQRT_2),
@@ -193,6 +185,23 @@ fn is_zero_band(band: &[f32]) -> bool {
     !band.iter().any(|&x| x != 0.0)
 }
 
+/// Gets the intensity stereo ratios table, and the invalid intensity position of each scale factor
+/// in channel 1.
+///
+/// NOTE: Some decoders consider 7 to be the invalid intensity position in MPEG2 and MPEG2.5 as
+/// well. However, according to ISO/IEC 13818-3, the /maximum/ value of the scale factor that
+/// codes the intensity position is considered invalid. Since the length of a scale factor varies
+/// by band, so does the invalid intensity position.
+fn intensity_params(header: &FrameHeader, granule: &Granule) -> (&'static [(f32, f32)], [u8; 39]) {
+    if header.is_mpeg1() {
+        (&INTENSITY_STEREO_RATIOS_MPEG1[..], [INTENSITY_INV_POS_MPEG1; 39])
+    }
+    else {
+        let is_scale = granule.channels[1].scalefac_compress & 1;
+        (&INTENSITY_STEREO_RATIOS_MPEG2[usize::from(is_scale)][..], granule.channels[1].is_inv_pos)
+    }
+}
+
 /// Decodes all intensity stereo coded bands within an entire long block and returns the intensity
 /// bound.
 fn process_intensity_long_block(
@@ -212,14 +221,8 @@ fn process_intensity_long_block(
     // The rzero sample index is the index of last non-zero sample plus 1.
     let rzero = granule.channels[1].rzero;
 
-    // Select the intensity stereo ratios table.
-    let (is_table, is_inv_pos) = if header.is_mpeg1() {
-        (&INTENSITY_STEREO_RATIOS_MPEG1[..], INTENSITY_INV_POS_MPEG1)
-    }
-    else {
-        let is_scale = granule.channels[1].scalefac_compress & 1;
-        (&INTENSITY_STEREO_RATIOS_MPEG2[usize::from(is_scale)][..], INTENSITY_INV_POS_MPEG2)
-    };
+    // Select the intensity stereo ratios table, and get the invalid intensity positions.
+    let (is_table, inv_pos) = intensity_params(header, granule);
 
     let bands = &SFB_LONG_BANDS[header.sample_rate_idx];
 
@@ -229,13 +232,17 @@ fn process_intensity_long_block(
     is_pos.copy_from_slice(&granule.channels[1].scalefacs[..22]);
     is_pos[21] = is_pos[20];
 
-    // Create an iterator that yields a band start-end pair, and scale-factor.
-    let bands_iter = bands.iter().zip(&bands[1..]).zip(is_pos.iter());
+    let mut is_inv_pos = [0; 22];
+    is_inv_pos.copy_from_slice(&inv_pos[..22]);
+    is_inv_pos[21] = is_inv_pos[20];
+
+    // Create an iterator that yields a band start-end pair, scale-factor, and invalid position.
+    let bands_iter = bands.iter().zip(&bands[1..]).zip(is_pos.iter().zip(is_inv_pos.iter()));
 
     let mut bound = max_bound;
 
     // Iterate over each band and decode the intensity stereo coding if the band is zero.
-    for ((&start, &end), &is_pos) in bands_iter.rev() {
+    for ((&start, &end), (&is_pos, &is_inv_pos)) in bands_iter.rev() {
         // Bands starting above rzero are always 0, however bands below it are ambiguous.
         let is_zero_band = start >= rzero || is_zero_band(&ch1[start..end]);
 
@@ -338,20 +345,22 @@ fn process_intensity_short_block(
         (&SFB_SHORT_BANDS[header.sample_rate_idx][..], None, 39)
     };
 
-    // Select the intensity stereo ratios table based on the bitstream version.
-    let (is_table, is_inv_pos) = if header.is_mpeg1() {
-        (&INTENSITY_STEREO_RATIOS_MPEG1[..], INTENSITY_INV_POS_MPEG1)
-    }
-    else {
-        let is_scale = granule.channels[1].scalefac_compress & 1;
-        (&INTENSITY_STEREO_RATIOS_MPEG2[usize::from(is_scale)][..], INTENSITY_INV_POS_MPEG2)
-    };
+    // Select the intensity stereo ratios table based on the bitstream version, and get the invalid
+    // intensity positions.
+    let (is_table, inv_pos) = intensity_params(header, granule);
 
     // The intensity position for the final band (last three short windows) is not coded and is
-    // copied from the previous band.
+    // copied from the previous band. If the block is mixed, the final band ends before the last
+    // scale-factor.
+    let n_coded = sfi - 3;
+
     let mut is_pos = [0; 39];
-    is_pos[..36].copy_from_slice(&granule.channels[1].scalefacs[..36]);
-    is_pos[36..].copy_from_slice(&granule.channels[1].scalefacs[33..36]);
+    is_pos[..n_coded].copy_from_slice(&granule.channels[1].scalefacs[..n_coded]);
+    is_pos[n_coded..sfi].copy_from_slice(&granule.channels[1].scalefacs[n_coded - 3..n_coded]);
+
+    let mut is_inv_pos = [0; 39];
+    is_inv_pos[..n_coded].copy_from_slice(&inv_pos[..n_coded]);
+    is_inv_pos[n_coded..sfi].copy_from_slice(&inv_pos[n_coded - 3..n_coded]);
 
     let mut window_is_zero = [true; 3];
 
@@ -379,7 +388,7 @@ fn process_intensity_short_block(
             process_intensity(
                 is_pos[sfi - 1],
                 is_table,
-                is_inv_pos,
+                is_inv_pos[sfi - 1],
                 mid_side,
                 &mut ch0[s2..s3],
                 &mut ch1[s2..s3],
@@ -400,7 +409,7 @@ fn process_intensity_short_block(
             process_intensity(
                 is_pos[sfi - 1],
                 is_table,
-                is_inv_pos,
+                is_inv_pos[sfi - 1],
                 mid_side,
                 &mut ch0[s1..s2],
                 &mut ch1[s1..s2],
@@ -419,7 +428,7 @@ fn process_intensity_short_block(
             process_intensity(
                 is_pos[sfi - 1],
                 is_table,
-                is_inv_pos,
+                is_inv_pos[sfi - 1],
                 mid_side,
                 &mut ch0[s0..s1],
                 &mut ch1[s0..s1],
@@ -460,7 +469,7 @@ fn process_intensity_short_block(
                     process_intensity(
                         is_pos[sfi - 1],
                         is_table,
-                        is_inv_pos,
+                        is_inv_pos[sfi - 1],
                         mid_side,
                         &mut ch0[start..end],
                         &mut ch1[start..end],
@@ -551,3 +560,65 @@ pub(super) fn stereo(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::stereo;
+    use crate::common::*;
+    use crate::layer3::{Granule, GranuleChannel};
+
+    #[test]
+    fn verify_intensity_inv_pos_mpeg2() {
+        let header = FrameHeader {
+            version: MpegVersion::Mpeg2,
+            layer: MpegLayer::Layer3,
+            bitrate: 64_000,
+            sample_rate: 22_050,
+            sample_rate_idx: 3,
+            channel_mode: ChannelMode::JointStereo(Mode::Layer3 {
+                mid_side: false,
+                intensity: true,
+            }),
+            emphasis: Emphasis::None,
+            is_copyrighted: false,
+            is_original: false,
+            has_padding: false,
+            has_crc: false,
+            is_free_format: false,
+            frame_size: 209,
+        };
+
+        let mut granule =
+            Granule { channels: [GranuleChannel::default(), GranuleChannel::default()] };
+
+        granule.channels[0].rzero = 576;
+
+        // Channel 1 is entirely intensity stereo coded with 3-bit scale factors, and i0 is
+        // 1 / sqrt(2).
+        granule.channels[1].scalefac_compress = 1;
+        granule.channels[1].is_inv_pos = [7; 39];
+
+        // Band 20 (and therefore band 21) has an invalid intensity position, while band 19 has an
+        // intensity position of 2.
+        granule.channels[1].scalefacs[20] = 7;
+        granule.channels[1].scalefacs[19] = 2;
+
+        let mut samples = [[0.0; 576]; 2];
+        samples[0] = [1.0; 576];
+
+        stereo(&header, &mut granule, &mut samples).unwrap();
+
+        let bands = &crate::layer3::common::SFB_LONG_BANDS[3];
+
+        // Bands with an invalid intensity position are not intensity stereo decoded.
+        assert!(samples[0][bands[20]..].iter().all(|&s| s == 1.0));
+        assert!(samples[1][bands[20]..].iter().all(|&s| s == 0.0));
+
+        // For even intensity positions, the left channel is unscaled, and the right channel is
+        // scaled by i0 ^ (is_pos / 2).
+        for (&l, &r) in samples[0][bands[19]..bands[20]].iter().zip(&samples[1][bands[19]..]) {
+            assert_eq!(l, 1.0);
+            assert!((r - std::f32::consts::FRAC_1_SQRT_2).abs() < 1e-6);
+        }
+    }
+}
@@ -326,7 +326,7 @@ fn run_test(path: &str, opts: &TestOptions, result: &mut TestResult) -> Result<(
     let tgt_ms = Box::new(File::open(Path::new(path))?);
     let tgt_mss = MediaSourceStream::new(tgt_ms, Default::default());
 
-    let tgt_fmt_opts = FormatOptions { enable_gapless: opts.gapless, ..Default::default() };
+    let tgt_fmt_opts = FormatOptions::default().with_enable_gapless(opts.gapless);
 
     let mut tgt_inst = DecoderInstance::try_open(tgt_mss, &tgt_fmt_opts)?;
 
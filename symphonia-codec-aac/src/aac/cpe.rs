@@ -154,11 +154,12 @@ impl ChannelPair {
         dsp: &mut dsp::Dsp,
         abuf: &mut AudioBuffer<f32>,
         rate_idx: usize,
+        channel_map: &[usize],
     ) {
-        self.ics0.synth_channel(dsp, rate_idx, abuf.chan_mut(self.channel));
+        self.ics0.synth_channel(dsp, rate_idx, abuf.chan_mut(channel_map[self.channel]));
 
         if self.is_pair {
-            self.ics1.synth_channel(dsp, rate_idx, abuf.chan_mut(self.channel + 1));
+            self.ics1.synth_channel(dsp, rate_idx, abuf.chan_mut(channel_map[self.channel + 1]));
         }
     }
 }
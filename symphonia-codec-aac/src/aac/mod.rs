@@ -20,6 +20,8 @@ use symphonia_core::io::{BitReaderLtr, FiniteBitStream, ReadBitsLtr};
 use symphonia_core::support_codec;
 use symphonia_core::units::Duration;
 
+use log::warn;
+
 mod codebooks;
 mod common;
 mod cpe;
@@ -30,6 +32,12 @@ mod window;
 use crate::common::*;
 use common::*;
 
+/// Fill element extension payload type for SBR data.
+const EXT_SBR_DATA: u32 = 0xd;
+
+/// Fill element extension payload type for SBR data protected by a CRC.
+const EXT_SBR_DATA_CRC: u32 = 0xe;
+
 struct M4AInfo {
     otype: M4AType,
     srate: u32,
@@ -99,6 +107,10 @@ impl M4AInfo {
         self.channels = Self::read_channel_config(&mut bs)?;
 
         if (self.otype == M4AType::Sbr) || (self.otype == M4AType::PS) {
+            // Explicit, hierarchical, signalling of SBR and PS.
+            self.sbr_present = true;
+            self.ps_present = self.otype == M4AType::PS;
+
             let ext_srate = Self::read_sampling_frequency(&mut bs)?;
             self.otype = Self::read_object_type(&mut bs)?;
 
@@ -246,7 +258,9 @@ impl M4AInfo {
             _ => {}
         };
 
-        if self.sbr_ps_info.is_some() && (bs.bits_left() >= 16) {
+        // Explicit, backwards-compatible, signalling of SBR and PS is only present if the
+        // hierarchical signalling was not used.
+        if self.sbr_ps_info.is_none() && (bs.bits_left() >= 16) {
             let sync = bs.read_bits_leq32(11)?;
 
             if sync == 0x2B7 {
@@ -287,10 +301,34 @@ impl std::fmt::Display for M4AInfo {
     }
 }
 
+/// Get the mapping from the order channels are coded in the bitstream to their index in the
+/// output audio buffer for a default channel configuration. The output audio buffer is ordered by
+/// channel position, see `map_channels`.
+fn output_channel_map(channels: usize) -> &'static [usize] {
+    match channels {
+        // C, L, R
+        3 => &[2, 0, 1],
+        // C, L, R, Cs
+        4 => &[2, 0, 1, 3],
+        // C, L, R, Ls, Rs
+        5 => &[2, 0, 1, 3, 4],
+        // C, L, R, Ls, Rs, LFE
+        6 => &[2, 0, 1, 4, 5, 3],
+        // C, L, R, Lw, Rw, Ls, Rs, LFE
+        8 => &[2, 0, 1, 6, 7, 4, 5, 3],
+        // Mono or stereo.
+        _ => &[0, 1],
+    }
+}
+
 /// Advanced Audio Coding (AAC) decoder.
 ///
 /// Implements a decoder for Advanced Audio Decoding Low-Complexity (AAC-LC) as defined in
 /// ISO/IEC 13818-7 and ISO/IEC 14496-3.
+///
+/// High-Efficiency AAC (HE-AAC) is not supported. Spectral Band Replication (SBR) and Parametric
+/// Stereo (PS) data is skipped, and only the AAC-LC core is decoded. Therefore, for an HE-AAC
+/// stream, the output has half the sample rate and bandwidth of the original audio.
 pub struct AacDecoder {
     // info: NACodecInfoRef,
     m4ainfo: M4AInfo,
@@ -368,14 +406,24 @@ impl AacDecoder {
                 }
                 6 => {
                     // ID_FIL
-                    let mut count = bs.read_bits_leq32(4)? as usize;
+                    let mut count = bs.read_bits_leq32(4)?;
                     if count == 15 {
-                        count += bs.read_bits_leq32(8)? as usize;
+                        count += bs.read_bits_leq32(8)?;
                         count -= 1;
                     }
-                    for _ in 0..count {
-                        // ext payload
-                        bs.ignore_bits(8)?;
+                    if count > 0 {
+                        // The extension payload type.
+                        let ext_type = bs.read_bits_leq32(4)?;
+
+                        // Implicit signalling of SBR.
+                        if (ext_type == EXT_SBR_DATA || ext_type == EXT_SBR_DATA_CRC)
+                            && !self.m4ainfo.sbr_present
+                        {
+                            warn!("aac: sbr is not supported, decoding the core at half bandwidth");
+                            self.m4ainfo.sbr_present = true;
+                        }
+
+                        bs.ignore_bits(8 * count - 4)?;
                     }
                 }
                 7 => {
@@ -386,8 +434,9 @@ impl AacDecoder {
             };
         }
         let rate_idx = GASubbandInfo::find_idx(self.m4ainfo.srate);
+        let channel_map = output_channel_map(self.m4ainfo.channels);
         for pair in 0..cur_pair {
-            self.pairs[pair].synth_audio(&mut self.dsp, &mut self.buf, rate_idx);
+            self.pairs[pair].synth_audio(&mut self.dsp, &mut self.buf, rate_idx, channel_map);
         }
         Ok(())
     }
@@ -453,11 +502,20 @@ impl Decoder for AacDecoder {
 
         //print!("edata:"); for s in edata.iter() { print!(" {:02X}", *s);}println!("");
 
-        if (m4ainfo.otype != M4AType::Lc) || (m4ainfo.channels > 2) || (m4ainfo.samples != 1024) {
+        if (m4ainfo.otype != M4AType::Lc) || (m4ainfo.samples != 1024) {
             return unsupported_error("aac: aac too complex");
         }
 
-        let spec = SignalSpec::new(m4ainfo.srate, map_channels(m4ainfo.channels as u32).unwrap());
+        if m4ainfo.sbr_present {
+            warn!("aac: sbr is not supported, decoding the core at half bandwidth");
+        }
+
+        let channels = match map_channels(m4ainfo.channels as u32) {
+            Some(channels) => channels,
+            None => return unsupported_error("aac: unsupported channel configuration"),
+        };
+
+        let spec = SignalSpec::new(m4ainfo.srate, channels);
 
         let duration = m4ainfo.samples as Duration;
         let srate = m4ainfo.srate;
@@ -504,3 +562,242 @@ impl Decoder for AacDecoder {
         self.buf.as_audio_buffer_ref()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::audio::{AudioBufferRef, Channels, Signal};
+    use symphonia_core::codecs::{CodecParameters, Decoder, DecoderOptions, CODEC_TYPE_AAC};
+    use symphonia_core::formats::Packet;
+    use symphonia_core::io::BitWriterLtr;
+
+    use super::{output_channel_map, AacDecoder};
+    use crate::common::map_channels;
+
+    /// Builds an AudioSpecificConfig for a 22.05 kHz AAC-LC core with the provided channel
+    /// configuration. If `explicit_sbr` is true, SBR is signalled hierarchically with a 44.1 kHz
+    /// extension sample rate.
+    fn make_asc(channel_config: u32, explicit_sbr: bool) -> Vec<u8> {
+        let mut bw = BitWriterLtr::new();
+        if explicit_sbr {
+            // Object type SBR, core sample rate index (22.05 kHz), channel configuration,
+            // extension sample rate index (44.1 kHz), and then the object type of the core.
+            bw.write_bits_leq32(5, 5);
+            bw.write_bits_leq32(7, 4);
+            bw.write_bits_leq32(channel_config, 4);
+            bw.write_bits_leq32(3, 4);
+            bw.write_bits_leq32(2, 5);
+        }
+        else {
+            bw.write_bits_leq32(2, 5);
+            bw.write_bits_leq32(7, 4);
+            bw.write_bits_leq32(channel_config, 4);
+        }
+        // GASpecificConfig: 1024 sample frames, no core coder, no extension.
+        bw.write_bits_leq32(0, 3);
+        bw.into_inner()
+    }
+
+    /// Writes an individual channel stream that codes the lowest `max_sfb` scale factor bands
+    /// with perceptual noise substitution.
+    fn write_ics(bw: &mut BitWriterLtr, global_gain: u32, max_sfb: u32) {
+        bw.write_bits_leq32(global_gain, 8);
+        // ICS info: reserved bit, ONLY_LONG_SEQUENCE, sine window, max_sfb, no prediction.
+        bw.write_bits_leq32(0, 1);
+        bw.write_bits_leq32(0, 2);
+        bw.write_bits_leq32(0, 1);
+        bw.write_bits_leq32(max_sfb, 6);
+        bw.write_bits_leq32(0, 1);
+        // Section data: one section of all bands using NOISE_HCB.
+        bw.write_bits_leq32(13, 4);
+        bw.write_bits_leq32(max_sfb, 5);
+        // Scale factor data: the first noise energy is PCM coded, the remainder are coded with a
+        // delta of 0, which is a single 0 bit.
+        bw.write_bits_leq32(256, 9);
+        bw.write_bits_leq32(0, max_sfb - 1);
+        // No pulse, TNS, or gain control data.
+        bw.write_bits_leq32(0, 3);
+    }
+
+    /// Writes a fill element carrying SBR extension data.
+    fn write_sbr_fil(bw: &mut BitWriterLtr, payload: &[u8]) {
+        // ID_FIL, byte count, and the SBR extension payload type. The payload ends with 4 fill
+        // bits to byte align the extension payload.
+        bw.write_bits_leq32(6, 3);
+        bw.write_bits_leq32(payload.len() as u32 + 1, 4);
+        bw.write_bits_leq32(0xd, 4);
+        for &byte in payload {
+            bw.write_bits_leq32(u32::from(byte), 8);
+        }
+        bw.write_bits_leq32(0xf, 4);
+    }
+
+    /// Builds a raw data block of a 3 channel access unit. If `sbr_payload` is provided, each
+    /// channel element is followed by a fill element carrying its SBR extension data, as in an
+    /// HE-AAC stream.
+    fn make_raw_data_block(sbr_payload: Option<&[u8]>) -> Vec<u8> {
+        let mut bw = BitWriterLtr::new();
+
+        // ID_SCE, element instance tag.
+        bw.write_bits_leq32(0, 3);
+        bw.write_bits_leq32(0, 4);
+        write_ics(&mut bw, 100, 4);
+
+        if let Some(payload) = sbr_payload {
+            write_sbr_fil(&mut bw, payload);
+        }
+
+        // ID_CPE, element instance tag, no common window.
+        bw.write_bits_leq32(1, 3);
+        bw.write_bits_leq32(0, 4);
+        bw.write_bits_leq32(0, 1);
+        write_ics(&mut bw, 100, 6);
+        write_ics(&mut bw, 110, 2);
+
+        if let Some(payload) = sbr_payload {
+            write_sbr_fil(&mut bw, payload);
+        }
+
+        // ID_END
+        bw.write_bits_leq32(7, 3);
+        bw.into_inner()
+    }
+
+    fn make_decoder(asc: Vec<u8>) -> AacDecoder {
+        let mut params = CodecParameters::new();
+        params.for_codec(CODEC_TYPE_AAC).with_extra_data(asc.into_boxed_slice());
+        AacDecoder::try_new(&params, &DecoderOptions::default()).unwrap()
+    }
+
+    fn decode(decoder: &mut AacDecoder, buf: &[u8]) -> Vec<Vec<f32>> {
+        let packet = Packet::new_from_slice(0, 0, 1024, buf);
+        match decoder.decode(&packet).unwrap() {
+            AudioBufferRef::F32(buf) => {
+                assert_eq!(buf.spec().rate, 22_050);
+                assert_eq!(buf.frames(), 1024);
+                (0..buf.spec().channels.count()).map(|ch| buf.chan(ch).to_vec()).collect()
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn verify_decode_he_aac_core() {
+        const SBR_PAYLOAD: [u8; 6] = [0x8b, 0x41, 0x5c, 0x00, 0x3e, 0xa7];
+
+        for &explicit_sbr in &[true, false] {
+            let mut he_aac = make_decoder(make_asc(3, explicit_sbr));
+            let mut aac_lc = make_decoder(make_asc(3, false));
+
+            assert_eq!(he_aac.m4ainfo.sbr_present, explicit_sbr);
+
+            for _ in 0..2 {
+                let he_aac_chans = decode(&mut he_aac, &make_raw_data_block(Some(&SBR_PAYLOAD)));
+                let aac_lc_chans = decode(&mut aac_lc, &make_raw_data_block(None));
+
+                // The AAC-LC core is decoded at half the output sample rate, and the SBR fill
+                // elements are skipped without affecting the core.
+                assert_eq!(he_aac_chans.len(), 3);
+
+                for samples in &he_aac_chans {
+                    assert!(samples.iter().all(|s| s.is_finite()));
+                    assert!(samples.iter().any(|&s| s != 0.0));
+                }
+
+                assert_eq!(he_aac_chans, aac_lc_chans);
+            }
+
+            // Implicit signalling is detected from the fill elements.
+            assert!(he_aac.m4ainfo.sbr_present);
+        }
+    }
+
+    /// Builds a raw data block for channel configuration 6 (5.1) or 7 (7.1). The channels are
+    /// coded in the bitstream order with increasing global gains, such that the loudness of an
+    /// output channel identifies its position in the bitstream.
+    fn make_surround_raw_data_block(channel_config: u32) -> Vec<u8> {
+        let mut bw = BitWriterLtr::new();
+        let mut gains = (0..).map(|i| 100 + 4 * i);
+
+        // ID_SCE for the centre channel.
+        bw.write_bits_leq32(0, 3);
+        bw.write_bits_leq32(0, 4);
+        write_ics(&mut bw, gains.next().unwrap(), 8);
+
+        // An ID_CPE for each pair of channels.
+        let num_pairs = if channel_config == 7 { 3 } else { 2 };
+
+        for tag in 0..num_pairs {
+            bw.write_bits_leq32(1, 3);
+            bw.write_bits_leq32(tag, 4);
+            bw.write_bits_leq32(0, 1);
+            write_ics(&mut bw, gains.next().unwrap(), 8);
+            write_ics(&mut bw, gains.next().unwrap(), 8);
+        }
+
+        // ID_LFE
+        bw.write_bits_leq32(3, 3);
+        bw.write_bits_leq32(0, 4);
+        write_ics(&mut bw, gains.next().unwrap(), 8);
+
+        // ID_END
+        bw.write_bits_leq32(7, 3);
+        bw.into_inner()
+    }
+
+    #[test]
+    fn verify_output_channel_map() {
+        // The channels in the order they are coded in the bitstream.
+        let surround_5_1 = [
+            Channels::FRONT_CENTRE,
+            Channels::FRONT_LEFT,
+            Channels::FRONT_RIGHT,
+            Channels::SIDE_LEFT,
+            Channels::SIDE_RIGHT,
+            Channels::LFE1,
+        ];
+
+        let surround_7_1 = [
+            Channels::FRONT_CENTRE,
+            Channels::FRONT_LEFT,
+            Channels::FRONT_RIGHT,
+            Channels::FRONT_LEFT_WIDE,
+            Channels::FRONT_RIGHT_WIDE,
+            Channels::SIDE_LEFT,
+            Channels::SIDE_RIGHT,
+            Channels::LFE1,
+        ];
+
+        for &(channel_config, coded) in &[(6, &surround_5_1[..]), (7, &surround_7_1[..])] {
+            let layout = map_channels(coded.len() as u32).unwrap();
+            let map = output_channel_map(coded.len());
+
+            // The layout contains exactly the coded channels, and each coded channel is written
+            // to the position of its channel in the layout.
+            assert_eq!(layout.count(), coded.len());
+            assert_eq!(map.len(), coded.len());
+
+            for (&channel, &out) in coded.iter().zip(map) {
+                assert!(layout.contains(channel));
+                assert_eq!(layout.iter().position(|c| c == channel), Some(out));
+            }
+
+            // Decode an access unit and check that the output channels are in layout order.
+            let mut decoder = make_decoder(make_asc(channel_config, false));
+            let chans = decode(&mut decoder, &make_surround_raw_data_block(channel_config));
+
+            let rms: Vec<f32> = chans
+                .iter()
+                .map(|samples| {
+                    (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+                })
+                .collect();
+
+            // Each coded channel is louder than the previous, so the loudness of the output
+            // channels, visited in bitstream order, must increase.
+            for pair in map.windows(2) {
+                assert!(rms[pair[0]] > 0.0);
+                assert!(rms[pair[1]] > 1.5 * rms[pair[0]]);
+            }
+        }
+    }
+}
@@ -0,0 +1,249 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The AC-3 parametric bit allocation (ATSC A/52, Section 7.2).
+
+use symphonia_core::errors::{decode_error, Result};
+
+use crate::tables::*;
+
+/// The maximum number of delta bit allocation segments of a channel.
+const MAX_DELTA_SEGS: usize = 8;
+
+/// The bit allocation parameters shared by all channels of an audio block.
+#[derive(Copy, Clone, Default)]
+pub struct BitAllocParams {
+    pub fscod: usize,
+    pub slow_decay: i32,
+    pub fast_decay: i32,
+    pub slow_gain: i32,
+    pub db_per_bit: i32,
+    pub floor: i32,
+}
+
+/// The delta bit allocation of a channel. The masking curve of each segment of bands is adjusted
+/// by the encoder.
+#[derive(Copy, Clone, Default)]
+pub struct DeltaBitAlloc {
+    pub n_segs: usize,
+    pub offsets: [u8; MAX_DELTA_SEGS],
+    pub lens: [u8; MAX_DELTA_SEGS],
+    pub deltas: [u8; MAX_DELTA_SEGS],
+}
+
+/// The bit allocation parameters of a channel.
+#[derive(Copy, Clone, Default)]
+pub struct ChannelBitAlloc {
+    /// The first frequency bin of the channel.
+    pub start: usize,
+    /// The end of the frequency bins of the channel.
+    pub end: usize,
+    pub fast_gain: i32,
+    pub snr_offset: i32,
+    /// The initial fast leak of the coupling channel.
+    pub fast_leak: i32,
+    /// The initial slow leak of the coupling channel.
+    pub slow_leak: i32,
+    /// The delta bit allocation, if used.
+    pub delta: Option<DeltaBitAlloc>,
+}
+
+/// Add two values in the log power domain.
+#[inline(always)]
+fn log_add(a: i32, b: i32) -> i32 {
+    let c = a - b;
+    let addr = ((c.abs() >> 1) as usize).min(255);
+
+    if c >= 0 {
+        a + LOG_ADD_TABLE[addr]
+    }
+    else {
+        b + LOG_ADD_TABLE[addr]
+    }
+}
+
+/// Update the compensation for the low frequency masking of a band.
+fn calc_low_comp(low_comp: i32, psd0: i32, psd1: i32, band: usize) -> i32 {
+    if band < 7 {
+        if psd0 + 256 == psd1 {
+            384
+        }
+        else if psd0 > psd1 {
+            (low_comp - 64).max(0)
+        }
+        else {
+            low_comp
+        }
+    }
+    else if band < 20 {
+        if psd0 + 256 == psd1 {
+            320
+        }
+        else if psd0 > psd1 {
+            (low_comp - 64).max(0)
+        }
+        else {
+            low_comp
+        }
+    }
+    else {
+        (low_comp - 128).max(0)
+    }
+}
+
+/// Calculate the bit allocation pointers of the frequency bins of a channel from its exponents.
+pub fn calc_bap(
+    params: &BitAllocParams,
+    chan: &ChannelBitAlloc,
+    exps: &[u8; 256],
+    bap: &mut [u8; 256],
+) -> Result<()> {
+    let start = chan.start;
+    let end = chan.end;
+
+    // If all the SNR offsets are 0, then no bits are allocated.
+    if chan.snr_offset == -960 {
+        bap.iter_mut().for_each(|b| *b = 0);
+        return Ok(());
+    }
+
+    // Map the exponents into the power spectral density.
+    let mut psd = [0i32; 256];
+
+    for (psd, &exp) in psd[start..end].iter_mut().zip(&exps[start..end]) {
+        *psd = 3072 - (i32::from(exp) << 7);
+    }
+
+    // Integrate the power spectral density of each band.
+    let band_start = MASK_TABLE[start];
+    let band_end = MASK_TABLE[end - 1] + 1;
+
+    let mut band_psd = [0i32; 50];
+
+    let mut bin = start;
+
+    for band in band_start..band_end {
+        let last_bin = BAND_START[band + 1].min(end);
+
+        band_psd[band] = psd[bin];
+
+        for &psd in &psd[bin + 1..last_bin] {
+            band_psd[band] = log_add(band_psd[band], psd);
+        }
+
+        bin = last_bin;
+    }
+
+    // Compute the excitation function.
+    let mut excite = [0i32; 50];
+
+    let mut fast_leak = chan.fast_leak;
+    let mut slow_leak = chan.slow_leak;
+
+    let begin = if band_start == 0 {
+        // The LFE channel only has 7 bands, therefore the last band must not be compared with the
+        // following band.
+        let is_lfe = band_end == 7;
+
+        let mut low_comp = calc_low_comp(0, band_psd[0], band_psd[1], 0);
+        excite[0] = band_psd[0] - chan.fast_gain - low_comp;
+
+        low_comp = calc_low_comp(low_comp, band_psd[1], band_psd[2], 1);
+        excite[1] = band_psd[1] - chan.fast_gain - low_comp;
+
+        let mut begin = 7;
+
+        for band in 2..7 {
+            if !is_lfe || band != 6 {
+                low_comp = calc_low_comp(low_comp, band_psd[band], band_psd[band + 1], band);
+            }
+
+            fast_leak = band_psd[band] - chan.fast_gain;
+            slow_leak = band_psd[band] - params.slow_gain;
+            excite[band] = fast_leak - low_comp;
+
+            if (!is_lfe || band != 6) && band_psd[band] <= band_psd[band + 1] {
+                begin = band + 1;
+                break;
+            }
+        }
+
+        for band in begin..band_end.min(22) {
+            if !is_lfe || band != 6 {
+                low_comp = calc_low_comp(low_comp, band_psd[band], band_psd[band + 1], band);
+            }
+
+            fast_leak = (fast_leak - params.fast_decay).max(band_psd[band] - chan.fast_gain);
+            slow_leak = (slow_leak - params.slow_decay).max(band_psd[band] - params.slow_gain);
+            excite[band] = (fast_leak - low_comp).max(slow_leak);
+        }
+
+        22
+    }
+    else {
+        // The coupling channel starts with the leak values from the bitstream.
+        band_start
+    };
+
+    for band in begin..band_end {
+        fast_leak = (fast_leak - params.fast_decay).max(band_psd[band] - chan.fast_gain);
+        slow_leak = (slow_leak - params.slow_decay).max(band_psd[band] - params.slow_gain);
+        excite[band] = fast_leak.max(slow_leak);
+    }
+
+    // Compute the masking curve.
+    let mut mask = [0i32; 50];
+
+    for band in band_start..band_end {
+        if band_psd[band] < params.db_per_bit {
+            excite[band] += (params.db_per_bit - band_psd[band]) >> 2;
+        }
+
+        mask[band] = excite[band].max(HEARING_THRESHOLD[params.fscod][band]);
+    }
+
+    // Apply the delta bit allocation.
+    if let Some(delta) = &chan.delta {
+        let mut band = 0;
+
+        for seg in 0..delta.n_segs {
+            band += usize::from(delta.offsets[seg]);
+
+            let len = usize::from(delta.lens[seg]);
+
+            if band + len > mask.len() {
+                return decode_error("ac3: invalid delta bit allocation");
+            }
+
+            let ba = i32::from(delta.deltas[seg]);
+            let delta = if ba >= 4 { (ba - 3) << 7 } else { (ba - 4) << 7 };
+
+            for mask in &mut mask[band..band + len] {
+                *mask += delta;
+            }
+
+            band += len;
+        }
+    }
+
+    // Compute the bit allocation pointers.
+    let mut bin = start;
+
+    for band in band_start..band_end {
+        let last_bin = BAND_START[band + 1].min(end);
+
+        let mask = ((mask[band] - chan.snr_offset - params.floor).max(0) & 0x1fe0) + params.floor;
+
+        for (bap, &psd) in bap[bin..last_bin].iter_mut().zip(&psd[bin..last_bin]) {
+            *bap = BAP_TABLE[((psd - mask) >> 5).clamp(0, 63) as usize];
+        }
+
+        bin = last_bin;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,873 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The AC-3 audio block (ATSC A/52, Sections 5.4.3 and 7).
+
+use symphonia_core::errors::{decode_error, Result};
+use symphonia_core::io::{BitReaderLtr, ReadBitsLtr};
+
+use crate::bitalloc::{calc_bap, BitAllocParams, ChannelBitAlloc, DeltaBitAlloc};
+use crate::header::{FrameHeader, ACMOD_DUAL_MONO, ACMOD_STEREO, SAMPLES_PER_BLOCK};
+use crate::tables::*;
+
+/// The maximum number of full bandwidth channels.
+pub const MAX_FBW_CHANNELS: usize = 5;
+
+/// The index of the coupling channel.
+const CPL_CH: usize = 5;
+
+/// The index of the low frequency effects channel.
+pub const LFE_CH: usize = 6;
+
+/// The number of channels, including the coupling and low frequency effects channels.
+pub const N_CHANNELS: usize = 7;
+
+/// The maximum number of coupling sub-bands.
+const MAX_CPL_SUBBANDS: usize = 18;
+
+/// The end of the frequency bins of the low frequency effects channel.
+const LFE_END: usize = 7;
+
+/// The first frequency bin of each rematrixing band, followed by the end of the last band.
+const REMATRIX_BANDS: [usize; 5] = [13, 25, 37, 61, 253];
+
+/// The exponent strategy to reuse the exponents of the previous audio block.
+const EXP_REUSE: u8 = 0;
+
+/// The delta bit allocation mode to reuse the delta bit allocation of the previous audio block.
+const DELTA_REUSE: u8 = 0;
+
+/// The delta bit allocation mode for a new delta bit allocation.
+const DELTA_NEW: u8 = 1;
+
+/// The delta bit allocation mode for no delta bit allocation.
+const DELTA_NONE: u8 = 2;
+
+/// The amplitude of the dither of frequency bins that are allocated no bits.
+const DITHER_LEVEL: f32 = 0.707;
+
+/// Get the gain of a dynamic range control word.
+fn dynamic_range_gain(dynrng: u32) -> f32 {
+    // The 3 most-significant bits are a signed exponent, and the remaining 5 bits are the
+    // fractional part of the mantissa.
+    let exp = ((dynrng as u8) as i8 >> 5) as i32;
+    let mant = 1.0 + (dynrng & 0x1f) as f32 / 32.0;
+
+    mant * 2f32.powi(exp)
+}
+
+/// Get the value of the mantissa `m` of a symmetric quantizer with `n_levels` levels.
+#[inline(always)]
+fn symmetric_level(m: u32, n_levels: u32) -> f32 {
+    (2 * m as i32 - (n_levels as i32 - 1)) as f32 / n_levels as f32
+}
+
+/// Read a set of exponents coded with the given exponent strategy as 7-bit groups of 3
+/// differential exponents.
+fn read_exponent_groups(
+    bs: &mut BitReaderLtr<'_>,
+    strategy: u8,
+    n_groups: usize,
+    abs_exp: u8,
+    exps: &mut [u8],
+) -> Result<()> {
+    // Each exponent is shared by 1, 2, or 4 frequency bins.
+    let group_size = if strategy == 3 { 4 } else { usize::from(strategy) };
+
+    if exps.len() < 3 * group_size * n_groups {
+        return decode_error("ac3: too many exponents");
+    }
+
+    let mut exp = i32::from(abs_exp);
+
+    let mut iter = exps.chunks_exact_mut(group_size);
+
+    for _ in 0..n_groups {
+        let group = bs.read_bits_leq32(7)?;
+
+        if group >= 125 {
+            return decode_error("ac3: invalid exponent group");
+        }
+
+        for &delta in &[group / 25, (group % 25) / 5, group % 5] {
+            exp += delta as i32 - 2;
+
+            if exp < 0 || exp > 24 {
+                return decode_error("ac3: invalid exponent");
+            }
+
+            iter.next().unwrap().iter_mut().for_each(|e| *e = exp as u8);
+        }
+    }
+
+    Ok(())
+}
+
+/// The grouped mantissas of an audio block. The mantissas of bit allocation pointers 1, 2, and 4
+/// are coded in groups which may be shared by multiple channels.
+#[derive(Default)]
+struct GroupedMantissas {
+    bap1: [f32; 3],
+    bap2: [f32; 3],
+    bap4: [f32; 2],
+    /// The number of mantissas remaining in each group.
+    n_bap1: usize,
+    n_bap2: usize,
+    n_bap4: usize,
+}
+
+impl GroupedMantissas {
+    /// Read the mantissa of a frequency bin given its bit allocation pointer.
+    fn read(&mut self, bs: &mut BitReaderLtr<'_>, bap: u8) -> Result<f32> {
+        let mantissa = match bap {
+            1 => {
+                if self.n_bap1 == 0 {
+                    let group = bs.read_bits_leq32(5)?;
+
+                    if group >= 27 {
+                        return decode_error("ac3: invalid mantissa group");
+                    }
+
+                    self.bap1 = [
+                        symmetric_level(group / 9, 3),
+                        symmetric_level((group % 9) / 3, 3),
+                        symmetric_level(group % 3, 3),
+                    ];
+                    self.n_bap1 = 3;
+                }
+
+                self.n_bap1 -= 1;
+                self.bap1[2 - self.n_bap1]
+            }
+            2 => {
+                if self.n_bap2 == 0 {
+                    let group = bs.read_bits_leq32(7)?;
+
+                    if group >= 125 {
+                        return decode_error("ac3: invalid mantissa group");
+                    }
+
+                    self.bap2 = [
+                        symmetric_level(group / 25, 5),
+                        symmetric_level((group % 25) / 5, 5),
+                        symmetric_level(group % 5, 5),
+                    ];
+                    self.n_bap2 = 3;
+                }
+
+                self.n_bap2 -= 1;
+                self.bap2[2 - self.n_bap2]
+            }
+            3 => {
+                let m = bs.read_bits_leq32(3)?;
+
+                if m == 7 {
+                    return decode_error("ac3: invalid mantissa");
+                }
+
+                symmetric_level(m, 7)
+            }
+            4 => {
+                if self.n_bap4 == 0 {
+                    let group = bs.read_bits_leq32(7)?;
+
+                    if group >= 121 {
+                        return decode_error("ac3: invalid mantissa group");
+                    }
+
+                    self.bap4 = [symmetric_level(group / 11, 11), symmetric_level(group % 11, 11)];
+                    self.n_bap4 = 2;
+                }
+
+                self.n_bap4 -= 1;
+                self.bap4[1 - self.n_bap4]
+            }
+            5 => {
+                let m = bs.read_bits_leq32(4)?;
+
+                if m == 15 {
+                    return decode_error("ac3: invalid mantissa");
+                }
+
+                symmetric_level(m, 15)
+            }
+            _ => {
+                // The remaining quantizers are asymmetric and coded in two's complement.
+                let bits = match bap {
+                    14 => 14,
+                    15 => 16,
+                    _ => u32::from(bap) - 1,
+                };
+
+                bs.read_bits_leq32_signed(bits)? as f32 / (1 << (bits - 1)) as f32
+            }
+        };
+
+        Ok(mantissa)
+    }
+}
+
+/// The state of a channel that may be reused by the following audio blocks of a frame.
+#[derive(Copy, Clone)]
+struct Channel {
+    exps: [u8; SAMPLES_PER_BLOCK],
+    bap: [u8; SAMPLES_PER_BLOCK],
+    /// The bit allocation parameters. The frequency range is the range of the exponents.
+    alloc: ChannelBitAlloc,
+    /// Indicates if the exponents were decoded while the channel was coupled.
+    exps_in_cpl: bool,
+}
+
+impl Default for Channel {
+    fn default() -> Self {
+        Channel {
+            exps: [0; SAMPLES_PER_BLOCK],
+            bap: [0; SAMPLES_PER_BLOCK],
+            alloc: Default::default(),
+            exps_in_cpl: false,
+        }
+    }
+}
+
+/// The decoder of the audio blocks of a frame.
+pub struct AudioBlockDecoder {
+    chans: [Channel; N_CHANNELS],
+    /// The frequency coefficients of each full bandwidth channel, the coupling channel, and the
+    /// low frequency effects channel.
+    coeffs: [[f32; SAMPLES_PER_BLOCK]; N_CHANNELS],
+    /// Indicates if each full bandwidth channel uses the short transforms.
+    block_switch: [bool; MAX_FBW_CHANNELS],
+    dither: [bool; MAX_FBW_CHANNELS],
+    /// The dynamic range gain of all channels, or of each channel if dual mono.
+    dynrng: [f32; 2],
+    params: BitAllocParams,
+    cpl_in_use: bool,
+    in_cpl: [bool; MAX_FBW_CHANNELS],
+    phase_flags_in_use: bool,
+    /// The coupling begin frequency code.
+    cpl_begf: u32,
+    /// The frequency range of the coupling channel.
+    cpl_start: usize,
+    cpl_end: usize,
+    /// The end of the frequency bins of each coupling band.
+    cpl_band_ends: [usize; MAX_CPL_SUBBANDS],
+    n_cpl_bands: usize,
+    cpl_coords: [[f32; MAX_CPL_SUBBANDS]; MAX_FBW_CHANNELS],
+    phase_flags: [bool; MAX_CPL_SUBBANDS],
+    remat_flags: [bool; 4],
+    n_remat_bands: usize,
+    /// The state of the dither generator.
+    rng: u32,
+}
+
+impl AudioBlockDecoder {
+    pub fn new() -> Self {
+        AudioBlockDecoder {
+            chans: Default::default(),
+            coeffs: [[0.0; SAMPLES_PER_BLOCK]; N_CHANNELS],
+            block_switch: [false; MAX_FBW_CHANNELS],
+            dither: [false; MAX_FBW_CHANNELS],
+            dynrng: [1.0; 2],
+            params: Default::default(),
+            cpl_in_use: false,
+            in_cpl: [false; MAX_FBW_CHANNELS],
+            phase_flags_in_use: false,
+            cpl_begf: 0,
+            cpl_start: 0,
+            cpl_end: 0,
+            cpl_band_ends: [0; MAX_CPL_SUBBANDS],
+            n_cpl_bands: 0,
+            cpl_coords: [[0.0; MAX_CPL_SUBBANDS]; MAX_FBW_CHANNELS],
+            phase_flags: [false; MAX_CPL_SUBBANDS],
+            remat_flags: [false; 4],
+            n_remat_bands: 0,
+            rng: 1,
+        }
+    }
+
+    /// Get the frequency coefficients of a full bandwidth channel, or the low frequency effects
+    /// channel, of the last audio block decoded.
+    pub fn coeffs(&self, ch: usize) -> &[f32; SAMPLES_PER_BLOCK] {
+        &self.coeffs[ch]
+    }
+
+    /// Indicates if a channel of the last audio block decoded uses the short transforms.
+    pub fn is_short(&self, ch: usize) -> bool {
+        ch < MAX_FBW_CHANNELS && self.block_switch[ch]
+    }
+
+    /// Get the next dither value.
+    fn dither(&mut self) -> f32 {
+        self.rng = self.rng.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        DITHER_LEVEL * (self.rng as i32) as f32 / 2_147_483_648.0
+    }
+
+    /// Read and decode an audio block. Most of the state of the first audio block of a frame must
+    /// be present, while the following audio blocks may reuse the state of the previous block.
+    pub fn read(
+        &mut self,
+        bs: &mut BitReaderLtr<'_>,
+        header: &FrameHeader,
+        blk: usize,
+    ) -> Result<()> {
+        let n_fbw = header.n_fbw_channels();
+        let is_first = blk == 0;
+
+        for ch in 0..n_fbw {
+            self.block_switch[ch] = bs.read_bool()?;
+        }
+
+        for ch in 0..n_fbw {
+            self.dither[ch] = bs.read_bool()?;
+        }
+
+        let n_dynrng = if header.acmod == ACMOD_DUAL_MONO { 2 } else { 1 };
+
+        for dynrng in &mut self.dynrng[..n_dynrng] {
+            if bs.read_bool()? {
+                *dynrng = dynamic_range_gain(bs.read_bits_leq32(8)?);
+            }
+            else if is_first {
+                *dynrng = 1.0;
+            }
+        }
+
+        // Coupling strategy.
+        if bs.read_bool()? {
+            self.read_cpl_strategy(bs, header)?;
+        }
+        else if is_first {
+            return decode_error("ac3: coupling strategy must be present in the first block");
+        }
+
+        // Coupling coordinates.
+        if self.cpl_in_use {
+            self.read_cpl_coords(bs, header, is_first)?;
+        }
+
+        // Rematrixing.
+        if header.acmod == ACMOD_STEREO {
+            if bs.read_bool()? {
+                self.n_remat_bands = if !self.cpl_in_use || self.cpl_begf > 2 {
+                    4
+                }
+                else if self.cpl_begf > 0 {
+                    3
+                }
+                else {
+                    2
+                };
+
+                for flag in &mut self.remat_flags[..self.n_remat_bands] {
+                    *flag = bs.read_bool()?;
+                }
+            }
+            else if is_first {
+                return decode_error("ac3: rematrixing strategy must be present in the first block");
+            }
+        }
+
+        self.read_exponents(bs, header, is_first)?;
+
+        self.read_bit_alloc(bs, header, is_first)?;
+
+        // Skip the unused data.
+        if bs.read_bool()? {
+            let len = bs.read_bits_leq32(9)?;
+            bs.ignore_bits(8 * len)?;
+        }
+
+        self.read_mantissas(bs, header)?;
+
+        if self.cpl_in_use {
+            self.decouple(n_fbw);
+        }
+
+        if header.acmod == ACMOD_STEREO {
+            self.rematrix();
+        }
+
+        // Apply the dynamic range gain.
+        let lfe = if header.lfe { Some(LFE_CH) } else { None };
+
+        for ch in (0..n_fbw).chain(lfe) {
+            let gain = if header.acmod == ACMOD_DUAL_MONO && ch == 1 {
+                self.dynrng[1]
+            }
+            else {
+                self.dynrng[0]
+            };
+
+            if gain != 1.0 {
+                self.coeffs[ch].iter_mut().for_each(|c| *c *= gain);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read_cpl_strategy(&mut self, bs: &mut BitReaderLtr<'_>, header: &FrameHeader) -> Result<()> {
+        let n_fbw = header.n_fbw_channels();
+
+        self.cpl_in_use = bs.read_bool()?;
+
+        if !self.cpl_in_use {
+            self.in_cpl = [false; MAX_FBW_CHANNELS];
+            self.phase_flags_in_use = false;
+            return Ok(());
+        }
+
+        // Coupling requires at least two channels that are not independent.
+        if n_fbw < 2 || header.acmod == ACMOD_DUAL_MONO {
+            return decode_error("ac3: coupling is not allowed for mono or dual mono");
+        }
+
+        for in_cpl in &mut self.in_cpl[..n_fbw] {
+            *in_cpl = bs.read_bool()?;
+        }
+
+        self.phase_flags_in_use = header.acmod == ACMOD_STEREO && bs.read_bool()?;
+
+        if !self.phase_flags_in_use {
+            self.phase_flags = [false; MAX_CPL_SUBBANDS];
+        }
+
+        let begf = bs.read_bits_leq32(4)?;
+        let endf = bs.read_bits_leq32(4)? + 3;
+
+        if begf >= endf {
+            return decode_error("ac3: invalid coupling frequency range");
+        }
+
+        self.cpl_begf = begf;
+
+        // The coupling channel is divided into sub-bands of 12 frequency bins.
+        self.cpl_start = 12 * begf as usize + 37;
+        self.cpl_end = 12 * endf as usize + 37;
+
+        let n_subbands = (endf - begf) as usize;
+
+        // Adjacent sub-bands may be combined into a single coupling band.
+        self.n_cpl_bands = 0;
+
+        for sbnd in 0..n_subbands {
+            if sbnd == 0 || !bs.read_bool()? {
+                self.n_cpl_bands += 1;
+            }
+
+            self.cpl_band_ends[self.n_cpl_bands - 1] = self.cpl_start + 12 * (sbnd + 1);
+        }
+
+        Ok(())
+    }
+
+    fn read_cpl_coords(
+        &mut self,
+        bs: &mut BitReaderLtr<'_>,
+        header: &FrameHeader,
+        is_first: bool,
+    ) -> Result<()> {
+        let n_fbw = header.n_fbw_channels();
+
+        let mut is_coords_present = false;
+
+        for ch in 0..n_fbw {
+            if !self.in_cpl[ch] {
+                continue;
+            }
+
+            if bs.read_bool()? {
+                is_coords_present = true;
+
+                let master = 3 * bs.read_bits_leq32(2)?;
+
+                for coord in &mut self.cpl_coords[ch][..self.n_cpl_bands] {
+                    let exp = bs.read_bits_leq32(4)?;
+                    let mant = bs.read_bits_leq32(4)? as f32;
+
+                    // The mantissa is normalized unless the exponent is the largest exponent.
+                    let mant = if exp == 15 { mant / 16.0 } else { (mant + 16.0) / 32.0 };
+
+                    *coord = 8.0 * mant / (1 << (exp + master)) as f32;
+                }
+            }
+            else if is_first {
+                return decode_error("ac3: coupling coordinates must be present in the first block");
+            }
+        }
+
+        if self.phase_flags_in_use && is_coords_present {
+            for flag in &mut self.phase_flags[..self.n_cpl_bands] {
+                *flag = bs.read_bool()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read_exponents(
+        &mut self,
+        bs: &mut BitReaderLtr<'_>,
+        header: &FrameHeader,
+        is_first: bool,
+    ) -> Result<()> {
+        let n_fbw = header.n_fbw_channels();
+
+        // Exponent strategies.
+        let cpl_strategy = if self.cpl_in_use { bs.read_bits_leq32(2)? as u8 } else { EXP_REUSE };
+
+        let mut strategies = [EXP_REUSE; MAX_FBW_CHANNELS];
+
+        for strategy in &mut strategies[..n_fbw] {
+            *strategy = bs.read_bits_leq32(2)? as u8;
+        }
+
+        let lfe_strategy = if header.lfe { bs.read_bits_leq32(1)? as u8 } else { EXP_REUSE };
+
+        // The frequency range of each full bandwidth channel. The bandwidth of channels that are
+        // not coupled is only coded with new exponents.
+        let mut ends = [0; MAX_FBW_CHANNELS];
+
+        for ch in 0..n_fbw {
+            let chan = &self.chans[ch];
+
+            ends[ch] = if self.in_cpl[ch] {
+                self.cpl_start
+            }
+            else if strategies[ch] != EXP_REUSE {
+                let bwcod = bs.read_bits_leq32(6)? as usize;
+
+                if bwcod > 60 {
+                    return decode_error("ac3: invalid channel bandwidth code");
+                }
+
+                3 * (bwcod + 12) + 37
+            }
+            else {
+                chan.alloc.end
+            };
+
+            if strategies[ch] == EXP_REUSE
+                && (is_first || chan.exps_in_cpl != self.in_cpl[ch] || chan.alloc.end != ends[ch])
+            {
+                return decode_error("ac3: exponents must be present");
+            }
+        }
+
+        // Coupling channel exponents.
+        if self.cpl_in_use {
+            let chan = &mut self.chans[CPL_CH];
+
+            if cpl_strategy != EXP_REUSE {
+                let abs_exp = (bs.read_bits_leq32(4)? as u8) << 1;
+
+                let group_size = if cpl_strategy == 3 { 4 } else { usize::from(cpl_strategy) };
+                let n_groups = (self.cpl_end - self.cpl_start) / (3 * group_size);
+
+                let exps = &mut chan.exps[self.cpl_start..self.cpl_end];
+                read_exponent_groups(bs, cpl_strategy, n_groups, abs_exp, exps)?;
+
+                chan.alloc.start = self.cpl_start;
+                chan.alloc.end = self.cpl_end;
+            }
+            else if is_first || chan.alloc.start != self.cpl_start || chan.alloc.end != self.cpl_end
+            {
+                return decode_error("ac3: coupling exponents must be present");
+            }
+        }
+
+        // Full bandwidth channel exponents.
+        for ch in 0..n_fbw {
+            let strategy = strategies[ch];
+
+            if strategy == EXP_REUSE {
+                continue;
+            }
+
+            let chan = &mut self.chans[ch];
+
+            chan.exps[0] = bs.read_bits_leq32(4)? as u8;
+
+            let group_size = if strategy == 3 { 4 } else { usize::from(strategy) };
+            let n_groups = (ends[ch] - 1 + 3 * group_size - 3) / (3 * group_size);
+
+            let abs_exp = chan.exps[0];
+            read_exponent_groups(bs, strategy, n_groups, abs_exp, &mut chan.exps[1..])?;
+
+            // The gain range is only useful to decoders with limited dynamic range.
+            bs.ignore_bits(2)?;
+
+            chan.alloc.start = 0;
+            chan.alloc.end = ends[ch];
+            chan.exps_in_cpl = self.in_cpl[ch];
+        }
+
+        // Low frequency effects channel exponents.
+        if header.lfe {
+            let chan = &mut self.chans[LFE_CH];
+
+            if lfe_strategy != EXP_REUSE {
+                chan.exps[0] = bs.read_bits_leq32(4)? as u8;
+
+                let abs_exp = chan.exps[0];
+                read_exponent_groups(bs, 1, 2, abs_exp, &mut chan.exps[1..])?;
+
+                chan.alloc.start = 0;
+                chan.alloc.end = LFE_END;
+            }
+            else if is_first {
+                return decode_error("ac3: exponents must be present");
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read_bit_alloc(
+        &mut self,
+        bs: &mut BitReaderLtr<'_>,
+        header: &FrameHeader,
+        is_first: bool,
+    ) -> Result<()> {
+        let n_fbw = header.n_fbw_channels();
+
+        // Bit allocation parametric information.
+        if bs.read_bool()? {
+            self.params = BitAllocParams {
+                fscod: usize::from(header.fscod),
+                slow_decay: SLOW_DECAY[bs.read_bits_leq32(2)? as usize],
+                fast_decay: FAST_DECAY[bs.read_bits_leq32(2)? as usize],
+                slow_gain: SLOW_GAIN[bs.read_bits_leq32(2)? as usize],
+                db_per_bit: DB_PER_BIT[bs.read_bits_leq32(2)? as usize],
+                floor: FLOOR[bs.read_bits_leq32(3)? as usize],
+            };
+        }
+        else if is_first {
+            return decode_error("ac3: bit allocation info must be present in the first block");
+        }
+
+        // The channels that are allocated bits, in bitstream order.
+        let mut order = [0; N_CHANNELS];
+        let mut n_chans = 0;
+
+        if self.cpl_in_use {
+            order[0] = CPL_CH;
+            n_chans += 1;
+        }
+
+        for ch in 0..n_fbw {
+            order[n_chans] = ch;
+            n_chans += 1;
+        }
+
+        if header.lfe {
+            order[n_chans] = LFE_CH;
+            n_chans += 1;
+        }
+
+        let order = &order[..n_chans];
+
+        // SNR offsets.
+        if bs.read_bool()? {
+            let coarse = bs.read_bits_leq32(6)? as i32;
+
+            for &ch in order {
+                let fine = bs.read_bits_leq32(4)? as i32;
+
+                let alloc = &mut self.chans[ch].alloc;
+
+                alloc.snr_offset = (((coarse - 15) << 4) + fine) << 2;
+                alloc.fast_gain = FAST_GAIN[bs.read_bits_leq32(3)? as usize];
+            }
+        }
+        else if is_first {
+            return decode_error("ac3: snr offsets must be present in the first block");
+        }
+
+        // Coupling leak initialization.
+        if self.cpl_in_use {
+            if bs.read_bool()? {
+                let alloc = &mut self.chans[CPL_CH].alloc;
+
+                alloc.fast_leak = ((bs.read_bits_leq32(3)? as i32) << 8) + 768;
+                alloc.slow_leak = ((bs.read_bits_leq32(3)? as i32) << 8) + 768;
+            }
+            else if is_first {
+                return decode_error("ac3: coupling leak must be present in the first block");
+            }
+        }
+
+        // Delta bit allocation. The low frequency effects channel has no delta bit allocation.
+        let delta_chans = &order[..n_chans - usize::from(header.lfe)];
+
+        if bs.read_bool()? {
+            let mut modes = [DELTA_NONE; N_CHANNELS];
+
+            for &ch in delta_chans {
+                modes[ch] = bs.read_bits_leq32(2)? as u8;
+            }
+
+            for &ch in delta_chans {
+                let alloc = &mut self.chans[ch].alloc;
+
+                match modes[ch] {
+                    // There is no delta bit allocation to reuse in the first block.
+                    DELTA_REUSE if is_first => alloc.delta = None,
+                    DELTA_REUSE => (),
+                    DELTA_NEW => {
+                        let mut delta = DeltaBitAlloc {
+                            n_segs: bs.read_bits_leq32(3)? as usize + 1,
+                            ..Default::default()
+                        };
+
+                        for seg in 0..delta.n_segs {
+                            delta.offsets[seg] = bs.read_bits_leq32(5)? as u8;
+                            delta.lens[seg] = bs.read_bits_leq32(4)? as u8;
+                            delta.deltas[seg] = bs.read_bits_leq32(3)? as u8;
+                        }
+
+                        alloc.delta = Some(delta);
+                    }
+                    DELTA_NONE => alloc.delta = None,
+                    _ => return decode_error("ac3: reserved delta bit allocation mode"),
+                }
+            }
+        }
+        else if is_first {
+            for &ch in delta_chans {
+                self.chans[ch].alloc.delta = None;
+            }
+        }
+
+        // Compute the bit allocation of every channel.
+        for &ch in order {
+            let chan = &mut self.chans[ch];
+            calc_bap(&self.params, &chan.alloc, &chan.exps, &mut chan.bap)?;
+        }
+
+        Ok(())
+    }
+
+    fn read_mantissas(&mut self, bs: &mut BitReaderLtr<'_>, header: &FrameHeader) -> Result<()> {
+        let n_fbw = header.n_fbw_channels();
+
+        let mut grouped: GroupedMantissas = Default::default();
+
+        let mut is_cpl_read = false;
+
+        for ch in 0..n_fbw {
+            self.read_channel_mantissas(bs, &mut grouped, ch, self.dither[ch])?;
+
+            // The mantissas of the coupling channel follow the first coupled channel.
+            if self.in_cpl[ch] && !is_cpl_read {
+                self.read_channel_mantissas(bs, &mut grouped, CPL_CH, false)?;
+                is_cpl_read = true;
+            }
+        }
+
+        if header.lfe {
+            self.read_channel_mantissas(bs, &mut grouped, LFE_CH, false)?;
+        }
+
+        Ok(())
+    }
+
+    /// Read the mantissas of a channel, and compute its frequency coefficients. Frequency bins
+    /// that are allocated no bits are dithered if `dither` is set.
+    fn read_channel_mantissas(
+        &mut self,
+        bs: &mut BitReaderLtr<'_>,
+        grouped: &mut GroupedMantissas,
+        ch: usize,
+        dither: bool,
+    ) -> Result<()> {
+        let start = self.chans[ch].alloc.start;
+        let end = self.chans[ch].alloc.end;
+
+        self.coeffs[ch][..start].iter_mut().for_each(|c| *c = 0.0);
+        self.coeffs[ch][end..].iter_mut().for_each(|c| *c = 0.0);
+
+        for bin in start..end {
+            let bap = self.chans[ch].bap[bin];
+
+            let mantissa = match bap {
+                0 if dither => self.dither(),
+                0 => 0.0,
+                _ => grouped.read(bs, bap)?,
+            };
+
+            let exp = self.chans[ch].exps[bin];
+
+            self.coeffs[ch][bin] = mantissa / (1 << exp) as f32;
+        }
+
+        Ok(())
+    }
+
+    /// Compute the frequency coefficients of the coupled channels from the coupling channel.
+    fn decouple(&mut self, n_fbw: usize) {
+        for ch in 0..n_fbw {
+            if !self.in_cpl[ch] {
+                continue;
+            }
+
+            let mut band = 0;
+
+            for bin in self.cpl_start..self.cpl_end {
+                if bin >= self.cpl_band_ends[band] {
+                    band += 1;
+                }
+
+                let mut coord = self.cpl_coords[ch][band];
+
+                // The phase of the right channel may be inverted.
+                if ch == 1 && self.phase_flags[band] {
+                    coord = -coord;
+                }
+
+                // The coupling channel is dithered separately for each coupled channel.
+                let cpl = if self.chans[CPL_CH].bap[bin] != 0 {
+                    self.coeffs[CPL_CH][bin]
+                }
+                else if self.dither[ch] {
+                    self.dither() / (1 << self.chans[CPL_CH].exps[bin]) as f32
+                }
+                else {
+                    0.0
+                };
+
+                self.coeffs[ch][bin] = coord * cpl;
+            }
+
+            self.coeffs[ch][self.cpl_end..].iter_mut().for_each(|c| *c = 0.0);
+        }
+    }
+
+    /// Undo the sum and difference coding of the left and right channels.
+    fn rematrix(&mut self) {
+        let end = self.chans[0].alloc.end.min(self.chans[1].alloc.end);
+
+        let (left, right) = self.coeffs.split_at_mut(1);
+
+        for band in 0..self.n_remat_bands {
+            if !self.remat_flags[band] {
+                continue;
+            }
+
+            let start = REMATRIX_BANDS[band];
+            let band_end = REMATRIX_BANDS[band + 1].min(end);
+
+            for bin in start..band_end {
+                let l = left[0][bin];
+                let r = right[0][bin];
+
+                left[0][bin] = l + r;
+                right[0][bin] = l - r;
+            }
+        }
+    }
+}
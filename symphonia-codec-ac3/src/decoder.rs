@@ -0,0 +1,833 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::audio::{AsAudioBufferRef, AudioBuffer, AudioBufferRef, Signal};
+use symphonia_core::checksum::Crc16Ansi;
+use symphonia_core::codecs::{CodecDescriptor, CodecParameters, CODEC_TYPE_EAC3};
+use symphonia_core::codecs::{Decoder, DecoderOptions, FinalizeResult};
+use symphonia_core::errors::{decode_error, unsupported_error, Result};
+use symphonia_core::formats::Packet;
+use symphonia_core::io::{BitReaderLtr, Monitor};
+use symphonia_core::support_codec;
+
+use crate::block::{AudioBlockDecoder, LFE_CH, N_CHANNELS};
+use crate::header::*;
+use crate::imdct::Transform;
+
+/// Dolby Digital (AC-3) decoder.
+///
+/// The channels of a frame are decoded as coded. Downmixing, if required, is left to the
+/// application.
+pub struct Ac3Decoder {
+    params: CodecParameters,
+    block: Box<AudioBlockDecoder>,
+    transform: Transform,
+    /// The delayed second half of the windowed samples of the previous audio block of each
+    /// channel.
+    delay: [[f32; SAMPLES_PER_BLOCK]; N_CHANNELS],
+    buf: AudioBuffer<f32>,
+}
+
+impl Ac3Decoder {
+    fn decode_inner(&mut self, packet: &Packet) -> Result<()> {
+        let data = packet.buf();
+
+        let mut bs = BitReaderLtr::new(data);
+
+        let header = read_frame_header(&mut bs)?;
+
+        if header.frame_size > data.len() {
+            return decode_error("ac3: packet is shorter than the frame");
+        }
+
+        // The CRC of the frame, excluding the sync word, is 0 if the frame is intact.
+        let mut crc = Crc16Ansi::new(0);
+        crc.process_buf_bytes(&data[2..header.frame_size]);
+
+        if crc.crc() != 0 {
+            return decode_error("ac3: crc mismatch");
+        }
+
+        // The audio buffer can only be created after the first frame is decoded.
+        if self.buf.is_unused() {
+            self.buf = AudioBuffer::new(SAMPLES_PER_FRAME as u64, header.spec());
+        }
+        else if self.buf.spec() != &header.spec() {
+            return decode_error("ac3: invalid audio buffer signal spec for packet");
+        }
+
+        self.buf.clear();
+        self.buf.render_reserved(Some(SAMPLES_PER_FRAME));
+
+        // The audio buffer planes are in channel order, while the channels are coded in bitstream
+        // order. Map each coded channel to its plane.
+        let channels = header.channels().bits();
+
+        let mut planes = [(0, 0); N_CHANNELS];
+        let mut n_planes = 0;
+
+        let lfe = if header.lfe { Some(LFE_CH) } else { None };
+
+        for (ch, pos) in (0..header.n_fbw_channels()).chain(lfe).zip(header.positions()) {
+            planes[n_planes] = (ch, (channels & (pos.bits() - 1)).count_ones() as usize);
+            n_planes += 1;
+        }
+
+        for blk in 0..BLOCKS_PER_FRAME {
+            self.block.read(&mut bs, &header, blk)?;
+
+            let range = blk * SAMPLES_PER_BLOCK..(blk + 1) * SAMPLES_PER_BLOCK;
+
+            for &(ch, plane) in &planes[..n_planes] {
+                self.transform.transform(
+                    self.block.coeffs(ch),
+                    self.block.is_short(ch),
+                    &mut self.delay[ch],
+                    &mut self.buf.chan_mut(plane)[range.clone()],
+                );
+            }
+        }
+
+        self.buf.trim(packet.trim_start() as usize, packet.trim_end() as usize);
+
+        Ok(())
+    }
+}
+
+impl Decoder for Ac3Decoder {
+    fn try_new(params: &CodecParameters, _options: &DecoderOptions) -> Result<Self> {
+        if params.codec != CODEC_TYPE_EAC3 {
+            return unsupported_error("ac3: invalid codec type");
+        }
+
+        Ok(Ac3Decoder {
+            params: params.clone(),
+            block: Box::new(AudioBlockDecoder::new()),
+            transform: Transform::new(),
+            delay: [[0.0; SAMPLES_PER_BLOCK]; N_CHANNELS],
+            buf: AudioBuffer::unused(),
+        })
+    }
+
+    fn supported_codecs() -> &'static [CodecDescriptor] {
+        &[support_codec!(CODEC_TYPE_EAC3, "ac3", "Dolby Digital (AC-3)")]
+    }
+
+    fn reset(&mut self) {
+        // Discard the overlapping samples of the previous frame.
+        self.delay = [[0.0; SAMPLES_PER_BLOCK]; N_CHANNELS];
+    }
+
+    fn codec_params(&self) -> &CodecParameters {
+        &self.params
+    }
+
+    fn decode(&mut self, packet: &Packet) -> Result<AudioBufferRef<'_>> {
+        if let Err(e) = self.decode_inner(packet) {
+            self.buf.clear();
+            Err(e)
+        }
+        else {
+            Ok(self.buf.as_audio_buffer_ref())
+        }
+    }
+
+    fn finalize(&mut self) -> FinalizeResult {
+        Default::default()
+    }
+
+    fn last_decoded(&self) -> AudioBufferRef<'_> {
+        self.buf.as_audio_buffer_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+    use std::ops::Range;
+
+    use symphonia_core::audio::{AudioBufferRef, Channels, Signal};
+    use symphonia_core::checksum::Crc16Ansi;
+    use symphonia_core::codecs::{CodecParameters, Decoder, CODEC_TYPE_EAC3};
+    use symphonia_core::formats::Packet;
+    use symphonia_core::io::{BitReaderLtr, BitWriterLtr, Monitor};
+
+    use super::Ac3Decoder;
+    use crate::bitalloc::{calc_bap, BitAllocParams, ChannelBitAlloc, DeltaBitAlloc};
+    use crate::block::AudioBlockDecoder;
+    use crate::header::{read_frame_header, BLOCKS_PER_FRAME, SAMPLES_PER_BLOCK};
+    use crate::tables::{DB_PER_BIT, FAST_DECAY, FAST_GAIN, FLOOR, SLOW_DECAY, SLOW_GAIN};
+
+    /// The size of the test frame in bytes: 640 kbps at 48 kHz.
+    const FRAME_SIZE: usize = 2560;
+
+    /// The index of the coupling channel in the model.
+    const CPL: usize = 2;
+
+    /// The first frequency bin of each rematrixing band, followed by the end of the last band.
+    const REMATRIX_BANDS: [usize; 5] = [13, 25, 37, 61, 253];
+
+    /// Get the number of bits of an asymmetrically quantized mantissa.
+    fn asymmetric_bits(bap: u8) -> u32 {
+        match bap {
+            14 => 14,
+            15 => 16,
+            _ => u32::from(bap) - 1,
+        }
+    }
+
+    /// Pick a quantized mantissa for a frequency bin allocated `bap`. Returns the coded mantissa,
+    /// and its value.
+    fn pick_mantissa(bap: u8, seed: usize) -> (u32, f32) {
+        match bap {
+            1..=5 => {
+                let n_levels = [3, 5, 7, 11, 15][usize::from(bap) - 1];
+                let m = (seed % n_levels) as u32;
+
+                (m, (2 * m as i32 - (n_levels as i32 - 1)) as f32 / n_levels as f32)
+            }
+            _ => {
+                let bits = asymmetric_bits(bap);
+                let m = (seed as u32).wrapping_mul(2_654_435_761) >> (32 - bits);
+                let value = ((m << (32 - bits)) as i32 >> (32 - bits)) as f32;
+
+                (m, value / (1 << (bits - 1)) as f32)
+            }
+        }
+    }
+
+    /// Write mantissas, in bitstream order, given their bit allocation pointers. The mantissas of
+    /// bit allocation pointers 1, 2, and 4 are grouped, and a group is written in place of the
+    /// first mantissa of the group.
+    fn write_mantissas(w: &mut BitWriterLtr, mantissas: &[(u8, u32)]) {
+        let mut remaining = [0; 5];
+
+        for (i, &(bap, m)) in mantissas.iter().enumerate() {
+            match bap {
+                1 | 2 | 4 => {
+                    let (group_len, n_levels, bits) = match bap {
+                        1 => (3, 3, 5),
+                        2 => (3, 5, 7),
+                        _ => (2, 11, 7),
+                    };
+
+                    if remaining[usize::from(bap)] == 0 {
+                        let mut group = mantissas[i..]
+                            .iter()
+                            .filter(|&&(b, _)| b == bap)
+                            .map(|&(_, m)| m)
+                            .chain(std::iter::repeat(0));
+
+                        let code = (0..group_len)
+                            .fold(0, |code, _| code * n_levels + group.next().unwrap());
+
+                        w.write_bits_leq32(code, bits);
+                        remaining[usize::from(bap)] = group_len;
+                    }
+
+                    remaining[usize::from(bap)] -= 1;
+                }
+                3 => w.write_bits_leq32(m, 3),
+                5 => w.write_bits_leq32(m, 4),
+                _ => w.write_bits_leq32(m, asymmetric_bits(bap)),
+            }
+        }
+    }
+
+    /// The state of the left, right, and coupling channels that the test encoder tracks to
+    /// compute the expected frequency coefficients of each audio block.
+    struct Model {
+        params: BitAllocParams,
+        exps: [[u8; SAMPLES_PER_BLOCK]; 3],
+        allocs: [ChannelBitAlloc; 3],
+        in_cpl: [bool; 2],
+        cpl_band_ends: Vec<usize>,
+        cpl_coords: [Vec<f32>; 2],
+        phase_flags: Vec<bool>,
+        remat_flags: Vec<bool>,
+        gain: f32,
+        /// The bit allocation pointers that were used.
+        used_baps: [bool; 16],
+    }
+
+    impl Model {
+        fn new() -> Self {
+            Model {
+                params: Default::default(),
+                exps: [[0; SAMPLES_PER_BLOCK]; 3],
+                allocs: Default::default(),
+                in_cpl: [false; 2],
+                cpl_band_ends: Vec::new(),
+                cpl_coords: Default::default(),
+                phase_flags: Vec::new(),
+                remat_flags: Vec::new(),
+                gain: 1.0,
+                used_baps: [false; 16],
+            }
+        }
+
+        /// Write the differentially coded exponent groups of the frequency bins `bins` of a
+        /// channel, starting from the absolute exponent `exp`.
+        fn write_exps(
+            &mut self,
+            w: &mut BitWriterLtr,
+            ch: usize,
+            bins: Range<usize>,
+            strategy: u8,
+            mut exp: i32,
+            groups: &[u32],
+        ) {
+            let group_size = if strategy == 3 { 4 } else { usize::from(strategy) };
+
+            assert_eq!(3 * group_size * groups.len(), bins.len());
+
+            let mut iter = self.exps[ch][bins].chunks_exact_mut(group_size);
+
+            for &group in groups {
+                w.write_bits_leq32(group, 7);
+
+                for &delta in &[group / 25, (group % 25) / 5, group % 5] {
+                    exp += delta as i32 - 2;
+                    iter.next().unwrap().iter_mut().for_each(|e| *e = exp as u8);
+                }
+            }
+        }
+
+        /// Write the SNR offsets of the channels, and set their fast gain.
+        fn write_snr_offsets(&mut self, w: &mut BitWriterLtr, coarse: i32, fine: &[(usize, i32)]) {
+            w.write_bool(true);
+            w.write_bits_leq32(coarse as u32, 6);
+
+            for &(ch, fine) in fine {
+                w.write_bits_leq32(fine as u32, 4);
+                w.write_bits_leq32(4, 3);
+
+                self.allocs[ch].snr_offset = (((coarse - 15) << 4) + fine) << 2;
+                self.allocs[ch].fast_gain = FAST_GAIN[4];
+            }
+        }
+
+        /// Write the mantissas of an audio block, and compute the expected frequency coefficients
+        /// of the left and right channels.
+        fn write_mantissas(
+            &mut self,
+            w: &mut BitWriterLtr,
+            blk: usize,
+        ) -> [[f32; SAMPLES_PER_BLOCK]; 2] {
+            // The coupling channel follows the first coupled channel.
+            let mut coded = vec![0];
+
+            if self.in_cpl[0] {
+                coded.push(CPL);
+            }
+
+            coded.push(1);
+
+            if self.in_cpl[1] && !self.in_cpl[0] {
+                coded.push(CPL);
+            }
+
+            let mut raw = [[0.0; SAMPLES_PER_BLOCK]; 3];
+            let mut mantissas = Vec::new();
+
+            for &ch in &coded {
+                let mut bap = [0; SAMPLES_PER_BLOCK];
+                calc_bap(&self.params, &self.allocs[ch], &self.exps[ch], &mut bap).unwrap();
+
+                for bin in self.allocs[ch].start..self.allocs[ch].end {
+                    if bap[bin] == 0 {
+                        continue;
+                    }
+
+                    let (m, value) = pick_mantissa(bap[bin], 1000 * blk + 256 * ch + bin);
+
+                    mantissas.push((bap[bin], m));
+                    raw[ch][bin] = value / (1 << self.exps[ch][bin]) as f32;
+
+                    self.used_baps[usize::from(bap[bin])] = true;
+                }
+            }
+
+            write_mantissas(w, &mantissas);
+
+            let mut coeffs = [raw[0], raw[1]];
+
+            // Decouple.
+            let cpl_start = self.allocs[CPL].start;
+
+            for (ch, coeffs) in coeffs.iter_mut().enumerate() {
+                if !self.in_cpl[ch] {
+                    continue;
+                }
+
+                for (band, &end) in self.cpl_band_ends.iter().enumerate() {
+                    let start = if band > 0 { self.cpl_band_ends[band - 1] } else { cpl_start };
+
+                    let mut coord = self.cpl_coords[ch][band];
+
+                    if ch == 1 && self.phase_flags.get(band) == Some(&true) {
+                        coord = -coord;
+                    }
+
+                    for (c, &cpl) in coeffs[start..end].iter_mut().zip(&raw[CPL][start..end]) {
+                        *c = coord * cpl;
+                    }
+                }
+            }
+
+            // Rematrix.
+            let end = self.allocs[0].end.min(self.allocs[1].end);
+
+            let [left, right] = &mut coeffs;
+
+            for (band, _) in self.remat_flags.iter().enumerate().filter(|(_, &flag)| flag) {
+                let bins = REMATRIX_BANDS[band]..REMATRIX_BANDS[band + 1].min(end);
+
+                for (l, r) in left[bins.clone()].iter_mut().zip(&mut right[bins]) {
+                    let (sum, diff) = (*l + *r, *l - *r);
+
+                    *l = sum;
+                    *r = diff;
+                }
+            }
+
+            for c in coeffs.iter_mut().flat_map(|c| c.iter_mut()) {
+                *c *= self.gain;
+            }
+
+            coeffs
+        }
+    }
+
+    /// Make a stereo frame at 48 kHz that exercises all the exponent strategies, coupling, phase
+    /// flags, rematrixing, dynamic range control, and delta bit allocation. Returns the frame,
+    /// and the expected frequency coefficients of the left and right channels of each block.
+    fn make_frame() -> (Vec<u8>, Vec<[[f32; SAMPLES_PER_BLOCK]; 2]>) {
+        let mut w = BitWriterLtr::new();
+        let mut m = Model::new();
+        let mut expected = Vec::new();
+
+        // Synchronization information, with a placeholder CRC, at 48 kHz and 640 kbps.
+        w.write_bits_leq32(0x0b77, 16);
+        w.write_bits_leq32(0, 16);
+        w.write_bits_leq32(0, 2);
+        w.write_bits_leq32(36, 6);
+
+        // Bit stream information: version 8, main audio service, 2/0, no LFE channel, and the
+        // Dolby Surround mode, dialogue normalization, and all optional fields absent.
+        w.write_bits_leq32(8, 5);
+        w.write_bits_leq32(0, 3);
+        w.write_bits_leq32(2, 3);
+        w.write_bits_leq32(0, 2);
+        w.write_bool(false);
+        w.write_bits_leq32(27, 5);
+        w.write_bits_leq32(0, 3);
+        w.write_bits_leq32(0, 2);
+        w.write_bits_leq32(0, 3);
+
+        // Block 0.
+        //
+        // Long transforms, no dither, and a dynamic range gain of 2.
+        w.write_bits_leq32(0, 4);
+        w.write_bool(true);
+        w.write_bits_leq32(0x20, 8);
+        m.gain = 2.0;
+
+        // Coupling of both channels, with phase flags, from sub-band 3 to 5 in 2 bands.
+        w.write_bool(true);
+        w.write_bool(true);
+        w.write_bits_leq32(0b11, 2);
+        w.write_bool(true);
+        w.write_bits_leq32(3, 4);
+        w.write_bits_leq32(2, 4);
+        w.write_bool(false);
+        m.in_cpl = [true, true];
+        m.cpl_band_ends = vec![85, 97];
+
+        // Coupling coordinates. The last coordinate of the right channel is not normalized.
+        w.write_bool(true);
+        w.write_bits_leq32(0, 2);
+        w.write_bits_leq32(0, 8);
+        w.write_bits_leq32(0x18, 8);
+        w.write_bool(true);
+        w.write_bits_leq32(1, 2);
+        w.write_bits_leq32(0, 8);
+        w.write_bits_leq32(0xf8, 8);
+        m.cpl_coords = [vec![4.0, 3.0], vec![0.5, 1.0 / 65536.0]];
+
+        w.write_bool(false);
+        w.write_bool(true);
+        m.phase_flags = vec![false, true];
+
+        // Rematrixing of 4 bands.
+        w.write_bool(true);
+        w.write_bits_leq32(0b1010, 4);
+        m.remat_flags = vec![true, false, true, false];
+
+        // D45 coupling exponents, D15 left exponents, and D25 right exponents.
+        w.write_bits_leq32(3, 2);
+        w.write_bits_leq32(1, 2);
+        w.write_bits_leq32(2, 2);
+
+        w.write_bits_leq32(5, 4);
+        m.write_exps(&mut w, CPL, 73..97, 3, 10, &[82, 64]);
+        m.allocs[CPL].start = 73;
+        m.allocs[CPL].end = 97;
+
+        w.write_bits_leq32(6, 4);
+        m.exps[0][0] = 6;
+        m.write_exps(&mut w, 0, 1..73, 1, 6, &[63, 61].repeat(12));
+        w.write_bits_leq32(0, 2);
+        m.allocs[0].end = 73;
+
+        w.write_bits_leq32(8, 4);
+        m.exps[1][0] = 8;
+        m.write_exps(&mut w, 1, 1..73, 2, 8, &[82; 12]);
+        w.write_bits_leq32(0, 2);
+        m.allocs[1].end = 73;
+
+        // Bit allocation parameters.
+        w.write_bool(true);
+        w.write_bits_leq32(0b10_01_01_10, 8);
+        w.write_bits_leq32(4, 3);
+        m.params = BitAllocParams {
+            fscod: 0,
+            slow_decay: SLOW_DECAY[2],
+            fast_decay: FAST_DECAY[1],
+            slow_gain: SLOW_GAIN[1],
+            db_per_bit: DB_PER_BIT[2],
+            floor: FLOOR[4],
+        };
+
+        m.write_snr_offsets(&mut w, 40, &[(CPL, 4), (0, 8), (1, 15)]);
+
+        // Coupling leak initialization.
+        w.write_bool(true);
+        w.write_bits_leq32(2, 3);
+        w.write_bits_leq32(1, 3);
+        m.allocs[CPL].fast_leak = (2 << 8) + 768;
+        m.allocs[CPL].slow_leak = (1 << 8) + 768;
+
+        // A new delta bit allocation for the left channel, that removes bits from 3 bands.
+        w.write_bool(true);
+        w.write_bits_leq32(0b10_01_10, 6);
+        w.write_bits_leq32(0, 3);
+        w.write_bits_leq32(10, 5);
+        w.write_bits_leq32(3, 4);
+        w.write_bits_leq32(6, 3);
+
+        let mut delta = DeltaBitAlloc { n_segs: 1, ..Default::default() };
+        delta.offsets[0] = 10;
+        delta.lens[0] = 3;
+        delta.deltas[0] = 6;
+        m.allocs[0].delta = Some(delta);
+
+        // 2 bytes of skipped data.
+        w.write_bool(true);
+        w.write_bits_leq32(2, 9);
+        w.write_bits_leq32(0xabcd, 16);
+
+        expected.push(m.write_mantissas(&mut w, 0));
+
+        // Block 1.
+        //
+        // A dynamic range gain of 0.5, new coupling coordinates and phase flags for the right
+        // channel, and everything else reused.
+        w.write_bits_leq32(0, 4);
+        w.write_bool(true);
+        w.write_bits_leq32(0xe0, 8);
+        m.gain = 0.5;
+
+        w.write_bool(false);
+        w.write_bool(false);
+        w.write_bool(true);
+        w.write_bits_leq32(0, 2);
+        w.write_bits_leq32(0x2f, 8);
+        w.write_bits_leq32(0x34, 8);
+        m.cpl_coords[1] = vec![1.9375, 0.625];
+
+        w.write_bool(true);
+        w.write_bool(false);
+        m.phase_flags = vec![true, false];
+
+        w.write_bool(false);
+        w.write_bits_leq32(0, 6);
+        w.write_bits_leq32(0, 5);
+
+        expected.push(m.write_mantissas(&mut w, 1));
+
+        // Block 2.
+        //
+        // Coupling is turned off, so new exponents with new bandwidths are required, and all 4
+        // bands may be rematrixed. The dynamic range gain of the previous block is reused.
+        w.write_bits_leq32(0, 4);
+        w.write_bool(false);
+
+        w.write_bool(true);
+        w.write_bool(false);
+        m.in_cpl = [false, false];
+
+        w.write_bool(true);
+        w.write_bits_leq32(0b0101, 4);
+        m.remat_flags = vec![false, true, false, true];
+
+        // D45 left exponents up to bin 133, and D15 right exponents up to bin 73.
+        w.write_bits_leq32(3, 2);
+        w.write_bits_leq32(1, 2);
+        w.write_bits_leq32(20, 6);
+        w.write_bits_leq32(0, 6);
+
+        w.write_bits_leq32(5, 4);
+        m.exps[0][0] = 5;
+        m.write_exps(&mut w, 0, 1..133, 3, 5, &[63; 11]);
+        w.write_bits_leq32(0, 2);
+        m.allocs[0].end = 133;
+
+        w.write_bits_leq32(3, 4);
+        m.exps[1][0] = 3;
+        m.write_exps(&mut w, 1, 1..73, 1, 3, &[62; 24]);
+        w.write_bits_leq32(0, 2);
+        m.allocs[1].end = 73;
+
+        w.write_bool(false);
+        m.write_snr_offsets(&mut w, 12, &[(0, 0), (1, 12)]);
+
+        // Reuse the delta bit allocation of the left channel, and a new delta bit allocation of
+        // 2 segments for the right channel.
+        w.write_bool(true);
+        w.write_bits_leq32(0b00_01, 4);
+        w.write_bits_leq32(1, 3);
+        w.write_bits_leq32(5, 5);
+        w.write_bits_leq32(2, 4);
+        w.write_bits_leq32(1, 3);
+        w.write_bits_leq32(3, 5);
+        w.write_bits_leq32(4, 4);
+        w.write_bits_leq32(7, 3);
+
+        let mut delta = DeltaBitAlloc { n_segs: 2, ..Default::default() };
+        delta.offsets[..2].copy_from_slice(&[5, 3]);
+        delta.lens[..2].copy_from_slice(&[2, 4]);
+        delta.deltas[..2].copy_from_slice(&[1, 7]);
+        m.allocs[1].delta = Some(delta);
+
+        w.write_bool(false);
+
+        expected.push(m.write_mantissas(&mut w, 2));
+
+        // Block 3.
+        //
+        // A dynamic range gain of 1.96875, and everything else reused.
+        w.write_bits_leq32(0, 4);
+        w.write_bool(true);
+        w.write_bits_leq32(0x1f, 8);
+        m.gain = 1.96875;
+
+        w.write_bits_leq32(0, 2);
+        w.write_bits_leq32(0, 4);
+        w.write_bits_leq32(0, 4);
+
+        expected.push(m.write_mantissas(&mut w, 3));
+
+        // Block 4.
+        //
+        // Coupling of the left channel from sub-band 1 to 4, with the first 2 sub-bands combined
+        // into one band. The right channel reuses its exponents, while the left channel and the
+        // coupling channel require new exponents.
+        w.write_bits_leq32(0, 4);
+        w.write_bool(false);
+
+        w.write_bool(true);
+        w.write_bool(true);
+        w.write_bits_leq32(0b10, 2);
+        w.write_bool(false);
+        w.write_bits_leq32(1, 4);
+        w.write_bits_leq32(1, 4);
+        w.write_bool(true);
+        w.write_bool(false);
+        m.in_cpl = [true, false];
+        m.cpl_band_ends = vec![73, 85];
+        m.phase_flags = Vec::new();
+
+        w.write_bool(true);
+        w.write_bits_leq32(0, 2);
+        w.write_bits_leq32(0x3a, 8);
+        w.write_bits_leq32(0x00, 8);
+        m.cpl_coords[0] = vec![0.8125, 4.0];
+
+        // Rematrixing of 3 bands.
+        w.write_bool(true);
+        w.write_bits_leq32(0b110, 3);
+        m.remat_flags = vec![true, true, false];
+
+        // D15 coupling exponents, D25 left exponents, and reused right exponents.
+        w.write_bits_leq32(1, 2);
+        w.write_bits_leq32(2, 2);
+        w.write_bits_leq32(0, 2);
+
+        w.write_bits_leq32(4, 4);
+        m.write_exps(&mut w, CPL, 49..85, 1, 8, &[82; 12]);
+        m.allocs[CPL].start = 49;
+        m.allocs[CPL].end = 85;
+
+        w.write_bits_leq32(7, 4);
+        m.exps[0][0] = 7;
+        m.write_exps(&mut w, 0, 1..49, 2, 7, &[64, 60].repeat(4));
+        w.write_bits_leq32(0, 2);
+        m.allocs[0].end = 49;
+
+        // Reuse the coupling leak, and the delta bit allocations.
+        w.write_bool(false);
+        m.write_snr_offsets(&mut w, 20, &[(CPL, 2), (0, 6), (1, 9)]);
+        w.write_bits_leq32(0, 3);
+
+        expected.push(m.write_mantissas(&mut w, 4));
+
+        // Block 5.
+        //
+        // Everything reused.
+        w.write_bits_leq32(0, 6);
+        w.write_bits_leq32(0, 2);
+        w.write_bits_leq32(0, 6);
+        w.write_bits_leq32(0, 5);
+
+        expected.push(m.write_mantissas(&mut w, 5));
+
+        // All quantizers should be exercised.
+        assert!(m.used_baps[1..].iter().all(|&used| used), "{:?}", m.used_baps);
+
+        // Pad the frame, and append the CRC of the frame.
+        let mut frame = w.into_inner();
+
+        assert!(frame.len() <= FRAME_SIZE - 2);
+        frame.resize(FRAME_SIZE, 0);
+
+        let mut crc = Crc16Ansi::new(0);
+        crc.process_buf_bytes(&frame[2..FRAME_SIZE - 2]);
+
+        frame[FRAME_SIZE - 2..].copy_from_slice(&crc.crc().to_be_bytes());
+
+        (frame, expected)
+    }
+
+    /// Compute the output samples of a channel given the frequency coefficients of its audio
+    /// blocks using a direct implementation of the inverse transform, the Kaiser-Bessel derived
+    /// window, and overlap-add.
+    fn reference_output(blocks: &[[f32; SAMPLES_PER_BLOCK]]) -> Vec<f64> {
+        let n = SAMPLES_PER_BLOCK;
+
+        // The modified Bessel function of the first kind.
+        let i0 = |x: f64| {
+            let mut term = 1.0;
+            let mut sum = 1.0;
+
+            for k in 1..64 {
+                term *= x / (2 * k) as f64;
+                sum += term * term;
+            }
+
+            sum
+        };
+
+        let kaiser: Vec<f64> = (0..=n)
+            .map(|j| i0(5.0 * PI * ((j * (n - j)) as f64).sqrt() / (n / 2) as f64))
+            .collect();
+
+        let total: f64 = kaiser.iter().sum();
+
+        let window: Vec<f64> =
+            (0..n).map(|i| (kaiser[..=i].iter().sum::<f64>() / total).sqrt()).collect();
+
+        let mut out = Vec::new();
+        let mut delay = vec![0.0; n];
+
+        for coeffs in blocks {
+            let y: Vec<f64> = (0..2 * n)
+                .map(|i| {
+                    let t = PI / n as f64 * (i as f64 + 0.5 + n as f64 / 2.0);
+
+                    coeffs.iter().enumerate().fold(0.0, |sum, (k, &c)| {
+                        sum - 2.0 * f64::from(c) * (t * (k as f64 + 0.5)).cos()
+                    })
+                })
+                .collect();
+
+            for i in 0..n {
+                out.push(y[i] * window[i] + delay[i]);
+                delay[i] = y[n + i] * window[n - 1 - i];
+            }
+        }
+
+        out
+    }
+
+    #[test]
+    fn verify_decode_audio_blocks() {
+        let (frame, expected) = make_frame();
+
+        let mut bs = BitReaderLtr::new(&frame);
+
+        let header = read_frame_header(&mut bs).unwrap();
+
+        let mut block = AudioBlockDecoder::new();
+
+        for (blk, expected) in expected.iter().enumerate() {
+            block.read(&mut bs, &header, blk).unwrap();
+
+            assert_eq!(block.coeffs(0), &expected[0], "left channel of block {}", blk);
+            assert_eq!(block.coeffs(1), &expected[1], "right channel of block {}", blk);
+        }
+    }
+
+    #[test]
+    fn verify_decode_frame() {
+        let (frame, expected) = make_frame();
+
+        let params = CodecParameters::new().for_codec(CODEC_TYPE_EAC3).clone();
+
+        let mut decoder = Ac3Decoder::try_new(&params, &Default::default()).unwrap();
+
+        // Decode the frame twice to overlap the last block of the first frame with the first
+        // block of the second frame.
+        let mut output = [Vec::new(), Vec::new()];
+
+        for _ in 0..2 {
+            let packet = Packet::new_from_slice(0, 0, 1536, &frame);
+
+            let buf = match decoder.decode(&packet).unwrap() {
+                AudioBufferRef::F32(buf) => buf,
+                _ => unreachable!(),
+            };
+
+            assert_eq!(buf.spec().rate, 48_000);
+            assert_eq!(buf.spec().channels, Channels::FRONT_LEFT | Channels::FRONT_RIGHT);
+            assert_eq!(buf.frames(), BLOCKS_PER_FRAME * SAMPLES_PER_BLOCK);
+
+            output[0].extend_from_slice(buf.chan(0));
+            output[1].extend_from_slice(buf.chan(1));
+        }
+
+        for ch in 0..2 {
+            let blocks: Vec<_> =
+                expected.iter().chain(&expected).map(|coeffs| coeffs[ch]).collect();
+
+            let reference = reference_output(&blocks);
+
+            // The output should not be silent.
+            assert!(reference.iter().any(|&s| s.abs() > 0.01));
+
+            for (i, (&s, &r)) in output[ch].iter().zip(&reference).enumerate() {
+                assert!((f64::from(s) - r).abs() < 1e-5, "sample {} of channel {}", i, ch);
+            }
+        }
+
+        // A corrupted frame fails the CRC check.
+        let mut corrupted = frame.clone();
+        corrupted[100] ^= 0x10;
+
+        assert!(decoder.decode(&Packet::new_from_slice(0, 0, 1536, &corrupted)).is_err());
+    }
+}
@@ -0,0 +1,211 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::support_format;
+
+use symphonia_core::codecs::{CodecParameters, CODEC_TYPE_EAC3};
+use symphonia_core::errors::{seek_error, Error, Result, SeekErrorKind};
+use symphonia_core::formats::prelude::*;
+use symphonia_core::io::*;
+use symphonia_core::meta::{Metadata, MetadataLog};
+use symphonia_core::probe::{Descriptor, Instantiate, QueryDescriptor};
+
+use std::io::{Seek, SeekFrom};
+
+use log::debug;
+
+use crate::header::*;
+
+/// Dolby Digital (AC-3) elementary stream format reader.
+///
+/// `Ac3Reader` implements a demuxer for a raw stream of AC-3 frames.
+pub struct Ac3Reader {
+    reader: MediaSourceStream,
+    tracks: Vec<Track>,
+    cues: Vec<Cue>,
+    metadata: MetadataLog,
+    first_frame_pos: u64,
+    next_packet_ts: u64,
+}
+
+impl QueryDescriptor for Ac3Reader {
+    fn query() -> &'static [Descriptor] {
+        &[support_format!(
+            "ac3",
+            "Dolby Digital (AC-3)",
+            &["ac3"],
+            &["audio/ac3"],
+            &[&[0x0b, 0x77]]
+        )]
+    }
+
+    fn score(_context: &[u8]) -> u8 {
+        255
+    }
+}
+
+/// Synchronize the reader to the start of the next frame, and get the size of that frame in bytes.
+/// After returning, the reader is positioned at the start of the frame.
+fn sync_frame<B: ReadBytes + SeekBuffered>(reader: &mut B) -> Result<usize> {
+    loop {
+        let mut sync = 0u16;
+
+        while sync != SYNC_WORD {
+            sync = (sync << 8) | u16::from(reader.read_u8()?);
+        }
+
+        let mut buf = [0u8; SYNC_INFO_LEN];
+        buf[..2].copy_from_slice(&sync.to_be_bytes());
+        reader.read_buf_exact(&mut buf[2..])?;
+
+        match read_frame_size(&buf) {
+            Ok(frame_size) => {
+                reader.seek_buffered_rev(SYNC_INFO_LEN);
+                return Ok(frame_size);
+            }
+            // An unsupported stream, such as E-AC-3, cannot be synchronized to.
+            Err(err @ Error::Unsupported(_)) => return Err(err),
+            // The sync word was a false positive. Resume the search after it.
+            Err(_) => {
+                reader.seek_buffered_rev(SYNC_INFO_LEN - 2);
+            }
+        }
+    }
+}
+
+impl FormatReader for Ac3Reader {
+    fn try_new(mut source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
+        let frame_size = sync_frame(&mut source)?;
+
+        let first_frame_pos = source.pos();
+
+        // Read the first frame to populate the codec parameters.
+        let frame = source.read_boxed_slice_exact(frame_size)?;
+
+        let header = read_frame_header(&mut BitReaderLtr::new(&frame))?;
+
+        let mut params = CodecParameters::new();
+
+        params
+            .for_codec(CODEC_TYPE_EAC3)
+            .with_sample_rate(header.sample_rate)
+            .with_channels(header.channels())
+            .with_time_base(TimeBase::new(1, header.sample_rate))
+            .with_max_frames_per_packet(SAMPLES_PER_FRAME as u64);
+
+        // Rewind back to the start of the frame.
+        source.seek_buffered_rev(frame_size);
+
+        Ok(Ac3Reader {
+            reader: source,
+            tracks: vec![Track::new(0, params)],
+            cues: Vec::new(),
+            metadata: Default::default(),
+            first_frame_pos,
+            next_packet_ts: 0,
+        })
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        let frame_size = sync_frame(&mut self.reader)?;
+
+        let ts = self.next_packet_ts;
+
+        self.next_packet_ts += SAMPLES_PER_FRAME as u64;
+
+        Ok(Packet::new_from_boxed_slice(
+            0,
+            ts,
+            SAMPLES_PER_FRAME as u64,
+            self.reader.read_boxed_slice_exact(frame_size)?,
+        ))
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        // Get the timestamp of the desired audio frame.
+        let required_ts = match to {
+            // Frame timestamp given.
+            SeekTo::TimeStamp { ts, .. } => ts,
+            // Time value given, calculate frame timestamp from sample rate.
+            SeekTo::Time { time, .. } => {
+                // Use the sample rate to calculate the frame timestamp. If sample rate is not
+                // known, the seek cannot be completed.
+                if let Some(sample_rate) = self.tracks[0].codec_params.sample_rate {
+                    TimeBase::new(1, sample_rate).calc_timestamp(time)
+                }
+                else {
+                    return seek_error(SeekErrorKind::Unseekable);
+                }
+            }
+        };
+
+        debug!("seeking to ts={}", required_ts);
+
+        // If the desired timestamp is less-than the next packet timestamp, attempt to seek
+        // to the start of the stream.
+        if required_ts < self.next_packet_ts {
+            // If the reader is not seekable then only forward seeks are possible.
+            if self.reader.is_seekable() {
+                let seeked_pos = self.reader.seek(SeekFrom::Start(self.first_frame_pos))?;
+
+                // Since the elementary stream has no timestamp information, the position seeked
+                // to must be exactly as requested.
+                if seeked_pos != self.first_frame_pos {
+                    return seek_error(SeekErrorKind::Unseekable);
+                }
+            }
+            else {
+                return seek_error(SeekErrorKind::ForwardOnly);
+            }
+
+            // Successfuly seeked to the start of the stream, reset the next packet timestamp.
+            self.next_packet_ts = 0;
+        }
+
+        // Parse frames from the stream until the frame containing the desired timestamp is
+        // reached.
+        loop {
+            let frame_size = sync_frame(&mut self.reader)?;
+
+            // If the next frame's timestamp would exceed the desired timestamp, end the search.
+            // The reader is already at the start of the frame.
+            if self.next_packet_ts + SAMPLES_PER_FRAME as u64 > required_ts {
+                break;
+            }
+
+            // Otherwise, ignore the frame.
+            self.reader.ignore_bytes(frame_size as u64)?;
+
+            // Increment the timestamp for the next packet.
+            self.next_packet_ts += SAMPLES_PER_FRAME as u64;
+        }
+
+        debug!(
+            "seeked to ts={} (delta={})",
+            self.next_packet_ts,
+            required_ts as i64 - self.next_packet_ts as i64
+        );
+
+        Ok(SeekedTo { track_id: 0, required_ts, actual_ts: self.next_packet_ts })
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.reader
+    }
+}
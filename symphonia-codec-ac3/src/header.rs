@@ -0,0 +1,281 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::audio::{Channels, SignalSpec};
+use symphonia_core::errors::{decode_error, unsupported_error, Result};
+use symphonia_core::io::{BitReaderLtr, ReadBitsLtr};
+
+/// The synchronization word at the start of every frame.
+pub const SYNC_WORD: u16 = 0x0b77;
+
+/// The length of the synchronization information and bit stream identification at the start of
+/// every frame.
+pub const SYNC_INFO_LEN: usize = 6;
+
+/// The number of audio blocks in a frame.
+pub const BLOCKS_PER_FRAME: usize = 6;
+
+/// The number of samples per channel in an audio block.
+pub const SAMPLES_PER_BLOCK: usize = 256;
+
+/// The number of samples per channel in a frame.
+pub const SAMPLES_PER_FRAME: usize = BLOCKS_PER_FRAME * SAMPLES_PER_BLOCK;
+
+/// The sample rates indexed by the sample rate code.
+const SAMPLE_RATES: [u32; 3] = [48_000, 44_100, 32_000];
+
+/// The bit rates in kbps indexed by half of the frame size code.
+const BIT_RATES: [u32; 19] =
+    [32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384, 448, 512, 576, 640];
+
+/// The number of full bandwidth channels of each audio coding mode.
+const FBW_CHANNELS: [usize; 8] = [2, 1, 2, 3, 3, 4, 4, 5];
+
+/// The position of each full bandwidth channel, in bitstream order, of each audio coding mode.
+const FBW_POSITIONS: [&[Channels]; 8] = [
+    // 1+1, dual mono.
+    &[Channels::FRONT_LEFT, Channels::FRONT_RIGHT],
+    // 1/0, centre.
+    &[Channels::FRONT_LEFT],
+    // 2/0, left and right.
+    &[Channels::FRONT_LEFT, Channels::FRONT_RIGHT],
+    // 3/0, left, centre, and right.
+    &[Channels::FRONT_LEFT, Channels::FRONT_CENTRE, Channels::FRONT_RIGHT],
+    // 2/1, left, right, and surround.
+    &[Channels::FRONT_LEFT, Channels::FRONT_RIGHT, Channels::REAR_CENTRE],
+    // 3/1, left, centre, right, and surround.
+    &[Channels::FRONT_LEFT, Channels::FRONT_CENTRE, Channels::FRONT_RIGHT, Channels::REAR_CENTRE],
+    // 2/2, left, right, left surround, and right surround.
+    &[Channels::FRONT_LEFT, Channels::FRONT_RIGHT, Channels::REAR_LEFT, Channels::REAR_RIGHT],
+    // 3/2, left, centre, right, left surround, and right surround.
+    &[
+        Channels::FRONT_LEFT,
+        Channels::FRONT_CENTRE,
+        Channels::FRONT_RIGHT,
+        Channels::REAR_LEFT,
+        Channels::REAR_RIGHT,
+    ],
+];
+
+/// The audio coding mode for two independent mono channels.
+pub const ACMOD_DUAL_MONO: u8 = 0;
+
+/// The audio coding mode for stereo.
+pub const ACMOD_STEREO: u8 = 2;
+
+/// The synchronization information and bit stream information of an AC-3 frame.
+#[derive(Debug)]
+pub struct FrameHeader {
+    /// The sample rate code.
+    pub fscod: u8,
+    pub sample_rate: u32,
+    /// The size of the frame in bytes.
+    pub frame_size: usize,
+    /// The audio coding mode.
+    pub acmod: u8,
+    /// Indicates if the low frequency effects channel is present.
+    pub lfe: bool,
+}
+
+impl FrameHeader {
+    /// The number of full bandwidth channels.
+    pub fn n_fbw_channels(&self) -> usize {
+        FBW_CHANNELS[usize::from(self.acmod)]
+    }
+
+    /// The positions of the coded channels in bitstream order. The low frequency effects channel,
+    /// if present, is last.
+    pub fn positions(&self) -> impl Iterator<Item = Channels> + '_ {
+        let lfe = if self.lfe { Some(Channels::LFE1) } else { None };
+
+        FBW_POSITIONS[usize::from(self.acmod)].iter().copied().chain(lfe)
+    }
+
+    /// The channels of the frame.
+    pub fn channels(&self) -> Channels {
+        self.positions().fold(Channels::empty(), |channels, pos| channels | pos)
+    }
+
+    pub fn spec(&self) -> SignalSpec {
+        SignalSpec::new(self.sample_rate, self.channels())
+    }
+}
+
+/// Check that the bit stream identification is of a supported version.
+fn check_bsid(bsid: u8) -> Result<()> {
+    match bsid {
+        // Versions up to 8 are backwards compatible with AC-3. Versions 9 and 10 use half and
+        // quarter sample rates, but are otherwise identical to AC-3.
+        0..=10 => Ok(()),
+        // Versions 11 to 16 are E-AC-3.
+        11..=16 => unsupported_error("ac3: e-ac-3 is not supported"),
+        _ => decode_error("ac3: invalid bit stream identification"),
+    }
+}
+
+/// Get the size of a frame in bytes given its sample rate and frame size codes.
+fn frame_size(fscod: u8, frmsizecod: u8) -> Result<usize> {
+    if fscod == 3 {
+        return decode_error("ac3: reserved sample rate code");
+    }
+
+    if frmsizecod > 37 {
+        return decode_error("ac3: invalid frame size code");
+    }
+
+    let bit_rate = BIT_RATES[usize::from(frmsizecod >> 1)];
+
+    // The frame size is measured in 16-bit words. At 44.1 kHz, the frame size is padded by a
+    // word for odd frame size codes.
+    let words = match fscod {
+        0 => 2 * bit_rate,
+        1 => (bit_rate * 96_000) / 44_100 + u32::from(frmsizecod & 1),
+        _ => 3 * bit_rate,
+    };
+
+    Ok(2 * words as usize)
+}
+
+/// Get the size of a frame in bytes from the synchronization information at the start of the
+/// frame.
+pub fn read_frame_size(buf: &[u8; SYNC_INFO_LEN]) -> Result<usize> {
+    if u16::from_be_bytes([buf[0], buf[1]]) != SYNC_WORD {
+        return decode_error("ac3: invalid sync word");
+    }
+
+    check_bsid(buf[5] >> 3)?;
+
+    frame_size(buf[4] >> 6, buf[4] & 0x3f)
+}
+
+/// Read the synchronization information and the bit stream information of a frame. After
+/// returning, the bit reader is positioned at the first audio block.
+pub fn read_frame_header(bs: &mut BitReaderLtr<'_>) -> Result<FrameHeader> {
+    if bs.read_bits_leq32(16)? as u16 != SYNC_WORD {
+        return decode_error("ac3: invalid sync word");
+    }
+
+    // The CRC of the first 5/8ths of the frame.
+    let _crc1 = bs.read_bits_leq32(16)?;
+
+    let fscod = bs.read_bits_leq32(2)? as u8;
+    let frmsizecod = bs.read_bits_leq32(6)? as u8;
+
+    let frame_size = frame_size(fscod, frmsizecod)?;
+
+    let bsid = bs.read_bits_leq32(5)? as u8;
+
+    check_bsid(bsid)?;
+
+    let sample_rate = SAMPLE_RATES[usize::from(fscod)] >> bsid.saturating_sub(8);
+
+    // The bit stream mode indicates the type of service, for example, a commentary.
+    let _bsmod = bs.read_bits_leq32(3)?;
+
+    let acmod = bs.read_bits_leq32(3)? as u8;
+
+    // The centre and surround mix levels, and the Dolby Surround mode, are only used when
+    // downmixing.
+    if acmod & 0x1 != 0 && acmod != 0x1 {
+        bs.ignore_bits(2)?;
+    }
+
+    if acmod & 0x4 != 0 {
+        bs.ignore_bits(2)?;
+    }
+
+    if acmod == ACMOD_STEREO {
+        bs.ignore_bits(2)?;
+    }
+
+    let lfe = bs.read_bool()?;
+
+    // Dual mono frames repeat the program information for the second channel.
+    let n_programs = if acmod == ACMOD_DUAL_MONO { 2 } else { 1 };
+
+    for _ in 0..n_programs {
+        // Dialogue normalization.
+        bs.ignore_bits(5)?;
+
+        // Compression gain word.
+        if bs.read_bool()? {
+            bs.ignore_bits(8)?;
+        }
+
+        // Language code.
+        if bs.read_bool()? {
+            bs.ignore_bits(8)?;
+        }
+
+        // Mixing level and room type.
+        if bs.read_bool()? {
+            bs.ignore_bits(7)?;
+        }
+    }
+
+    // Copyright and original bit stream flags.
+    bs.ignore_bits(2)?;
+
+    // Time codes, or for bit stream identification 6, the extended bit stream information. Both
+    // have the same size.
+    if bs.read_bool()? {
+        bs.ignore_bits(14)?;
+    }
+
+    if bs.read_bool()? {
+        bs.ignore_bits(14)?;
+    }
+
+    // Additional bit stream information.
+    if bs.read_bool()? {
+        let len = bs.read_bits_leq32(6)? + 1;
+        bs.ignore_bits(8 * len)?;
+    }
+
+    Ok(FrameHeader { fscod, sample_rate, frame_size, acmod, lfe })
+}
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::audio::Channels;
+    use symphonia_core::io::{BitReaderLtr, FiniteBitStream};
+
+    use super::{read_frame_header, read_frame_size};
+
+    #[test]
+    fn verify_read_frame_header() {
+        // A 3/2 frame at 44.1 kHz and 448 kbps (with padding), with the LFE channel, a
+        // compression gain word, and time codes.
+        let buf = [0x0b, 0x77, 0x00, 0x00, 0x5f, 0x40, 0xe1, 0xfc, 0x00, 0x60, 0x00, 0x00];
+
+        assert_eq!(read_frame_size(&[0x0b, 0x77, 0x00, 0x00, 0x5f, 0x40]).unwrap(), 1952);
+
+        let mut bs = BitReaderLtr::new(&buf);
+
+        let header = read_frame_header(&mut bs).unwrap();
+
+        assert_eq!(header.sample_rate, 44_100);
+        assert_eq!(header.frame_size, 1952);
+        assert_eq!(header.n_fbw_channels(), 5);
+        assert!(header.lfe);
+
+        // The bit stream information is 91 bits long.
+        assert_eq!(bs.bits_left(), 5);
+        assert_eq!(
+            header.channels(),
+            Channels::FRONT_LEFT
+                | Channels::FRONT_RIGHT
+                | Channels::FRONT_CENTRE
+                | Channels::REAR_LEFT
+                | Channels::REAR_RIGHT
+                | Channels::LFE1
+        );
+
+        // E-AC-3 frames are not supported.
+        assert!(read_frame_size(&[0x0b, 0x77, 0x00, 0x00, 0x5f, 0x80]).is_err());
+    }
+}
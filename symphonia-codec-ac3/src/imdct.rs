@@ -0,0 +1,145 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The AC-3 transform, windowing, and overlap-add (ATSC A/52, Section 7.9).
+
+use std::f64::consts;
+
+use symphonia_core::dsp::mdct::Imdct;
+
+use crate::header::SAMPLES_PER_BLOCK;
+
+/// The alpha parameter of the Kaiser-Bessel derived window.
+const KBD_ALPHA: f64 = 5.0;
+
+/// Compute the modified Bessel function of the first kind, I0(2 * sqrt(x)).
+fn bessel_i0(x: f64) -> f64 {
+    let mut val = 1.0;
+
+    for n in (1..64).rev() {
+        val *= x / f64::from(n * n);
+        val += 1.0;
+    }
+
+    val
+}
+
+/// Generate the 512-point Kaiser-Bessel derived window.
+fn generate_window() -> [f32; 2 * SAMPLES_PER_BLOCK] {
+    let n = SAMPLES_PER_BLOCK;
+
+    let alpha2 = (KBD_ALPHA * consts::PI / n as f64).powi(2);
+
+    let mut kb = [0.0; SAMPLES_PER_BLOCK];
+    let mut sum = 0.0;
+
+    for (i, kb) in kb.iter_mut().enumerate() {
+        sum += bessel_i0((i * (n - i)) as f64 * alpha2);
+        *kb = sum;
+    }
+
+    sum += 1.0;
+
+    // The window is symmetric.
+    let mut window = [0.0; 2 * SAMPLES_PER_BLOCK];
+
+    for (i, &kb) in kb.iter().enumerate() {
+        let w = (kb / sum).sqrt() as f32;
+
+        window[i] = w;
+        window[2 * n - 1 - i] = w;
+    }
+
+    window
+}
+
+/// The inverse transform of the frequency coefficients of an audio block of a channel.
+pub struct Transform {
+    imdct_long: Imdct,
+    imdct_short: Imdct,
+    window: [f32; 2 * SAMPLES_PER_BLOCK],
+    /// The output of the inverse transform of the audio block.
+    buf: [f32; 2 * SAMPLES_PER_BLOCK],
+    /// The outputs of the inverse transforms of the two short blocks.
+    short: [[f32; SAMPLES_PER_BLOCK]; 2],
+}
+
+impl Transform {
+    pub fn new() -> Self {
+        Transform {
+            imdct_long: Imdct::new_scaled(SAMPLES_PER_BLOCK, -2.0),
+            imdct_short: Imdct::new_scaled(SAMPLES_PER_BLOCK / 2, -2.0),
+            window: generate_window(),
+            buf: [0.0; 2 * SAMPLES_PER_BLOCK],
+            short: [[0.0; SAMPLES_PER_BLOCK]; 2],
+        }
+    }
+
+    /// Transform the 256 frequency coefficients of an audio block into 256 samples, overlapping
+    /// them with the delayed samples of the previous block. If `is_short` is set, the coefficients
+    /// are the interleaved coefficients of two short transforms.
+    pub fn transform(
+        &mut self,
+        coeffs: &[f32; SAMPLES_PER_BLOCK],
+        is_short: bool,
+        delay: &mut [f32; SAMPLES_PER_BLOCK],
+        out: &mut [f32],
+    ) {
+        let n = SAMPLES_PER_BLOCK;
+
+        if is_short {
+            let mut x = [[0.0; SAMPLES_PER_BLOCK / 2]; 2];
+
+            for (i, pair) in coeffs.chunks_exact(2).enumerate() {
+                x[0][i] = pair[0];
+                x[1][i] = pair[1];
+            }
+
+            self.imdct_short.imdct(&x[0], &mut self.short[0]);
+            self.imdct_short.imdct(&x[1], &mut self.short[1]);
+
+            // The IMDCT is offset by a quarter of its length relative to the short transforms of
+            // AC-3. The first short transform has no offset, and the second short transform is
+            // offset by half of its length. Samples that wrap around the IMDCT are negated.
+            let (first, second) = self.buf.split_at_mut(n);
+
+            for (i, s) in first.iter_mut().enumerate() {
+                *s = if i < n / 4 {
+                    -self.short[0][i + 3 * n / 4]
+                }
+                else {
+                    self.short[0][i - n / 4]
+                };
+            }
+
+            for (i, s) in second.iter_mut().enumerate() {
+                *s = if i < 3 * n / 4 {
+                    self.short[1][i + n / 4]
+                }
+                else {
+                    -self.short[1][i - 3 * n / 4]
+                };
+            }
+        }
+        else {
+            self.imdct_long.imdct(coeffs, &mut self.buf);
+        }
+
+        // Window, and overlap-add the first half with the delayed second half of the previous
+        // block.
+        let (first, second) = self.buf.split_at(n);
+        let (win_first, win_second) = self.window.split_at(n);
+
+        for (((out, &s), &w), &d) in out.iter_mut().zip(first).zip(win_first).zip(delay.iter()) {
+            *out = s * w + d;
+        }
+
+        for ((d, &s), &w) in delay.iter_mut().zip(second).zip(win_second) {
+            *d = s * w;
+        }
+    }
+}
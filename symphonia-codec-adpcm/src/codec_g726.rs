@@ -0,0 +1,441 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! G.726 ADPCM at 16, 24, 32, and 40 kbit/s. The names of the functions and state variables
+//! follow the block diagrams of ITU-T Recommendation G.726.
+
+use symphonia_core::errors::{unsupported_error, Result};
+use symphonia_core::io::{BitReaderLtr, BitReaderRtl, ReadBitsLtr, ReadBitsRtl};
+use symphonia_core::util::clamp::clamp_i16;
+
+use crate::common::from_i16_shift;
+
+/// The log of the quantized difference signal, the scale factor multiplier, and the speed control
+/// of the quantizer of a bit rate, indexed by code.
+struct QuantizerTables {
+    dqln: &'static [i32],
+    wi: &'static [i32],
+    fi: &'static [i32],
+}
+
+#[rustfmt::skip]
+const QUANTIZER_16K: QuantizerTables = QuantizerTables {
+    dqln: &[116, 365, 365, 116],
+    wi: &[-704, 14048, 14048, -704],
+    fi: &[0, 0xe00, 0xe00, 0],
+};
+
+#[rustfmt::skip]
+const QUANTIZER_24K: QuantizerTables = QuantizerTables {
+    dqln: &[-2048, 135, 273, 373, 373, 273, 135, -2048],
+    wi: &[-128, 960, 4384, 18624, 18624, 4384, 960, -128],
+    fi: &[0, 0x200, 0x400, 0xe00, 0xe00, 0x400, 0x200, 0],
+};
+
+#[rustfmt::skip]
+const QUANTIZER_32K: QuantizerTables = QuantizerTables {
+    dqln: &[
+        -2048, 4, 135, 213, 273, 323, 373, 425,
+        425, 373, 323, 273, 213, 135, 4, -2048,
+    ],
+    wi: &[
+        -384, 576, 1312, 2048, 3584, 6336, 11360, 35904,
+        35904, 11360, 6336, 3584, 2048, 1312, 576, -384,
+    ],
+    fi: &[
+        0, 0, 0, 0x200, 0x200, 0x200, 0x600, 0xe00,
+        0xe00, 0x600, 0x200, 0x200, 0x200, 0, 0, 0,
+    ],
+};
+
+#[rustfmt::skip]
+const QUANTIZER_40K: QuantizerTables = QuantizerTables {
+    dqln: &[
+        -2048, -66, 28, 104, 169, 224, 274, 318,
+        358, 395, 429, 459, 488, 514, 539, 566,
+        566, 539, 514, 488, 459, 429, 395, 358,
+        318, 274, 224, 169, 104, 28, -66, -2048,
+    ],
+    wi: &[
+        448, 448, 768, 1248, 1280, 1312, 1856, 3200,
+        4512, 5728, 7008, 8960, 11456, 14080, 16928, 22272,
+        22272, 16928, 14080, 11456, 8960, 7008, 5728, 4512,
+        3200, 1856, 1312, 1280, 1248, 768, 448, 448,
+    ],
+    fi: &[
+        0, 0, 0, 0, 0, 0x200, 0x200, 0x200,
+        0x200, 0x200, 0x400, 0x600, 0x800, 0xa00, 0xc00, 0xc00,
+        0xc00, 0xc00, 0xa00, 0x800, 0x600, 0x400, 0x200, 0x200,
+        0x200, 0x200, 0x200, 0, 0, 0, 0, 0,
+    ],
+};
+
+/// Get the number of bits required to represent `val`, up to 15 bits.
+fn quan(val: i32) -> i32 {
+    (0..15).find(|&i| val < 1 << i).unwrap_or(15)
+}
+
+/// Convert a magnitude, and a sign, to the 4-bit exponent and 6-bit mantissa floating point
+/// format of the predictor.
+fn to_float(mag: i32, is_negative: bool) -> i32 {
+    let float = if mag == 0 {
+        0x20
+    }
+    else {
+        let exp = quan(mag);
+        (exp << 6) + ((mag << 6) >> exp)
+    };
+
+    if is_negative {
+        float - 0x400
+    }
+    else {
+        float
+    }
+}
+
+/// Multiply a predictor coefficient by a sample in the floating point format of the predictor.
+fn fmult(an: i32, srn: i32) -> i32 {
+    let anmag = if an > 0 { an } else { (-an) & 0x1fff };
+    let anexp = quan(anmag) - 6;
+
+    let anmant = if anmag == 0 {
+        32
+    }
+    else if anexp >= 0 {
+        anmag >> anexp
+    }
+    else {
+        anmag << -anexp
+    };
+
+    let wanexp = anexp + ((srn >> 6) & 0xf) - 13;
+    let wanmant = (anmant * (srn & 0x3f) + 0x30) >> 4;
+
+    let retval = if wanexp >= 0 { (wanmant << wanexp) & 0x7fff } else { wanmant >> -wanexp };
+
+    if (an ^ srn) < 0 {
+        -retval
+    }
+    else {
+        retval
+    }
+}
+
+/// The state of the adaptive quantizer and predictor.
+struct G726State {
+    /// The locked, or steady-state, quantizer scale factor.
+    yl: i32,
+    /// The unlocked, or non-steady-state, quantizer scale factor.
+    yu: i32,
+    /// The short-term average of the speed control.
+    dms: i32,
+    /// The long-term average of the speed control.
+    dml: i32,
+    /// The speed control weighting of the locked and unlocked scale factors.
+    ap: i32,
+    /// The coefficients of the second-order pole section of the predictor.
+    a: [i32; 2],
+    /// The coefficients of the sixth-order zero section of the predictor.
+    b: [i32; 6],
+    /// The signs of the previous two partially reconstructed signal samples.
+    pk: [bool; 2],
+    /// The previous six quantized difference signal samples in floating point.
+    dq: [i32; 6],
+    /// The previous two reconstructed signal samples in floating point.
+    sr: [i32; 2],
+    /// Indicates if a tone was detected in the previous sample.
+    td: bool,
+}
+
+impl Default for G726State {
+    fn default() -> Self {
+        G726State {
+            yl: 34816,
+            yu: 544,
+            dms: 0,
+            dml: 0,
+            ap: 0,
+            a: [0; 2],
+            b: [0; 6],
+            pk: [false; 2],
+            dq: [32; 6],
+            sr: [32; 2],
+            td: false,
+        }
+    }
+}
+
+impl G726State {
+    /// The quantizer scale factor.
+    fn step_size(&self) -> i32 {
+        if self.ap >= 256 {
+            return self.yu;
+        }
+
+        let y = self.yl >> 6;
+        let dif = self.yu - y;
+        let al = self.ap >> 2;
+
+        if dif > 0 {
+            y + ((dif * al) >> 6)
+        }
+        else if dif < 0 {
+            y + ((dif * al + 0x3f) >> 6)
+        }
+        else {
+            y
+        }
+    }
+
+    /// Decode a code to a 14-bit linear sample.
+    fn decode(&mut self, code: usize, bits: u32, tables: &QuantizerTables) -> i32 {
+        // Signal estimate.
+        let sezi = (0..6).map(|i| fmult(self.b[i] >> 2, self.dq[i])).sum::<i32>();
+        let sei = sezi + fmult(self.a[1] >> 2, self.sr[1]) + fmult(self.a[0] >> 2, self.sr[0]);
+
+        let sez = sezi >> 1;
+        let se = sei >> 1;
+
+        let y = self.step_size();
+
+        // Inverse adaptive quantizer. The quantized difference signal is in sign-magnitude form.
+        let is_negative = code & (1 << (bits - 1)) != 0;
+
+        let dql = tables.dqln[code] + (y >> 2);
+
+        let mag = if dql < 0 {
+            0
+        }
+        else {
+            let dex = (dql >> 7) & 0xf;
+            let dqt = 128 + (dql & 0x7f);
+            (dqt << 7) >> (14 - dex)
+        };
+
+        // Reconstructed signal.
+        let sr = if is_negative { se - (mag & 0x3fff) } else { se + mag };
+
+        // Partially reconstructed signal.
+        let dqsez = sr - se + sez;
+
+        self.update(bits, y, tables.wi[code], tables.fi[code], mag, is_negative, sr, dqsez);
+
+        sr
+    }
+
+    /// Update the state of the quantizer and predictor after decoding a sample.
+    #[allow(clippy::too_many_arguments)]
+    fn update(
+        &mut self,
+        bits: u32,
+        y: i32,
+        wi: i32,
+        fi: i32,
+        mag: i32,
+        is_negative: bool,
+        sr: i32,
+        dqsez: i32,
+    ) {
+        let pk0 = dqsez < 0;
+
+        // Tone and transition detector.
+        let ylint = self.yl >> 15;
+        let ylfrac = (self.yl >> 10) & 0x1f;
+        let thr2 = if ylint > 9 { 31 << 10 } else { (32 + ylfrac) << ylint };
+        let dqthr = (thr2 + (thr2 >> 1)) >> 1;
+
+        let tr = self.td && mag > dqthr;
+
+        // Quantizer scale factor adaptation.
+        self.yu = (y + ((wi - y) >> 5)).clamp(544, 5120);
+        self.yl += self.yu + ((-self.yl) >> 6);
+
+        // Adaptive predictor coefficients. The coefficients are reset if a transition from a
+        // partial band signal, such as a modem tone, is detected.
+        let mut a2p = 0;
+
+        if tr {
+            self.a = [0; 2];
+            self.b = [0; 6];
+        }
+        else {
+            let pks1 = pk0 ^ self.pk[0];
+
+            a2p = self.a[1] - (self.a[1] >> 7);
+
+            if dqsez != 0 {
+                let fa1 = if pks1 { self.a[0] } else { -self.a[0] };
+
+                if fa1 < -8191 {
+                    a2p -= 0x100;
+                }
+                else if fa1 > 8191 {
+                    a2p += 0xff;
+                }
+                else {
+                    a2p += fa1 >> 5;
+                }
+
+                a2p = if pk0 ^ self.pk[1] {
+                    if a2p <= -12160 {
+                        -12288
+                    }
+                    else if a2p >= 12416 {
+                        12288
+                    }
+                    else {
+                        a2p - 0x80
+                    }
+                }
+                else if a2p <= -12416 {
+                    -12288
+                }
+                else if a2p >= 12160 {
+                    12288
+                }
+                else {
+                    a2p + 0x80
+                };
+            }
+
+            self.a[1] = a2p;
+
+            self.a[0] -= self.a[0] >> 8;
+
+            if dqsez != 0 {
+                self.a[0] += if pks1 { -192 } else { 192 };
+            }
+
+            let a1ul = 15360 - a2p;
+            self.a[0] = self.a[0].clamp(-a1ul, a1ul);
+
+            // The zero section leaks faster for the 40 kbit/s quantizer.
+            let leak = if bits == 5 { 9 } else { 8 };
+
+            for (b, &dq) in self.b.iter_mut().zip(&self.dq) {
+                *b -= *b >> leak;
+
+                if mag != 0 {
+                    *b += if is_negative == (dq < 0) { 128 } else { -128 };
+                }
+            }
+        }
+
+        self.dq.rotate_right(1);
+        self.dq[0] = to_float(mag, is_negative);
+
+        self.sr[1] = self.sr[0];
+        self.sr[0] = if sr > -32768 { to_float(sr.abs(), sr < 0) } else { -992 };
+
+        self.pk[1] = self.pk[0];
+        self.pk[0] = pk0;
+
+        self.td = !tr && a2p < -11776;
+
+        // Adaptation speed control.
+        self.dms += (fi - self.dms) >> 5;
+        self.dml += ((fi << 2) - self.dml) >> 7;
+
+        if tr {
+            self.ap = 256;
+        }
+        else if y < 1536 || self.td || ((self.dms << 2) - self.dml).abs() >= (self.dml >> 3) {
+            self.ap += (0x200 - self.ap) >> 4;
+        }
+        else {
+            self.ap += (-self.ap) >> 4;
+        }
+    }
+}
+
+/// A G.726 decoder for a single channel. State is kept between packets.
+pub(crate) struct G726Decoder {
+    bits: u32,
+    is_lsb_first: bool,
+    tables: &'static QuantizerTables,
+    state: G726State,
+}
+
+impl G726Decoder {
+    /// Instantiate a decoder for codes of `bits` bits. If `is_lsb_first` is set, codes are packed
+    /// starting from the least-significant bit of each byte.
+    pub fn try_new(bits: u32, is_lsb_first: bool) -> Result<Self> {
+        let tables = match bits {
+            2 => &QUANTIZER_16K,
+            3 => &QUANTIZER_24K,
+            4 => &QUANTIZER_32K,
+            5 => &QUANTIZER_40K,
+            _ => return unsupported_error("adpcm: g.726 bits per coded sample must be 2 to 5"),
+        };
+
+        Ok(G726Decoder { bits, is_lsb_first, tables, state: Default::default() })
+    }
+
+    /// Get the number of samples coded in a buffer of `len` bytes.
+    pub fn num_samples(&self, len: usize) -> usize {
+        8 * len / self.bits as usize
+    }
+
+    pub fn reset(&mut self) {
+        self.state = Default::default();
+    }
+
+    /// Decode a buffer of packed codes into `out`.
+    pub fn decode(&mut self, buf: &[u8], out: &mut [i32]) -> Result<()> {
+        if self.is_lsb_first {
+            let mut bs = BitReaderRtl::new(buf);
+
+            for sample in out.iter_mut() {
+                let code = bs.read_bits_leq32(self.bits)? as usize;
+                *sample = self.decode_code(code);
+            }
+        }
+        else {
+            let mut bs = BitReaderLtr::new(buf);
+
+            for sample in out.iter_mut() {
+                let code = bs.read_bits_leq32(self.bits)? as usize;
+                *sample = self.decode_code(code);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn decode_code(&mut self, code: usize) -> i32 {
+        // The reconstructed signal has a 14-bit dynamic range.
+        let sr = self.state.decode(code, self.bits, self.tables);
+        from_i16_shift!(clamp_i16(sr << 2))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fmult, quan, to_float};
+
+    #[test]
+    fn verify_float_conversion() {
+        assert_eq!(quan(0), 0);
+        assert_eq!(quan(1), 1);
+        assert_eq!(quan(0x4000), 15);
+        assert_eq!(quan(0x7fff), 15);
+
+        // Zero is represented by a mantissa of 32 and an exponent of 0.
+        assert_eq!(to_float(0, false), 0x20);
+        assert_eq!(to_float(0, true), -992);
+        assert_eq!(to_float(1, false), (1 << 6) + 32);
+        assert_eq!(to_float(100, true), (7 << 6) + 50 - 0x400);
+
+        // The products are doubled, and rounded to the precision of the 6-bit mantissas, such
+        // that a coefficient of 0.5 approximately preserves a sample.
+        assert_eq!(fmult(0x2000 >> 2, to_float(100, false)), 103);
+        assert_eq!(fmult(-(0x2000 >> 2), to_float(100, false)), -103);
+        assert_eq!(fmult(0x2000 >> 2, to_float(100, true)), -103);
+        assert_eq!(fmult(0, to_float(100, false)), 0);
+    }
+}
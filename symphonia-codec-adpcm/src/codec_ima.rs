@@ -5,7 +5,7 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
-use symphonia_core::errors::Result;
+use symphonia_core::errors::{decode_error, Result};
 use symphonia_core::io::ReadBytes;
 use symphonia_core::util::clamp::clamp_i16;
 
@@ -40,6 +40,9 @@ impl AdpcmImaBlockStatus {
     fn read_preamble<B: ReadBytes>(stream: &mut B) -> Result<Self> {
         let predictor = u16_to_i32!(stream.read_u16()?);
         let step_index = stream.read_byte()? as i32;
+        if step_index > 88 {
+            return decode_error("adpcm: step index exceeds range");
+        }
         //reserved byte
         let _ = stream.read_byte()?;
         let status = Self { predictor, step_index };
@@ -97,3 +100,29 @@ pub(crate) fn decode_stereo<B: ReadBytes>(
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::io::BufReader;
+
+    use super::decode_mono;
+
+    #[test]
+    fn verify_decode_mono() {
+        // A block of 5 frames: a preamble with a predictor of 0 and a step index of 0, followed by
+        // the codes 7, 7, 9, and 0.
+        let block = [0, 0, 0, 0, 0x77, 0x09];
+        let mut buf = [0; 5];
+        decode_mono(&mut BufReader::new(&block), &mut buf, 5).unwrap();
+
+        let expected: Vec<i32> = [0, 13, 43, 31, 34].iter().map(|&s: &i32| s << 16).collect();
+        assert_eq!(buf.to_vec(), expected);
+
+        // The maximum step index is 88.
+        let block = [0, 0, 88, 0, 0, 0];
+        assert!(decode_mono(&mut BufReader::new(&block), &mut buf, 5).is_ok());
+
+        let block = [0, 0, 89, 0, 0, 0];
+        assert!(decode_mono(&mut BufReader::new(&block), &mut buf, 5).is_err());
+    }
+}
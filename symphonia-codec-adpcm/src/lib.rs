@@ -19,22 +19,32 @@ use symphonia_core::support_codec;
 use symphonia_core::audio::{AsAudioBufferRef, AudioBuffer, AudioBufferRef, Signal, SignalSpec};
 use symphonia_core::codecs::{CodecDescriptor, CodecParameters, CodecType};
 use symphonia_core::codecs::{Decoder, DecoderOptions, FinalizeResult};
+use symphonia_core::codecs::{CODEC_TYPE_ADPCM_G726, CODEC_TYPE_ADPCM_G726LE};
 use symphonia_core::codecs::{CODEC_TYPE_ADPCM_IMA_WAV, CODEC_TYPE_ADPCM_MS};
-use symphonia_core::errors::{unsupported_error, Result};
+use symphonia_core::errors::{decode_error, unsupported_error, Result};
 use symphonia_core::formats::Packet;
 use symphonia_core::io::ReadBytes;
 
+mod codec_g726;
 mod codec_ima;
 mod codec_ms;
 mod common;
 
 fn is_supported_adpcm_codec(codec_type: CodecType) -> bool {
-    matches!(codec_type, CODEC_TYPE_ADPCM_MS | CODEC_TYPE_ADPCM_IMA_WAV)
+    matches!(
+        codec_type,
+        CODEC_TYPE_ADPCM_MS
+            | CODEC_TYPE_ADPCM_IMA_WAV
+            | CODEC_TYPE_ADPCM_G726
+            | CODEC_TYPE_ADPCM_G726LE
+    )
 }
 
 enum InnerDecoder {
     AdpcmMs,
     AdpcmIma,
+    /// G.726 is not coded in blocks, and is stateful.
+    G726(codec_g726::G726Decoder),
 }
 
 impl InnerDecoder {
@@ -42,6 +52,7 @@ impl InnerDecoder {
         match *self {
             InnerDecoder::AdpcmMs => codec_ms::decode_mono,
             InnerDecoder::AdpcmIma => codec_ima::decode_mono,
+            InnerDecoder::G726(_) => unreachable!(),
         }
     }
 
@@ -51,6 +62,7 @@ impl InnerDecoder {
         match *self {
             InnerDecoder::AdpcmMs => codec_ms::decode_stereo,
             InnerDecoder::AdpcmIma => codec_ima::decode_stereo,
+            InnerDecoder::G726(_) => unreachable!(),
         }
     }
 }
@@ -64,6 +76,24 @@ pub struct AdpcmDecoder {
 
 impl AdpcmDecoder {
     fn decode_inner(&mut self, packet: &Packet) -> Result<()> {
+        if let InnerDecoder::G726(decoder) = &mut self.inner_decoder {
+            // Every byte of a G.726 packet is packed codes of a single channel.
+            let n_frames = decoder.num_samples(packet.buf().len());
+
+            if n_frames > self.buf.capacity() {
+                return decode_error("adpcm: packet exceeds the maximum frames per packet");
+            }
+
+            self.buf.clear();
+            self.buf.render_reserved(Some(n_frames));
+
+            decoder.decode(packet.buf(), self.buf.chan_mut(0))?;
+
+            self.buf.trim(packet.trim_start() as usize, packet.trim_end() as usize);
+
+            return Ok(());
+        }
+
         let mut stream = packet.as_buf_reader();
 
         let frames_per_block = self.params.frames_per_block.unwrap() as usize;
@@ -99,6 +129,8 @@ impl AdpcmDecoder {
             _ => unreachable!(),
         }
 
+        self.buf.trim(packet.trim_start() as usize, packet.trim_end() as usize);
+
         Ok(())
     }
 }
@@ -115,7 +147,11 @@ impl Decoder for AdpcmDecoder {
             _ => return unsupported_error("adpcm: maximum frames per packet is required"),
         };
 
-        if params.frames_per_block.is_none() || params.frames_per_block.unwrap() == 0 {
+        // G.726 is not coded in blocks.
+        let is_g726 = matches!(params.codec, CODEC_TYPE_ADPCM_G726 | CODEC_TYPE_ADPCM_G726LE);
+
+        if !is_g726 && (params.frames_per_block.is_none() || params.frames_per_block.unwrap() == 0)
+        {
             return unsupported_error("adpcm: valid frames per block is required");
         }
 
@@ -134,9 +170,28 @@ impl Decoder for AdpcmDecoder {
             return unsupported_error("adpcm: channels or channel_layout is required");
         };
 
+        // Only mono and stereo ADPCM is supported.
+        if spec.channels.count() > 2 {
+            return unsupported_error("adpcm: more than two channels are not supported");
+        }
+
         let inner_decoder = match params.codec {
             CODEC_TYPE_ADPCM_MS => InnerDecoder::AdpcmMs,
             CODEC_TYPE_ADPCM_IMA_WAV => InnerDecoder::AdpcmIma,
+            CODEC_TYPE_ADPCM_G726 | CODEC_TYPE_ADPCM_G726LE => {
+                if spec.channels.count() != 1 {
+                    return unsupported_error("adpcm: only mono g.726 is supported");
+                }
+
+                let bits = match params.bits_per_coded_sample {
+                    Some(bits) => bits,
+                    _ => return unsupported_error("adpcm: bits per coded sample is required"),
+                };
+
+                let is_lsb_first = params.codec == CODEC_TYPE_ADPCM_G726LE;
+
+                InnerDecoder::G726(codec_g726::G726Decoder::try_new(bits, is_lsb_first)?)
+            }
             _ => return unsupported_error("adpcm: codec is unsupported"),
         };
 
@@ -151,11 +206,16 @@ impl Decoder for AdpcmDecoder {
         &[
             support_codec!(CODEC_TYPE_ADPCM_MS, "adpcm_ms", "Microsoft ADPCM"),
             support_codec!(CODEC_TYPE_ADPCM_IMA_WAV, "adpcm_ima_wav", "ADPCM IMA WAV"),
+            support_codec!(CODEC_TYPE_ADPCM_G726, "adpcm_g726", "G.726 ADPCM"),
+            support_codec!(CODEC_TYPE_ADPCM_G726LE, "adpcm_g726le", "G.726 ADPCM little-endian"),
         ]
     }
 
     fn reset(&mut self) {
-        // No state is stored between packets, therefore do nothing.
+        // Only G.726 stores state between packets.
+        if let InnerDecoder::G726(decoder) = &mut self.inner_decoder {
+            decoder.reset();
+        }
     }
 
     fn codec_params(&self) -> &CodecParameters {
@@ -180,3 +240,56 @@ impl Decoder for AdpcmDecoder {
         self.buf.as_audio_buffer_ref()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::audio::{AudioBufferRef, Channels, Signal};
+    use symphonia_core::codecs::{CodecParameters, CodecType, Decoder, DecoderOptions};
+    use symphonia_core::codecs::{CODEC_TYPE_ADPCM_IMA_WAV, CODEC_TYPE_ADPCM_MS};
+    use symphonia_core::formats::Packet;
+
+    use super::AdpcmDecoder;
+
+    fn make_params(codec: CodecType, channels: Channels) -> CodecParameters {
+        let mut params = CodecParameters::new();
+        params
+            .for_codec(codec)
+            .with_sample_rate(8000)
+            .with_channels(channels)
+            .with_frames_per_block(5)
+            .with_max_frames_per_packet(10);
+        params
+    }
+
+    #[test]
+    fn verify_channel_count() {
+        let stereo = Channels::FRONT_LEFT | Channels::FRONT_RIGHT;
+
+        for &codec in &[CODEC_TYPE_ADPCM_IMA_WAV, CODEC_TYPE_ADPCM_MS] {
+            let params = make_params(codec, stereo);
+            assert!(AdpcmDecoder::try_new(&params, &DecoderOptions::default()).is_ok());
+
+            let params = make_params(codec, stereo | Channels::FRONT_CENTRE);
+            assert!(AdpcmDecoder::try_new(&params, &DecoderOptions::default()).is_err());
+        }
+    }
+
+    #[test]
+    fn verify_decode_trimmed() {
+        let params = make_params(CODEC_TYPE_ADPCM_IMA_WAV, Channels::FRONT_LEFT);
+        let mut decoder = AdpcmDecoder::try_new(&params, &DecoderOptions::default()).unwrap();
+
+        // Two blocks of 5 frames each, with the last 3 frames of the second block trimmed.
+        let data = [0, 0, 0, 0, 0x77, 0x77, 0x10, 0, 0, 0, 0, 0];
+        let packet = Packet::new_trimmed_from_slice(0, 0, 7, 0, 3, &data);
+
+        match decoder.decode(&packet).unwrap() {
+            AudioBufferRef::S32(buf) => {
+                let expected: Vec<i32> =
+                    [0, 13, 43, 106, 242, 16, 16].iter().map(|&s: &i32| s << 16).collect();
+                assert_eq!(buf.chan(0).to_vec(), expected);
+            }
+            _ => unreachable!(),
+        }
+    }
+}
@@ -184,8 +184,8 @@ impl MagicCookie {
             return unsupported_error("alac: not compatible with alac version 0");
         }
 
-        // A bit-depth greater than 32 is not allowed.
-        if config.bit_depth > 32 {
+        // A bit-depth of 0, or greater than 32, is not allowed.
+        if config.bit_depth == 0 || config.bit_depth > 32 {
             return decode_error("alac: invalid bit depth");
         }
 
@@ -667,12 +667,23 @@ fn decode_sce_or_cpe<B: ReadBitsLtr>(
     let num_samples =
         if is_partial_frame { bs.read_bits_leq32(32)? } else { config.frame_length } as usize;
 
+    // A partial frame may not be longer than a full frame.
+    if num_samples > config.frame_length as usize {
+        return decode_error("alac: partial frame length exceeds frame length");
+    }
+
     if !is_uncompressed {
         // The number of upper sample bits that will be predicted per channel. This may be less-than
         // the bit-depth if the lower sample bits will be encoded separately. If decoding a CPE,
         // each channel gets an extra bit allocated to it for mid-side encoding.
         let pred_bits = config.bit_depth - shift + u8::from(is_cpe);
 
+        // The reference encoder always shifts 32-bit samples, a CPE could not be predicted in 32
+        // bits otherwise.
+        if pred_bits > 32 {
+            return decode_error("alac: invalid shift value for bit depth");
+        }
+
         let mid_side_shift = bs.read_bits_leq32(8)? as u8;
         let mid_side_weight = bs.read_bits_leq32_signed(8)?;
 
@@ -819,3 +830,137 @@ fn decorrelate_mid_side(out0: &mut [i32], out1: &mut [i32], weight: i32, shift:
         *s1 = *s0 - *s1;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::audio::{AudioBufferRef, Signal};
+    use symphonia_core::codecs::{CodecParameters, Decoder, DecoderOptions, CODEC_TYPE_ALAC};
+    use symphonia_core::errors::Error;
+    use symphonia_core::formats::Packet;
+    use symphonia_core::io::BitWriterLtr;
+
+    use super::{AlacDecoder, ALAC_ELEM_TAG_CPE, ALAC_ELEM_TAG_END, ALAC_ELEM_TAG_SCE};
+
+    /// Make the 24-byte magic cookie of a stream.
+    fn make_cookie(frame_length: u32, bit_depth: u8, num_channels: u8) -> Vec<u8> {
+        let mut buf = frame_length.to_be_bytes().to_vec();
+        // Compatible version, bit depth, pb, mb, kb, and the number of channels.
+        buf.extend_from_slice(&[0, bit_depth, 40, 10, 14, num_channels]);
+        // Max run, max frame bytes, average bit rate, and sample rate.
+        buf.extend_from_slice(&255u16.to_be_bytes());
+        buf.extend_from_slice(&0u32.to_be_bytes());
+        buf.extend_from_slice(&0u32.to_be_bytes());
+        buf.extend_from_slice(&44100u32.to_be_bytes());
+        buf
+    }
+
+    fn make_decoder(
+        frame_length: u32,
+        bit_depth: u8,
+        num_channels: u8,
+    ) -> Result<AlacDecoder, Error> {
+        let mut params = CodecParameters::new();
+        params
+            .for_codec(CODEC_TYPE_ALAC)
+            .with_extra_data(make_cookie(frame_length, bit_depth, num_channels).into_boxed_slice());
+
+        AlacDecoder::try_new(&params, &DecoderOptions::default())
+    }
+
+    /// Write the header of a SCE or CPE element.
+    fn write_elem_header(
+        bs: &mut BitWriterLtr,
+        tag: u32,
+        num_samples: Option<u32>,
+        shift: u32,
+        is_uncompressed: bool,
+    ) {
+        bs.write_bits_leq32(tag, 3);
+        // Instance tag, and unused header bits.
+        bs.write_bits_leq32(0, 4);
+        bs.write_bits_leq32(0, 12);
+        bs.write_bits_leq32(u32::from(num_samples.is_some()), 1);
+        bs.write_bits_leq32(shift / 8, 2);
+        bs.write_bits_leq32(u32::from(is_uncompressed), 1);
+
+        if let Some(num_samples) = num_samples {
+            bs.write_bits_leq32(num_samples, 32);
+        }
+    }
+
+    fn decode(decoder: &mut AlacDecoder, buf: Vec<u8>) -> Result<Vec<i32>, Error> {
+        match decoder.decode(&Packet::new_from_boxed_slice(0, 0, 0, buf.into_boxed_slice()))? {
+            AudioBufferRef::S32(buf) => Ok(buf.chan(0).to_vec()),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn verify_invalid_bit_depth() {
+        assert!(make_decoder(4096, 16, 2).is_ok());
+
+        assert!(matches!(
+            make_decoder(4096, 0, 2),
+            Err(Error::DecodeError("alac: invalid bit depth"))
+        ));
+        assert!(matches!(
+            make_decoder(4096, 33, 2),
+            Err(Error::DecodeError("alac: invalid bit depth"))
+        ));
+    }
+
+    #[test]
+    fn verify_partial_frame_length() {
+        let mut decoder = make_decoder(4, 16, 1).unwrap();
+
+        // A partial frame of uncompressed samples.
+        let mut bs = BitWriterLtr::new();
+        write_elem_header(&mut bs, ALAC_ELEM_TAG_SCE, Some(3), 0, true);
+        for &sample in &[1, -2, 3] {
+            bs.write_bits_leq32(sample as u32 & 0xffff, 16);
+        }
+        bs.write_bits_leq32(ALAC_ELEM_TAG_END, 3);
+
+        assert_eq!(
+            decode(&mut decoder, bs.into_inner()).unwrap(),
+            vec![1 << 16, -2 << 16, 3 << 16]
+        );
+
+        // A partial frame longer than the frame length.
+        let mut bs = BitWriterLtr::new();
+        write_elem_header(&mut bs, ALAC_ELEM_TAG_SCE, Some(5), 0, true);
+        for _ in 0..5 {
+            bs.write_bits_leq32(0, 16);
+        }
+        bs.write_bits_leq32(ALAC_ELEM_TAG_END, 3);
+
+        assert!(matches!(
+            decode(&mut decoder, bs.into_inner()),
+            Err(Error::DecodeError("alac: partial frame length exceeds frame length"))
+        ));
+    }
+
+    #[test]
+    fn verify_invalid_shift() {
+        let mut decoder = make_decoder(4, 32, 2).unwrap();
+
+        // A 32-bit compressed CPE must be shifted.
+        let mut bs = BitWriterLtr::new();
+        write_elem_header(&mut bs, ALAC_ELEM_TAG_CPE, None, 0, false);
+        bs.write_bits_leq32(0, 32);
+
+        assert!(matches!(
+            decode(&mut decoder, bs.into_inner()),
+            Err(Error::DecodeError("alac: invalid shift value for bit depth"))
+        ));
+
+        // An uncompressed 32-bit CPE does not need to be shifted.
+        let mut bs = BitWriterLtr::new();
+        write_elem_header(&mut bs, ALAC_ELEM_TAG_CPE, Some(1), 0, true);
+        bs.write_bits_leq32(0x8000_0000, 32);
+        bs.write_bits_leq32(0x7fff_ffff, 32);
+        bs.write_bits_leq32(ALAC_ELEM_TAG_END, 3);
+
+        assert_eq!(decode(&mut decoder, bs.into_inner()).unwrap(), vec![i32::MIN]);
+    }
+}
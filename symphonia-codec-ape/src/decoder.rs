@@ -0,0 +1,685 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::audio::{AsAudioBufferRef, AudioBuffer, AudioBufferRef, Signal, SignalSpec};
+use symphonia_core::codecs::{CodecDescriptor, CodecParameters, CODEC_TYPE_MONKEYS_AUDIO};
+use symphonia_core::codecs::{Decoder, DecoderOptions, FinalizeResult};
+use symphonia_core::errors::{decode_error, unsupported_error, Result};
+use symphonia_core::formats::Packet;
+use symphonia_core::io::{BufReader, ReadBytes};
+use symphonia_core::support_codec;
+
+use crate::entropy::{EntropyDecoder, RiceState};
+use crate::filter::{filter_params, NnFilter};
+use crate::predictor::Predictor;
+
+/// The minimum supported file version. Older files use a different entropy coder and predictor.
+const MIN_DECODER_VERSION: u16 = 3930;
+
+/// Frame flag: the left channel, or the only channel, is silent.
+const FRAME_FLAG_LEFT_SILENCE: u32 = 0x1;
+/// Frame flag: the right channel is silent.
+const FRAME_FLAG_RIGHT_SILENCE: u32 = 0x2;
+/// Frame flag: both channels are identical and are coded as one.
+const FRAME_FLAG_PSEUDO_STEREO: u32 = 0x4;
+
+/// The reflected CRC-32 (IEEE 802.3) lookup table.
+const CRC32_TABLE: [u32; 256] = make_crc32_table();
+
+const fn make_crc32_table() -> [u32; 256] {
+    let mut table = [0; 256];
+
+    let mut i = 0;
+
+    while i < 256 {
+        let mut crc = i as u32;
+
+        let mut j = 0;
+
+        while j < 8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+            j += 1;
+        }
+
+        table[i] = crc;
+        i += 1;
+    }
+
+    table
+}
+
+/// Update a CRC-32 with the little-endian bytes of a sample.
+#[inline(always)]
+fn crc32_update(mut crc: u32, sample: u32, n_bytes: u32) -> u32 {
+    for i in 0..n_bytes {
+        let byte = (sample >> (8 * i)) as u8;
+        crc = (crc >> 8) ^ CRC32_TABLE[usize::from((crc as u8) ^ byte)];
+    }
+    crc
+}
+
+/// Monkey's Audio decoder.
+///
+/// Files from version 3.93 are supported. Only the fast and normal compression levels are tested.
+pub struct ApeDecoder {
+    params: CodecParameters,
+    version: u16,
+    bits_per_sample: u32,
+    /// The NN filters of the Y and X channels, in the order they are applied.
+    filters: Vec<[NnFilter; 2]>,
+    predictor: Predictor,
+    buf: AudioBuffer<i32>,
+}
+
+impl ApeDecoder {
+    /// Calculate the CRC of the decoded samples of the frame as they are stored in a WAVE file.
+    fn calc_crc(&self) -> u32 {
+        let planes = self.buf.planes();
+        let planes = planes.planes();
+
+        let n_bytes = self.bits_per_sample / 8;
+
+        let mut crc = u32::MAX;
+
+        for i in 0..self.buf.frames() {
+            for plane in planes {
+                // 8-bit samples are unsigned.
+                let sample = if n_bytes == 1 { plane[i].wrapping_add(0x80) } else { plane[i] };
+
+                crc = crc32_update(crc, sample as u32, n_bytes);
+            }
+        }
+
+        (!crc) >> 1
+    }
+
+    fn decode_inner(&mut self, packet: &Packet) -> Result<()> {
+        let data = packet.buf();
+
+        let mut reader = BufReader::new(data);
+
+        // The frame CRC. If the most-significant bit is set, the frame flags follow.
+        let mut crc = reader.read_be_u32()?;
+
+        let flags = if crc & 0x8000_0000 != 0 {
+            crc &= 0x7fff_ffff;
+            reader.read_be_u32()?
+        }
+        else {
+            0
+        };
+
+        let n_frames = packet.dur as usize;
+
+        if n_frames > self.buf.capacity() {
+            return decode_error("ape: frame is too long");
+        }
+
+        self.buf.clear();
+        self.buf.render_reserved(Some(n_frames));
+
+        let n_channels = self.buf.spec().channels.count();
+
+        let is_mono = n_channels == 1 || flags & FRAME_FLAG_PSEUDO_STEREO != 0;
+
+        let silence = FRAME_FLAG_LEFT_SILENCE | FRAME_FLAG_RIGHT_SILENCE;
+
+        let is_silent = if is_mono { flags & silence != 0 } else { flags & silence == silence };
+
+        if is_silent {
+            for ch in 0..n_channels {
+                self.buf.chan_mut(ch).iter_mut().for_each(|s| *s = 0);
+            }
+        }
+        else {
+            // The first byte of the range coded data is ignored.
+            reader.ignore_bytes(1)?;
+
+            let mut entropy = EntropyDecoder::new(&data[reader.pos() as usize..], self.version);
+
+            // Each frame is decoded independently.
+            self.predictor.reset();
+
+            for filters in self.filters.iter_mut() {
+                filters[0].reset();
+                filters[1].reset();
+            }
+
+            if is_mono {
+                let y = self.buf.chan_mut(0);
+
+                let mut rice_y = RiceState::new();
+
+                for s in y.iter_mut() {
+                    *s = entropy.decode(&mut rice_y)?;
+                }
+
+                for filters in self.filters.iter_mut() {
+                    filters[0].decompress(y);
+                }
+
+                self.predictor.decompress_mono(y);
+
+                if n_channels == 2 {
+                    let (y, x) = self.buf.chan_pair_mut(0, 1);
+                    x.copy_from_slice(y);
+                }
+            }
+            else {
+                let (y, x) = self.buf.chan_pair_mut(0, 1);
+
+                let mut rice_y = RiceState::new();
+                let mut rice_x = RiceState::new();
+
+                // The residuals of both channels are interleaved. The order of each pair of
+                // residuals changed in version 3.95.
+                for (y, x) in y.iter_mut().zip(x.iter_mut()) {
+                    if self.version >= 3950 {
+                        *y = entropy.decode(&mut rice_y)?;
+                        *x = entropy.decode(&mut rice_x)?;
+                    }
+                    else {
+                        *x = entropy.decode(&mut rice_x)?;
+                        *y = entropy.decode(&mut rice_y)?;
+                    }
+                }
+
+                for filters in self.filters.iter_mut() {
+                    filters[0].decompress(y);
+                    filters[1].decompress(x);
+                }
+
+                self.predictor.decompress_stereo(y, x);
+
+                // Convert the Y and X channels to the left and right channels.
+                for (y, x) in y.iter_mut().zip(x.iter_mut()) {
+                    let left = x.wrapping_sub(*y / 2);
+                    let right = left.wrapping_add(*y);
+
+                    *y = left;
+                    *x = right;
+                }
+            }
+        }
+
+        if self.calc_crc() != crc {
+            return decode_error("ape: crc mismatch");
+        }
+
+        let shift = 32 - self.bits_per_sample;
+        self.buf.transform(|sample| sample << shift);
+
+        Ok(())
+    }
+}
+
+impl Decoder for ApeDecoder {
+    fn try_new(params: &CodecParameters, _options: &DecoderOptions) -> Result<Self> {
+        if params.codec != CODEC_TYPE_MONKEYS_AUDIO {
+            return unsupported_error("ape: invalid codec type");
+        }
+
+        // The extra data contains the file version, compression level, and format flags.
+        let (version, compression_level) = match params.extra_data.as_deref() {
+            Some(&[v0, v1, c0, c1, ..]) => {
+                (u16::from_le_bytes([v0, v1]), u16::from_le_bytes([c0, c1]))
+            }
+            _ => return unsupported_error("ape: missing extra data"),
+        };
+
+        if version < MIN_DECODER_VERSION {
+            return unsupported_error("ape: unsupported file version");
+        }
+
+        let filter_params = match filter_params(compression_level) {
+            Some(filter_params) => filter_params,
+            None => return unsupported_error("ape: unsupported compression level"),
+        };
+
+        let rate = match params.sample_rate {
+            Some(rate) => rate,
+            None => return unsupported_error("ape: sample rate is required"),
+        };
+
+        let channels = match params.channels {
+            Some(channels) if channels.count() <= 2 => channels,
+            Some(_) => return unsupported_error("ape: more than 2 channels are not supported"),
+            None => return unsupported_error("ape: channels are required"),
+        };
+
+        let bits_per_sample = match params.bits_per_sample {
+            Some(bits @ 8) | Some(bits @ 16) | Some(bits @ 24) => bits,
+            Some(_) => return unsupported_error("ape: unsupported bits per sample"),
+            None => return unsupported_error("ape: bits per sample is required"),
+        };
+
+        let max_frames = match params.max_frames_per_packet {
+            Some(max_frames) => max_frames,
+            None => return unsupported_error("ape: maximum frames per packet is required"),
+        };
+
+        let filters = filter_params
+            .iter()
+            .map(|&(order, shift)| {
+                [NnFilter::new(order, shift, version), NnFilter::new(order, shift, version)]
+            })
+            .collect();
+
+        Ok(ApeDecoder {
+            params: params.clone(),
+            version,
+            bits_per_sample,
+            filters,
+            predictor: Predictor::new(version),
+            buf: AudioBuffer::new(max_frames, SignalSpec::new(rate, channels)),
+        })
+    }
+
+    fn supported_codecs() -> &'static [CodecDescriptor] {
+        &[support_codec!(CODEC_TYPE_MONKEYS_AUDIO, "ape", "Monkey's Audio")]
+    }
+
+    fn reset(&mut self) {
+        // Frames are decoded independently, therefore do nothing.
+    }
+
+    fn codec_params(&self) -> &CodecParameters {
+        &self.params
+    }
+
+    fn decode(&mut self, packet: &Packet) -> Result<AudioBufferRef<'_>> {
+        if let Err(e) = self.decode_inner(packet) {
+            self.buf.clear();
+            Err(e)
+        }
+        else {
+            Ok(self.buf.as_audio_buffer_ref())
+        }
+    }
+
+    fn finalize(&mut self) -> FinalizeResult {
+        Default::default()
+    }
+
+    fn last_decoded(&self) -> AudioBufferRef<'_> {
+        self.buf.as_audio_buffer_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::audio::{AudioBufferRef, Channels, Signal};
+    use symphonia_core::codecs::{CodecParameters, Decoder, CODEC_TYPE_MONKEYS_AUDIO};
+    use symphonia_core::formats::Packet;
+
+    use super::ApeDecoder;
+    use crate::filter::{filter_params, NnFilter};
+    use crate::predictor::Predictor;
+
+    /// The cumulative frequencies of the overflow model used since version 3.98.
+    const COUNTS: [u32; 22] = [
+        0, 19578, 36160, 48417, 56323, 60899, 63265, 64435, 64971, 65232, 65351, 65416, 65447,
+        65466, 65476, 65482, 65485, 65488, 65490, 65491, 65492, 65493,
+    ];
+
+    /// A range encoder that is the counterpart of the range decoder.
+    struct RangeEncoder {
+        buf: Vec<u8>,
+        low: u32,
+        range: u32,
+        /// The last byte of the output that may still be changed by a carry.
+        buffer: u32,
+        /// The number of 0xff bytes that follow the buffered byte.
+        help: u32,
+    }
+
+    impl RangeEncoder {
+        fn new() -> Self {
+            RangeEncoder { buf: Vec::new(), low: 0, range: 1 << 31, buffer: 0, help: 0 }
+        }
+
+        fn push(&mut self, carry: bool) {
+            let (byte, follow) = if carry { (self.buffer + 1, 0x00) } else { (self.buffer, 0xff) };
+
+            self.buf.push(byte as u8);
+            self.buf.extend(std::iter::repeat(follow).take(self.help as usize));
+            self.help = 0;
+        }
+
+        fn normalize(&mut self) {
+            while self.range <= 1 << 23 {
+                if self.low < 0xff << 23 {
+                    self.push(false);
+                    self.buffer = self.low >> 23;
+                }
+                else if self.low & (1 << 31) != 0 {
+                    self.push(true);
+                    self.buffer = (self.low >> 23) & 0xff;
+                }
+                else {
+                    self.help += 1;
+                }
+
+                self.low = (self.low << 8) & ((1 << 31) - 1);
+                self.range <<= 8;
+            }
+        }
+
+        /// Encode a symbol with frequency `sy_f` and cumulative frequency `lt_f` given the total
+        /// frequency `tot_f`.
+        fn encode(&mut self, sy_f: u32, lt_f: u32, tot_f: u32) {
+            self.normalize();
+
+            let r = self.range / tot_f;
+
+            self.low += r * lt_f;
+            self.range = r * sy_f;
+        }
+
+        fn finish(mut self) -> Vec<u8> {
+            self.normalize();
+
+            let last = (self.low >> 23) + 1;
+
+            self.push(last > 0xff);
+            self.buf.extend_from_slice(&[last as u8, 0, 0, 0]);
+            self.buf
+        }
+    }
+
+    /// Encode a residual given the adaptive sum of the channel.
+    fn encode_residual(rc: &mut RangeEncoder, ksum: &mut u32, residual: i32) {
+        let x = if residual > 0 { 2 * residual - 1 } else { -2 * residual } as u32;
+
+        let pivot = (*ksum >> 5).max(1);
+
+        *ksum = *ksum + (x + 1) / 2 - ((*ksum + 16) >> 5);
+
+        let overflow = x / pivot;
+        let base = x % pivot;
+
+        match overflow {
+            0..=20 => {
+                let o = overflow as usize;
+                rc.encode(COUNTS[o + 1] - COUNTS[o], COUNTS[o], 1 << 16);
+            }
+            21..=62 => rc.encode(1, 65472 + overflow, 1 << 16),
+            _ => {
+                // The escape symbol, followed by the overflow in 32 bits.
+                rc.encode(1, 0xffff, 1 << 16);
+                rc.encode(1, overflow >> 16, 1 << 16);
+                rc.encode(1, overflow & 0xffff, 1 << 16);
+            }
+        }
+
+        if pivot < 1 << 16 {
+            rc.encode(1, base, pivot);
+        }
+        else {
+            // Large pivots are split into the most-significant 16 bits, and the remaining bits.
+            let split = 1 << (16 - pivot.leading_zeros());
+
+            rc.encode(1, base / split, pivot / split + 1);
+            rc.encode(1, base % split, split);
+        }
+    }
+
+    /// Find the input of a stage of the decoder given its desired output. The output of each
+    /// stage is its input plus a prediction that only depends on past inputs.
+    fn invert<S: Clone>(state: &mut S, output: i32, step: impl Fn(&mut S, i32) -> i32) -> i32 {
+        let prediction = step(&mut state.clone(), 0);
+        let input = output.wrapping_sub(prediction);
+
+        assert_eq!(step(state, input), output);
+
+        input
+    }
+
+    /// Encode the range coded data of a frame of version 3.99 that decodes to the given channels
+    /// of samples.
+    fn encode_frame(compression_level: u16, samples: &[Vec<i32>]) -> Vec<u8> {
+        let version = 3990;
+
+        // Convert the left and right channels into the Y and X channels.
+        let mut chans = match samples {
+            [mono] => vec![mono.clone()],
+            [left, right] => {
+                let y: Vec<i32> = left.iter().zip(right).map(|(&l, &r)| r - l).collect();
+                let x = left.iter().zip(&y).map(|(&l, &y)| l + y / 2).collect();
+                vec![y, x]
+            }
+            _ => unreachable!(),
+        };
+
+        // Undo the predictor.
+        let mut predictor = Predictor::new(version);
+
+        if let [y] = chans.as_mut_slice() {
+            for y in y.iter_mut() {
+                *y = invert(&mut predictor, *y, |p, y| {
+                    let mut y = [y];
+                    p.decompress_mono(&mut y);
+                    y[0]
+                });
+            }
+        }
+        else {
+            let (y, x) = chans.split_at_mut(1);
+
+            for (y, x) in y[0].iter_mut().zip(x[0].iter_mut()) {
+                // The prediction of the X channel depends on the Y channel of the same sample.
+                let mut p = predictor.clone();
+
+                *y = invert(&mut p, *y, |p, y| {
+                    let (mut y, mut x) = ([y], [0]);
+                    p.decompress_stereo(&mut y, &mut x);
+                    y[0]
+                });
+
+                let y_in = *y;
+
+                *x = invert(&mut predictor, *x, |p, x| {
+                    let (mut y, mut x) = ([y_in], [x]);
+                    p.decompress_stereo(&mut y, &mut x);
+                    x[0]
+                });
+            }
+        }
+
+        // Undo the NN filters in the reverse order they are applied.
+        for &(order, shift) in filter_params(compression_level).unwrap().iter().rev() {
+            for chan in chans.iter_mut() {
+                let mut filter = NnFilter::new(order, shift, version);
+
+                for s in chan.iter_mut() {
+                    *s = invert(&mut filter, *s, |f, s| {
+                        let mut s = [s];
+                        f.decompress(&mut s);
+                        s[0]
+                    });
+                }
+            }
+        }
+
+        // Code the interleaved residuals.
+        let mut rc = RangeEncoder::new();
+        let mut ksums = [(1 << 10) * 16; 2];
+
+        for i in 0..chans[0].len() {
+            for (chan, ksum) in chans.iter().zip(ksums.iter_mut()) {
+                encode_residual(&mut rc, ksum, chan[i]);
+            }
+        }
+
+        rc.finish()
+    }
+
+    /// Calculate the CRC-32 of the bytes.
+    fn crc32(bytes: impl Iterator<Item = u8>) -> u32 {
+        let mut crc = !0u32;
+
+        for byte in bytes {
+            crc ^= u32::from(byte);
+
+            for _ in 0..8 {
+                crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+            }
+        }
+
+        !crc
+    }
+
+    /// Calculate the CRC of the samples as they are stored in a WAVE file, as stored in the frame
+    /// header.
+    fn ape_crc(samples: &[Vec<i32>], bits_per_sample: u32) -> u32 {
+        let n_bytes = bits_per_sample as usize / 8;
+
+        let bytes = (0..samples[0].len()).flat_map(|i| {
+            samples.iter().flat_map(move |chan| {
+                // 8-bit samples are unsigned.
+                let sample = if bits_per_sample == 8 { chan[i] + 0x80 } else { chan[i] };
+
+                sample.to_le_bytes().to_vec().into_iter().take(n_bytes)
+            })
+        });
+
+        crc32(bytes) >> 1
+    }
+
+    /// Make a test signal of a triangle wave and noise in each channel.
+    fn make_signal(n_channels: usize, bits_per_sample: u32, len: usize) -> Vec<Vec<i32>> {
+        let amp = 1 << (bits_per_sample - 2);
+        let mut rng = 1u32;
+
+        (0..n_channels)
+            .map(|ch| {
+                let period = 200 - 50 * ch as i32;
+
+                (0..len as i32)
+                    .map(|i| {
+                        rng = rng.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+
+                        let t = i % period;
+                        let triangle = (if t < period / 2 { t } else { period - t }) - period / 4;
+                        let noise = (rng >> 24) as i32 - 128;
+
+                        triangle * amp / period + noise * (amp >> 10)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn make_decoder(
+        compression_level: u16,
+        channels: Channels,
+        bits_per_sample: u32,
+        max_frames: u64,
+    ) -> ApeDecoder {
+        let mut extra_data = 3990u16.to_le_bytes().to_vec();
+        extra_data.extend_from_slice(&compression_level.to_le_bytes());
+        extra_data.extend_from_slice(&[0, 0]);
+
+        let mut params = CodecParameters::new();
+
+        params
+            .for_codec(CODEC_TYPE_MONKEYS_AUDIO)
+            .with_sample_rate(44100)
+            .with_channels(channels)
+            .with_bits_per_sample(bits_per_sample)
+            .with_max_frames_per_packet(max_frames)
+            .with_extra_data(extra_data.into_boxed_slice());
+
+        ApeDecoder::try_new(&params, &Default::default()).unwrap()
+    }
+
+    /// Encode a frame of the samples, decode it, and check that the decoded samples match. Returns
+    /// the CRC-32 of the coded frame, and the CRC of the decoded samples.
+    fn verify_decode(
+        compression_level: u16,
+        bits_per_sample: u32,
+        samples: &[Vec<i32>],
+    ) -> (u32, u32) {
+        let channels = match samples.len() {
+            1 => Channels::FRONT_LEFT,
+            _ => Channels::FRONT_LEFT | Channels::FRONT_RIGHT,
+        };
+
+        let len = samples[0].len();
+
+        let mut decoder = make_decoder(compression_level, channels, bits_per_sample, len as u64);
+
+        let mut data = ape_crc(samples, bits_per_sample).to_be_bytes().to_vec();
+        data.extend(encode_frame(compression_level, samples));
+
+        let packet = Packet::new_from_slice(0, 0, len as u64, &data);
+
+        let decoded: Vec<Vec<i32>> = match decoder.decode(&packet).unwrap() {
+            AudioBufferRef::S32(buf) => {
+                assert_eq!(buf.frames(), len);
+
+                let shift = 32 - bits_per_sample;
+
+                (0..samples.len())
+                    .map(|ch| buf.chan(ch).iter().map(|&s| s >> shift).collect())
+                    .collect()
+            }
+            _ => panic!("unexpected sample format"),
+        };
+
+        assert_eq!(decoded, samples);
+
+        // A frame with a different CRC is rejected.
+        data[3] ^= 1;
+
+        assert!(decoder.decode(&Packet::new_from_slice(0, 0, len as u64, &data)).is_err());
+
+        (crc32(data[4..].iter().copied()), ape_crc(&decoded, bits_per_sample))
+    }
+
+    #[test]
+    fn verify_decode_fast() {
+        let samples = make_signal(2, 16, 1500);
+
+        assert_eq!(verify_decode(1000, 16, &samples), (3703022749, 2117198333));
+    }
+
+    #[test]
+    fn verify_decode_normal() {
+        let samples = make_signal(2, 16, 1500);
+
+        assert_eq!(verify_decode(2000, 16, &samples), (3172674171, 2117198333));
+
+        let samples = make_signal(1, 24, 1500);
+
+        assert_eq!(verify_decode(2000, 24, &samples), (682489894, 1205425192));
+    }
+
+    #[test]
+    fn verify_silent_frame() {
+        let mut decoder = make_decoder(2000, Channels::FRONT_LEFT | Channels::FRONT_RIGHT, 16, 16);
+
+        // A silent stereo frame of 4 blocks, with the frame flags following the frame CRC.
+        let data = vec![0xf6, 0x5d, 0xa5, 0xaa, 0, 0, 0, 0x3];
+
+        let packet = Packet::new_from_slice(0, 0, 4, &data);
+
+        match decoder.decode(&packet).unwrap() {
+            AudioBufferRef::S32(buf) => {
+                assert_eq!(buf.frames(), 4);
+                assert_eq!(buf.chan(0), &[0; 4]);
+                assert_eq!(buf.chan(1), &[0; 4]);
+            }
+            _ => panic!("unexpected sample format"),
+        }
+
+        // The CRC must match the decoded samples.
+        let data = vec![0xf6, 0x5d, 0xa5, 0xab, 0, 0, 0, 0x3];
+
+        assert!(decoder.decode(&Packet::new_from_slice(0, 0, 4, &data)).is_err());
+    }
+}
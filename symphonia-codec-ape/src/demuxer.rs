@@ -0,0 +1,423 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::io::{Seek, SeekFrom};
+
+use symphonia_core::audio::Channels;
+use symphonia_core::codecs::{CodecParameters, CODEC_TYPE_MONKEYS_AUDIO};
+use symphonia_core::errors::{end_of_stream_error, seek_error, unsupported_error};
+use symphonia_core::errors::{Result, SeekErrorKind};
+use symphonia_core::formats::prelude::*;
+use symphonia_core::io::*;
+use symphonia_core::meta::{Metadata, MetadataLog};
+use symphonia_core::probe::{Descriptor, Instantiate, QueryDescriptor};
+use symphonia_core::support_format;
+
+use symphonia_metadata::trailing::read_trailing_tags;
+
+use log::{debug, warn};
+
+use crate::header::*;
+
+/// Monkey's Audio native format reader.
+///
+/// `ApeReader` implements a demuxer for the native Monkey's Audio format.
+///
+/// Frames are stored as a stream of little-endian 32-bit words, and start on a byte boundary
+/// within a word. Each packet contains the bitstream of a frame in most-significant bit first
+/// byte order, starting at the first byte of the frame. The codec extra data contains the file
+/// version, compression level, and format flags, as 16-bit little-endian integers.
+pub struct ApeReader {
+    reader: MediaSourceStream,
+    tracks: Vec<Track>,
+    cues: Vec<Cue>,
+    metadata: MetadataLog,
+    blocks_per_frame: u32,
+    final_frame_blocks: u32,
+    /// The absolute position of each frame, followed by the end of the last frame.
+    frame_pos: Vec<u64>,
+    next_frame: usize,
+}
+
+impl QueryDescriptor for ApeReader {
+    fn query() -> &'static [Descriptor] {
+        &[support_format!(
+            "ape",
+            "Monkey's Audio",
+            &["ape"],
+            &["audio/ape", "audio/x-ape"],
+            &[b"MAC "]
+        )]
+    }
+
+    fn score(_context: &[u8]) -> u8 {
+        255
+    }
+}
+
+/// Convert a buffer of little-endian 32-bit words into a most-significant bit first bitstream.
+fn words_to_bitstream(buf: &mut [u8]) {
+    for word in buf.chunks_exact_mut(4) {
+        word.reverse();
+    }
+}
+
+impl FormatReader for ApeReader {
+    fn try_new(mut source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
+        // Positions in the file are relative to the start of the stream marker.
+        let start_pos = source.pos();
+
+        let marker = source.read_quad_bytes()?;
+
+        if marker != APE_STREAM_MARKER {
+            return unsupported_error("ape: missing mac stream marker");
+        }
+
+        let header = ApeHeader::read(&mut source)?;
+
+        let mut metadata: MetadataLog = Default::default();
+
+        // If the source is seekable, read the tags at the end of the stream, and then return to
+        // the frame data.
+        let data_end_pos = if source.is_seekable() {
+            let pos = source.pos();
+
+            let data_end_pos = match read_trailing_tags(&mut source, &mut metadata) {
+                Ok(data_end_pos) => data_end_pos,
+                Err(err) => {
+                    warn!("ape: failed to read tags: {}", err);
+                    None
+                }
+            };
+
+            source.seek(SeekFrom::Start(pos))?;
+
+            data_end_pos
+        }
+        else {
+            None
+        };
+
+        let num_frames = header.total_frames as usize;
+
+        let mut frame_pos: Vec<u64> =
+            header.seek_table[..num_frames].iter().map(|pos| start_pos + pos).collect();
+
+        // Frame positions must be increasing, and follow the header.
+        if frame_pos[0] < source.pos() || frame_pos.windows(2).any(|pos| pos[0] > pos[1]) {
+            return unsupported_error("ape: invalid seek table");
+        }
+
+        // The end of the last frame is given by the descriptor in newer files. Otherwise, the last
+        // frame extends to the first tag, or is assumed to be at most 4 bytes per block.
+        let last_frame_pos = frame_pos[num_frames - 1];
+
+        let frame_data_end = match (header.frame_data_end, data_end_pos) {
+            (Some(end), _) => start_pos + end,
+            (None, Some(end)) => end,
+            (None, None) => last_frame_pos + 4 * u64::from(header.final_frame_blocks),
+        };
+
+        if frame_data_end < last_frame_pos {
+            return unsupported_error("ape: invalid frame data length");
+        }
+
+        frame_pos.push(frame_data_end);
+
+        let channels = if header.channels <= 32 {
+            Channels::from_bits(u32::MAX >> (32 - header.channels))
+        }
+        else {
+            None
+        };
+
+        let channels = match channels {
+            Some(channels) => channels,
+            None => return unsupported_error("ape: unsupported number of channels"),
+        };
+
+        let mut extra_data = Vec::with_capacity(6);
+        extra_data.extend_from_slice(&header.version.to_le_bytes());
+        extra_data.extend_from_slice(&header.compression_level.to_le_bytes());
+        extra_data.extend_from_slice(&header.format_flags.to_le_bytes());
+
+        let mut params = CodecParameters::new();
+
+        params
+            .for_codec(CODEC_TYPE_MONKEYS_AUDIO)
+            .with_sample_rate(header.sample_rate)
+            .with_time_base(TimeBase::new(1, header.sample_rate))
+            .with_channels(channels)
+            .with_bits_per_sample(header.bits_per_sample)
+            .with_n_frames(header.total_blocks())
+            .with_max_frames_per_packet(u64::from(header.blocks_per_frame))
+            .with_extra_data(extra_data.into_boxed_slice());
+
+        Ok(ApeReader {
+            reader: source,
+            tracks: vec![Track::new(0, params)],
+            cues: Vec::new(),
+            metadata,
+            blocks_per_frame: header.blocks_per_frame,
+            final_frame_blocks: header.final_frame_blocks,
+            frame_pos,
+            next_frame: 0,
+        })
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        let num_frames = self.frame_pos.len() - 1;
+
+        if self.next_frame >= num_frames {
+            return end_of_stream_error();
+        }
+
+        let first_pos = self.frame_pos[0];
+        let start = self.frame_pos[self.next_frame];
+        let end = self.frame_pos[self.next_frame + 1];
+
+        // Read the whole words containing the frame. The first and last words may be shared with
+        // the previous and next frames.
+        let skip = ((start - first_pos) % 4) as usize;
+        let aligned_start = start - skip as u64;
+        let aligned_len = (end - aligned_start + 3) / 4 * 4;
+
+        let pos = self.reader.pos();
+
+        if aligned_start < pos && pos - aligned_start <= 4 {
+            self.reader.seek_buffered_rev((pos - aligned_start) as usize);
+        }
+        else if aligned_start > pos {
+            self.reader.ignore_bytes(aligned_start - pos)?;
+        }
+        else if aligned_start < pos {
+            self.reader.seek(SeekFrom::Start(aligned_start))?;
+        }
+
+        // The last word of the final frame may be truncated.
+        let mut buf = vec![0; aligned_len as usize];
+
+        if self.next_frame + 1 < num_frames {
+            self.reader.read_buf_exact(&mut buf)?;
+        }
+        else {
+            self.reader.read_buf_exact(&mut buf[..(end - aligned_start) as usize])?;
+        }
+
+        words_to_bitstream(&mut buf);
+
+        buf.truncate((end - aligned_start) as usize);
+        buf.drain(..skip);
+
+        let ts = self.next_frame as u64 * u64::from(self.blocks_per_frame);
+
+        let dur = if self.next_frame + 1 < num_frames {
+            self.blocks_per_frame
+        }
+        else {
+            self.final_frame_blocks
+        };
+
+        self.next_frame += 1;
+
+        Ok(Packet::new_from_boxed_slice(0, ts, u64::from(dur), buf.into_boxed_slice())
+            .with_pos(start))
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        let params = &self.tracks[0].codec_params;
+
+        let required_ts = match to {
+            // Frame timestamp given.
+            SeekTo::TimeStamp { ts, .. } => ts,
+            // Time value given, calculate frame timestamp from sample rate.
+            SeekTo::Time { time, .. } => {
+                // The sample rate is always known.
+                TimeBase::new(1, params.sample_rate.unwrap()).calc_timestamp(time)
+            }
+        };
+
+        if required_ts > params.n_frames.unwrap_or(0) {
+            return seek_error(SeekErrorKind::OutOfRange);
+        }
+
+        // The seek table contains the position of every frame.
+        let num_frames = self.frame_pos.len() - 1;
+
+        let frame = ((required_ts / u64::from(self.blocks_per_frame)) as usize).min(num_frames - 1);
+
+        debug!("seeking to frame_ts={} in frame {}", required_ts, frame);
+
+        // Frames are read from the start of their first word, so only unseekable sources need to
+        // be positioned here.
+        if !self.reader.is_seekable() {
+            let first_pos = self.frame_pos[0];
+            let start = self.frame_pos[frame];
+            let aligned_start = start - (start - first_pos) % 4;
+
+            let pos = self.reader.pos();
+
+            if aligned_start >= pos {
+                self.reader.ignore_bytes(aligned_start - pos)?;
+            }
+            else if pos - aligned_start > 4 {
+                return seek_error(SeekErrorKind::ForwardOnly);
+            }
+        }
+
+        self.next_frame = frame;
+
+        let actual_ts = frame as u64 * u64::from(self.blocks_per_frame);
+
+        Ok(SeekedTo { track_id: 0, actual_ts, required_ts })
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.reader
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use symphonia_core::codecs::CODEC_TYPE_MONKEYS_AUDIO;
+    use symphonia_core::errors::{Error, SeekErrorKind};
+    use symphonia_core::formats::{FormatReader, SeekMode, SeekTo};
+    use symphonia_core::io::{MediaSourceStream, ReadOnlySource};
+    use symphonia_core::units::Time;
+
+    use super::ApeReader;
+
+    /// Make a version 3.99 file with 3 frames of 20 bytes of frame data in total. The frames start
+    /// at offsets 0, 6, and 13 of the frame data.
+    fn make_ape() -> Vec<u8> {
+        const FRAME_DATA_START: u32 = 52 + 24 + 12;
+
+        // Descriptor.
+        let mut ape = b"MAC ".to_vec();
+        ape.extend_from_slice(&3990u16.to_le_bytes());
+        ape.extend_from_slice(&0u16.to_le_bytes());
+        for len in &[52u32, 24, 12, 0, 20, 0, 0] {
+            ape.extend_from_slice(&len.to_le_bytes());
+        }
+        ape.extend_from_slice(&[0; 16]);
+
+        // Header: 2000 compression level, 16-bit stereo at 44.1kHz, with 1000 blocks per frame,
+        // and 500 blocks in the final frame.
+        ape.extend_from_slice(&2000u16.to_le_bytes());
+        ape.extend_from_slice(&0u16.to_le_bytes());
+        for value in &[1000u32, 500, 3] {
+            ape.extend_from_slice(&value.to_le_bytes());
+        }
+        ape.extend_from_slice(&16u16.to_le_bytes());
+        ape.extend_from_slice(&2u16.to_le_bytes());
+        ape.extend_from_slice(&44100u32.to_le_bytes());
+
+        // Seek table.
+        for offset in &[0u32, 6, 13] {
+            ape.extend_from_slice(&(FRAME_DATA_START + offset).to_le_bytes());
+        }
+
+        // Frame data.
+        ape.extend(0..20);
+        ape
+    }
+
+    #[test]
+    fn verify_ape_reader() {
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(make_ape())), Default::default());
+        let mut reader = ApeReader::try_new(mss, &Default::default()).unwrap();
+
+        let params = &reader.tracks()[0].codec_params;
+        assert_eq!(params.codec, CODEC_TYPE_MONKEYS_AUDIO);
+        assert_eq!(params.sample_rate, Some(44100));
+        assert_eq!(params.n_frames, Some(2500));
+        assert_eq!(params.extra_data.as_deref(), Some(&[0x96, 0x0f, 0xd0, 0x07, 0, 0][..]));
+
+        // The frame data is a stream of little-endian words, while packets are big-endian.
+        let expected: [&[u8]; 3] =
+            [&[3, 2, 1, 0, 7, 6], &[5, 4, 11, 10, 9, 8, 15], &[14, 13, 12, 19, 18, 17, 16]];
+
+        for (i, data) in expected.iter().enumerate() {
+            let packet = reader.next_packet().unwrap();
+            assert_eq!(packet.ts, 1000 * i as u64);
+            assert_eq!(packet.dur, if i < 2 { 1000 } else { 500 });
+            assert_eq!(&packet.data[..], *data);
+        }
+        assert!(reader.next_packet().is_err());
+
+        let seeked = reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 1500, track_id: 0 });
+        assert_eq!(seeked.unwrap().actual_ts, 1000);
+        assert_eq!(&reader.next_packet().unwrap().data[..], expected[1]);
+    }
+
+    #[test]
+    fn verify_ape_reader_seek() {
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(make_ape())), Default::default());
+        let mut reader = ApeReader::try_new(mss, &Default::default()).unwrap();
+
+        // Seek to the frame containing the timestamp, in any order.
+        for &(ts, frame) in &[(2499, 2), (0, 0), (1999, 1), (2000, 2), (999, 0), (1000, 1)] {
+            let seeked = reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts, track_id: 0 });
+            let seeked = seeked.unwrap();
+
+            assert_eq!(seeked.required_ts, ts);
+            assert_eq!(seeked.actual_ts, 1000 * frame);
+
+            let packet = reader.next_packet().unwrap();
+            assert_eq!(packet.ts, 1000 * frame);
+            assert_eq!(packet.pos(), Some(88 + [0, 6, 13][frame as usize]));
+        }
+
+        // Seek to a time, in seconds.
+        let time = Time::new(0, 1500.0 / 44100.0);
+        let seeked = reader.seek(SeekMode::Accurate, SeekTo::Time { time, track_id: None });
+        assert_eq!(seeked.unwrap().actual_ts, 1000);
+
+        // The end of the stream is the start of the final frame, and the stream ends after it.
+        let seeked = reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 2500, track_id: 0 });
+        assert_eq!(seeked.unwrap().actual_ts, 2000);
+        assert_eq!(reader.next_packet().unwrap().ts, 2000);
+        assert!(reader.next_packet().is_err());
+
+        let seeked = reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 2501, track_id: 0 });
+        assert!(matches!(seeked, Err(Error::SeekError(SeekErrorKind::OutOfRange))));
+    }
+
+    #[test]
+    fn verify_ape_reader_seek_unseekable() {
+        let source = ReadOnlySource::new(Cursor::new(make_ape()));
+        let mss = MediaSourceStream::new(Box::new(source), Default::default());
+        let mut reader = ApeReader::try_new(mss, &Default::default()).unwrap();
+
+        // Seeking forward skips to the frame.
+        let seeked = reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 1000, track_id: 0 });
+        assert_eq!(seeked.unwrap().actual_ts, 1000);
+        assert_eq!(&reader.next_packet().unwrap().data[..], &[5, 4, 11, 10, 9, 8, 15]);
+
+        // Seeking backward to the frame sharing the last word read is possible, but not further.
+        let seeked = reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 2000, track_id: 0 });
+        assert_eq!(seeked.unwrap().actual_ts, 2000);
+        assert_eq!(&reader.next_packet().unwrap().data[..], &[14, 13, 12, 19, 18, 17, 16]);
+
+        let seeked = reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 0, track_id: 0 });
+        assert!(matches!(seeked, Err(Error::SeekError(SeekErrorKind::ForwardOnly))));
+    }
+}
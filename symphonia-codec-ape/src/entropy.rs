@@ -0,0 +1,255 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The Monkey's Audio range decoder and residual entropy decoder.
+
+use symphonia_core::errors::{decode_error, Result};
+
+/// The number of bits in the range decoder state.
+const CODE_BITS: u32 = 32;
+
+/// The top of the range.
+const TOP_VALUE: u32 = 1 << (CODE_BITS - 1);
+
+/// The number of bits in the first partial byte.
+const EXTRA_BITS: u32 = (CODE_BITS - 2) % 8 + 1;
+
+/// The bottom of the range. The range is renormalized when it falls to or below this value.
+const BOTTOM_VALUE: u32 = TOP_VALUE >> 8;
+
+/// The number of symbols of the overflow model. The last symbol is an escape.
+const MODEL_ELEMENTS: u32 = 64;
+
+/// The cumulative frequencies of the overflow model used before version 3.98.
+const COUNTS_3970: [u32; 22] = [
+    0, 14824, 28224, 39348, 47855, 53994, 58171, 60926, 62682, 63786, 64463, 64878, 65126, 65276,
+    65365, 65419, 65450, 65469, 65480, 65487, 65491, 65493,
+];
+
+/// The cumulative frequencies of the overflow model used since version 3.98.
+const COUNTS_3980: [u32; 22] = [
+    0, 19578, 36160, 48417, 56323, 60899, 63265, 64435, 64971, 65232, 65351, 65416, 65447, 65466,
+    65476, 65482, 65485, 65488, 65490, 65491, 65492, 65493,
+];
+
+/// The range decoder.
+struct RangeDecoder<'a> {
+    buf: &'a [u8],
+    pos: usize,
+    low: u32,
+    range: u32,
+    /// The scale of the symbol frequencies last decoded.
+    help: u32,
+    /// The last bytes read. The least-significant bit of the last byte read has not been
+    /// consumed.
+    buffer: u32,
+}
+
+impl<'a> RangeDecoder<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        let mut rc =
+            RangeDecoder { buf, pos: 0, low: 0, range: 1 << EXTRA_BITS, help: 0, buffer: 0 };
+
+        rc.buffer = rc.read_byte();
+        rc.low = rc.buffer >> (8 - EXTRA_BITS);
+        rc
+    }
+
+    /// Read the next byte. The range decoder reads ahead of the last symbol, therefore reads past
+    /// the end of the frame return 0.
+    fn read_byte(&mut self) -> u32 {
+        match self.buf.get(self.pos) {
+            Some(&byte) => {
+                self.pos += 1;
+                u32::from(byte)
+            }
+            None => 0,
+        }
+    }
+
+    fn normalize(&mut self) {
+        while self.range <= BOTTOM_VALUE {
+            self.buffer = (self.buffer << 8) | self.read_byte();
+            self.low = (self.low << 8) | ((self.buffer >> 1) & 0xff);
+            self.range <<= 8;
+        }
+    }
+
+    /// Decode the cumulative frequency of the next symbol given the total frequency `tot_f`. The
+    /// symbol must then be consumed by calling `update`.
+    fn decode_culfreq(&mut self, tot_f: u32) -> u32 {
+        self.normalize();
+        self.help = self.range / tot_f;
+        self.low / self.help
+    }
+
+    /// Like `decode_culfreq`, but for a total frequency of `1 << shift`.
+    fn decode_culshift(&mut self, shift: u32) -> u32 {
+        self.normalize();
+        self.help = self.range >> shift;
+        self.low / self.help
+    }
+
+    /// Consume the symbol with the frequency `sy_f` and cumulative frequency `lt_f`.
+    fn update(&mut self, sy_f: u32, lt_f: u32) {
+        self.low = self.low.wrapping_sub(self.help.wrapping_mul(lt_f));
+        self.range = self.help.wrapping_mul(sy_f);
+    }
+
+    /// Decode `n` bits with equal probability.
+    fn decode_bits(&mut self, n: u32) -> u32 {
+        let sym = self.decode_culshift(n);
+        self.update(1, sym);
+        sym
+    }
+
+    /// Decode a symbol of the overflow model given its cumulative frequencies.
+    fn decode_overflow(&mut self, counts: &[u32; 22]) -> Result<u32> {
+        let cf = self.decode_culshift(16);
+
+        // Symbols past the end of the model are escaped with a frequency of 1.
+        if cf >= counts[21] {
+            if cf > 0xffff {
+                return decode_error("ape: invalid overflow symbol");
+            }
+
+            self.update(1, cf);
+            return Ok(cf + MODEL_ELEMENTS - 1 - 0xffff);
+        }
+
+        let symbol = counts[1..].iter().position(|&count| count > cf).unwrap_or(20);
+
+        self.update(counts[symbol + 1] - counts[symbol], counts[symbol]);
+        Ok(symbol as u32)
+    }
+}
+
+/// The adaptive Rice parameter state of a channel.
+pub struct RiceState {
+    k: u32,
+    ksum: u32,
+}
+
+impl RiceState {
+    pub fn new() -> Self {
+        RiceState { k: 10, ksum: (1 << 10) * 16 }
+    }
+
+    fn update(&mut self, x: u32) {
+        let lim = if self.k > 0 { 1 << (self.k + 4) } else { 0 };
+
+        self.ksum = self
+            .ksum
+            .wrapping_add(x.wrapping_add(1) / 2)
+            .wrapping_sub(self.ksum.wrapping_add(16) >> 5);
+
+        if self.ksum < lim {
+            self.k -= 1;
+        }
+        else if self.ksum >= (1 << (self.k + 5)) && self.k < 24 {
+            self.k += 1;
+        }
+    }
+}
+
+/// Convert an unsigned residual into a signed residual.
+#[inline(always)]
+fn to_signed(x: u32) -> i32 {
+    if x & 1 != 0 {
+        (x >> 1) as i32 + 1
+    }
+    else {
+        -((x >> 1) as i32)
+    }
+}
+
+/// The entropy decoder of the residuals of a frame.
+pub struct EntropyDecoder<'a> {
+    rc: RangeDecoder<'a>,
+    version: u16,
+}
+
+impl<'a> EntropyDecoder<'a> {
+    /// Instantiate an entropy decoder for a file of the given version over the range coded data
+    /// of a frame.
+    pub fn new(buf: &'a [u8], version: u16) -> Self {
+        EntropyDecoder { rc: RangeDecoder::new(buf), version }
+    }
+
+    /// Decode the next residual of the channel with the given Rice state.
+    pub fn decode(&mut self, rice: &mut RiceState) -> Result<i32> {
+        let x = if self.version >= 3990 {
+            self.decode_value_3990(rice)?
+        }
+        else {
+            self.decode_value_3900(rice)?
+        };
+
+        rice.update(x);
+
+        Ok(to_signed(x))
+    }
+
+    fn decode_value_3900(&mut self, rice: &RiceState) -> Result<u32> {
+        let mut overflow = self.rc.decode_overflow(&COUNTS_3970)?;
+
+        // The escape symbol is followed by the number of low-order bits.
+        let k = if overflow == MODEL_ELEMENTS - 1 {
+            overflow = 0;
+            self.rc.decode_bits(5)
+        }
+        else {
+            rice.k.saturating_sub(1)
+        };
+
+        let x = if k <= 16 || self.version < 3910 {
+            if k > 23 {
+                return decode_error("ape: too many residual bits");
+            }
+
+            self.rc.decode_bits(k)
+        }
+        else {
+            let lo = self.rc.decode_bits(16);
+            lo | (self.rc.decode_bits(k - 16) << 16)
+        };
+
+        Ok(x.wrapping_add(overflow << k))
+    }
+
+    fn decode_value_3990(&mut self, rice: &RiceState) -> Result<u32> {
+        let pivot = (rice.ksum >> 5).max(1);
+
+        let mut overflow = self.rc.decode_overflow(&COUNTS_3980)?;
+
+        // The escape symbol is followed by the overflow coded in 32 bits.
+        if overflow == MODEL_ELEMENTS - 1 {
+            overflow = self.rc.decode_bits(16) << 16;
+            overflow |= self.rc.decode_bits(16);
+        }
+
+        let base = if pivot < 0x10000 {
+            let base = self.rc.decode_culfreq(pivot);
+            self.rc.update(1, base);
+            base
+        }
+        else {
+            // Only the most-significant 16 bits of the pivot are coded with a single symbol.
+            let n_bits = 16 - pivot.leading_zeros();
+
+            let base_hi = self.rc.decode_culfreq((pivot >> n_bits) + 1);
+            self.rc.update(1, base_hi);
+
+            let base_lo = self.rc.decode_culfreq(1 << n_bits);
+            self.rc.update(1, base_lo);
+
+            (base_hi << n_bits).wrapping_add(base_lo)
+        };
+
+        Ok(base.wrapping_add(overflow.wrapping_mul(pivot)))
+    }
+}
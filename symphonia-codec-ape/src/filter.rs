@@ -0,0 +1,161 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The adaptive neural network (NN) filters applied before the predictor.
+
+/// The number of samples filtered before the history buffers are moved back to their start.
+const WINDOW_LEN: usize = 512;
+
+/// The orders and number of fractional bits of the NN filters of each compression level, in the
+/// order they are applied.
+const FILTER_PARAMS: [&[(usize, u32)]; 5] = [
+    // Fast.
+    &[],
+    // Normal.
+    &[(16, 11)],
+    // High.
+    &[(64, 11)],
+    // Extra high.
+    &[(32, 10), (256, 13)],
+    // Insane.
+    &[(16, 11), (256, 13), (1280, 15)],
+];
+
+/// Get the orders and number of fractional bits of the NN filters for a compression level, or
+/// `None` if the compression level is unknown.
+pub fn filter_params(compression_level: u16) -> Option<&'static [(usize, u32)]> {
+    match compression_level {
+        1000 | 2000 | 3000 | 4000 | 5000 => {
+            Some(FILTER_PARAMS[usize::from(compression_level / 1000 - 1)])
+        }
+        _ => None,
+    }
+}
+
+/// An adaptive NN filter of a single channel.
+#[derive(Clone)]
+pub struct NnFilter {
+    order: usize,
+    shift: u32,
+    version: u16,
+    coeffs: Vec<i16>,
+    /// The saturated past outputs of the filter.
+    input: Vec<i16>,
+    /// The adaption values of the past outputs of the filter.
+    adapt: Vec<i16>,
+    /// The position of the next output in the history buffers.
+    pos: usize,
+    /// The running average of the magnitude of the output.
+    avg: i32,
+}
+
+impl NnFilter {
+    pub fn new(order: usize, shift: u32, version: u16) -> Self {
+        NnFilter {
+            order,
+            shift,
+            version,
+            coeffs: vec![0; order],
+            input: vec![0; order + WINDOW_LEN],
+            adapt: vec![0; order + WINDOW_LEN],
+            pos: order,
+            avg: 0,
+        }
+    }
+
+    /// Reset the filter to its initial state.
+    pub fn reset(&mut self) {
+        self.coeffs.iter_mut().for_each(|c| *c = 0);
+        self.input[..self.order].iter_mut().for_each(|s| *s = 0);
+        self.adapt[..self.order].iter_mut().for_each(|s| *s = 0);
+        self.pos = self.order;
+        self.avg = 0;
+    }
+
+    /// Undo the filter on the samples of a channel.
+    pub fn decompress(&mut self, buf: &mut [i32]) {
+        let order = self.order;
+
+        for s in buf.iter_mut() {
+            let input = &self.input[self.pos - order..self.pos];
+            let adapt = &self.adapt[self.pos - order..self.pos];
+
+            // Predict with the current coefficients, and then adapt the coefficients towards the
+            // sign of the residual.
+            let mut dot = 0i32;
+
+            for ((c, &i), &a) in self.coeffs.iter_mut().zip(input).zip(adapt) {
+                dot = dot.wrapping_add(i32::from(*c) * i32::from(i));
+
+                if *s > 0 {
+                    *c = c.wrapping_sub(a);
+                }
+                else if *s < 0 {
+                    *c = c.wrapping_add(a);
+                }
+            }
+
+            // Round the fixed-point prediction.
+            let prediction = (i64::from(dot) + (1 << (self.shift - 1))) >> self.shift;
+
+            let out = s.wrapping_add(prediction as i32);
+
+            self.input[self.pos] = out.max(i32::from(i16::MIN)).min(i32::from(i16::MAX)) as i16;
+
+            if self.version >= 3980 {
+                let abs = i64::from(out).abs();
+                let avg = i64::from(self.avg);
+
+                let mag = if abs > 3 * avg {
+                    32
+                }
+                else if abs > (4 * avg) / 3 {
+                    16
+                }
+                else if abs > 0 {
+                    8
+                }
+                else {
+                    0
+                };
+
+                self.adapt[self.pos] = if out > 0 { -mag } else { mag };
+
+                self.avg += ((abs - avg) / 16) as i32;
+
+                self.adapt[self.pos - 1] >>= 1;
+                self.adapt[self.pos - 2] >>= 1;
+                self.adapt[self.pos - 8] >>= 1;
+            }
+            else {
+                self.adapt[self.pos] = if out > 0 {
+                    -4
+                }
+                else if out < 0 {
+                    4
+                }
+                else {
+                    0
+                };
+
+                self.adapt[self.pos - 4] >>= 1;
+                self.adapt[self.pos - 8] >>= 1;
+            }
+
+            *s = out;
+
+            self.pos += 1;
+
+            // Move the most recent history back to the start of the buffers when they are full.
+            if self.pos == self.input.len() {
+                self.input.copy_within(WINDOW_LEN.., 0);
+                self.adapt.copy_within(WINDOW_LEN.., 0);
+                self.pos = order;
+            }
+        }
+    }
+}
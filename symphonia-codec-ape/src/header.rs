@@ -0,0 +1,410 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::errors::{decode_error, unsupported_error, Result};
+use symphonia_core::io::ReadBytes;
+
+/// The stream marker of a Monkey's Audio file.
+pub const APE_STREAM_MARKER: [u8; 4] = *b"MAC ";
+
+/// The minimum, and maximum, supported file versions. Files older than version 3.81 store the
+/// bit offset of each frame in a separate table, and are not supported.
+const MIN_FILE_VERSION: u16 = 3810;
+const MAX_FILE_VERSION: u16 = 3990;
+
+/// Files from version 3.98 start with a descriptor followed by the header.
+const DESCRIPTOR_FILE_VERSION: u16 = 3980;
+
+/// The "extra high" compression level.
+const COMPRESSION_LEVEL_EXTRA_HIGH: u16 = 4000;
+
+/// Format flag: the samples are 8-bit.
+const FORMAT_FLAG_8_BIT: u16 = 0x1;
+/// Format flag: a peak level follows the header.
+const FORMAT_FLAG_HAS_PEAK_LEVEL: u16 = 0x4;
+/// Format flag: the samples are 24-bit.
+const FORMAT_FLAG_24_BIT: u16 = 0x8;
+/// Format flag: the number of seek table entries follows the header.
+const FORMAT_FLAG_HAS_SEEK_ELEMENTS: u16 = 0x10;
+/// Format flag: the WAVE header is not stored, and should be created by the decoder.
+const FORMAT_FLAG_CREATE_WAV_HEADER: u16 = 0x20;
+
+/// The parsed descriptor and header of a Monkey's Audio file.
+#[derive(Debug)]
+pub struct ApeHeader {
+    /// The file version, multiplied by 1000.
+    pub version: u16,
+    /// The compression level.
+    pub compression_level: u16,
+    /// The format flags.
+    pub format_flags: u16,
+    /// The number of audio frames (blocks) in a frame, except the final frame.
+    pub blocks_per_frame: u32,
+    /// The number of audio frames (blocks) in the final frame.
+    pub final_frame_blocks: u32,
+    /// The total number of frames.
+    pub total_frames: u32,
+    /// The number of bits per sample.
+    pub bits_per_sample: u32,
+    /// The number of channels.
+    pub channels: u32,
+    /// The sample rate in Hz.
+    pub sample_rate: u32,
+    /// The positions of each frame in the file, relative to the start of the stream marker.
+    pub seek_table: Vec<u64>,
+    /// The position of the end of the frame data relative to the start of the stream marker, if
+    /// known.
+    pub frame_data_end: Option<u64>,
+}
+
+impl ApeHeader {
+    /// Read the descriptor and header, and seek table of a Monkey's Audio file. The reader must be
+    /// positioned after the stream marker.
+    pub fn read<B: ReadBytes>(reader: &mut B) -> Result<ApeHeader> {
+        let version = reader.read_u16()?;
+
+        if version < MIN_FILE_VERSION || version > MAX_FILE_VERSION {
+            return unsupported_error("ape: unsupported file version");
+        }
+
+        let header = if version >= DESCRIPTOR_FILE_VERSION {
+            read_header(reader, version)?
+        }
+        else {
+            read_old_header(reader, version)?
+        };
+
+        if header.total_frames == 0 || header.blocks_per_frame == 0 {
+            return decode_error("ape: no frames");
+        }
+
+        if header.final_frame_blocks == 0 || header.final_frame_blocks > header.blocks_per_frame {
+            return decode_error("ape: invalid final frame length");
+        }
+
+        if header.channels == 0 {
+            return decode_error("ape: invalid number of channels");
+        }
+
+        if header.sample_rate == 0 {
+            return decode_error("ape: invalid sample rate");
+        }
+
+        if (header.seek_table.len() as u64) < u64::from(header.total_frames) {
+            return decode_error("ape: seek table is too short");
+        }
+
+        Ok(header)
+    }
+
+    /// Gets the total number of audio frames (blocks) in the stream.
+    pub fn total_blocks(&self) -> u64 {
+        u64::from(self.total_frames - 1) * u64::from(self.blocks_per_frame)
+            + u64::from(self.final_frame_blocks)
+    }
+}
+
+/// Read the seek table. Entries are 32-bit and wrap around in files larger than 4 GiB.
+fn read_seek_table<B: ReadBytes>(reader: &mut B, num_entries: u32) -> Result<Vec<u64>> {
+    let mut seek_table = Vec::new();
+
+    let mut base = 0;
+    let mut prev = 0;
+
+    for _ in 0..num_entries {
+        let entry = u64::from(reader.read_u32()?);
+
+        if entry < prev {
+            base += 1 << 32;
+        }
+
+        prev = entry;
+        seek_table.push(base + entry);
+    }
+
+    Ok(seek_table)
+}
+
+/// Read the descriptor and header used since version 3.98.
+fn read_header<B: ReadBytes>(reader: &mut B, version: u16) -> Result<ApeHeader> {
+    // Descriptor.
+    let _padding = reader.read_u16()?;
+    let descriptor_len = reader.read_u32()?;
+    let header_len = reader.read_u32()?;
+    let seek_table_len = reader.read_u32()?;
+    let wav_header_len = reader.read_u32()?;
+    let frame_data_len = u64::from(reader.read_u32()?);
+    let frame_data_len_high = u64::from(reader.read_u32()?);
+    let _terminating_data_len = reader.read_u32()?;
+    let mut _md5 = [0; 16];
+    reader.read_buf_exact(&mut _md5)?;
+
+    // The descriptor read so far is 52 bytes long, including the stream marker and version.
+    if descriptor_len < 52 || header_len < 24 {
+        return decode_error("ape: invalid descriptor");
+    }
+
+    reader.ignore_bytes(u64::from(descriptor_len) - 52)?;
+
+    // Header.
+    let compression_level = reader.read_u16()?;
+    let format_flags = reader.read_u16()?;
+    let blocks_per_frame = reader.read_u32()?;
+    let final_frame_blocks = reader.read_u32()?;
+    let total_frames = reader.read_u32()?;
+    let bits_per_sample = u32::from(reader.read_u16()?);
+    let channels = u32::from(reader.read_u16()?);
+    let sample_rate = reader.read_u32()?;
+
+    reader.ignore_bytes(u64::from(header_len) - 24)?;
+
+    let seek_table = read_seek_table(reader, seek_table_len / 4)?;
+
+    // The WAVE header precedes the frame data.
+    reader.ignore_bytes(u64::from(wav_header_len))?;
+
+    let frame_data_start = u64::from(descriptor_len)
+        + u64::from(header_len)
+        + u64::from(seek_table_len)
+        + u64::from(wav_header_len);
+
+    let frame_data_end = frame_data_start + ((frame_data_len_high << 32) | frame_data_len);
+
+    Ok(ApeHeader {
+        version,
+        compression_level,
+        format_flags,
+        blocks_per_frame,
+        final_frame_blocks,
+        total_frames,
+        bits_per_sample,
+        channels,
+        sample_rate,
+        seek_table,
+        frame_data_end: Some(frame_data_end),
+    })
+}
+
+/// Read the header used before version 3.98.
+fn read_old_header<B: ReadBytes>(reader: &mut B, version: u16) -> Result<ApeHeader> {
+    let compression_level = reader.read_u16()?;
+    let format_flags = reader.read_u16()?;
+    let channels = u32::from(reader.read_u16()?);
+    let sample_rate = reader.read_u32()?;
+    let wav_header_len = reader.read_u32()?;
+    let _terminating_data_len = reader.read_u32()?;
+    let total_frames = reader.read_u32()?;
+    let final_frame_blocks = reader.read_u32()?;
+
+    if format_flags & FORMAT_FLAG_HAS_PEAK_LEVEL != 0 {
+        let _peak_level = reader.read_u32()?;
+    }
+
+    let num_seek_entries = if format_flags & FORMAT_FLAG_HAS_SEEK_ELEMENTS != 0 {
+        reader.read_u32()?
+    }
+    else {
+        total_frames
+    };
+
+    if format_flags & FORMAT_FLAG_CREATE_WAV_HEADER == 0 {
+        reader.ignore_bytes(u64::from(wav_header_len))?;
+    }
+
+    let seek_table = read_seek_table(reader, num_seek_entries)?;
+
+    let bits_per_sample = if format_flags & FORMAT_FLAG_8_BIT != 0 {
+        8
+    }
+    else if format_flags & FORMAT_FLAG_24_BIT != 0 {
+        24
+    }
+    else {
+        16
+    };
+
+    // The number of blocks per frame is implied by the version and compression level.
+    let blocks_per_frame = if version >= 3950 {
+        73728 * 4
+    }
+    else if version >= 3900
+        || (version >= 3800 && compression_level == COMPRESSION_LEVEL_EXTRA_HIGH)
+    {
+        73728
+    }
+    else {
+        9216
+    };
+
+    Ok(ApeHeader {
+        version,
+        compression_level,
+        format_flags,
+        blocks_per_frame,
+        final_frame_blocks,
+        total_frames,
+        bits_per_sample,
+        channels,
+        sample_rate,
+        seek_table,
+        frame_data_end: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::io::{BufReader, ReadBytes};
+
+    use super::ApeHeader;
+
+    fn push_u16(buf: &mut Vec<u8>, value: u16) {
+        buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn push_u32(buf: &mut Vec<u8>, value: u32) {
+        buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    /// Make the descriptor and header of a version 3.99 file, following the stream marker, with
+    /// the given number of blocks per frame, blocks in the final frame, and seek table.
+    fn make_header(blocks_per_frame: u32, final_frame_blocks: u32, seek_table: &[u32]) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        // Descriptor, with 4 bytes of unknown descriptor fields, and a 44 byte WAVE header.
+        push_u16(&mut buf, 3990);
+        push_u16(&mut buf, 0);
+        push_u32(&mut buf, 56);
+        push_u32(&mut buf, 28);
+        push_u32(&mut buf, 4 * seek_table.len() as u32);
+        push_u32(&mut buf, 44);
+        push_u32(&mut buf, 0x1234);
+        push_u32(&mut buf, 0x1);
+        push_u32(&mut buf, 0);
+        buf.extend_from_slice(&[0; 16 + 4]);
+
+        // Header, with 4 bytes of unknown header fields.
+        push_u16(&mut buf, 3000);
+        push_u16(&mut buf, 0);
+        push_u32(&mut buf, blocks_per_frame);
+        push_u32(&mut buf, final_frame_blocks);
+        push_u32(&mut buf, seek_table.len() as u32);
+        push_u16(&mut buf, 24);
+        push_u16(&mut buf, 6);
+        push_u32(&mut buf, 96000);
+        buf.extend_from_slice(&[0; 4]);
+
+        for &entry in seek_table {
+            push_u32(&mut buf, entry);
+        }
+
+        buf.extend_from_slice(&[0; 44]);
+        buf
+    }
+
+    #[test]
+    fn verify_read_header() {
+        let buf = make_header(73728, 100, &[172, 1000, 5000]);
+
+        let mut reader = BufReader::new(&buf);
+        let header = ApeHeader::read(&mut reader).unwrap();
+
+        // The reader is positioned at the frame data.
+        assert_eq!(reader.pos(), buf.len() as u64);
+
+        assert_eq!(header.version, 3990);
+        assert_eq!(header.compression_level, 3000);
+        assert_eq!(header.blocks_per_frame, 73728);
+        assert_eq!(header.final_frame_blocks, 100);
+        assert_eq!(header.total_frames, 3);
+        assert_eq!(header.bits_per_sample, 24);
+        assert_eq!(header.channels, 6);
+        assert_eq!(header.sample_rate, 96000);
+        assert_eq!(header.seek_table, [172, 1000, 5000]);
+        assert_eq!(header.total_blocks(), 2 * 73728 + 100);
+
+        // The frame data follows the WAVE header, and is 0x1_0000_1234 bytes long.
+        assert_eq!(header.frame_data_end, Some(56 + 28 + 12 + 44 + 0x1_0000_1234));
+    }
+
+    #[test]
+    fn verify_read_old_header() {
+        let mut buf = Vec::new();
+
+        // A version 3.97 header of 24-bit mono audio, with a peak level, a seek table of 2
+        // entries, and an 8 byte WAVE header.
+        push_u16(&mut buf, 3970);
+        push_u16(&mut buf, 4000);
+        push_u16(&mut buf, 0x4 | 0x8 | 0x10);
+        push_u16(&mut buf, 1);
+        push_u32(&mut buf, 22050);
+        push_u32(&mut buf, 8);
+        push_u32(&mut buf, 0);
+        push_u32(&mut buf, 2);
+        push_u32(&mut buf, 1000);
+        push_u32(&mut buf, 0x7fff);
+        push_u32(&mut buf, 2);
+        buf.extend_from_slice(&[0xff; 8]);
+        push_u32(&mut buf, 48);
+        push_u32(&mut buf, 4000);
+
+        let mut reader = BufReader::new(&buf);
+        let header = ApeHeader::read(&mut reader).unwrap();
+
+        assert_eq!(reader.pos(), buf.len() as u64);
+
+        assert_eq!(header.version, 3970);
+        assert_eq!(header.compression_level, 4000);
+        assert_eq!(header.bits_per_sample, 24);
+        assert_eq!(header.channels, 1);
+        assert_eq!(header.sample_rate, 22050);
+        assert_eq!(header.total_frames, 2);
+        assert_eq!(header.final_frame_blocks, 1000);
+        assert_eq!(header.seek_table, [48, 4000]);
+        assert_eq!(header.frame_data_end, None);
+
+        // Since version 3.95, frames contain 294912 blocks.
+        assert_eq!(header.blocks_per_frame, 73728 * 4);
+        assert_eq!(header.total_blocks(), 73728 * 4 + 1000);
+    }
+
+    #[test]
+    fn verify_read_seek_table() {
+        // Entries wrap around in files larger than 4 GiB.
+        let buf = make_header(1, 1, &[172, 0xffff_0000, 0x10, 0xffff_fff0, 0x20]);
+
+        let header = ApeHeader::read(&mut BufReader::new(&buf)).unwrap();
+
+        assert_eq!(
+            header.seek_table,
+            [172, 0xffff_0000, 0x1_0000_0010, 0x1_ffff_fff0, 0x2_0000_0020]
+        );
+    }
+
+    #[test]
+    fn verify_invalid_header() {
+        // Unsupported versions.
+        let mut buf = make_header(10, 5, &[172]);
+        buf[..2].copy_from_slice(&3800u16.to_le_bytes());
+        assert!(ApeHeader::read(&mut BufReader::new(&buf)).is_err());
+
+        buf[..2].copy_from_slice(&4000u16.to_le_bytes());
+        assert!(ApeHeader::read(&mut BufReader::new(&buf)).is_err());
+
+        // The final frame is longer than the other frames.
+        let buf = make_header(10, 11, &[172]);
+        assert!(ApeHeader::read(&mut BufReader::new(&buf)).is_err());
+
+        // There are no frames.
+        let buf = make_header(10, 5, &[]);
+        assert!(ApeHeader::read(&mut BufReader::new(&buf)).is_err());
+
+        // The seek table is shorter than the number of frames.
+        let mut buf = make_header(10, 5, &[172, 200]);
+        buf[64..68].copy_from_slice(&3u32.to_le_bytes());
+        assert!(ApeHeader::read(&mut BufReader::new(&buf)).is_err());
+    }
+}
@@ -0,0 +1,234 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The Monkey's Audio predictor applied after the NN filters.
+
+/// The number of samples predicted before the history buffer is moved back to its start.
+const WINDOW_LEN: usize = 512;
+
+/// The number of past values of the history buffer available to the predictor.
+const HISTORY_LEN: usize = 50;
+
+/// The offsets of the values of each channel in the history buffer. The X channel is only
+/// present in stereo frames.
+const Y_DELAY_A: usize = 50;
+const Y_DELAY_B: usize = 42;
+const X_DELAY_A: usize = 34;
+const X_DELAY_B: usize = 26;
+const Y_ADAPT_A: usize = 18;
+const X_ADAPT_A: usize = 14;
+const Y_ADAPT_B: usize = 10;
+const X_ADAPT_B: usize = 5;
+
+/// The initial coefficients of the stage 1 filter.
+const INITIAL_COEFFS_A: [i32; 4] = [360, 317, -109, 98];
+
+/// Returns the negated sign of `x`.
+#[inline(always)]
+fn ape_sign(x: i32) -> i32 {
+    (x < 0) as i32 - (x > 0) as i32
+}
+
+/// Apply the scaled first-order filter to `x` given the previous output.
+#[inline(always)]
+fn first_order(x: i32, prev: i32) -> i32 {
+    x.wrapping_add(prev.wrapping_mul(31) >> 5)
+}
+
+/// The predictor of the Y, and if stereo, the X channel of a frame.
+#[derive(Clone)]
+pub struct Predictor {
+    version: u16,
+    buf: Vec<i32>,
+    /// The position of the current sample in the history buffer.
+    pos: usize,
+    last_a: [i32; 2],
+    filter_a: [i32; 2],
+    filter_b: [i32; 2],
+    coeffs_a: [[i32; 4]; 2],
+    coeffs_b: [[i32; 5]; 2],
+}
+
+impl Predictor {
+    pub fn new(version: u16) -> Self {
+        Predictor {
+            version,
+            buf: vec![0; WINDOW_LEN + HISTORY_LEN],
+            pos: 0,
+            last_a: [0; 2],
+            filter_a: [0; 2],
+            filter_b: [0; 2],
+            coeffs_a: [INITIAL_COEFFS_A; 2],
+            coeffs_b: [[0; 5]; 2],
+        }
+    }
+
+    /// Reset the predictor to its initial state.
+    pub fn reset(&mut self) {
+        self.buf.iter_mut().for_each(|s| *s = 0);
+        self.pos = 0;
+        self.last_a = [0; 2];
+        self.filter_a = [0; 2];
+        self.filter_b = [0; 2];
+        self.coeffs_a = [INITIAL_COEFFS_A; 2];
+        self.coeffs_b = [[0; 5]; 2];
+    }
+
+    /// Advance to the next sample.
+    fn advance(&mut self) {
+        self.pos += 1;
+
+        // Move the most recent history back to the start of the buffer when it is full.
+        if self.pos == WINDOW_LEN {
+            self.buf.copy_within(WINDOW_LEN.., 0);
+            self.pos = 0;
+        }
+    }
+
+    /// Undo the prediction of the samples of a single channel.
+    pub fn decompress_mono(&mut self, y: &mut [i32]) {
+        for s in y.iter_mut() {
+            *s = if self.version >= 3950 {
+                self.update_mono(*s, 0, Y_DELAY_A, Y_ADAPT_A)
+            }
+            else {
+                self.update_3930(*s, 0, Y_DELAY_A)
+            };
+
+            self.advance();
+        }
+    }
+
+    /// Undo the prediction of the samples of the Y and X channels.
+    pub fn decompress_stereo(&mut self, y: &mut [i32], x: &mut [i32]) {
+        for (y, x) in y.iter_mut().zip(x.iter_mut()) {
+            if self.version >= 3950 {
+                *y = self.update_stereo(*y, 0, Y_DELAY_A, Y_DELAY_B, Y_ADAPT_A, Y_ADAPT_B);
+                *x = self.update_stereo(*x, 1, X_DELAY_A, X_DELAY_B, X_ADAPT_A, X_ADAPT_B);
+            }
+            else {
+                *y = self.update_3930(*y, 0, Y_DELAY_A);
+                *x = self.update_3930(*x, 1, X_DELAY_A);
+            }
+
+            self.advance();
+        }
+    }
+
+    /// Predict a sample of a channel using the predictor of versions before 3.95.
+    fn update_3930(&mut self, input: i32, ch: usize, delay_a: usize) -> i32 {
+        let buf = &mut self.buf[self.pos..];
+
+        buf[delay_a] = self.last_a[ch];
+
+        let d = [
+            buf[delay_a],
+            buf[delay_a].wrapping_sub(buf[delay_a - 1]),
+            buf[delay_a - 1].wrapping_sub(buf[delay_a - 2]),
+            buf[delay_a - 2].wrapping_sub(buf[delay_a - 3]),
+        ];
+
+        let coeffs = &mut self.coeffs_a[ch];
+
+        let prediction = d
+            .iter()
+            .zip(coeffs.iter())
+            .fold(0i32, |acc, (&d, &c)| acc.wrapping_add(d.wrapping_mul(c)));
+
+        self.last_a[ch] = input.wrapping_add(prediction >> 9);
+        self.filter_a[ch] = first_order(self.last_a[ch], self.filter_a[ch]);
+
+        // Adapt the coefficients towards the sign of the residual.
+        let sign = ape_sign(input);
+
+        for (c, &d) in coeffs.iter_mut().zip(d.iter()) {
+            *c = c.wrapping_add(if d < 0 { sign } else { -sign });
+        }
+
+        self.filter_a[ch]
+    }
+
+    /// Predict a sample of a mono channel using the predictor of versions since 3.95.
+    fn update_mono(&mut self, input: i32, ch: usize, delay_a: usize, adapt_a: usize) -> i32 {
+        let buf = &mut self.buf[self.pos..];
+
+        buf[delay_a] = self.last_a[ch];
+        buf[delay_a - 1] = buf[delay_a].wrapping_sub(buf[delay_a - 1]);
+        buf[adapt_a] = ape_sign(buf[delay_a]);
+        buf[adapt_a - 1] = ape_sign(buf[delay_a - 1]);
+
+        let coeffs = &mut self.coeffs_a[ch];
+
+        let prediction =
+            (0..4).fold(0i32, |acc, i| acc.wrapping_add(buf[delay_a - i].wrapping_mul(coeffs[i])));
+
+        self.last_a[ch] = input.wrapping_add(prediction >> 10);
+        self.filter_a[ch] = first_order(self.last_a[ch], self.filter_a[ch]);
+
+        let sign = ape_sign(input);
+
+        for (i, c) in coeffs.iter_mut().enumerate() {
+            *c = c.wrapping_add(buf[adapt_a - i] * sign);
+        }
+
+        self.filter_a[ch]
+    }
+
+    /// Predict a sample of a stereo channel using the predictor of versions since 3.95. The
+    /// prediction is refined using the most recent output of the other channel.
+    fn update_stereo(
+        &mut self,
+        input: i32,
+        ch: usize,
+        delay_a: usize,
+        delay_b: usize,
+        adapt_a: usize,
+        adapt_b: usize,
+    ) -> i32 {
+        let buf = &mut self.buf[self.pos..];
+
+        buf[delay_a] = self.last_a[ch];
+        buf[delay_a - 1] = buf[delay_a].wrapping_sub(buf[delay_a - 1]);
+        buf[adapt_a] = ape_sign(buf[delay_a]);
+        buf[adapt_a - 1] = ape_sign(buf[delay_a - 1]);
+
+        let coeffs_a = &mut self.coeffs_a[ch];
+
+        let prediction_a = (0..4)
+            .fold(0i32, |acc, i| acc.wrapping_add(buf[delay_a - i].wrapping_mul(coeffs_a[i])));
+
+        // Apply the scaled first-order filter to the other channel.
+        let other = self.filter_a[ch ^ 1];
+
+        buf[delay_b] = other.wrapping_sub(self.filter_b[ch].wrapping_mul(31) >> 5);
+        buf[delay_b - 1] = buf[delay_b].wrapping_sub(buf[delay_b - 1]);
+        buf[adapt_b] = ape_sign(buf[delay_b]);
+        buf[adapt_b - 1] = ape_sign(buf[delay_b - 1]);
+
+        self.filter_b[ch] = other;
+
+        let coeffs_b = &mut self.coeffs_b[ch];
+
+        let prediction_b = (0..5)
+            .fold(0i32, |acc, i| acc.wrapping_add(buf[delay_b - i].wrapping_mul(coeffs_b[i])));
+
+        self.last_a[ch] = input.wrapping_add(prediction_a.wrapping_add(prediction_b >> 1) >> 10);
+        self.filter_a[ch] = first_order(self.last_a[ch], self.filter_a[ch]);
+
+        let sign = ape_sign(input);
+
+        for (i, c) in coeffs_a.iter_mut().enumerate() {
+            *c = c.wrapping_add(buf[adapt_a - i] * sign);
+        }
+
+        for (i, c) in coeffs_b.iter_mut().enumerate() {
+            *c = c.wrapping_add(buf[adapt_b - i] * sign);
+        }
+
+        self.filter_a[ch]
+    }
+}
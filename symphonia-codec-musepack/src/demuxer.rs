@@ -0,0 +1,744 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::convert::TryFrom;
+use std::io::{Seek, SeekFrom};
+
+use symphonia_core::audio::Channels;
+use symphonia_core::codecs::{CodecParameters, CODEC_TYPE_MUSEPACK};
+use symphonia_core::errors::{decode_error, end_of_stream_error, seek_error, unsupported_error};
+use symphonia_core::errors::{Result, SeekErrorKind};
+use symphonia_core::formats::prelude::*;
+use symphonia_core::formats::util::trim_packet;
+use symphonia_core::io::*;
+use symphonia_core::meta::{Metadata, MetadataLog};
+use symphonia_core::probe::{Descriptor, Instantiate, QueryDescriptor};
+use symphonia_core::support_format;
+
+use symphonia_metadata::trailing::read_trailing_tags;
+
+use log::{debug, warn};
+
+use crate::sv7::*;
+use crate::sv8::*;
+
+/// The maximum length of a SV8 packet, other than an audio packet, that will be read.
+const MAX_SV8_HEADER_PACKET_LEN: u64 = 1 << 20;
+
+/// The state of a SV7 stream.
+struct Sv7Stream {
+    /// The number of frames in the stream.
+    num_frames: u32,
+    /// The position, and bit offset within the first 32-bit word, of each frame that has been
+    /// located so far. The first frame always follows the header.
+    frames: Vec<(u64, u32)>,
+}
+
+/// The state of a SV8 stream.
+struct Sv8Stream {
+    /// The number of audio frames in each audio packet.
+    packet_len: u64,
+    /// The number of audio packets in the stream.
+    num_packets: u64,
+    /// The number of audio frames in the stream, rounded up to a whole number of frames.
+    num_frames: u64,
+    /// The position of the first audio packet.
+    first_packet_pos: u64,
+    /// The seek table, with absolute positions, if available.
+    seek_table: Option<SeekTable>,
+    /// If true, the stream end packet was read.
+    ended: bool,
+}
+
+enum Stream {
+    Sv7(Sv7Stream),
+    Sv8(Sv8Stream),
+}
+
+/// Musepack native format reader.
+///
+/// `MpcReader` implements a demuxer for the SV7 and SV8 Musepack stream formats.
+///
+/// SV7 frames are stored as a bitstream of little-endian 32-bit words, and do not start on a byte
+/// boundary. Each packet contains the bitstream of one frame, excluding the frame length field, in
+/// most-significant bit first byte order. SV8 packets contain the payload of an audio packet.
+///
+/// The codec extra data starts with the stream version. For SV7, this is followed by the 16 bytes
+/// of header fields following the number of frames. For SV8, this is followed by the last 2 bytes
+/// of the stream header.
+pub struct MpcReader {
+    reader: MediaSourceStream,
+    tracks: Vec<Track>,
+    cues: Vec<Cue>,
+    metadata: MetadataLog,
+    gapless: bool,
+    stream: Stream,
+    /// The index of the next SV7 frame, or SV8 audio packet, to read.
+    next_packet: u64,
+}
+
+impl QueryDescriptor for MpcReader {
+    fn query() -> &'static [Descriptor] {
+        &[support_format!(
+            "mpc",
+            "Musepack",
+            &["mpc", "mp+", "mpp"],
+            &["audio/musepack", "audio/x-musepack"],
+            &[b"MP+", b"MPCK"]
+        )]
+    }
+
+    fn score(_context: &[u8]) -> u8 {
+        255
+    }
+}
+
+/// Read the payload of a SV8 packet, other than an audio packet.
+fn read_sv8_payload(reader: &mut MediaSourceStream, header: &PacketHeader) -> Result<Box<[u8]>> {
+    if header.payload_len > MAX_SV8_HEADER_PACKET_LEN {
+        return decode_error("mpc: packet is too large");
+    }
+
+    Ok(reader.read_boxed_slice_exact(header.payload_len as usize)?)
+}
+
+/// Read a SV8 seek table packet at the given position.
+fn read_sv8_seek_table(
+    reader: &mut MediaSourceStream,
+    pos: u64,
+    num_packets: u64,
+) -> Result<SeekTable> {
+    reader.seek(SeekFrom::Start(pos))?;
+
+    let header = PacketHeader::read(reader)?;
+
+    if header.key != KEY_SEEK_TABLE {
+        return decode_error("mpc: missing seek table");
+    }
+
+    SeekTable::read(&read_sv8_payload(reader, &header)?, num_packets)
+}
+
+impl MpcReader {
+    fn try_new_sv7(
+        source: &mut MediaSourceStream,
+        marker_pos: u64,
+    ) -> Result<(Stream, CodecParameters)> {
+        let header = Sv7Header::read(source)?;
+
+        let mut extra_data = Vec::with_capacity(1 + header.fields.len());
+        extra_data.push(header.version);
+        extra_data.extend_from_slice(&header.fields);
+
+        let mut params = CodecParameters::new();
+
+        params
+            .for_codec(CODEC_TYPE_MUSEPACK)
+            .with_sample_rate(header.sample_rate)
+            .with_time_base(TimeBase::new(1, header.sample_rate))
+            .with_channels(Channels::FRONT_LEFT | Channels::FRONT_RIGHT)
+            .with_n_frames(
+                u64::from(header.num_frames - 1) * u64::from(SV7_FRAME_LEN)
+                    + u64::from(header.last_frame_len()),
+            )
+            .with_max_frames_per_packet(u64::from(SV7_FRAME_LEN))
+            .with_extra_data(extra_data.into_boxed_slice());
+
+        if header.is_true_gapless {
+            params.with_padding(SV7_FRAME_LEN - header.last_frame_len());
+        }
+
+        // Frames are stored as a bitstream of 32-bit words that start at the stream marker.
+        let stream = Sv7Stream {
+            num_frames: header.num_frames,
+            frames: vec![(marker_pos + SV7_HEADER_LEN as u64, 0)],
+        };
+
+        Ok((Stream::Sv7(stream), params))
+    }
+
+    fn try_new_sv8(
+        source: &mut MediaSourceStream,
+        marker_pos: u64,
+    ) -> Result<(Stream, CodecParameters)> {
+        let mut header = None;
+        let mut seek_table = None;
+        let mut seek_table_pos = None;
+
+        // Read packets until the first audio packet.
+        loop {
+            let packet_pos = source.pos();
+            let packet = PacketHeader::read(source)?;
+
+            match packet.key {
+                KEY_STREAM_HEADER => {
+                    header = Some(StreamHeader::read(&read_sv8_payload(source, &packet)?)?);
+                }
+                KEY_SEEK_TABLE_OFFSET => {
+                    // The seek table offset is relative to the start of the seek table offset
+                    // packet.
+                    let buf = read_sv8_payload(source, &packet)?;
+                    let (offset, _) = read_size(&mut BufReader::new(&buf))?;
+                    seek_table_pos = Some(packet_pos + offset);
+                }
+                KEY_SEEK_TABLE => {
+                    let buf = read_sv8_payload(source, &packet)?;
+
+                    if let Some(header) = &header {
+                        match SeekTable::read(&buf, header.num_packets()) {
+                            Ok(table) => seek_table = Some(table),
+                            Err(err) => warn!("mpc: failed to read seek table: {}", err),
+                        }
+                    }
+                }
+                KEY_AUDIO_PACKET | KEY_STREAM_END => {
+                    source.seek_buffered_rev(packet.header_len);
+                    break;
+                }
+                _ => source.ignore_bytes(packet.payload_len)?,
+            }
+        }
+
+        let header = match header {
+            Some(header) => header,
+            None => return decode_error("mpc: missing stream header"),
+        };
+
+        let first_packet_pos = source.pos();
+
+        // If the seek table follows the audio packets, read it if the source is seekable, and then
+        // return to the first audio packet.
+        if let (None, Some(pos)) = (&seek_table, seek_table_pos) {
+            if source.is_seekable() {
+                match read_sv8_seek_table(source, pos, header.num_packets()) {
+                    Ok(table) => seek_table = Some(table),
+                    Err(err) => warn!("mpc: failed to read seek table: {}", err),
+                }
+
+                source.seek(SeekFrom::Start(first_packet_pos))?;
+            }
+        }
+
+        // Seek table positions are relative to the stream marker.
+        if let Some(table) = &mut seek_table {
+            for pos in table.positions.iter_mut() {
+                *pos += marker_pos;
+            }
+        }
+
+        let channels = match header.channels {
+            1 => Channels::FRONT_LEFT,
+            _ => Channels::FRONT_LEFT | Channels::FRONT_RIGHT,
+        };
+
+        let mut extra_data = Vec::with_capacity(1 + header.fields.len());
+        extra_data.push(8);
+        extra_data.extend_from_slice(&header.fields);
+
+        let mut params = CodecParameters::new();
+
+        params
+            .for_codec(CODEC_TYPE_MUSEPACK)
+            .with_sample_rate(header.sample_rate)
+            .with_time_base(TimeBase::new(1, header.sample_rate))
+            .with_channels(channels)
+            .with_n_frames(header.num_samples - header.beginning_silence)
+            .with_max_frames_per_packet(header.packet_len())
+            .with_extra_data(extra_data.into_boxed_slice());
+
+        // The last frame is padded to a whole frame.
+        let frame_len = u64::from(SV8_FRAME_LEN);
+        let num_frames = (header.num_samples + frame_len - 1) / frame_len * frame_len;
+
+        if let Ok(delay) = u32::try_from(header.beginning_silence) {
+            params.with_delay(delay);
+        }
+
+        params.with_padding((num_frames - header.num_samples) as u32);
+
+        let stream = Sv8Stream {
+            packet_len: header.packet_len(),
+            num_packets: header.num_packets(),
+            num_frames,
+            first_packet_pos,
+            seek_table,
+            ended: false,
+        };
+
+        Ok((Stream::Sv8(stream), params))
+    }
+
+    /// Position the reader at a position in the stream that is at most 8 bytes before, or any
+    /// number of bytes after, the current position. Seeking further back requires a seekable
+    /// source.
+    fn reposition(&mut self, pos: u64) -> Result<()> {
+        let current_pos = self.reader.pos();
+
+        if pos < current_pos && current_pos - pos <= 8 {
+            self.reader.seek_buffered_rev((current_pos - pos) as usize);
+        }
+        else if pos > current_pos {
+            self.reader.ignore_bytes(pos - current_pos)?;
+        }
+        else if pos < current_pos {
+            if !self.reader.is_seekable() {
+                return seek_error(SeekErrorKind::ForwardOnly);
+            }
+
+            self.reader.seek(SeekFrom::Start(pos))?;
+        }
+
+        Ok(())
+    }
+
+    /// Read the length, in bits, of the SV7 frame at the given position and bit offset. The reader
+    /// is left at the start of the 32-bit word containing the first bit of the frame.
+    fn read_sv7_frame_len(&mut self, pos: u64, offset: u32) -> Result<u32> {
+        self.reposition(pos)?;
+
+        let mut words = [self.reader.read_u32()?, 0];
+
+        // The frame length field spans two words if it starts after the first 12 bits.
+        if offset > 32 - SV7_FRAME_LEN_BITS {
+            words[1] = self.reader.read_u32()?;
+            self.reader.seek_buffered_rev(8);
+        }
+        else {
+            self.reader.seek_buffered_rev(4);
+        }
+
+        Ok(frame_len_bits(words, offset))
+    }
+
+    /// Locate the given SV7 frame by reading the length of each frame after the last frame that
+    /// was located.
+    fn locate_sv7_frame(&mut self, frame: usize) -> Result<()> {
+        loop {
+            let (num_located, (pos, offset)) = match &self.stream {
+                Stream::Sv7(stream) => {
+                    (stream.frames.len(), stream.frames[stream.frames.len() - 1])
+                }
+                _ => unreachable!(),
+            };
+
+            if num_located > frame {
+                return Ok(());
+            }
+
+            let len = self.read_sv7_frame_len(pos, offset)?;
+            let next = next_sv7_frame(pos, offset, len);
+
+            if let Stream::Sv7(stream) = &mut self.stream {
+                stream.frames.push(next);
+            }
+        }
+    }
+
+    fn next_sv7_packet(&mut self) -> Result<Packet> {
+        let frame = self.next_packet as usize;
+
+        let (pos, offset) = match &self.stream {
+            Stream::Sv7(stream) if frame < stream.num_frames as usize => stream.frames[frame],
+            _ => return end_of_stream_error(),
+        };
+
+        let len = self.read_sv7_frame_len(pos, offset)?;
+
+        // Read all the words containing the frame, and convert them into a bitstream.
+        let total_len = offset + SV7_FRAME_LEN_BITS + len;
+
+        let mut buf = vec![0; 4 * ((total_len as usize + 31) / 32)];
+        self.reader.read_buf_exact(&mut buf)?;
+
+        words_to_bitstream(&mut buf);
+
+        let data = extract_bits(&buf, (offset + SV7_FRAME_LEN_BITS) as usize, len as usize);
+
+        if let Stream::Sv7(stream) = &mut self.stream {
+            if stream.frames.len() == frame + 1 {
+                stream.frames.push(next_sv7_frame(pos, offset, len));
+            }
+        }
+
+        let ts = self.next_packet * u64::from(SV7_FRAME_LEN);
+
+        self.next_packet += 1;
+
+        Ok(Packet::new_from_boxed_slice(0, ts, u64::from(SV7_FRAME_LEN), data.into_boxed_slice())
+            .with_pos(pos))
+    }
+
+    fn next_sv8_packet(&mut self) -> Result<Packet> {
+        loop {
+            let stream = match &mut self.stream {
+                Stream::Sv8(stream) if !stream.ended => stream,
+                _ => return end_of_stream_error(),
+            };
+
+            let pos = self.reader.pos();
+            let header = PacketHeader::read(&mut self.reader)?;
+
+            match header.key {
+                KEY_AUDIO_PACKET => {
+                    let data = self.reader.read_boxed_slice_exact(header.payload_len as usize)?;
+
+                    let ts = self.next_packet * stream.packet_len;
+
+                    // The last audio packet may contain fewer frames.
+                    let dur = stream.packet_len.min(stream.num_frames.saturating_sub(ts));
+
+                    self.next_packet += 1;
+
+                    return Ok(Packet::new_from_boxed_slice(0, ts, dur, data).with_pos(pos));
+                }
+                KEY_STREAM_END => {
+                    stream.ended = true;
+                }
+                _ => self.reader.ignore_bytes(header.payload_len)?,
+            }
+        }
+    }
+
+    /// Skip SV8 packets until the given audio packet.
+    fn skip_sv8_packets(&mut self, packet: u64) -> Result<()> {
+        while self.next_packet < packet {
+            let header = PacketHeader::read(&mut self.reader)?;
+
+            match header.key {
+                KEY_AUDIO_PACKET => self.next_packet += 1,
+                KEY_STREAM_END => {
+                    if let Stream::Sv8(stream) = &mut self.stream {
+                        stream.ended = true;
+                    }
+                    break;
+                }
+                _ => (),
+            }
+
+            self.reader.ignore_bytes(header.payload_len)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Gets the position, and bit offset, of the SV7 frame following a frame of the given length.
+fn next_sv7_frame(pos: u64, offset: u32, len: u32) -> (u64, u32) {
+    let end = u64::from(offset) + u64::from(SV7_FRAME_LEN_BITS) + u64::from(len);
+    (pos + 4 * (end / 32), (end % 32) as u32)
+}
+
+impl FormatReader for MpcReader {
+    fn try_new(mut source: MediaSourceStream, options: &FormatOptions) -> Result<Self> {
+        // Positions in the stream are relative to the start of the stream marker.
+        let marker_pos = source.pos();
+
+        let marker = source.read_quad_bytes()?;
+
+        let (stream, mut params) = if marker[..3] == SV7_STREAM_MARKER {
+            // The last byte of the marker is the stream version.
+            source.seek_buffered_rev(1);
+            MpcReader::try_new_sv7(&mut source, marker_pos)?
+        }
+        else if marker == SV8_STREAM_MARKER {
+            MpcReader::try_new_sv8(&mut source, marker_pos)?
+        }
+        else {
+            return unsupported_error("mpc: missing stream marker");
+        };
+
+        // If gapless playback is disabled, the number of frames includes the delay and padding.
+        if !options.enable_gapless {
+            let n_frames = params.n_frames.unwrap_or(0)
+                + u64::from(params.delay.unwrap_or(0))
+                + u64::from(params.padding.unwrap_or(0));
+
+            params.with_n_frames(n_frames);
+        }
+
+        let mut metadata: MetadataLog = Default::default();
+
+        // If the source is seekable, read the tags at the end of the stream, and then return to
+        // the first packet.
+        if source.is_seekable() {
+            let pos = source.pos();
+
+            if let Err(err) = read_trailing_tags(&mut source, &mut metadata) {
+                warn!("mpc: failed to read tags: {}", err);
+            }
+
+            source.seek(SeekFrom::Start(pos))?;
+        }
+
+        Ok(MpcReader {
+            reader: source,
+            tracks: vec![Track::new(0, params)],
+            cues: Vec::new(),
+            metadata,
+            gapless: options.enable_gapless,
+            stream,
+            next_packet: 0,
+        })
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        let mut packet = match self.stream {
+            Stream::Sv7(_) => self.next_sv7_packet()?,
+            Stream::Sv8(_) => self.next_sv8_packet()?,
+        };
+
+        if self.gapless {
+            let params = &self.tracks[0].codec_params;
+            trim_packet(&mut packet, params.delay.unwrap_or(0), params.n_frames);
+        }
+
+        Ok(packet)
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        let params = &self.tracks[0].codec_params;
+
+        let required_ts = match to {
+            // Frame timestamp given.
+            SeekTo::TimeStamp { ts, .. } => ts,
+            // Time value given, calculate frame timestamp from sample rate.
+            SeekTo::Time { time, .. } => {
+                // The sample rate is always known.
+                TimeBase::new(1, params.sample_rate.unwrap()).calc_timestamp(time)
+            }
+        };
+
+        if required_ts > params.n_frames.unwrap_or(0) {
+            return seek_error(SeekErrorKind::OutOfRange);
+        }
+
+        // If gapless playback is enabled, the timestamps of packets are offset by the delay.
+        let delay = if self.gapless { u64::from(params.delay.unwrap_or(0)) } else { 0 };
+
+        debug!("seeking to frame_ts={} (+{} delay)", required_ts, delay);
+
+        let ts = required_ts + delay;
+
+        let actual_ts = match &self.stream {
+            Stream::Sv7(stream) => {
+                let frame = (ts / u64::from(SV7_FRAME_LEN)).min(u64::from(stream.num_frames - 1));
+
+                // Frames must be located sequentially, so frames after the last located frame are
+                // located by scanning forward.
+                if let Some(&(pos, _)) = stream.frames.get(frame as usize) {
+                    if !self.reader.is_seekable() && pos + 8 < self.reader.pos() {
+                        return seek_error(SeekErrorKind::ForwardOnly);
+                    }
+                }
+                else {
+                    self.locate_sv7_frame(frame as usize)?;
+                }
+
+                self.next_packet = frame;
+
+                frame * u64::from(SV7_FRAME_LEN)
+            }
+            Stream::Sv8(stream) => {
+                let packet_len = stream.packet_len;
+                let packet = (ts / packet_len).min(stream.num_packets - 1);
+
+                // Find the closest seek table entry at, or before, the required packet.
+                let entry = match &stream.seek_table {
+                    Some(table) if !table.positions.is_empty() => {
+                        let index = ((packet >> table.log2_interval) as usize)
+                            .min(table.positions.len() - 1);
+
+                        Some(((index as u64) << table.log2_interval, table.positions[index]))
+                    }
+                    _ => None,
+                };
+
+                let first_packet_pos = stream.first_packet_pos;
+
+                // Jump to the seek table entry if it is closer than the next packet, or return to
+                // the first packet if seeking backwards without a seek table entry.
+                match entry {
+                    Some((entry_packet, pos))
+                        if entry_packet > self.next_packet || packet < self.next_packet =>
+                    {
+                        self.reposition(pos)?;
+                        self.next_packet = entry_packet;
+                    }
+                    None if packet < self.next_packet => {
+                        self.reposition(first_packet_pos)?;
+                        self.next_packet = 0;
+                    }
+                    _ => (),
+                }
+
+                if let Stream::Sv8(stream) = &mut self.stream {
+                    stream.ended = false;
+                }
+
+                self.skip_sv8_packets(packet)?;
+
+                self.next_packet * packet_len
+            }
+        };
+
+        let actual_ts = actual_ts.saturating_sub(delay);
+
+        debug!(
+            "seeked to packet_ts={} (delta={})",
+            actual_ts,
+            actual_ts as i64 - required_ts as i64
+        );
+
+        Ok(SeekedTo { track_id: 0, actual_ts, required_ts })
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.reader
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use symphonia_core::formats::{FormatOptions, FormatReader, SeekMode, SeekTo};
+    use symphonia_core::io::MediaSourceStream;
+
+    use super::MpcReader;
+
+    fn open(buf: Vec<u8>) -> MpcReader {
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(buf)), Default::default());
+        let options = FormatOptions::default().with_enable_gapless(true);
+        MpcReader::try_new(mss, &options).unwrap()
+    }
+
+    fn seek(reader: &mut MpcReader, ts: u64) -> u64 {
+        reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts, track_id: 0 }).unwrap().actual_ts
+    }
+
+    /// Pack a sequence of bits into bytes, most-significant bit first.
+    fn pack_bits(bits: &[bool]) -> Vec<u8> {
+        bits.chunks(8)
+            .map(|byte| {
+                byte.iter().enumerate().fold(0, |acc, (i, &b)| acc | (u8::from(b) << (7 - i)))
+            })
+            .collect()
+    }
+
+    /// Gets the data bits of a SV7 frame with the given length.
+    fn sv7_frame_bits(frame: usize, len: usize) -> Vec<bool> {
+        (0..len).map(|i| (i * 7 + frame) % 3 == 0).collect()
+    }
+
+    /// Make a true gapless SV7 stream with 3 frames of 13, 40, and 5 bits, and 100 valid samples
+    /// in the last frame.
+    fn make_sv7() -> Vec<u8> {
+        let mut sv7 = b"MP+\x07".to_vec();
+        sv7.extend_from_slice(&3u32.to_le_bytes());
+        sv7.extend_from_slice(&[0; 12]);
+        sv7.extend_from_slice(&(0x8000_0000u32 | (100 << 20)).to_le_bytes());
+        sv7.extend_from_slice(&0u32.to_le_bytes());
+
+        let mut bits = Vec::new();
+
+        for (frame, &len) in [13, 40, 5].iter().enumerate() {
+            bits.extend((0..20).rev().map(|i| (len >> i) & 1 != 0));
+            bits.extend(sv7_frame_bits(frame, len));
+        }
+
+        bits.resize((bits.len() + 31) / 32 * 32, false);
+
+        // The bitstream is stored as little-endian words.
+        for word in pack_bits(&bits).chunks_exact(4) {
+            sv7.extend(word.iter().rev());
+        }
+
+        sv7
+    }
+
+    #[test]
+    fn verify_sv7_reader() {
+        let mut reader = open(make_sv7());
+
+        let params = &reader.tracks()[0].codec_params;
+        assert_eq!(params.sample_rate, Some(44100));
+        assert_eq!(params.n_frames, Some(2 * 1152 + 100));
+
+        for (frame, &len) in [13, 40, 5].iter().enumerate() {
+            let packet = reader.next_packet().unwrap();
+            assert_eq!(packet.ts, 1152 * frame as u64);
+            assert_eq!(packet.dur, if frame < 2 { 1152 } else { 100 });
+            assert_eq!(&packet.data[..], &pack_bits(&sv7_frame_bits(frame, len))[..]);
+        }
+        assert!(reader.next_packet().is_err());
+
+        assert_eq!(seek(&mut reader, 1200), 1152);
+        assert_eq!(&reader.next_packet().unwrap().data[..], &pack_bits(&sv7_frame_bits(1, 40))[..]);
+
+        // Frames that were not yet located are found by scanning.
+        let mut reader = open(make_sv7());
+
+        assert_eq!(seek(&mut reader, 2400), 2304);
+        assert_eq!(&reader.next_packet().unwrap().data[..], &pack_bits(&sv7_frame_bits(2, 5))[..]);
+    }
+
+    /// Make a SV8 stream with 3 audio packets of 1 frame each, 3000 samples, and 100 samples of
+    /// beginning silence. The seek table follows the audio packets.
+    fn make_sv8() -> Vec<u8> {
+        let mut sv8 = b"MPCK".to_vec();
+        // Stream header: 44.1kHz, stereo, 1 frame per audio packet.
+        sv8.extend_from_slice(b"SH\x0d\x00\x00\x00\x00\x08\x97\x38\x64\x00\x10");
+        // Seek table offset, relative to the start of this packet.
+        sv8.extend_from_slice(b"SO\x04\x1c");
+        for i in 0..3 {
+            sv8.extend_from_slice(b"AP\x08");
+            sv8.extend_from_slice(&[i; 5]);
+        }
+        // Seek table: entries at 21, 29, and 37.
+        sv8.extend_from_slice(b"ST\x09\x03\x01\x51\xd8\x00\x00");
+        sv8.extend_from_slice(b"SE\x03");
+        sv8
+    }
+
+    #[test]
+    fn verify_sv8_reader() {
+        let mut reader = open(make_sv8());
+
+        let params = &reader.tracks()[0].codec_params;
+        assert_eq!(params.delay, Some(100));
+        assert_eq!(params.n_frames, Some(2900));
+        assert_eq!(params.extra_data.as_deref(), Some(&[8, 0x00, 0x10][..]));
+
+        let expected = [(0, 1052, 100, 0), (1052, 1152, 0, 0), (2204, 696, 0, 456)];
+
+        for (i, &(ts, dur, trim_start, trim_end)) in expected.iter().enumerate() {
+            let packet = reader.next_packet().unwrap();
+            assert_eq!((packet.ts, packet.dur), (ts, dur));
+            assert_eq!((packet.trim_start, packet.trim_end), (trim_start, trim_end));
+            assert_eq!(&packet.data[..], &[i as u8; 5]);
+        }
+        assert!(reader.next_packet().is_err());
+
+        assert_eq!(seek(&mut reader, 2300), 2204);
+        assert_eq!(&reader.next_packet().unwrap().data[..], &[2; 5]);
+
+        assert_eq!(seek(&mut reader, 0), 0);
+        assert_eq!(&reader.next_packet().unwrap().data[..], &[0; 5]);
+    }
+}
@@ -0,0 +1,170 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::errors::{decode_error, unsupported_error, Result};
+use symphonia_core::io::ReadBytes;
+
+/// The stream marker of a SV7 stream.
+pub const SV7_STREAM_MARKER: [u8; 3] = *b"MP+";
+
+/// The length of the SV7 header in bytes, including the stream marker.
+pub const SV7_HEADER_LEN: usize = 28;
+
+/// The number of audio frames (samples per channel) in a SV7 frame.
+pub const SV7_FRAME_LEN: u32 = 1152;
+
+/// The length of the frame length field that precedes each frame, in bits.
+pub const SV7_FRAME_LEN_BITS: u32 = 20;
+
+/// The sample rates, indexed by the sample rate field of the header.
+const SAMPLE_RATES: [u32; 4] = [44100, 48000, 37800, 32000];
+
+/// The parsed header of a SV7 stream.
+#[derive(Debug)]
+pub struct Sv7Header {
+    /// The stream version. The lower 4 bits are the major version, and are always 7.
+    pub version: u8,
+    /// The number of frames in the stream.
+    pub num_frames: u32,
+    /// The sample rate in Hz.
+    pub sample_rate: u32,
+    /// If true, the number of valid samples in the last frame is known.
+    pub is_true_gapless: bool,
+    /// The number of valid samples in the last frame, if the stream is true gapless.
+    pub last_frame_samples: u32,
+    /// The header fields, stored as 4 little-endian 32-bit words, following the number of frames.
+    pub fields: [u8; 16],
+}
+
+impl Sv7Header {
+    /// Read a SV7 header. The reader must be positioned after the stream marker.
+    pub fn read<B: ReadBytes>(reader: &mut B) -> Result<Sv7Header> {
+        let version = reader.read_u8()?;
+
+        // Only SV7 and SV7.1 streams are supported. Older stream versions do not have a marker.
+        if version & 0xf != 7 || version >> 4 > 1 {
+            return unsupported_error("mpc: unsupported sv7 stream version");
+        }
+
+        let num_frames = reader.read_u32()?;
+
+        let mut fields = [0; 16];
+        reader.read_buf_exact(&mut fields)?;
+
+        // The encoder version is not used.
+        let _encoder_info = reader.read_u32()?;
+
+        let word = |i: usize| {
+            u32::from_le_bytes([
+                fields[4 * i],
+                fields[4 * i + 1],
+                fields[4 * i + 2],
+                fields[4 * i + 3],
+            ])
+        };
+
+        // Intensity stereo was never used by any encoder.
+        if word(0) & 0x8000_0000 != 0 {
+            return unsupported_error("mpc: intensity stereo is not supported");
+        }
+
+        if num_frames == 0 {
+            return decode_error("mpc: no frames");
+        }
+
+        let sample_rate = SAMPLE_RATES[((word(0) >> 16) & 0x3) as usize];
+
+        let is_true_gapless = word(3) & 0x8000_0000 != 0;
+        let last_frame_samples = (word(3) >> 20) & 0x7ff;
+
+        if is_true_gapless && last_frame_samples > SV7_FRAME_LEN {
+            return decode_error("mpc: invalid last frame length");
+        }
+
+        Ok(Sv7Header {
+            version,
+            num_frames,
+            sample_rate,
+            is_true_gapless,
+            last_frame_samples,
+            fields,
+        })
+    }
+
+    /// Gets the number of valid samples in the last frame.
+    pub fn last_frame_len(&self) -> u32 {
+        if self.is_true_gapless && self.last_frame_samples > 0 {
+            self.last_frame_samples
+        }
+        else {
+            SV7_FRAME_LEN
+        }
+    }
+}
+
+/// Gets the length of a frame excluding the frame length field, in bits, given the first two
+/// 32-bit words of the frame, and the offset in bits of the frame within the first word.
+pub fn frame_len_bits(words: [u32; 2], offset: u32) -> u32 {
+    let bits = (u64::from(words[0]) << 32) | u64::from(words[1]);
+    ((bits >> (64 - SV7_FRAME_LEN_BITS - offset)) & 0xfffff) as u32
+}
+
+/// Convert a buffer of little-endian 32-bit words into a most-significant bit first bitstream.
+pub fn words_to_bitstream(buf: &mut [u8]) {
+    for word in buf.chunks_exact_mut(4) {
+        word.reverse();
+    }
+}
+
+/// Copy `len` bits, starting at bit `offset`, from a most-significant bit first bitstream into a
+/// new buffer. Any unused bits in the last byte of the new buffer are 0.
+pub fn extract_bits(buf: &[u8], offset: usize, len: usize) -> Vec<u8> {
+    let shift = offset % 8;
+    let src = &buf[offset / 8..];
+
+    let mut dst: Vec<u8> = (0..(len + 7) / 8)
+        .map(|i| {
+            let lo = src.get(i + 1).copied().unwrap_or(0);
+
+            if shift > 0 {
+                (src[i] << shift) | (lo >> (8 - shift))
+            }
+            else {
+                src[i]
+            }
+        })
+        .collect();
+
+    if len % 8 != 0 {
+        if let Some(last) = dst.last_mut() {
+            *last &= 0xff << (8 - len % 8);
+        }
+    }
+
+    dst
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verify_extract_bits() {
+        let buf = [0b1010_1100, 0b0101_1111, 0b1000_0001];
+
+        assert_eq!(extract_bits(&buf, 0, 16), vec![0b1010_1100, 0b0101_1111]);
+        assert_eq!(extract_bits(&buf, 4, 12), vec![0b1100_0101, 0b1111_0000]);
+        assert_eq!(extract_bits(&buf, 6, 11), vec![0b0001_0111, 0b1110_0000]);
+        assert_eq!(extract_bits(&buf, 20, 4), vec![0b0001_0000]);
+    }
+
+    #[test]
+    fn verify_frame_len_bits() {
+        assert_eq!(frame_len_bits([0xabcd_e123, 0], 0), 0xabcde);
+        assert_eq!(frame_len_bits([0x0000_0fed, 0xcb00_0000], 20), 0xfedcb);
+    }
+}
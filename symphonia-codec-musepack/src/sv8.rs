@@ -0,0 +1,257 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::errors::{decode_error, unsupported_error, Result};
+use symphonia_core::io::{BitReaderLtr, BufReader, ReadBitsLtr, ReadBytes};
+
+/// The stream marker of a SV8 stream.
+pub const SV8_STREAM_MARKER: [u8; 4] = *b"MPCK";
+
+/// The number of audio frames (samples per channel) in a SV8 frame.
+pub const SV8_FRAME_LEN: u32 = 1152;
+
+/// Packet key: stream header.
+pub const KEY_STREAM_HEADER: [u8; 2] = *b"SH";
+/// Packet key: seek table offset.
+pub const KEY_SEEK_TABLE_OFFSET: [u8; 2] = *b"SO";
+/// Packet key: seek table.
+pub const KEY_SEEK_TABLE: [u8; 2] = *b"ST";
+/// Packet key: audio packet.
+pub const KEY_AUDIO_PACKET: [u8; 2] = *b"AP";
+/// Packet key: stream end.
+pub const KEY_STREAM_END: [u8; 2] = *b"SE";
+
+/// The sample rates, indexed by the sample rate field of the stream header.
+const SAMPLE_RATES: [u32; 4] = [44100, 48000, 37800, 32000];
+
+/// The maximum number of bytes in a variable-length size.
+const MAX_SIZE_LEN: usize = 8;
+
+/// Read a variable-length size. Each byte stores 7 bits of the size, most-significant first, and
+/// the most-significant bit of each byte is set if another byte follows.
+pub fn read_size<B: ReadBytes>(reader: &mut B) -> Result<(u64, usize)> {
+    let mut size = 0;
+
+    for len in 1..=MAX_SIZE_LEN {
+        let byte = reader.read_u8()?;
+
+        size = (size << 7) | u64::from(byte & 0x7f);
+
+        if byte & 0x80 == 0 {
+            return Ok((size, len));
+        }
+    }
+
+    decode_error("mpc: invalid size")
+}
+
+/// The header of a SV8 packet.
+#[derive(Copy, Clone, Debug)]
+pub struct PacketHeader {
+    /// The packet key.
+    pub key: [u8; 2],
+    /// The length of the packet header in bytes.
+    pub header_len: usize,
+    /// The length of the packet payload in bytes.
+    pub payload_len: u64,
+}
+
+impl PacketHeader {
+    /// Read a packet header.
+    pub fn read<B: ReadBytes>(reader: &mut B) -> Result<PacketHeader> {
+        let key = reader.read_double_bytes()?;
+
+        // Keys are always two upper-case letters.
+        if !key.iter().all(|c| c.is_ascii_uppercase()) {
+            return decode_error("mpc: invalid packet key");
+        }
+
+        // The size includes the key, and the size itself.
+        let (size, size_len) = read_size(reader)?;
+
+        let header_len = 2 + size_len;
+
+        if size < header_len as u64 {
+            return decode_error("mpc: invalid packet size");
+        }
+
+        Ok(PacketHeader { key, header_len, payload_len: size - header_len as u64 })
+    }
+}
+
+/// The parsed stream header packet of a SV8 stream.
+#[derive(Debug)]
+pub struct StreamHeader {
+    /// The total number of samples (audio frames), including the beginning silence.
+    pub num_samples: u64,
+    /// The number of samples (audio frames) of silence at the start of the stream.
+    pub beginning_silence: u64,
+    /// The sample rate in Hz.
+    pub sample_rate: u32,
+    /// The number of channels.
+    pub channels: u32,
+    /// The number of frames in each audio packet.
+    pub frames_per_packet: u32,
+    /// The last two bytes of the stream header, containing the sample rate, maximum band, number of
+    /// channels, mid-side stereo flag, and number of frames per audio packet.
+    pub fields: [u8; 2],
+}
+
+impl StreamHeader {
+    /// Read a stream header from the payload of a stream header packet.
+    pub fn read(buf: &[u8]) -> Result<StreamHeader> {
+        let mut reader = BufReader::new(buf);
+
+        // The CRC of the remainder of the stream header.
+        let _crc = reader.read_be_u32()?;
+
+        if reader.read_u8()? != 8 {
+            return unsupported_error("mpc: unsupported sv8 stream version");
+        }
+
+        let (num_samples, _) = read_size(&mut reader)?;
+        let (beginning_silence, _) = read_size(&mut reader)?;
+
+        let mut fields = [0; 2];
+        reader.read_buf_exact(&mut fields)?;
+
+        let sample_rate = match SAMPLE_RATES.get(usize::from(fields[0] >> 5)) {
+            Some(&sample_rate) => sample_rate,
+            None => return decode_error("mpc: invalid sample rate"),
+        };
+
+        let channels = u32::from(fields[1] >> 4) + 1;
+
+        if channels > 2 {
+            return unsupported_error("mpc: more than 2 channels are not supported");
+        }
+
+        let frames_per_packet = 1 << (2 * u32::from(fields[1] & 0x7));
+
+        if beginning_silence > num_samples {
+            return decode_error("mpc: invalid beginning silence");
+        }
+
+        Ok(StreamHeader {
+            num_samples,
+            beginning_silence,
+            sample_rate,
+            channels,
+            frames_per_packet,
+            fields,
+        })
+    }
+
+    /// Gets the number of samples (audio frames) in an audio packet.
+    pub fn packet_len(&self) -> u64 {
+        u64::from(self.frames_per_packet) * u64::from(SV8_FRAME_LEN)
+    }
+
+    /// Gets the number of audio packets in the stream.
+    pub fn num_packets(&self) -> u64 {
+        (self.num_samples + self.packet_len() - 1) / self.packet_len()
+    }
+}
+
+/// Read a variable-length size from a bitstream.
+fn read_bits_size(bs: &mut BitReaderLtr<'_>) -> Result<u64> {
+    let mut size = 0;
+
+    for _ in 0..MAX_SIZE_LEN {
+        let byte = bs.read_bits_leq32(8)?;
+
+        size = (size << 7) | u64::from(byte & 0x7f);
+
+        if byte & 0x80 == 0 {
+            return Ok(size);
+        }
+    }
+
+    decode_error("mpc: invalid size")
+}
+
+/// A SV8 seek table.
+#[derive(Debug)]
+pub struct SeekTable {
+    /// The position of every `1 << log2_interval` audio packet, relative to the stream marker.
+    pub positions: Vec<u64>,
+    /// The base 2 logarithm of the number of audio packets between each entry.
+    pub log2_interval: u32,
+}
+
+impl SeekTable {
+    /// Read a seek table from the payload of a seek table packet.
+    pub fn read(buf: &[u8], num_packets: u64) -> Result<SeekTable> {
+        let mut bs = BitReaderLtr::new(buf);
+
+        let num_entries = read_bits_size(&mut bs)?;
+
+        if num_entries > num_packets {
+            return decode_error("mpc: too many seek table entries");
+        }
+
+        let log2_interval = bs.read_bits_leq32(4)?;
+
+        let mut positions = Vec::with_capacity(num_entries as usize);
+
+        // The first two entries are stored as-is.
+        for _ in 0..num_entries.min(2) {
+            positions.push(read_bits_size(&mut bs)?);
+        }
+
+        // Subsequent entries are stored as the difference to the position predicted by the
+        // previous two entries.
+        for i in 2..num_entries as usize {
+            let mut diff = i64::from(bs.read_unary_zeros_capped(33)?) << 12;
+            diff += i64::from(bs.read_bits_leq32(12)?);
+
+            if diff & 1 != 0 {
+                diff = -(diff & !1);
+            }
+
+            let predicted = 2 * positions[i - 1] as i64 - positions[i - 2] as i64;
+
+            match predicted.checked_add(diff >> 1) {
+                Some(pos) if pos >= 0 => positions.push(pos as u64),
+                _ => return decode_error("mpc: invalid seek table entry"),
+            }
+        }
+
+        Ok(SeekTable { positions, log2_interval })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verify_packet_header() {
+        let buf = [b'A', b'P', 0x81, 0x03];
+
+        let header = PacketHeader::read(&mut BufReader::new(&buf)).unwrap();
+
+        assert_eq!(header.key, KEY_AUDIO_PACKET);
+        assert_eq!(header.header_len, 4);
+        assert_eq!(header.payload_len, 0x83 - 4);
+
+        assert!(PacketHeader::read(&mut BufReader::new(b"ap\x04")).is_err());
+        assert!(PacketHeader::read(&mut BufReader::new(b"AP\x02")).is_err());
+    }
+
+    #[test]
+    fn verify_seek_table() {
+        // 4 entries, an interval of 2 packets, entries 100 and 200, followed by a difference of +4
+        // and -2 from the predicted positions of 300 and 408.
+        let buf = [0x04, 0x16, 0x48, 0x14, 0x88, 0x04, 0x40, 0x14];
+
+        let table = SeekTable::read(&buf, 8).unwrap();
+
+        assert_eq!(table.log2_interval, 1);
+        assert_eq!(table.positions, vec![100, 200, 304, 406]);
+    }
+}
@@ -0,0 +1,344 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::io::{Seek, SeekFrom};
+
+use symphonia_core::audio::Channels;
+use symphonia_core::codecs::{CodecParameters, CODEC_TYPE_TTA};
+use symphonia_core::errors::{decode_error, end_of_stream_error, seek_error, unsupported_error};
+use symphonia_core::errors::{Result, SeekErrorKind};
+use symphonia_core::formats::prelude::*;
+use symphonia_core::io::*;
+use symphonia_core::meta::{Metadata, MetadataLog};
+use symphonia_core::probe::{Descriptor, Instantiate, QueryDescriptor};
+use symphonia_core::support_format;
+
+use symphonia_metadata::trailing::read_trailing_tags;
+
+use log::{debug, warn};
+
+/// The stream marker of a TTA file.
+const TTA_STREAM_MARKER: [u8; 4] = *b"TTA1";
+
+/// The length of the TTA header, including the stream marker and CRC.
+const TTA_HEADER_LEN: usize = 22;
+
+/// The audio format of a file with unencrypted integer PCM samples.
+const TTA_FORMAT_SIMPLE: u16 = 1;
+
+/// The audio format of a file with password protected integer PCM samples.
+const TTA_FORMAT_ENCRYPTED: u16 = 2;
+
+/// Calculate the CRC-32 of a buffer, as used by TTA files. This is the reflected CRC-32 with the
+/// polynomial 0x04c11db7, an initial value of 0xffffffff, and a final XOR of 0xffffffff.
+fn crc32(buf: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffff_u32;
+
+    for &byte in buf {
+        crc ^= u32::from(byte);
+
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+
+    !crc
+}
+
+/// True Audio (TTA) native format reader.
+///
+/// `TtaReader` implements a demuxer for the native True Audio format. Each packet contains one
+/// frame, including its CRC. The codec extra data contains the 22 byte TTA header.
+pub struct TtaReader {
+    reader: MediaSourceStream,
+    tracks: Vec<Track>,
+    cues: Vec<Cue>,
+    metadata: MetadataLog,
+    frame_len: u64,
+    n_frames: u64,
+    /// The absolute position of each frame, followed by the end of the last frame.
+    frame_pos: Vec<u64>,
+    next_frame: usize,
+}
+
+impl QueryDescriptor for TtaReader {
+    fn query() -> &'static [Descriptor] {
+        &[support_format!("tta", "True Audio", &["tta"], &["audio/x-tta"], &[b"TTA1"])]
+    }
+
+    fn score(_context: &[u8]) -> u8 {
+        255
+    }
+}
+
+impl FormatReader for TtaReader {
+    fn try_new(mut source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
+        let mut header = [0; TTA_HEADER_LEN];
+        source.read_buf_exact(&mut header)?;
+
+        if header[0..4] != TTA_STREAM_MARKER {
+            return unsupported_error("tta: missing tta1 stream marker");
+        }
+
+        let mut reader = BufReader::new(&header[4..]);
+
+        let format = reader.read_u16()?;
+        let n_channels = reader.read_u16()?;
+        let bits_per_sample = reader.read_u16()?;
+        let sample_rate = reader.read_u32()?;
+        let n_frames = u64::from(reader.read_u32()?);
+        let crc = reader.read_u32()?;
+
+        if crc != crc32(&header[..TTA_HEADER_LEN - 4]) {
+            return decode_error("tta: header crc mismatch");
+        }
+
+        match format {
+            TTA_FORMAT_SIMPLE => (),
+            TTA_FORMAT_ENCRYPTED => return unsupported_error("tta: encrypted files not supported"),
+            _ => return unsupported_error("tta: unsupported audio format"),
+        }
+
+        if sample_rate == 0 {
+            return decode_error("tta: invalid sample rate");
+        }
+
+        if bits_per_sample < 8 || bits_per_sample > 24 {
+            return unsupported_error("tta: unsupported bits per sample");
+        }
+
+        let channels = match n_channels {
+            1..=32 => Channels::from_bits(u32::MAX >> (32 - u32::from(n_channels))),
+            _ => None,
+        };
+
+        let channels = match channels {
+            Some(channels) => channels,
+            None => return unsupported_error("tta: unsupported number of channels"),
+        };
+
+        // Every frame, except the final frame, is 256 / 245 seconds long.
+        let frame_len = 256 * u64::from(sample_rate) / 245;
+
+        let num_frames = (n_frames + frame_len - 1) / frame_len;
+
+        // The seek table contains the length of each frame, and is followed by its CRC.
+        let mut seek_table = vec![0; 4 * num_frames as usize];
+        source.read_buf_exact(&mut seek_table)?;
+
+        if source.read_u32()? != crc32(&seek_table) {
+            warn!("tta: seek table crc mismatch");
+        }
+
+        let mut frame_pos = Vec::with_capacity(num_frames as usize + 1);
+
+        let mut pos = source.pos();
+        frame_pos.push(pos);
+
+        for len in seek_table.chunks_exact(4) {
+            pos += u64::from(u32::from_le_bytes([len[0], len[1], len[2], len[3]]));
+            frame_pos.push(pos);
+        }
+
+        let mut metadata: MetadataLog = Default::default();
+
+        // If the source is seekable, read the tags at the end of the stream, and then return to
+        // the frame data.
+        if source.is_seekable() {
+            let pos = source.pos();
+
+            if let Err(err) = read_trailing_tags(&mut source, &mut metadata) {
+                warn!("tta: failed to read tags: {}", err);
+            }
+
+            source.seek(SeekFrom::Start(pos))?;
+        }
+
+        let mut params = CodecParameters::new();
+
+        params
+            .for_codec(CODEC_TYPE_TTA)
+            .with_sample_rate(sample_rate)
+            .with_time_base(TimeBase::new(1, sample_rate))
+            .with_channels(channels)
+            .with_bits_per_sample(u32::from(bits_per_sample))
+            .with_n_frames(n_frames)
+            .with_max_frames_per_packet(frame_len)
+            .with_extra_data(Box::from(&header[..]));
+
+        Ok(TtaReader {
+            reader: source,
+            tracks: vec![Track::new(0, params)],
+            cues: Vec::new(),
+            metadata,
+            frame_len,
+            n_frames,
+            frame_pos,
+            next_frame: 0,
+        })
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        let num_frames = self.frame_pos.len() - 1;
+
+        if self.next_frame >= num_frames {
+            return end_of_stream_error();
+        }
+
+        let start = self.frame_pos[self.next_frame];
+        let end = self.frame_pos[self.next_frame + 1];
+
+        let pos = self.reader.pos();
+
+        if start > pos {
+            self.reader.ignore_bytes(start - pos)?;
+        }
+        else if start < pos {
+            self.reader.seek(SeekFrom::Start(start))?;
+        }
+
+        let buf = self.reader.read_boxed_slice_exact((end - start) as usize)?;
+
+        let ts = self.next_frame as u64 * self.frame_len;
+        let dur = self.frame_len.min(self.n_frames - ts);
+
+        self.next_frame += 1;
+
+        Ok(Packet::new_from_boxed_slice(0, ts, dur, buf).with_pos(start))
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        let params = &self.tracks[0].codec_params;
+
+        let required_ts = match to {
+            // Frame timestamp given.
+            SeekTo::TimeStamp { ts, .. } => ts,
+            // Time value given, calculate frame timestamp from sample rate.
+            SeekTo::Time { time, .. } => {
+                // The sample rate is always known.
+                TimeBase::new(1, params.sample_rate.unwrap()).calc_timestamp(time)
+            }
+        };
+
+        if required_ts > self.n_frames {
+            return seek_error(SeekErrorKind::OutOfRange);
+        }
+
+        // The seek table contains the position of every frame.
+        let num_frames = self.frame_pos.len() - 1;
+
+        if num_frames == 0 {
+            return seek_error(SeekErrorKind::OutOfRange);
+        }
+
+        let frame = ((required_ts / self.frame_len) as usize).min(num_frames - 1);
+
+        debug!("seeking to frame_ts={} in frame {}", required_ts, frame);
+
+        // Frames are positioned when they are read, so only unseekable sources need to be
+        // positioned here.
+        if !self.reader.is_seekable() {
+            let start = self.frame_pos[frame];
+            let pos = self.reader.pos();
+
+            if start >= pos {
+                self.reader.ignore_bytes(start - pos)?;
+            }
+            else {
+                return seek_error(SeekErrorKind::ForwardOnly);
+            }
+        }
+
+        self.next_frame = frame;
+
+        let actual_ts = frame as u64 * self.frame_len;
+
+        Ok(SeekedTo { track_id: 0, actual_ts, required_ts })
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.reader
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use symphonia_core::codecs::CODEC_TYPE_TTA;
+    use symphonia_core::formats::{FormatReader, SeekMode, SeekTo};
+    use symphonia_core::io::MediaSourceStream;
+
+    use super::{crc32, TtaReader};
+
+    /// Make a 16-bit stereo file at 245 Hz, with 256 audio frames per frame, and 600 audio frames
+    /// in total.
+    fn make_tta() -> Vec<u8> {
+        let mut tta = b"TTA1".to_vec();
+        tta.extend_from_slice(&1u16.to_le_bytes());
+        tta.extend_from_slice(&2u16.to_le_bytes());
+        tta.extend_from_slice(&16u16.to_le_bytes());
+        tta.extend_from_slice(&245u32.to_le_bytes());
+        tta.extend_from_slice(&600u32.to_le_bytes());
+        let crc = crc32(&tta);
+        tta.extend_from_slice(&crc.to_le_bytes());
+
+        let mut seek_table = Vec::new();
+        for len in &[10u32, 12, 5] {
+            seek_table.extend_from_slice(&len.to_le_bytes());
+        }
+        tta.extend_from_slice(&seek_table);
+        tta.extend_from_slice(&crc32(&seek_table).to_le_bytes());
+
+        tta.extend(0..27);
+        tta
+    }
+
+    #[test]
+    fn verify_crc32() {
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+    }
+
+    #[test]
+    fn verify_tta_reader() {
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(make_tta())), Default::default());
+        let mut reader = TtaReader::try_new(mss, &Default::default()).unwrap();
+
+        let params = &reader.tracks()[0].codec_params;
+        assert_eq!(params.codec, CODEC_TYPE_TTA);
+        assert_eq!(params.n_frames, Some(600));
+        assert_eq!(params.bits_per_sample, Some(16));
+        assert_eq!(params.extra_data.as_ref().map(|data| data.len()), Some(22));
+
+        let packet = reader.next_packet().unwrap();
+        assert_eq!((packet.ts, packet.dur), (0, 256));
+        assert_eq!(&packet.data[..], &(0..10).collect::<Vec<u8>>()[..]);
+
+        reader.next_packet().unwrap();
+
+        let packet = reader.next_packet().unwrap();
+        assert_eq!((packet.ts, packet.dur), (512, 88));
+        assert_eq!(&packet.data[..], &[22, 23, 24, 25, 26]);
+        assert!(reader.next_packet().is_err());
+
+        let seeked = reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 300, track_id: 0 });
+        assert_eq!(seeked.unwrap().actual_ts, 256);
+        assert_eq!(reader.next_packet().unwrap().data[0], 10);
+    }
+}
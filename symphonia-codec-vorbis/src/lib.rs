@@ -297,7 +297,7 @@ impl Decoder for VorbisDecoder {
         // Map the channels
         let channels = match vorbis_channels_to_channels(ident.n_channels) {
             Some(channels) => channels,
-            _ => return unsupported_error("vorbis: unknown channel map (fix me)"),
+            _ => return unsupported_error("vorbis: unsupported number of channels"),
         };
 
         // Initialize the output buffer.
@@ -761,3 +761,131 @@ pub fn map_vorbis_channel(num_channels: u8, ch: usize) -> usize {
 
     usize::from(mapped_ch)
 }
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::audio::{AudioBufferRef, Channels, Signal};
+    use symphonia_core::codecs::{CodecParameters, Decoder, CODEC_TYPE_VORBIS};
+    use symphonia_core::formats::Packet;
+    use symphonia_core::io::BitWriterRtl;
+
+    use super::{map_vorbis_channel, VorbisDecoder};
+
+    /// Make the identification and setup headers of a stream with a minimal setup: a single
+    /// codebook, an empty floor 1, an empty residue, and a single mapping and short block mode.
+    fn make_headers(n_channels: u8) -> Vec<u8> {
+        // Identification header with block sizes of 256 and 2048.
+        let mut buf = b"\x01vorbis".to_vec();
+        buf.extend_from_slice(&0u32.to_le_bytes());
+        buf.push(n_channels);
+        buf.extend_from_slice(&44100u32.to_le_bytes());
+        buf.extend_from_slice(&[0; 12]);
+        buf.extend_from_slice(&[0xb8, 0x01]);
+
+        // Setup header.
+        buf.extend_from_slice(b"\x05vorbis");
+
+        let mut bs = BitWriterRtl::new();
+
+        // A codebook of 1 dimension, and 2 entries of length 1, without a lookup table.
+        bs.write_bits_leq32(0, 8);
+        bs.write_bits_leq32(0x564342, 24);
+        bs.write_bits_leq32(1, 16);
+        bs.write_bits_leq32(2, 24);
+        bs.write_bits_leq32(0, 2);
+        bs.write_bits_leq32(0, 10);
+        bs.write_bits_leq32(0, 4);
+
+        // The time domain transform placeholder.
+        bs.write_bits_leq32(0, 6);
+        bs.write_bits_leq32(0, 16);
+
+        // A type 1 floor without partitions.
+        bs.write_bits_leq32(0, 6);
+        bs.write_bits_leq32(1, 16);
+        bs.write_bits_leq32(0, 5);
+        bs.write_bits_leq32(0, 2);
+        bs.write_bits_leq32(8, 4);
+
+        // A type 0 residue without any codebooks.
+        bs.write_bits_leq32(0, 6);
+        bs.write_bits_leq32(0, 16);
+        bs.write_bits_leq32(0, 24);
+        bs.write_bits_leq32(0, 24);
+        bs.write_bits_leq32(15, 24);
+        bs.write_bits_leq32(0, 6);
+        bs.write_bits_leq32(0, 8);
+        bs.write_bits_leq32(0, 4);
+
+        // A type 0 mapping with one submap, and no coupling.
+        bs.write_bits_leq32(0, 6);
+        bs.write_bits_leq32(0, 16);
+        bs.write_bits_leq32(0, 4);
+        bs.write_bits_leq32(0, 24);
+
+        // A short block mode.
+        bs.write_bits_leq32(0, 6);
+        bs.write_bits_leq32(0, 1);
+        bs.write_bits_leq32(0, 32);
+        bs.write_bits_leq32(0, 8);
+
+        // Framing flag.
+        bs.write_bits_leq32(1, 1);
+
+        buf.extend(bs.into_inner());
+        buf
+    }
+
+    /// Make an audio packet in which every channel is unused.
+    fn make_silent_packet(n_channels: u8) -> Vec<u8> {
+        let mut bs = BitWriterRtl::new();
+        bs.write_bits_leq32(0, 1);
+
+        for _ in 0..n_channels {
+            bs.write_bits_leq32(0, 1);
+        }
+
+        bs.into_inner()
+    }
+
+    #[test]
+    fn verify_decode_many_channels() {
+        let mut params = CodecParameters::new();
+        params.for_codec(CODEC_TYPE_VORBIS).with_extra_data(make_headers(10).into_boxed_slice());
+
+        let mut decoder = VorbisDecoder::try_new(&params, &Default::default()).unwrap();
+
+        let packet = Packet::new_from_boxed_slice(0, 0, 0, make_silent_packet(10).into());
+
+        // The first packet only primes the overlap-add.
+        decoder.decode(&packet).unwrap();
+
+        match decoder.decode(&packet).unwrap() {
+            AudioBufferRef::F32(buf) => {
+                // The first 10 channel positions are assigned in order.
+                assert_eq!(buf.spec().channels, Channels::from_bits(0x3ff).unwrap());
+                assert_eq!(buf.frames(), 128);
+
+                for ch in 0..10 {
+                    assert!(buf.chan(ch).iter().all(|&s| s == 0.0));
+                }
+            }
+            _ => panic!("unexpected sample format"),
+        }
+
+        // Streams with more channels than there are channel positions are not supported.
+        params.with_extra_data(make_headers(27).into_boxed_slice());
+        assert!(VorbisDecoder::try_new(&params, &Default::default()).is_err());
+    }
+
+    #[test]
+    fn verify_map_vorbis_channel() {
+        // Mapping 0 channels are reordered into Symphonia's channel order.
+        let mapped: Vec<usize> = (0..6).map(|ch| map_vorbis_channel(6, ch)).collect();
+        assert_eq!(mapped, [0, 2, 1, 4, 5, 3]);
+
+        // The order of more than 8 channels is application-defined, and left unchanged.
+        let mapped: Vec<usize> = (0..10).map(|ch| map_vorbis_channel(10, ch)).collect();
+        assert_eq!(mapped, (0..10).collect::<Vec<usize>>());
+    }
+}
@@ -0,0 +1,741 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::audio::{AsAudioBufferRef, AudioBuffer, AudioBufferRef, Signal, SignalSpec};
+use symphonia_core::codecs::{CodecDescriptor, CodecParameters, CODEC_TYPE_WAVPACK};
+use symphonia_core::codecs::{Decoder, DecoderOptions, FinalizeResult};
+use symphonia_core::errors::{decode_error, unsupported_error, Result};
+use symphonia_core::formats::Packet;
+use symphonia_core::io::{BitReaderRtl, BufReader, ReadBytes};
+use symphonia_core::support_codec;
+
+use crate::decorr::{restore_weight, DecorrPass, MAX_NUM_TERMS, MAX_TERM};
+use crate::entropy::{exp2s, WordsDecoder};
+use crate::header::*;
+
+/// The parameters of 32-bit integer samples. The encoder may remove redundant low-order bits
+/// from 32-bit samples before coding them.
+struct Int32Info {
+    /// The number of low-order bits sent in a separate bitstream.
+    sent_bits: u8,
+    /// The number of low-order bits that are always zero.
+    zeros: u8,
+    /// The number of low-order bits that are always one.
+    ones: u8,
+    /// The number of low-order bits that are always equal to the lowest coded bit.
+    dups: u8,
+}
+
+/// Read the decorrelation terms and deltas. The terms are stored in the reverse order that the
+/// passes are applied by the decoder.
+fn read_decorr_terms(buf: &[u8], is_mono: bool) -> Result<Vec<DecorrPass>> {
+    if buf.len() > MAX_NUM_TERMS {
+        return decode_error("wavpack: too many decorrelation terms");
+    }
+
+    let mut passes = vec![DecorrPass::default(); buf.len()];
+
+    for (pass, &byte) in passes.iter_mut().rev().zip(buf) {
+        pass.term = i32::from(byte & 0x1f) - 5;
+        pass.delta = i32::from(byte >> 5);
+
+        match pass.term {
+            1..=8 | 17 | 18 => (),
+            // Cross-channel terms are only valid in stereo blocks.
+            -3..=-1 if !is_mono => (),
+            _ => return decode_error("wavpack: invalid decorrelation term"),
+        }
+    }
+
+    Ok(passes)
+}
+
+/// Read the initial decorrelation weights. Weights may be omitted for the first passes stored,
+/// in which case they are zero.
+fn read_decorr_weights(buf: &[u8], passes: &mut [DecorrPass], is_mono: bool) -> Result<()> {
+    let n_weights = if is_mono { 1 } else { 2 };
+
+    if buf.len() / n_weights > passes.len() {
+        return decode_error("wavpack: too many decorrelation weights");
+    }
+
+    for (pass, weights) in passes.iter_mut().rev().zip(buf.chunks_exact(n_weights)) {
+        pass.weight_a = restore_weight(weights[0] as i8);
+
+        if !is_mono {
+            pass.weight_b = restore_weight(weights[1] as i8);
+        }
+    }
+
+    Ok(())
+}
+
+/// Read a decorrelation sample. Samples are stored in the same logarithmic form as the entropy
+/// coder medians.
+fn read_decorr_sample(reader: &mut BufReader<'_>) -> Result<i32> {
+    Ok(exp2s(i32::from(reader.read_u16()? as i16)))
+}
+
+/// Read the initial decorrelation samples. Samples may be omitted for the first passes stored, in
+/// which case they are zero.
+fn read_decorr_samples(buf: &[u8], passes: &mut [DecorrPass], is_mono: bool) -> Result<()> {
+    let mut reader = BufReader::new(buf);
+
+    for pass in passes.iter_mut().rev() {
+        if reader.pos() >= buf.len() as u64 {
+            break;
+        }
+
+        if pass.term > MAX_TERM as i32 {
+            pass.samples_a[0] = read_decorr_sample(&mut reader)?;
+            pass.samples_a[1] = read_decorr_sample(&mut reader)?;
+
+            if !is_mono {
+                pass.samples_b[0] = read_decorr_sample(&mut reader)?;
+                pass.samples_b[1] = read_decorr_sample(&mut reader)?;
+            }
+        }
+        else if pass.term < 0 {
+            pass.samples_a[0] = read_decorr_sample(&mut reader)?;
+            pass.samples_b[0] = read_decorr_sample(&mut reader)?;
+        }
+        else {
+            for i in 0..pass.term as usize {
+                pass.samples_a[i] = read_decorr_sample(&mut reader)?;
+
+                if !is_mono {
+                    pass.samples_b[i] = read_decorr_sample(&mut reader)?;
+                }
+            }
+        }
+    }
+
+    if reader.pos() != buf.len() as u64 {
+        return decode_error("wavpack: invalid decorrelation samples");
+    }
+
+    Ok(())
+}
+
+/// Read the initial entropy coder medians of each channel.
+fn read_entropy_vars(buf: &[u8], is_mono: bool) -> Result<[[u32; 3]; 2]> {
+    if buf.len() != if is_mono { 6 } else { 12 } {
+        return decode_error("wavpack: invalid entropy variables");
+    }
+
+    let mut medians = [[0; 3]; 2];
+
+    for (i, median) in buf.chunks_exact(2).enumerate() {
+        let log = u16::from_le_bytes([median[0], median[1]]);
+        medians[i / 3][i % 3] = exp2s(i32::from(log)) as u32;
+    }
+
+    Ok(medians)
+}
+
+/// Read the parameters of 32-bit integer samples.
+fn read_int32_info(buf: &[u8]) -> Result<Int32Info> {
+    match *buf {
+        [sent_bits, zeros, ones, dups] => Ok(Int32Info { sent_bits, zeros, ones, dups }),
+        _ => decode_error("wavpack: invalid int32 info"),
+    }
+}
+
+/// Decode the single, or interleaved stereo, channel samples of a block into `samples`.
+fn decode_block(header: &BlockHeader, body: &[u8], samples: &mut Vec<i32>) -> Result<()> {
+    if header.is_hybrid() {
+        return unsupported_error("wavpack: hybrid mode is not supported");
+    }
+
+    if header.is_float() {
+        return unsupported_error("wavpack: floating point samples are not supported");
+    }
+
+    if header.is_dsd() {
+        return unsupported_error("wavpack: dsd audio is not supported");
+    }
+
+    let is_mono = header.is_mono_data();
+
+    let mut terms = None;
+    let mut weights = None;
+    let mut decorr_samples = None;
+    let mut entropy_vars = None;
+    let mut int32_info = None;
+    let mut bitstream = None;
+
+    for sub_block in read_sub_blocks(body)? {
+        match sub_block.id {
+            ID_DECORR_TERMS => terms = Some(sub_block.data),
+            ID_DECORR_WEIGHTS => weights = Some(sub_block.data),
+            ID_DECORR_SAMPLES => decorr_samples = Some(sub_block.data),
+            ID_ENTROPY_VARS => entropy_vars = Some(sub_block.data),
+            ID_INT32_INFO => int32_info = Some(read_int32_info(sub_block.data)?),
+            ID_WV_BITSTREAM => bitstream = Some(sub_block.data),
+            _ => (),
+        }
+    }
+
+    // The weights and samples of the decorrelation passes depend on the terms.
+    let mut passes = read_decorr_terms(terms.unwrap_or_default(), is_mono)?;
+
+    if let Some(buf) = weights {
+        read_decorr_weights(buf, &mut passes, is_mono)?;
+    }
+
+    if let Some(buf) = decorr_samples {
+        read_decorr_samples(buf, &mut passes, is_mono)?;
+    }
+
+    let medians = match entropy_vars {
+        Some(buf) => read_entropy_vars(buf, is_mono)?,
+        None => Default::default(),
+    };
+
+    let n_channels = if is_mono { 1 } else { 2 };
+
+    samples.clear();
+    samples.resize(n_channels * header.block_samples as usize, 0);
+
+    // A block without a bitstream only has zero residuals.
+    if let Some(buf) = bitstream {
+        WordsDecoder::new(medians).decode(&mut BitReaderRtl::new(buf), samples, n_channels)?;
+    }
+
+    for pass in passes.iter_mut() {
+        if is_mono {
+            pass.decorrelate_mono(samples);
+        }
+        else {
+            pass.decorrelate_stereo(samples);
+        }
+    }
+
+    // Convert mid and side channels back to left and right channels.
+    if !is_mono && header.is_joint_stereo() {
+        for s in samples.chunks_exact_mut(2) {
+            s[1] = s[1].wrapping_sub(s[0] >> 1);
+            s[0] = s[0].wrapping_add(s[1]);
+        }
+    }
+
+    let crc = samples.iter().fold(u32::MAX, |crc, &s| crc.wrapping_mul(3).wrapping_add(s as u32));
+
+    if crc != header.crc {
+        return decode_error("wavpack: crc mismatch");
+    }
+
+    // Restore the redundant low-order bits of 32-bit samples.
+    if let Some(info) = int32_info.filter(|_| header.is_int32()) {
+        if info.sent_bits > 0 {
+            return unsupported_error("wavpack: int32 extended bitstream is not supported");
+        }
+
+        let zeros = u32::from(info.zeros);
+        let ones = u32::from(info.ones);
+        let dups = u32::from(info.dups);
+
+        for s in samples.iter_mut() {
+            if zeros > 0 {
+                *s = s.wrapping_shl(zeros);
+            }
+            else if ones > 0 {
+                *s = s.wrapping_add(1).wrapping_shl(ones).wrapping_sub(1);
+            }
+            else if dups > 0 {
+                let lsb = *s & 1;
+                *s = s.wrapping_add(lsb).wrapping_shl(dups).wrapping_sub(lsb);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// WavPack decoder.
+///
+/// Only the lossless integer modes of WavPack are supported.
+pub struct WavPackDecoder {
+    params: CodecParameters,
+    buf: AudioBuffer<i32>,
+    /// The single, or interleaved stereo, channel samples of the block being decoded.
+    samples: Vec<i32>,
+}
+
+impl WavPackDecoder {
+    fn decode_inner(&mut self, packet: &Packet) -> Result<()> {
+        let data = packet.buf();
+
+        let mut reader = BufReader::new(data);
+
+        let n_channels = self.buf.spec().channels.count();
+        let mut next_channel = 0;
+
+        self.buf.clear();
+
+        // A packet is a frame of one or more blocks, each of which codes the next one or two
+        // channels.
+        while reader.pos() < data.len() as u64 {
+            let header = BlockHeader::read(&mut reader)?;
+            let body = reader.read_buf_bytes_ref(header.body_len())?;
+
+            let block_channels = header.num_channels() as usize;
+
+            if next_channel + block_channels > n_channels {
+                return decode_error("wavpack: too many channels");
+            }
+
+            let n_frames = header.block_samples as usize;
+
+            if next_channel == 0 {
+                if n_frames > self.buf.capacity() {
+                    self.buf = AudioBuffer::new(n_frames as u64, *self.buf.spec());
+                }
+
+                self.buf.render_reserved(Some(n_frames));
+            }
+            else if n_frames != self.buf.frames() {
+                return decode_error("wavpack: block length mismatch");
+            }
+
+            decode_block(&header, body, &mut self.samples)?;
+
+            // The samples are shifted such that the most-significant bit of the coded bit depth
+            // is the most-significant bit of the output sample.
+            let shift = header.shift() + 32 - 8 * header.bytes_per_sample();
+
+            if shift >= 32 {
+                return decode_error("wavpack: invalid sample shift");
+            }
+
+            if header.is_mono_data() {
+                let plane = self.buf.chan_mut(next_channel);
+
+                for (out, &s) in plane.iter_mut().zip(&self.samples) {
+                    *out = s << shift;
+                }
+
+                // Both channels of a false stereo block are identical.
+                if block_channels == 2 {
+                    let (src, dst) = self.buf.chan_pair_mut(next_channel, next_channel + 1);
+                    dst.copy_from_slice(src);
+                }
+            }
+            else {
+                let (left, right) = self.buf.chan_pair_mut(next_channel, next_channel + 1);
+
+                let frames = left.iter_mut().zip(right.iter_mut());
+
+                for ((l, r), s) in frames.zip(self.samples.chunks_exact(2)) {
+                    *l = s[0] << shift;
+                    *r = s[1] << shift;
+                }
+            }
+
+            next_channel += block_channels;
+        }
+
+        if next_channel != n_channels {
+            return decode_error("wavpack: missing channels");
+        }
+
+        Ok(())
+    }
+}
+
+impl Decoder for WavPackDecoder {
+    fn try_new(params: &CodecParameters, _options: &DecoderOptions) -> Result<Self> {
+        if params.codec != CODEC_TYPE_WAVPACK {
+            return unsupported_error("wavpack: invalid codec type");
+        }
+
+        let rate = match params.sample_rate {
+            Some(rate) => rate,
+            None => return unsupported_error("wavpack: sample rate is required"),
+        };
+
+        let channels = match params.channels {
+            Some(channels) => channels,
+            None => return unsupported_error("wavpack: channels are required"),
+        };
+
+        let max_frames = match params.max_frames_per_packet {
+            Some(max_frames) => max_frames,
+            None => return unsupported_error("wavpack: maximum frames per packet is required"),
+        };
+
+        Ok(WavPackDecoder {
+            params: params.clone(),
+            buf: AudioBuffer::new(max_frames, SignalSpec::new(rate, channels)),
+            samples: Vec::new(),
+        })
+    }
+
+    fn supported_codecs() -> &'static [CodecDescriptor] {
+        &[support_codec!(CODEC_TYPE_WAVPACK, "wavpack", "WavPack")]
+    }
+
+    fn reset(&mut self) {
+        // No state is stored between packets, therefore do nothing.
+    }
+
+    fn codec_params(&self) -> &CodecParameters {
+        &self.params
+    }
+
+    fn decode(&mut self, packet: &Packet) -> Result<AudioBufferRef<'_>> {
+        if let Err(e) = self.decode_inner(packet) {
+            self.buf.clear();
+            Err(e)
+        }
+        else {
+            Ok(self.buf.as_audio_buffer_ref())
+        }
+    }
+
+    fn finalize(&mut self) -> FinalizeResult {
+        Default::default()
+    }
+
+    fn last_decoded(&self) -> AudioBufferRef<'_> {
+        self.buf.as_audio_buffer_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use symphonia_core::audio::Channels;
+    use symphonia_core::io::BitWriterRtl;
+
+    #[test]
+    fn verify_decode_block() {
+        let body = [
+            // Terms -1 and 17, both with a delta of 2, in reverse order.
+            ID_DECORR_TERMS,
+            0x01,
+            0x44,
+            0x56,
+            // Weights of each pass in reverse order.
+            ID_DECORR_WEIGHTS,
+            0x02,
+            0x40,
+            0x20,
+            0x30,
+            0xf0,
+            // Samples of each pass in reverse order.
+            ID_DECORR_SAMPLES,
+            0x06,
+            0x80,
+            0x04,
+            0x80,
+            0x03,
+            0x00,
+            0x06,
+            0x80,
+            0x05,
+            0x00,
+            0xfb,
+            0x00,
+            0x04,
+            // Medians of each channel.
+            ID_ENTROPY_VARS,
+            0x06,
+            0x80,
+            0x08,
+            0x00,
+            0x08,
+            0x00,
+            0x07,
+            0x80,
+            0x07,
+            0x00,
+            0x07,
+            0x00,
+            0x06,
+            ID_WV_BITSTREAM,
+            0x0a,
+            0x5a,
+            0xc3,
+            0x17,
+            0x88,
+            0x2e,
+            0x91,
+            0x40,
+            0x0f,
+            0x73,
+            0xe4,
+            0x2b,
+            0x9d,
+            0x61,
+            0x05,
+            0xba,
+            0xcc,
+            0x38,
+            0x7e,
+            0xd2,
+            0x49,
+        ];
+
+        // A 16-bit joint stereo block of 6 samples.
+        let mut header = BlockHeader {
+            block_size: 0,
+            total_samples: None,
+            block_index: 0,
+            block_samples: 6,
+            flags: 0x1 | 0x10,
+            crc: 0x003e_5c56,
+        };
+
+        let mut samples = Vec::new();
+
+        decode_block(&header, &body, &mut samples).unwrap();
+
+        assert_eq!(samples, [25, -4, 19, 10, -3, -4, -19, 8, -12, 4, -6, -3]);
+
+        header.crc ^= 1;
+        assert!(decode_block(&header, &body, &mut samples).is_err());
+    }
+
+    /// Get the number of ones in the unary prefix of a word, and the range of the word, updating
+    /// the medians in the same way as the entropy decoder.
+    fn word_range(medians: &mut [u32; 3], value: u32) -> (u32, u32, u32) {
+        let median = |medians: &[u32; 3], i: usize| (medians[i] >> 4) + 1;
+        let inc =
+            |medians: &mut [u32; 3], i: usize, div: u32| medians[i] += (medians[i] + div) / div * 5;
+        let dec = |medians: &mut [u32; 3], i: usize, div: u32| {
+            medians[i] -= (medians[i] + div - 2) / div * 2
+        };
+
+        let m0 = median(medians, 0);
+
+        if value < m0 {
+            dec(medians, 0, 128);
+            return (0, 0, m0 - 1);
+        }
+
+        inc(medians, 0, 128);
+
+        let m1 = median(medians, 1);
+
+        if value - m0 < m1 {
+            dec(medians, 1, 64);
+            return (1, m0, m0 + m1 - 1);
+        }
+
+        inc(medians, 1, 64);
+
+        let low = m0 + m1;
+        let m2 = median(medians, 2);
+
+        if value - low < m2 {
+            dec(medians, 2, 32);
+            return (2, low, low + m2 - 1);
+        }
+
+        inc(medians, 2, 32);
+
+        let k = (value - low) / m2;
+        (2 + k, low + k * m2, low + (k + 1) * m2 - 1)
+    }
+
+    /// Entropy code words, alternating between channels if `n_channels` is 2. The medians must be
+    /// large enough that runs of zeros and escape codes are never used.
+    fn encode_words(words: &[i32], n_channels: usize, mut medians: [[u32; 3]; 2]) -> Vec<u8> {
+        let mut coded = Vec::new();
+
+        for (i, &word) in words.iter().enumerate() {
+            let value = if word < 0 { !word as u32 } else { word as u32 };
+            let ch = if n_channels == 2 { i & 1 } else { 0 };
+            let (ones_count, low, high) = word_range(&mut medians[ch], value);
+            coded.push((ones_count, value - low, high - low, u32::from(word < 0)));
+        }
+
+        let mut bs = BitWriterRtl::new();
+        let mut holding_one = 0;
+        let mut holding_zero = false;
+
+        for (i, &(ones_count, code, max_code, sign)) in coded.iter().enumerate() {
+            if holding_zero {
+                assert_eq!(ones_count, 0);
+                holding_zero = false;
+            }
+            else {
+                // The low bit of the unary code signals if the next word has a non-zero prefix.
+                let next_ones = coded.get(i + 1).map_or(0, |next| next.0);
+                let unary = 2 * (ones_count - holding_one) + u32::from(next_ones > 0);
+                assert!(unary < 16);
+
+                bs.write_bits_leq32(u32::MAX, unary);
+                bs.write_bool(false);
+
+                holding_one = unary & 1;
+                holding_zero = holding_one == 0;
+            }
+
+            if max_code >= 2 {
+                let n_bits = 32 - max_code.leading_zeros();
+                let extras = (1 << n_bits) - max_code - 1;
+
+                if code < extras {
+                    bs.write_bits_leq32(code, n_bits - 1);
+                }
+                else {
+                    bs.write_bits_leq32((code + extras) >> 1, n_bits - 1);
+                    bs.write_bits_leq32(code + extras, 1);
+                }
+            }
+            else if max_code == 1 {
+                bs.write_bits_leq32(code, 1);
+            }
+
+            bs.write_bits_leq32(sign, 1);
+        }
+
+        bs.into_inner()
+    }
+
+    /// Build a 16-bit, 44.1 kHz, WavPack block with the given flags and metadata sub-blocks.
+    fn build_block(flags: u32, samples: &[i32], sub_blocks: &[(u8, &[u8])]) -> Vec<u8> {
+        let mut body = Vec::new();
+
+        for &(id, data) in sub_blocks {
+            let odd = data.len() % 2;
+            body.push(id | if odd == 1 { 0x40 } else { 0 });
+            body.push(((data.len() + odd) / 2) as u8);
+            body.extend_from_slice(data);
+            body.resize(body.len() + odd, 0);
+        }
+
+        let n_channels = if flags & 0x4 != 0 || flags & 0x4000_0000 != 0 { 1 } else { 2 };
+        let crc =
+            samples.iter().fold(u32::MAX, |crc, &s| crc.wrapping_mul(3).wrapping_add(s as u32));
+
+        let mut block = WAVPACK_BLOCK_ID.to_vec();
+        block
+            .extend_from_slice(&((WAVPACK_BLOCK_HEADER_LEN - 8 + body.len()) as u32).to_le_bytes());
+        block.extend_from_slice(&0x410u16.to_le_bytes());
+        block.extend_from_slice(&[0, 0]);
+        block.extend_from_slice(&u32::MAX.to_le_bytes());
+        block.extend_from_slice(&0u32.to_le_bytes());
+        block.extend_from_slice(&((samples.len() / n_channels) as u32).to_le_bytes());
+        block.extend_from_slice(&(flags | 0x1 | 0x800 | 0x1000 | (9 << 23)).to_le_bytes());
+        block.extend_from_slice(&crc.to_le_bytes());
+        block.extend_from_slice(&body);
+        block
+    }
+
+    /// Decode a packet containing a single block.
+    fn decode_packet(channels: Channels, block: Vec<u8>) -> Vec<Vec<i32>> {
+        let mut params = CodecParameters::new();
+        params
+            .for_codec(CODEC_TYPE_WAVPACK)
+            .with_sample_rate(44100)
+            .with_channels(channels)
+            .with_max_frames_per_packet(64);
+
+        let mut decoder = WavPackDecoder::try_new(&params, &Default::default()).unwrap();
+
+        let packet = Packet::new_from_slice(0, 0, 0, &block);
+
+        match decoder.decode(&packet).unwrap() {
+            AudioBufferRef::S32(buf) => {
+                (0..buf.spec().channels.count()).map(|ch| buf.chan(ch).to_vec()).collect()
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /// Medians of 512 for both channels, stored as base-2 logarithms.
+    const ENTROPY_VARS: [u8; 12] =
+        [0x00, 0x0a, 0x00, 0x0a, 0x00, 0x0a, 0x00, 0x0a, 0x00, 0x0a, 0x00, 0x0a];
+    const MEDIANS: [[u32; 3]; 2] = [[512; 3]; 2];
+
+    #[test]
+    fn verify_decode_mono_block() {
+        // A pass with a term of 1 and a fixed weight of 1.0 predicts each sample from the previous
+        // sample, therefore the samples are the running sum of the residuals.
+        let residuals = [3, 5, -2, 0, -40, 17, 120, -1, 0, 0, 9, -300];
+
+        let samples: Vec<i32> = residuals
+            .iter()
+            .scan(0, |sum, &r| {
+                *sum += r;
+                Some(*sum)
+            })
+            .collect();
+
+        let bitstream = encode_words(&residuals, 1, MEDIANS);
+
+        let block = build_block(
+            0x4,
+            &samples,
+            &[
+                (ID_DECORR_TERMS, &[0x06]),
+                (ID_DECORR_WEIGHTS, &[0x7f]),
+                (ID_ENTROPY_VARS, &ENTROPY_VARS[..6]),
+                (ID_WV_BITSTREAM, &bitstream),
+            ],
+        );
+
+        let planes = decode_packet(Channels::FRONT_LEFT, block);
+
+        let expected: Vec<i32> = samples.iter().map(|s| s << 16).collect();
+        assert_eq!(planes, [expected]);
+    }
+
+    #[test]
+    fn verify_decode_stereo_block() {
+        let left = [100, -100, 37, 0, 5, -6, 200, 1];
+        let right = [90, -95, 0, 12, -5, 6, 190, -1];
+
+        // Joint stereo codes the difference of the channels, and the right channel plus half the
+        // difference.
+        let mut words = Vec::new();
+
+        for (&l, &r) in left.iter().zip(&right) {
+            let diff = l - r;
+            words.push(diff);
+            words.push(r + (diff >> 1));
+        }
+
+        let bitstream = encode_words(&words, 2, MEDIANS);
+
+        let samples: Vec<i32> = left.iter().zip(&right).flat_map(|(&l, &r)| [l, r]).collect();
+
+        let block = build_block(
+            0x10,
+            &samples,
+            &[(ID_ENTROPY_VARS, &ENTROPY_VARS), (ID_WV_BITSTREAM, &bitstream)],
+        );
+
+        let planes = decode_packet(Channels::FRONT_LEFT | Channels::FRONT_RIGHT, block);
+
+        assert_eq!(planes[0], left.iter().map(|s| s << 16).collect::<Vec<i32>>());
+        assert_eq!(planes[1], right.iter().map(|s| s << 16).collect::<Vec<i32>>());
+    }
+
+    #[test]
+    fn verify_decode_false_stereo_block() {
+        // Both channels are identical, and only one is coded.
+        let samples = [1, -2, 3, -4, 5, -6, 7, -8, 250, -250];
+
+        let bitstream = encode_words(&samples, 1, MEDIANS);
+
+        let block = build_block(
+            0x4000_0000,
+            &samples,
+            &[(ID_ENTROPY_VARS, &ENTROPY_VARS[..6]), (ID_WV_BITSTREAM, &bitstream)],
+        );
+
+        let planes = decode_packet(Channels::FRONT_LEFT | Channels::FRONT_RIGHT, block);
+
+        let expected: Vec<i32> = samples.iter().map(|s| s << 16).collect();
+        assert_eq!(planes, [expected.clone(), expected]);
+    }
+}
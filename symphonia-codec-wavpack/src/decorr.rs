@@ -0,0 +1,213 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The WavPack decorrelation passes.
+
+/// The maximum positive term that predicts from a previous sample of the same channel.
+pub const MAX_TERM: usize = 8;
+
+/// The maximum number of decorrelation passes in a block.
+pub const MAX_NUM_TERMS: usize = 16;
+
+/// The maximum magnitude of the weight of a cross-channel term.
+const MAX_CROSS_WEIGHT: i32 = 1024;
+
+/// A decorrelation pass.
+///
+/// Positive terms of 1 through 8 predict a sample from the sample `term` samples earlier in the
+/// same channel. Terms of 17 and 18 predict a sample by extrapolating from the previous two
+/// samples of the same channel. Negative terms, only valid for stereo blocks, predict a sample
+/// from the other channel.
+#[derive(Clone, Default)]
+pub struct DecorrPass {
+    pub term: i32,
+    pub delta: i32,
+    pub weight_a: i32,
+    pub weight_b: i32,
+    pub samples_a: [i32; MAX_TERM],
+    pub samples_b: [i32; MAX_TERM],
+}
+
+/// Convert a weight from its stored 8-bit form.
+pub fn restore_weight(weight: i8) -> i32 {
+    let weight = i32::from(weight) << 3;
+
+    if weight > 0 {
+        weight + ((weight + 64) >> 7)
+    }
+    else {
+        weight
+    }
+}
+
+/// Apply a weight with 10 fractional bits to a sample.
+#[inline(always)]
+fn apply_weight(weight: i32, sample: i32) -> i32 {
+    ((i64::from(weight) * i64::from(sample) + 512) >> 10) as i32
+}
+
+/// Adapt a weight towards the sign agreement of the source sample and its residual.
+#[inline(always)]
+fn update_weight(weight: &mut i32, delta: i32, source: i32, result: i32) {
+    if source != 0 && result != 0 {
+        if (source ^ result) < 0 {
+            *weight = weight.wrapping_sub(delta);
+        }
+        else {
+            *weight = weight.wrapping_add(delta);
+        }
+    }
+}
+
+/// Like `update_weight`, but for cross-channel terms where the weight is clipped.
+#[inline(always)]
+fn update_weight_clip(weight: &mut i32, delta: i32, source: i32, result: i32) {
+    if source != 0 && result != 0 {
+        if (source ^ result) < 0 {
+            *weight = (*weight - delta).max(-MAX_CROSS_WEIGHT);
+        }
+        else {
+            *weight = (*weight + delta).min(MAX_CROSS_WEIGHT);
+        }
+    }
+}
+
+/// Extrapolate the next sample for terms 17 and 18 given the previous two samples.
+#[inline(always)]
+fn extrapolate(term: i32, samples: &[i32; MAX_TERM]) -> i32 {
+    if term == 17 {
+        samples[0].wrapping_mul(2).wrapping_sub(samples[1])
+    }
+    else {
+        samples[0].wrapping_mul(3).wrapping_sub(samples[1]) >> 1
+    }
+}
+
+impl DecorrPass {
+    /// Undo the decorrelation pass on single channel samples.
+    pub fn decorrelate_mono(&mut self, buf: &mut [i32]) {
+        match self.term {
+            17 | 18 => {
+                for s in buf.iter_mut() {
+                    let sam = extrapolate(self.term, &self.samples_a);
+
+                    self.samples_a[1] = self.samples_a[0];
+                    self.samples_a[0] = apply_weight(self.weight_a, sam).wrapping_add(*s);
+                    update_weight(&mut self.weight_a, self.delta, sam, *s);
+                    *s = self.samples_a[0];
+                }
+            }
+            term => {
+                // The previous samples are stored in a circular buffer.
+                let mut m = 0;
+                let mut k = term as usize & (MAX_TERM - 1);
+
+                for s in buf.iter_mut() {
+                    let sam = self.samples_a[m];
+
+                    self.samples_a[k] = apply_weight(self.weight_a, sam).wrapping_add(*s);
+                    update_weight(&mut self.weight_a, self.delta, sam, *s);
+                    *s = self.samples_a[k];
+
+                    m = (m + 1) & (MAX_TERM - 1);
+                    k = (k + 1) & (MAX_TERM - 1);
+                }
+
+                // Reorder the circular buffer such that it starts at the oldest sample again.
+                self.samples_a.rotate_left(m);
+            }
+        }
+    }
+
+    /// Undo the decorrelation pass on interleaved stereo samples.
+    pub fn decorrelate_stereo(&mut self, buf: &mut [i32]) {
+        match self.term {
+            17 | 18 => {
+                for s in buf.chunks_exact_mut(2) {
+                    let sam = extrapolate(self.term, &self.samples_a);
+
+                    self.samples_a[1] = self.samples_a[0];
+                    self.samples_a[0] = apply_weight(self.weight_a, sam).wrapping_add(s[0]);
+                    update_weight(&mut self.weight_a, self.delta, sam, s[0]);
+                    s[0] = self.samples_a[0];
+
+                    let sam = extrapolate(self.term, &self.samples_b);
+
+                    self.samples_b[1] = self.samples_b[0];
+                    self.samples_b[0] = apply_weight(self.weight_b, sam).wrapping_add(s[1]);
+                    update_weight(&mut self.weight_b, self.delta, sam, s[1]);
+                    s[1] = self.samples_b[0];
+                }
+            }
+            -1 => {
+                // The left channel is predicted from the previous right sample, and the right
+                // channel from the current left sample.
+                for s in buf.chunks_exact_mut(2) {
+                    let sam_a = apply_weight(self.weight_a, self.samples_a[0]).wrapping_add(s[0]);
+                    update_weight_clip(&mut self.weight_a, self.delta, self.samples_a[0], s[0]);
+                    s[0] = sam_a;
+
+                    self.samples_a[0] = apply_weight(self.weight_b, sam_a).wrapping_add(s[1]);
+                    update_weight_clip(&mut self.weight_b, self.delta, sam_a, s[1]);
+                    s[1] = self.samples_a[0];
+                }
+            }
+            -2 => {
+                // The right channel is predicted from the previous left sample, and the left
+                // channel from the current right sample.
+                for s in buf.chunks_exact_mut(2) {
+                    let sam_b = apply_weight(self.weight_b, self.samples_b[0]).wrapping_add(s[1]);
+                    update_weight_clip(&mut self.weight_b, self.delta, self.samples_b[0], s[1]);
+                    s[1] = sam_b;
+
+                    self.samples_b[0] = apply_weight(self.weight_a, sam_b).wrapping_add(s[0]);
+                    update_weight_clip(&mut self.weight_a, self.delta, sam_b, s[0]);
+                    s[0] = self.samples_b[0];
+                }
+            }
+            -3 => {
+                // Each channel is predicted from the previous sample of the other channel.
+                for s in buf.chunks_exact_mut(2) {
+                    let sam_a = apply_weight(self.weight_a, self.samples_a[0]).wrapping_add(s[0]);
+                    update_weight_clip(&mut self.weight_a, self.delta, self.samples_a[0], s[0]);
+
+                    let sam_b = apply_weight(self.weight_b, self.samples_b[0]).wrapping_add(s[1]);
+                    update_weight_clip(&mut self.weight_b, self.delta, self.samples_b[0], s[1]);
+
+                    self.samples_b[0] = sam_a;
+                    self.samples_a[0] = sam_b;
+                    s[0] = sam_a;
+                    s[1] = sam_b;
+                }
+            }
+            term => {
+                let mut m = 0;
+                let mut k = term as usize & (MAX_TERM - 1);
+
+                for s in buf.chunks_exact_mut(2) {
+                    let sam = self.samples_a[m];
+
+                    self.samples_a[k] = apply_weight(self.weight_a, sam).wrapping_add(s[0]);
+                    update_weight(&mut self.weight_a, self.delta, sam, s[0]);
+                    s[0] = self.samples_a[k];
+
+                    let sam = self.samples_b[m];
+
+                    self.samples_b[k] = apply_weight(self.weight_b, sam).wrapping_add(s[1]);
+                    update_weight(&mut self.weight_b, self.delta, sam, s[1]);
+                    s[1] = self.samples_b[k];
+
+                    m = (m + 1) & (MAX_TERM - 1);
+                    k = (k + 1) & (MAX_TERM - 1);
+                }
+
+                self.samples_a.rotate_left(m);
+                self.samples_b.rotate_left(m);
+            }
+        }
+    }
+}
@@ -0,0 +1,498 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::io::{Seek, SeekFrom};
+
+use symphonia_core::audio::Channels;
+use symphonia_core::codecs::{CodecParameters, CODEC_TYPE_WAVPACK};
+use symphonia_core::errors::{decode_error, end_of_stream_error, seek_error, unsupported_error};
+use symphonia_core::errors::{Result, SeekErrorKind};
+use symphonia_core::formats::prelude::*;
+use symphonia_core::io::*;
+use symphonia_core::meta::{Metadata, MetadataLog};
+use symphonia_core::probe::{Descriptor, Instantiate, QueryDescriptor};
+use symphonia_core::sample::SampleFormat;
+use symphonia_core::support_format;
+
+use symphonia_metadata::trailing::read_trailing_tags;
+
+use log::{debug, warn};
+
+use crate::header::*;
+
+/// A block header, and the raw bytes it was read from.
+struct RawBlockHeader {
+    pos: u64,
+    buf: [u8; WAVPACK_BLOCK_HEADER_LEN],
+    header: BlockHeader,
+}
+
+/// A frame made up of one or more blocks sharing the same block index. Streams with more than two
+/// channels are coded as multiple blocks of one or two channels each.
+struct Frame {
+    pos: u64,
+    ts: u64,
+    dur: u64,
+    data: Vec<u8>,
+}
+
+/// WavPack native format reader.
+///
+/// `WavPackReader` implements a demuxer for the native WavPack format.
+pub struct WavPackReader {
+    reader: MediaSourceStream,
+    tracks: Vec<Track>,
+    cues: Vec<Cue>,
+    metadata: MetadataLog,
+    /// The position of the first block.
+    first_block_pos: u64,
+    /// The position of the end of the audio blocks, excluding any trailing tags.
+    data_end_pos: u64,
+    /// A frame that was read, but not yet returned.
+    pending: Option<Frame>,
+    /// The timestamp of the next frame.
+    next_ts: u64,
+}
+
+impl QueryDescriptor for WavPackReader {
+    fn query() -> &'static [Descriptor] {
+        &[support_format!(
+            "wavpack",
+            "WavPack",
+            &["wv"],
+            &["audio/wavpack", "audio/x-wavpack"],
+            &[b"wvpk"]
+        )]
+    }
+
+    fn score(_context: &[u8]) -> u8 {
+        255
+    }
+}
+
+/// Read the next block header, synchronizing to the next block if the reader is not positioned
+/// at one. Returns `None` at the end of the audio blocks.
+fn read_block_header(reader: &mut MediaSourceStream, end: u64) -> Result<Option<RawBlockHeader>> {
+    let mut buf = [0; WAVPACK_BLOCK_HEADER_LEN];
+
+    loop {
+        if reader.pos() + WAVPACK_BLOCK_HEADER_LEN as u64 > end {
+            return Ok(None);
+        }
+
+        // Synchronize to the block identifier.
+        let mut marker = reader.read_quad_bytes()?;
+
+        while marker != WAVPACK_BLOCK_ID {
+            if reader.pos() + WAVPACK_BLOCK_HEADER_LEN as u64 - 4 > end {
+                return Ok(None);
+            }
+
+            marker.copy_within(1.., 0);
+            marker[3] = reader.read_u8()?;
+        }
+
+        let pos = reader.pos() - 4;
+
+        buf[..4].copy_from_slice(&marker);
+        reader.read_buf_exact(&mut buf[4..])?;
+
+        match BlockHeader::read(&mut BufReader::new(&buf)) {
+            Ok(header) => return Ok(Some(RawBlockHeader { pos, buf, header })),
+            // Likely a false synchronization, try again after the false block identifier.
+            Err(err) => {
+                warn!("wavpack: invalid block header at {}: {}", pos, err);
+                reader.seek_buffered(pos + 4);
+            }
+        }
+    }
+}
+
+/// Read the remainder of a frame starting at the given initial block header.
+fn read_frame(reader: &mut MediaSourceStream, end: u64, first: RawBlockHeader) -> Result<Frame> {
+    let pos = first.pos;
+    let ts = first.header.block_index;
+    let dur = u64::from(first.header.block_samples);
+
+    let mut data = Vec::new();
+    let mut block = first;
+
+    loop {
+        data.extend_from_slice(&block.buf);
+
+        let body_pos = data.len();
+        data.resize(body_pos + block.header.body_len(), 0);
+        reader.read_buf_exact(&mut data[body_pos..])?;
+
+        if block.header.is_final_block() {
+            break;
+        }
+
+        block = match read_block_header(reader, end)? {
+            Some(block) => block,
+            None => return decode_error("wavpack: truncated frame"),
+        };
+
+        if block.header.block_index != ts || block.header.is_initial_block() {
+            return decode_error("wavpack: incomplete frame");
+        }
+    }
+
+    Ok(Frame { pos, ts, dur, data })
+}
+
+/// Read the next frame with audio. Blocks containing only metadata, and blocks that do not start
+/// a frame, are skipped.
+fn read_next_frame(reader: &mut MediaSourceStream, end: u64) -> Result<Option<Frame>> {
+    while let Some(block) = read_block_header(reader, end)? {
+        if block.header.block_samples > 0 && block.header.is_initial_block() {
+            return read_frame(reader, end, block).map(Some);
+        }
+
+        reader.ignore_bytes(block.header.body_len() as u64)?;
+    }
+
+    Ok(None)
+}
+
+/// Build the codec parameters from the blocks of the first frame.
+fn codec_params(frame: &Frame) -> Result<CodecParameters> {
+    let mut reader = BufReader::new(&frame.data);
+
+    let mut num_channels = 0;
+    let mut first_header = None;
+    let mut first_metadata = None;
+
+    while reader.pos() < frame.data.len() as u64 {
+        let header = BlockHeader::read(&mut reader)?;
+        let body = reader.read_buf_bytes_ref(header.body_len())?;
+
+        if header.is_dsd() {
+            return unsupported_error("wavpack: dsd audio is not supported");
+        }
+
+        num_channels += header.num_channels();
+
+        // The stream parameters are stored in the initial block.
+        if first_header.is_none() {
+            first_header = Some(header);
+            first_metadata = Some(BlockMetadata::read(body)?);
+        }
+    }
+
+    let (header, metadata) = match (first_header, first_metadata) {
+        (Some(header), Some(metadata)) => (header, metadata),
+        _ => return decode_error("wavpack: empty frame"),
+    };
+
+    let sample_rate = match header.sample_rate().or(metadata.sample_rate) {
+        Some(sample_rate) if sample_rate > 0 => sample_rate,
+        _ => return decode_error("wavpack: unknown sample rate"),
+    };
+
+    // Use the channel mask if it agrees with the number of coded channels. Otherwise, assign
+    // channel positions in-order.
+    let channels = match metadata.channel_info {
+        Some((count, mask)) if count == num_channels && mask.count_ones() == count => {
+            Channels::from_bits(mask)
+        }
+        _ if num_channels <= 32 => Channels::from_bits(u32::MAX >> (32 - num_channels)),
+        _ => None,
+    };
+
+    let channels = match channels {
+        Some(channels) => channels,
+        None => return unsupported_error("wavpack: unsupported channel layout"),
+    };
+
+    let mut params = CodecParameters::new();
+
+    params
+        .for_codec(CODEC_TYPE_WAVPACK)
+        .with_sample_rate(sample_rate)
+        .with_time_base(TimeBase::new(1, sample_rate))
+        .with_channels(channels)
+        .with_max_frames_per_packet(frame.dur);
+
+    if header.is_float() {
+        params.with_sample_format(SampleFormat::F32).with_bits_per_sample(32);
+    }
+    else {
+        params.with_bits_per_sample(8 * header.bytes_per_sample());
+    }
+
+    if let Some(total_samples) = header.total_samples {
+        params.with_n_frames(total_samples);
+    }
+
+    Ok(params)
+}
+
+impl FormatReader for WavPackReader {
+    fn try_new(mut source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
+        // The stream should start with a block.
+        let marker = source.read_quad_bytes()?;
+
+        if marker != WAVPACK_BLOCK_ID {
+            return unsupported_error("wavpack: missing wvpk stream marker");
+        }
+
+        source.seek_buffered_rel(-4);
+
+        let first_block_pos = source.pos();
+
+        let mut metadata: MetadataLog = Default::default();
+
+        // If the source is seekable, read the tags at the end of the stream, and then return to
+        // the first block.
+        let data_end_pos = if source.is_seekable() {
+            let data_end_pos = match read_trailing_tags(&mut source, &mut metadata) {
+                Ok(data_end_pos) => data_end_pos.unwrap_or(u64::MAX),
+                Err(err) => {
+                    warn!("wavpack: failed to read tags: {}", err);
+                    u64::MAX
+                }
+            };
+
+            source.seek(SeekFrom::Start(first_block_pos))?;
+
+            data_end_pos
+        }
+        else {
+            u64::MAX
+        };
+
+        // The stream parameters can only be determined from a complete frame since multichannel
+        // streams are coded as multiple blocks. The frame is held until the first call to
+        // next_packet.
+        let frame = match read_next_frame(&mut source, data_end_pos)? {
+            Some(frame) => frame,
+            None => return decode_error("wavpack: no audio blocks"),
+        };
+
+        let params = codec_params(&frame)?;
+
+        Ok(WavPackReader {
+            reader: source,
+            tracks: vec![Track::new(0, params)],
+            cues: Vec::new(),
+            metadata,
+            first_block_pos,
+            data_end_pos,
+            next_ts: frame.ts,
+            pending: Some(frame),
+        })
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        let frame = match self.pending.take() {
+            Some(frame) => frame,
+            None => match read_next_frame(&mut self.reader, self.data_end_pos)? {
+                Some(frame) => frame,
+                None => return end_of_stream_error(),
+            },
+        };
+
+        self.next_ts = frame.ts + frame.dur;
+
+        Ok(Packet::new_from_boxed_slice(0, frame.ts, frame.dur, frame.data.into_boxed_slice())
+            .with_pos(frame.pos))
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        let params = &self.tracks[0].codec_params;
+
+        let required_ts = match to {
+            // Frame timestamp given.
+            SeekTo::TimeStamp { ts, .. } => ts,
+            // Time value given, calculate frame timestamp from sample rate.
+            SeekTo::Time { time, .. } => {
+                // The sample rate is always known.
+                TimeBase::new(1, params.sample_rate.unwrap()).calc_timestamp(time)
+            }
+        };
+
+        // If the total number of frames in the track is known, verify the desired frame timestamp
+        // does not exceed it.
+        if let Some(n_frames) = params.n_frames {
+            if required_ts > n_frames {
+                return seek_error(SeekErrorKind::OutOfRange);
+            }
+        }
+
+        debug!("seeking to frame_ts={}", required_ts);
+
+        // A pending frame starts at the next timestamp, and can be used if it contains the
+        // required timestamp.
+        let frame_start_ts = match &self.pending {
+            Some(frame) => frame.ts,
+            None => self.next_ts,
+        };
+
+        // WavPack streams have no index. Therefore, blocks must be scanned from the current
+        // position, or the start of the stream if the required timestamp is in the past.
+        if required_ts < frame_start_ts {
+            if !self.reader.is_seekable() {
+                return seek_error(SeekErrorKind::ForwardOnly);
+            }
+
+            self.reader.seek(SeekFrom::Start(self.first_block_pos))?;
+            self.pending = None;
+        }
+
+        if let Some(frame) = &self.pending {
+            if required_ts < frame.ts + frame.dur {
+                let actual_ts = frame.ts;
+                return Ok(SeekedTo { track_id: 0, actual_ts, required_ts });
+            }
+
+            self.pending = None;
+        }
+
+        // Skip blocks until the initial block of the frame containing the required timestamp.
+        loop {
+            let block = match read_block_header(&mut self.reader, self.data_end_pos)? {
+                Some(block) => block,
+                None => return seek_error(SeekErrorKind::OutOfRange),
+            };
+
+            let header = &block.header;
+
+            let end_ts = header.block_index + u64::from(header.block_samples);
+
+            if header.block_samples > 0 && header.is_initial_block() && required_ts < end_ts {
+                let frame = read_frame(&mut self.reader, self.data_end_pos, block)?;
+
+                let actual_ts = frame.ts;
+
+                self.next_ts = frame.ts;
+                self.pending = Some(frame);
+
+                debug!("seeked to frame_ts={}", actual_ts);
+
+                return Ok(SeekedTo { track_id: 0, actual_ts, required_ts });
+            }
+
+            self.reader.ignore_bytes(header.body_len() as u64)?;
+        }
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.reader
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use symphonia_core::audio::Channels;
+    use symphonia_core::formats::{FormatReader, SeekMode, SeekTo};
+    use symphonia_core::io::MediaSourceStream;
+    use symphonia_core::meta::StandardTagKey;
+
+    use super::WavPackReader;
+
+    const FLAG_16_BIT: u32 = 0x1;
+    const FLAG_MONO: u32 = 0x4;
+    const FLAG_INITIAL: u32 = 0x800;
+    const FLAG_FINAL: u32 = 0x1000;
+    const FLAG_44100_HZ: u32 = 9 << 23;
+
+    fn write_block(wv: &mut Vec<u8>, index: u32, samples: u32, flags: u32, body: &[u8]) {
+        wv.extend_from_slice(b"wvpk");
+        wv.extend_from_slice(&(24 + body.len() as u32).to_le_bytes());
+        wv.extend_from_slice(&0x410u16.to_le_bytes());
+        wv.extend_from_slice(&[0, 0]);
+        // 3 frames of 4096 samples.
+        wv.extend_from_slice(&(3 * 4096u32).to_le_bytes());
+        wv.extend_from_slice(&index.to_le_bytes());
+        wv.extend_from_slice(&samples.to_le_bytes());
+        wv.extend_from_slice(&(flags | FLAG_16_BIT | FLAG_44100_HZ).to_le_bytes());
+        wv.extend_from_slice(&0u32.to_le_bytes());
+        wv.extend_from_slice(body);
+    }
+
+    #[test]
+    fn verify_wavpack_reader() {
+        let mut wv = Vec::new();
+
+        for i in 0..3 {
+            let index = 4096 * i;
+
+            // A stereo block, with channel info for 3 channels (front-left, front-right, and
+            // front-centre), followed by a mono block.
+            let body = [0x0d | 0x40, 0x02, 0x03, 0x07, 0x00, 0x00, 0x0a, 0x01, 0xaa, 0xbb];
+            write_block(&mut wv, index, 4096, FLAG_INITIAL, &body);
+            write_block(&mut wv, index, 4096, FLAG_MONO | FLAG_FINAL, &[0x0a, 0x01, 0xcc, 0xdd]);
+
+            // A metadata only block between frames.
+            if i == 0 {
+                write_block(&mut wv, 0, 0, FLAG_INITIAL | FLAG_FINAL, &[0, 0]);
+            }
+        }
+
+        let frame_len = 2 * 32 + 10 + 4;
+
+        // An APEv2 tag footer with a title.
+        let mut item = 4u32.to_le_bytes().to_vec();
+        item.extend_from_slice(&[0; 4]);
+        item.extend_from_slice(b"Title\0Song");
+
+        wv.extend_from_slice(b"APETAGEX");
+        wv.extend_from_slice(&2000u32.to_le_bytes());
+        wv.extend_from_slice(&(item.len() as u32 + 32).to_le_bytes());
+        wv.extend_from_slice(&1u32.to_le_bytes());
+        wv.extend_from_slice(&[0; 12]);
+        let footer = wv.split_off(wv.len() - 32);
+        wv.extend_from_slice(&item);
+        wv.extend_from_slice(&footer);
+
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(wv)), Default::default());
+        let mut reader = WavPackReader::try_new(mss, &Default::default()).unwrap();
+
+        let params = &reader.tracks()[0].codec_params;
+        assert_eq!(params.sample_rate, Some(44100));
+        assert_eq!(params.bits_per_sample, Some(16));
+        assert_eq!(params.n_frames, Some(3 * 4096));
+        assert_eq!(
+            params.channels,
+            Some(Channels::FRONT_LEFT | Channels::FRONT_RIGHT | Channels::FRONT_CENTRE)
+        );
+
+        let metadata = reader.metadata();
+        let tags = metadata.current().unwrap().tags();
+        assert_eq!(tags[0].std_key, Some(StandardTagKey::TrackTitle));
+
+        for i in 0..3 {
+            let packet = reader.next_packet().unwrap();
+            assert_eq!(packet.ts, 4096 * i);
+            assert_eq!(packet.dur, 4096);
+            assert_eq!(packet.data.len(), frame_len);
+        }
+        assert!(reader.next_packet().is_err());
+
+        let seeked = reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 5000, track_id: 0 });
+        assert_eq!(seeked.unwrap().actual_ts, 4096);
+
+        let packet = reader.next_packet().unwrap();
+        assert_eq!(packet.ts, 4096);
+        assert_eq!(packet.data.len(), frame_len);
+    }
+}
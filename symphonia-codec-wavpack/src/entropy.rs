@@ -0,0 +1,237 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The WavPack entropy decoder for lossless blocks.
+
+use symphonia_core::errors::{decode_error, Result};
+use symphonia_core::io::{BitReaderRtl, ReadBitsRtl};
+
+/// The maximum number of ones in the unary prefix of a word before an escape code follows.
+const LIMIT_ONES: u32 = 16;
+
+/// The rate at which each median adapts. A larger divisor adapts more slowly.
+const MEDIAN_DIVS: [u32; 3] = [128, 64, 32];
+
+/// The fractional part of 2^x in 1/256ths, for x in [0, 1) in 1/256ths.
+#[rustfmt::skip]
+const EXP2_TABLE: [u8; 256] = [
+    0x00, 0x01, 0x01, 0x02, 0x03, 0x03, 0x04, 0x05, 0x06, 0x06, 0x07, 0x08, 0x08, 0x09, 0x0a, 0x0b,
+    0x0b, 0x0c, 0x0d, 0x0e, 0x0e, 0x0f, 0x10, 0x10, 0x11, 0x12, 0x13, 0x13, 0x14, 0x15, 0x16, 0x16,
+    0x17, 0x18, 0x19, 0x19, 0x1a, 0x1b, 0x1c, 0x1d, 0x1d, 0x1e, 0x1f, 0x20, 0x20, 0x21, 0x22, 0x23,
+    0x24, 0x24, 0x25, 0x26, 0x27, 0x28, 0x28, 0x29, 0x2a, 0x2b, 0x2c, 0x2c, 0x2d, 0x2e, 0x2f, 0x30,
+    0x30, 0x31, 0x32, 0x33, 0x34, 0x35, 0x35, 0x36, 0x37, 0x38, 0x39, 0x3a, 0x3a, 0x3b, 0x3c, 0x3d,
+    0x3e, 0x3f, 0x40, 0x41, 0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48, 0x48, 0x49, 0x4a, 0x4b,
+    0x4c, 0x4d, 0x4e, 0x4f, 0x50, 0x51, 0x51, 0x52, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5a,
+    0x5b, 0x5c, 0x5d, 0x5e, 0x5e, 0x5f, 0x60, 0x61, 0x62, 0x63, 0x64, 0x65, 0x66, 0x67, 0x68, 0x69,
+    0x6a, 0x6b, 0x6c, 0x6d, 0x6e, 0x6f, 0x70, 0x71, 0x72, 0x73, 0x74, 0x75, 0x76, 0x77, 0x78, 0x79,
+    0x7a, 0x7b, 0x7c, 0x7d, 0x7e, 0x7f, 0x80, 0x81, 0x82, 0x83, 0x84, 0x85, 0x87, 0x88, 0x89, 0x8a,
+    0x8b, 0x8c, 0x8d, 0x8e, 0x8f, 0x90, 0x91, 0x92, 0x93, 0x95, 0x96, 0x97, 0x98, 0x99, 0x9a, 0x9b,
+    0x9c, 0x9d, 0x9f, 0xa0, 0xa1, 0xa2, 0xa3, 0xa4, 0xa5, 0xa6, 0xa8, 0xa9, 0xaa, 0xab, 0xac, 0xad,
+    0xaf, 0xb0, 0xb1, 0xb2, 0xb3, 0xb4, 0xb6, 0xb7, 0xb8, 0xb9, 0xba, 0xbc, 0xbd, 0xbe, 0xbf, 0xc0,
+    0xc2, 0xc3, 0xc4, 0xc5, 0xc6, 0xc8, 0xc9, 0xca, 0xcb, 0xcd, 0xce, 0xcf, 0xd0, 0xd2, 0xd3, 0xd4,
+    0xd6, 0xd7, 0xd8, 0xd9, 0xdb, 0xdc, 0xdd, 0xde, 0xe0, 0xe1, 0xe2, 0xe4, 0xe5, 0xe6, 0xe8, 0xe9,
+    0xea, 0xec, 0xed, 0xee, 0xf0, 0xf1, 0xf2, 0xf4, 0xf5, 0xf6, 0xf8, 0xf9, 0xfa, 0xfc, 0xfd, 0xff,
+];
+
+/// Convert a signed base-2 logarithm with 8 fractional bits back into a linear value.
+pub fn exp2s(log: i32) -> i32 {
+    if log < 0 {
+        return exp2s(log.wrapping_neg()).wrapping_neg();
+    }
+
+    let value = u32::from(EXP2_TABLE[(log & 0xff) as usize]) | 0x100;
+    let exp = (log >> 8) as u32;
+
+    if exp <= 9 {
+        (value >> (9 - exp)) as i32
+    }
+    else {
+        value.wrapping_shl(exp - 9) as i32
+    }
+}
+
+/// Read an Elias gamma-like code: a unary count of the total number of bits, followed by all the
+/// bits of the value except its implied most-significant bit.
+fn read_escape(bs: &mut BitReaderRtl<'_>) -> Result<u32> {
+    let n_bits = bs.read_unary_ones_capped(33)?;
+
+    match n_bits {
+        0 | 1 => Ok(n_bits),
+        33 => decode_error("wavpack: invalid escape code"),
+        _ => Ok(bs.read_bits_leq32(n_bits - 1)? | (1 << (n_bits - 1))),
+    }
+}
+
+/// Read a value in the range [0, max_code] using a truncated binary code.
+fn read_code(bs: &mut BitReaderRtl<'_>, max_code: u32) -> Result<u32> {
+    if max_code < 2 {
+        return if max_code == 1 { Ok(bs.read_bit()?) } else { Ok(0) };
+    }
+
+    let n_bits = 32 - max_code.leading_zeros();
+
+    // The number of unused codes in the range of n_bits. The first `extras` values are coded with
+    // one less bit.
+    let extras = ((1u64 << n_bits) - u64::from(max_code) - 1) as u32;
+
+    let code = bs.read_bits_leq32(n_bits - 1)?;
+
+    if code >= extras {
+        Ok((code << 1).wrapping_sub(extras).wrapping_add(bs.read_bit()?))
+    }
+    else {
+        Ok(code)
+    }
+}
+
+/// The state of the entropy decoder of a block.
+pub struct WordsDecoder {
+    /// The adaptive medians of each channel.
+    medians: [[u32; 3]; 2],
+    /// The number of zero words remaining in the current run of zeros.
+    zeros_acc: u32,
+    /// The next word has no unary prefix.
+    holding_zero: bool,
+    /// The next word has an additional one in its unary prefix.
+    holding_one: bool,
+}
+
+impl WordsDecoder {
+    /// Instantiate an entropy decoder with the initial medians of each channel.
+    pub fn new(medians: [[u32; 3]; 2]) -> Self {
+        WordsDecoder { medians, zeros_acc: 0, holding_zero: false, holding_one: false }
+    }
+
+    /// Decode words into `out`. If `n_channels` is 2, words alternate between the channels.
+    pub fn decode(
+        &mut self,
+        bs: &mut BitReaderRtl<'_>,
+        out: &mut [i32],
+        n_channels: usize,
+    ) -> Result<()> {
+        for (i, word) in out.iter_mut().enumerate() {
+            // When the medians of both channels are very small, runs of zeros are coded instead.
+            if self.medians[0][0] < 2
+                && self.medians[1][0] < 2
+                && !self.holding_zero
+                && !self.holding_one
+            {
+                if self.zeros_acc > 0 {
+                    self.zeros_acc -= 1;
+
+                    if self.zeros_acc > 0 {
+                        *word = 0;
+                        continue;
+                    }
+                }
+                else {
+                    self.zeros_acc = read_escape(bs)?;
+
+                    if self.zeros_acc > 0 {
+                        self.medians = [[0; 3]; 2];
+                        *word = 0;
+                        continue;
+                    }
+                }
+            }
+
+            let ones_count = self.read_ones_count(bs)?;
+
+            let medians = &mut self.medians[if n_channels == 2 { i & 1 } else { 0 }];
+
+            // The unary prefix selects the range of the word, where the first three ranges are
+            // sized by their median, and all subsequent ranges are sized by the third median.
+            let (low, high) = if ones_count == 0 {
+                let high = get_median(medians, 0) - 1;
+                dec_median(medians, 0);
+                (0, high)
+            }
+            else {
+                let mut low = get_median(medians, 0);
+                inc_median(medians, 0);
+
+                if ones_count == 1 {
+                    let high = low.wrapping_add(get_median(medians, 1) - 1);
+                    dec_median(medians, 1);
+                    (low, high)
+                }
+                else {
+                    low = low.wrapping_add(get_median(medians, 1));
+                    inc_median(medians, 1);
+
+                    if ones_count == 2 {
+                        let high = low.wrapping_add(get_median(medians, 2) - 1);
+                        dec_median(medians, 2);
+                        (low, high)
+                    }
+                    else {
+                        let step = get_median(medians, 2);
+                        low = low.wrapping_add((ones_count - 2).wrapping_mul(step));
+                        inc_median(medians, 2);
+                        (low, low.wrapping_add(step - 1))
+                    }
+                }
+            };
+
+            let value = low.wrapping_add(read_code(bs, high.wrapping_sub(low))?);
+
+            *word = if bs.read_bool()? { !value as i32 } else { value as i32 };
+        }
+
+        Ok(())
+    }
+
+    /// Read the number of ones in the unary prefix of the next word.
+    fn read_ones_count(&mut self, bs: &mut BitReaderRtl<'_>) -> Result<u32> {
+        if self.holding_zero {
+            self.holding_zero = false;
+            return Ok(0);
+        }
+
+        let mut ones_count = bs.read_unary_ones_capped(LIMIT_ONES + 1)?;
+
+        if ones_count > LIMIT_ONES {
+            return decode_error("wavpack: invalid word prefix");
+        }
+        else if ones_count == LIMIT_ONES {
+            ones_count = read_escape(bs)?.wrapping_add(LIMIT_ONES);
+        }
+
+        // The unary code is twice the number of ones of the word prefix. If it is odd, a one is
+        // carried into the prefix of the next word. Otherwise, the prefix of the next word is
+        // zero and is not coded.
+        ones_count = if self.holding_one {
+            self.holding_one = ones_count & 1 != 0;
+            (ones_count >> 1) + 1
+        }
+        else {
+            self.holding_one = ones_count & 1 != 0;
+            ones_count >> 1
+        };
+
+        self.holding_zero = !self.holding_one;
+
+        Ok(ones_count)
+    }
+}
+
+#[inline(always)]
+fn get_median(medians: &[u32; 3], i: usize) -> u32 {
+    (medians[i] >> 4) + 1
+}
+
+#[inline(always)]
+fn inc_median(medians: &mut [u32; 3], i: usize) {
+    let div = MEDIAN_DIVS[i];
+    medians[i] = medians[i].wrapping_add((medians[i].wrapping_add(div) / div).wrapping_mul(5));
+}
+
+#[inline(always)]
+fn dec_median(medians: &mut [u32; 3], i: usize) {
+    let div = MEDIAN_DIVS[i];
+    medians[i] = medians[i].wrapping_sub((medians[i].wrapping_add(div - 2) / div) * 2);
+}
@@ -0,0 +1,364 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::errors::{decode_error, unsupported_error, Result};
+use symphonia_core::io::{BufReader, ReadBytes};
+
+/// The identifier that starts every WavPack block.
+pub const WAVPACK_BLOCK_ID: [u8; 4] = *b"wvpk";
+
+/// The length of a WavPack block header in bytes.
+pub const WAVPACK_BLOCK_HEADER_LEN: usize = 32;
+
+/// The minimum, and maximum, supported stream versions.
+const MIN_STREAM_VERSION: u16 = 0x402;
+const MAX_STREAM_VERSION: u16 = 0x410;
+
+/// The maximum length of a block, as set by the reference encoder.
+const MAX_BLOCK_LEN: u32 = 1 << 20;
+
+/// Block flag: the number of bytes per sample, minus one.
+const FLAG_BYTES_STORED: u32 = 0x3;
+/// Block flag: the block contains a single channel.
+const FLAG_MONO: u32 = 0x4;
+/// Block flag: the block is coded in hybrid (lossy) mode.
+const FLAG_HYBRID: u32 = 0x8;
+/// Block flag: the stereo channels are coded as mid and side channels.
+const FLAG_JOINT_STEREO: u32 = 0x10;
+/// Block flag: the samples are floating point.
+const FLAG_FLOAT_DATA: u32 = 0x80;
+/// Block flag: the samples are 32-bit integers with extra information in a metadata sub-block.
+const FLAG_INT32_DATA: u32 = 0x100;
+/// Block flag: the block is the first block of a multichannel frame.
+const FLAG_INITIAL_BLOCK: u32 = 0x800;
+/// Block flag: the block is the last block of a multichannel frame.
+const FLAG_FINAL_BLOCK: u32 = 0x1000;
+/// Block flag: the number of bits the decoded samples must be shifted left by.
+const FLAG_SHIFT_MASK: u32 = 0x0003_e000;
+const FLAG_SHIFT_SHIFT: u32 = 13;
+/// Block flag: the index of the sample rate in the standard sample rate table.
+const FLAG_SAMPLE_RATE_MASK: u32 = 0x0780_0000;
+const FLAG_SAMPLE_RATE_SHIFT: u32 = 23;
+/// Block flag: the block is stereo, but both channels are identical and coded as one.
+const FLAG_FALSE_STEREO: u32 = 0x4000_0000;
+/// Block flag: the block contains DSD audio.
+const FLAG_DSD: u32 = 0x8000_0000;
+
+/// The standard sample rates, indexed by the sample rate index of the block flags. Index 15
+/// indicates a non-standard sample rate stored in a metadata sub-block.
+const SAMPLE_RATES: [u32; 15] = [
+    6000, 8000, 9600, 11025, 12000, 16000, 22050, 24000, 32000, 44100, 48000, 64000, 88200, 96000,
+    192000,
+];
+
+/// Metadata sub-block identifier: the decorrelation terms and deltas.
+pub const ID_DECORR_TERMS: u8 = 0x02;
+/// Metadata sub-block identifier: the initial decorrelation weights.
+pub const ID_DECORR_WEIGHTS: u8 = 0x03;
+/// Metadata sub-block identifier: the initial decorrelation samples.
+pub const ID_DECORR_SAMPLES: u8 = 0x04;
+/// Metadata sub-block identifier: the initial entropy coder medians.
+pub const ID_ENTROPY_VARS: u8 = 0x05;
+/// Metadata sub-block identifier: the parameters of 32-bit integer data.
+pub const ID_INT32_INFO: u8 = 0x09;
+/// Metadata sub-block identifier: the entropy coded audio bitstream.
+pub const ID_WV_BITSTREAM: u8 = 0x0a;
+/// Metadata sub-block identifier: the channel count and channel mask.
+const ID_CHANNEL_INFO: u8 = 0x0d;
+/// Metadata sub-block identifier: a non-standard sample rate.
+const ID_SAMPLE_RATE: u8 = 0x27;
+
+/// Metadata sub-block identifier flag: the sub-block has a 24-bit length.
+const ID_LARGE: u8 = 0x80;
+/// Metadata sub-block identifier flag: the last byte of the sub-block is padding.
+const ID_ODD_SIZE: u8 = 0x40;
+/// The mask for the function of a metadata sub-block.
+const ID_FUNCTION_MASK: u8 = 0x3f;
+
+/// A WavPack block header.
+#[derive(Copy, Clone, Debug)]
+pub struct BlockHeader {
+    /// The length of the block in bytes, excluding the block identifier and length fields.
+    pub block_size: u32,
+    /// The total number of samples (audio frames) in the stream, if known.
+    pub total_samples: Option<u64>,
+    /// The index of the first sample (audio frame) in the block.
+    pub block_index: u64,
+    /// The number of samples (audio frames) in the block. Blocks with no samples only contain
+    /// metadata.
+    pub block_samples: u32,
+    /// The block flags.
+    pub flags: u32,
+    /// The CRC of the decoded samples.
+    pub crc: u32,
+}
+
+impl BlockHeader {
+    /// Read a block header, including the block identifier.
+    pub fn read<B: ReadBytes>(reader: &mut B) -> Result<BlockHeader> {
+        if reader.read_quad_bytes()? != WAVPACK_BLOCK_ID {
+            return decode_error("wavpack: missing block id");
+        }
+
+        let block_size = reader.read_u32()?;
+        let version = reader.read_u16()?;
+        let block_index_u8 = reader.read_u8()?;
+        let total_samples_u8 = reader.read_u8()?;
+        let total_samples = reader.read_u32()?;
+        let block_index = reader.read_u32()?;
+        let block_samples = reader.read_u32()?;
+        let flags = reader.read_u32()?;
+        let crc = reader.read_u32()?;
+
+        if version < MIN_STREAM_VERSION || version > MAX_STREAM_VERSION {
+            return unsupported_error("wavpack: unsupported stream version");
+        }
+
+        if (block_size as usize) < WAVPACK_BLOCK_HEADER_LEN - 8 || block_size > MAX_BLOCK_LEN {
+            return decode_error("wavpack: invalid block size");
+        }
+
+        // A total number of samples of -1 indicates the total is unknown. Otherwise, the upper 8
+        // bits are stored separately, and are offset by the upper 8 bits themselves.
+        let total_samples = if total_samples == u32::MAX {
+            None
+        }
+        else {
+            Some(
+                (u64::from(total_samples_u8) << 32) + u64::from(total_samples)
+                    - u64::from(total_samples_u8),
+            )
+        };
+
+        let block_index = (u64::from(block_index_u8) << 32) | u64::from(block_index);
+
+        Ok(BlockHeader { block_size, total_samples, block_index, block_samples, flags, crc })
+    }
+
+    /// Gets the length of the block body in bytes, the data following the block header.
+    pub fn body_len(&self) -> usize {
+        self.block_size as usize + 8 - WAVPACK_BLOCK_HEADER_LEN
+    }
+
+    /// Returns true if the block is the first block of a frame.
+    pub fn is_initial_block(&self) -> bool {
+        self.flags & FLAG_INITIAL_BLOCK != 0
+    }
+
+    /// Returns true if the block is the last block of a frame.
+    pub fn is_final_block(&self) -> bool {
+        self.flags & FLAG_FINAL_BLOCK != 0
+    }
+
+    /// Returns true if the block contains DSD audio.
+    pub fn is_dsd(&self) -> bool {
+        self.flags & FLAG_DSD != 0
+    }
+
+    /// Returns true if the block contains floating point samples.
+    pub fn is_float(&self) -> bool {
+        self.flags & FLAG_FLOAT_DATA != 0
+    }
+
+    /// Returns true if the block is coded in hybrid (lossy) mode.
+    pub fn is_hybrid(&self) -> bool {
+        self.flags & FLAG_HYBRID != 0
+    }
+
+    /// Returns true if the stereo channels are coded as mid and side channels.
+    pub fn is_joint_stereo(&self) -> bool {
+        self.flags & FLAG_JOINT_STEREO != 0
+    }
+
+    /// Returns true if the block contains 32-bit integer samples.
+    pub fn is_int32(&self) -> bool {
+        self.flags & FLAG_INT32_DATA != 0
+    }
+
+    /// Returns true if only one channel is coded in the block. This is the case for mono blocks,
+    /// and false stereo blocks.
+    pub fn is_mono_data(&self) -> bool {
+        self.flags & (FLAG_MONO | FLAG_FALSE_STEREO) != 0
+    }
+
+    /// Gets the number of bits the decoded samples must be shifted left by.
+    pub fn shift(&self) -> u32 {
+        (self.flags & FLAG_SHIFT_MASK) >> FLAG_SHIFT_SHIFT
+    }
+
+    /// Gets the number of channels in the block.
+    pub fn num_channels(&self) -> u32 {
+        if self.flags & FLAG_MONO != 0 {
+            1
+        }
+        else {
+            2
+        }
+    }
+
+    /// Gets the number of bytes per sample.
+    pub fn bytes_per_sample(&self) -> u32 {
+        (self.flags & FLAG_BYTES_STORED) + 1
+    }
+
+    /// Gets the sample rate, if it is a standard sample rate.
+    pub fn sample_rate(&self) -> Option<u32> {
+        let index = (self.flags & FLAG_SAMPLE_RATE_MASK) >> FLAG_SAMPLE_RATE_SHIFT;
+        SAMPLE_RATES.get(index as usize).copied()
+    }
+}
+
+/// The stream parameters stored in the metadata sub-blocks of a block.
+#[derive(Debug, Default)]
+pub struct BlockMetadata {
+    /// The number of channels, and the channel mask, of the stream.
+    pub channel_info: Option<(u32, u32)>,
+    /// A non-standard sample rate.
+    pub sample_rate: Option<u32>,
+}
+
+/// Read the channel information metadata sub-block.
+fn read_channel_info(buf: &[u8]) -> Option<(u32, u32)> {
+    let le_u32 = |bytes: &[u8]| bytes.iter().rev().fold(0, |acc, &b| (acc << 8) | u32::from(b));
+
+    match buf.len() {
+        0 => None,
+        // The number of channels, followed by up-to 4 bytes of channel mask.
+        1..=5 => Some((u32::from(buf[0]), le_u32(&buf[1..]))),
+        // The extended form supporting up-to 4096 channels. The upper 4 bits of the channel count
+        // are stored in the third byte, followed by a 3 byte channel mask.
+        _ => {
+            let num_channels = (u32::from(buf[0]) | (u32::from(buf[2] & 0xf) << 8)) + 1;
+            Some((num_channels, le_u32(&buf[3..6])))
+        }
+    }
+}
+
+/// A metadata sub-block of a block body.
+pub struct SubBlock<'a> {
+    /// The function of the sub-block.
+    pub id: u8,
+    /// The data of the sub-block, excluding any padding.
+    pub data: &'a [u8],
+}
+
+/// Read all the metadata sub-blocks of a block body.
+pub fn read_sub_blocks(body: &[u8]) -> Result<Vec<SubBlock<'_>>> {
+    let mut sub_blocks = Vec::new();
+
+    let mut reader = BufReader::new(body);
+
+    while reader.pos() < body.len() as u64 {
+        let id = reader.read_u8()?;
+
+        // The length of the sub-block is stored in 16-bit words.
+        let mut len = if id & ID_LARGE != 0 {
+            reader.read_u24()? as usize * 2
+        }
+        else {
+            usize::from(reader.read_u8()?) * 2
+        };
+
+        let buf = reader.read_buf_bytes_ref(len)?;
+
+        if id & ID_ODD_SIZE != 0 {
+            len = len.saturating_sub(1);
+        }
+
+        sub_blocks.push(SubBlock { id: id & ID_FUNCTION_MASK, data: &buf[..len] });
+    }
+
+    Ok(sub_blocks)
+}
+
+impl BlockMetadata {
+    /// Read the stream parameters from the metadata sub-blocks of a block body.
+    pub fn read(body: &[u8]) -> Result<BlockMetadata> {
+        let mut metadata: BlockMetadata = Default::default();
+
+        for SubBlock { id, data: buf } in read_sub_blocks(body)? {
+            match id {
+                ID_CHANNEL_INFO => metadata.channel_info = read_channel_info(buf),
+                ID_SAMPLE_RATE if buf.len() >= 3 => {
+                    let mut sample_rate =
+                        u32::from(buf[0]) | (u32::from(buf[1]) << 8) | (u32::from(buf[2]) << 16);
+
+                    // In newer streams, a fourth byte holds the upper bits of the sample rate.
+                    if buf.len() >= 4 {
+                        sample_rate |= u32::from(buf[3] & 0x7f) << 24;
+                    }
+
+                    metadata.sample_rate = Some(sample_rate);
+                }
+                _ => (),
+            }
+        }
+
+        Ok(metadata)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verify_block_header() {
+        let mut buf = WAVPACK_BLOCK_ID.to_vec();
+        buf.extend_from_slice(&1000u32.to_le_bytes());
+        buf.extend_from_slice(&0x410u16.to_le_bytes());
+        // Upper 8 bits of the block index and total samples.
+        buf.extend_from_slice(&[0x01, 0x02]);
+        buf.extend_from_slice(&10u32.to_le_bytes());
+        buf.extend_from_slice(&20u32.to_le_bytes());
+        buf.extend_from_slice(&4096u32.to_le_bytes());
+        // 16-bit, stereo, initial and final block, 44.1kHz.
+        buf.extend_from_slice(&(0x1u32 | 0x800 | 0x1000 | (9 << 23)).to_le_bytes());
+        buf.extend_from_slice(&0u32.to_le_bytes());
+
+        let header = BlockHeader::read(&mut BufReader::new(&buf)).unwrap();
+
+        assert_eq!(header.body_len(), 1000 + 8 - 32);
+        assert_eq!(header.total_samples, Some((2 << 32) + 10 - 2));
+        assert_eq!(header.block_index, (1 << 32) + 20);
+        assert_eq!(header.block_samples, 4096);
+        assert_eq!(header.num_channels(), 2);
+        assert_eq!(header.bytes_per_sample(), 2);
+        assert_eq!(header.sample_rate(), Some(44100));
+        assert!(header.is_initial_block() && header.is_final_block());
+    }
+
+    #[test]
+    fn verify_block_metadata() {
+        let body = [
+            // An unknown sub-block.
+            0x02,
+            0x01,
+            0xaa,
+            0xbb,
+            // Channel info with an odd size: 6 channels, mask 0x3f.
+            ID_CHANNEL_INFO | ID_ODD_SIZE,
+            0x02,
+            0x06,
+            0x3f,
+            0x00,
+            0x00,
+            // A sample rate of 50000 Hz with an odd size.
+            ID_SAMPLE_RATE | ID_ODD_SIZE,
+            0x02,
+            0x50,
+            0xc3,
+            0x00,
+            0x00,
+        ];
+
+        let metadata = BlockMetadata::read(&body).unwrap();
+
+        assert_eq!(metadata.channel_info, Some((6, 0x3f)));
+        assert_eq!(metadata.sample_rate, Some(50000));
+    }
+}
@@ -0,0 +1,339 @@
+// Symphonia Conformance Test Harness
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+#![warn(rust_2018_idioms)]
+#![forbid(unsafe_code)]
+
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use clap::{Arg, ArgMatches};
+use log::{info, warn};
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+/// The default manifest of vector sets.
+const DEFAULT_MANIFEST: &str = include_str!("../vectors.txt");
+
+/// The name of the file written into a vector set's cache directory once it has been fetched.
+const FETCHED_MARKER: &str = ".fetched";
+
+/// A set of test vectors distributed as a single archive.
+#[derive(Debug, PartialEq)]
+struct VectorSet {
+    /// The name of the vector set, and its directory in the cache.
+    name: String,
+    /// The reference decoder given to symphonia-check.
+    ref_decoder: String,
+    /// The file extensions of the vectors in the set.
+    extensions: Vec<String>,
+    /// The URL of the archive, or `None` if the vectors must be placed in the cache manually.
+    url: Option<String>,
+}
+
+impl VectorSet {
+    fn dir(&self, cache: &Path) -> PathBuf {
+        cache.join(&self.name)
+    }
+}
+
+fn parse_manifest(text: &str) -> Result<Vec<VectorSet>> {
+    let mut sets = Vec::new();
+
+    for (num, line) in text.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split_whitespace().collect();
+
+        if fields.len() != 4 {
+            return Err(format!("manifest line {}: expected 4 fields", num + 1).into());
+        }
+
+        sets.push(VectorSet {
+            name: fields[0].to_string(),
+            ref_decoder: fields[1].to_string(),
+            extensions: fields[2].split(',').map(|ext| ext.to_ascii_lowercase()).collect(),
+            url: if fields[3] == "-" { None } else { Some(fields[3].to_string()) },
+        });
+    }
+
+    Ok(sets)
+}
+
+fn run_command(cmd: &mut Command) -> Result<()> {
+    let status = cmd.status()?;
+
+    if !status.success() {
+        return Err(format!("command {:?} failed with {}", cmd, status).into());
+    }
+
+    Ok(())
+}
+
+/// Download and extract a vector set into the cache, unless it was already fetched.
+fn fetch(set: &VectorSet, cache: &Path) -> Result<()> {
+    let dir = set.dir(cache);
+
+    let url = match &set.url {
+        Some(url) => url,
+        None => {
+            if !dir.is_dir() {
+                warn!("{}: must be placed in {} manually", set.name, dir.display());
+            }
+            return Ok(());
+        }
+    };
+
+    if dir.join(FETCHED_MARKER).exists() {
+        info!("{}: already fetched", set.name);
+        return Ok(());
+    }
+
+    fs::create_dir_all(&dir)?;
+
+    let archive = cache.join(format!("{}.tar.gz", set.name));
+
+    println!("Fetching {} from {}", set.name, url);
+
+    run_command(Command::new("curl").arg("-fsSL").arg("-o").arg(&archive).arg(url))?;
+    run_command(Command::new("tar").arg("-xzf").arg(&archive).arg("-C").arg(&dir))?;
+
+    fs::remove_file(&archive)?;
+    fs::write(dir.join(FETCHED_MARKER), url)?;
+
+    Ok(())
+}
+
+/// Recursively collect all files with one of the provided extensions.
+fn collect_files(dir: &Path, extensions: &[String], files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            collect_files(&path, extensions, files)?;
+        }
+        else if let Some(ext) = path.extension().and_then(|ext| ext.to_str()) {
+            if extensions.contains(&ext.to_ascii_lowercase()) {
+                files.push(path);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Default)]
+struct Summary {
+    n_passed: u32,
+    n_failed: u32,
+    n_errors: u32,
+}
+
+fn run(sets: &[VectorSet], cache: &Path, checker: &Path, filter: Option<&str>) -> Result<Summary> {
+    let mut summary: Summary = Default::default();
+
+    for set in sets {
+        let dir = set.dir(cache);
+
+        if !dir.is_dir() {
+            warn!("{}: not in the cache, skipping", set.name);
+            continue;
+        }
+
+        let mut files = Vec::new();
+        collect_files(&dir, &set.extensions, &mut files)?;
+        files.sort();
+
+        for file in files {
+            let rel_path = file.strip_prefix(cache).unwrap_or(&file);
+
+            let is_selected = filter.map_or(true, |filter| {
+                set.name.contains(filter) || rel_path.to_string_lossy().contains(filter)
+            });
+
+            if !is_selected {
+                continue;
+            }
+
+            // symphonia-check exits with 0 on a pass, 1 on a fail, and any other code on an error.
+            let status = Command::new(checker)
+                .arg("-q")
+                .arg("--ref")
+                .arg(&set.ref_decoder)
+                .arg(&file)
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()?;
+
+            let result = match status.code() {
+                Some(0) => {
+                    summary.n_passed += 1;
+                    "PASS"
+                }
+                Some(1) => {
+                    summary.n_failed += 1;
+                    "FAIL"
+                }
+                _ => {
+                    summary.n_errors += 1;
+                    "ERROR"
+                }
+            };
+
+            println!("[{:<5}] {}", result, rel_path.display());
+        }
+    }
+
+    Ok(summary)
+}
+
+fn default_checker_path() -> Result<PathBuf> {
+    let exe = std::env::current_exe()?;
+    Ok(exe.with_file_name(format!("symphonia-check{}", std::env::consts::EXE_SUFFIX)))
+}
+
+fn run_harness(matches: &ArgMatches) -> Result<i32> {
+    let manifest = match matches.value_of("manifest") {
+        Some(path) => fs::read_to_string(path)?,
+        None => DEFAULT_MANIFEST.to_string(),
+    };
+
+    let sets = parse_manifest(&manifest)?;
+
+    let cache = match matches.value_of("cache") {
+        Some(path) => PathBuf::from(path),
+        None => Path::new(env!("CARGO_MANIFEST_DIR")).join("../target/conformance"),
+    };
+
+    match matches.subcommand() {
+        Some(("list", _)) => {
+            for set in &sets {
+                let url = set.url.as_deref().unwrap_or("(manual)");
+                println!("{:<16} {:<8} {}", set.name, set.ref_decoder, url);
+            }
+            Ok(0)
+        }
+        Some(("fetch", _)) => {
+            for set in &sets {
+                fetch(set, &cache)?;
+            }
+            Ok(0)
+        }
+        Some(("run", sub_matches)) => {
+            let checker = match sub_matches.value_of("check") {
+                Some(path) => PathBuf::from(path),
+                None => default_checker_path()?,
+            };
+
+            if !checker.exists() {
+                return Err(format!(
+                    "symphonia-check was not found at {}, build it or use --check",
+                    checker.display()
+                )
+                .into());
+            }
+
+            if !sub_matches.is_present("no-fetch") {
+                for set in &sets {
+                    fetch(set, &cache)?;
+                }
+            }
+
+            let summary = run(&sets, &cache, &checker, sub_matches.value_of("filter"))?;
+
+            println!();
+            println!("Conformance Results");
+            println!("=================================================");
+            println!();
+            println!("  Passed: {:>8}", summary.n_passed);
+            println!("  Failed: {:>8}", summary.n_failed);
+            println!("  Errors: {:>8}", summary.n_errors);
+            println!();
+
+            Ok(if summary.n_failed > 0 { 1 } else { 0 })
+        }
+        _ => unreachable!(),
+    }
+}
+
+fn main() {
+    pretty_env_logger::init();
+
+    let matches = clap::Command::new("Symphonia Conformance")
+        .version("1.0")
+        .about("Fetch conformance vectors and check Symphonia's output against a reference")
+        .subcommand_required(true)
+        .arg(
+            Arg::new("cache")
+                .long("cache")
+                .takes_value(true)
+                .help("The directory to cache vectors in (default: target/conformance)"),
+        )
+        .arg(
+            Arg::new("manifest")
+                .long("manifest")
+                .takes_value(true)
+                .help("Use a manifest of vector sets other than the default"),
+        )
+        .subcommand(clap::Command::new("list").about("List the vector sets in the manifest"))
+        .subcommand(clap::Command::new("fetch").about("Fetch all vector sets into the cache"))
+        .subcommand(
+            clap::Command::new("run")
+                .about("Fetch all vector sets, and check every vector")
+                .arg(
+                    Arg::new("filter")
+                        .long("filter")
+                        .takes_value(true)
+                        .help("Only check vectors whose set name or path contains this string"),
+                )
+                .arg(
+                    Arg::new("check")
+                        .long("check")
+                        .takes_value(true)
+                        .help("The path to symphonia-check (default: next to this executable)"),
+                )
+                .arg(Arg::new("no-fetch").long("no-fetch").help("Do not fetch missing vectors")),
+        )
+        .get_matches();
+
+    match run_harness(&matches) {
+        Ok(code) => std::process::exit(code),
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            std::process::exit(2);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_manifest, VectorSet, DEFAULT_MANIFEST};
+
+    #[test]
+    fn verify_parse_manifest() {
+        let sets = parse_manifest("# comment\n\nmp3-iso  ffmpeg  mp1,MP2,bit  -\n").unwrap();
+
+        assert_eq!(
+            sets,
+            vec![VectorSet {
+                name: "mp3-iso".to_string(),
+                ref_decoder: "ffmpeg".to_string(),
+                extensions: vec!["mp1".to_string(), "mp2".to_string(), "bit".to_string()],
+                url: None,
+            }]
+        );
+
+        assert!(parse_manifest("flac flac flac").is_err());
+        assert!(!parse_manifest(DEFAULT_MANIFEST).unwrap().is_empty());
+    }
+}
@@ -1247,3 +1247,176 @@ impl<S: Sample + RawSample> RawSampleBuffer<S> {
         self.n_written = n_samples;
     }
 }
+
+/// `BlockReframer` reassembles audio buffers of any length into blocks of a fixed number of
+/// frames.
+///
+/// Frames that do not fill a complete block are carried over to the next call to `push`. This is
+/// useful for consumers, such as audio callbacks, that must always be provided the same number of
+/// frames.
+pub struct BlockReframer<S: Sample> {
+    block: AudioBuffer<S>,
+}
+
+impl<S: Sample> BlockReframer<S> {
+    /// Instantiate a new `BlockReframer` that produces blocks of `block_len` frames with the
+    /// provided signal specification.
+    pub fn new(block_len: usize, spec: SignalSpec) -> Self {
+        assert!(block_len > 0, "block length must be greater than 0");
+
+        BlockReframer { block: AudioBuffer::new(block_len as Duration, spec) }
+    }
+
+    /// Gets the number of frames per block.
+    pub fn block_len(&self) -> usize {
+        self.block.capacity()
+    }
+
+    /// Gets the signal specification of the blocks.
+    pub fn spec(&self) -> &SignalSpec {
+        self.block.spec()
+    }
+
+    /// Gets the number of frames carried over for the next block.
+    pub fn buffered(&self) -> usize {
+        self.block.frames()
+    }
+
+    /// Discards all frames carried over for the next block.
+    pub fn reset(&mut self) {
+        self.block.clear();
+    }
+
+    /// Appends all the frames of the source `AudioBufferRef`, and calls `f` with every complete
+    /// block. The signal specification of the source must match that of the `BlockReframer`.
+    pub fn push<F>(&mut self, src: AudioBufferRef<'_>, f: F)
+    where
+        S: ConvertibleSample,
+        F: FnMut(&AudioBuffer<S>),
+    {
+        match src {
+            AudioBufferRef::U8(buf) => self.push_typed(&buf, f),
+            AudioBufferRef::U16(buf) => self.push_typed(&buf, f),
+            AudioBufferRef::U24(buf) => self.push_typed(&buf, f),
+            AudioBufferRef::U32(buf) => self.push_typed(&buf, f),
+            AudioBufferRef::S8(buf) => self.push_typed(&buf, f),
+            AudioBufferRef::S16(buf) => self.push_typed(&buf, f),
+            AudioBufferRef::S24(buf) => self.push_typed(&buf, f),
+            AudioBufferRef::S32(buf) => self.push_typed(&buf, f),
+            AudioBufferRef::F32(buf) => self.push_typed(&buf, f),
+            AudioBufferRef::F64(buf) => self.push_typed(&buf, f),
+        }
+    }
+
+    /// Appends all the frames of the source `AudioBuffer`, and calls `f` with every complete
+    /// block. The signal specification of the source must match that of the `BlockReframer`.
+    pub fn push_typed<F, T>(&mut self, src: &AudioBuffer<T>, mut f: F)
+    where
+        T: Sample + IntoSample<S>,
+        F: FnMut(&AudioBuffer<S>),
+    {
+        assert!(src.spec == self.block.spec, "signal specification mismatch");
+
+        let n_channels = src.spec.channels.count();
+        let mut offset = 0;
+
+        while offset < src.n_frames {
+            let start = self.block.n_frames;
+            let n_frames = (self.block.n_capacity - start).min(src.n_frames - offset);
+
+            self.block.render_reserved(Some(n_frames));
+
+            for ch in 0..n_channels {
+                let src_ch = &src.chan(ch)[offset..offset + n_frames];
+                let dst_ch = &mut self.block.chan_mut(ch)[start..start + n_frames];
+
+                for (d, &s) in dst_ch.iter_mut().zip(src_ch) {
+                    *d = s.into_sample();
+                }
+            }
+
+            offset += n_frames;
+
+            if self.block.n_frames == self.block.n_capacity {
+                f(&self.block);
+                self.block.clear();
+            }
+        }
+    }
+
+    /// Pads the frames carried over with silence to complete a final block, and calls `f` with
+    /// the block. Nothing is done if no frames were carried over.
+    pub fn flush<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&AudioBuffer<S>),
+    {
+        if self.block.n_frames == 0 {
+            return;
+        }
+
+        let start = self.block.n_frames;
+
+        self.block.render_reserved(None);
+
+        for plane in self.block.planes_mut().planes() {
+            for s in plane[start..].iter_mut() {
+                *s = S::MID;
+            }
+        }
+
+        f(&self.block);
+        self.block.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AudioBuffer, BlockReframer, Channels, Signal, SignalSpec};
+
+    #[test]
+    fn verify_block_reframer() {
+        let spec = SignalSpec::new(8000, Channels::FRONT_LEFT | Channels::FRONT_RIGHT);
+
+        let mut reframer = BlockReframer::<f32>::new(4, spec);
+        let mut blocks = Vec::new();
+
+        for &len in &[3usize, 6, 1, 0, 2] {
+            let mut src = AudioBuffer::<i16>::new(len as u64, spec);
+            src.render_reserved(Some(len));
+
+            for (i, s) in src.chan_mut(0).iter_mut().enumerate() {
+                *s = 16384 * (i as i16 % 2);
+            }
+            for s in src.chan_mut(1).iter_mut() {
+                *s = -16384;
+            }
+
+            reframer.push_typed(&src, |block| {
+                assert_eq!(block.frames(), 4);
+                blocks.push((block.chan(0).to_vec(), block.chan(1).to_vec()));
+            });
+        }
+
+        assert_eq!(blocks.len(), 3);
+        assert_eq!(reframer.buffered(), 0);
+        assert_eq!(blocks[0].0, &[0.0, 0.5, 0.0, 0.0]);
+        assert_eq!(blocks[1].0, &[0.5, 0.0, 0.5, 0.0]);
+        assert_eq!(blocks[2].0, &[0.5, 0.0, 0.0, 0.5]);
+        assert!(blocks.iter().all(|block| block.1 == [-0.5; 4]));
+
+        let mut src = AudioBuffer::<i16>::new(1, spec);
+        src.render_reserved(Some(1));
+        src.chan_mut(1)[0] = 16384;
+
+        reframer.push_typed(&src, |_| panic!("no complete block"));
+        assert_eq!(reframer.buffered(), 1);
+
+        let mut n_flushed = 0;
+        reframer.flush(|block| {
+            assert_eq!(block.chan(1), &[0.5, 0.0, 0.0, 0.0]);
+            n_flushed += 1;
+        });
+        assert_eq!(n_flushed, 1);
+        assert_eq!(reframer.buffered(), 0);
+    }
+}
@@ -13,7 +13,7 @@ use std::default::Default;
 use std::fmt;
 
 use crate::audio::{AudioBufferRef, Channels, Layout};
-use crate::errors::{unsupported_error, Result};
+use crate::errors::{codec_params_error, unsupported_error, CodecParamsErrorKind, Result};
 use crate::formats::Packet;
 use crate::sample::SampleFormat;
 use crate::units::TimeBase;
@@ -79,6 +79,20 @@ impl fmt::Display for CodecType {
     }
 }
 
+impl From<CodecType> for u32 {
+    fn from(codec: CodecType) -> Self {
+        codec.0
+    }
+}
+
+impl From<u32> for CodecType {
+    /// Converts a raw identifier, such as one received across a plugin boundary, into a
+    /// `CodecType`.
+    fn from(id: u32) -> Self {
+        CodecType(id)
+    }
+}
+
 /// Null codec
 pub const CODEC_TYPE_NULL: CodecType = CodecType(0x0);
 
@@ -178,6 +192,18 @@ pub const CODEC_TYPE_ADPCM_IMA_WAV: CodecType = CodecType(0x204);
 /// ADPCM IMA QuickTime
 pub const CODEC_TYPE_ADPCM_IMA_QT: CodecType = CodecType(0x205);
 
+// DSD audio codecs
+//-----------------
+
+/// DSD 1-bit least-significant bit first interleaved
+pub const CODEC_TYPE_DSD_LSBF: CodecType = CodecType(0x300);
+/// DSD 1-bit least-significant bit first planar
+pub const CODEC_TYPE_DSD_LSBF_PLANAR: CodecType = CodecType(0x301);
+/// DSD 1-bit most-significant bit first interleaved
+pub const CODEC_TYPE_DSD_MSBF: CodecType = CodecType(0x302);
+/// DSD 1-bit most-significant bit first planar
+pub const CODEC_TYPE_DSD_MSBF_PLANAR: CodecType = CodecType(0x303);
+
 // Compressed lossy audio codecs
 //------------------------------
 
@@ -213,6 +239,12 @@ pub const CODEC_TYPE_AC4: CodecType = CodecType(0x100d);
 pub const CODEC_TYPE_DCA: CodecType = CodecType(0x100e);
 /// Windows Media Audio
 pub const CODEC_TYPE_WMA: CodecType = CodecType(0x100f);
+/// Adaptive Multi-Rate Narrowband (AMR-NB)
+pub const CODEC_TYPE_AMR_NB: CodecType = CodecType(0x1010);
+/// Adaptive Multi-Rate Wideband (AMR-WB)
+pub const CODEC_TYPE_AMR_WB: CodecType = CodecType(0x1011);
+/// Windows Media Audio Professional
+pub const CODEC_TYPE_WMA_PRO: CodecType = CodecType(0x1012);
 
 // Compressed lossless audio codecs
 //---------------------------------
@@ -227,6 +259,10 @@ pub const CODEC_TYPE_MONKEYS_AUDIO: CodecType = CodecType(0x2002);
 pub const CODEC_TYPE_ALAC: CodecType = CodecType(0x2003);
 /// True Audio (TTA)
 pub const CODEC_TYPE_TTA: CodecType = CodecType(0x2004);
+/// Direct Stream Transfer (DST)
+pub const CODEC_TYPE_DST: CodecType = CodecType(0x2005);
+/// Windows Media Audio Lossless
+pub const CODEC_TYPE_WMA_LOSSLESS: CodecType = CodecType(0x2006);
 
 /// A method and expected value to perform verification on the decoded audio.
 #[derive(Copy, Clone, Debug)]
@@ -431,6 +467,50 @@ impl CodecParameters {
         self.verification_check = Some(code);
         self
     }
+
+    /// Validate the codec parameters. Parameters that are not provided are not validated.
+    ///
+    /// Decoders are instantiated by a `CodecRegistry` only if the codec parameters are valid.
+    pub fn validate(&self) -> Result<()> {
+        if self.sample_rate == Some(0) {
+            return codec_params_error(CodecParamsErrorKind::ZeroSampleRate);
+        }
+
+        if let Some(channels) = self.channels {
+            if channels.is_empty() {
+                return codec_params_error(CodecParamsErrorKind::NoChannels);
+            }
+        }
+
+        if let Some(bits_per_sample) = self.bits_per_sample {
+            if bits_per_sample == 0 || bits_per_sample > 64 {
+                return codec_params_error(CodecParamsErrorKind::InvalidBitsPerSample);
+            }
+
+            // The decoded samples must fit within the sample format.
+            if let Some(sample_format) = self.sample_format {
+                let max_bits = match sample_format {
+                    SampleFormat::U8 | SampleFormat::S8 => 8,
+                    SampleFormat::U16 | SampleFormat::S16 => 16,
+                    SampleFormat::U24 | SampleFormat::S24 => 24,
+                    SampleFormat::U32 | SampleFormat::S32 | SampleFormat::F32 => 32,
+                    SampleFormat::F64 => 64,
+                };
+
+                if bits_per_sample > max_bits {
+                    return codec_params_error(CodecParamsErrorKind::InconsistentBitsPerSample);
+                }
+            }
+        }
+
+        if let Some(bits_per_coded_sample) = self.bits_per_coded_sample {
+            if bits_per_coded_sample == 0 || bits_per_coded_sample > 64 {
+                return codec_params_error(CodecParamsErrorKind::InvalidBitsPerCodedSample);
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl Default for CodecParameters {
@@ -457,6 +537,9 @@ pub struct DecoderOptions {
 
 /// A `Decoder` implements a codec's decode algorithm. It consumes `Packet`s and produces
 /// `AudioBuffer`s.
+///
+/// A `Decoder` must be `Send` and `Sync` such that it may be moved to, or shared with, another
+/// thread. Implementations must not use thread-bound types such as `Rc` or `RefCell`.
 pub trait Decoder: Send + Sync {
     /// Attempts to instantiates a `Decoder` using the provided `CodecParameters`.
     fn try_new(params: &CodecParameters, options: &DecoderOptions) -> Result<Self>
@@ -493,6 +576,10 @@ pub trait Decoder: Send + Sync {
     fn decode(&mut self, packet: &Packet) -> Result<AudioBufferRef>;
 
     /// Optionally, obtain post-decode information such as the verification status.
+    ///
+    /// This should be called once after the last packet of the stream has been decoded. Decoders
+    /// that do not support verification, or were not instantiated with verification enabled,
+    /// return a default `FinalizeResult`.
     fn finalize(&mut self) -> FinalizeResult;
 
     /// Allows read access to the internal audio buffer.
@@ -560,6 +647,7 @@ impl CodecRegistry {
         options: &DecoderOptions,
     ) -> Result<Box<dyn Decoder>> {
         if let Some(descriptor) = self.codecs.get(&params.codec) {
+            params.validate()?;
             Ok((descriptor.inst_func)(params, options)?)
         }
         else {
@@ -586,3 +674,52 @@ macro_rules! support_codec {
         }
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::CodecParameters;
+    use crate::audio::Channels;
+    use crate::errors::{CodecParamsErrorKind, Error};
+    use crate::sample::SampleFormat;
+
+    #[test]
+    fn verify_codec_params_validation() {
+        assert!(CodecParameters::new().validate().is_ok());
+
+        let mut params = CodecParameters::new();
+        params
+            .with_sample_rate(44100)
+            .with_channels(Channels::FRONT_LEFT | Channels::FRONT_RIGHT)
+            .with_sample_format(SampleFormat::S24)
+            .with_bits_per_sample(24);
+        assert!(params.validate().is_ok());
+
+        let mut params = CodecParameters::new();
+        params.with_sample_rate(0);
+        assert!(matches!(
+            params.validate(),
+            Err(Error::InvalidCodecParams(CodecParamsErrorKind::ZeroSampleRate))
+        ));
+
+        let mut params = CodecParameters::new();
+        params.with_channels(Channels::empty());
+        assert!(matches!(
+            params.validate(),
+            Err(Error::InvalidCodecParams(CodecParamsErrorKind::NoChannels))
+        ));
+
+        let mut params = CodecParameters::new();
+        params.with_bits_per_sample(0);
+        assert!(matches!(
+            params.validate(),
+            Err(Error::InvalidCodecParams(CodecParamsErrorKind::InvalidBitsPerSample))
+        ));
+
+        let mut params = CodecParameters::new();
+        params.with_sample_format(SampleFormat::S16).with_bits_per_sample(24);
+        assert!(matches!(
+            params.validate(),
+            Err(Error::InvalidCodecParams(CodecParamsErrorKind::InconsistentBitsPerSample))
+        ));
+    }
+}
@@ -675,9 +675,52 @@ impl<S> ConvertibleSample for S where
 {
 }
 
+/// Converts a slice of samples of sample format `F` into a slice of samples of sample format `T`.
+///
+/// This function allows applications with their own buffer types to reuse the sample format
+/// conversions implemented by `FromSample`. No dithering is applied.
+///
+/// # Panics
+///
+/// Panics if the source and destination slices are not the same length.
+pub fn convert_slice<F, T>(src: &[F], dst: &mut [T])
+where
+    F: Sample,
+    T: Sample + FromSample<F>,
+{
+    assert_eq!(src.len(), dst.len(), "source and destination must be the same length");
+
+    for (d, &s) in dst.iter_mut().zip(src) {
+        *d = T::from_sample(s);
+    }
+}
+
+/// Converts a slice of samples of sample format `F` into a slice of samples of sample format `T`
+/// while applying the provided dither.
+///
+/// The dither is only applied if the conversion from `F` to `T` reduces precision. Otherwise, this
+/// function is equivalent to `convert_slice`.
+///
+/// # Panics
+///
+/// Panics if the source and destination slices are not the same length.
+pub fn convert_slice_dithered<F, T, D>(src: &[F], dst: &mut [T], dither: &mut D)
+where
+    F: Sample + dither::MaybeDither<T>,
+    T: Sample + FromSample<F>,
+    D: dither::Dither<F, T>,
+{
+    assert_eq!(src.len(), dst.len(), "source and destination must be the same length");
+
+    for (d, &s) in dst.iter_mut().zip(src) {
+        *d = T::from_sample(s.maybe_dither(dither));
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::FromSample;
+    use super::dither::Triangular;
+    use super::{convert_slice, convert_slice_dithered, FromSample};
     use crate::sample::{i24, u24, Sample};
     use std::{i16, i32, i8, u16, u32, u8};
 
@@ -1110,4 +1153,19 @@ mod tests {
         assert_eq!(f32::from_sample(0f64), 0.0);
         assert_eq!(f32::from_sample(-1.0f64), -1.0);
     }
+
+    #[test]
+    fn verify_convert_slice() {
+        let src = [i24::MIN, i24::MID, i24::MAX];
+
+        let mut dst = [0.0f32; 3];
+        convert_slice(&src, &mut dst);
+        assert_eq!(dst, [-1.0, 0.0, 8_388_607.0 / 8_388_608.0]);
+
+        let mut dst = [0i16; 3];
+        convert_slice_dithered(&src, &mut dst, &mut Triangular::new());
+        assert!(dst[0] <= i16::MIN + 1);
+        assert!(dst[1].abs() <= 1);
+        assert!(dst[2] >= i16::MAX - 1);
+    }
 }
@@ -0,0 +1,555 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The `channel_map` module reorders, duplicates, and mixes audio channels.
+//!
+//! A [`ChannelMap`] is parsed from a compact textual description of the output channels. It is
+//! turned into a [`ChannelMixer`] for the channels of a particular signal, which then mixes the
+//! decoded audio directly into the output buffer.
+//!
+//! # Syntax
+//!
+//! A channel map is a comma-separated list of output channels, in the order they are output. Each
+//! output channel is one of:
+//!
+//!  * A channel name, such as `FL`, to take the input channel with that position.
+//!  * `#N` to take the N-th input channel, counting from 0.
+//!  * A sum of terms, `FL+0.5*FC`, where each term is a channel optionally scaled by a gain.
+//!  * `-` for a silent channel.
+//!  * `2.0` (or `stereo`) for a left and right downmix of all input channels, or `1.0` (or `mono`)
+//!    for a single channel downmix.
+//!
+//! The channel map may be prefixed by a list of names for the input channels followed by `->`
+//! (or `→`). This overrides the positions of the input channels, for example, when a file does not
+//! describe its channel layout.
+//!
+//! Input channels that are not present in the signal are silent. The channel names are: `FL`,
+//! `FR`, `FC`, `LFE` (or `LFE1`), `RL`, `RR`, `FLC`, `FRC`, `RC`, `SL`, `SR`, `TC`, `TFL`,
+//! `TFC`, `TFR`, `TRL`, `TRC`, `TRR`, `RLC`, `RRC`, `FLW`, `FRW`, `FLH`, `FCH`, `FRH`, and
+//! `LFE2`.
+//!
+//! For example, `FR,FL` swaps the left and right channels, `FL,FR,-,-,FC,LFE` outputs 5.1 audio
+//! to a device that expects silence in its third and fourth channels, and `FL,FR,LFE->2.0`
+//! downmixes 2.1 audio with an undescribed layout to stereo.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::audio::{AudioBuffer, AudioBufferRef, Channels, Signal};
+use crate::conv::FromSample;
+use crate::sample::Sample;
+
+/// The gain of a channel mixed into a downmix at reduced power (-3 dB).
+const HALF_POWER: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+const CHANNEL_NAMES: [(&str, Channels); 27] = [
+    ("FL", Channels::FRONT_LEFT),
+    ("FR", Channels::FRONT_RIGHT),
+    ("FC", Channels::FRONT_CENTRE),
+    ("LFE", Channels::LFE1),
+    ("LFE1", Channels::LFE1),
+    ("RL", Channels::REAR_LEFT),
+    ("RR", Channels::REAR_RIGHT),
+    ("FLC", Channels::FRONT_LEFT_CENTRE),
+    ("FRC", Channels::FRONT_RIGHT_CENTRE),
+    ("RC", Channels::REAR_CENTRE),
+    ("SL", Channels::SIDE_LEFT),
+    ("SR", Channels::SIDE_RIGHT),
+    ("TC", Channels::TOP_CENTRE),
+    ("TFL", Channels::TOP_FRONT_LEFT),
+    ("TFC", Channels::TOP_FRONT_CENTRE),
+    ("TFR", Channels::TOP_FRONT_RIGHT),
+    ("TRL", Channels::TOP_REAR_LEFT),
+    ("TRC", Channels::TOP_REAR_CENTRE),
+    ("TRR", Channels::TOP_REAR_RIGHT),
+    ("RLC", Channels::REAR_LEFT_CENTRE),
+    ("RRC", Channels::REAR_RIGHT_CENTRE),
+    ("FLW", Channels::FRONT_LEFT_WIDE),
+    ("FRW", Channels::FRONT_RIGHT_WIDE),
+    ("FLH", Channels::FRONT_LEFT_HIGH),
+    ("FCH", Channels::FRONT_CENTRE_HIGH),
+    ("FRH", Channels::FRONT_RIGHT_HIGH),
+    ("LFE2", Channels::LFE2),
+];
+
+/// The maximum number of output channels.
+const MAX_OUTPUT_CHANNELS: usize = 26;
+
+/// An error describing why a channel map could not be parsed or applied.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChannelMapError {
+    /// The channel map, or one of its output channels, is empty.
+    Empty,
+    /// A channel name is not known.
+    UnknownChannel(String),
+    /// A gain is not a valid number.
+    InvalidGain(String),
+    /// There are more output channels than can be described by `Channels`.
+    TooManyChannels,
+    /// The number of input channel names does not match the number of channels of the signal.
+    InputMismatch { expected: usize, actual: usize },
+}
+
+impl fmt::Display for ChannelMapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChannelMapError::Empty => write!(f, "channel map is empty"),
+            ChannelMapError::UnknownChannel(name) => write!(f, "unknown channel '{}'", name),
+            ChannelMapError::InvalidGain(gain) => write!(f, "invalid gain '{}'", gain),
+            ChannelMapError::TooManyChannels => write!(f, "too many output channels"),
+            ChannelMapError::InputMismatch { expected, actual } => {
+                write!(f, "channel map names {} input channels, but there are {}", expected, actual)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ChannelMapError {}
+
+/// The source of a term of a mix.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Source {
+    /// The input channel with the provided position.
+    Channel(Channels),
+    /// The input channel with the provided index.
+    Index(usize),
+}
+
+/// A single channel of a downmix.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Downmix {
+    Mono,
+    Left,
+    Right,
+}
+
+/// The description of an output channel.
+#[derive(Clone, Debug, PartialEq)]
+enum Output {
+    Silence,
+    Mix(Vec<(f32, Source)>),
+    Downmix(Downmix),
+}
+
+fn parse_channel(name: &str) -> Result<Channels, ChannelMapError> {
+    CHANNEL_NAMES
+        .iter()
+        .find(|(other, _)| other.eq_ignore_ascii_case(name))
+        .map(|&(_, channel)| channel)
+        .ok_or_else(|| ChannelMapError::UnknownChannel(name.to_string()))
+}
+
+fn parse_source(source: &str) -> Result<Source, ChannelMapError> {
+    match source.strip_prefix('#') {
+        Some(index) => index
+            .parse()
+            .map(Source::Index)
+            .map_err(|_| ChannelMapError::UnknownChannel(source.to_string())),
+        None => parse_channel(source).map(Source::Channel),
+    }
+}
+
+fn parse_term(term: &str) -> Result<(f32, Source), ChannelMapError> {
+    match term.find('*') {
+        Some(pos) => {
+            let gain = term[..pos].trim();
+            let gain = gain.parse().map_err(|_| ChannelMapError::InvalidGain(gain.to_string()))?;
+            Ok((gain, parse_source(term[pos + 1..].trim())?))
+        }
+        None => Ok((1.0, parse_source(term)?)),
+    }
+}
+
+/// A `ChannelMap` describes the output channels of a mix. See the [module](self) documentation
+/// for the syntax.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChannelMap {
+    input: Option<Vec<Channels>>,
+    outputs: Vec<Output>,
+}
+
+impl FromStr for ChannelMap {
+    type Err = ChannelMapError;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let arrow = spec.find("->").map(|pos| (pos, 2)).or_else(|| {
+            let arrow = '→';
+            spec.find(arrow).map(|pos| (pos, arrow.len_utf8()))
+        });
+
+        let (input, outputs) = match arrow {
+            Some((pos, len)) => (Some(&spec[..pos]), &spec[pos + len..]),
+            None => (None, spec),
+        };
+
+        let input = match input {
+            Some(input) => Some(
+                input
+                    .split(',')
+                    .map(|name| parse_channel(name.trim()))
+                    .collect::<Result<_, _>>()?,
+            ),
+            None => None,
+        };
+
+        let mut map = ChannelMap { input, outputs: Vec::new() };
+
+        for output in outputs.split(',') {
+            match output.trim() {
+                "" => return Err(ChannelMapError::Empty),
+                "-" => map.outputs.push(Output::Silence),
+                "1.0" | "mono" => map.outputs.push(Output::Downmix(Downmix::Mono)),
+                "2.0" | "stereo" => {
+                    map.outputs.push(Output::Downmix(Downmix::Left));
+                    map.outputs.push(Output::Downmix(Downmix::Right));
+                }
+                mix => {
+                    let terms = mix.split('+').map(|term| parse_term(term.trim()));
+                    map.outputs.push(Output::Mix(terms.collect::<Result<_, _>>()?));
+                }
+            }
+        }
+
+        if map.outputs.len() > MAX_OUTPUT_CHANNELS {
+            return Err(ChannelMapError::TooManyChannels);
+        }
+
+        Ok(map)
+    }
+}
+
+/// Gets the gain of an input channel in the left channel of a downmix.
+fn downmix_left_gain(channel: Channels) -> f32 {
+    match channel {
+        Channels::FRONT_LEFT
+        | Channels::FRONT_LEFT_CENTRE
+        | Channels::FRONT_LEFT_WIDE
+        | Channels::FRONT_LEFT_HIGH => 1.0,
+        Channels::REAR_LEFT
+        | Channels::REAR_LEFT_CENTRE
+        | Channels::SIDE_LEFT
+        | Channels::TOP_FRONT_LEFT
+        | Channels::TOP_REAR_LEFT => HALF_POWER,
+        Channels::FRONT_CENTRE
+        | Channels::REAR_CENTRE
+        | Channels::TOP_CENTRE
+        | Channels::TOP_FRONT_CENTRE
+        | Channels::TOP_REAR_CENTRE
+        | Channels::FRONT_CENTRE_HIGH => HALF_POWER,
+        _ => 0.0,
+    }
+}
+
+/// Gets the channel mirrored across the centre line.
+fn mirror(channel: Channels) -> Channels {
+    const PAIRS: [(Channels, Channels); 10] = [
+        (Channels::FRONT_LEFT, Channels::FRONT_RIGHT),
+        (Channels::REAR_LEFT, Channels::REAR_RIGHT),
+        (Channels::FRONT_LEFT_CENTRE, Channels::FRONT_RIGHT_CENTRE),
+        (Channels::SIDE_LEFT, Channels::SIDE_RIGHT),
+        (Channels::TOP_FRONT_LEFT, Channels::TOP_FRONT_RIGHT),
+        (Channels::TOP_REAR_LEFT, Channels::TOP_REAR_RIGHT),
+        (Channels::REAR_LEFT_CENTRE, Channels::REAR_RIGHT_CENTRE),
+        (Channels::FRONT_LEFT_WIDE, Channels::FRONT_RIGHT_WIDE),
+        (Channels::FRONT_LEFT_HIGH, Channels::FRONT_RIGHT_HIGH),
+        (Channels::LFE1, Channels::LFE2),
+    ];
+
+    for &(left, right) in PAIRS.iter() {
+        if channel == left {
+            return right;
+        }
+        else if channel == right {
+            return left;
+        }
+    }
+
+    channel
+}
+
+impl ChannelMap {
+    /// Instantiate a `ChannelMixer` that applies the channel map to a signal with the provided
+    /// channels.
+    pub fn mixer(&self, channels: Channels) -> Result<ChannelMixer, ChannelMapError> {
+        let positions: Vec<Channels> = match &self.input {
+            Some(input) if input.len() != channels.count() => {
+                return Err(ChannelMapError::InputMismatch {
+                    expected: input.len(),
+                    actual: channels.count(),
+                })
+            }
+            Some(input) => input.clone(),
+            None => channels.iter().collect(),
+        };
+
+        let n_in = positions.len();
+        let n_out = self.outputs.len();
+
+        let mut matrix = vec![0.0; n_in * n_out];
+        let mut labels = Vec::with_capacity(n_out);
+
+        for (output, row) in self.outputs.iter().zip(matrix.chunks_exact_mut(n_in.max(1))) {
+            let row = &mut row[..n_in];
+
+            let label = match output {
+                Output::Silence => None,
+                Output::Mix(terms) => {
+                    for &(gain, source) in terms {
+                        let index = match source {
+                            Source::Channel(channel) => {
+                                positions.iter().position(|&c| c == channel)
+                            }
+                            Source::Index(index) => Some(index).filter(|&index| index < n_in),
+                        };
+
+                        if let Some(index) = index {
+                            row[index] += gain;
+                        }
+                    }
+
+                    match terms.as_slice() {
+                        [(gain, Source::Channel(channel))] if *gain == 1.0 => Some(*channel),
+                        _ => None,
+                    }
+                }
+                Output::Downmix(downmix) => {
+                    for (gain, &position) in row.iter_mut().zip(&positions) {
+                        // A single input channel is duplicated to all channels of the downmix.
+                        let left = if n_in == 1 { 1.0 } else { downmix_left_gain(position) };
+                        let right =
+                            if n_in == 1 { 1.0 } else { downmix_left_gain(mirror(position)) };
+
+                        *gain = match downmix {
+                            Downmix::Mono => 0.5 * (left + right),
+                            Downmix::Left => left,
+                            Downmix::Right => right,
+                        };
+                    }
+
+                    match downmix {
+                        Downmix::Mono | Downmix::Left => Some(Channels::FRONT_LEFT),
+                        Downmix::Right => Some(Channels::FRONT_RIGHT),
+                    }
+                }
+            };
+
+            labels.push(label);
+        }
+
+        // If every output channel is a distinct position given in ascending order, then the output
+        // channels are those positions. Otherwise, the output channels only denote the order in
+        // which they are output.
+        let is_ordered = labels.windows(2).all(|pair| match pair {
+            [Some(a), Some(b)] => a.bits() < b.bits(),
+            _ => false,
+        });
+
+        let output_channels = match labels.as_slice() {
+            [Some(label), ..] if is_ordered => {
+                labels.iter().fold(*label, |acc, label| acc | label.unwrap())
+            }
+            _ => Channels::from_bits_truncate((1 << n_out) - 1),
+        };
+
+        Ok(ChannelMixer { n_in, n_out, matrix, output_channels })
+    }
+}
+
+/// A `ChannelMixer` mixes the channels of an audio buffer into output channels as described by a
+/// [`ChannelMap`].
+#[derive(Clone, Debug)]
+pub struct ChannelMixer {
+    n_in: usize,
+    n_out: usize,
+    /// The gain of each input channel for each output channel, in output-major order.
+    matrix: Vec<f32>,
+    output_channels: Channels,
+}
+
+impl ChannelMixer {
+    /// Gets the number of output channels.
+    pub fn n_output_channels(&self) -> usize {
+        self.n_out
+    }
+
+    /// Gets the output channels.
+    ///
+    /// If the channel map outputs distinct channels in the standard order, then these are those
+    /// channels. Otherwise, the output channels are in an order `Channels` can not express. In
+    /// that case, the first N channel positions are returned, where each position only denotes
+    /// the index of the output channel.
+    pub fn output_channels(&self) -> Channels {
+        self.output_channels
+    }
+
+    /// Gets the gain of an input channel in an output channel.
+    pub fn gain(&self, output: usize, input: usize) -> f32 {
+        self.matrix[output * self.n_in + input]
+    }
+
+    fn mix_sample<S: Sample>(&self, planes: &[&[S]], output: usize, frame: usize) -> f32
+    where
+        f32: FromSample<S>,
+    {
+        let row = &self.matrix[output * self.n_in..(output + 1) * self.n_in];
+
+        row.iter()
+            .zip(planes)
+            .filter(|(&gain, _)| gain != 0.0)
+            .map(|(&gain, plane)| gain * f32::from_sample(plane[frame]))
+            .sum()
+    }
+
+    fn mix_interleaved_typed<S: Sample>(&self, src: &AudioBuffer<S>, dst: &mut [f32]) -> usize
+    where
+        f32: FromSample<S>,
+    {
+        assert!(src.spec().channels.count() == self.n_in, "input channels do not match");
+
+        let n_samples = src.frames() * self.n_out;
+        assert!(dst.len() >= n_samples, "destination buffer is too small");
+
+        let planes = src.planes();
+
+        for (frame, out) in dst[..n_samples].chunks_exact_mut(self.n_out.max(1)).enumerate() {
+            for (output, sample) in out.iter_mut().enumerate() {
+                *sample = self.mix_sample(planes.planes(), output, frame);
+            }
+        }
+
+        n_samples
+    }
+
+    fn mix_typed<S: Sample>(&self, src: &AudioBuffer<S>, dst: &mut AudioBuffer<f32>)
+    where
+        f32: FromSample<S>,
+    {
+        assert!(src.spec().channels.count() == self.n_in, "input channels do not match");
+        assert!(dst.spec().channels.count() == self.n_out, "output channels do not match");
+
+        dst.clear();
+        dst.render_reserved(Some(src.frames()));
+
+        let planes = src.planes();
+
+        for output in 0..self.n_out {
+            for (frame, sample) in dst.chan_mut(output).iter_mut().enumerate() {
+                *sample = self.mix_sample(planes.planes(), output, frame);
+            }
+        }
+    }
+
+    /// Mixes the source audio buffer into the destination slice, with output channels
+    /// interleaved, and returns the number of samples written.
+    ///
+    /// Panics if the source does not have the input channels of the mixer, or if the destination
+    /// is too small.
+    pub fn mix_interleaved(&self, src: AudioBufferRef<'_>, dst: &mut [f32]) -> usize {
+        match src {
+            AudioBufferRef::U8(buf) => self.mix_interleaved_typed(&buf, dst),
+            AudioBufferRef::U16(buf) => self.mix_interleaved_typed(&buf, dst),
+            AudioBufferRef::U24(buf) => self.mix_interleaved_typed(&buf, dst),
+            AudioBufferRef::U32(buf) => self.mix_interleaved_typed(&buf, dst),
+            AudioBufferRef::S8(buf) => self.mix_interleaved_typed(&buf, dst),
+            AudioBufferRef::S16(buf) => self.mix_interleaved_typed(&buf, dst),
+            AudioBufferRef::S24(buf) => self.mix_interleaved_typed(&buf, dst),
+            AudioBufferRef::S32(buf) => self.mix_interleaved_typed(&buf, dst),
+            AudioBufferRef::F32(buf) => self.mix_interleaved_typed(&buf, dst),
+            AudioBufferRef::F64(buf) => self.mix_interleaved_typed(&buf, dst),
+        }
+    }
+
+    /// Mixes the source audio buffer into the destination audio buffer, replacing its contents.
+    /// The destination must have as many channels as the mixer outputs, and the capacity for all
+    /// frames of the source.
+    ///
+    /// Panics if the source does not have the input channels of the mixer, or if the destination
+    /// does not have the output channels.
+    pub fn mix(&self, src: AudioBufferRef<'_>, dst: &mut AudioBuffer<f32>) {
+        match src {
+            AudioBufferRef::U8(buf) => self.mix_typed(&buf, dst),
+            AudioBufferRef::U16(buf) => self.mix_typed(&buf, dst),
+            AudioBufferRef::U24(buf) => self.mix_typed(&buf, dst),
+            AudioBufferRef::U32(buf) => self.mix_typed(&buf, dst),
+            AudioBufferRef::S8(buf) => self.mix_typed(&buf, dst),
+            AudioBufferRef::S16(buf) => self.mix_typed(&buf, dst),
+            AudioBufferRef::S24(buf) => self.mix_typed(&buf, dst),
+            AudioBufferRef::S32(buf) => self.mix_typed(&buf, dst),
+            AudioBufferRef::F32(buf) => self.mix_typed(&buf, dst),
+            AudioBufferRef::F64(buf) => self.mix_typed(&buf, dst),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ChannelMap, ChannelMapError, HALF_POWER};
+    use crate::audio::{AsAudioBufferRef, AudioBuffer, Channels, Layout, Signal, SignalSpec};
+
+    #[test]
+    fn verify_channel_map_parse() {
+        assert!("FL, FR ,lfe,-,#3,0.5*FC+SL".parse::<ChannelMap>().is_ok());
+        assert!("FL,FR,LFE→2.0".parse::<ChannelMap>().is_ok());
+        assert_eq!("FL,,FR".parse::<ChannelMap>(), Err(ChannelMapError::Empty));
+        assert_eq!(
+            "FL,XX".parse::<ChannelMap>(),
+            Err(ChannelMapError::UnknownChannel("XX".to_string()))
+        );
+        assert_eq!(
+            "a*FL".parse::<ChannelMap>(),
+            Err(ChannelMapError::InvalidGain("a".to_string()))
+        );
+    }
+
+    #[test]
+    fn verify_channel_mixer() {
+        let layout_21 = Layout::TwoPointOne.into_channels();
+
+        // Swapping channels outputs them in an order that `Channels` can not express.
+        let mixer = "FR,FL,-".parse::<ChannelMap>().unwrap().mixer(layout_21).unwrap();
+        assert_eq!(mixer.output_channels().count(), 3);
+        assert_eq!(mixer.gain(0, 1), 1.0);
+        assert_eq!(mixer.gain(1, 0), 1.0);
+
+        // A downmix of 2.1 audio to stereo drops the LFE channel.
+        let mixer = "FL,FR,LFE->2.0".parse::<ChannelMap>().unwrap().mixer(layout_21).unwrap();
+        assert_eq!(mixer.output_channels(), Layout::Stereo.into_channels());
+        assert_eq!(mixer.gain(0, 2), 0.0);
+
+        let mut src = AudioBuffer::<i16>::new(4, SignalSpec::new(44100, layout_21));
+        src.render_reserved(Some(2));
+        src.chan_mut(0).copy_from_slice(&[16384, 16384]);
+        src.chan_mut(1).copy_from_slice(&[-16384, 0]);
+        src.chan_mut(2).copy_from_slice(&[8192, 8192]);
+
+        let mut dst = [0.0; 4];
+        assert_eq!(mixer.mix_interleaved(src.as_audio_buffer_ref(), &mut dst), 4);
+        assert_eq!(dst, [0.5, -0.5, 0.5, 0.0]);
+
+        // A single channel is duplicated to both channels of a stereo downmix.
+        let mono = Layout::Mono.into_channels();
+        let mixer = "2.0".parse::<ChannelMap>().unwrap().mixer(mono).unwrap();
+        assert_eq!((mixer.gain(0, 0), mixer.gain(1, 0)), (1.0, 1.0));
+
+        // A 5.1 downmix to mono mixes the centre channel at reduced power.
+        let layout_51 = Layout::FivePointOne.into_channels();
+        let mixer = "1.0".parse::<ChannelMap>().unwrap().mixer(layout_51).unwrap();
+        assert_eq!(mixer.gain(0, 2), HALF_POWER);
+
+        let mut dst = AudioBuffer::<f32>::new(4, SignalSpec::new(44100, Channels::FRONT_LEFT));
+        "FL+FR"
+            .parse::<ChannelMap>()
+            .unwrap()
+            .mixer(layout_21)
+            .unwrap()
+            .mix(src.as_audio_buffer_ref(), &mut dst);
+        assert_eq!(dst.chan(0), &[0.0, 0.5]);
+
+        // The number of named input channels must match the signal.
+        assert!("FL->FL".parse::<ChannelMap>().unwrap().mixer(layout_21).is_err());
+    }
+}
@@ -36,6 +36,37 @@ impl SeekErrorKind {
     }
 }
 
+/// `CodecParamsErrorKind` is a list of reasons why a set of codec parameters may be invalid.
+#[derive(Debug)]
+pub enum CodecParamsErrorKind {
+    /// The sample rate is 0.
+    ZeroSampleRate,
+    /// The channel map contains no channels.
+    NoChannels,
+    /// The number of bits per decoded sample is 0, or greater than 64.
+    InvalidBitsPerSample,
+    /// The number of bits per encoded sample is 0, or greater than 64.
+    InvalidBitsPerCodedSample,
+    /// The number of bits per decoded sample exceeds the width of the sample format.
+    InconsistentBitsPerSample,
+}
+
+impl CodecParamsErrorKind {
+    fn as_str(&self) -> &'static str {
+        match *self {
+            CodecParamsErrorKind::ZeroSampleRate => "sample rate is 0",
+            CodecParamsErrorKind::NoChannels => "channel map is empty",
+            CodecParamsErrorKind::InvalidBitsPerSample => "bits per sample is out-of-range",
+            CodecParamsErrorKind::InvalidBitsPerCodedSample => {
+                "bits per coded sample is out-of-range"
+            }
+            CodecParamsErrorKind::InconsistentBitsPerSample => {
+                "bits per sample exceeds the sample format"
+            }
+        }
+    }
+}
+
 /// `Error` provides an enumeration of all possible errors reported by Symphonia.
 #[derive(Debug)]
 pub enum Error {
@@ -52,6 +83,8 @@ pub enum Error {
     LimitError(&'static str),
     /// The demuxer or decoder needs to be reset before continuing.
     ResetRequired,
+    /// The codec parameters provided to instantiate a decoder were invalid.
+    InvalidCodecParams(CodecParamsErrorKind),
 }
 
 impl fmt::Display for Error {
@@ -73,6 +106,9 @@ impl fmt::Display for Error {
             Error::ResetRequired => {
                 write!(f, "decoder needs to be reset")
             }
+            Error::InvalidCodecParams(ref kind) => {
+                write!(f, "invalid codec parameters: {}", kind.as_str())
+            }
         }
     }
 }
@@ -86,6 +122,7 @@ impl std::error::Error for Error {
             Error::Unsupported(_) => None,
             Error::LimitError(_) => None,
             Error::ResetRequired => None,
+            Error::InvalidCodecParams(_) => None,
         }
     }
 }
@@ -123,6 +160,11 @@ pub fn reset_error<T>() -> Result<T> {
     Err(Error::ResetRequired)
 }
 
+/// Convenience function to create an invalid codec parameters error.
+pub fn codec_params_error<T>(kind: CodecParamsErrorKind) -> Result<T> {
+    Err(Error::InvalidCodecParams(kind))
+}
+
 /// Convenience function to create an end-of-stream error.
 pub fn end_of_stream_error<T>() -> Result<T> {
     Err(Error::IoError(io::Error::new(io::ErrorKind::UnexpectedEof, "end of stream")))
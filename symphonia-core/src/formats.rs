@@ -8,18 +8,23 @@
 //! The `format` module provides the traits and support structures necessary to implement media
 //! demuxers.
 
+use std::time::Duration;
+
 use crate::codecs::CodecParameters;
 use crate::errors::Result;
 use crate::io::{BufReader, MediaSourceStream};
-use crate::meta::{Metadata, Tag};
+use crate::meta::{Metadata, StandardTagKey, Tag, Value};
 use crate::units::{Time, TimeStamp};
 
+use bitflags::bitflags;
+
 pub mod prelude {
     //! The `formats` module prelude.
 
     pub use crate::units::{Duration, TimeBase, TimeStamp};
 
-    pub use super::{Cue, FormatOptions, FormatReader, Packet, SeekMode, SeekTo, SeekedTo, Track};
+    pub use super::{Cue, FormatOptions, FormatReader, Packet, SeekMode, SeekTo, SeekedTo};
+    pub use super::{LoopRegion, Program, TimestampDiscontinuity, Track, TrackFilter, TrackFlags};
 }
 
 /// `SeekTo` specifies a position to seek to.
@@ -67,7 +72,11 @@ pub enum SeekMode {
 }
 
 /// `FormatOptions` is a common set of options that all demuxers use.
+///
+/// New options may be added in minor releases. Construct the options from one of the presets, or
+/// from the default, and then use the builder-style setters to modify them.
 #[derive(Copy, Clone, Debug)]
+#[non_exhaustive]
 pub struct FormatOptions {
     /// If a `FormatReader` requires a seek index, but the container does not provide one, build the
     /// seek index during instantiation instead of building it progressively. Default: `false`.
@@ -88,6 +97,28 @@ pub struct FormatOptions {
     /// When enabled, this option will also alter the value and interpretation of timestamps and
     /// durations such that they are relative to the non-trimmed region.
     pub enable_gapless: bool,
+    /// If gapless playback is enabled, but the media does not contain the encoder delay or
+    /// padding, assume the delay commonly added by the encoders of the codec. Default: `false`.
+    ///
+    /// This is a heuristic that may trim too much or too little audio, but is often better than
+    /// not trimming at all. For example, MP3 files without a LAME tag are assumed to have the
+    /// standard 576 frame encoder delay, plus the 529 frame decoder delay.
+    pub estimate_gapless: bool,
+    /// If set, only the selected tracks are demuxed, and packets of all other tracks are
+    /// discarded as early as possible. This is most useful for containers that multiplex many
+    /// programs, such as broadcast transport streams. Selecting a program or track that does not
+    /// exist is an error. Default: `None`.
+    ///
+    /// Note: Not all `FormatReader`s support track filtering. Those that do not ignore this
+    /// option.
+    pub track_filter: Option<TrackFilter>,
+    /// If set, the maximum duration of a packet for containers that store unframed audio, such as
+    /// PCM or ADPCM in WAVE. Smaller packets reduce the latency added by demuxing and decoding,
+    /// at the cost of more per-packet overhead. Default: `None`.
+    ///
+    /// Note: Packets always contain at least one whole block or frame. This option does not
+    /// affect containers whose packets are defined by the encoder.
+    pub max_packet_duration: Option<Duration>,
 }
 
 impl Default for FormatOptions {
@@ -96,10 +127,105 @@ impl Default for FormatOptions {
             prebuild_seek_index: false,
             seek_index_fill_rate: 20,
             enable_gapless: false,
+            estimate_gapless: false,
+            track_filter: None,
+            max_packet_duration: None,
+        }
+    }
+}
+
+impl FormatOptions {
+    /// The options for low-latency consumers, such as intercom or VoIP, that should add as little
+    /// buffering as possible. Packets of unframed audio are limited to 10 ms.
+    pub fn low_latency() -> Self {
+        FormatOptions::default().with_max_packet_duration(Some(Duration::from_millis(10)))
+    }
+
+    /// Sets if the seek index should be built during instantiation.
+    pub fn with_prebuild_seek_index(mut self, prebuild_seek_index: bool) -> Self {
+        self.prebuild_seek_index = prebuild_seek_index;
+        self
+    }
+
+    /// Sets how often, in seconds of decoded content, an entry is added to the seek index.
+    pub fn with_seek_index_fill_rate(mut self, seek_index_fill_rate: u16) -> Self {
+        self.seek_index_fill_rate = seek_index_fill_rate;
+        self
+    }
+
+    /// Sets if support for gapless playback is enabled.
+    pub fn with_enable_gapless(mut self, enable_gapless: bool) -> Self {
+        self.enable_gapless = enable_gapless;
+        self
+    }
+
+    /// Sets if the common encoder delay should be assumed when the media does not provide it.
+    pub fn with_estimate_gapless(mut self, estimate_gapless: bool) -> Self {
+        self.estimate_gapless = estimate_gapless;
+        self
+    }
+
+    /// Sets the tracks to be demuxed.
+    pub fn with_track_filter(mut self, track_filter: Option<TrackFilter>) -> Self {
+        self.track_filter = track_filter;
+        self
+    }
+
+    /// Sets the maximum duration of a packet for containers that store unframed audio.
+    pub fn with_max_packet_duration(mut self, max_packet_duration: Option<Duration>) -> Self {
+        self.max_packet_duration = max_packet_duration;
+        self
+    }
+}
+
+/// `TrackFilter` selects the tracks to be demuxed by a `FormatReader`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TrackFilter {
+    /// Only demux the tracks of the program with the provided program number.
+    Program(u32),
+    /// Only demux the track with the provided track identifier. For transport streams, the track
+    /// identifier is the packet identifier (PID).
+    Track(u32),
+    /// Demux all tracks except the track with the provided track identifier.
+    ExcludeTrack(u32),
+}
+
+impl TrackFilter {
+    /// Returns true if the track with the provided identifier is selected by the filter given the
+    /// programs of the media.
+    pub fn is_selected(&self, track_id: u32, programs: &[Program]) -> bool {
+        match *self {
+            TrackFilter::Program(number) => programs
+                .iter()
+                .any(|program| program.number == number && program.track_ids.contains(&track_id)),
+            TrackFilter::Track(id) => id == track_id,
+            TrackFilter::ExcludeTrack(id) => id != track_id,
         }
     }
 }
 
+/// A `Program` is a group of tracks that are presented together, such as a single service of a
+/// broadcast transport stream.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Program {
+    /// The program number.
+    pub number: u32,
+    /// The identifiers of the tracks that belong to the program.
+    pub track_ids: Vec<u32>,
+}
+
+/// A `TimestampDiscontinuity` is a break in the timestamps of a track that was detected, and
+/// corrected, by a `FormatReader`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TimestampDiscontinuity {
+    /// The track ID.
+    pub track_id: u32,
+    /// The timestamp of the first packet of the track after the discontinuity.
+    pub ts: u64,
+    /// The discontinuity of the raw timestamps of the track.
+    pub discontinuity: util::Discontinuity,
+}
+
 /// A `Cue` is a designated point of time within a media stream.
 ///
 /// A `Cue` may be a mapping from either a source track, a chapter, cuesheet, or a timestamp
@@ -107,6 +233,11 @@ impl Default for FormatOptions {
 /// timestamp and the next. Each `Cue` may contain an optional index of points relative to the `Cue`
 /// that never exceed the timestamp of the next `Cue`. A `Cue` may also have associated `Tag`s.
 #[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_crate::Serialize, serde_crate::Deserialize),
+    serde(crate = "serde_crate")
+)]
 pub struct Cue {
     /// A unique index for the `Cue`.
     pub index: u32,
@@ -124,6 +255,11 @@ pub struct Cue {
 /// A `CuePoint` provides more precise indexing within a parent `Cue`. Additional `Tag`s may be
 /// associated with a `CuePoint`.
 #[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_crate::Serialize, serde_crate::Deserialize),
+    serde(crate = "serde_crate")
+)]
 pub struct CuePoint {
     /// The offset of the first frame in the `CuePoint` relative to the start of the parent `Cue`.
     pub start_offset_ts: u64,
@@ -131,6 +267,155 @@ pub struct CuePoint {
     pub tags: Vec<Tag>,
 }
 
+/// The number of CD-DA frames (sectors) per second. Cuesheet positions are measured in these
+/// frames.
+pub const CUESHEET_FRAMES_PER_SEC: u64 = 75;
+
+/// A `Cuesheet` describes the layout of tracks within one or more media files, typically a
+/// single-file image of an entire album.
+///
+/// A `Cuesheet` is a more detailed representation than a list of `Cue`s. It may be read from an
+/// external cuesheet file, or from a cuesheet embedded in a media format, and then converted
+/// into `Cue`s once the sample rate of the media is known.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_crate::Serialize, serde_crate::Deserialize),
+    serde(crate = "serde_crate")
+)]
+pub struct Cuesheet {
+    /// The Media Catalog Number (MCN) of the disc, if present.
+    pub catalog: Option<String>,
+    /// A list of `Tag`s associated with the entire disc (e.g., album title, performer).
+    pub tags: Vec<Tag>,
+    /// The tracks of the cuesheet in the order they appear.
+    pub tracks: Vec<CuesheetTrack>,
+}
+
+/// A `CuesheetTrack` is a single track within a `Cuesheet`.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_crate::Serialize, serde_crate::Deserialize),
+    serde(crate = "serde_crate")
+)]
+pub struct CuesheetTrack {
+    /// The track number.
+    pub number: u32,
+    /// The file containing the track, if known. All positions of the track are relative to the
+    /// start of this file.
+    pub file: Option<String>,
+    /// The International Standard Recording Code (ISRC) of the track, if present.
+    pub isrc: Option<String>,
+    /// If `true`, the track contains audio data.
+    pub is_audio: bool,
+    /// If `true`, the track was mastered with pre-emphasis.
+    pub pre_emphasis: bool,
+    /// The length of silence, in CD-DA frames, that is not stored in the file but should be
+    /// inserted before the track.
+    pub pregap: Option<u64>,
+    /// The length of silence, in CD-DA frames, that is not stored in the file but should be
+    /// inserted after the track.
+    pub postgap: Option<u64>,
+    /// A list of `Tag`s associated with the track (e.g., track title, performer).
+    pub tags: Vec<Tag>,
+    /// The index points of the track in ascending order.
+    pub indices: Vec<CuesheetIndex>,
+}
+
+/// A `CuesheetIndex` is an index point within a `CuesheetTrack`.
+///
+/// Index 0, if present, marks the start of the pre-gap of a track, while index 1 marks the start
+/// of the track itself. All further indices mark subdivisions within the track.
+#[derive(Copy, Clone, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_crate::Serialize, serde_crate::Deserialize),
+    serde(crate = "serde_crate")
+)]
+pub struct CuesheetIndex {
+    /// The index number.
+    pub number: u32,
+    /// The position of the index point, in CD-DA frames, from the start of the track's file.
+    pub start: u64,
+}
+
+impl CuesheetTrack {
+    /// Gets the position, in CD-DA frames, of the start of the track (index 1). If the track does
+    /// not have an index 1, the first index is used instead.
+    pub fn start(&self) -> Option<u64> {
+        self.indices
+            .iter()
+            .find(|idx| idx.number == 1)
+            .or_else(|| self.indices.first())
+            .map(|idx| idx.start)
+    }
+}
+
+impl Cuesheet {
+    /// Converts the `Cuesheet` into a list of `Cue`s given the sample rate of the media.
+    ///
+    /// Each `CuesheetTrack` becomes a `Cue` starting at its first index point, and each index
+    /// point becomes a `CuePoint` relative to the start of the `Cue`. Since the positions of a
+    /// track are relative to the start of its file, the resulting `Cue`s are only meaningful for
+    /// tracks that share the same file.
+    pub fn to_cues(&self, sample_rate: u32) -> Vec<Cue> {
+        let to_ts = |frames: u64| frames * u64::from(sample_rate) / CUESHEET_FRAMES_PER_SEC;
+
+        self.tracks
+            .iter()
+            .map(|track| {
+                let start = track.indices.first().map_or(0, |idx| idx.start);
+
+                let mut tags = track.tags.clone();
+
+                if let Some(isrc) = &track.isrc {
+                    tags.push(Tag::new(
+                        Some(StandardTagKey::IdentIsrc),
+                        "ISRC",
+                        Value::from(isrc.as_str()),
+                    ));
+                }
+
+                let points = track
+                    .indices
+                    .iter()
+                    .map(|idx| CuePoint {
+                        start_offset_ts: to_ts(idx.start.saturating_sub(start)),
+                        tags: Vec::new(),
+                    })
+                    .collect();
+
+                Cue { index: track.number, start_ts: to_ts(start), tags, points }
+            })
+            .collect()
+    }
+}
+
+/// A `LoopRegion` is a region of a track that should be played repeatedly, commonly used by game
+/// audio and samplers.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct LoopRegion {
+    /// The timestamp of the first frame of the loop.
+    pub start_ts: u64,
+    /// The timestamp of the frame after the last frame of the loop.
+    pub end_ts: u64,
+    /// The number of times the loop region should be played, or 0 if the loop should be played
+    /// indefinitely.
+    pub play_count: u32,
+}
+
+bitflags! {
+    /// Flags signalled by the container for a `Track`.
+    #[derive(Default)]
+    pub struct TrackFlags: u32 {
+        /// The track should be selected for playback if the user has no other preference.
+        const DEFAULT = 0x1;
+        /// The track should be selected for playback regardless of the user's preferences.
+        const FORCED  = 0x2;
+    }
+}
+
 /// A `Track` is an independently coded media bitstream. A media format may contain multiple tracks
 /// in one container. Each of those tracks are represented by one `Track`.
 #[derive(Clone, Debug)]
@@ -141,11 +426,15 @@ pub struct Track {
     pub codec_params: CodecParameters,
     /// The language of the track. May be unknown.
     pub language: Option<String>,
+    /// The name of the track. May be unknown.
+    pub name: Option<String>,
+    /// The flags signalled by the container for the track.
+    pub flags: TrackFlags,
 }
 
 impl Track {
     pub fn new(id: u32, codec_params: CodecParameters) -> Self {
-        Track { id, codec_params, language: None }
+        Track { id, codec_params, language: None, name: None, flags: TrackFlags::empty() }
     }
 }
 
@@ -165,6 +454,14 @@ impl Track {
 /// `FormatReader` provides an Iterator-like interface over packets for easy consumption and
 /// filtering. Seeking will invalidate the state of any `Decoder` processing packets from the
 /// `FormatReader` and should be reset after a successful seek operation.
+///
+/// A `FormatReader` must support unseekable media sources such as pipes or network streams. When
+/// the source is unseekable, the reader may only skip data by reading it, may only rewind within
+/// the buffer of the `MediaSourceStream`, and may not know the duration of its tracks. Seeking
+/// backwards on an unseekable source should return a `SeekErrorKind::ForwardOnly` error.
+///
+/// A `FormatReader` must be `Send` and `Sync` such that it may be moved to, or shared with,
+/// another thread.
 pub trait FormatReader: Send + Sync {
     /// Attempt to instantiate a `FormatReader` using the provided `FormatOptions` and
     /// `MediaSourceStream`. The reader will probe the container to verify format support, determine
@@ -196,11 +493,32 @@ pub trait FormatReader: Send + Sync {
     /// Gets a list of tracks in the container.
     fn tracks(&self) -> &[Track];
 
+    /// Gets a list of programs in the content, if the container groups tracks into programs.
+    /// Programs may be selected for demuxing with `FormatOptions::track_filter`.
+    fn programs(&self) -> &[Program] {
+        &[]
+    }
+
+    /// Takes the timestamp discontinuities that were detected, and corrected, since the last call.
+    ///
+    /// Only readers that normalize the raw timestamps of a container, such as a transport stream
+    /// reader, detect discontinuities. A reader keeps a limited number of discontinuities, dropping
+    /// the oldest, so this should be called regularly, for example, after every packet.
+    fn take_discontinuities(&mut self) -> Vec<TimestampDiscontinuity> {
+        Vec::new()
+    }
+
     /// Gets the default track. If the `FormatReader` has a method of determining the default track,
-    /// this function should return it. Otherwise, the first track is returned. If no tracks are
-    /// present then `None` is returned.
+    /// this function should return it. Otherwise, the track is selected as described by
+    /// [`util::select_default_track`]. If no tracks are present then `None` is returned.
     fn default_track(&self) -> Option<&Track> {
-        self.tracks().first()
+        util::select_default_track(self.tracks(), None)
+    }
+
+    /// Gets the default track, preferring tracks in the provided language. The language should be
+    /// an ISO 639 or BCP 47 language code. If no tracks are present then `None` is returned.
+    fn default_track_for_language(&self, language: &str) -> Option<&Track> {
+        util::select_default_track(self.tracks(), Some(language))
     }
 
     /// Get the next packet from the container.
@@ -238,17 +556,19 @@ pub struct Packet {
     pub trim_end: u32,
     /// The packet buffer.
     pub data: Box<[u8]>,
+    /// The byte position of the packet in the media source stream, if known.
+    pos: Option<u64>,
 }
 
 impl Packet {
     /// Create a new `Packet` from a slice.
     pub fn new_from_slice(track_id: u32, ts: u64, dur: u64, buf: &[u8]) -> Self {
-        Packet { track_id, ts, dur, trim_start: 0, trim_end: 0, data: Box::from(buf) }
+        Packet { track_id, ts, dur, trim_start: 0, trim_end: 0, data: Box::from(buf), pos: None }
     }
 
     /// Create a new `Packet` from a boxed slice.
     pub fn new_from_boxed_slice(track_id: u32, ts: u64, dur: u64, data: Box<[u8]>) -> Self {
-        Packet { track_id, ts, dur, trim_start: 0, trim_end: 0, data }
+        Packet { track_id, ts, dur, trim_start: 0, trim_end: 0, data, pos: None }
     }
 
     /// Create a new `Packet` with trimming information from a slice.
@@ -260,7 +580,7 @@ impl Packet {
         trim_end: u32,
         buf: &[u8],
     ) -> Self {
-        Packet { track_id, ts, dur, trim_start, trim_end, data: Box::from(buf) }
+        Packet { track_id, ts, dur, trim_start, trim_end, data: Box::from(buf), pos: None }
     }
 
     /// Create a new `Packet` with trimming information from a boxed slice.
@@ -272,7 +592,19 @@ impl Packet {
         trim_end: u32,
         data: Box<[u8]>,
     ) -> Self {
-        Packet { track_id, ts, dur, trim_start, trim_end, data }
+        Packet { track_id, ts, dur, trim_start, trim_end, data, pos: None }
+    }
+
+    /// Sets the byte position of the packet in the media source stream.
+    pub fn with_pos(mut self, pos: u64) -> Self {
+        self.pos = Some(pos);
+        self
+    }
+
+    /// Get the byte position of the packet in the media source stream, if known by the
+    /// `FormatReader`. This is useful for debugging, but should not be used for seeking.
+    pub fn pos(&self) -> Option<u64> {
+        self.pos
     }
 
     /// The track identifier of the track this packet belongs to.
@@ -327,7 +659,96 @@ impl Packet {
 pub mod util {
     //! Helper utilities for implementing `FormatReader`s.
 
-    use super::Packet;
+    use super::{LoopRegion, Packet, Track, TrackFlags};
+    use crate::codecs::CODEC_TYPE_NULL;
+    use crate::meta::Tag;
+
+    /// Gets a `LoopRegion` from the loop tags of a metadata revision.
+    ///
+    /// A loop is described by a `LOOPSTART` tag, and either a `LOOPLENGTH` or `LOOPEND` tag, where
+    /// `LOOPEND` is the timestamp of the last frame of the loop. An optional `LOOPCOUNT` tag
+    /// specifies the number of times the loop should be played. These are the tags used by RPG
+    /// Maker and other game engines in Vorbis comments, and by Symphonia for the sampler loops of
+    /// other formats.
+    pub fn loop_region_from_tags(tags: &[Tag]) -> Option<LoopRegion> {
+        let get = |key: &str| {
+            tags.iter()
+                .find(|tag| tag.key.eq_ignore_ascii_case(key))
+                .and_then(|tag| tag.value.to_string().trim().parse::<u64>().ok())
+        };
+
+        let start_ts = get("LOOPSTART")?;
+
+        let end_ts = match get("LOOPLENGTH") {
+            Some(len) => start_ts.checked_add(len)?,
+            None => get("LOOPEND")?.checked_add(1)?,
+        };
+
+        if end_ts <= start_ts {
+            return None;
+        }
+
+        let play_count = get("LOOPCOUNT").map_or(0, |count| count.min(u64::from(u32::MAX)) as u32);
+
+        Some(LoopRegion { start_ts, end_ts, play_count })
+    }
+
+    /// Gets the primary language subtag of a language code.
+    fn primary_language(language: &str) -> &str {
+        language.split(|c| c == '-' || c == '_').next().unwrap_or("")
+    }
+
+    /// Returns true if the track language matches the preferred language. A language matches if
+    /// it is equal to, or has the same primary language subtag as, the preferred language (e.g.,
+    /// "en-US" matches "en").
+    fn is_language_match(track: &Track, language: &str) -> bool {
+        match &track.language {
+            Some(lang) => {
+                lang.eq_ignore_ascii_case(language)
+                    || primary_language(lang).eq_ignore_ascii_case(primary_language(language))
+            }
+            None => false,
+        }
+    }
+
+    /// Selects the default track from a list of tracks.
+    ///
+    /// Tracks with a known codec are always preferred over tracks without one. Amongst those, the
+    /// first track satisfying the most of the following criteria, in order of importance, is
+    /// selected:
+    ///
+    /// 1. The track is in the preferred language, if one is provided.
+    /// 2. The track is flagged as the default track.
+    /// 3. The track is flagged as a forced track.
+    ///
+    /// If no tracks are provided then `None` is returned.
+    pub fn select_default_track<'a>(
+        tracks: &'a [Track],
+        language: Option<&str>,
+    ) -> Option<&'a Track> {
+        let score = |track: &Track| {
+            let mut score = 0;
+
+            if track.codec_params.codec != CODEC_TYPE_NULL {
+                score |= 0x8;
+            }
+            if language.map_or(false, |language| is_language_match(track, language)) {
+                score |= 0x4;
+            }
+            if track.flags.contains(TrackFlags::DEFAULT) {
+                score |= 0x2;
+            }
+            if track.flags.contains(TrackFlags::FORCED) {
+                score |= 0x1;
+            }
+
+            score
+        };
+
+        // Select the first track with the highest score. Since max_by_key returns the last maximum
+        // element, iterate in reverse.
+        tracks.iter().rev().max_by_key(|track| score(track))
+    }
 
     /// A `SeekPoint` is a mapping between a sample or frame number to byte offset within a media
     /// stream.
@@ -482,9 +903,257 @@ pub mod util {
         }
     }
 
+    /// `Discontinuity` describes a break in the timeline of raw timestamps that was detected, and
+    /// corrected, by a `TimestampNormalizer`.
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub enum Discontinuity {
+        /// The raw timestamp wrapped-around the maximum value representable by the container.
+        Wrapped,
+        /// The raw timestamp jumped forwards or backwards by more than the maximum allowed gap.
+        Jump {
+            /// The last raw timestamp before the jump.
+            from: u64,
+            /// The first raw timestamp after the jump.
+            to: u64,
+        },
+    }
+
+    /// A `TimestampNormalizer` maps the raw timestamps of a stream onto a continuous and monotonic
+    /// timeline.
+    ///
+    /// Containers that are designed for broadcast or live streaming (e.g., MPEG-TS, or captures of
+    /// internet radio) often have timestamps that wrap-around after a fixed number of bits, or
+    /// that abruptly jump when the stream is spliced or the encoder is restarted. A
+    /// `TimestampNormalizer` detects these discontinuities and renormalizes the timestamps such
+    /// that they continue from where the previous packet ended.
+    ///
+    /// The first timestamp passed to the normalizer is used as-is.
+    pub struct TimestampNormalizer {
+        /// If the raw timestamps wrap-around, the mask of the valid timestamp bits.
+        mask: Option<u64>,
+        /// The maximum difference between consecutive raw timestamps that is not considered a
+        /// discontinuity.
+        max_gap: u64,
+        /// The last raw timestamp, and its normalized timestamp.
+        last: Option<(u64, u64)>,
+        /// The expected normalized timestamp of the next packet.
+        next_ts: u64,
+        /// If true, the next timestamp starts a new timeline.
+        is_discontinuous: bool,
+        /// The total number of discontinuities detected.
+        num_discontinuities: u64,
+    }
+
+    impl TimestampNormalizer {
+        /// Instantiate a `TimestampNormalizer` for raw timestamps that never wrap-around.
+        /// Consecutive timestamps differing by more than `max_gap` are considered a discontinuity.
+        pub fn new(max_gap: u64) -> Self {
+            TimestampNormalizer {
+                mask: None,
+                max_gap,
+                last: None,
+                next_ts: 0,
+                is_discontinuous: false,
+                num_discontinuities: 0,
+            }
+        }
+
+        /// Instantiate a `TimestampNormalizer` for raw timestamps that are `bits` wide and
+        /// wrap-around to 0 after reaching their maximum value (e.g, 33 for MPEG-TS PTS).
+        /// Consecutive timestamps differing by more than `max_gap` are considered a discontinuity.
+        pub fn with_wrap_bits(bits: u32, max_gap: u64) -> Self {
+            assert!(bits > 0 && bits < 64);
+
+            TimestampNormalizer { mask: Some((1 << bits) - 1), ..Self::new(max_gap) }
+        }
+
+        /// Gets the total number of discontinuities that were detected.
+        pub fn num_discontinuities(&self) -> u64 {
+            self.num_discontinuities
+        }
+
+        /// Resets the normalizer such that the next timestamp is considered to be the first.
+        pub fn reset(&mut self) {
+            self.last = None;
+            self.next_ts = 0;
+            self.is_discontinuous = false;
+        }
+
+        /// Signals that the next timestamp starts a new timeline, such as when the container
+        /// flags a discontinuity. The next timestamp is then a discontinuity regardless of its
+        /// difference to the last timestamp.
+        pub fn signal_discontinuity(&mut self) {
+            self.is_discontinuous = true;
+        }
+
+        /// Normalize the raw timestamp, `ts`, of a packet with duration `dur`. Returns the
+        /// normalized timestamp, and the discontinuity that was detected, if any.
+        pub fn normalize(&mut self, ts: u64, dur: u64) -> (u64, Option<Discontinuity>) {
+            let ts = self.mask.map_or(ts, |mask| ts & mask);
+
+            let (norm_ts, discontinuity) = match self.last {
+                Some((last_ts, last_norm_ts)) => {
+                    // Calculate the signed difference between the raw timestamps. If the
+                    // timestamps wrap-around, the shortest distance around the "clock" is used.
+                    let (delta, wrapped) = match self.mask {
+                        Some(mask) => {
+                            let fwd = ts.wrapping_sub(last_ts) & mask;
+
+                            if fwd <= mask >> 1 {
+                                (i128::from(fwd), ts < last_ts)
+                            }
+                            else {
+                                (-i128::from((mask - fwd) + 1), ts > last_ts)
+                            }
+                        }
+                        None => (i128::from(ts) - i128::from(last_ts), false),
+                    };
+
+                    if delta.unsigned_abs() <= u128::from(self.max_gap) && !self.is_discontinuous {
+                        let norm_ts = (i128::from(last_norm_ts) + delta).max(0) as u64;
+                        (norm_ts, if wrapped { Some(Discontinuity::Wrapped) } else { None })
+                    }
+                    else {
+                        // The jump is too large. Continue from where the last packet ended.
+                        (self.next_ts, Some(Discontinuity::Jump { from: last_ts, to: ts }))
+                    }
+                }
+                None => (ts, None),
+            };
+
+            if discontinuity.is_some() {
+                self.num_discontinuities += 1;
+            }
+
+            self.is_discontinuous = false;
+            self.last = Some((ts, norm_ts));
+            self.next_ts = norm_ts + dur;
+
+            (norm_ts, discontinuity)
+        }
+    }
+
     #[cfg(test)]
     mod tests {
+        use super::{loop_region_from_tags, LoopRegion};
+        use super::{select_default_track, Track, TrackFlags};
+        use super::{Discontinuity, TimestampNormalizer};
         use super::{SeekIndex, SeekPoint, SeekSearchResult};
+        use crate::codecs::{CodecParameters, CODEC_TYPE_FLAC, CODEC_TYPE_NULL};
+        use crate::formats::{Program, TrackFilter};
+        use crate::meta::{Tag, Value};
+
+        #[test]
+        fn verify_track_filter() {
+            let programs = vec![
+                Program { number: 1, track_ids: vec![0x100, 0x101] },
+                Program { number: 2, track_ids: vec![0x200] },
+            ];
+
+            assert!(TrackFilter::Program(1).is_selected(0x101, &programs));
+            assert!(!TrackFilter::Program(1).is_selected(0x200, &programs));
+            assert!(!TrackFilter::Program(3).is_selected(0x100, &programs));
+            assert!(TrackFilter::Track(0x200).is_selected(0x200, &[]));
+            assert!(!TrackFilter::Track(0x200).is_selected(0x100, &programs));
+            assert!(TrackFilter::ExcludeTrack(0x200).is_selected(0x100, &programs));
+            assert!(!TrackFilter::ExcludeTrack(0x200).is_selected(0x200, &[]));
+        }
+
+        #[test]
+        fn verify_loop_region_from_tags() {
+            let tag = |key, value: &str| Tag::new(None, key, Value::from(value));
+
+            let tags = vec![tag("LOOPSTART", "44100"), tag("LOOPLENGTH", "88200")];
+            assert_eq!(
+                loop_region_from_tags(&tags),
+                Some(LoopRegion { start_ts: 44100, end_ts: 132300, play_count: 0 })
+            );
+
+            let tags = vec![tag("loopstart", "10"), tag("LOOPEND", "19"), tag("LOOPCOUNT", "3")];
+            assert_eq!(
+                loop_region_from_tags(&tags),
+                Some(LoopRegion { start_ts: 10, end_ts: 20, play_count: 3 })
+            );
+
+            assert_eq!(loop_region_from_tags(&[tag("LOOPSTART", "10")]), None);
+            assert_eq!(loop_region_from_tags(&[tag("LOOPSTART", "x"), tag("LOOPEND", "5")]), None);
+            assert_eq!(
+                loop_region_from_tags(&[tag("LOOPSTART", "10"), tag("LOOPLENGTH", "0")]),
+                None
+            );
+        }
+
+        #[test]
+        fn verify_select_default_track() {
+            let make_track = |id, codec, language: Option<&str>, flags| {
+                let mut params = CodecParameters::new();
+                params.for_codec(codec);
+
+                let mut track = Track::new(id, params);
+                track.language = language.map(|lang| lang.to_string());
+                track.flags = flags;
+                track
+            };
+
+            let tracks = vec![
+                make_track(0, CODEC_TYPE_NULL, Some("en"), TrackFlags::DEFAULT),
+                make_track(1, CODEC_TYPE_FLAC, Some("de"), TrackFlags::empty()),
+                make_track(2, CODEC_TYPE_FLAC, Some("en-US"), TrackFlags::empty()),
+                make_track(3, CODEC_TYPE_FLAC, Some("fr"), TrackFlags::DEFAULT),
+                make_track(4, CODEC_TYPE_FLAC, Some("eng"), TrackFlags::FORCED),
+            ];
+
+            let select = |language| select_default_track(&tracks, language).map(|t| t.id);
+
+            assert_eq!(select(None), Some(3));
+            assert_eq!(select(Some("en")), Some(2));
+            assert_eq!(select(Some("eng")), Some(4));
+            assert_eq!(select(Some("de-AT")), Some(1));
+            assert_eq!(select(Some("ja")), Some(3));
+            assert_eq!(select_default_track(&tracks[..3], None).map(|t| t.id), Some(1));
+            assert_eq!(select_default_track(&tracks[..1], None).map(|t| t.id), Some(0));
+            assert_eq!(select_default_track(&[], None).map(|t| t.id), None);
+        }
+
+        #[test]
+        fn verify_timestamp_normalizer() {
+            // Wrap-around of a 33-bit timestamp.
+            let mut norm = TimestampNormalizer::with_wrap_bits(33, 90000);
+            let max = (1u64 << 33) - 1;
+
+            assert_eq!(norm.normalize(max - 1000, 1000), (max - 1000, None));
+            assert_eq!(norm.normalize(max - 100, 100), (max - 100, None));
+            assert_eq!(norm.normalize(900, 1000), (max + 901, Some(Discontinuity::Wrapped)));
+            assert_eq!(norm.normalize(1900, 1000), (max + 1901, None));
+
+            // Small backwards step is tolerated.
+            assert_eq!(norm.normalize(1800, 1000), (max + 1801, None));
+
+            // Large forward jump continues from the end of the last packet.
+            assert_eq!(
+                norm.normalize(1_000_000, 1000),
+                (max + 2801, Some(Discontinuity::Jump { from: 1800, to: 1_000_000 }))
+            );
+
+            // Large backward jump without wrap-around.
+            let mut norm = TimestampNormalizer::new(10);
+            assert_eq!(norm.normalize(500, 5), (500, None));
+            assert_eq!(norm.normalize(505, 5), (505, None));
+            assert_eq!(norm.normalize(0, 5), (510, Some(Discontinuity::Jump { from: 505, to: 0 })));
+            assert_eq!(norm.normalize(5, 5), (515, None));
+            assert_eq!(norm.num_discontinuities(), 1);
+
+            // A signalled discontinuity is a jump even if the difference is small.
+            norm.signal_discontinuity();
+            assert_eq!(norm.normalize(8, 5), (520, Some(Discontinuity::Jump { from: 5, to: 8 })));
+            assert_eq!(norm.normalize(13, 5), (525, None));
+            assert_eq!(norm.num_discontinuities(), 2);
+
+            // A signalled discontinuity before the first timestamp is ignored.
+            norm.reset();
+            norm.signal_discontinuity();
+            assert_eq!(norm.normalize(100, 5), (100, None));
+        }
 
         #[test]
         fn verify_seek_index_search() {
@@ -38,6 +38,59 @@ pub mod vlc {
         Reverse,
     }
 
+    /// Generates canonical Huffman codewords from a list of codeword lengths.
+    ///
+    /// Codewords are assigned in order of increasing length, and then in order of their position
+    /// in the list for codewords of the same length. A codeword length of 0 indicates an unused
+    /// codeword, and will be assigned a codeword of 0. Codeword lengths may not exceed 32 bits.
+    ///
+    /// This function will fail if the codeword lengths over-subscribe the code space.
+    pub fn canonical_code_words(code_lens: &[u8]) -> io::Result<Vec<u32>> {
+        let mut len_counts = [0u64; 33];
+
+        for &len in code_lens {
+            if len > 32 {
+                return codebook_error("core (io): codeword length exceeds 32 bits");
+            }
+            len_counts[usize::from(len)] += 1;
+        }
+
+        // Unused codewords do not occupy any code space.
+        len_counts[0] = 0;
+
+        // Find the first codeword of each length.
+        let mut next_code = [0u64; 33];
+        let mut code = 0;
+
+        for len in 1..33 {
+            code = (code + len_counts[len - 1]) << 1;
+
+            if code + len_counts[len] > 1 << len {
+                return codebook_error("core (io): codeword lengths over-subscribe the code space");
+            }
+
+            next_code[len] = code;
+        }
+
+        let code_words = code_lens
+            .iter()
+            .map(|&len| {
+                let len = usize::from(len);
+
+                if len > 0 {
+                    let code = next_code[len];
+                    next_code[len] += 1;
+                    code as u32
+                }
+                else {
+                    0
+                }
+            })
+            .collect();
+
+        Ok(code_words)
+    }
+
     /// `CodebookEntry` provides the functions required for an entry in the `Codebook`.
     pub trait CodebookEntry: Copy + Clone + Default {
         /// The type of a value in this entry.
@@ -518,6 +571,19 @@ pub mod vlc {
 
             Ok(Codebook { table, max_code_len: u32::from(max_code_len), init_block_len })
         }
+
+        /// Construct a `Codebook` of canonical Huffman codewords using the given codeword lengths,
+        /// and values. See [`canonical_code_words`] for how codewords are assigned.
+        ///
+        /// This function will panic if the number of code lengths and values differ.
+        pub fn make_canonical<E: CodebookEntry>(
+            &mut self,
+            code_lens: &[u8],
+            values: &[E::ValueType],
+        ) -> io::Result<Codebook<E>> {
+            let code_words = canonical_code_words(code_lens)?;
+            self.make(&code_words, code_lens, values)
+        }
     }
 }
 
@@ -913,9 +979,21 @@ impl<'a, B: ReadBytes> private::FetchBitsLtr for BitStreamLtr<'a, B> {
         Ok(())
     }
 
-    #[inline(always)]
+    #[inline]
     fn fetch_bits_partial(&mut self) -> io::Result<()> {
-        todo!()
+        // Top-up the bit buffer a byte at a time. Reaching the end of the stream is not an error
+        // since a partial fetch does not guarantee any bits will be buffered.
+        while self.n_bits_left <= u64::BITS - u8::BITS {
+            match self.reader.read_u8() {
+                Ok(byte) => {
+                    self.bits |= u64::from(byte) << (u64::BITS - u8::BITS - self.n_bits_left);
+                    self.n_bits_left += u8::BITS;
+                }
+                Err(ref err) if err.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
     }
 
     #[inline(always)]
@@ -1362,9 +1440,21 @@ impl<'a, B: ReadBytes> private::FetchBitsRtl for BitStreamRtl<'a, B> {
         Ok(())
     }
 
-    #[inline(always)]
+    #[inline]
     fn fetch_bits_partial(&mut self) -> io::Result<()> {
-        todo!()
+        // Top-up the bit buffer a byte at a time. Reaching the end of the stream is not an error
+        // since a partial fetch does not guarantee any bits will be buffered.
+        while self.n_bits_left <= u64::BITS - u8::BITS {
+            match self.reader.read_u8() {
+                Ok(byte) => {
+                    self.bits |= u64::from(byte) << self.n_bits_left;
+                    self.n_bits_left += u8::BITS;
+                }
+                Err(ref err) if err.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
     }
 
     #[inline(always)]
@@ -1466,9 +1556,10 @@ impl<'a> FiniteBitStream for BitReaderRtl<'a> {
 
 #[cfg(test)]
 mod tests {
-    use super::vlc::{BitOrder, Codebook, CodebookBuilder, Entry8x8};
-    use super::{BitReaderLtr, ReadBitsLtr};
-    use super::{BitReaderRtl, ReadBitsRtl};
+    use super::vlc::{canonical_code_words, BitOrder, Codebook, CodebookBuilder, Entry8x8};
+    use super::{BitReaderLtr, BitStreamLtr, ReadBitsLtr};
+    use super::{BitReaderRtl, BitStreamRtl, ReadBitsRtl};
+    use crate::io::BufReader;
 
     #[test]
     #[allow(clippy::bool_assert_comparison)]
@@ -1831,6 +1922,45 @@ mod tests {
             (0..text.len()).into_iter().map(|_| bs.read_codebook(&codebook).unwrap().0).collect();
 
         assert_eq!(text, std::str::from_utf8(&decoded).unwrap());
+
+        // Repeat using a BitStream.
+        let mut reader = BufReader::new(&buf);
+        let mut bs = BitStreamLtr::new(&mut reader);
+
+        let decoded: Vec<u8> =
+            (0..text.len()).into_iter().map(|_| bs.read_codebook(&codebook).unwrap().0).collect();
+
+        assert_eq!(text, std::str::from_utf8(&decoded).unwrap());
+    }
+
+    #[test]
+    fn verify_canonical_code_words() {
+        // Example code from RFC 1951, section 3.2.2.
+        let code_lens = [3, 3, 3, 3, 3, 2, 4, 4];
+
+        assert_eq!(
+            canonical_code_words(&code_lens).unwrap(),
+            [0b010, 0b011, 0b100, 0b101, 0b110, 0b00, 0b1110, 0b1111]
+        );
+
+        // Unused codewords.
+        assert_eq!(canonical_code_words(&[1, 0, 2, 2]).unwrap(), [0b0, 0, 0b10, 0b11]);
+
+        // Over-subscribed code space.
+        assert!(canonical_code_words(&[1, 1, 1]).is_err());
+        assert!(canonical_code_words(&[33]).is_err());
+
+        let values = [b'a', b'b', b'c', b'd', b'e', b'f', b'g', b'h'];
+
+        let mut builder = CodebookBuilder::new(BitOrder::Verbatim);
+        let codebook = builder.make_canonical::<Entry8x8>(&code_lens, &values).unwrap();
+
+        // Encodes "hfab" using the canonical codewords.
+        let mut bs = BitReaderLtr::new(&[0b1111_0001, 0b0011_0000]);
+
+        let decoded: Vec<u8> = (0..4).map(|_| bs.read_codebook(&codebook).unwrap().0).collect();
+
+        assert_eq!(&decoded, b"hfab");
     }
 
     // BitStreamRtl
@@ -2127,5 +2257,14 @@ mod tests {
             (0..text.len()).into_iter().map(|_| bs.read_codebook(&codebook).unwrap().0).collect();
 
         assert_eq!(text, std::str::from_utf8(&decoded).unwrap());
+
+        // Repeat using a BitStream.
+        let mut reader = BufReader::new(&buf);
+        let mut bs = BitStreamRtl::new(&mut reader);
+
+        let decoded: Vec<u8> =
+            (0..text.len()).into_iter().map(|_| bs.read_codebook(&codebook).unwrap().0).collect();
+
+        assert_eq!(text, std::str::from_utf8(&decoded).unwrap());
     }
 }
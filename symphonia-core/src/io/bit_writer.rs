@@ -0,0 +1,162 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// `BitWriterLtr` writes bits from most-significant to least-significant into a growable byte
+/// buffer. Bits written by a `BitWriterLtr` are read back in the same order by a `BitReaderLtr`.
+///
+/// Any unwritten bits of the last byte are 0.
+#[derive(Default)]
+pub struct BitWriterLtr {
+    buf: Vec<u8>,
+    n_bits: u64,
+}
+
+impl BitWriterLtr {
+    /// Instantiate a new, empty, `BitWriterLtr`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Writes a single bit.
+    pub fn write_bool(&mut self, bit: bool) {
+        if self.n_bits % 8 == 0 {
+            self.buf.push(0);
+        }
+
+        if bit {
+            *self.buf.last_mut().unwrap() |= 0x80 >> (self.n_bits % 8);
+        }
+
+        self.n_bits += 1;
+    }
+
+    /// Writes the least-significant `bit_width` bits of `bits`, where `bit_width` is <= 32.
+    pub fn write_bits_leq32(&mut self, bits: u32, bit_width: u32) {
+        debug_assert!(bit_width <= u32::BITS);
+
+        for i in (0..bit_width).rev() {
+            self.write_bool((bits >> i) & 1 != 0);
+        }
+    }
+
+    /// Gets the number of bits written.
+    pub fn bits_written(&self) -> u64 {
+        self.n_bits
+    }
+
+    /// Gets a reference to the bytes written so far.
+    pub fn written(&self) -> &[u8] {
+        &self.buf
+    }
+
+    /// Consumes the `BitWriterLtr`, returning the bytes written.
+    pub fn into_inner(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+/// `BitWriterRtl` writes bits from least-significant to most-significant into a growable byte
+/// buffer. Bits written by a `BitWriterRtl` are read back in the same order by a `BitReaderRtl`.
+///
+/// Any unwritten bits of the last byte are 0.
+#[derive(Default)]
+pub struct BitWriterRtl {
+    buf: Vec<u8>,
+    n_bits: u64,
+}
+
+impl BitWriterRtl {
+    /// Instantiate a new, empty, `BitWriterRtl`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Writes a single bit.
+    pub fn write_bool(&mut self, bit: bool) {
+        if self.n_bits % 8 == 0 {
+            self.buf.push(0);
+        }
+
+        if bit {
+            *self.buf.last_mut().unwrap() |= 1 << (self.n_bits % 8);
+        }
+
+        self.n_bits += 1;
+    }
+
+    /// Writes the least-significant `bit_width` bits of `bits`, where `bit_width` is <= 32.
+    pub fn write_bits_leq32(&mut self, bits: u32, bit_width: u32) {
+        debug_assert!(bit_width <= u32::BITS);
+
+        for i in 0..bit_width {
+            self.write_bool((bits >> i) & 1 != 0);
+        }
+    }
+
+    /// Gets the number of bits written.
+    pub fn bits_written(&self) -> u64 {
+        self.n_bits
+    }
+
+    /// Gets a reference to the bytes written so far.
+    pub fn written(&self) -> &[u8] {
+        &self.buf
+    }
+
+    /// Consumes the `BitWriterRtl`, returning the bytes written.
+    pub fn into_inner(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BitWriterLtr, BitWriterRtl};
+    use crate::io::{BitReaderLtr, BitReaderRtl, ReadBitsLtr, ReadBitsRtl};
+
+    #[test]
+    fn verify_bitwriterltr() {
+        let mut bw = BitWriterLtr::new();
+        bw.write_bool(true);
+        bw.write_bits_leq32(0b010, 3);
+        bw.write_bits_leq32(0xabcd_1234, 32);
+        bw.write_bits_leq32(0x5, 0);
+        bw.write_bits_leq32(0x1f, 5);
+
+        assert_eq!(bw.bits_written(), 41);
+        assert_eq!(bw.written(), &[0xaa, 0xbc, 0xd1, 0x23, 0x4f, 0x80]);
+
+        let buf = bw.into_inner();
+        let mut bs = BitReaderLtr::new(&buf);
+        assert!(bs.read_bool().unwrap());
+        assert_eq!(bs.read_bits_leq32(3).unwrap(), 0b010);
+        assert_eq!(bs.read_bits_leq32(32).unwrap(), 0xabcd_1234);
+        assert_eq!(bs.read_bits_leq32(5).unwrap(), 0x1f);
+        assert_eq!(bs.read_bits_leq32(7).unwrap(), 0);
+    }
+
+    #[test]
+    fn verify_bitwriterrtl() {
+        let mut bw = BitWriterRtl::new();
+        bw.write_bool(true);
+        bw.write_bits_leq32(0b010, 3);
+        bw.write_bits_leq32(0xabcd_1234, 32);
+        bw.write_bits_leq32(0x5, 0);
+        bw.write_bits_leq32(0x1f, 5);
+
+        assert_eq!(bw.bits_written(), 41);
+        assert_eq!(bw.written(), &[0x45, 0x23, 0xd1, 0xbc, 0xfa, 0x01]);
+
+        let buf = bw.into_inner();
+        let mut bs = BitReaderRtl::new(&buf);
+        assert!(bs.read_bool().unwrap());
+        assert_eq!(bs.read_bits_leq32(3).unwrap(), 0b010);
+        assert_eq!(bs.read_bits_leq32(32).unwrap(), 0xabcd_1234);
+        assert_eq!(bs.read_bits_leq32(5).unwrap(), 0x1f);
+        assert_eq!(bs.read_bits_leq32(7).unwrap(), 0);
+    }
+}
@@ -0,0 +1,178 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::io;
+
+use super::{FiniteWriteStream, WriteBytes};
+
+#[inline(always)]
+fn overrun_error<T>() -> io::Result<T> {
+    Err(io::Error::new(io::ErrorKind::WriteZero, "buffer overrun"))
+}
+
+/// A `BufWriter` writes bytes into a fixed-size byte buffer.
+pub struct BufWriter<'a> {
+    buf: &'a mut [u8],
+    pos: usize,
+}
+
+impl<'a> BufWriter<'a> {
+    /// Instantiate a new `BufWriter` with a given byte buffer.
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        BufWriter { buf, pos: 0 }
+    }
+
+    /// Gets a reference to the bytes written so far.
+    pub fn written(&self) -> &[u8] {
+        &self.buf[..self.pos]
+    }
+}
+
+impl<'a> WriteBytes for BufWriter<'a> {
+    #[inline(always)]
+    fn write_byte(&mut self, byte: u8) -> io::Result<()> {
+        if self.pos >= self.buf.len() {
+            return overrun_error();
+        }
+
+        self.buf[self.pos] = byte;
+        self.pos += 1;
+
+        Ok(())
+    }
+
+    fn write_buf_exact(&mut self, buf: &[u8]) -> io::Result<()> {
+        if self.buf.len() - self.pos < buf.len() {
+            return overrun_error();
+        }
+
+        self.buf[self.pos..self.pos + buf.len()].copy_from_slice(buf);
+        self.pos += buf.len();
+
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn pos(&self) -> u64 {
+        self.pos as u64
+    }
+}
+
+impl<'a> FiniteWriteStream for BufWriter<'a> {
+    #[inline(always)]
+    fn byte_len(&self) -> u64 {
+        self.buf.len() as u64
+    }
+
+    #[inline(always)]
+    fn bytes_written(&self) -> u64 {
+        self.pos as u64
+    }
+
+    #[inline(always)]
+    fn bytes_available(&self) -> u64 {
+        (self.buf.len() - self.pos) as u64
+    }
+}
+
+/// A `WriteStream` writes bytes to any sink implementing [`std::io::Write`], such as a file or a
+/// `Vec<u8>`, and tracks the number of bytes written.
+///
+/// Writes are passed directly to the sink. Wrap the sink in a [`std::io::BufWriter`] if many
+/// small writes are expected.
+pub struct WriteStream<W: io::Write> {
+    inner: W,
+    pos: u64,
+}
+
+impl<W: io::Write> WriteStream<W> {
+    /// Instantiate a new `WriteStream` writing to the provided sink.
+    pub fn new(inner: W) -> Self {
+        WriteStream { inner, pos: 0 }
+    }
+
+    /// Gets a reference to the underlying sink.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Flushes the underlying sink.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+
+    /// Unwraps this `WriteStream`, returning the underlying sink.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: io::Write> WriteBytes for WriteStream<W> {
+    #[inline(always)]
+    fn write_byte(&mut self, byte: u8) -> io::Result<()> {
+        self.write_buf_exact(&[byte])
+    }
+
+    fn write_buf_exact(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.inner.write_all(buf)?;
+        self.pos += buf.len() as u64;
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn pos(&self) -> u64 {
+        self.pos
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BufWriter, WriteStream};
+    use crate::io::{BufReader, FiniteWriteStream, ReadBytes, WriteBytes};
+
+    fn write_all<W: WriteBytes>(writer: &mut W) -> std::io::Result<()> {
+        writer.write_u8(0xf0)?;
+        writer.write_be_i16(-2)?;
+        writer.write_u24(0x123456)?;
+        writer.write_be_i24(-3)?;
+        writer.write_u32(0xdeadbeef)?;
+        writer.write_be_u64(0x0102030405060708)?;
+        writer.write_f32(1.5)?;
+        writer.write_be_f64(-0.25)?;
+        writer.write_zeros(300)
+    }
+
+    #[test]
+    fn verify_write_bytes() {
+        let mut stream = WriteStream::new(Vec::new());
+        write_all(&mut stream).unwrap();
+
+        assert_eq!(stream.pos(), 333);
+
+        let buf = stream.into_inner();
+        let mut reader = BufReader::new(&buf);
+
+        assert_eq!(reader.read_u8().unwrap(), 0xf0);
+        assert_eq!(reader.read_be_i16().unwrap(), -2);
+        assert_eq!(reader.read_u24().unwrap(), 0x123456);
+        assert_eq!(reader.read_be_i24().unwrap(), -3);
+        assert_eq!(reader.read_u32().unwrap(), 0xdeadbeef);
+        assert_eq!(reader.read_be_u64().unwrap(), 0x0102030405060708);
+        assert_eq!(reader.read_f32().unwrap(), 1.5);
+        assert_eq!(reader.read_be_f64().unwrap(), -0.25);
+        assert!(buf[33..].iter().all(|&b| b == 0));
+
+        // A buffer writer produces the same bytes, and fails once full.
+        let mut fixed = [0u8; 333];
+        let mut writer = BufWriter::new(&mut fixed);
+        write_all(&mut writer).unwrap();
+
+        assert_eq!(writer.bytes_available(), 0);
+        assert_eq!(writer.written(), buf.as_slice());
+        assert!(writer.write_u8(0).is_err());
+    }
+}
@@ -19,14 +19,69 @@ fn end_of_stream_error<T>() -> io::Result<T> {
 }
 
 /// `MediaSourceStreamOptions` specifies the buffering behaviour of a `MediaSourceStream`.
+///
+/// New options may be added in minor releases. Construct the options from one of the presets, or
+/// from the default, and then use the builder-style setters to modify them.
+#[derive(Copy, Clone, Debug)]
+#[non_exhaustive]
 pub struct MediaSourceStreamOptions {
-    /// The maximum buffer size. Must be a power of 2. Must be > 32kB.
+    /// The maximum buffer size. Must be a power of 2. Must be > `max_read_len`.
     pub buffer_len: usize,
+    /// The length of the first read from the inner reader after instantiation or a seek. Must be
+    /// > 0, and <= `max_read_len`. The default is 1kB.
+    pub min_read_len: usize,
+    /// The maximum length of a read from the inner reader. The read length doubles after every
+    /// read until it reaches this length. The default is 32kB.
+    pub max_read_len: usize,
+}
+
+impl MediaSourceStreamOptions {
+    /// Options favouring low latency, such as for live network streams. Reads from the inner
+    /// reader are kept small so that decoding may begin as soon as possible.
+    pub fn low_latency() -> Self {
+        MediaSourceStreamOptions {
+            buffer_len: 32 * 1024,
+            min_read_len: 256,
+            max_read_len: 4 * 1024,
+        }
+    }
+
+    /// Options favouring throughput, such as for local files. Reads from the inner reader are
+    /// large to minimize system call overhead.
+    pub fn high_throughput() -> Self {
+        MediaSourceStreamOptions {
+            buffer_len: 1024 * 1024,
+            min_read_len: 32 * 1024,
+            max_read_len: 256 * 1024,
+        }
+    }
+
+    /// Sets the maximum buffer size.
+    pub fn with_buffer_len(mut self, buffer_len: usize) -> Self {
+        self.buffer_len = buffer_len;
+        self
+    }
+
+    /// Sets the length of the first read from the inner reader after instantiation or a seek.
+    pub fn with_min_read_len(mut self, min_read_len: usize) -> Self {
+        self.min_read_len = min_read_len;
+        self
+    }
+
+    /// Sets the maximum length of a read from the inner reader.
+    pub fn with_max_read_len(mut self, max_read_len: usize) -> Self {
+        self.max_read_len = max_read_len;
+        self
+    }
 }
 
 impl Default for MediaSourceStreamOptions {
     fn default() -> Self {
-        MediaSourceStreamOptions { buffer_len: 64 * 1024 }
+        MediaSourceStreamOptions {
+            buffer_len: 64 * 1024,
+            min_read_len: 1024,
+            max_read_len: 32 * 1024,
+        }
     }
 }
 
@@ -41,14 +96,16 @@ impl Default for MediaSourceStreamOptions {
 ///
 /// First, to minimize system call and dynamic dispatch overhead on the inner reader, and to
 /// amortize that overhead over many bytes, `MediaSourceStream` implements an exponentially growing
-/// read-ahead buffer. The read-ahead length starts at 1kB, and doubles in length as more sequential
-/// reads are performed until it reaches 32kB. Growing the read-ahead length over time reduces the
-/// excess data buffered on consecutive `seek()` calls.
+/// read-ahead buffer. By default, the read-ahead length starts at 1kB, and doubles in length as
+/// more sequential reads are performed until it reaches 32kB. Growing the read-ahead length over
+/// time reduces the excess data buffered on consecutive `seek()` calls. The read-ahead lengths may
+/// be tuned with [`MediaSourceStreamOptions`].
 ///
 /// Second, to better support non-seekable sources, `MediaSourceStream` implements a configurable
 /// length buffer cache. By default, the buffer caches allows backtracking by up-to the minimum of
-/// either `buffer_len - 32kB` or the total number of bytes read since instantiation or the last
-/// buffer cache invalidation. Note that regular a `seek()` will invalidate the buffer cache.
+/// either `buffer_len - max_read_len` or the total number of bytes read since instantiation or the
+/// last buffer cache invalidation. A regular `seek()` to a buffered position is served from the
+/// buffer cache, while a `seek()` to any other position will invalidate the buffer cache.
 pub struct MediaSourceStream {
     /// The source reader.
     inner: Box<dyn MediaSource>,
@@ -62,6 +119,10 @@ pub struct MediaSourceStream {
     write_pos: usize,
     /// The current block size for a new read.
     read_block_len: usize,
+    /// The block size of the first read after instantiation or a seek.
+    min_block_len: usize,
+    /// The maximum block size.
+    max_block_len: usize,
     /// Absolute position of the inner stream.
     abs_pos: u64,
     /// Relative position of the inner stream from the last seek or 0. This is a count of bytes
@@ -70,13 +131,13 @@ pub struct MediaSourceStream {
 }
 
 impl MediaSourceStream {
-    const MIN_BLOCK_LEN: usize = 1 * 1024;
-    const MAX_BLOCK_LEN: usize = 32 * 1024;
-
     pub fn new(source: Box<dyn MediaSource>, options: MediaSourceStreamOptions) -> Self {
         // The buffer length must be a power of 2, and > the maximum read block length.
         assert!(options.buffer_len.count_ones() == 1);
-        assert!(options.buffer_len > Self::MAX_BLOCK_LEN);
+        assert!(options.buffer_len > options.max_read_len);
+        // The read block length must be > 0, and the minimum must not exceed the maximum.
+        assert!(options.min_read_len > 0);
+        assert!(options.min_read_len <= options.max_read_len);
 
         MediaSourceStream {
             inner: source,
@@ -84,12 +145,57 @@ impl MediaSourceStream {
             ring_mask: options.buffer_len - 1,
             read_pos: 0,
             write_pos: 0,
-            read_block_len: Self::MIN_BLOCK_LEN,
+            read_block_len: options.min_read_len,
+            min_block_len: options.min_read_len,
+            max_block_len: options.max_read_len,
             abs_pos: 0,
             rel_pos: 0,
         }
     }
 
+    /// Reads up-to the number of bytes required to fill `buf` without consuming them, and returns
+    /// the number of bytes read.
+    ///
+    /// The buffer cache is grown as necessary to retain the peeked bytes. As such, peeking does not
+    /// require the underlying `MediaSource` to be seekable.
+    pub fn peek_buf(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let start = self.pos();
+
+        self.ensure_seekback_buffer(buf.len());
+
+        let mut len = 0;
+
+        // Read until the buffer is full, or the end of the stream is reached.
+        while len < buf.len() {
+            match self.read(&mut buf[len..]) {
+                Ok(0) => break,
+                Ok(n) => len += n,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => (),
+                Err(err) => {
+                    self.seek_buffered(start);
+                    return Err(err);
+                }
+            }
+        }
+
+        self.seek_buffered(start);
+
+        Ok(len)
+    }
+
+    /// Reads exactly the number of bytes required to fill `buf` without consuming them, or returns
+    /// an error.
+    ///
+    /// The buffer cache is grown as necessary to retain the peeked bytes. As such, peeking does not
+    /// require the underlying `MediaSource` to be seekable.
+    pub fn peek_buf_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        if self.peek_buf(buf)? < buf.len() {
+            return end_of_stream_error();
+        }
+
+        Ok(())
+    }
+
     /// Returns if the buffer has been exhausted This is a marginally more efficient way of checking
     /// if `unread_buffer_len() == 0`.
     #[inline(always)]
@@ -129,7 +235,7 @@ impl MediaSourceStream {
 
             // Grow the read block length exponentially to reduce the overhead of buffering on
             // consecutive seeks.
-            self.read_block_len = cmp::min(self.read_block_len << 1, Self::MAX_BLOCK_LEN);
+            self.read_block_len = cmp::min(self.read_block_len << 1, self.max_block_len);
         }
 
         Ok(())
@@ -168,7 +274,7 @@ impl MediaSourceStream {
     fn reset(&mut self, pos: u64) {
         self.read_pos = 0;
         self.write_pos = 0;
-        self.read_block_len = Self::MIN_BLOCK_LEN;
+        self.read_block_len = self.min_block_len;
         self.abs_pos = pos;
         self.rel_pos = 0;
     }
@@ -215,6 +321,35 @@ impl io::Read for MediaSourceStream {
 
 impl io::Seek for MediaSourceStream {
     fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        // If the position seeked to is buffered, seek within the buffer instead of seeking the
+        // inner reader. This retains the buffer, and does not require the inner reader to be
+        // seekable.
+        let current = self.pos();
+
+        let offset = |base: u64, delta: i64| {
+            if delta >= 0 {
+                base.checked_add(delta as u64)
+            }
+            else {
+                base.checked_sub(delta.unsigned_abs())
+            }
+        };
+
+        let target = match pos {
+            io::SeekFrom::Start(pos) => Some(pos),
+            io::SeekFrom::Current(delta) => offset(current, delta),
+            io::SeekFrom::End(delta) => self.inner.byte_len().and_then(|len| offset(len, delta)),
+        };
+
+        if let Some(target) = target {
+            let start = current - self.read_buffer_len() as u64;
+            let end = current + self.unread_buffer_len() as u64;
+
+            if (start..=end).contains(&target) {
+                return Ok(self.seek_buffered(target));
+            }
+        }
+
         // The current position of the underlying reader is ahead of the current position of the
         // MediaSourceStream by how ever many bytes have not been read from the read-ahead buffer
         // yet. When seeking from the current position adjust the position delta to offset that
@@ -379,10 +514,10 @@ impl SeekBuffered for MediaSourceStream {
     fn ensure_seekback_buffer(&mut self, len: usize) {
         let ring_len = self.ring.len();
 
-        // A fetch can overwrite a maximum of MAX_BLOCK_LEN bytes in the ring. Therefore, for there
-        // to always be `len` bytes available for seekback, the ring must be len + MAX_BLOCK_LEN in
+        // A fetch can overwrite a maximum of max_block_len bytes in the ring. Therefore, for there
+        // to always be `len` bytes available for seekback, the ring must be len + max_block_len in
         // length. Round-up to the next power-of-2 as that is an invariant of the ring.
-        let new_ring_len = (Self::MAX_BLOCK_LEN + len).next_power_of_two();
+        let new_ring_len = (self.max_block_len + len).next_power_of_two();
 
         // Only grow the ring if necessary.
         if ring_len < new_ring_len {
@@ -467,8 +602,9 @@ impl SeekBuffered for MediaSourceStream {
 
 #[cfg(test)]
 mod tests {
-    use super::{MediaSourceStream, ReadBytes, SeekBuffered};
-    use std::io::{Cursor, Read};
+    use super::{MediaSourceStream, MediaSourceStreamOptions, ReadBytes, SeekBuffered};
+    use crate::io::{MediaSource, ReadOnlySource};
+    use std::io::{Cursor, Read, Seek, SeekFrom};
 
     /// Generate a random vector of bytes of the specified length using a PRNG.
     fn generate_random_bytes(len: usize) -> Box<[u8]> {
@@ -531,6 +667,26 @@ mod tests {
         }
     }
 
+    #[test]
+    fn verify_mss_ignore_bytes_unseekable() {
+        let data = generate_random_bytes(5 * 96 * 1024);
+
+        let ms = ReadOnlySource::new(Cursor::new(data.clone()));
+        let mut mss = MediaSourceStream::new(Box::new(ms), Default::default());
+
+        assert!(!mss.is_seekable());
+
+        // Ignoring more than twice the ring buffer length would seek a seekable source.
+        mss.ignore_bytes(3 * 96 * 1024 + 7).unwrap();
+
+        assert_eq!(mss.pos(), 3 * 96 * 1024 + 7);
+        assert_eq!(mss.read_byte().unwrap(), data[3 * 96 * 1024 + 7]);
+
+        // Ignored bytes remain rewindable.
+        mss.seek_buffered_rev(1024);
+        assert_eq!(mss.read_byte().unwrap(), data[3 * 96 * 1024 + 7 - 1023]);
+    }
+
     #[test]
     fn verify_mss_read_to_end() {
         let data = generate_random_bytes(5 * 96 * 1024);
@@ -542,6 +698,59 @@ mod tests {
         assert_eq!(output.into_boxed_slice(), data);
     }
 
+    #[test]
+    fn verify_mss_seek_within_buffer() {
+        let data = generate_random_bytes(256 * 1024);
+
+        // The source is not seekable, so only buffered seeks can succeed.
+        let ms = ReadOnlySource::new(Cursor::new(data.clone()));
+        let mut mss = MediaSourceStream::new(Box::new(ms), Default::default());
+
+        mss.ignore_bytes(4096).unwrap();
+
+        assert_eq!(mss.seek(SeekFrom::Current(-100)).unwrap(), 3996);
+        assert_eq!(mss.read_byte().unwrap(), data[3996]);
+
+        assert_eq!(mss.seek(SeekFrom::Start(10)).unwrap(), 10);
+        assert_eq!(mss.read_byte().unwrap(), data[10]);
+
+        // Seeking beyond the buffer requires seeking the source.
+        assert!(mss.seek(SeekFrom::Start(128 * 1024)).is_err());
+    }
+
+    #[test]
+    fn verify_mss_read_lengths() {
+        let data = generate_random_bytes(4 * 1024 * 1024);
+
+        let custom = MediaSourceStreamOptions::default()
+            .with_buffer_len(16 * 1024)
+            .with_min_read_len(512)
+            .with_max_read_len(2 * 1024);
+
+        for options in &[
+            MediaSourceStreamOptions::low_latency(),
+            MediaSourceStreamOptions::high_throughput(),
+            custom,
+        ] {
+            let ms = Cursor::new(data.clone());
+            let mut mss = MediaSourceStream::new(Box::new(ms), *options);
+
+            // The first read is limited to the minimum read length.
+            assert_eq!(mss.read_byte().unwrap(), data[0]);
+            assert_eq!(mss.unread_buffer_len(), options.min_read_len - 1);
+
+            // Backtracking by up-to buffer_len - max_read_len bytes is always possible.
+            let seekback = options.buffer_len - options.max_read_len;
+            mss.ignore_bytes(2 * seekback as u64).unwrap();
+            mss.seek_buffered_rel(-(seekback as isize));
+            assert_eq!(mss.pos(), 1 + seekback as u64);
+
+            let mut output: Vec<u8> = Vec::new();
+            mss.read_to_end(&mut output).unwrap();
+            assert_eq!(&output[..], &data[1 + seekback..]);
+        }
+    }
+
     #[test]
     fn verify_mss_seek_buffered() {
         let data = generate_random_bytes(1024 * 1024);
@@ -608,4 +817,31 @@ mod tests {
         assert_eq!(mss.read_u32().unwrap(), 2378776723);
         assert_eq!(mss.read_u64().unwrap(), 5170196279331153683);
     }
+
+    #[test]
+    fn verify_mss_peek() {
+        let data = generate_random_bytes(256 * 1024);
+
+        let ms = ReadOnlySource::new(Cursor::new(data.clone()));
+        let mut mss = MediaSourceStream::new(Box::new(ms), Default::default());
+
+        mss.ignore_bytes(100).unwrap();
+
+        // Peek beyond the default buffer length.
+        let mut peeked = vec![0; 128 * 1024];
+        mss.peek_buf_exact(&mut peeked).unwrap();
+
+        assert_eq!(mss.pos(), 100);
+        assert_eq!(&peeked[..], &data[100..100 + peeked.len()]);
+
+        let mut read = vec![0; peeked.len()];
+        ReadBytes::read_buf_exact(&mut mss, &mut read).unwrap();
+        assert_eq!(read, peeked);
+
+        // Peeking past the end of the stream is not an error, but an exact peek is.
+        let mut tail = vec![0; 256 * 1024];
+        assert_eq!(mss.peek_buf(&mut tail).unwrap(), data.len() - mss.pos() as usize);
+        assert!(mss.peek_buf_exact(&mut tail).is_err());
+        assert_eq!(mss.pos(), 100 + read.len() as u64);
+    }
 }
@@ -18,20 +18,31 @@
 //! trait. Likewise, all `Reader`s and `Stream`s operating on bits of data at a time implement
 //! either the [`ReadBitsLtr`] or [`ReadBitsRtl`] traits depending on the order in which they
 //! consume bits.
+//!
+//! Bytes may also be written. A [`BufWriter`] writes into a `&mut [u8]`, and a [`WriteStream`]
+//! writes to any sink implementing [`std::io::Write`]. Both implement the [`WriteBytes`] trait.
+//! Bits may be written into a growable buffer with a [`BitWriterLtr`] or [`BitWriterRtl`], for
+//! example, to build bitstreams for tests.
 
 use std::io;
 use std::mem;
 
 mod bit;
+mod bit_writer;
 mod buf_reader;
+mod buf_writer;
 mod media_source_stream;
 mod monitor_stream;
+mod ring_source;
 mod scoped_stream;
 
 pub use bit::*;
+pub use bit_writer::{BitWriterLtr, BitWriterRtl};
 pub use buf_reader::BufReader;
+pub use buf_writer::{BufWriter, WriteStream};
 pub use media_source_stream::{MediaSourceStream, MediaSourceStreamOptions};
 pub use monitor_stream::{Monitor, MonitorStream};
+pub use ring_source::{RingSource, RingSourceWriter};
 pub use scoped_stream::ScopedStream;
 
 /// `MediaSource` is a composite trait of [`std::io::Read`] and [`std::io::Seek`]. A source *must*
@@ -89,6 +100,31 @@ impl<T: std::convert::AsRef<[u8]> + Send + Sync> MediaSource for io::Cursor<T> {
     }
 }
 
+impl<M: MediaSource + ?Sized> MediaSource for Box<M> {
+    /// Returns if the boxed `MediaSource` is seekable.
+    fn is_seekable(&self) -> bool {
+        (**self).is_seekable()
+    }
+
+    /// Returns the length in bytes of the boxed `MediaSource`, if available.
+    fn byte_len(&self) -> Option<u64> {
+        (**self).byte_len()
+    }
+}
+
+impl<M: MediaSource> MediaSource for io::BufReader<M> {
+    /// Returns if the `MediaSource` wrapped by the `std::io::BufReader` is seekable.
+    fn is_seekable(&self) -> bool {
+        self.get_ref().is_seekable()
+    }
+
+    /// Returns the length in bytes of the `MediaSource` wrapped by the `std::io::BufReader`, if
+    /// available.
+    fn byte_len(&self) -> Option<u64> {
+        self.get_ref().byte_len()
+    }
+}
+
 /// `ReadOnlySource` wraps any source implementing [`std::io::Read`] in an unseekable
 /// [`MediaSource`].
 pub struct ReadOnlySource<R: io::Read> {
@@ -505,3 +541,215 @@ pub trait FiniteStream {
     /// Returns the number of bytes available for reading.
     fn bytes_available(&self) -> u64;
 }
+
+/// `WriteBytes` provides methods to write bytes, and little- or big-endian unsigned integers or
+/// floating-point values of standard widths.
+pub trait WriteBytes {
+    /// Writes a single byte to the stream or returns an error.
+    fn write_byte(&mut self, byte: u8) -> io::Result<()>;
+
+    /// Writes the entire provided buffer to the stream or returns an error.
+    fn write_buf_exact(&mut self, buf: &[u8]) -> io::Result<()>;
+
+    /// Gets the position of the stream, that is the number of bytes written.
+    fn pos(&self) -> u64;
+
+    /// Writes a single unsigned byte to the stream or returns an error.
+    #[inline(always)]
+    fn write_u8(&mut self, value: u8) -> io::Result<()> {
+        self.write_byte(value)
+    }
+
+    /// Writes a single signed byte to the stream or returns an error.
+    #[inline(always)]
+    fn write_i8(&mut self, value: i8) -> io::Result<()> {
+        self.write_byte(value as u8)
+    }
+
+    /// Writes an unsigned 16-bit little-endian integer to the stream or returns an error.
+    #[inline(always)]
+    fn write_u16(&mut self, value: u16) -> io::Result<()> {
+        self.write_buf_exact(&value.to_le_bytes())
+    }
+
+    /// Writes a signed 16-bit little-endian integer to the stream or returns an error.
+    #[inline(always)]
+    fn write_i16(&mut self, value: i16) -> io::Result<()> {
+        self.write_buf_exact(&value.to_le_bytes())
+    }
+
+    /// Writes an unsigned 16-bit big-endian integer to the stream or returns an error.
+    #[inline(always)]
+    fn write_be_u16(&mut self, value: u16) -> io::Result<()> {
+        self.write_buf_exact(&value.to_be_bytes())
+    }
+
+    /// Writes a signed 16-bit big-endian integer to the stream or returns an error.
+    #[inline(always)]
+    fn write_be_i16(&mut self, value: i16) -> io::Result<()> {
+        self.write_buf_exact(&value.to_be_bytes())
+    }
+
+    /// Writes the lower 24 bits of the value as an unsigned 24-bit little-endian integer to the
+    /// stream or returns an error.
+    #[inline(always)]
+    fn write_u24(&mut self, value: u32) -> io::Result<()> {
+        self.write_buf_exact(&value.to_le_bytes()[0..3])
+    }
+
+    /// Writes the lower 24 bits of the value as a signed 24-bit little-endian integer to the
+    /// stream or returns an error.
+    #[inline(always)]
+    fn write_i24(&mut self, value: i32) -> io::Result<()> {
+        self.write_u24(value as u32)
+    }
+
+    /// Writes the lower 24 bits of the value as an unsigned 24-bit big-endian integer to the
+    /// stream or returns an error.
+    #[inline(always)]
+    fn write_be_u24(&mut self, value: u32) -> io::Result<()> {
+        self.write_buf_exact(&value.to_be_bytes()[1..4])
+    }
+
+    /// Writes the lower 24 bits of the value as a signed 24-bit big-endian integer to the stream
+    /// or returns an error.
+    #[inline(always)]
+    fn write_be_i24(&mut self, value: i32) -> io::Result<()> {
+        self.write_be_u24(value as u32)
+    }
+
+    /// Writes an unsigned 32-bit little-endian integer to the stream or returns an error.
+    #[inline(always)]
+    fn write_u32(&mut self, value: u32) -> io::Result<()> {
+        self.write_buf_exact(&value.to_le_bytes())
+    }
+
+    /// Writes a signed 32-bit little-endian integer to the stream or returns an error.
+    #[inline(always)]
+    fn write_i32(&mut self, value: i32) -> io::Result<()> {
+        self.write_buf_exact(&value.to_le_bytes())
+    }
+
+    /// Writes an unsigned 32-bit big-endian integer to the stream or returns an error.
+    #[inline(always)]
+    fn write_be_u32(&mut self, value: u32) -> io::Result<()> {
+        self.write_buf_exact(&value.to_be_bytes())
+    }
+
+    /// Writes a signed 32-bit big-endian integer to the stream or returns an error.
+    #[inline(always)]
+    fn write_be_i32(&mut self, value: i32) -> io::Result<()> {
+        self.write_buf_exact(&value.to_be_bytes())
+    }
+
+    /// Writes an unsigned 64-bit little-endian integer to the stream or returns an error.
+    #[inline(always)]
+    fn write_u64(&mut self, value: u64) -> io::Result<()> {
+        self.write_buf_exact(&value.to_le_bytes())
+    }
+
+    /// Writes a signed 64-bit little-endian integer to the stream or returns an error.
+    #[inline(always)]
+    fn write_i64(&mut self, value: i64) -> io::Result<()> {
+        self.write_buf_exact(&value.to_le_bytes())
+    }
+
+    /// Writes an unsigned 64-bit big-endian integer to the stream or returns an error.
+    #[inline(always)]
+    fn write_be_u64(&mut self, value: u64) -> io::Result<()> {
+        self.write_buf_exact(&value.to_be_bytes())
+    }
+
+    /// Writes a signed 64-bit big-endian integer to the stream or returns an error.
+    #[inline(always)]
+    fn write_be_i64(&mut self, value: i64) -> io::Result<()> {
+        self.write_buf_exact(&value.to_be_bytes())
+    }
+
+    /// Writes a 32-bit little-endian IEEE-754 floating-point value to the stream or returns an
+    /// error.
+    #[inline(always)]
+    fn write_f32(&mut self, value: f32) -> io::Result<()> {
+        self.write_buf_exact(&value.to_le_bytes())
+    }
+
+    /// Writes a 32-bit big-endian IEEE-754 floating-point value to the stream or returns an
+    /// error.
+    #[inline(always)]
+    fn write_be_f32(&mut self, value: f32) -> io::Result<()> {
+        self.write_buf_exact(&value.to_be_bytes())
+    }
+
+    /// Writes a 64-bit little-endian IEEE-754 floating-point value to the stream or returns an
+    /// error.
+    #[inline(always)]
+    fn write_f64(&mut self, value: f64) -> io::Result<()> {
+        self.write_buf_exact(&value.to_le_bytes())
+    }
+
+    /// Writes a 64-bit big-endian IEEE-754 floating-point value to the stream or returns an
+    /// error.
+    #[inline(always)]
+    fn write_be_f64(&mut self, value: f64) -> io::Result<()> {
+        self.write_buf_exact(&value.to_be_bytes())
+    }
+
+    /// Writes the specified number of zero bytes to the stream or returns an error. This is
+    /// commonly used to write padding.
+    fn write_zeros(&mut self, count: u64) -> io::Result<()> {
+        let zeros = [0u8; 256];
+
+        let mut remaining = count;
+
+        while remaining > 0 {
+            let len = remaining.min(zeros.len() as u64) as usize;
+            self.write_buf_exact(&zeros[..len])?;
+            remaining -= len as u64;
+        }
+
+        Ok(())
+    }
+}
+
+impl<W: WriteBytes> WriteBytes for &mut W {
+    #[inline(always)]
+    fn write_byte(&mut self, byte: u8) -> io::Result<()> {
+        (*self).write_byte(byte)
+    }
+
+    #[inline(always)]
+    fn write_buf_exact(&mut self, buf: &[u8]) -> io::Result<()> {
+        (*self).write_buf_exact(buf)
+    }
+
+    #[inline(always)]
+    fn pos(&self) -> u64 {
+        (**self).pos()
+    }
+}
+
+impl<F: FiniteWriteStream> FiniteWriteStream for &mut F {
+    fn byte_len(&self) -> u64 {
+        (**self).byte_len()
+    }
+
+    fn bytes_written(&self) -> u64 {
+        (**self).bytes_written()
+    }
+
+    fn bytes_available(&self) -> u64 {
+        (**self).bytes_available()
+    }
+}
+
+/// A `FiniteWriteStream` is a stream that has a known capacity in bytes.
+pub trait FiniteWriteStream {
+    /// Returns the capacity of the stream in bytes.
+    fn byte_len(&self) -> u64;
+
+    /// Returns the number of bytes that have been written.
+    fn bytes_written(&self) -> u64;
+
+    /// Returns the number of bytes that may still be written.
+    fn bytes_available(&self) -> u64;
+}
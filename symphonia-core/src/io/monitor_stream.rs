@@ -5,6 +5,7 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+use std::cmp::min;
 use std::io;
 
 use super::ReadBytes;
@@ -123,8 +124,21 @@ impl<B: ReadBytes, M: Monitor> ReadBytes for MonitorStream<B, M> {
         Ok(result)
     }
 
-    fn ignore_bytes(&mut self, count: u64) -> io::Result<()> {
-        self.inner.ignore_bytes(count)
+    fn ignore_bytes(&mut self, mut count: u64) -> io::Result<()> {
+        // Ignored bytes must still be observed by the monitor. For example, a checksum must include
+        // all bytes regardless of whether they were read or ignored.
+        let mut buf = [0u8; 1024];
+
+        while count > 0 {
+            let len = min(count, buf.len() as u64) as usize;
+
+            self.inner.read_buf_exact(&mut buf[..len])?;
+            self.monitor.process_buf_bytes(&buf[..len]);
+
+            count -= len as u64;
+        }
+
+        Ok(())
     }
 
     #[inline(always)]
@@ -132,3 +146,28 @@ impl<B: ReadBytes, M: Monitor> ReadBytes for MonitorStream<B, M> {
         self.inner.pos()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::MonitorStream;
+    use crate::checksum::Crc32;
+    use crate::io::{BufReader, Monitor, ReadBytes};
+
+    #[test]
+    fn verify_monitor_stream_ignore_bytes() {
+        let data: Vec<u8> = (0..4096u32).map(|i| (i * 7 + 3) as u8).collect();
+
+        let mut expected = Crc32::new(0);
+        expected.process_buf_bytes(&data);
+
+        let mut stream = MonitorStream::new(BufReader::new(&data), Crc32::new(0));
+
+        stream.read_quad_bytes().unwrap();
+        stream.ignore_bytes(2048).unwrap();
+        stream.read_byte().unwrap();
+        stream.ignore_bytes(2043).unwrap();
+
+        assert_eq!(stream.monitor().crc(), expected.crc());
+        assert_eq!(stream.pos(), 4096);
+    }
+}
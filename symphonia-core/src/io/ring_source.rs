@@ -0,0 +1,300 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::collections::VecDeque;
+use std::io;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use super::MediaSource;
+
+struct RingState {
+    buf: VecDeque<u8>,
+    capacity: usize,
+    /// The writer was closed or dropped. No more bytes will be written.
+    is_writer_closed: bool,
+    /// The reader was dropped. Written bytes would never be read.
+    is_reader_dropped: bool,
+}
+
+struct Ring {
+    state: Mutex<RingState>,
+    /// Signalled when bytes are written, or the writer is closed.
+    readable: Condvar,
+    /// Signalled when bytes are read, or the reader is dropped.
+    writable: Condvar,
+}
+
+impl Ring {
+    fn lock(&self) -> MutexGuard<'_, RingState> {
+        // The state is always consistent, so a poisoned lock can be safely recovered.
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+/// Waits on a condition variable until the deadline, if any. Returns the guard, and true if the
+/// deadline passed.
+fn wait<'a>(
+    cvar: &Condvar,
+    guard: MutexGuard<'a, RingState>,
+    deadline: Option<Instant>,
+) -> (MutexGuard<'a, RingState>, bool) {
+    match deadline {
+        Some(deadline) => {
+            let now = Instant::now();
+
+            if now >= deadline {
+                return (guard, true);
+            }
+
+            let (guard, _) =
+                cvar.wait_timeout(guard, deadline - now).unwrap_or_else(|err| err.into_inner());
+
+            (guard, false)
+        }
+        None => (cvar.wait(guard).unwrap_or_else(|err| err.into_inner()), false),
+    }
+}
+
+/// `RingSource` is an unseekable [`MediaSource`] that reads bytes pushed by another thread into a
+/// fixed-capacity ring buffer, such as the output of a capture API or a network socket.
+///
+/// Reads block until bytes are available, the read timeout passes, or the [`RingSourceWriter`] is
+/// closed. Once the writer is closed, and all bytes were read, reads return 0 to signal the end of
+/// the stream. If the read timeout passes, a `TimedOut` IO error is returned.
+pub struct RingSource {
+    ring: Arc<Ring>,
+    timeout: Option<Duration>,
+}
+
+/// `RingSourceWriter` pushes bytes into the ring buffer of a [`RingSource`].
+///
+/// Dropping the writer closes it.
+pub struct RingSourceWriter {
+    ring: Arc<Ring>,
+    timeout: Option<Duration>,
+}
+
+impl RingSource {
+    /// Instantiate a new `RingSource` with a ring buffer of the provided capacity in bytes, and
+    /// its writer.
+    pub fn new(capacity: usize) -> (RingSourceWriter, RingSource) {
+        assert!(capacity > 0, "capacity must be greater than 0");
+
+        let ring = Arc::new(Ring {
+            state: Mutex::new(RingState {
+                buf: VecDeque::with_capacity(capacity),
+                capacity,
+                is_writer_closed: false,
+                is_reader_dropped: false,
+            }),
+            readable: Condvar::new(),
+            writable: Condvar::new(),
+        });
+
+        (RingSourceWriter { ring: ring.clone(), timeout: None }, RingSource { ring, timeout: None })
+    }
+
+    /// Sets the maximum duration a read will block waiting for bytes. If `None`, reads block
+    /// indefinitely. Default: `None`.
+    pub fn set_read_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    /// Gets the number of bytes buffered and not yet read.
+    pub fn buffered(&self) -> usize {
+        self.ring.lock().buf.len()
+    }
+}
+
+impl io::Read for RingSource {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+
+        let mut state = self.ring.lock();
+
+        while state.buf.is_empty() {
+            if state.is_writer_closed {
+                return Ok(0);
+            }
+
+            let (guard, is_timed_out) = wait(&self.ring.readable, state, deadline);
+            state = guard;
+
+            if is_timed_out && state.buf.is_empty() && !state.is_writer_closed {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "ring source read timed out"));
+            }
+        }
+
+        let len = buf.len().min(state.buf.len());
+
+        for (dst, src) in buf.iter_mut().zip(state.buf.drain(..len)) {
+            *dst = src;
+        }
+
+        self.ring.writable.notify_one();
+
+        Ok(len)
+    }
+}
+
+impl io::Seek for RingSource {
+    fn seek(&mut self, _: io::SeekFrom) -> io::Result<u64> {
+        Err(io::Error::new(io::ErrorKind::Other, "source does not support seeking"))
+    }
+}
+
+impl MediaSource for RingSource {
+    fn is_seekable(&self) -> bool {
+        false
+    }
+
+    fn byte_len(&self) -> Option<u64> {
+        None
+    }
+}
+
+impl Drop for RingSource {
+    fn drop(&mut self) {
+        self.ring.lock().is_reader_dropped = true;
+        self.ring.writable.notify_all();
+    }
+}
+
+impl RingSourceWriter {
+    /// Sets the maximum duration a write will block waiting for free space. If `None`, writes
+    /// block indefinitely. Default: `None`.
+    pub fn set_write_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    /// Pushes as many bytes as there is free space for without blocking, and returns the number
+    /// of bytes pushed.
+    pub fn try_write(&self, buf: &[u8]) -> usize {
+        let mut state = self.ring.lock();
+
+        let len = buf.len().min(state.capacity - state.buf.len());
+        state.buf.extend(&buf[..len]);
+
+        if len > 0 {
+            self.ring.readable.notify_one();
+        }
+
+        len
+    }
+
+    /// Closes the writer. The reader will read all remaining bytes, and then reach the end of the
+    /// stream.
+    pub fn close(&self) {
+        self.ring.lock().is_writer_closed = true;
+        self.ring.readable.notify_all();
+    }
+}
+
+impl io::Write for RingSourceWriter {
+    /// Pushes bytes, blocking until there is free space, the write timeout passes, or the reader
+    /// is dropped.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+
+        let mut state = self.ring.lock();
+
+        loop {
+            if state.is_reader_dropped {
+                return Err(io::Error::new(io::ErrorKind::BrokenPipe, "ring source was dropped"));
+            }
+
+            if state.is_writer_closed {
+                return Err(io::Error::new(io::ErrorKind::Other, "ring source writer is closed"));
+            }
+
+            if state.buf.len() < state.capacity {
+                break;
+            }
+
+            let (guard, is_timed_out) = wait(&self.ring.writable, state, deadline);
+            state = guard;
+
+            if is_timed_out && state.buf.len() >= state.capacity {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "ring source write timed out"));
+            }
+        }
+
+        let len = buf.len().min(state.capacity - state.buf.len());
+        state.buf.extend(&buf[..len]);
+
+        self.ring.readable.notify_one();
+
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for RingSourceWriter {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{ErrorKind, Read, Write};
+    use std::time::Duration;
+
+    use super::RingSource;
+
+    #[test]
+    fn verify_ring_source() {
+        let (mut writer, mut source) = RingSource::new(100);
+
+        let data: Vec<u8> = (0..10_000).map(|i| (i % 251) as u8).collect();
+        let expected = data.clone();
+
+        let thread = std::thread::spawn(move || {
+            for chunk in data.chunks(37) {
+                writer.write_all(chunk).unwrap();
+            }
+        });
+
+        let mut read = Vec::new();
+        source.read_to_end(&mut read).unwrap();
+        thread.join().unwrap();
+
+        assert_eq!(read, expected);
+    }
+
+    #[test]
+    fn verify_ring_source_timeouts() {
+        let (mut writer, mut source) = RingSource::new(4);
+
+        source.set_read_timeout(Some(Duration::from_millis(10)));
+        writer.set_write_timeout(Some(Duration::from_millis(10)));
+
+        let mut buf = [0; 8];
+        assert_eq!(source.read(&mut buf).unwrap_err().kind(), ErrorKind::TimedOut);
+
+        assert_eq!(writer.try_write(b"abcdef"), 4);
+        assert_eq!(writer.write(b"ef").unwrap_err().kind(), ErrorKind::TimedOut);
+
+        assert_eq!(source.read(&mut buf).unwrap(), 4);
+        assert_eq!(&buf[..4], b"abcd");
+
+        drop(writer);
+        assert_eq!(source.read(&mut buf).unwrap(), 0);
+    }
+}
@@ -40,15 +40,39 @@ impl<B: ReadBytes> ScopedStream<B> {
         &mut self.inner
     }
 
-    /// Ignores the remainder of the `ScopedStream`.
+    /// Ignores the remainder of the `ScopedStream`. Afterwards, the inner stream is positioned at
+    /// the end of the scope, and calling this function again has no effect.
     pub fn ignore(&mut self) -> io::Result<()> {
-        self.inner.ignore_bytes(self.len - self.read)
+        let remaining = self.len - self.read;
+        self.read = self.len;
+        self.inner.ignore_bytes(remaining)
     }
 
-    /// Convert the `ScopedStream` to the inner stream.
+    /// Instantiates a new `ScopedStream`, nested within this `ScopedStream`, with an upper limit
+    /// on the number of bytes that can be read. Bytes read from the nested scope are accounted
+    /// for by this scope.
+    ///
+    /// Returns an error if the nested scope would extend past the end of this scope.
+    pub fn scope(&mut self, len: u64) -> io::Result<ScopedStream<&mut Self>> {
+        if len > self.len - self.read {
+            return out_of_bounds_error();
+        }
+
+        Ok(ScopedStream::new(self, len))
+    }
+
+    /// Convert the `ScopedStream` to the inner stream. The inner stream is positioned after the
+    /// last byte read from the `ScopedStream`.
     pub fn into_inner(self) -> B {
         self.inner
     }
+
+    /// Ignores the remainder of the `ScopedStream`, and converts it to the inner stream. The inner
+    /// stream is positioned at the end of the scope.
+    pub fn finish(mut self) -> io::Result<B> {
+        self.ignore()?;
+        Ok(self.inner)
+    }
 }
 
 impl<B: ReadBytes> FiniteStream for ScopedStream<B> {
@@ -177,7 +201,9 @@ impl<B: ReadBytes + SeekBuffered> SeekBuffered for ScopedStream<B> {
     #[inline(always)]
     fn seek_buffered(&mut self, pos: u64) -> u64 {
         // Clamp the seekable position to within the bounds of the ScopedStream.
-        self.inner.seek_buffered(pos.clamp(self.start, self.start + self.len))
+        let pos = self.inner.seek_buffered(pos.clamp(self.start, self.start + self.len));
+        self.read = pos - self.start;
+        pos
     }
 
     #[inline(always)]
@@ -186,6 +212,47 @@ impl<B: ReadBytes + SeekBuffered> SeekBuffered for ScopedStream<B> {
         // within the bounds of the ScopedStream.
         let max_back = self.read.min(std::isize::MAX as u64) as isize;
         let max_forward = (self.len - self.read).min(std::isize::MAX as u64) as isize;
-        self.inner.seek_buffered_rel(delta.clamp(-max_back, max_forward))
+        let pos = self.inner.seek_buffered_rel(delta.clamp(-max_back, max_forward));
+        self.read = pos - self.start;
+        pos
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::ScopedStream;
+    use crate::io::{FiniteStream, MediaSourceStream, ReadBytes, SeekBuffered};
+
+    #[test]
+    fn verify_scoped_stream_nesting() {
+        let data: Vec<u8> = (0..16).collect();
+
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(data)), Default::default());
+
+        let mut outer = ScopedStream::new(mss, 12);
+        assert_eq!(outer.read_byte().unwrap(), 0);
+
+        // A nested scope may not extend past the end of the outer scope.
+        assert!(outer.scope(12).is_err());
+
+        let mut inner = outer.scope(4).unwrap();
+        assert_eq!(inner.read_double_bytes().unwrap(), [1, 2]);
+        assert!(inner.read_quad_bytes().is_err());
+        inner.ignore().unwrap();
+        inner.ignore().unwrap();
+
+        assert_eq!(outer.bytes_read(), 5);
+        assert_eq!(outer.read_byte().unwrap(), 5);
+
+        // Buffered seeks are accounted for.
+        outer.seek_buffered_rel(-2);
+        assert_eq!(outer.bytes_available(), 8);
+        assert_eq!(outer.read_byte().unwrap(), 4);
+
+        let mut reader = outer.finish().unwrap();
+        assert_eq!(reader.pos(), 12);
+        assert_eq!(reader.read_byte().unwrap(), 12);
     }
 }
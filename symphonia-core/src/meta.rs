@@ -61,6 +61,33 @@ pub struct MetadataOptions {
 
     /// The maximum size limit in bytes that a visual (picture) may occupy.
     pub limit_visual_bytes: Limit,
+
+    /// A processor invoked for each visual (picture) as it is read, before it is stored in a
+    /// metadata revision. This may be used to downscale, re-encode, or discard visuals such that
+    /// original images are not retained in memory.
+    pub visual_processor: Option<&'static dyn VisualProcessor>,
+}
+
+/// A `VisualProcessor` transforms `Visual`s as they are read.
+///
+/// Symphonia does not decode images. Applications may implement this trait using their own image
+/// library to, for example, replace large pictures with thumbnails. This trait is implemented for
+/// all functions and closures of the form `Fn(Visual) -> Option<Visual>`.
+pub trait VisualProcessor: Send + Sync {
+    /// Processes the provided `Visual`. Returns the processed `Visual`, or `None` to discard it.
+    fn process(&self, visual: Visual) -> Option<Visual>;
+}
+
+impl<F: Fn(Visual) -> Option<Visual> + Send + Sync> VisualProcessor for F {
+    fn process(&self, visual: Visual) -> Option<Visual> {
+        self(visual)
+    }
+}
+
+impl fmt::Debug for dyn VisualProcessor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("VisualProcessor")
+    }
 }
 
 /// `StandardVisualKey` is an enumeration providing standardized keys for common visual dispositions.
@@ -70,6 +97,11 @@ pub struct MetadataOptions {
 /// The visual types listed here are derived from, though do not entirely cover, the ID3v2 APIC
 /// frame specification.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_crate::Serialize, serde_crate::Deserialize),
+    serde(crate = "serde_crate")
+)]
 pub enum StandardVisualKey {
     FileIcon,
     OtherIcon,
@@ -96,6 +128,11 @@ pub enum StandardVisualKey {
 /// A tag reader may assign a `StandardTagKey` to a `Tag` if the tag's key is generally
 /// accepted to map to a specific usage.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_crate::Serialize, serde_crate::Deserialize),
+    serde(crate = "serde_crate")
+)]
 pub enum StandardTagKey {
     AcoustidFingerprint,
     AcoustidId,
@@ -216,6 +253,11 @@ pub enum StandardTagKey {
 /// format, the actual data type a specific tag may have a lesser width or encoding than the data
 /// type in this enumeration.
 #[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_crate::Serialize, serde_crate::Deserialize),
+    serde(crate = "serde_crate")
+)]
 pub enum Value {
     /// A binary buffer.
     Binary(Box<[u8]>),
@@ -291,6 +333,11 @@ impl fmt::Display for Value {
 
 /// A `Tag` encapsulates a key-value pair of metadata.
 #[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_crate::Serialize, serde_crate::Deserialize),
+    serde(crate = "serde_crate")
+)]
 pub struct Tag {
     /// If the `Tag`'s key string is commonly associated with a typical type, meaning, or purpose,
     /// then if recognized a `StandardTagKey` will be assigned to this `Tag`.
@@ -333,6 +380,11 @@ impl fmt::Display for Tag {
 
 /// A 2 dimensional (width and height) size type.
 #[derive(Copy, Clone, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_crate::Serialize, serde_crate::Deserialize),
+    serde(crate = "serde_crate")
+)]
 pub struct Size {
     /// The width in pixels.
     pub width: u32,
@@ -342,6 +394,11 @@ pub struct Size {
 
 /// `ColorMode` indicates how the color of a pixel is encoded in a `Visual`.
 #[derive(Copy, Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_crate::Serialize, serde_crate::Deserialize),
+    serde(crate = "serde_crate")
+)]
 pub enum ColorMode {
     /// Each pixel in the `Visual` stores its own color information.
     Discrete,
@@ -353,6 +410,11 @@ pub enum ColorMode {
 
 /// A `Visual` is any 2 dimensional graphic.
 #[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_crate::Serialize, serde_crate::Deserialize),
+    serde(crate = "serde_crate")
+)]
 pub struct Visual {
     /// The Media Type (MIME Type) used to encode the `Visual`.
     pub media_type: String,
@@ -381,6 +443,11 @@ pub struct Visual {
 
 /// `VendorData` is any binary metadata that is proprietary to a certain application or vendor.
 #[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_crate::Serialize, serde_crate::Deserialize),
+    serde(crate = "serde_crate")
+)]
 pub struct VendorData {
     /// A text representation of the vendor's application identifier.
     pub ident: String,
@@ -390,6 +457,11 @@ pub struct VendorData {
 
 /// `Metadata` is a container for a single discrete revision of metadata information.
 #[derive(Clone, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_crate::Serialize, serde_crate::Deserialize),
+    serde(crate = "serde_crate")
+)]
 pub struct MetadataRevision {
     tags: Vec<Tag>,
     visuals: Vec<Visual>,
@@ -411,6 +483,13 @@ impl MetadataRevision {
     pub fn vendor_data(&self) -> &[VendorData] {
         &self.vendor_data
     }
+
+    /// Replaces each `Visual` in this revision with the result of the provided `VisualProcessor`.
+    /// Each original `Visual` is dropped as soon as it is processed.
+    pub fn process_visuals(&mut self, processor: &dyn VisualProcessor) {
+        let visuals = std::mem::take(&mut self.visuals);
+        self.visuals = visuals.into_iter().filter_map(|visual| processor.process(visual)).collect();
+    }
 }
 
 /// `MetadataBuilder` is the builder for `Metadata` revisions.
@@ -488,6 +567,14 @@ impl<'a> Metadata<'a> {
             None
         }
     }
+
+    /// Processes the `Visual`s of all revisions with the provided `VisualProcessor`. See
+    /// [`MetadataRevision::process_visuals`].
+    pub fn process_visuals(&mut self, processor: &dyn VisualProcessor) {
+        for rev in self.revisions.iter_mut() {
+            rev.process_visuals(processor);
+        }
+    }
 }
 
 /// `MetadataLog` is a container for time-ordered `Metadata` revisions.
@@ -517,3 +604,53 @@ pub trait MetadataReader: Send + Sync {
     /// Read all metadata and return it if successful.
     fn read_all(&mut self, reader: &mut MediaSourceStream) -> Result<MetadataRevision>;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{MetadataBuilder, MetadataLog, MetadataOptions, StandardVisualKey, Visual};
+
+    fn visual(usage: StandardVisualKey, len: usize) -> Visual {
+        Visual {
+            media_type: "image/png".to_string(),
+            dimensions: None,
+            bits_per_pixel: None,
+            color_mode: None,
+            usage: Some(usage),
+            tags: Vec::new(),
+            data: vec![0; len].into_boxed_slice(),
+        }
+    }
+
+    #[test]
+    fn verify_process_visuals() {
+        // Keep only front covers, and replace them with a "thumbnail".
+        let options = MetadataOptions {
+            visual_processor: Some(&|visual: Visual| match visual.usage {
+                Some(StandardVisualKey::FrontCover) => {
+                    Some(Visual { data: vec![1; 16].into_boxed_slice(), ..visual })
+                }
+                _ => None,
+            }),
+            ..Default::default()
+        };
+
+        let mut log = MetadataLog::default();
+
+        for _ in 0..2 {
+            let mut builder = MetadataBuilder::new();
+            builder.add_visual(visual(StandardVisualKey::FrontCover, 4096));
+            builder.add_visual(visual(StandardVisualKey::BackCover, 4096));
+            log.push(builder.metadata());
+        }
+
+        let mut metadata = log.metadata();
+        metadata.process_visuals(options.visual_processor.unwrap());
+
+        for _ in 0..2 {
+            let visuals = metadata.current().unwrap().visuals();
+            assert_eq!(visuals.len(), 1);
+            assert_eq!(visuals[0].data.len(), 16);
+            metadata.pop();
+        }
+    }
+}
@@ -8,6 +8,8 @@
 //! The `probe` module provides methods and traits to support auto-detection of media formats from
 //! arbitrary media streams.
 
+use std::time::{Duration, Instant};
+
 use crate::errors::{unsupported_error, Result};
 use crate::formats::{FormatOptions, FormatReader};
 use crate::io::{MediaSourceStream, ReadBytes, SeekBuffered};
@@ -189,6 +191,46 @@ pub struct ProbeResult {
     pub metadata: ProbedMetadata,
 }
 
+/// `ProbeOptions` bounds the amount of work a `Probe` may perform before giving up.
+///
+/// When probing untrusted media, these limits prevent a crafted media source from stalling the
+/// probe. If a limit is reached, the probe fails with an unsupported error.
+#[derive(Copy, Clone, Debug)]
+pub struct ProbeOptions {
+    /// The maximum number of bytes to scan for a format marker, in total, over the probe. Bytes
+    /// consumed by metadata readers are not counted. Default: 1 MiB.
+    pub search_limit: u64,
+    /// The maximum amount of wall-clock time the search for a format marker may take, in total,
+    /// over the probe. The time taken by metadata readers, and to instantiate the format reader,
+    /// is not bounded. Default: `None`.
+    pub time_limit: Option<Duration>,
+}
+
+impl Default for ProbeOptions {
+    fn default() -> Self {
+        ProbeOptions { search_limit: 1024 * 1024, time_limit: None }
+    }
+}
+
+/// The state of a search bounded by `ProbeOptions`.
+struct SearchBudget {
+    bytes_left: u64,
+    deadline: Option<Instant>,
+}
+
+impl SearchBudget {
+    fn new(options: &ProbeOptions) -> Self {
+        // Only query the clock if required since it is not available on all platforms.
+        let deadline = options.time_limit.map(|limit| Instant::now() + limit);
+
+        SearchBudget { bytes_left: options.search_limit, deadline }
+    }
+
+    fn is_expired(&self) -> bool {
+        self.deadline.map_or(false, |deadline| Instant::now() >= deadline)
+    }
+}
+
 /// `Probe` scans a `MediaSourceStream` for metadata and container formats, and provides an
 /// iterator-like interface to instantiate readers for the formats encountered.
 #[derive(Default)]
@@ -198,8 +240,6 @@ pub struct Probe {
 }
 
 impl Probe {
-    const PROBE_SEARCH_LIMIT: u64 = 1 * 1024 * 1024;
-
     /// Register all `Descriptor`s supported by the parameterized type.
     pub fn register_all<Q: QueryDescriptor>(&mut self) {
         for descriptor in Q::query() {
@@ -224,26 +264,54 @@ impl Probe {
         self.registered.push(*descriptor);
     }
 
-    /// Searches the provided `MediaSourceStream` for metadata or a container format.
+    /// Searches the provided `MediaSourceStream` for metadata or a container format using the
+    /// default `ProbeOptions`.
     pub fn next(&self, mss: &mut MediaSourceStream) -> Result<Instantiate> {
+        self.search(mss, &mut SearchBudget::new(&Default::default()))
+    }
+
+    /// Searches the provided `MediaSourceStream` for metadata or a container format within the
+    /// limits of the provided `ProbeOptions`.
+    pub fn next_with_options(
+        &self,
+        mss: &mut MediaSourceStream,
+        options: &ProbeOptions,
+    ) -> Result<Instantiate> {
+        self.search(mss, &mut SearchBudget::new(options))
+    }
+
+    fn search(
+        &self,
+        mss: &mut MediaSourceStream,
+        budget: &mut SearchBudget,
+    ) -> Result<Instantiate> {
         let mut win = 0u16;
 
         let init_pos = mss.pos();
         let mut count = 0;
 
         // Scan the stream byte-by-byte. Shifting each byte through a 2-byte window.
-        while let Ok(byte) = mss.read_byte() {
+        while budget.bytes_left > 0 {
+            let byte = match mss.read_byte() {
+                Ok(byte) => byte,
+                Err(_) => break,
+            };
+
             win = (win << 8) | u16::from(byte);
 
             count += 1;
+            budget.bytes_left -= 1;
 
             if count % 4096 == 0 {
                 debug!(
-                    "searching for format marker... {}+{} / {} bytes.",
-                    init_pos,
-                    count,
-                    Probe::PROBE_SEARCH_LIMIT
+                    "searching for format marker... {}+{} bytes, {} bytes left.",
+                    init_pos, count, budget.bytes_left
                 );
+
+                if budget.is_expired() {
+                    error!("reached probe time limit @ {}+{} bytes.", init_pos, count);
+                    return unsupported_error("core (probe): probe time limit reached");
+                }
             }
 
             // Use the bloom filter to check if the the window may be a prefix of a registered
@@ -291,8 +359,10 @@ impl Probe {
             }
         }
 
-        // Could not find any marker within the probe limit.
-        error!("reached probe limit of {} bytes.", Probe::PROBE_SEARCH_LIMIT);
+        if budget.bytes_left == 0 {
+            // Could not find any marker within the probe limit.
+            error!("reached probe search limit @ {}+{} bytes.", init_pos, count);
+        }
 
         unsupported_error("core (probe): no suitable format reader found")
     }
@@ -301,20 +371,42 @@ impl Probe {
     /// during the search will be queued and attached to the `FormatReader` instance once a
     /// container format is found.
     pub fn format(
+        &self,
+        hint: &Hint,
+        mss: MediaSourceStream,
+        format_opts: &FormatOptions,
+        metadata_opts: &MetadataOptions,
+    ) -> Result<ProbeResult> {
+        self.format_with_options(hint, mss, format_opts, metadata_opts, &Default::default())
+    }
+
+    /// Searches the provided `MediaSourceStream` for a container format within the limits of the
+    /// provided `ProbeOptions`. Otherwise, the same as [`Probe::format`].
+    pub fn format_with_options(
         &self,
         _hint: &Hint,
         mut mss: MediaSourceStream,
         format_opts: &FormatOptions,
         metadata_opts: &MetadataOptions,
+        probe_opts: &ProbeOptions,
     ) -> Result<ProbeResult> {
         let mut metadata: MetadataLog = Default::default();
 
+        // The limits apply to the probe as a whole, not to each individual search.
+        let mut budget = SearchBudget::new(probe_opts);
+
         // Loop over all elements in the stream until a container format is found.
         loop {
-            match self.next(&mut mss)? {
+            match self.search(&mut mss, &mut budget)? {
                 // If a container format is found, return an instance to it's reader.
                 Instantiate::Format(fmt) => {
-                    let format = fmt(mss, format_opts)?;
+                    let mut format = fmt(mss, format_opts)?;
+
+                    // Process the visuals read by the format reader while it was instantiated.
+                    // Visuals read afterwards must be processed by the consumer.
+                    if let Some(processor) = metadata_opts.visual_processor {
+                        format.metadata().process_visuals(processor);
+                    }
 
                     let metadata =
                         if metadata.metadata().current().is_some() { Some(metadata) } else { None };
@@ -325,7 +417,13 @@ impl Probe {
                 // push it onto the metadata log.
                 Instantiate::Metadata(meta) => {
                     let mut reader = meta(metadata_opts);
-                    metadata.push(reader.read_all(&mut mss)?);
+                    let mut rev = reader.read_all(&mut mss)?;
+
+                    if let Some(processor) = metadata_opts.visual_processor {
+                        rev.process_visuals(processor);
+                    }
+
+                    metadata.push(rev);
 
                     debug!("chaining a metadata element.");
                 }
@@ -368,3 +466,42 @@ macro_rules! support_metadata {
         }
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::{Descriptor, Instantiate, Probe, ProbeOptions};
+    use crate::errors::unsupported_error;
+    use crate::io::MediaSourceStream;
+
+    #[test]
+    fn verify_probe_search_limit() {
+        let mut probe = Probe::default();
+
+        probe.register(&Descriptor {
+            short_name: "test",
+            long_name: "Test",
+            extensions: &[],
+            mime_types: &[],
+            markers: &[b"MARK"],
+            score: |_| 255,
+            inst: Instantiate::Format(|_, _| unsupported_error("test")),
+        });
+
+        let mut data = vec![0; 8192];
+        data.extend_from_slice(b"MARK");
+        data.extend_from_slice(&[0; 16]);
+
+        let new_mss =
+            || MediaSourceStream::new(Box::new(Cursor::new(data.clone())), Default::default());
+
+        assert!(probe.next(&mut new_mss()).is_ok());
+
+        let options = ProbeOptions { search_limit: 4096, ..Default::default() };
+        assert!(probe.next_with_options(&mut new_mss(), &options).is_err());
+
+        let options = ProbeOptions { search_limit: 8194, ..Default::default() };
+        assert!(probe.next_with_options(&mut new_mss(), &options).is_ok());
+    }
+}
@@ -12,7 +12,7 @@ use std::fmt;
 use crate::util::clamp::{clamp_f32, clamp_f64, clamp_i24, clamp_u24};
 
 /// SampleFormat describes the data encoding for an audio sample.
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum SampleFormat {
     /// Unsigned 8-bit integer.
     U8,
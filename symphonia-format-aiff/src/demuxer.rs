@@ -0,0 +1,552 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::io::{Seek, SeekFrom};
+
+use symphonia_core::audio::Channels;
+use symphonia_core::codecs::*;
+use symphonia_core::errors::{decode_error, end_of_stream_error, seek_error, unsupported_error};
+use symphonia_core::errors::{Result, SeekErrorKind};
+use symphonia_core::formats::prelude::*;
+use symphonia_core::io::*;
+use symphonia_core::meta::{Metadata, MetadataBuilder, MetadataLog, StandardTagKey, Tag, Value};
+use symphonia_core::probe::{Descriptor, Instantiate, QueryDescriptor};
+use symphonia_core::support_format;
+
+use symphonia_metadata::id3v2;
+
+use log::{debug, warn};
+
+/// The marker that starts every AIFF file.
+const AIFF_STREAM_MARKER: [u8; 4] = *b"FORM";
+
+/// The form type of an AIFF file.
+const AIFF_FORM_TYPE: [u8; 4] = *b"AIFF";
+
+/// The form type of an AIFF-C file.
+const AIFC_FORM_TYPE: [u8; 4] = *b"AIFC";
+
+/// The maximum length of a text chunk that will be read.
+const AIFF_MAX_TEXT_LEN: u32 = 64 * 1024;
+
+/// The maximum number of frames that will be in a packet.
+const AIFF_MAX_FRAMES_PER_PACKET: u64 = 1152;
+
+/// The common chunk.
+struct CommonChunk {
+    n_channels: u16,
+    n_frames: u32,
+    sample_size: u16,
+    sample_rate: u32,
+    /// The compression type. Always `NONE` for AIFF.
+    compression: [u8; 4],
+}
+
+/// Converts an 80-bit IEEE 754 extended precision number into an integer sample rate.
+fn sample_rate_from_extended(buf: &[u8; 10]) -> Result<u32> {
+    let sign_exp = u16::from_be_bytes([buf[0], buf[1]]);
+    let mantissa =
+        u64::from_be_bytes([buf[2], buf[3], buf[4], buf[5], buf[6], buf[7], buf[8], buf[9]]);
+
+    // The mantissa has an explicit integer bit. Get the number of fractional bits to shift out.
+    let shift = 16383 + 63 - i32::from(sign_exp & 0x7fff);
+
+    // The sample rate must be positive, and within the range of a 32-bit integer.
+    if sign_exp & 0x8000 != 0 || shift < 32 || shift > 63 {
+        return decode_error("aiff: invalid sample rate");
+    }
+
+    // Round to the nearest integer sample rate.
+    let rate = ((mantissa >> (shift - 1)) + 1) >> 1;
+
+    match rate {
+        rate if rate > 0 && rate <= u64::from(u32::MAX) => Ok(rate as u32),
+        _ => decode_error("aiff: invalid sample rate"),
+    }
+}
+
+/// Read the common chunk.
+fn read_comm_chunk<B: ReadBytes>(reader: &mut B, len: u32, is_aifc: bool) -> Result<CommonChunk> {
+    let min_len = if is_aifc { 22 } else { 18 };
+
+    if len < min_len {
+        return decode_error("aiff: invalid comm chunk length");
+    }
+
+    let n_channels = reader.read_be_u16()?;
+    let n_frames = reader.read_be_u32()?;
+    let sample_size = reader.read_be_u16()?;
+
+    let mut rate = [0; 10];
+    reader.read_buf_exact(&mut rate)?;
+
+    let sample_rate = sample_rate_from_extended(&rate)?;
+
+    // AIFF-C adds the compression type, followed by a human-readable compression name.
+    let compression = if is_aifc { reader.read_quad_bytes()? } else { *b"NONE" };
+
+    reader.ignore_bytes(u64::from(len - min_len))?;
+
+    Ok(CommonChunk { n_channels, n_frames, sample_size, sample_rate, compression })
+}
+
+/// Gets the codec, and number of bits per sample in the audio data, for a common chunk.
+fn codec_from_comm(comm: &CommonChunk) -> Result<(CodecType, u32)> {
+    // Uncompressed samples are stored in the smallest number of whole bytes that can contain the
+    // sample size.
+    let width = (u32::from(comm.sample_size) + 7) & !7;
+
+    let codec = match (&comm.compression, width) {
+        (b"NONE", 8) | (b"twos", 8) | (b"sowt", 8) => (CODEC_TYPE_PCM_S8, 8),
+        (b"NONE", 16) | (b"twos", 16) => (CODEC_TYPE_PCM_S16BE, 16),
+        (b"NONE", 24) | (b"twos", 24) => (CODEC_TYPE_PCM_S24BE, 24),
+        (b"NONE", 32) | (b"twos", 32) => (CODEC_TYPE_PCM_S32BE, 32),
+        (b"sowt", 16) => (CODEC_TYPE_PCM_S16LE, 16),
+        (b"sowt", 24) => (CODEC_TYPE_PCM_S24LE, 24),
+        (b"sowt", 32) => (CODEC_TYPE_PCM_S32LE, 32),
+        (b"raw ", 8) => (CODEC_TYPE_PCM_U8, 8),
+        (b"in24", _) => (CODEC_TYPE_PCM_S24BE, 24),
+        (b"in32", _) => (CODEC_TYPE_PCM_S32BE, 32),
+        (b"fl32", _) | (b"FL32", _) => (CODEC_TYPE_PCM_F32BE, 32),
+        (b"fl64", _) | (b"FL64", _) => (CODEC_TYPE_PCM_F64BE, 64),
+        (b"alaw", _) | (b"ALAW", _) => (CODEC_TYPE_PCM_ALAW, 8),
+        (b"ulaw", _) | (b"ULAW", _) => (CODEC_TYPE_PCM_MULAW, 8),
+        _ => return unsupported_error("aiff: unsupported compression type or sample size"),
+    };
+
+    Ok(codec)
+}
+
+/// Gets the channels for a channel count. The channel layouts defined by AIFF are ambiguous for
+/// more than 2 channels.
+fn channels_from_count(count: u16) -> Result<Channels> {
+    let channels = match u32::from(count) {
+        count @ 1..=32 => Channels::from_bits(u32::MAX >> (32 - count)),
+        _ => None,
+    };
+
+    match channels {
+        Some(channels) => Ok(channels),
+        None => unsupported_error("aiff: unsupported number of channels"),
+    }
+}
+
+/// Read a text chunk into a tag.
+fn read_text_chunk(buf: &[u8], id: &[u8; 4], builder: &mut MetadataBuilder) {
+    let (std_key, key) = match id {
+        b"NAME" => (StandardTagKey::TrackTitle, "NAME"),
+        b"AUTH" => (StandardTagKey::Artist, "AUTH"),
+        b"(c) " => (StandardTagKey::Copyright, "(c)"),
+        _ => (StandardTagKey::Comment, "ANNO"),
+    };
+
+    // The text may be null-terminated.
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+
+    let text = String::from_utf8_lossy(&buf[..len]);
+    let text = text.trim();
+
+    if !text.is_empty() {
+        builder.add_tag(Tag::new(Some(std_key), key, Value::from(text.to_string())));
+    }
+}
+
+/// Audio Interchange File Format (AIFF) reader.
+///
+/// `AiffReader` implements a demuxer for the AIFF and AIFF-C formats.
+pub struct AiffReader {
+    reader: MediaSourceStream,
+    tracks: Vec<Track>,
+    cues: Vec<Cue>,
+    metadata: MetadataLog,
+    /// The length of an audio frame, all channels, in bytes.
+    frame_len: u64,
+    max_frames_per_packet: u64,
+    data_start_pos: u64,
+    data_end_pos: u64,
+}
+
+impl QueryDescriptor for AiffReader {
+    fn query() -> &'static [Descriptor] {
+        &[support_format!(
+            "aiff",
+            "Audio Interchange File Format",
+            &["aiff", "aif", "aifc"],
+            &["audio/aiff", "audio/x-aiff", "audio/x-aifc"],
+            &[b"FORM"]
+        )]
+    }
+
+    fn score(_context: &[u8]) -> u8 {
+        255
+    }
+}
+
+impl FormatReader for AiffReader {
+    fn try_new(mut source: MediaSourceStream, options: &FormatOptions) -> Result<Self> {
+        // All fields are big-endian.
+        if source.read_quad_bytes()? != AIFF_STREAM_MARKER {
+            return unsupported_error("aiff: missing form stream marker");
+        }
+
+        let form_len = source.read_be_u32()?;
+
+        let is_aifc = match source.read_quad_bytes()? {
+            AIFF_FORM_TYPE => false,
+            AIFC_FORM_TYPE => true,
+            _ => return unsupported_error("aiff: form is not aiff or aifc"),
+        };
+
+        let form_end_pos = 8 + u64::from(form_len);
+
+        let mut comm = None;
+        let mut data = None;
+
+        let mut metadata: MetadataLog = Default::default();
+        let mut builder = MetadataBuilder::new();
+
+        // Chunks may appear in any order. Read chunks until the sound data chunk is found, or, if
+        // the stream is seekable, until the end of the form.
+        while source.pos() + 8 <= form_end_pos {
+            let id = match source.read_quad_bytes() {
+                Ok(id) => id,
+                // Tolerate a truncated form after the sound data chunk.
+                Err(_) if data.is_some() => break,
+                Err(err) => return Err(err.into()),
+            };
+
+            let len = source.read_be_u32()?;
+
+            // Chunks are padded to an even length.
+            let padded_len = u64::from(len) + u64::from(len & 1);
+
+            match &id {
+                b"COMM" => {
+                    comm = Some(read_comm_chunk(&mut source, len, is_aifc)?);
+                    source.ignore_bytes(padded_len - u64::from(len))?;
+                }
+                b"SSND" => {
+                    if len < 8 {
+                        return decode_error("aiff: invalid ssnd chunk length");
+                    }
+
+                    // The offset to the first sample frame, and the block size used for alignment.
+                    let offset = source.read_be_u32()?;
+                    let _block_size = source.read_be_u32()?;
+
+                    if offset > len - 8 {
+                        return decode_error("aiff: invalid ssnd data offset");
+                    }
+
+                    let start_pos = source.pos() + u64::from(offset);
+                    let end_pos = source.pos() + u64::from(len - 8);
+
+                    data = Some((start_pos, end_pos));
+
+                    if !source.is_seekable() {
+                        // The rest of the stream cannot be read without losing the audio data.
+                        if comm.is_none() {
+                            return unsupported_error("aiff: comm chunk follows the ssnd chunk");
+                        }
+
+                        source.ignore_bytes(u64::from(offset))?;
+                        break;
+                    }
+
+                    source.seek(SeekFrom::Start(end_pos + u64::from(len & 1)))?;
+                }
+                b"NAME" | b"AUTH" | b"(c) " | b"ANNO" if len <= AIFF_MAX_TEXT_LEN => {
+                    let buf = source.read_boxed_slice_exact(len as usize)?;
+                    source.ignore_bytes(padded_len - u64::from(len))?;
+
+                    read_text_chunk(&buf, &id, &mut builder);
+                }
+                b"ID3 " | b"id3 " => {
+                    let buf = source.read_boxed_slice_exact(len as usize)?;
+                    source.ignore_bytes(padded_len - u64::from(len))?;
+
+                    let mut id3_builder = MetadataBuilder::new();
+
+                    match id3v2::read_id3v2(&mut BufReader::new(&buf), &mut id3_builder) {
+                        Ok(_) => metadata.push(id3_builder.metadata()),
+                        Err(err) => warn!("aiff: failed to read id3v2 tag: {}", err),
+                    }
+                }
+                _ => {
+                    debug!("ignoring chunk id={:?} len={}", String::from_utf8_lossy(&id), len);
+                    source.ignore_bytes(padded_len)?;
+                }
+            }
+        }
+
+        let comm = match comm {
+            Some(comm) => comm,
+            None => return decode_error("aiff: missing comm chunk"),
+        };
+
+        let (data_start_pos, data_end_pos) = match data {
+            Some(data) => data,
+            None => return decode_error("aiff: missing ssnd chunk"),
+        };
+
+        // If the stream is seekable, the chunks following the sound data chunk have been read. Seek
+        // back to the first sample frame.
+        if source.is_seekable() {
+            source.seek(SeekFrom::Start(data_start_pos))?;
+        }
+
+        let text_metadata = builder.metadata();
+
+        if !text_metadata.tags().is_empty() {
+            metadata.push(text_metadata);
+        }
+
+        let (codec, bits_per_sample) = codec_from_comm(&comm)?;
+
+        let channels = channels_from_count(comm.n_channels)?;
+
+        let frame_len = u64::from(comm.n_channels) * u64::from(bits_per_sample / 8);
+
+        // The sound data chunk may be padded beyond the last sample frame.
+        let data_end_pos = data_end_pos.min(data_start_pos + u64::from(comm.n_frames) * frame_len);
+
+        let mut max_frames_per_packet = AIFF_MAX_FRAMES_PER_PACKET;
+
+        if let Some(max_dur) = options.max_packet_duration {
+            max_frames_per_packet = max_frames_per_packet
+                .min(max_dur.as_micros() as u64 * u64::from(comm.sample_rate) / 1_000_000)
+                .max(1);
+        }
+
+        let mut params = CodecParameters::new();
+
+        params
+            .for_codec(codec)
+            .with_sample_rate(comm.sample_rate)
+            .with_time_base(TimeBase::new(1, comm.sample_rate))
+            .with_channels(channels)
+            .with_max_frames_per_packet(max_frames_per_packet)
+            .with_n_frames(u64::from(comm.n_frames));
+
+        // Uncompressed samples are left-justified within the bytes that contain them. The
+        // companded encodings do not have a bits per sample.
+        match codec {
+            CODEC_TYPE_PCM_MULAW | CODEC_TYPE_PCM_ALAW => (),
+            CODEC_TYPE_PCM_F32BE | CODEC_TYPE_PCM_F64BE => {
+                params.with_bits_per_sample(bits_per_sample);
+            }
+            _ => {
+                params
+                    .with_bits_per_sample(u32::from(comm.sample_size).min(bits_per_sample))
+                    .with_bits_per_coded_sample(bits_per_sample);
+            }
+        }
+
+        Ok(AiffReader {
+            reader: source,
+            tracks: vec![Track::new(0, params)],
+            cues: Vec::new(),
+            metadata,
+            frame_len,
+            max_frames_per_packet,
+            data_start_pos,
+            data_end_pos,
+        })
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        let pos = self.reader.pos();
+
+        // Determine the number of complete audio frames remaining.
+        let num_frames_left =
+            if pos < self.data_end_pos { (self.data_end_pos - pos) / self.frame_len } else { 0 };
+
+        if num_frames_left == 0 {
+            return end_of_stream_error();
+        }
+
+        let dur = num_frames_left.min(self.max_frames_per_packet);
+
+        let ts = (pos - self.data_start_pos) / self.frame_len;
+
+        let buf = self.reader.read_boxed_slice_exact((dur * self.frame_len) as usize)?;
+
+        Ok(Packet::new_from_boxed_slice(0, ts, dur, buf).with_pos(pos))
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        let params = &self.tracks[0].codec_params;
+
+        let required_ts = match to {
+            // Frame timestamp given.
+            SeekTo::TimeStamp { ts, .. } => ts,
+            // Time value given, calculate frame timestamp from sample rate.
+            SeekTo::Time { time, .. } => {
+                // The sample rate is always known.
+                TimeBase::new(1, params.sample_rate.unwrap()).calc_timestamp(time)
+            }
+        };
+
+        if let Some(n_frames) = params.n_frames {
+            if required_ts > n_frames {
+                return seek_error(SeekErrorKind::OutOfRange);
+            }
+        }
+
+        debug!("seeking to frame_ts={}", required_ts);
+
+        let seek_pos = self.data_start_pos + required_ts * self.frame_len;
+
+        // If the reader supports seeking, seek directly to the frame. Otherwise, forward seeks
+        // are emulated by consuming bytes.
+        if self.reader.is_seekable() {
+            self.reader.seek(SeekFrom::Start(seek_pos))?;
+        }
+        else {
+            let current_pos = self.reader.pos();
+
+            if seek_pos >= current_pos {
+                self.reader.ignore_bytes(seek_pos - current_pos)?;
+            }
+            else {
+                return seek_error(SeekErrorKind::ForwardOnly);
+            }
+        }
+
+        Ok(SeekedTo { track_id: 0, actual_ts: required_ts, required_ts })
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.reader
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use symphonia_core::codecs::{CODEC_TYPE_PCM_S16LE, CODEC_TYPE_PCM_S24BE};
+    use symphonia_core::formats::{FormatReader, SeekMode, SeekTo};
+    use symphonia_core::io::MediaSourceStream;
+    use symphonia_core::meta::StandardTagKey;
+
+    use super::{sample_rate_from_extended, AiffReader};
+
+    fn chunk(id: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut buf = id.to_vec();
+        buf.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        buf.extend_from_slice(data);
+        if data.len() % 2 != 0 {
+            buf.push(0);
+        }
+        buf
+    }
+
+    fn make_aiff(form_type: &[u8], comm: &[u8], chunks: &[Vec<u8>]) -> Vec<u8> {
+        let mut body = form_type.to_vec();
+        body.extend(chunk(b"COMM", comm));
+        for chunk in chunks {
+            body.extend_from_slice(chunk);
+        }
+        let mut aiff = b"FORM".to_vec();
+        aiff.extend_from_slice(&(body.len() as u32).to_be_bytes());
+        aiff.extend(body);
+        aiff
+    }
+
+    fn make_comm(channels: u16, frames: u32, sample_size: u16, compression: &[u8]) -> Vec<u8> {
+        let mut comm = channels.to_be_bytes().to_vec();
+        comm.extend_from_slice(&frames.to_be_bytes());
+        comm.extend_from_slice(&sample_size.to_be_bytes());
+        // 44100 Hz as an 80-bit extended precision number.
+        comm.extend_from_slice(&[0x40, 0x0e, 0xac, 0x44, 0, 0, 0, 0, 0, 0]);
+        comm.extend_from_slice(compression);
+        comm
+    }
+
+    fn open(aiff: Vec<u8>) -> AiffReader {
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(aiff)), Default::default());
+        AiffReader::try_new(mss, &Default::default()).unwrap()
+    }
+
+    #[test]
+    fn verify_sample_rate() {
+        let rate = [0x40, 0x0e, 0xac, 0x44, 0, 0, 0, 0, 0, 0];
+        assert_eq!(sample_rate_from_extended(&rate).unwrap(), 44100);
+
+        let rate = [0x40, 0x0b, 0xfa, 0, 0, 0, 0, 0, 0, 0];
+        assert_eq!(sample_rate_from_extended(&rate).unwrap(), 8000);
+
+        assert!(sample_rate_from_extended(&[0xc0, 0x0e, 0xac, 0x44, 0, 0, 0, 0, 0, 0]).is_err());
+        assert!(sample_rate_from_extended(&[0; 10]).is_err());
+    }
+
+    #[test]
+    fn verify_aiff_reader() {
+        // A mono 20-bit file, with 4 bytes of alignment before the first sample frame, and a
+        // title chunk following the sound data chunk.
+        let mut ssnd = vec![0, 0, 0, 4, 0, 0, 0, 0, 0xff, 0xff, 0xff, 0xff];
+        ssnd.extend((0..3 * 2000).map(|i| (i / 3) as u8));
+
+        let chunks = [chunk(b"SSND", &ssnd), chunk(b"NAME", b"Title")];
+        let mut reader = open(make_aiff(b"AIFF", &make_comm(1, 2000, 20, &[]), &chunks));
+
+        let params = &reader.tracks()[0].codec_params;
+        assert_eq!(params.codec, CODEC_TYPE_PCM_S24BE);
+        assert_eq!(params.sample_rate, Some(44100));
+        assert_eq!(params.n_frames, Some(2000));
+        assert_eq!(params.bits_per_sample, Some(20));
+        assert_eq!(params.bits_per_coded_sample, Some(24));
+
+        let metadata = reader.metadata();
+        let tags = metadata.current().unwrap().tags();
+        assert_eq!(tags[0].std_key, Some(StandardTagKey::TrackTitle));
+        assert_eq!(tags[0].value.to_string(), "Title");
+
+        let packet = reader.next_packet().unwrap();
+        assert_eq!((packet.ts, packet.dur), (0, 1152));
+        assert_eq!(&packet.data[..3], &[0, 0, 0]);
+
+        let packet = reader.next_packet().unwrap();
+        assert_eq!((packet.ts, packet.dur), (1152, 848));
+        assert!(reader.next_packet().is_err());
+
+        let seeked = reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 1000, track_id: 0 });
+        assert_eq!(seeked.unwrap().actual_ts, 1000);
+        assert_eq!(reader.next_packet().unwrap().data[0], 1000u32 as u8);
+    }
+
+    #[test]
+    fn verify_aifc_reader() {
+        // A stereo little-endian 16-bit file, with a padded compression name.
+        let mut ssnd = vec![0; 8];
+        ssnd.extend_from_slice(&[0; 16]);
+
+        let comm = make_comm(2, 4, 16, b"sowt\x03abc");
+        let mut reader = open(make_aiff(b"AIFC", &comm, &[chunk(b"SSND", &ssnd)]));
+
+        let params = &reader.tracks()[0].codec_params;
+        assert_eq!(params.codec, CODEC_TYPE_PCM_S16LE);
+        assert_eq!(params.n_frames, Some(4));
+
+        let packet = reader.next_packet().unwrap();
+        assert_eq!((packet.ts, packet.dur, packet.data.len()), (0, 4, 16));
+        assert!(reader.next_packet().is_err());
+    }
+}
@@ -0,0 +1,336 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::io::{Seek, SeekFrom};
+
+use symphonia_core::audio::Channels;
+use symphonia_core::codecs::{CodecParameters, CodecType, CODEC_TYPE_AMR_NB, CODEC_TYPE_AMR_WB};
+use symphonia_core::errors::{end_of_stream_error, seek_error, unsupported_error};
+use symphonia_core::errors::{Result, SeekErrorKind};
+use symphonia_core::formats::prelude::*;
+use symphonia_core::io::*;
+use symphonia_core::meta::{Metadata, MetadataLog};
+use symphonia_core::probe::{Descriptor, Instantiate, QueryDescriptor};
+use symphonia_core::support_format;
+
+use log::debug;
+
+/// The magic header of a single channel AMR-NB file.
+const AMR_NB_MAGIC: &[u8] = b"#!AMR\n";
+/// The magic header of a single channel AMR-WB file.
+const AMR_WB_MAGIC: &[u8] = b"#!AMR-WB\n";
+/// The magic header of a multi-channel AMR-NB file.
+const AMR_NB_MC_MAGIC: &[u8] = b"#!AMR_MC1.0\n";
+/// The magic header of a multi-channel AMR-WB file.
+const AMR_WB_MC_MAGIC: &[u8] = b"#!AMR-WB_MC1.0\n";
+
+/// The length of the longest magic header.
+const AMR_MAX_MAGIC_LEN: usize = 15;
+
+/// The number of speech data bytes following the frame header, indexed by frame type, for
+/// AMR-NB. Reserved frame types, and frames with no data, are 0.
+const AMR_NB_FRAME_SIZES: [usize; 16] = [12, 13, 15, 17, 19, 20, 26, 31, 5, 0, 0, 0, 0, 0, 0, 0];
+
+/// The number of speech data bytes following the frame header, indexed by frame type, for
+/// AMR-WB. Reserved frame types, lost speech frames, and frames with no data, are 0.
+const AMR_WB_FRAME_SIZES: [usize; 16] = [17, 23, 32, 36, 40, 46, 50, 58, 60, 5, 0, 0, 0, 0, 0, 0];
+
+/// The AMR variant of a file.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Variant {
+    /// AMR Narrowband.
+    Nb,
+    /// AMR Wideband.
+    Wb,
+}
+
+impl Variant {
+    fn codec(&self) -> CodecType {
+        match self {
+            Variant::Nb => CODEC_TYPE_AMR_NB,
+            Variant::Wb => CODEC_TYPE_AMR_WB,
+        }
+    }
+
+    fn sample_rate(&self) -> u32 {
+        match self {
+            Variant::Nb => 8000,
+            Variant::Wb => 16000,
+        }
+    }
+
+    /// Gets the number of audio frames in an AMR frame. Every AMR frame is 20 ms long.
+    fn frame_len(&self) -> u64 {
+        u64::from(self.sample_rate() / 50)
+    }
+
+    /// Gets the total length of an AMR frame in bytes, including the frame header, given the
+    /// frame header.
+    fn frame_size(&self, header: u8) -> usize {
+        let frame_type = usize::from((header >> 3) & 0xf);
+
+        let size = match self {
+            Variant::Nb => AMR_NB_FRAME_SIZES[frame_type],
+            Variant::Wb => AMR_WB_FRAME_SIZES[frame_type],
+        };
+
+        1 + size
+    }
+}
+
+/// Adaptive Multi-Rate (AMR) format reader.
+///
+/// `AmrReader` implements a demuxer for the single channel AMR-NB and AMR-WB storage format
+/// (RFC 4867, section 5). Each packet contains one AMR frame, including its frame header.
+pub struct AmrReader {
+    reader: MediaSourceStream,
+    tracks: Vec<Track>,
+    cues: Vec<Cue>,
+    metadata: MetadataLog,
+    variant: Variant,
+    data_start_pos: u64,
+    /// The index of the next AMR frame.
+    next_frame: u64,
+}
+
+impl QueryDescriptor for AmrReader {
+    fn query() -> &'static [Descriptor] {
+        &[support_format!(
+            "amr",
+            "Adaptive Multi-Rate",
+            &["amr", "awb"],
+            &["audio/amr", "audio/amr-wb"],
+            &[AMR_NB_MAGIC, AMR_WB_MAGIC]
+        )]
+    }
+
+    fn score(_context: &[u8]) -> u8 {
+        255
+    }
+}
+
+impl AmrReader {
+    /// Skip the next AMR frame. Returns false if the end of the stream was reached.
+    fn skip_frame(&mut self) -> Result<bool> {
+        let header = match self.reader.read_u8() {
+            Ok(header) => header,
+            Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(false),
+            Err(err) => return Err(err.into()),
+        };
+
+        self.reader.ignore_bytes(self.variant.frame_size(header) as u64 - 1)?;
+        self.next_frame += 1;
+
+        Ok(true)
+    }
+}
+
+impl FormatReader for AmrReader {
+    fn try_new(mut source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
+        // The magic headers are 6 to 15 bytes long. Read the shortest one, and then read further
+        // only if required.
+        let mut magic = [0; AMR_MAX_MAGIC_LEN];
+        source.read_buf_exact(&mut magic[..AMR_NB_MAGIC.len()])?;
+
+        let variant = if magic[..AMR_NB_MAGIC.len()] == *AMR_NB_MAGIC {
+            Variant::Nb
+        }
+        else {
+            source.read_buf_exact(&mut magic[AMR_NB_MAGIC.len()..AMR_WB_MAGIC.len()])?;
+
+            if magic[..AMR_WB_MAGIC.len()] == *AMR_WB_MAGIC {
+                Variant::Wb
+            }
+            else if AMR_NB_MC_MAGIC.starts_with(&magic[..AMR_WB_MAGIC.len()])
+                || AMR_WB_MC_MAGIC.starts_with(&magic[..AMR_WB_MAGIC.len()])
+            {
+                return unsupported_error("amr: multi-channel files are not supported");
+            }
+            else {
+                return unsupported_error("amr: missing amr magic header");
+            }
+        };
+
+        let sample_rate = variant.sample_rate();
+
+        let mut params = CodecParameters::new();
+
+        params
+            .for_codec(variant.codec())
+            .with_sample_rate(sample_rate)
+            .with_time_base(TimeBase::new(1, sample_rate))
+            .with_channels(Channels::FRONT_LEFT)
+            .with_max_frames_per_packet(variant.frame_len());
+
+        let data_start_pos = source.pos();
+
+        Ok(AmrReader {
+            reader: source,
+            tracks: vec![Track::new(0, params)],
+            cues: Vec::new(),
+            metadata: Default::default(),
+            variant,
+            data_start_pos,
+            next_frame: 0,
+        })
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        let pos = self.reader.pos();
+
+        let header = match self.reader.read_u8() {
+            Ok(header) => header,
+            Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => {
+                return end_of_stream_error()
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        // The packet includes the frame header since it is required to decode the frame.
+        let mut buf = vec![0; self.variant.frame_size(header)];
+        buf[0] = header;
+
+        // A truncated final frame is dropped.
+        match self.reader.read_buf_exact(&mut buf[1..]) {
+            Ok(_) => (),
+            Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => {
+                return end_of_stream_error()
+            }
+            Err(err) => return Err(err.into()),
+        }
+
+        let frame_len = self.variant.frame_len();
+        let ts = self.next_frame * frame_len;
+
+        self.next_frame += 1;
+
+        Ok(Packet::new_from_slice(0, ts, frame_len, &buf).with_pos(pos))
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        let required_ts = match to {
+            // Frame timestamp given.
+            SeekTo::TimeStamp { ts, .. } => ts,
+            // Time value given, calculate frame timestamp from sample rate.
+            SeekTo::Time { time, .. } => {
+                TimeBase::new(1, self.variant.sample_rate()).calc_timestamp(time)
+            }
+        };
+
+        let frame = required_ts / self.variant.frame_len();
+
+        debug!("seeking to frame_ts={} in amr frame {}", required_ts, frame);
+
+        // The size of an AMR frame depends on its frame type, and there is no seek index.
+        // Therefore, seeking requires reading every frame header from the start of the stream, or
+        // the current frame if seeking forward.
+        if frame < self.next_frame {
+            if self.reader.is_seekable() {
+                self.reader.seek(SeekFrom::Start(self.data_start_pos))?;
+                self.next_frame = 0;
+            }
+            else {
+                return seek_error(SeekErrorKind::ForwardOnly);
+            }
+        }
+
+        while self.next_frame < frame {
+            if !self.skip_frame()? {
+                return seek_error(SeekErrorKind::OutOfRange);
+            }
+        }
+
+        let actual_ts = frame * self.variant.frame_len();
+
+        Ok(SeekedTo { track_id: 0, actual_ts, required_ts })
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.reader
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use symphonia_core::codecs::{CODEC_TYPE_AMR_NB, CODEC_TYPE_AMR_WB};
+    use symphonia_core::formats::{FormatReader, SeekMode, SeekTo};
+    use symphonia_core::io::MediaSourceStream;
+
+    use super::AmrReader;
+
+    fn open(amr: Vec<u8>) -> AmrReader {
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(amr)), Default::default());
+        AmrReader::try_new(mss, &Default::default()).unwrap()
+    }
+
+    #[test]
+    fn verify_amr_nb_reader() {
+        // A 12.2 kbit/s frame, a SID frame, a no data frame, and a truncated 4.75 kbit/s frame.
+        let mut amr = b"#!AMR\n".to_vec();
+        amr.push(7 << 3 | 0x4);
+        amr.extend_from_slice(&[0xaa; 31]);
+        amr.push(8 << 3 | 0x4);
+        amr.extend_from_slice(&[0xbb; 5]);
+        amr.push(15 << 3 | 0x4);
+        amr.push(0x4);
+        amr.extend_from_slice(&[0xcc; 4]);
+
+        let mut reader = open(amr);
+
+        let params = &reader.tracks()[0].codec_params;
+        assert_eq!(params.codec, CODEC_TYPE_AMR_NB);
+        assert_eq!(params.sample_rate, Some(8000));
+
+        let packet = reader.next_packet().unwrap();
+        assert_eq!((packet.ts, packet.dur, packet.data.len()), (0, 160, 32));
+
+        let packet = reader.next_packet().unwrap();
+        assert_eq!((packet.ts, packet.data.len()), (160, 6));
+
+        let packet = reader.next_packet().unwrap();
+        assert_eq!((packet.ts, packet.data.len()), (320, 1));
+        assert!(reader.next_packet().is_err());
+
+        let seeked = reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 200, track_id: 0 });
+        assert_eq!(seeked.unwrap().actual_ts, 160);
+        assert_eq!(&reader.next_packet().unwrap().data[1..], &[0xbb; 5]);
+
+        let seeked = reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 800, track_id: 0 });
+        assert!(seeked.is_err());
+    }
+
+    #[test]
+    fn verify_amr_wb_reader() {
+        let mut amr = b"#!AMR-WB\n".to_vec();
+        amr.push(8 << 3 | 0x4);
+        amr.extend_from_slice(&[0; 60]);
+
+        let mut reader = open(amr);
+
+        let params = &reader.tracks()[0].codec_params;
+        assert_eq!(params.codec, CODEC_TYPE_AMR_WB);
+        assert_eq!(params.sample_rate, Some(16000));
+
+        let packet = reader.next_packet().unwrap();
+        assert_eq!((packet.ts, packet.dur, packet.data.len()), (0, 320, 61));
+        assert!(reader.next_packet().is_err());
+    }
+}
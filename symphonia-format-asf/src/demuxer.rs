@@ -0,0 +1,802 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::collections::VecDeque;
+use std::io::{Seek, SeekFrom};
+
+use symphonia_core::audio::Channels;
+use symphonia_core::codecs::*;
+use symphonia_core::errors::{decode_error, end_of_stream_error, seek_error, unsupported_error};
+use symphonia_core::errors::{Result, SeekErrorKind};
+use symphonia_core::formats::prelude::*;
+use symphonia_core::io::*;
+use symphonia_core::meta::{Metadata, MetadataBuilder, MetadataLog};
+use symphonia_core::probe::{Descriptor, Instantiate, QueryDescriptor};
+use symphonia_core::support_format;
+
+use log::{debug, info, warn};
+
+use crate::objects::*;
+use crate::packet::{descramble, read_data_packet, Payload};
+
+/// The media object being reassembled from the payloads of one or more data packets.
+struct MediaObject {
+    num: u8,
+    pres_time: u32,
+    len: usize,
+    buf: Vec<u8>,
+}
+
+/// The state of an audio stream.
+struct StreamState {
+    /// The stream number. Also used as the track ID.
+    number: u8,
+    spread: Option<AudioSpread>,
+    avg_bytes_per_sec: u32,
+    object: Option<MediaObject>,
+    /// The timestamp and data of the last complete media object. A media object is held until
+    /// the next media object of the stream is complete so that its duration may be determined.
+    pending: Option<(u64, Box<[u8]>)>,
+}
+
+impl StreamState {
+    fn new(stream: &AudioStream) -> Self {
+        StreamState {
+            number: stream.number,
+            spread: stream.spread,
+            avg_bytes_per_sec: stream.format.avg_bytes_per_sec,
+            object: None,
+            pending: None,
+        }
+    }
+
+    /// Reset the stream to its initial state.
+    fn reset(&mut self) {
+        self.object = None;
+        self.pending = None;
+    }
+
+    /// Read a payload of the stream, and queue a packet if a media object was completed.
+    fn read_payload(
+        &mut self,
+        payload: &Payload<'_>,
+        preroll: u64,
+        packets: &mut VecDeque<Packet>,
+    ) {
+        // A payload at offset 0 starts a new media object.
+        if payload.offset == 0 {
+            if self.object.is_some() {
+                debug!("discarding incomplete media object of stream={}", self.number);
+            }
+
+            self.object = Some(MediaObject {
+                num: payload.object_num,
+                pres_time: payload.pres_time,
+                len: payload.object_len as usize,
+                buf: Vec::with_capacity(payload.object_len as usize),
+            });
+        }
+
+        // Payloads of a media object whose start was not read are ignored.
+        let object = match self.object.as_mut() {
+            Some(object) => object,
+            None => return,
+        };
+
+        if object.num != payload.object_num || object.buf.len() != payload.offset as usize {
+            warn!("lost payloads of stream={}, discarding media object", self.number);
+            self.object = None;
+            return;
+        }
+
+        object.buf.extend_from_slice(payload.data);
+
+        if object.buf.len() < object.len {
+            return;
+        }
+
+        let object = self.object.take().unwrap();
+
+        if object.buf.len() > object.len {
+            warn!("media object of stream={} exceeds its length, discarding", self.number);
+            return;
+        }
+
+        let buf = match self.spread {
+            Some(spread) if spread.is_scrambled() => {
+                let span = usize::from(spread.span);
+                let packet_len = usize::from(spread.packet_len);
+                let chunk_len = usize::from(spread.chunk_len);
+
+                // Only media objects that are exactly one span of virtual packets are scrambled.
+                if object.len == span * packet_len {
+                    descramble(&object.buf, span, packet_len, chunk_len)
+                }
+                else {
+                    object.buf
+                }
+            }
+            _ => object.buf,
+        };
+
+        let ts = u64::from(object.pres_time).saturating_sub(preroll);
+
+        if let Some((pending_ts, data)) = self.pending.take() {
+            packets.push_back(Packet::new_from_boxed_slice(
+                u32::from(self.number),
+                pending_ts,
+                ts.saturating_sub(pending_ts),
+                data,
+            ));
+        }
+
+        self.pending = Some((ts, buf.into_boxed_slice()));
+    }
+
+    /// Queue the held media object, if any, at the end of the stream.
+    fn flush(&mut self, end_ts: Option<u64>, packets: &mut VecDeque<Packet>) {
+        if let Some((ts, data)) = self.pending.take() {
+            // The duration of the last media object is the remainder of the stream, if known.
+            // Otherwise, it is estimated from the average bitrate.
+            let dur = match end_ts {
+                Some(end_ts) if end_ts > ts => end_ts - ts,
+                _ if self.avg_bytes_per_sec > 0 => {
+                    data.len() as u64 * 1000 / u64::from(self.avg_bytes_per_sec)
+                }
+                _ => 0,
+            };
+
+            packets.push_back(Packet::new_from_boxed_slice(u32::from(self.number), ts, dur, data));
+        }
+    }
+}
+
+/// Get the channels for a number of channels.
+fn channels_from_count(count: u16) -> Option<Channels> {
+    if count <= 32 {
+        Channels::from_bits(u32::MAX >> (32 - u32::from(count)))
+    }
+    else {
+        None
+    }
+}
+
+/// Build the codec parameters of an audio stream. Returns `None` if the audio format is not
+/// supported.
+fn codec_params(stream: &AudioStream, duration: Option<u64>) -> Option<CodecParameters> {
+    let format = &stream.format;
+
+    let codec = match (format.format_tag, format.bits_per_sample) {
+        (0x0001, 8) => CODEC_TYPE_PCM_U8,
+        (0x0001, 16) => CODEC_TYPE_PCM_S16LE,
+        (0x0001, 24) => CODEC_TYPE_PCM_S24LE,
+        (0x0001, 32) => CODEC_TYPE_PCM_S32LE,
+        (0x0003, 32) => CODEC_TYPE_PCM_F32LE,
+        (0x0003, 64) => CODEC_TYPE_PCM_F64LE,
+        (0x0006, _) => CODEC_TYPE_PCM_ALAW,
+        (0x0007, _) => CODEC_TYPE_PCM_MULAW,
+        (0x0050, _) => CODEC_TYPE_MP2,
+        (0x0055, _) => CODEC_TYPE_MP3,
+        // WMA version 1 and 2 are distinguished by the format tag in the extra data.
+        (0x0160, _) | (0x0161, _) => CODEC_TYPE_WMA,
+        (0x0162, _) => CODEC_TYPE_WMA_PRO,
+        (0x0163, _) => CODEC_TYPE_WMA_LOSSLESS,
+        _ => {
+            warn!(
+                "ignoring stream={} with unsupported format tag={:#x}",
+                stream.number, format.format_tag
+            );
+            return None;
+        }
+    };
+
+    let channels = match channels_from_count(format.n_channels) {
+        Some(channels) => channels,
+        None => {
+            warn!("ignoring stream={} with {} channels", stream.number, format.n_channels);
+            return None;
+        }
+    };
+
+    let mut params = CodecParameters::new();
+
+    // Presentation times are in milliseconds.
+    params
+        .for_codec(codec)
+        .with_sample_rate(format.sample_rate)
+        .with_time_base(TimeBase::new(1, 1000))
+        .with_channels(channels);
+
+    if let Some(duration) = duration {
+        params.with_n_frames(duration);
+    }
+
+    if format.format_tag == 0x0001 || format.format_tag == 0x0003 {
+        params
+            .with_bits_per_sample(u32::from(format.bits_per_sample))
+            .with_bits_per_coded_sample(u32::from(format.bits_per_sample));
+    }
+
+    // The WMA decoders require the block alignment and bitrate, in addition to the codec
+    // specific data. Therefore, the extra data is the complete waveform audio format structure.
+    if codec == CODEC_TYPE_WMA || codec == CODEC_TYPE_WMA_PRO || codec == CODEC_TYPE_WMA_LOSSLESS {
+        params.with_extra_data(format.buf.clone());
+    }
+
+    Some(params)
+}
+
+/// Advanced Systems Format (ASF) format reader.
+///
+/// `AsfReader` implements a demuxer for the audio streams of an ASF container, such as a Windows
+/// Media Audio (WMA) file. Each packet contains one media object, reassembled from the payloads
+/// of the data packets. Timestamps are the presentation times of the media objects, in
+/// milliseconds, less the preroll.
+///
+/// For Windows Media Audio streams, the codec extra data is the complete waveform audio format
+/// (WAVEFORMATEX) structure of the stream.
+pub struct AsfReader {
+    reader: MediaSourceStream,
+    tracks: Vec<Track>,
+    cues: Vec<Cue>,
+    metadata: MetadataLog,
+    streams: Vec<StreamState>,
+    /// Media objects that have not been returned yet.
+    packets: VecDeque<Packet>,
+    /// A buffer for one data packet.
+    buf: Vec<u8>,
+    preroll: u64,
+    /// The duration of the file in milliseconds, if known.
+    duration: Option<u64>,
+    data_start_pos: u64,
+    /// The number of data packets, if known.
+    num_packets: Option<u64>,
+    /// The index of the next data packet.
+    next_packet_index: u64,
+}
+
+impl QueryDescriptor for AsfReader {
+    fn query() -> &'static [Descriptor] {
+        &[support_format!(
+            "asf",
+            "Advanced Systems Format",
+            &["asf", "wma"],
+            &["audio/x-ms-wma", "video/x-ms-asf"],
+            &[&HEADER_OBJECT]
+        )]
+    }
+
+    fn score(_context: &[u8]) -> u8 {
+        255
+    }
+}
+
+impl AsfReader {
+    /// Read the next data packet, and the payloads of the audio streams it carries. Returns false
+    /// if the end of the data object was reached.
+    fn read_data_packet(&mut self) -> Result<bool> {
+        if let Some(num_packets) = self.num_packets {
+            if self.next_packet_index >= num_packets {
+                return Ok(false);
+            }
+        }
+
+        match self.reader.read_buf_exact(&mut self.buf) {
+            Ok(_) => (),
+            Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(false),
+            Err(err) => return Err(err.into()),
+        }
+
+        self.next_packet_index += 1;
+
+        let packet = match read_data_packet(&self.buf) {
+            Ok(packet) => packet,
+            Err(err) => {
+                warn!("skipping invalid data packet: {}", err);
+                return Ok(true);
+            }
+        };
+
+        for payload in packet.payloads.iter() {
+            if let Some(stream) = self.streams.iter_mut().find(|s| s.number == payload.stream) {
+                stream.read_payload(payload, self.preroll, &mut self.packets);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Read data packets until at least one media object is queued.
+    fn read_more_packets(&mut self) -> Result<()> {
+        while self.packets.is_empty() {
+            if !self.read_data_packet()? {
+                for stream in self.streams.iter_mut() {
+                    stream.flush(self.duration, &mut self.packets);
+                }
+
+                if self.packets.is_empty() {
+                    return end_of_stream_error();
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Seek to a data packet, and discard all buffered media objects.
+    fn seek_to_data_packet(&mut self, index: u64) -> Result<()> {
+        let pos = self.data_start_pos + index * self.buf.len() as u64;
+
+        self.reader.seek(SeekFrom::Start(pos))?;
+        self.next_packet_index = index;
+        self.packets.clear();
+
+        for stream in self.streams.iter_mut() {
+            stream.reset();
+        }
+
+        Ok(())
+    }
+
+    /// Find the last data packet with a send time at or before the given time, by binary search.
+    fn find_data_packet(&mut self, num_packets: u64, time: u64) -> Result<u64> {
+        let mut lo = 0;
+        let mut hi = num_packets;
+
+        while lo + 1 < hi {
+            let mid = lo + (hi - lo) / 2;
+
+            self.seek_to_data_packet(mid)?;
+            self.reader.read_buf_exact(&mut self.buf)?;
+
+            if u64::from(read_data_packet(&self.buf)?.send_time) <= time {
+                lo = mid;
+            }
+            else {
+                hi = mid;
+            }
+        }
+
+        Ok(lo)
+    }
+
+    /// Discard media objects until the media object of the track containing the required
+    /// timestamp. Returns the timestamp of that media object.
+    fn discard_until(&mut self, track_id: u32, required_ts: u64) -> Result<u64> {
+        loop {
+            self.read_more_packets()?;
+
+            let packet = self.packets.front().unwrap();
+
+            if packet.track_id() == track_id && packet.ts + packet.dur > required_ts {
+                return Ok(packet.ts);
+            }
+
+            self.packets.pop_front();
+        }
+    }
+}
+
+impl FormatReader for AsfReader {
+    fn try_new(mut source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
+        let header = ObjectHeader::read(&mut source)?;
+
+        if header.guid != HEADER_OBJECT {
+            return unsupported_error("asf: missing header object");
+        }
+
+        if header.data_len() < HEADER_OBJECT_FIELDS_LEN {
+            return decode_error("asf: invalid header object length");
+        }
+
+        let num_objects = source.read_u32()?;
+        let _reserved1 = source.read_u8()?;
+        let _reserved2 = source.read_u8()?;
+
+        let mut remaining = header.data_len() - HEADER_OBJECT_FIELDS_LEN;
+
+        let mut file_props = None;
+        let mut audio_streams = Vec::new();
+        let mut builder = MetadataBuilder::new();
+        let mut has_metadata = false;
+
+        for _ in 0..num_objects {
+            if remaining < OBJECT_HEADER_LEN {
+                break;
+            }
+
+            let header = ObjectHeader::read(&mut source)?;
+
+            if header.len > remaining {
+                return decode_error("asf: header object is truncated");
+            }
+
+            remaining -= header.len;
+
+            let len = header.data_len() as usize;
+
+            match header.guid {
+                FILE_PROPERTIES_OBJECT => {
+                    file_props = Some(FileProperties::read(&source.read_boxed_slice_exact(len)?)?);
+                }
+                STREAM_PROPERTIES_OBJECT => {
+                    let buf = source.read_boxed_slice_exact(len)?;
+
+                    if let Some(stream) = read_stream_properties(&buf)? {
+                        audio_streams.push(stream);
+                    }
+                }
+                HEADER_EXTENSION_OBJECT => {
+                    let buf = source.read_boxed_slice_exact(len)?;
+                    read_header_extension(&buf, &mut audio_streams, &mut builder)?;
+                    has_metadata = true;
+                }
+                CONTENT_DESCRIPTION_OBJECT => {
+                    let buf = source.read_boxed_slice_exact(len)?;
+                    read_content_description(&buf, &mut builder)?;
+                    has_metadata = true;
+                }
+                EXTENDED_CONTENT_DESCRIPTION_OBJECT => {
+                    let buf = source.read_boxed_slice_exact(len)?;
+                    read_extended_content_description(&buf, &mut builder)?;
+                    has_metadata = true;
+                }
+                _ => source.ignore_bytes(header.data_len())?,
+            }
+        }
+
+        source.ignore_bytes(remaining)?;
+
+        let file_props = match file_props {
+            Some(file_props) => file_props,
+            None => return decode_error("asf: missing file properties object"),
+        };
+
+        // The data object immediately follows the header object.
+        let data = ObjectHeader::read(&mut source)?;
+
+        if data.guid != DATA_OBJECT || data.len < OBJECT_HEADER_LEN + DATA_OBJECT_FIELDS_LEN {
+            return decode_error("asf: missing data object");
+        }
+
+        // File ID, total data packets, and reserved field.
+        source.ignore_bytes(DATA_OBJECT_FIELDS_LEN)?;
+
+        let data_start_pos = source.pos();
+
+        let packet_len = u64::from(file_props.packet_len);
+
+        // If the file properties do not provide the number of data packets, it may be calculated
+        // from the length of the data object, if known.
+        let num_packets = file_props.num_packets.or_else(|| {
+            let len = data.len - OBJECT_HEADER_LEN - DATA_OBJECT_FIELDS_LEN;
+            if len > 0 {
+                Some(len / packet_len)
+            }
+            else {
+                None
+            }
+        });
+
+        let preroll = file_props.preroll;
+
+        // The play duration includes the preroll.
+        let duration = file_props.play_duration.map(|dur| (dur / 10_000).saturating_sub(preroll));
+
+        let mut tracks = Vec::new();
+        let mut streams = Vec::new();
+
+        for stream in audio_streams.iter() {
+            if streams.iter().any(|s: &StreamState| s.number == stream.number) {
+                continue;
+            }
+
+            if let Some(params) = codec_params(stream, duration) {
+                info!(
+                    "found stream={} with format tag={:#x}",
+                    stream.number, stream.format.format_tag
+                );
+
+                tracks.push(Track::new(u32::from(stream.number), params));
+                streams.push(StreamState::new(stream));
+            }
+        }
+
+        if streams.is_empty() {
+            return unsupported_error("asf: no supported audio streams");
+        }
+
+        let mut metadata: MetadataLog = Default::default();
+
+        if has_metadata {
+            metadata.push(builder.metadata());
+        }
+
+        Ok(AsfReader {
+            reader: source,
+            tracks,
+            cues: Vec::new(),
+            metadata,
+            streams,
+            packets: VecDeque::new(),
+            buf: vec![0; packet_len as usize],
+            preroll,
+            duration,
+            data_start_pos,
+            num_packets,
+            next_packet_index: 0,
+        })
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        self.read_more_packets()?;
+        Ok(self.packets.pop_front().unwrap())
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        // Select the track to seek, or the first track if one was not provided.
+        let track = match to {
+            SeekTo::TimeStamp { track_id, .. } => self.tracks.iter().find(|t| t.id == track_id),
+            SeekTo::Time { track_id: Some(track_id), .. } => {
+                self.tracks.iter().find(|t| t.id == track_id)
+            }
+            SeekTo::Time { track_id: None, .. } => self.tracks.first(),
+        };
+
+        let track = match track {
+            Some(track) => track,
+            None => return seek_error(SeekErrorKind::InvalidTrack),
+        };
+
+        let track_id = track.id;
+
+        let required_ts = match to {
+            SeekTo::TimeStamp { ts, .. } => ts,
+            SeekTo::Time { time, .. } => track.codec_params.time_base.unwrap().calc_timestamp(time),
+        };
+
+        if let Some(duration) = self.duration {
+            if required_ts > duration {
+                return seek_error(SeekErrorKind::OutOfRange);
+            }
+        }
+
+        debug!("seeking to ts={} of track={}", required_ts, track_id);
+
+        let num_packets = match self.num_packets {
+            Some(num_packets) if self.reader.is_seekable() => num_packets,
+            _ => {
+                // Without random access, only forward seeks are possible.
+                let next_ts = self
+                    .packets
+                    .iter()
+                    .find(|p| p.track_id() == track_id)
+                    .map(|p| p.ts)
+                    .or_else(|| {
+                        let stream = self.streams.iter().find(|s| u32::from(s.number) == track_id);
+                        stream.and_then(|s| s.pending.as_ref()).map(|(ts, _)| *ts)
+                    })
+                    .unwrap_or(0);
+
+                if required_ts < next_ts {
+                    return seek_error(SeekErrorKind::ForwardOnly);
+                }
+
+                let actual_ts = self.discard_until(track_id, required_ts)?;
+
+                return Ok(SeekedTo { track_id, required_ts, actual_ts });
+            }
+        };
+
+        // A media object is sent no earlier than its timestamp, and no later than its presentation
+        // time. Therefore, the last data packet sent at or before the required timestamp is a
+        // good estimate of where the media object containing it starts. If the media object was
+        // sent earlier, search again for an earlier data packet.
+        let mut time = required_ts;
+        let mut last_index = None;
+
+        let actual_ts = loop {
+            let mut index = self.find_data_packet(num_packets, time)?;
+
+            if let Some(last_index) = last_index {
+                if index >= last_index {
+                    index = last_index - 1;
+                }
+            }
+
+            self.seek_to_data_packet(index)?;
+
+            let ts = self.discard_until(track_id, required_ts)?;
+
+            if ts <= required_ts || index == 0 {
+                break ts;
+            }
+
+            last_index = Some(index);
+            time = time.saturating_sub(ts - required_ts);
+        };
+
+        debug!("seeked to ts={} (delta={})", actual_ts, required_ts as i64 - actual_ts as i64);
+
+        Ok(SeekedTo { track_id, required_ts, actual_ts })
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.reader
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use symphonia_core::codecs::CODEC_TYPE_WMA;
+    use symphonia_core::formats::{FormatReader, SeekMode, SeekTo};
+    use symphonia_core::io::MediaSourceStream;
+    use symphonia_core::meta::StandardTagKey;
+
+    use super::AsfReader;
+    use crate::objects::*;
+
+    const PACKET_LEN: usize = 64;
+
+    fn utf16(s: &str) -> Vec<u8> {
+        s.encode_utf16().chain(Some(0)).flat_map(|c| c.to_le_bytes()).collect()
+    }
+
+    fn object(guid: Guid, data: &[u8]) -> Vec<u8> {
+        let mut buf = guid.to_vec();
+        buf.extend_from_slice(&(OBJECT_HEADER_LEN + data.len() as u64).to_le_bytes());
+        buf.extend_from_slice(data);
+        buf
+    }
+
+    fn file_properties(num_packets: u64) -> Vec<u8> {
+        let mut buf = vec![0; 16 + 8 + 8];
+        buf.extend_from_slice(&num_packets.to_le_bytes());
+        buf.extend_from_slice(&(6_000 * 10_000u64).to_le_bytes()); // Play duration.
+        buf.extend_from_slice(&0u64.to_le_bytes()); // Send duration.
+        buf.extend_from_slice(&3000u64.to_le_bytes()); // Preroll.
+        buf.extend_from_slice(&0x2u32.to_le_bytes()); // Flags: seekable.
+        buf.extend_from_slice(&(PACKET_LEN as u32).to_le_bytes());
+        buf.extend_from_slice(&(PACKET_LEN as u32).to_le_bytes());
+        buf.extend_from_slice(&128_000u32.to_le_bytes());
+        object(FILE_PROPERTIES_OBJECT, &buf)
+    }
+
+    fn stream_properties() -> Vec<u8> {
+        // WMA version 2, stereo, 44.1 kHz.
+        let mut wave_format = vec![0x61, 0x01, 2, 0];
+        wave_format.extend_from_slice(&44_100u32.to_le_bytes());
+        wave_format.extend_from_slice(&16_000u32.to_le_bytes());
+        wave_format.extend_from_slice(&[0xe7, 0x05, 16, 0, 10, 0]);
+        wave_format.extend_from_slice(&[0; 10]);
+
+        let mut buf = AUDIO_MEDIA.to_vec();
+        buf.extend_from_slice(&[0; 16]); // No error correction.
+        buf.extend_from_slice(&0u64.to_le_bytes());
+        buf.extend_from_slice(&(wave_format.len() as u32).to_le_bytes());
+        buf.extend_from_slice(&0u32.to_le_bytes());
+        buf.extend_from_slice(&1u16.to_le_bytes()); // Stream number 1.
+        buf.extend_from_slice(&0u32.to_le_bytes());
+        buf.extend_from_slice(&wave_format);
+        object(STREAM_PROPERTIES_OBJECT, &buf)
+    }
+
+    fn extended_content_description() -> Vec<u8> {
+        let mut buf = 1u16.to_le_bytes().to_vec();
+        let name = utf16("WM/AlbumTitle");
+        let value = utf16("Album");
+        buf.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        buf.extend_from_slice(&name);
+        buf.extend_from_slice(&0u16.to_le_bytes());
+        buf.extend_from_slice(&(value.len() as u16).to_le_bytes());
+        buf.extend_from_slice(&value);
+        object(EXTENDED_CONTENT_DESCRIPTION_OBJECT, &buf)
+    }
+
+    /// Write a data packet carrying one payload of stream 1.
+    fn data_packet(
+        send_time: u32,
+        obj: u8,
+        offset: u32,
+        obj_len: u32,
+        pres: u32,
+        data: &[u8],
+    ) -> Vec<u8> {
+        // Length type flags: byte padding length. Property flags: byte replicated data length,
+        // dword offset, and byte media object number.
+        let mut buf = vec![0x82, 0, 0, 0x08, 0x5d];
+        let header_len = buf.len() + 1 + 4 + 2 + 1 + 1 + 4 + 1 + 8;
+        buf.push((PACKET_LEN - header_len - data.len()) as u8);
+        buf.extend_from_slice(&send_time.to_le_bytes());
+        buf.extend_from_slice(&0u16.to_le_bytes());
+        buf.extend_from_slice(&[0x81, obj]);
+        buf.extend_from_slice(&offset.to_le_bytes());
+        buf.push(8);
+        buf.extend_from_slice(&obj_len.to_le_bytes());
+        buf.extend_from_slice(&pres.to_le_bytes());
+        buf.extend_from_slice(data);
+        buf.resize(PACKET_LEN, 0);
+        buf
+    }
+
+    fn asf() -> Vec<u8> {
+        let packets = [
+            // A media object split over two data packets.
+            data_packet(0, 0, 0, 40, 3000, &[0xaa; 20]),
+            data_packet(0, 0, 20, 40, 3000, &[0xaa; 20]),
+            data_packet(2000, 1, 0, 30, 5000, &[0xbb; 30]),
+            data_packet(2500, 2, 0, 30, 5500, &[0xcc; 30]),
+        ];
+
+        let mut header = 3u32.to_le_bytes().to_vec();
+        header.extend_from_slice(&[1, 2]);
+        header.extend_from_slice(&file_properties(packets.len() as u64));
+        header.extend_from_slice(&stream_properties());
+        header.extend_from_slice(&extended_content_description());
+
+        let mut data = vec![0; 16];
+        data.extend_from_slice(&(packets.len() as u64).to_le_bytes());
+        data.extend_from_slice(&[1, 1]);
+        data.extend(packets.iter().flatten());
+
+        let mut asf = object(HEADER_OBJECT, &header);
+        asf.extend_from_slice(&object(DATA_OBJECT, &data));
+        asf
+    }
+
+    #[test]
+    fn verify_asf_reader() {
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(asf())), Default::default());
+        let mut reader = AsfReader::try_new(mss, &Default::default()).unwrap();
+
+        let track = &reader.tracks()[0];
+        assert_eq!(track.id, 1);
+        assert_eq!(track.codec_params.codec, CODEC_TYPE_WMA);
+        assert_eq!(track.codec_params.sample_rate, Some(44_100));
+        assert_eq!(track.codec_params.n_frames, Some(3000));
+        assert_eq!(track.codec_params.extra_data.as_ref().map(|d| d.len()), Some(28));
+
+        let metadata = reader.metadata();
+        let tags = metadata.current().unwrap().tags();
+        assert_eq!(tags[0].std_key, Some(StandardTagKey::Album));
+        assert_eq!(tags[0].value.to_string(), "Album");
+
+        let packet = reader.next_packet().unwrap();
+        assert_eq!((packet.ts, packet.dur, packet.data.len()), (0, 2000, 40));
+
+        let packet = reader.next_packet().unwrap();
+        assert_eq!((packet.ts, packet.dur), (2000, 500));
+
+        // The duration of the last media object is the remainder of the file.
+        let packet = reader.next_packet().unwrap();
+        assert_eq!((packet.ts, packet.dur, packet.data[0]), (2500, 500, 0xcc));
+        assert!(reader.next_packet().is_err());
+
+        let seeked = reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 2200, track_id: 1 });
+        assert_eq!(seeked.unwrap().actual_ts, 2000);
+        assert_eq!(reader.next_packet().unwrap().data[0], 0xbb);
+
+        // The first media object was sent before the data packet found by send time.
+        let seeked = reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 1000, track_id: 1 });
+        assert_eq!(seeked.unwrap().actual_ts, 0);
+        assert_eq!(reader.next_packet().unwrap().data.len(), 40);
+    }
+}
@@ -0,0 +1,484 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::errors::{decode_error, Result};
+use symphonia_core::io::{BufReader, FiniteStream, ReadBytes};
+use symphonia_core::meta::{MetadataBuilder, Value};
+
+use symphonia_metadata::asf;
+
+use log::warn;
+
+/// A GUID in its on-disk byte order.
+pub type Guid = [u8; 16];
+
+/// Header Object, 75B22630-668E-11CF-A6D9-00AA0062CE6C.
+pub const HEADER_OBJECT: Guid = [
+    0x30, 0x26, 0xb2, 0x75, 0x8e, 0x66, 0xcf, 0x11, 0xa6, 0xd9, 0x00, 0xaa, 0x00, 0x62, 0xce, 0x6c,
+];
+
+/// Data Object, 75B22636-668E-11CF-A6D9-00AA0062CE6C.
+pub const DATA_OBJECT: Guid = [
+    0x36, 0x26, 0xb2, 0x75, 0x8e, 0x66, 0xcf, 0x11, 0xa6, 0xd9, 0x00, 0xaa, 0x00, 0x62, 0xce, 0x6c,
+];
+
+/// File Properties Object, 8CABDCA1-A947-11CF-8EE4-00C00C205365.
+pub const FILE_PROPERTIES_OBJECT: Guid = [
+    0xa1, 0xdc, 0xab, 0x8c, 0x47, 0xa9, 0xcf, 0x11, 0x8e, 0xe4, 0x00, 0xc0, 0x0c, 0x20, 0x53, 0x65,
+];
+
+/// Stream Properties Object, B7DC0791-A9B7-11CF-8EE6-00C00C205365.
+pub const STREAM_PROPERTIES_OBJECT: Guid = [
+    0x91, 0x07, 0xdc, 0xb7, 0xb7, 0xa9, 0xcf, 0x11, 0x8e, 0xe6, 0x00, 0xc0, 0x0c, 0x20, 0x53, 0x65,
+];
+
+/// Header Extension Object, 5FBF03B5-A92E-11CF-8EE3-00C00C205365.
+pub const HEADER_EXTENSION_OBJECT: Guid = [
+    0xb5, 0x03, 0xbf, 0x5f, 0x2e, 0xa9, 0xcf, 0x11, 0x8e, 0xe3, 0x00, 0xc0, 0x0c, 0x20, 0x53, 0x65,
+];
+
+/// Content Description Object, 75B22633-668E-11CF-A6D9-00AA0062CE6C.
+pub const CONTENT_DESCRIPTION_OBJECT: Guid = [
+    0x33, 0x26, 0xb2, 0x75, 0x8e, 0x66, 0xcf, 0x11, 0xa6, 0xd9, 0x00, 0xaa, 0x00, 0x62, 0xce, 0x6c,
+];
+
+/// Extended Content Description Object, D2D0A440-E307-11D2-97F0-00A0C95EA850.
+pub const EXTENDED_CONTENT_DESCRIPTION_OBJECT: Guid = [
+    0x40, 0xa4, 0xd0, 0xd2, 0x07, 0xe3, 0xd2, 0x11, 0x97, 0xf0, 0x00, 0xa0, 0xc9, 0x5e, 0xa8, 0x50,
+];
+
+/// Extended Stream Properties Object, 14E6A5CB-C672-4332-8399-A96952065B5A.
+pub const EXTENDED_STREAM_PROPERTIES_OBJECT: Guid = [
+    0xcb, 0xa5, 0xe6, 0x14, 0x72, 0xc6, 0x32, 0x43, 0x83, 0x99, 0xa9, 0x69, 0x52, 0x06, 0x5b, 0x5a,
+];
+
+/// Metadata Object, C5F8CBEA-5BAF-4877-8467-AA8C44FA4CCA.
+pub const METADATA_OBJECT: Guid = [
+    0xea, 0xcb, 0xf8, 0xc5, 0xaf, 0x5b, 0x77, 0x48, 0x84, 0x67, 0xaa, 0x8c, 0x44, 0xfa, 0x4c, 0xca,
+];
+
+/// Metadata Library Object, 44231C94-9498-49D1-A141-1D134E457054.
+pub const METADATA_LIBRARY_OBJECT: Guid = [
+    0x94, 0x1c, 0x23, 0x44, 0x98, 0x94, 0xd1, 0x49, 0xa1, 0x41, 0x1d, 0x13, 0x4e, 0x45, 0x70, 0x54,
+];
+
+/// Audio Media stream type, F8699E40-5B4D-11CF-A8FD-00805F5C442B.
+pub const AUDIO_MEDIA: Guid = [
+    0x40, 0x9e, 0x69, 0xf8, 0x4d, 0x5b, 0xcf, 0x11, 0xa8, 0xfd, 0x00, 0x80, 0x5f, 0x5c, 0x44, 0x2b,
+];
+
+/// Audio Spread error correction type, BFC3CD50-618F-11CF-8BB2-00AA00B4E220.
+pub const AUDIO_SPREAD: Guid = [
+    0x50, 0xcd, 0xc3, 0xbf, 0x8f, 0x61, 0xcf, 0x11, 0x8b, 0xb2, 0x00, 0xaa, 0x00, 0xb4, 0xe2, 0x20,
+];
+
+/// The length of an object header.
+pub const OBJECT_HEADER_LEN: u64 = 24;
+
+/// The length of the header object, excluding the object header.
+pub const HEADER_OBJECT_FIELDS_LEN: u64 = 6;
+
+/// The length of the data object, excluding the object header and the data packets.
+pub const DATA_OBJECT_FIELDS_LEN: u64 = 26;
+
+/// File properties flag: the file is being broadcast, and the file size, packet count, and
+/// durations are not valid.
+const FILE_FLAG_BROADCAST: u32 = 0x1;
+
+/// The header common to all ASF objects.
+pub struct ObjectHeader {
+    pub guid: Guid,
+    /// The length of the object, including the object header.
+    pub len: u64,
+}
+
+impl ObjectHeader {
+    pub fn read<B: ReadBytes>(reader: &mut B) -> Result<ObjectHeader> {
+        let mut guid = [0; 16];
+        reader.read_buf_exact(&mut guid)?;
+
+        let len = reader.read_u64()?;
+
+        if len < OBJECT_HEADER_LEN {
+            return decode_error("asf: invalid object length");
+        }
+
+        Ok(ObjectHeader { guid, len })
+    }
+
+    /// Gets the length of the object, excluding the object header.
+    pub fn data_len(&self) -> u64 {
+        self.len - OBJECT_HEADER_LEN
+    }
+}
+
+/// The file properties object.
+pub struct FileProperties {
+    /// The number of data packets in the data object, if known.
+    pub num_packets: Option<u64>,
+    /// The time needed to play the file, in 100-nanosecond units, if known.
+    pub play_duration: Option<u64>,
+    /// The time to buffer data before playing the file, in milliseconds. Presentation times are
+    /// offset by the preroll.
+    pub preroll: u64,
+    /// The length of every data packet.
+    pub packet_len: u32,
+}
+
+impl FileProperties {
+    pub fn read(buf: &[u8]) -> Result<FileProperties> {
+        let mut reader = BufReader::new(buf);
+
+        // File ID, file size, and creation date.
+        reader.ignore_bytes(16 + 8 + 8)?;
+
+        let num_packets = reader.read_u64()?;
+        let play_duration = reader.read_u64()?;
+        let _send_duration = reader.read_u64()?;
+        let preroll = reader.read_u64()?;
+        let flags = reader.read_u32()?;
+        let min_packet_len = reader.read_u32()?;
+        let max_packet_len = reader.read_u32()?;
+        let _max_bitrate = reader.read_u32()?;
+
+        // Data packets are always the same length.
+        if min_packet_len != max_packet_len || min_packet_len == 0 {
+            return decode_error("asf: invalid data packet length");
+        }
+
+        let is_broadcast = flags & FILE_FLAG_BROADCAST != 0;
+
+        Ok(FileProperties {
+            num_packets: if is_broadcast { None } else { Some(num_packets) },
+            play_duration: if is_broadcast { None } else { Some(play_duration) },
+            preroll,
+            packet_len: min_packet_len,
+        })
+    }
+}
+
+/// The waveform audio format (WAVEFORMATEX) of an audio stream.
+pub struct AudioFormat {
+    pub format_tag: u16,
+    pub n_channels: u16,
+    pub sample_rate: u32,
+    pub avg_bytes_per_sec: u32,
+    pub bits_per_sample: u16,
+    /// The complete waveform audio format structure, including any codec specific data.
+    pub buf: Box<[u8]>,
+}
+
+impl AudioFormat {
+    fn read(buf: &[u8]) -> Result<AudioFormat> {
+        let mut reader = BufReader::new(buf);
+
+        let format_tag = reader.read_u16()?;
+        let n_channels = reader.read_u16()?;
+        let sample_rate = reader.read_u32()?;
+        let avg_bytes_per_sec = reader.read_u32()?;
+        let _block_align = reader.read_u16()?;
+        let bits_per_sample = reader.read_u16()?;
+
+        // The size of the codec specific data is optional for linear PCM.
+        if reader.bytes_available() >= 2 {
+            let len = u64::from(reader.read_u16()?);
+
+            if len > reader.bytes_available() {
+                return decode_error("asf: invalid audio format extra data length");
+            }
+        }
+
+        if n_channels == 0 || sample_rate == 0 {
+            return decode_error("asf: invalid audio format");
+        }
+
+        Ok(AudioFormat {
+            format_tag,
+            n_channels,
+            sample_rate,
+            avg_bytes_per_sec,
+            bits_per_sample,
+            buf: Box::from(buf),
+        })
+    }
+}
+
+/// The audio spread error correction parameters. Audio spread interleaves the chunks of
+/// consecutive media objects to reduce the impact of a lost data packet.
+#[derive(Copy, Clone)]
+pub struct AudioSpread {
+    /// The number of virtual packets the chunks are spread over.
+    pub span: u8,
+    /// The length of a virtual packet.
+    pub packet_len: u16,
+    /// The length of a chunk.
+    pub chunk_len: u16,
+}
+
+impl AudioSpread {
+    fn read(buf: &[u8]) -> Result<AudioSpread> {
+        let mut reader = BufReader::new(buf);
+
+        let span = reader.read_u8()?;
+        let packet_len = reader.read_u16()?;
+        let chunk_len = reader.read_u16()?;
+
+        Ok(AudioSpread { span, packet_len, chunk_len })
+    }
+
+    /// Returns true if the media objects must be descrambled.
+    pub fn is_scrambled(&self) -> bool {
+        self.span > 1 && self.chunk_len > 0 && self.packet_len % self.chunk_len == 0
+    }
+}
+
+/// An audio stream described by a stream properties object.
+pub struct AudioStream {
+    /// The stream number, from 1 to 127.
+    pub number: u8,
+    pub format: AudioFormat,
+    pub spread: Option<AudioSpread>,
+}
+
+/// Read a stream properties object. Returns `None` if the stream is not an audio stream.
+pub fn read_stream_properties(buf: &[u8]) -> Result<Option<AudioStream>> {
+    let mut reader = BufReader::new(buf);
+
+    let mut stream_type = [0; 16];
+    reader.read_buf_exact(&mut stream_type)?;
+
+    let mut error_correction_type = [0; 16];
+    reader.read_buf_exact(&mut error_correction_type)?;
+
+    let _time_offset = reader.read_u64()?;
+    let type_specific_len = reader.read_u32()? as usize;
+    let error_correction_len = reader.read_u32()? as usize;
+    let flags = reader.read_u16()?;
+    let _reserved = reader.read_u32()?;
+
+    if stream_type != AUDIO_MEDIA {
+        return Ok(None);
+    }
+
+    let number = (flags & 0x7f) as u8;
+
+    if number == 0 {
+        return decode_error("asf: invalid stream number");
+    }
+
+    if type_specific_len + error_correction_len > reader.bytes_available() as usize {
+        return decode_error("asf: invalid stream properties object length");
+    }
+
+    let format = AudioFormat::read(reader.read_buf_bytes_ref(type_specific_len)?)?;
+
+    let spread = if error_correction_type == AUDIO_SPREAD {
+        Some(AudioSpread::read(reader.read_buf_bytes_ref(error_correction_len)?)?)
+    }
+    else {
+        None
+    };
+
+    Ok(Some(AudioStream { number, format, spread }))
+}
+
+/// Read an extended stream properties object. Returns the embedded stream properties object, if
+/// present.
+fn read_extended_stream_properties(buf: &[u8]) -> Result<Option<AudioStream>> {
+    let mut reader = BufReader::new(buf);
+
+    // Start and end times, bitrates, buffer sizes, maximum object size, flags, stream number,
+    // language index, and average time per frame.
+    reader.ignore_bytes(8 + 8 + 4 + 4 + 4 + 4 + 4 + 4 + 4 + 4 + 2 + 2 + 8)?;
+
+    let num_stream_names = reader.read_u16()?;
+    let num_payload_ext_systems = reader.read_u16()?;
+
+    for _ in 0..num_stream_names {
+        let _language_index = reader.read_u16()?;
+        let len = reader.read_u16()?;
+        reader.ignore_bytes(u64::from(len))?;
+    }
+
+    for _ in 0..num_payload_ext_systems {
+        // Extension system ID and data size.
+        reader.ignore_bytes(16 + 2)?;
+        let len = reader.read_u32()?;
+        reader.ignore_bytes(u64::from(len))?;
+    }
+
+    if reader.bytes_available() == 0 {
+        return Ok(None);
+    }
+
+    let header = ObjectHeader::read(&mut reader)?;
+
+    if header.guid != STREAM_PROPERTIES_OBJECT || header.data_len() > reader.bytes_available() {
+        return decode_error("asf: invalid extended stream properties object");
+    }
+
+    read_stream_properties(reader.read_buf_bytes_ref(header.data_len() as usize)?)
+}
+
+/// Read a header extension object. Audio streams described by extended stream properties
+/// objects are added to the list of streams, and metadata objects are read into the metadata
+/// builder.
+pub fn read_header_extension(
+    buf: &[u8],
+    streams: &mut Vec<AudioStream>,
+    builder: &mut MetadataBuilder,
+) -> Result<()> {
+    let mut reader = BufReader::new(buf);
+
+    // Reserved GUID and reserved field.
+    reader.ignore_bytes(16 + 2)?;
+
+    let len = u64::from(reader.read_u32()?);
+
+    if len > reader.bytes_available() {
+        return decode_error("asf: invalid header extension object length");
+    }
+
+    while reader.bytes_available() >= OBJECT_HEADER_LEN {
+        let header = ObjectHeader::read(&mut reader)?;
+
+        if header.data_len() > reader.bytes_available() {
+            return decode_error("asf: invalid header extension object length");
+        }
+
+        let buf = reader.read_buf_bytes_ref(header.data_len() as usize)?;
+
+        match header.guid {
+            EXTENDED_STREAM_PROPERTIES_OBJECT => {
+                if let Some(stream) = read_extended_stream_properties(buf)? {
+                    streams.push(stream);
+                }
+            }
+            METADATA_OBJECT | METADATA_LIBRARY_OBJECT => read_metadata(buf, builder)?,
+            _ => (),
+        }
+    }
+
+    Ok(())
+}
+
+/// Read a UTF-16LE string of the given length in bytes.
+fn read_utf16_string(reader: &mut BufReader<'_>, len: usize) -> Result<String> {
+    Ok(asf::decode_utf16_string(reader.read_buf_bytes_ref(len)?))
+}
+
+/// Read a content description object.
+pub fn read_content_description(buf: &[u8], builder: &mut MetadataBuilder) -> Result<()> {
+    let mut reader = BufReader::new(buf);
+
+    let mut lens = [0; 5];
+
+    for len in lens.iter_mut() {
+        *len = reader.read_u16()? as usize;
+    }
+
+    for (&key, &len) in ["Title", "Author", "Copyright", "Description", "Rating"].iter().zip(&lens)
+    {
+        let value = read_utf16_string(&mut reader, len)?;
+
+        if !value.is_empty() {
+            builder.add_tag(asf::parse(key, Value::from(value)));
+        }
+    }
+
+    Ok(())
+}
+
+/// Read the value of a descriptor or metadata record of the given data type.
+fn read_value(reader: &mut BufReader<'_>, data_type: u16, len: usize) -> Result<Option<Value>> {
+    let buf = reader.read_buf_bytes_ref(len)?;
+
+    let value = match (data_type, buf.len()) {
+        (0, _) => Value::from(asf::decode_utf16_string(buf)),
+        (1, _) => Value::from(buf),
+        // Booleans are 4 bytes in the extended content description object, but 2 bytes in the
+        // metadata objects.
+        (2, 2) | (2, 4) => Value::from(buf[0] != 0),
+        (3, 4) => Value::from(u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]])),
+        (4, 8) => {
+            let mut qword = [0; 8];
+            qword.copy_from_slice(buf);
+            Value::from(u64::from_le_bytes(qword))
+        }
+        (5, 2) => Value::from(u16::from_le_bytes([buf[0], buf[1]])),
+        // GUIDs, and values of an unexpected length, are ignored.
+        _ => return Ok(None),
+    };
+
+    Ok(Some(value))
+}
+
+/// Add a named value to the metadata builder. Pictures are added as visuals, and all other
+/// values are added as tags.
+fn add_value(builder: &mut MetadataBuilder, name: &str, value: Value) {
+    match value {
+        Value::Binary(buf) if name.eq_ignore_ascii_case("WM/Picture") => {
+            match asf::parse_picture(&buf) {
+                Ok(visual) => {
+                    builder.add_visual(visual);
+                }
+                Err(err) => warn!("asf: invalid picture: {}", err),
+            }
+        }
+        value => {
+            builder.add_tag(asf::parse(name, value));
+        }
+    }
+}
+
+/// Read an extended content description object.
+pub fn read_extended_content_description(buf: &[u8], builder: &mut MetadataBuilder) -> Result<()> {
+    let mut reader = BufReader::new(buf);
+
+    let count = reader.read_u16()?;
+
+    for _ in 0..count {
+        let name_len = reader.read_u16()? as usize;
+        let name = read_utf16_string(&mut reader, name_len)?;
+
+        let data_type = reader.read_u16()?;
+        let len = reader.read_u16()? as usize;
+
+        if let Some(value) = read_value(&mut reader, data_type, len)? {
+            add_value(builder, &name, value);
+        }
+    }
+
+    Ok(())
+}
+
+/// Read a metadata object, or a metadata library object. Both use the same layout.
+fn read_metadata(buf: &[u8], builder: &mut MetadataBuilder) -> Result<()> {
+    let mut reader = BufReader::new(buf);
+
+    let count = reader.read_u16()?;
+
+    for _ in 0..count {
+        let _language_index = reader.read_u16()?;
+        let _stream_number = reader.read_u16()?;
+        let name_len = reader.read_u16()? as usize;
+        let data_type = reader.read_u16()?;
+        let len = reader.read_u32()? as usize;
+
+        let name = read_utf16_string(&mut reader, name_len)?;
+
+        if len > reader.bytes_available() as usize {
+            return decode_error("asf: invalid metadata record length");
+        }
+
+        if let Some(value) = read_value(&mut reader, data_type, len)? {
+            add_value(builder, &name, value);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,273 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::errors::{decode_error, unsupported_error, Result};
+use symphonia_core::io::{BufReader, ReadBytes};
+
+/// Error correction flag: error correction data is present.
+const EC_FLAG_PRESENT: u8 = 0x80;
+
+/// Error correction flag mask: the error correction data length type. Only 0 is valid.
+const EC_FLAG_LENGTH_TYPE_MASK: u8 = 0x60;
+
+/// Error correction flag mask: the length of the error correction data.
+const EC_FLAG_DATA_LEN_MASK: u8 = 0x0f;
+
+/// Length type flag: the data packet contains multiple payloads.
+const LT_FLAG_MULTIPLE_PAYLOADS: u8 = 0x1;
+
+/// The replicated data length that indicates the payload is compressed.
+const COMPRESSED_REPLICATED_DATA_LEN: u32 = 1;
+
+/// All, or part of, a media object carried by a data packet.
+pub struct Payload<'a> {
+    /// The stream number.
+    pub stream: u8,
+    /// The media object number. Wraps around after 255.
+    pub object_num: u8,
+    /// The offset of the payload data in the media object.
+    pub offset: u32,
+    /// The total length of the media object.
+    pub object_len: u32,
+    /// The presentation time of the media object in milliseconds, including the preroll.
+    pub pres_time: u32,
+    pub data: &'a [u8],
+}
+
+/// A parsed data packet.
+pub struct DataPacket<'a> {
+    /// The time at which the packet should be delivered, in milliseconds.
+    pub send_time: u32,
+    pub payloads: Vec<Payload<'a>>,
+}
+
+/// Read a field which has a length given by a 2-bit length type. A length type of 0 indicates
+/// the field is not present.
+fn read_field(reader: &mut BufReader<'_>, length_type: u8) -> Result<u32> {
+    let value = match length_type & 0x3 {
+        0 => 0,
+        1 => u32::from(reader.read_u8()?),
+        2 => u32::from(reader.read_u16()?),
+        _ => reader.read_u32()?,
+    };
+
+    Ok(value)
+}
+
+/// The header of a payload.
+struct PayloadHeader {
+    stream: u8,
+    object_num: u8,
+    offset: u32,
+    replicated_data_len: u32,
+}
+
+/// Read the payload header, and replicated data, of a payload.
+fn read_payload_header<'a>(
+    reader: &mut BufReader<'a>,
+    property_flags: u8,
+) -> Result<(PayloadHeader, &'a [u8])> {
+    // The most-significant bit is the key frame flag.
+    let stream = reader.read_u8()? & 0x7f;
+
+    // Media object numbers are truncated to their least-significant byte since that is all that
+    // is required to match the payloads of a media object.
+    let object_num = read_field(reader, property_flags >> 4)? as u8;
+    let offset = read_field(reader, property_flags >> 2)?;
+    let replicated_data_len = read_field(reader, property_flags)?;
+
+    let replicated_data = reader.read_buf_bytes_ref(replicated_data_len as usize)?;
+
+    Ok((PayloadHeader { stream, object_num, offset, replicated_data_len }, replicated_data))
+}
+
+/// Add a payload to the list of payloads. A compressed payload is expanded into one complete
+/// payload per media object.
+fn push_payload<'a>(
+    header: &PayloadHeader,
+    replicated_data: &[u8],
+    data: &'a [u8],
+    payloads: &mut Vec<Payload<'a>>,
+) -> Result<()> {
+    if header.replicated_data_len == COMPRESSED_REPLICATED_DATA_LEN {
+        // For a compressed payload, the offset field is the presentation time of the first media
+        // object, and the replicated data is the presentation time delta between media objects.
+        let delta = u32::from(replicated_data[0]);
+
+        let mut reader = BufReader::new(data);
+        let mut i = 0u32;
+
+        while reader.pos() < data.len() as u64 {
+            let len = reader.read_u8()?;
+            let data = reader.read_buf_bytes_ref(usize::from(len))?;
+
+            payloads.push(Payload {
+                stream: header.stream,
+                object_num: header.object_num.wrapping_add(i as u8),
+                offset: 0,
+                object_len: u32::from(len),
+                pres_time: header.offset.wrapping_add(i * delta),
+                data,
+            });
+
+            i += 1;
+        }
+    }
+    else if header.replicated_data_len >= 8 {
+        // The replicated data starts with the media object length and presentation time. It may
+        // be followed by payload extension data, which is ignored.
+        let r = replicated_data;
+
+        let object_len = u32::from_le_bytes([r[0], r[1], r[2], r[3]]);
+        let pres_time = u32::from_le_bytes([r[4], r[5], r[6], r[7]]);
+
+        payloads.push(Payload {
+            stream: header.stream,
+            object_num: header.object_num,
+            offset: header.offset,
+            object_len,
+            pres_time,
+            data,
+        });
+    }
+    else {
+        return decode_error("asf: invalid replicated data length");
+    }
+
+    Ok(())
+}
+
+/// Read a data packet.
+pub fn read_data_packet(buf: &[u8]) -> Result<DataPacket<'_>> {
+    let mut reader = BufReader::new(buf);
+
+    let mut flags = reader.read_u8()?;
+
+    // The optional error correction data precedes the payload parsing information. If it is
+    // present, the first byte is the error correction flags instead of the length type flags.
+    if flags & EC_FLAG_PRESENT != 0 {
+        if flags & EC_FLAG_LENGTH_TYPE_MASK != 0 {
+            return unsupported_error("asf: unsupported error correction data length type");
+        }
+
+        reader.ignore_bytes(u64::from(flags & EC_FLAG_DATA_LEN_MASK))?;
+
+        flags = reader.read_u8()?;
+    }
+
+    let property_flags = reader.read_u8()?;
+
+    let packet_len = read_field(&mut reader, flags >> 5)? as usize;
+    let _sequence = read_field(&mut reader, flags >> 1)?;
+    let padding_len = read_field(&mut reader, flags >> 3)? as usize;
+    let send_time = reader.read_u32()?;
+    let _duration = reader.read_u16()?;
+
+    // If the packet length is present, and less than the length of a data packet, the remainder
+    // is additional padding.
+    let len = if packet_len > 0 { packet_len.min(buf.len()) } else { buf.len() };
+
+    let end = match len.checked_sub(padding_len) {
+        Some(end) if end >= reader.pos() as usize => end,
+        _ => return decode_error("asf: invalid data packet padding length"),
+    };
+
+    let mut payloads = Vec::new();
+
+    if flags & LT_FLAG_MULTIPLE_PAYLOADS == 0 {
+        let (header, replicated_data) = read_payload_header(&mut reader, property_flags)?;
+
+        let data_len = match end.checked_sub(reader.pos() as usize) {
+            Some(data_len) => data_len,
+            None => return decode_error("asf: invalid payload length"),
+        };
+
+        let data = reader.read_buf_bytes_ref(data_len)?;
+
+        push_payload(&header, replicated_data, data, &mut payloads)?;
+    }
+    else {
+        let payload_flags = reader.read_u8()?;
+
+        let num_payloads = payload_flags & 0x3f;
+
+        for _ in 0..num_payloads {
+            let (header, replicated_data) = read_payload_header(&mut reader, property_flags)?;
+
+            let data_len = read_field(&mut reader, payload_flags >> 6)? as usize;
+
+            if reader.pos() as usize + data_len > end {
+                return decode_error("asf: invalid payload length");
+            }
+
+            let data = reader.read_buf_bytes_ref(data_len)?;
+
+            push_payload(&header, replicated_data, data, &mut payloads)?;
+        }
+    }
+
+    Ok(DataPacket { send_time, payloads })
+}
+
+/// Descramble a media object interleaved by audio spread error correction.
+///
+/// The media object is made up of `span` virtual packets, each made up of equal length chunks.
+/// The chunks are stored column-wise, such that consecutive chunks of a virtual packet are
+/// `span` chunks apart.
+pub fn descramble(buf: &[u8], span: usize, packet_len: usize, chunk_len: usize) -> Vec<u8> {
+    let chunks_per_packet = packet_len / chunk_len;
+
+    let mut out = Vec::with_capacity(buf.len());
+
+    for i in 0..buf.len() / chunk_len {
+        let row = i / span;
+        let col = i % span;
+
+        let src = (row + col * chunks_per_packet) * chunk_len;
+
+        out.extend_from_slice(&buf[src..src + chunk_len]);
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{descramble, read_data_packet};
+
+    #[test]
+    fn verify_descramble() {
+        // Two virtual packets of two chunks, stored column-wise.
+        let buf = [0, 0, 2, 2, 1, 1, 3, 3];
+        assert_eq!(descramble(&buf, 2, 4, 2), [0, 0, 1, 1, 2, 2, 3, 3]);
+    }
+
+    #[test]
+    fn verify_read_compressed_payload() {
+        let mut buf = vec![
+            0x82, 0x00, 0x00, // Error correction data.
+            0x08, // Length type flags: byte padding length.
+            0x5d, // Property flags: byte replicated data, dword offset, byte object number.
+            0x02, // Padding length.
+        ];
+        buf.extend_from_slice(&1000u32.to_le_bytes()); // Send time.
+        buf.extend_from_slice(&100u16.to_le_bytes()); // Duration.
+        buf.extend_from_slice(&[0x81, 7]); // Stream number and media object number.
+        buf.extend_from_slice(&3000u32.to_le_bytes()); // Presentation time.
+        buf.extend_from_slice(&[1, 40]); // Replicated data length and time delta.
+        buf.extend_from_slice(&[2, 0xaa, 0xaa, 1, 0xbb]); // Sub-payloads.
+        buf.extend_from_slice(&[0, 0]); // Padding.
+
+        let packet = read_data_packet(&buf).unwrap();
+        assert_eq!(packet.send_time, 1000);
+        assert_eq!(packet.payloads.len(), 2);
+
+        let payload = &packet.payloads[1];
+        assert_eq!((payload.stream, payload.object_num), (1, 8));
+        assert_eq!((payload.pres_time, payload.data), (3040, &[0xbb][..]));
+    }
+}
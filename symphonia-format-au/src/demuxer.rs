@@ -0,0 +1,470 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::io::{Seek, SeekFrom};
+
+use symphonia_core::audio::Channels;
+use symphonia_core::codecs::*;
+use symphonia_core::errors::{decode_error, end_of_stream_error, seek_error, unsupported_error};
+use symphonia_core::errors::{Result, SeekErrorKind};
+use symphonia_core::formats::prelude::*;
+use symphonia_core::io::*;
+use symphonia_core::meta::{Metadata, MetadataBuilder, MetadataLog, StandardTagKey, Tag, Value};
+use symphonia_core::probe::{Descriptor, Instantiate, QueryDescriptor};
+use symphonia_core::support_format;
+
+use log::debug;
+
+/// The magic number that starts every AU file.
+const AU_STREAM_MARKER: [u8; 4] = *b".snd";
+
+/// The length of the fixed part of the header.
+const AU_HEADER_LEN: u32 = 24;
+
+/// The value of the data size field if the size of the audio data is unknown.
+const AU_UNKNOWN_DATA_SIZE: u32 = 0xffff_ffff;
+
+/// The maximum length of the annotation field that will be read.
+const AU_MAX_ANNOTATION_LEN: u32 = 64 * 1024;
+
+/// The maximum number of frames that will be in a packet.
+const AU_MAX_FRAMES_PER_PACKET: u64 = 1152;
+
+/// Gets the codec, and number of bits per coded sample, for an AU encoding.
+fn codec_from_encoding(encoding: u32) -> Result<(CodecType, u32)> {
+    let codec = match encoding {
+        1 => (CODEC_TYPE_PCM_MULAW, 8),
+        2 => (CODEC_TYPE_PCM_S8, 8),
+        3 => (CODEC_TYPE_PCM_S16BE, 16),
+        4 => (CODEC_TYPE_PCM_S24BE, 24),
+        5 => (CODEC_TYPE_PCM_S32BE, 32),
+        6 => (CODEC_TYPE_PCM_F32BE, 32),
+        7 => (CODEC_TYPE_PCM_F64BE, 64),
+        23 => (CODEC_TYPE_ADPCM_G726LE, 4),
+        25 => (CODEC_TYPE_ADPCM_G726LE, 3),
+        26 => (CODEC_TYPE_ADPCM_G726LE, 5),
+        27 => (CODEC_TYPE_PCM_ALAW, 8),
+        _ => return unsupported_error("au: unsupported encoding"),
+    };
+
+    Ok(codec)
+}
+
+/// Gets the channels for a channel count. AU files do not define a channel layout.
+fn channels_from_count(count: u32) -> Result<Channels> {
+    let channels = match count {
+        1..=32 => Channels::from_bits(u32::MAX >> (32 - count)),
+        _ => None,
+    };
+
+    match channels {
+        Some(channels) => Ok(channels),
+        None => unsupported_error("au: unsupported number of channels"),
+    }
+}
+
+/// Gets the standard tag key for a key in the annotation field.
+fn std_key_from_annotation_key(key: &str) -> Option<StandardTagKey> {
+    let std_key = match key.to_ascii_lowercase().as_str() {
+        "title" => StandardTagKey::TrackTitle,
+        "artist" => StandardTagKey::Artist,
+        "album" => StandardTagKey::Album,
+        "track" => StandardTagKey::TrackNumber,
+        "genre" => StandardTagKey::Genre,
+        "comment" => StandardTagKey::Comment,
+        _ => return None,
+    };
+
+    Some(std_key)
+}
+
+/// Read the annotation field into tags.
+///
+/// The annotation is free-form text. However, some encoders write a list of newline separated
+/// `key=value` pairs. If every line is a pair, each pair is a tag, otherwise, the whole annotation
+/// is a comment.
+fn read_annotation(buf: &[u8], builder: &mut MetadataBuilder) {
+    // The annotation is null-terminated, and may be padded with more nulls.
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+
+    let text = String::from_utf8_lossy(&buf[..len]);
+    let text = text.trim();
+
+    if text.is_empty() {
+        return;
+    }
+
+    let pairs = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.split_once('='))
+        .collect::<Option<Vec<_>>>();
+
+    match pairs {
+        Some(pairs) => {
+            for (key, value) in pairs {
+                let key = key.trim();
+                let value = Value::from(value.trim().to_string());
+                builder.add_tag(Tag::new(std_key_from_annotation_key(key), key, value));
+            }
+        }
+        None => {
+            let value = Value::from(text.to_string());
+            builder.add_tag(Tag::new(Some(StandardTagKey::Comment), "annotation", value));
+        }
+    }
+}
+
+/// Sun/NeXT AU format reader.
+///
+/// `AuReader` implements a demuxer for the AU format.
+pub struct AuReader {
+    reader: MediaSourceStream,
+    tracks: Vec<Track>,
+    cues: Vec<Cue>,
+    metadata: MetadataLog,
+    /// The length of a block of audio frames, all channels, in bytes.
+    block_len: u64,
+    /// The number of audio frames in a block. Only the ADPCM encodings pack more than one frame
+    /// into a block.
+    frames_per_block: u64,
+    max_frames_per_packet: u64,
+    data_start_pos: u64,
+    data_end_pos: u64,
+}
+
+impl QueryDescriptor for AuReader {
+    fn query() -> &'static [Descriptor] {
+        &[support_format!(
+            "au",
+            "Sun/NeXT AU",
+            &["au", "snd"],
+            &["audio/basic", "audio/x-au"],
+            &[b".snd"]
+        )]
+    }
+
+    fn score(_context: &[u8]) -> u8 {
+        255
+    }
+}
+
+impl FormatReader for AuReader {
+    fn try_new(mut source: MediaSourceStream, options: &FormatOptions) -> Result<Self> {
+        // All fields in the header are big-endian.
+        if source.read_quad_bytes()? != AU_STREAM_MARKER {
+            return unsupported_error("au: missing .snd stream marker");
+        }
+
+        let data_offset = source.read_be_u32()?;
+        let data_size = source.read_be_u32()?;
+        let encoding = source.read_be_u32()?;
+        let sample_rate = source.read_be_u32()?;
+        let num_channels = source.read_be_u32()?;
+
+        if data_offset < AU_HEADER_LEN {
+            return decode_error("au: invalid data offset");
+        }
+
+        let (codec, bits_per_sample) = codec_from_encoding(encoding)?;
+
+        let channels = channels_from_count(num_channels)?;
+
+        if sample_rate == 0 {
+            return decode_error("au: invalid sample rate");
+        }
+
+        let mut metadata: MetadataLog = Default::default();
+
+        // The annotation field fills the space between the header and the audio data.
+        let annotation_len = data_offset - AU_HEADER_LEN;
+
+        if annotation_len > 0 {
+            if annotation_len <= AU_MAX_ANNOTATION_LEN {
+                let buf = source.read_boxed_slice_exact(annotation_len as usize)?;
+
+                let mut builder = MetadataBuilder::new();
+
+                read_annotation(&buf, &mut builder);
+
+                metadata.push(builder.metadata());
+            }
+            else {
+                source.ignore_bytes(u64::from(annotation_len))?;
+            }
+        }
+
+        // The ADPCM encodings pack every 8 samples into a whole number of bytes, and are only
+        // defined for mono.
+        let (block_len, frames_per_block) = if codec == CODEC_TYPE_ADPCM_G726LE {
+            if num_channels != 1 {
+                return unsupported_error("au: adpcm encodings must be mono");
+            }
+
+            (u64::from(bits_per_sample), 8)
+        }
+        else {
+            (u64::from(num_channels) * u64::from(bits_per_sample / 8), 1)
+        };
+
+        let data_start_pos = source.pos();
+
+        // If the data size is unknown, the audio data extends to the end of the stream.
+        let data_end_pos = if data_size != AU_UNKNOWN_DATA_SIZE {
+            data_start_pos + u64::from(data_size)
+        }
+        else {
+            source.byte_len().unwrap_or(u64::MAX)
+        };
+
+        let mut max_frames_per_packet = AU_MAX_FRAMES_PER_PACKET;
+
+        if let Some(max_dur) = options.max_packet_duration {
+            max_frames_per_packet = max_frames_per_packet
+                .min(max_dur.as_micros() as u64 * u64::from(sample_rate) / 1_000_000)
+                .max(1);
+        }
+
+        // Packets must contain a whole number of blocks.
+        max_frames_per_packet =
+            (max_frames_per_packet / frames_per_block).max(1) * frames_per_block;
+
+        let mut params = CodecParameters::new();
+
+        params
+            .for_codec(codec)
+            .with_sample_rate(sample_rate)
+            .with_time_base(TimeBase::new(1, sample_rate))
+            .with_channels(channels)
+            .with_max_frames_per_packet(max_frames_per_packet);
+
+        // The companded encodings do not have a bits per sample, and the ADPCM encodings only
+        // have a bits per coded sample.
+        if codec == CODEC_TYPE_ADPCM_G726LE {
+            params.with_bits_per_coded_sample(bits_per_sample);
+        }
+        else if codec != CODEC_TYPE_PCM_MULAW && codec != CODEC_TYPE_PCM_ALAW {
+            params
+                .with_bits_per_sample(bits_per_sample)
+                .with_bits_per_coded_sample(bits_per_sample);
+        }
+
+        if data_end_pos != u64::MAX {
+            params.with_n_frames((data_end_pos - data_start_pos) / block_len * frames_per_block);
+        }
+
+        Ok(AuReader {
+            reader: source,
+            tracks: vec![Track::new(0, params)],
+            cues: Vec::new(),
+            metadata,
+            block_len,
+            frames_per_block,
+            max_frames_per_packet,
+            data_start_pos,
+            data_end_pos,
+        })
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        let pos = self.reader.pos();
+
+        // Determine the number of complete blocks of audio frames remaining.
+        let num_blocks_left =
+            if pos < self.data_end_pos { (self.data_end_pos - pos) / self.block_len } else { 0 };
+
+        if num_blocks_left == 0 {
+            return end_of_stream_error();
+        }
+
+        let num_blocks = num_blocks_left.min(self.max_frames_per_packet / self.frames_per_block);
+
+        let dur = num_blocks * self.frames_per_block;
+
+        let ts = (pos - self.data_start_pos) / self.block_len * self.frames_per_block;
+
+        // If the data size was unknown, the end of the stream is only found by reading past it.
+        let len = (num_blocks * self.block_len) as usize;
+
+        let buf = match self.reader.read_boxed_slice_exact(len) {
+            Ok(buf) => buf,
+            Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => {
+                return end_of_stream_error();
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(Packet::new_from_boxed_slice(0, ts, dur, buf).with_pos(pos))
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        let params = &self.tracks[0].codec_params;
+
+        let required_ts = match to {
+            // Frame timestamp given.
+            SeekTo::TimeStamp { ts, .. } => ts,
+            // Time value given, calculate frame timestamp from sample rate.
+            SeekTo::Time { time, .. } => {
+                // The sample rate is always known.
+                TimeBase::new(1, params.sample_rate.unwrap()).calc_timestamp(time)
+            }
+        };
+
+        if let Some(n_frames) = params.n_frames {
+            if required_ts > n_frames {
+                return seek_error(SeekErrorKind::OutOfRange);
+            }
+        }
+
+        debug!("seeking to frame_ts={}", required_ts);
+
+        // Seek to the start of the block containing the frame.
+        let actual_ts = required_ts / self.frames_per_block * self.frames_per_block;
+
+        let seek_pos = self.data_start_pos + actual_ts / self.frames_per_block * self.block_len;
+
+        // If the reader supports seeking, seek directly to the frame. Otherwise, forward seeks
+        // are emulated by consuming bytes.
+        if self.reader.is_seekable() {
+            self.reader.seek(SeekFrom::Start(seek_pos))?;
+        }
+        else {
+            let current_pos = self.reader.pos();
+
+            if seek_pos >= current_pos {
+                self.reader.ignore_bytes(seek_pos - current_pos)?;
+            }
+            else {
+                return seek_error(SeekErrorKind::ForwardOnly);
+            }
+        }
+
+        Ok(SeekedTo { track_id: 0, actual_ts, required_ts })
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.reader
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use symphonia_core::codecs::{
+        CODEC_TYPE_ADPCM_G726LE, CODEC_TYPE_PCM_MULAW, CODEC_TYPE_PCM_S16BE,
+    };
+    use symphonia_core::formats::{FormatReader, SeekMode, SeekTo};
+    use symphonia_core::io::MediaSourceStream;
+    use symphonia_core::meta::StandardTagKey;
+
+    use super::AuReader;
+
+    fn make_au(
+        data_size: u32,
+        encoding: u32,
+        channels: u32,
+        annotation: &[u8],
+        data: &[u8],
+    ) -> Vec<u8> {
+        let mut au = b".snd".to_vec();
+        for value in &[24 + annotation.len() as u32, data_size, encoding, 8000, channels] {
+            au.extend_from_slice(&value.to_be_bytes());
+        }
+        au.extend_from_slice(annotation);
+        au.extend_from_slice(data);
+        au
+    }
+
+    fn open(au: Vec<u8>) -> AuReader {
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(au)), Default::default());
+        AuReader::try_new(mss, &Default::default()).unwrap()
+    }
+
+    #[test]
+    fn verify_au_reader() {
+        let data: Vec<u8> = (0..2000).map(|i| i as u8).collect();
+        let mut reader = open(make_au(2000, 1, 1, b"title=Title\nartist=Artist\0\0", &data));
+
+        let params = &reader.tracks()[0].codec_params;
+        assert_eq!(params.codec, CODEC_TYPE_PCM_MULAW);
+        assert_eq!(params.sample_rate, Some(8000));
+        assert_eq!(params.n_frames, Some(2000));
+
+        let metadata = reader.metadata();
+        let tags = metadata.current().unwrap().tags();
+        assert_eq!(tags[0].std_key, Some(StandardTagKey::TrackTitle));
+        assert_eq!(tags[0].value.to_string(), "Title");
+        assert_eq!(tags[1].std_key, Some(StandardTagKey::Artist));
+
+        let packet = reader.next_packet().unwrap();
+        assert_eq!((packet.ts, packet.dur), (0, 1152));
+
+        let packet = reader.next_packet().unwrap();
+        assert_eq!((packet.ts, packet.dur), (1152, 848));
+        assert!(reader.next_packet().is_err());
+
+        let seeked = reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 1000, track_id: 0 });
+        assert_eq!(seeked.unwrap().actual_ts, 1000);
+        assert_eq!(reader.next_packet().unwrap().data[0], 1000u32 as u8);
+    }
+
+    #[test]
+    fn verify_au_reader_unknown_size() {
+        // A stereo 16-bit file of unknown length, with a trailing partial frame, and a free-form
+        // annotation.
+        let mut reader = open(make_au(0xffff_ffff, 3, 2, b"Some notes\0\0", &[0; 18]));
+
+        let params = &reader.tracks()[0].codec_params;
+        assert_eq!(params.codec, CODEC_TYPE_PCM_S16BE);
+        assert_eq!(params.bits_per_sample, Some(16));
+        assert_eq!(params.n_frames, Some(4));
+
+        let metadata = reader.metadata();
+        let tags = metadata.current().unwrap().tags();
+        assert_eq!(tags[0].std_key, Some(StandardTagKey::Comment));
+        assert_eq!(tags[0].value.to_string(), "Some notes");
+
+        let packet = reader.next_packet().unwrap();
+        assert_eq!((packet.ts, packet.dur, packet.data.len()), (0, 4, 16));
+        assert!(reader.next_packet().is_err());
+    }
+
+    #[test]
+    fn verify_au_reader_g726() {
+        // A 24 kbit/s G.726 file with a trailing partial block.
+        let mut reader = open(make_au(32, 25, 1, b"\0\0\0\0", &[0; 32]));
+
+        let params = &reader.tracks()[0].codec_params;
+        assert_eq!(params.codec, CODEC_TYPE_ADPCM_G726LE);
+        assert_eq!(params.bits_per_sample, None);
+        assert_eq!(params.bits_per_coded_sample, Some(3));
+        assert_eq!(params.n_frames, Some(80));
+
+        let packet = reader.next_packet().unwrap();
+        assert_eq!((packet.ts, packet.dur, packet.data.len()), (0, 80, 30));
+        assert!(reader.next_packet().is_err());
+
+        let seeked = reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 21, track_id: 0 });
+        assert_eq!(seeked.unwrap().actual_ts, 16);
+
+        let packet = reader.next_packet().unwrap();
+        assert_eq!((packet.ts, packet.dur, packet.data.len()), (16, 64, 24));
+    }
+}
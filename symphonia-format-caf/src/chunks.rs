@@ -0,0 +1,366 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::errors::{decode_error, Result};
+use symphonia_core::io::{BufReader, ReadBytes};
+use symphonia_core::meta::{MetadataBuilder, MetadataRevision};
+
+use log::warn;
+
+/// The header of a CAF chunk.
+#[derive(Debug)]
+pub struct ChunkHeader {
+    /// The chunk type.
+    pub id: [u8; 4],
+    /// The length of the chunk body in bytes. Only the audio data chunk may have a length of -1,
+    /// which indicates that it extends to the end of the file.
+    pub len: i64,
+}
+
+impl ChunkHeader {
+    pub fn read<B: ReadBytes>(reader: &mut B) -> Result<ChunkHeader> {
+        let id = reader.read_quad_bytes()?;
+        let len = reader.read_be_i64()?;
+
+        if len < 0 && (len != -1 || id != *b"data") {
+            return decode_error("caf: invalid chunk length");
+        }
+
+        Ok(ChunkHeader { id, len })
+    }
+}
+
+/// The audio description chunk.
+#[derive(Debug)]
+pub struct AudioDescription {
+    /// The number of audio frames per second.
+    pub sample_rate: f64,
+    /// The audio format identifier.
+    pub format_id: [u8; 4],
+    /// Format specific flags.
+    pub format_flags: u32,
+    /// The number of bytes in a packet, or 0 if the packet size is variable.
+    pub bytes_per_packet: u32,
+    /// The number of audio frames in a packet, or 0 if the number of frames is variable.
+    pub frames_per_packet: u32,
+    /// The number of channels.
+    pub channels_per_frame: u32,
+    /// The number of bits per sample of a channel, or 0 for compressed formats.
+    pub bits_per_channel: u32,
+}
+
+/// The audio description chunk has a fixed length.
+pub const AUDIO_DESCRIPTION_LEN: i64 = 32;
+
+impl AudioDescription {
+    pub fn read<B: ReadBytes>(reader: &mut B) -> Result<AudioDescription> {
+        let desc = AudioDescription {
+            sample_rate: reader.read_be_f64()?,
+            format_id: reader.read_quad_bytes()?,
+            format_flags: reader.read_be_u32()?,
+            bytes_per_packet: reader.read_be_u32()?,
+            frames_per_packet: reader.read_be_u32()?,
+            channels_per_frame: reader.read_be_u32()?,
+            bits_per_channel: reader.read_be_u32()?,
+        };
+
+        if !(desc.sample_rate >= 1.0 && desc.sample_rate <= f64::from(u32::MAX)) {
+            return decode_error("caf: invalid sample rate");
+        }
+
+        if desc.channels_per_frame == 0 {
+            return decode_error("caf: invalid number of channels");
+        }
+
+        Ok(desc)
+    }
+
+    /// Returns true if both the size and number of audio frames of every packet is constant.
+    pub fn is_constant_bit_rate(&self) -> bool {
+        self.bytes_per_packet > 0 && self.frames_per_packet > 0
+    }
+}
+
+/// A packet described by a packet table.
+#[derive(Copy, Clone, Debug)]
+pub struct PacketEntry {
+    /// The offset of the packet from the start of the audio data.
+    pub offset: u64,
+    /// The length of the packet in bytes.
+    pub len: u32,
+    /// The timestamp of the first audio frame of the packet.
+    pub ts: u64,
+    /// The number of audio frames in the packet.
+    pub dur: u32,
+}
+
+/// The packet table chunk.
+#[derive(Debug)]
+pub struct PacketTable {
+    /// The number of valid audio frames, excluding the priming and remainder frames.
+    pub num_valid_frames: u64,
+    /// The number of priming (encoder delay) frames at the start of the stream.
+    pub priming_frames: u32,
+    /// The number of remainder (padding) frames at the end of the stream.
+    pub remainder_frames: u32,
+    /// The packets of the stream.
+    pub packets: Vec<PacketEntry>,
+}
+
+/// Read an integer encoded with the variable length encoding used by packet tables. Each byte
+/// contributes 7 bits, most-significant group first, and all but the last byte have the
+/// most-significant bit set.
+fn read_var_len_int<B: ReadBytes>(reader: &mut B) -> Result<u64> {
+    let mut value = 0u64;
+
+    for _ in 0..10 {
+        let byte = reader.read_u8()?;
+
+        value = (value << 7) | u64::from(byte & 0x7f);
+
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+
+    decode_error("caf: variable length integer is too long")
+}
+
+impl PacketTable {
+    pub fn read(buf: &[u8], desc: &AudioDescription) -> Result<PacketTable> {
+        let mut reader = BufReader::new(buf);
+
+        let num_packets = reader.read_be_i64()?;
+        let num_valid_frames = reader.read_be_i64()?;
+        let priming_frames = reader.read_be_i32()?;
+        let remainder_frames = reader.read_be_i32()?;
+
+        if num_packets < 0 || num_valid_frames < 0 || priming_frames < 0 || remainder_frames < 0 {
+            return decode_error("caf: invalid packet table header");
+        }
+
+        // Each entry is at least one byte long, so do not trust the number of packets when
+        // allocating.
+        let mut packets = Vec::with_capacity((num_packets as usize).min(buf.len()));
+
+        let mut offset = 0;
+        let mut ts = 0;
+
+        for _ in 0..num_packets {
+            let len = match desc.bytes_per_packet {
+                0 => read_var_len_int(&mut reader)?,
+                len => u64::from(len),
+            };
+
+            let dur = match desc.frames_per_packet {
+                0 => read_var_len_int(&mut reader)?,
+                dur => u64::from(dur),
+            };
+
+            if len > u64::from(u32::MAX) || dur > u64::from(u32::MAX) {
+                return decode_error("caf: packet table entry is too large");
+            }
+
+            packets.push(PacketEntry { offset, len: len as u32, ts, dur: dur as u32 });
+
+            offset += len;
+            ts += dur;
+        }
+
+        Ok(PacketTable {
+            num_valid_frames: num_valid_frames as u64,
+            priming_frames: priming_frames as u32,
+            remainder_frames: remainder_frames as u32,
+            packets,
+        })
+    }
+}
+
+/// Read a null-terminated UTF-8 string.
+fn read_null_terminated_string(reader: &mut BufReader<'_>) -> Result<String> {
+    let buf = reader.scan_bytes_ref(&[0], usize::MAX)?;
+
+    match buf.split_last() {
+        Some((0, buf)) => Ok(String::from_utf8_lossy(buf).into_owned()),
+        _ => decode_error("caf: information string is not null-terminated"),
+    }
+}
+
+/// Read an information chunk.
+pub fn read_info_chunk(buf: &[u8]) -> Result<MetadataRevision> {
+    let mut reader = BufReader::new(buf);
+
+    let num_entries = reader.read_be_u32()?;
+
+    let mut builder = MetadataBuilder::new();
+
+    for _ in 0..num_entries {
+        let key = read_null_terminated_string(&mut reader)?;
+        let value = read_null_terminated_string(&mut reader)?;
+
+        builder.add_tag(symphonia_metadata::caf::parse(&key, &value));
+    }
+
+    Ok(builder.metadata())
+}
+
+/// The length of an ALAC specific configuration.
+const ALAC_SPECIFIC_CONFIG_LEN: usize = 24;
+
+/// Get the ALAC specific configuration from an ALAC magic cookie.
+///
+/// The magic cookie is either the bare configuration, optionally followed by a channel layout, or
+/// a sequence of atoms containing an "alac" atom with the configuration.
+fn read_alac_magic_cookie(buf: &[u8]) -> Option<Box<[u8]>> {
+    if buf.len() == ALAC_SPECIFIC_CONFIG_LEN || buf.len() == 2 * ALAC_SPECIFIC_CONFIG_LEN {
+        return Some(Box::from(buf));
+    }
+
+    let mut atoms = buf;
+
+    while atoms.len() >= 8 {
+        let len = u32::from_be_bytes([atoms[0], atoms[1], atoms[2], atoms[3]]) as usize;
+
+        if len < 8 || len > atoms.len() {
+            break;
+        }
+
+        // The "alac" atom is a full atom, the configuration follows the version and flags.
+        if atoms[4..8] == *b"alac" && len >= 12 + ALAC_SPECIFIC_CONFIG_LEN {
+            return Some(Box::from(&atoms[12..12 + ALAC_SPECIFIC_CONFIG_LEN]));
+        }
+
+        atoms = &atoms[len..];
+    }
+
+    None
+}
+
+/// Read the header of a MPEG-4 object descriptor, returning the tag and length.
+fn read_descriptor_header(reader: &mut BufReader<'_>) -> Result<(u8, usize)> {
+    let tag = reader.read_u8()?;
+
+    let mut len = 0;
+
+    for _ in 0..4 {
+        let val = reader.read_u8()?;
+        len = (len << 7) | usize::from(val & 0x7f);
+        if val & 0x80 == 0 {
+            break;
+        }
+    }
+
+    Ok((tag, len))
+}
+
+/// Get the decoder specific information, the audio specific configuration, from an AAC magic
+/// cookie containing an elementary stream descriptor.
+fn read_aac_magic_cookie(buf: &[u8]) -> Result<Option<Box<[u8]>>> {
+    const ES_DESCRIPTOR: u8 = 0x03;
+    const DECODER_CONFIG_DESCRIPTOR: u8 = 0x04;
+    const DECODER_SPECIFIC_DESCRIPTOR: u8 = 0x05;
+
+    let mut reader = BufReader::new(buf);
+
+    let (tag, _) = read_descriptor_header(&mut reader)?;
+
+    if tag != ES_DESCRIPTOR {
+        return Ok(None);
+    }
+
+    // Skip the elementary stream id, and the optional fields signalled by the flags.
+    reader.ignore_bytes(2)?;
+
+    let flags = reader.read_u8()?;
+
+    if flags & 0x80 != 0 {
+        reader.ignore_bytes(2)?;
+    }
+    if flags & 0x40 != 0 {
+        let url_len = reader.read_u8()?;
+        reader.ignore_bytes(u64::from(url_len))?;
+    }
+    if flags & 0x20 != 0 {
+        reader.ignore_bytes(2)?;
+    }
+
+    let (tag, _) = read_descriptor_header(&mut reader)?;
+
+    if tag != DECODER_CONFIG_DESCRIPTOR {
+        return Ok(None);
+    }
+
+    // Skip the object type, stream type, buffer size, and bit rates.
+    reader.ignore_bytes(13)?;
+
+    let (tag, len) = read_descriptor_header(&mut reader)?;
+
+    if tag != DECODER_SPECIFIC_DESCRIPTOR {
+        return Ok(None);
+    }
+
+    Ok(Some(reader.read_boxed_slice_exact(len)?))
+}
+
+/// Get the codec extra data from the magic cookie of the given audio format.
+pub fn read_magic_cookie(format_id: [u8; 4], buf: &[u8]) -> Option<Box<[u8]>> {
+    match &format_id {
+        b"aac " => match read_aac_magic_cookie(buf) {
+            Ok(extra_data) => extra_data,
+            Err(err) => {
+                warn!("caf: invalid aac magic cookie: {}", err);
+                None
+            }
+        },
+        b"alac" => read_alac_magic_cookie(buf),
+        _ => Some(Box::from(buf)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verify_read_var_len_int() {
+        let buf = [0x7f, 0x81, 0x00, 0xff, 0xff, 0x7f];
+        let mut reader = BufReader::new(&buf[..]);
+
+        assert_eq!(read_var_len_int(&mut reader).unwrap(), 0x7f);
+        assert_eq!(read_var_len_int(&mut reader).unwrap(), 0x80);
+        assert_eq!(read_var_len_int(&mut reader).unwrap(), 0x1f_ffff);
+        assert!(read_var_len_int(&mut reader).is_err());
+    }
+
+    #[test]
+    fn verify_read_magic_cookie() {
+        let config = [0x12, 0x10];
+
+        // An elementary stream descriptor, with a decoder config and decoder specific descriptor.
+        let mut esds = vec![0x03, 0x19, 0x00, 0x00, 0x00, 0x04, 0x11, 0x40, 0x15];
+        esds.extend_from_slice(&[0; 11]);
+        esds.extend_from_slice(&[0x05, 0x02]);
+        esds.extend_from_slice(&config);
+        esds.extend_from_slice(&[0x06, 0x01, 0x02]);
+
+        assert_eq!(read_magic_cookie(*b"aac ", &esds).as_deref(), Some(&config[..]));
+
+        // An ALAC magic cookie wrapped in atoms.
+        let mut alac = vec![0, 0, 0, 12];
+        alac.extend_from_slice(b"frmaalac");
+        alac.extend_from_slice(&[0, 0, 0, 36]);
+        alac.extend_from_slice(b"alac");
+        alac.extend_from_slice(&[0; 4]);
+        alac.extend((0..24).map(|i| i as u8));
+        alac.extend_from_slice(&[0, 0, 0, 8, 0, 0, 0, 0]);
+
+        let expected = (0..24).map(|i| i as u8).collect::<Vec<u8>>();
+
+        assert_eq!(read_magic_cookie(*b"alac", &alac).as_deref(), Some(&expected[..]));
+        assert_eq!(read_magic_cookie(*b"alac", &expected).as_deref(), Some(&expected[..]));
+    }
+}
@@ -0,0 +1,680 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::io::{Seek, SeekFrom};
+
+use symphonia_core::audio::Channels;
+use symphonia_core::codecs::*;
+use symphonia_core::errors::{decode_error, end_of_stream_error, seek_error, unsupported_error};
+use symphonia_core::errors::{Error, Result, SeekErrorKind};
+use symphonia_core::formats::prelude::*;
+use symphonia_core::formats::util::trim_packet;
+use symphonia_core::io::*;
+use symphonia_core::meta::{Metadata, MetadataLog};
+use symphonia_core::probe::{Descriptor, Instantiate, QueryDescriptor};
+use symphonia_core::support_format;
+
+use log::{debug, warn};
+
+use crate::chunks::*;
+
+/// The CAF file type.
+const CAF_FILE_TYPE: [u8; 4] = *b"caff";
+
+/// The only CAF file version.
+const CAF_FILE_VERSION: u16 = 1;
+
+/// The maximum number of frames that will be in a packet of a constant bit-rate stream.
+const CAF_MAX_FRAMES_PER_PACKET: u64 = 1152;
+
+/// Linear PCM format flag: the samples are floating point.
+const LPCM_FLAG_IS_FLOAT: u32 = 0x1;
+
+/// Linear PCM format flag: the samples are little-endian.
+const LPCM_FLAG_IS_LITTLE_ENDIAN: u32 = 0x2;
+
+/// How the audio data is split into packets.
+enum Packetization {
+    /// Every CAF packet has the same size and duration. Packets are made up of a whole number of
+    /// CAF packets.
+    Constant { bytes_per_packet: u64, frames_per_packet: u64, max_packets_per_read: u64 },
+    /// Packet sizes, durations, or both, vary, and are described by a packet table.
+    Table { packets: Vec<PacketEntry>, next: usize },
+}
+
+/// Core Audio Format (CAF) format reader.
+///
+/// `CafReader` implements a demuxer for Apple's Core Audio Format container.
+pub struct CafReader {
+    reader: MediaSourceStream,
+    tracks: Vec<Track>,
+    cues: Vec<Cue>,
+    metadata: MetadataLog,
+    gapless: bool,
+    packetization: Packetization,
+    data_start_pos: u64,
+    data_end_pos: u64,
+}
+
+impl QueryDescriptor for CafReader {
+    fn query() -> &'static [Descriptor] {
+        &[support_format!("caf", "Core Audio Format", &["caf"], &["audio/x-caf"], &[b"caff"])]
+    }
+
+    fn score(_context: &[u8]) -> u8 {
+        255
+    }
+}
+
+/// The chunks preceding the audio data that are relevant to the reader.
+#[derive(Default)]
+struct Chunks {
+    desc: Option<AudioDescription>,
+    kuki: Option<Box<[u8]>>,
+    pakt: Option<Box<[u8]>>,
+}
+
+/// Read a chunk that is not the audio data chunk. Audio description, magic cookie, and packet
+/// table chunks are stored, information chunks are read into the metadata log, and all other
+/// chunks are skipped.
+fn read_chunk(
+    source: &mut MediaSourceStream,
+    header: &ChunkHeader,
+    chunks: &mut Chunks,
+    metadata: &mut MetadataLog,
+) -> Result<()> {
+    let len = header.len as u64;
+
+    match &header.id {
+        b"desc" => {
+            if header.len != AUDIO_DESCRIPTION_LEN {
+                return decode_error("caf: invalid audio description chunk length");
+            }
+            if chunks.desc.is_some() {
+                return decode_error("caf: more than one audio description chunk");
+            }
+            chunks.desc = Some(AudioDescription::read(source)?);
+        }
+        b"kuki" => chunks.kuki = Some(source.read_boxed_slice_exact(len as usize)?),
+        b"pakt" => chunks.pakt = Some(source.read_boxed_slice_exact(len as usize)?),
+        b"info" => {
+            let buf = source.read_boxed_slice_exact(len as usize)?;
+
+            match read_info_chunk(&buf) {
+                Ok(revision) => metadata.push(revision),
+                Err(err) => warn!("caf: invalid information chunk: {}", err),
+            }
+        }
+        _ => {
+            debug!("caf: skipping chunk {:?}", String::from_utf8_lossy(&header.id));
+            source.ignore_bytes(len)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Read the chunks that follow the audio data chunk, until the end of the stream.
+fn read_trailing_chunks(
+    source: &mut MediaSourceStream,
+    chunks: &mut Chunks,
+    metadata: &mut MetadataLog,
+) -> Result<()> {
+    let end = source.byte_len().unwrap_or(u64::MAX);
+
+    while source.pos() < end {
+        let header = ChunkHeader::read(source)?;
+
+        if header.id == *b"data" {
+            return decode_error("caf: more than one audio data chunk");
+        }
+
+        read_chunk(source, &header, chunks, metadata)?;
+    }
+
+    Ok(())
+}
+
+/// Get the channels for a number of channels.
+fn channels_from_count(count: u32) -> Result<Channels> {
+    let channels = if count <= 32 { Channels::from_bits(u32::MAX >> (32 - count)) } else { None };
+
+    match channels {
+        Some(channels) => Ok(channels),
+        None => unsupported_error("caf: unsupported number of channels"),
+    }
+}
+
+/// Get the codec for a linear PCM audio description.
+fn lpcm_codec(desc: &AudioDescription) -> Result<CodecType> {
+    let is_float = desc.format_flags & LPCM_FLAG_IS_FLOAT != 0;
+    let is_le = desc.format_flags & LPCM_FLAG_IS_LITTLE_ENDIAN != 0;
+
+    let codec = match (is_float, desc.bits_per_channel, is_le) {
+        (false, 8, _) => CODEC_TYPE_PCM_S8,
+        (false, 16, true) => CODEC_TYPE_PCM_S16LE,
+        (false, 16, false) => CODEC_TYPE_PCM_S16BE,
+        (false, 24, true) => CODEC_TYPE_PCM_S24LE,
+        (false, 24, false) => CODEC_TYPE_PCM_S24BE,
+        (false, 32, true) => CODEC_TYPE_PCM_S32LE,
+        (false, 32, false) => CODEC_TYPE_PCM_S32BE,
+        (true, 32, true) => CODEC_TYPE_PCM_F32LE,
+        (true, 32, false) => CODEC_TYPE_PCM_F32BE,
+        (true, 64, true) => CODEC_TYPE_PCM_F64LE,
+        (true, 64, false) => CODEC_TYPE_PCM_F64BE,
+        _ => return unsupported_error("caf: unsupported linear pcm format"),
+    };
+
+    // Only packed, interleaved, samples are supported. Therefore, each packet must contain one
+    // audio frame of all channels.
+    if desc.frames_per_packet != 1
+        || u64::from(desc.bytes_per_packet)
+            != u64::from(desc.channels_per_frame) * u64::from(desc.bits_per_channel / 8)
+    {
+        return unsupported_error("caf: unsupported linear pcm packet layout");
+    }
+
+    Ok(codec)
+}
+
+/// Build the codec parameters from the audio description and magic cookie.
+fn codec_params(desc: &AudioDescription, kuki: Option<&[u8]>) -> Result<CodecParameters> {
+    let sample_rate = desc.sample_rate as u32;
+
+    let mut params = CodecParameters::new();
+
+    params
+        .with_sample_rate(sample_rate)
+        .with_time_base(TimeBase::new(1, sample_rate))
+        .with_channels(channels_from_count(desc.channels_per_frame)?);
+
+    match &desc.format_id {
+        b"lpcm" => {
+            params
+                .for_codec(lpcm_codec(desc)?)
+                .with_bits_per_sample(desc.bits_per_channel)
+                .with_bits_per_coded_sample(desc.bits_per_channel);
+        }
+        b"alaw" => {
+            params.for_codec(CODEC_TYPE_PCM_ALAW);
+        }
+        b"ulaw" => {
+            params.for_codec(CODEC_TYPE_PCM_MULAW);
+        }
+        b"aac " => {
+            params.for_codec(CODEC_TYPE_AAC);
+        }
+        b"alac" => {
+            params.for_codec(CODEC_TYPE_ALAC);
+        }
+        b".mp1" => {
+            params.for_codec(CODEC_TYPE_MP1);
+        }
+        b".mp2" => {
+            params.for_codec(CODEC_TYPE_MP2);
+        }
+        b".mp3" => {
+            params.for_codec(CODEC_TYPE_MP3);
+        }
+        _ => return unsupported_error("caf: unsupported audio format"),
+    }
+
+    if let Some(extra_data) = kuki.and_then(|kuki| read_magic_cookie(desc.format_id, kuki)) {
+        params.with_extra_data(extra_data);
+    }
+
+    Ok(params)
+}
+
+impl FormatReader for CafReader {
+    fn try_new(mut source: MediaSourceStream, options: &FormatOptions) -> Result<Self> {
+        // The file header is made up of the file type, version, and flags.
+        let file_type = source.read_quad_bytes()?;
+
+        if file_type != CAF_FILE_TYPE {
+            return unsupported_error("caf: missing caff stream marker");
+        }
+
+        let version = source.read_be_u16()?;
+        let _flags = source.read_be_u16()?;
+
+        if version != CAF_FILE_VERSION {
+            return unsupported_error("caf: unsupported file version");
+        }
+
+        let mut chunks: Chunks = Default::default();
+        let mut metadata: MetadataLog = Default::default();
+
+        // Read chunks until the audio data chunk is found.
+        let data_header = loop {
+            let header = match ChunkHeader::read(&mut source) {
+                Ok(header) => header,
+                Err(Error::IoError(err)) if err.kind() == std::io::ErrorKind::UnexpectedEof => {
+                    return unsupported_error("caf: missing data chunk");
+                }
+                Err(err) => return Err(err),
+            };
+
+            // The audio description chunk must be the first chunk.
+            if chunks.desc.is_none() && header.id != *b"desc" {
+                return decode_error("caf: first chunk is not the audio description chunk");
+            }
+
+            if header.id == *b"data" {
+                break header;
+            }
+
+            read_chunk(&mut source, &header, &mut chunks, &mut metadata)?;
+        };
+
+        // The audio data chunk starts with an edit count.
+        if data_header.len >= 0 && data_header.len < 4 {
+            return decode_error("caf: invalid data chunk length");
+        }
+
+        let _edit_count = source.read_be_u32()?;
+
+        let data_start_pos = source.pos();
+
+        // A data chunk length of -1 indicates the data chunk extends to the end of the stream,
+        // otherwise, the data chunk may be followed by more chunks.
+        let data_end_pos = if data_header.len >= 0 {
+            let data_end_pos = data_start_pos + (data_header.len as u64 - 4);
+
+            // The packet table, and other metadata chunks, may be written after the audio data
+            // chunk. If the source is seekable, and the data chunk is not truncated, read them and
+            // then return to the start of the data chunk.
+            let is_complete = source.byte_len().map_or(false, |len| data_end_pos < len);
+
+            if source.is_seekable() && is_complete {
+                source.seek(SeekFrom::Start(data_end_pos))?;
+
+                if let Err(err) = read_trailing_chunks(&mut source, &mut chunks, &mut metadata) {
+                    warn!("caf: failed to read chunks after the data chunk: {}", err);
+                }
+
+                source.seek(SeekFrom::Start(data_start_pos))?;
+            }
+
+            data_end_pos
+        }
+        else {
+            source.byte_len().unwrap_or(u64::MAX)
+        };
+
+        // The first chunk is always the audio description chunk.
+        let desc = chunks.desc.unwrap();
+
+        let mut params = codec_params(&desc, chunks.kuki.as_deref())?;
+
+        let packetization = if desc.is_constant_bit_rate() {
+            let bytes_per_packet = u64::from(desc.bytes_per_packet);
+            let frames_per_packet = u64::from(desc.frames_per_packet);
+
+            let mut max_frames = CAF_MAX_FRAMES_PER_PACKET;
+
+            if let Some(max_dur) = options.max_packet_duration {
+                max_frames = max_frames.min(
+                    max_dur.as_micros() as u64 * u64::from(params.sample_rate.unwrap()) / 1_000_000,
+                );
+            }
+
+            let max_packets_per_read = (max_frames / frames_per_packet).max(1);
+
+            params.with_max_frames_per_packet(max_packets_per_read * frames_per_packet);
+
+            if data_end_pos != u64::MAX {
+                let n_packets = (data_end_pos - data_start_pos) / bytes_per_packet;
+                params.with_n_frames(n_packets * frames_per_packet);
+            }
+
+            Packetization::Constant { bytes_per_packet, frames_per_packet, max_packets_per_read }
+        }
+        else {
+            let table = match &chunks.pakt {
+                Some(pakt) => PacketTable::read(pakt, &desc)?,
+                None => return decode_error("caf: missing packet table chunk"),
+            };
+
+            let delay = table.priming_frames;
+            let padding = table.remainder_frames;
+
+            params.with_delay(delay).with_padding(padding);
+
+            // The number of valid frames excludes the priming and remainder frames.
+            if options.enable_gapless {
+                params.with_n_frames(table.num_valid_frames);
+            }
+            else {
+                params
+                    .with_n_frames(table.num_valid_frames + u64::from(delay) + u64::from(padding));
+            }
+
+            if let Some(max_dur) = table.packets.iter().map(|packet| packet.dur).max() {
+                params.with_max_frames_per_packet(u64::from(max_dur));
+            }
+
+            Packetization::Table { packets: table.packets, next: 0 }
+        };
+
+        Ok(CafReader {
+            reader: source,
+            tracks: vec![Track::new(0, params)],
+            cues: Vec::new(),
+            metadata,
+            gapless: options.enable_gapless,
+            packetization,
+            data_start_pos,
+            data_end_pos,
+        })
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        let pos = self.reader.pos();
+
+        let (ts, dur, len) = match &mut self.packetization {
+            Packetization::Constant {
+                bytes_per_packet,
+                frames_per_packet,
+                max_packets_per_read,
+            } => {
+                // Determine the number of complete CAF packets remaining in the data chunk.
+                let num_packets_left = if pos < self.data_end_pos {
+                    (self.data_end_pos - pos) / *bytes_per_packet
+                }
+                else {
+                    0
+                };
+
+                if num_packets_left == 0 {
+                    return end_of_stream_error();
+                }
+
+                let num_packets = num_packets_left.min(*max_packets_per_read);
+
+                let ts = (pos - self.data_start_pos) / *bytes_per_packet * *frames_per_packet;
+
+                (ts, num_packets * *frames_per_packet, num_packets * *bytes_per_packet)
+            }
+            Packetization::Table { packets, next } => {
+                let packet = match packets.get(*next) {
+                    Some(packet) => *packet,
+                    None => return end_of_stream_error(),
+                };
+
+                // The packet must be within the data chunk.
+                if self.data_start_pos + packet.offset + u64::from(packet.len) > self.data_end_pos {
+                    return end_of_stream_error();
+                }
+
+                *next += 1;
+
+                // Packets are contiguous, but the reader may not be positioned at the start of
+                // the packet if the previous packet was not read.
+                let packet_pos = self.data_start_pos + packet.offset;
+
+                if packet_pos != pos {
+                    self.reader.seek(SeekFrom::Start(packet_pos))?;
+                }
+
+                (packet.ts, u64::from(packet.dur), u64::from(packet.len))
+            }
+        };
+
+        let pos = self.reader.pos();
+
+        let buf = self.reader.read_boxed_slice_exact(len as usize)?;
+
+        let mut packet = Packet::new_from_boxed_slice(0, ts, dur, buf).with_pos(pos);
+
+        if self.gapless {
+            let params = &self.tracks[0].codec_params;
+            trim_packet(&mut packet, params.delay.unwrap_or(0), params.n_frames);
+        }
+
+        Ok(packet)
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        let params = &self.tracks[0].codec_params;
+
+        let required_ts = match to {
+            // Frame timestamp given.
+            SeekTo::TimeStamp { ts, .. } => ts,
+            // Time value given, calculate frame timestamp from sample rate.
+            SeekTo::Time { time, .. } => {
+                // The sample rate is always known.
+                TimeBase::new(1, params.sample_rate.unwrap()).calc_timestamp(time)
+            }
+        };
+
+        // If the total number of frames in the track is known, verify the desired frame timestamp
+        // does not exceed it.
+        if let Some(n_frames) = params.n_frames {
+            if required_ts > n_frames {
+                return seek_error(SeekErrorKind::OutOfRange);
+            }
+        }
+
+        // If gapless playback is enabled, the timestamps of packets are offset by the delay.
+        let delay = if self.gapless { u64::from(params.delay.unwrap_or(0)) } else { 0 };
+
+        debug!("seeking to frame_ts={} (+{} delay)", required_ts, delay);
+
+        // Find the packet containing the required audio frame, and its position.
+        let (seek_pos, actual_ts) = match &mut self.packetization {
+            Packetization::Constant {
+                bytes_per_packet,
+                frames_per_packet,
+                max_packets_per_read,
+            } => {
+                // Seek to a packet boundary so that packets have the same timestamps regardless of
+                // whether the stream was seeked or not.
+                let frames_per_read = *max_packets_per_read * *frames_per_packet;
+
+                let ts = (required_ts + delay) / frames_per_read * frames_per_read;
+
+                (self.data_start_pos + ts / *frames_per_packet * *bytes_per_packet, ts)
+            }
+            Packetization::Table { packets, next } => {
+                let ts = required_ts + delay;
+
+                // The index of the last packet starting at or before the required timestamp.
+                let index = packets.partition_point(|packet| packet.ts <= ts).saturating_sub(1);
+
+                let packet = match packets.get(index) {
+                    Some(packet) => *packet,
+                    None => return seek_error(SeekErrorKind::OutOfRange),
+                };
+
+                *next = index;
+
+                (self.data_start_pos + packet.offset, packet.ts)
+            }
+        };
+
+        // If the reader supports seeking, seek directly to the packet. Otherwise, forward seeks
+        // are emulated by consuming bytes.
+        if self.reader.is_seekable() {
+            self.reader.seek(SeekFrom::Start(seek_pos))?;
+        }
+        else {
+            let current_pos = self.reader.pos();
+
+            if seek_pos >= current_pos {
+                self.reader.ignore_bytes(seek_pos - current_pos)?;
+            }
+            else {
+                return seek_error(SeekErrorKind::ForwardOnly);
+            }
+        }
+
+        let actual_ts = actual_ts.saturating_sub(delay);
+
+        debug!(
+            "seeked to packet_ts={} (delta={})",
+            actual_ts,
+            actual_ts as i64 - required_ts as i64
+        );
+
+        Ok(SeekedTo { track_id: 0, actual_ts, required_ts })
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.reader
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use symphonia_core::codecs::{CODEC_TYPE_AAC, CODEC_TYPE_PCM_S16LE};
+    use symphonia_core::formats::{FormatOptions, FormatReader, SeekMode, SeekTo};
+    use symphonia_core::io::MediaSourceStream;
+    use symphonia_core::meta::StandardTagKey;
+
+    use super::CafReader;
+
+    fn write_chunk(caf: &mut Vec<u8>, id: &[u8; 4], body: &[u8]) {
+        caf.extend_from_slice(id);
+        caf.extend_from_slice(&(body.len() as i64).to_be_bytes());
+        caf.extend_from_slice(body);
+    }
+
+    fn write_desc(caf: &mut Vec<u8>, format_id: &[u8; 4], flags: u32, fields: [u32; 4]) {
+        let mut desc = 44100f64.to_be_bytes().to_vec();
+        desc.extend_from_slice(format_id);
+        desc.extend_from_slice(&flags.to_be_bytes());
+
+        for field in &fields {
+            desc.extend_from_slice(&field.to_be_bytes());
+        }
+
+        write_chunk(caf, b"desc", &desc);
+    }
+
+    fn open(caf: Vec<u8>, enable_gapless: bool) -> CafReader {
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(caf)), Default::default());
+        let options = FormatOptions::default().with_enable_gapless(enable_gapless);
+        CafReader::try_new(mss, &options).unwrap()
+    }
+
+    #[test]
+    fn verify_caf_reader_lpcm() {
+        let mut caf = b"caff\x00\x01\x00\x00".to_vec();
+
+        // Stereo, 16-bit, little-endian, linear PCM.
+        write_desc(&mut caf, b"lpcm", 0x2, [4, 1, 2, 16]);
+
+        let mut info = 2u32.to_be_bytes().to_vec();
+        info.extend_from_slice(b"title\0Test\0artist\0Someone\0");
+        write_chunk(&mut caf, b"info", &info);
+
+        let mut data = vec![0; 4];
+        data.extend((0..3000 * 4).map(|i| i as u8));
+        write_chunk(&mut caf, b"data", &data);
+
+        let mut reader = open(caf, false);
+
+        let params = &reader.tracks()[0].codec_params;
+        assert_eq!(params.codec, CODEC_TYPE_PCM_S16LE);
+        assert_eq!(params.sample_rate, Some(44100));
+        assert_eq!(params.channels.map(|channels| channels.count()), Some(2));
+        assert_eq!(params.n_frames, Some(3000));
+
+        let metadata = reader.metadata();
+        let tags = metadata.current().unwrap().tags();
+        assert_eq!(tags[0].std_key, Some(StandardTagKey::TrackTitle));
+        assert_eq!(tags[1].std_key, Some(StandardTagKey::Artist));
+
+        let durs = (0..3).map(|_| reader.next_packet().unwrap().dur).collect::<Vec<u64>>();
+        assert_eq!(durs, [1152, 1152, 696]);
+        assert!(reader.next_packet().is_err());
+
+        let seeked = reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 2000, track_id: 0 });
+        assert_eq!(seeked.unwrap().actual_ts, 1152);
+
+        let packet = reader.next_packet().unwrap();
+        assert_eq!(packet.ts, 1152);
+        assert_eq!(packet.data[0], (1152 * 4) as u8);
+    }
+
+    #[test]
+    fn verify_caf_reader_packet_table() {
+        let mut caf = b"caff\x00\x01\x00\x00".to_vec();
+
+        // Stereo AAC with a variable packet size.
+        write_desc(&mut caf, b"aac ", 0, [0, 1024, 2, 0]);
+
+        let mut kuki = vec![0x03, 0x19, 0x00, 0x00, 0x00, 0x04, 0x11, 0x40, 0x15];
+        kuki.extend_from_slice(&[0; 11]);
+        kuki.extend_from_slice(&[0x05, 0x02, 0x12, 0x10, 0x06, 0x01, 0x02]);
+        write_chunk(&mut caf, b"kuki", &kuki);
+
+        let sizes = [10u8, 200, 100, 50];
+
+        // 4 packets of 1024 frames, with 2112 priming frames and 288 remainder frames.
+        let mut pakt = Vec::new();
+        pakt.extend_from_slice(&4i64.to_be_bytes());
+        pakt.extend_from_slice(&(4 * 1024 - 2112 - 288i64).to_be_bytes());
+        pakt.extend_from_slice(&2112i32.to_be_bytes());
+        pakt.extend_from_slice(&288i32.to_be_bytes());
+        for &size in &sizes {
+            // Sizes of 128 or more require two bytes.
+            if size >= 0x80 {
+                pakt.push(0x80 | (size >> 7));
+            }
+            pakt.push(size & 0x7f);
+        }
+        write_chunk(&mut caf, b"pakt", &pakt);
+
+        let mut data = vec![0; 4];
+        for (i, &size) in sizes.iter().enumerate() {
+            data.extend(std::iter::repeat(i as u8).take(usize::from(size)));
+        }
+        write_chunk(&mut caf, b"data", &data);
+
+        let mut reader = open(caf, true);
+
+        let params = &reader.tracks()[0].codec_params;
+        assert_eq!(params.codec, CODEC_TYPE_AAC);
+        assert_eq!(params.extra_data.as_deref(), Some(&[0x12, 0x10][..]));
+        assert_eq!(params.delay, Some(2112));
+        assert_eq!(params.n_frames, Some(4 * 1024 - 2112 - 288));
+
+        let packets = (0..4).map(|_| reader.next_packet().unwrap()).collect::<Vec<_>>();
+        assert!(reader.next_packet().is_err());
+
+        for (i, packet) in packets.iter().enumerate() {
+            assert_eq!(packet.data.len(), usize::from(sizes[i]));
+            assert!(packet.data.iter().all(|&byte| byte == i as u8));
+        }
+
+        assert_eq!(packets[0].trim_start, 1024);
+        assert_eq!(packets[2].trim_start, 2112 - 2048);
+        assert_eq!(packets[3].ts, 3072 - 2112);
+        assert_eq!(packets[3].trim_end, 288);
+
+        let seeked = reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 1000, track_id: 0 });
+        assert_eq!(seeked.unwrap().actual_ts, 3072 - 2112);
+
+        assert_eq!(reader.next_packet().unwrap().data[0], 3);
+    }
+}
@@ -0,0 +1,720 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::io::{Seek, SeekFrom};
+
+use symphonia_core::audio::Channels;
+use symphonia_core::codecs::{CodecParameters, CODEC_TYPE_DSD_MSBF, CODEC_TYPE_DST};
+use symphonia_core::errors::{decode_error, end_of_stream_error, seek_error, unsupported_error};
+use symphonia_core::errors::{Result, SeekErrorKind};
+use symphonia_core::formats::prelude::*;
+use symphonia_core::io::*;
+use symphonia_core::meta::{Metadata, MetadataBuilder, MetadataLog, StandardTagKey, Tag, Value};
+use symphonia_core::probe::{Descriptor, Instantiate, QueryDescriptor};
+use symphonia_core::support_format;
+
+use symphonia_metadata::id3v2;
+
+use log::{debug, warn};
+
+/// The form chunk identifier that starts every DSDIFF file.
+const DFF_STREAM_MARKER: [u8; 4] = *b"FRM8";
+
+/// The number of bytes per channel in an uncompressed packet.
+const DSD_PACKET_LEN: u64 = 4096;
+
+/// The maximum length of a chunk, other than a sound data chunk, that will be read.
+const MAX_CHUNK_LEN: u64 = 1 << 20;
+
+/// The header of a DSDIFF chunk.
+#[derive(Copy, Clone, Debug)]
+struct ChunkHeader {
+    /// The chunk identifier.
+    id: [u8; 4],
+    /// The length of the chunk body in bytes, excluding the padding byte of odd length chunks.
+    len: u64,
+}
+
+impl ChunkHeader {
+    fn read<B: ReadBytes>(reader: &mut B) -> Result<ChunkHeader> {
+        let id = reader.read_quad_bytes()?;
+        let len = reader.read_be_u64()?;
+        Ok(ChunkHeader { id, len })
+    }
+
+    /// Gets the length of the chunk body in bytes, including the padding byte.
+    fn padded_len(&self) -> u64 {
+        self.len + (self.len & 1)
+    }
+
+    /// Read the chunk body, if it is not too large.
+    fn read_body<B: ReadBytes>(&self, reader: &mut B) -> Result<Box<[u8]>> {
+        if self.len > MAX_CHUNK_LEN {
+            return decode_error("dff: chunk is too large");
+        }
+
+        let buf = reader.read_boxed_slice_exact(self.len as usize)?;
+        reader.ignore_bytes(self.padded_len() - self.len)?;
+        Ok(buf)
+    }
+}
+
+/// The sound properties of a DSDIFF file.
+#[derive(Default)]
+struct Properties {
+    sample_rate: Option<u32>,
+    channel_ids: Vec<[u8; 4]>,
+    compression: Option<[u8; 4]>,
+}
+
+impl Properties {
+    /// Read the property chunk body.
+    fn read(buf: &[u8]) -> Result<Properties> {
+        let mut reader = BufReader::new(buf);
+
+        if reader.read_quad_bytes()? != *b"SND " {
+            return decode_error("dff: invalid property chunk type");
+        }
+
+        let mut props: Properties = Default::default();
+
+        while reader.bytes_available() > 0 {
+            let chunk = ChunkHeader::read(&mut reader)?;
+            let body = reader.read_buf_bytes_ref(chunk.len as usize)?;
+            reader.ignore_bytes(chunk.padded_len() - chunk.len)?;
+
+            let mut body = BufReader::new(body);
+
+            match &chunk.id {
+                b"FS  " => props.sample_rate = Some(body.read_be_u32()?),
+                b"CHNL" => {
+                    let num_channels = body.read_be_u16()?;
+
+                    props.channel_ids = (0..num_channels)
+                        .map(|_| body.read_quad_bytes())
+                        .collect::<std::io::Result<_>>()?;
+                }
+                b"CMPR" => props.compression = Some(body.read_quad_bytes()?),
+                _ => (),
+            }
+        }
+
+        Ok(props)
+    }
+}
+
+/// Gets the channels for the channel identifiers of the channels chunk. If the channels do not
+/// map to a set of positions in the standard order, the channels are assumed to be positional.
+fn channels_from_ids(ids: &[[u8; 4]]) -> Option<Channels> {
+    let mut channels = Channels::empty();
+
+    for id in ids {
+        let channel = match id {
+            b"SLFT" | b"MLFT" => Channels::FRONT_LEFT,
+            b"SRGT" | b"MRGT" => Channels::FRONT_RIGHT,
+            b"C   " => Channels::FRONT_CENTRE,
+            b"LFE " => Channels::LFE1,
+            b"LS  " => Channels::REAR_LEFT,
+            b"RS  " => Channels::REAR_RIGHT,
+            _ => Channels::empty(),
+        };
+
+        // Channels must be in the standard order.
+        if channel.is_empty() || channel.bits() <= channels.bits() {
+            return match ids.len() {
+                0 => None,
+                len @ 1..=32 => Channels::from_bits(u32::MAX >> (32 - len)),
+                _ => None,
+            };
+        }
+
+        channels |= channel;
+    }
+
+    if channels.is_empty() {
+        None
+    }
+    else {
+        Some(channels)
+    }
+}
+
+/// Read the edited master information chunk body.
+fn read_edited_master_info(buf: &[u8], builder: &mut MetadataBuilder) -> Result<()> {
+    let mut reader = BufReader::new(buf);
+
+    while reader.bytes_available() > 0 {
+        let chunk = ChunkHeader::read(&mut reader)?;
+        let body = reader.read_buf_bytes_ref(chunk.len as usize)?;
+        reader.ignore_bytes(chunk.padded_len() - chunk.len)?;
+
+        let std_key = match &chunk.id {
+            b"DIAR" => StandardTagKey::Artist,
+            b"DITI" => StandardTagKey::TrackTitle,
+            _ => continue,
+        };
+
+        // The text is preceded by its length.
+        let mut body = BufReader::new(body);
+        let len = body.read_be_u32()?;
+        let text = body.read_buf_bytes_ref(len as usize)?;
+
+        let key = String::from_utf8_lossy(&chunk.id);
+
+        builder.add_tag(Tag::new(
+            Some(std_key),
+            &key,
+            Value::from(String::from_utf8_lossy(text).into_owned()),
+        ));
+    }
+
+    Ok(())
+}
+
+/// The sound data of a DSDIFF file.
+enum SoundData {
+    /// Uncompressed DSD audio.
+    Dsd { start_pos: u64, end_pos: u64 },
+    /// DST compressed DSD audio.
+    Dst {
+        /// The position of the first chunk following the frame information chunk.
+        start_pos: u64,
+        end_pos: u64,
+        /// The number of DST frames.
+        num_frames: u32,
+        /// The number of audio frames in a DST frame.
+        frame_len: u64,
+        /// The position of each DST frame chunk that has been located so far.
+        frames: Vec<u64>,
+    },
+}
+
+/// Direct Stream Digital Interchange File Format (DSDIFF) format reader.
+///
+/// `DffReader` implements a demuxer for the DSDIFF format.
+///
+/// For uncompressed DSD audio, each packet contains up-to 4096 bytes per channel, interleaved
+/// byte-by-byte. For DST compressed audio, each packet contains one DST frame.
+pub struct DffReader {
+    reader: MediaSourceStream,
+    tracks: Vec<Track>,
+    cues: Vec<Cue>,
+    metadata: MetadataLog,
+    num_channels: u64,
+    sound: SoundData,
+    /// The index of the next packet, or DST frame.
+    next_packet: u64,
+}
+
+impl QueryDescriptor for DffReader {
+    fn query() -> &'static [Descriptor] {
+        &[support_format!(
+            "dff",
+            "Direct Stream Digital Interchange File Format",
+            &["dff"],
+            &["audio/dff", "audio/x-dff"],
+            &[b"FRM8"]
+        )]
+    }
+
+    fn score(_context: &[u8]) -> u8 {
+        255
+    }
+}
+
+impl DffReader {
+    /// Position the reader at or after the current position, or before the current position if
+    /// the source is seekable.
+    fn reposition(&mut self, pos: u64) -> Result<()> {
+        let current_pos = self.reader.pos();
+
+        if pos >= current_pos {
+            self.reader.ignore_bytes(pos - current_pos)?;
+        }
+        else if self.reader.is_seekable() {
+            self.reader.seek(SeekFrom::Start(pos))?;
+        }
+        else {
+            return seek_error(SeekErrorKind::ForwardOnly);
+        }
+
+        Ok(())
+    }
+
+    /// Read chunks until the next DST frame chunk. Returns the position and header of the chunk,
+    /// or `None` at the end of the sound data.
+    fn next_dst_frame_chunk(&mut self) -> Result<Option<(u64, ChunkHeader)>> {
+        let end_pos = match &self.sound {
+            SoundData::Dst { end_pos, .. } => *end_pos,
+            _ => unreachable!(),
+        };
+
+        loop {
+            let pos = self.reader.pos();
+
+            if pos >= end_pos {
+                return Ok(None);
+            }
+
+            let chunk = ChunkHeader::read(&mut self.reader)?;
+
+            if chunk.id == *b"DSTF" {
+                return Ok(Some((pos, chunk)));
+            }
+
+            // Skip CRC, and unknown, chunks.
+            self.reader.ignore_bytes(chunk.padded_len())?;
+        }
+    }
+
+    /// Record the position of a DST frame chunk if it is the next frame to be located.
+    fn locate_dst_frame(&mut self, index: u64, pos: u64) {
+        if let SoundData::Dst { frames, .. } = &mut self.sound {
+            if frames.len() as u64 == index {
+                frames.push(pos);
+            }
+        }
+    }
+
+    /// Position the reader at the given DST frame chunk, and return its index. Frames that were not
+    /// located are found by skipping frames after the last located frame. If the frame is past the
+    /// end of the sound data, the index of the end is returned.
+    fn seek_dst_frame(&mut self, frame: u64) -> Result<u64> {
+        let (start_pos, last) = match &self.sound {
+            SoundData::Dst { frames, .. } if (frame as usize) < frames.len() => {
+                let pos = frames[frame as usize];
+                self.reposition(pos)?;
+                return Ok(frame);
+            }
+            SoundData::Dst { start_pos, frames, .. } => (*start_pos, frames.last().copied()),
+            _ => unreachable!(),
+        };
+
+        let mut index = match last {
+            Some(pos) => {
+                self.reposition(pos)?;
+                self.num_located_dst_frames() - 1
+            }
+            None => {
+                self.reposition(start_pos)?;
+                0
+            }
+        };
+
+        while index < frame {
+            match self.next_dst_frame_chunk()? {
+                Some((pos, chunk)) => {
+                    self.locate_dst_frame(index, pos);
+                    self.reader.ignore_bytes(chunk.padded_len())?;
+                    index += 1;
+                }
+                None => break,
+            }
+        }
+
+        Ok(index)
+    }
+
+    fn num_located_dst_frames(&self) -> u64 {
+        match &self.sound {
+            SoundData::Dst { frames, .. } => frames.len() as u64,
+            _ => 0,
+        }
+    }
+}
+
+impl FormatReader for DffReader {
+    fn try_new(mut source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
+        if source.read_quad_bytes()? != DFF_STREAM_MARKER {
+            return unsupported_error("dff: missing frm8 chunk");
+        }
+
+        let form_len = source.read_be_u64()?;
+
+        if source.read_quad_bytes()? != *b"DSD " {
+            return unsupported_error("dff: unsupported form type");
+        }
+
+        // The form type is included in the length of the form chunk.
+        let form_end_pos = source.pos() - 4 + form_len;
+
+        let mut props = None;
+        let mut sound = None;
+        let mut builder = MetadataBuilder::new();
+
+        // Read chunks until the end of the form chunk. If the source is not seekable, stop at the
+        // sound data chunk.
+        while source.pos() < form_end_pos {
+            let chunk = ChunkHeader::read(&mut source)?;
+
+            let body_pos = source.pos();
+            let end_pos = body_pos + chunk.padded_len();
+
+            match &chunk.id {
+                b"FVER" => {
+                    let version = source.read_be_u32()?;
+
+                    if version >> 24 != 1 {
+                        return unsupported_error("dff: unsupported format version");
+                    }
+                }
+                b"PROP" => props = Some(Properties::read(&chunk.read_body(&mut source)?)?),
+                b"DSD " => {
+                    sound =
+                        Some(SoundData::Dsd { start_pos: body_pos, end_pos: body_pos + chunk.len });
+                }
+                b"DST " => {
+                    // The frame information chunk starts the DST sound data chunk.
+                    let frte = ChunkHeader::read(&mut source)?;
+
+                    if frte.id != *b"FRTE" || frte.len < 6 {
+                        return decode_error("dff: missing dst frame information chunk");
+                    }
+
+                    let num_frames = source.read_be_u32()?;
+                    let frame_rate = source.read_be_u16()?;
+
+                    source.ignore_bytes(frte.padded_len() - 6)?;
+
+                    // The property chunk always precedes the sound data chunk.
+                    let sample_rate = match props.as_ref().and_then(|props| props.sample_rate) {
+                        Some(sample_rate) => sample_rate,
+                        None => return decode_error("dff: missing sample rate"),
+                    };
+
+                    if frame_rate == 0 || u32::from(frame_rate) > sample_rate {
+                        return decode_error("dff: invalid dst frame rate");
+                    }
+
+                    sound = Some(SoundData::Dst {
+                        start_pos: source.pos(),
+                        end_pos: body_pos + chunk.len,
+                        num_frames,
+                        frame_len: u64::from(sample_rate / u32::from(frame_rate)),
+                        frames: Vec::new(),
+                    });
+                }
+                b"DIIN" => {
+                    if let Err(err) =
+                        read_edited_master_info(&chunk.read_body(&mut source)?, &mut builder)
+                    {
+                        warn!("dff: failed to read edited master information: {}", err);
+                    }
+                }
+                b"ID3 " => {
+                    if let Err(err) = id3v2::read_id3v2(&mut source, &mut builder) {
+                        warn!("dff: failed to read id3v2 tag: {}", err);
+                    }
+                }
+                _ => (),
+            }
+
+            if sound.is_some() && !source.is_seekable() {
+                break;
+            }
+
+            // Skip the remainder of the chunk.
+            let pos = source.pos();
+
+            if end_pos > pos {
+                source.ignore_bytes(end_pos - pos)?;
+            }
+            else if end_pos < pos {
+                source.seek(SeekFrom::Start(end_pos))?;
+            }
+        }
+
+        let props = match props {
+            Some(props) => props,
+            None => return decode_error("dff: missing property chunk"),
+        };
+
+        let sound = match sound {
+            Some(sound) => sound,
+            None => return decode_error("dff: missing sound data chunk"),
+        };
+
+        let sample_rate = match props.sample_rate {
+            Some(sample_rate) if sample_rate > 0 => sample_rate,
+            _ => return decode_error("dff: missing sample rate"),
+        };
+
+        let channels = match channels_from_ids(&props.channel_ids) {
+            Some(channels) => channels,
+            None => return decode_error("dff: invalid channels"),
+        };
+
+        let num_channels = props.channel_ids.len() as u64;
+
+        let mut params = CodecParameters::new();
+
+        params
+            .with_sample_rate(sample_rate)
+            .with_time_base(TimeBase::new(1, sample_rate))
+            .with_channels(channels)
+            .with_bits_per_sample(1);
+
+        let start_pos = match (&sound, props.compression.as_ref()) {
+            (SoundData::Dsd { start_pos, end_pos }, None | Some(b"DSD ")) => {
+                params
+                    .for_codec(CODEC_TYPE_DSD_MSBF)
+                    .with_n_frames(8 * ((end_pos - start_pos) / num_channels))
+                    .with_max_frames_per_packet(8 * DSD_PACKET_LEN);
+
+                *start_pos
+            }
+            (SoundData::Dst { start_pos, num_frames, frame_len, .. }, Some(b"DST ")) => {
+                params
+                    .for_codec(CODEC_TYPE_DST)
+                    .with_n_frames(u64::from(*num_frames) * frame_len)
+                    .with_max_frames_per_packet(*frame_len);
+
+                *start_pos
+            }
+            _ => return unsupported_error("dff: unsupported compression type"),
+        };
+
+        // Return to the sound data if the chunks following it were read.
+        if source.pos() != start_pos {
+            source.seek(SeekFrom::Start(start_pos))?;
+        }
+
+        let mut metadata: MetadataLog = Default::default();
+
+        let revision = builder.metadata();
+
+        if !revision.tags().is_empty() || !revision.visuals().is_empty() {
+            metadata.push(revision);
+        }
+
+        Ok(DffReader {
+            reader: source,
+            tracks: vec![Track::new(0, params)],
+            cues: Vec::new(),
+            metadata,
+            num_channels,
+            sound,
+            next_packet: 0,
+        })
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        match &self.sound {
+            SoundData::Dsd { end_pos, .. } => {
+                let pos = self.reader.pos();
+
+                if pos >= *end_pos {
+                    return end_of_stream_error();
+                }
+
+                // The last packet may be shorter.
+                let len = (self.num_channels * DSD_PACKET_LEN).min(*end_pos - pos);
+                let len = len - len % self.num_channels;
+
+                if len == 0 {
+                    return end_of_stream_error();
+                }
+
+                let buf = self.reader.read_boxed_slice_exact(len as usize)?;
+
+                let ts = self.next_packet * 8 * DSD_PACKET_LEN;
+                let dur = 8 * (len / self.num_channels);
+
+                self.next_packet += 1;
+
+                Ok(Packet::new_from_boxed_slice(0, ts, dur, buf).with_pos(pos))
+            }
+            SoundData::Dst { frame_len, .. } => {
+                let frame_len = *frame_len;
+
+                let (pos, chunk) = match self.next_dst_frame_chunk()? {
+                    Some(frame) => frame,
+                    None => return end_of_stream_error(),
+                };
+
+                self.locate_dst_frame(self.next_packet, pos);
+
+                let buf = chunk.read_body(&mut self.reader)?;
+
+                let ts = self.next_packet * frame_len;
+
+                self.next_packet += 1;
+
+                Ok(Packet::new_from_boxed_slice(0, ts, frame_len, buf).with_pos(pos))
+            }
+        }
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        let params = &self.tracks[0].codec_params;
+
+        let required_ts = match to {
+            // Frame timestamp given.
+            SeekTo::TimeStamp { ts, .. } => ts,
+            // Time value given, calculate frame timestamp from sample rate.
+            SeekTo::Time { time, .. } => {
+                // The sample rate is always known.
+                TimeBase::new(1, params.sample_rate.unwrap()).calc_timestamp(time)
+            }
+        };
+
+        if required_ts > params.n_frames.unwrap_or(0) {
+            return seek_error(SeekErrorKind::OutOfRange);
+        }
+
+        let actual_ts = match &self.sound {
+            SoundData::Dsd { start_pos, .. } => {
+                let packet = required_ts / (8 * DSD_PACKET_LEN);
+
+                debug!("seeking to frame_ts={} in packet {}", required_ts, packet);
+
+                let pos = start_pos + packet * self.num_channels * DSD_PACKET_LEN;
+
+                self.reposition(pos)?;
+                self.next_packet = packet;
+
+                packet * 8 * DSD_PACKET_LEN
+            }
+            SoundData::Dst { num_frames, frame_len, .. } => {
+                let frame_len = *frame_len;
+                let frame = (required_ts / frame_len).min(u64::from(num_frames.saturating_sub(1)));
+
+                debug!("seeking to frame_ts={} in dst frame {}", required_ts, frame);
+
+                self.next_packet = self.seek_dst_frame(frame)?;
+
+                self.next_packet * frame_len
+            }
+        };
+
+        Ok(SeekedTo { track_id: 0, actual_ts, required_ts })
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.reader
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use symphonia_core::codecs::{CODEC_TYPE_DSD_MSBF, CODEC_TYPE_DST};
+    use symphonia_core::formats::{FormatReader, SeekMode, SeekTo};
+    use symphonia_core::io::MediaSourceStream;
+    use symphonia_core::meta::StandardTagKey;
+
+    use super::DffReader;
+
+    fn chunk(id: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut buf = id.to_vec();
+        buf.extend_from_slice(&(body.len() as u64).to_be_bytes());
+        buf.extend_from_slice(body);
+        if body.len() % 2 != 0 {
+            buf.push(0);
+        }
+        buf
+    }
+
+    /// Make a stereo DSD64 file with the given compression type, and sound data chunk.
+    fn make_dff(compression: &[u8; 4], sound: Vec<u8>) -> Vec<u8> {
+        let mut prop = b"SND ".to_vec();
+        prop.extend(chunk(b"FS  ", &2822400u32.to_be_bytes()));
+        prop.extend(chunk(b"CHNL", b"\x00\x02SLFTSRGT"));
+        prop.extend(chunk(b"CMPR", &[&compression[..], b"\x00\x00"].concat()));
+
+        let mut form = b"DSD ".to_vec();
+        form.extend(chunk(b"FVER", &0x0105_0000u32.to_be_bytes()));
+        form.extend(chunk(b"PROP", &prop));
+        form.extend(sound);
+        form.extend(chunk(b"DIIN", &chunk(b"DITI", b"\x00\x00\x00\x05Title")));
+
+        chunk(b"FRM8", &form)
+    }
+
+    fn open(dff: Vec<u8>) -> DffReader {
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(dff)), Default::default());
+        DffReader::try_new(mss, &Default::default()).unwrap()
+    }
+
+    fn seek(reader: &mut DffReader, ts: u64) -> u64 {
+        reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts, track_id: 0 }).unwrap().actual_ts
+    }
+
+    #[test]
+    fn verify_dsd_reader() {
+        let data: Vec<u8> = (0..8192 + 6).map(|i| i as u8).collect();
+
+        let mut reader = open(make_dff(b"DSD ", chunk(b"DSD ", &data)));
+
+        let params = &reader.tracks()[0].codec_params;
+        assert_eq!(params.codec, CODEC_TYPE_DSD_MSBF);
+        assert_eq!(params.n_frames, Some(8 * 4099));
+
+        let metadata = reader.metadata();
+        let tags = metadata.current().unwrap().tags();
+        assert_eq!(tags[0].std_key, Some(StandardTagKey::TrackTitle));
+        assert_eq!(tags[0].value.to_string(), "Title");
+
+        let packet = reader.next_packet().unwrap();
+        assert_eq!((packet.ts, packet.dur, packet.data.len()), (0, 32768, 8192));
+
+        let packet = reader.next_packet().unwrap();
+        assert_eq!((packet.ts, packet.dur), (32768, 24));
+        assert_eq!(&packet.data[..], &data[8192..]);
+        assert!(reader.next_packet().is_err());
+
+        assert_eq!(seek(&mut reader, 100), 0);
+        assert_eq!(&reader.next_packet().unwrap().data[..4], &data[..4]);
+    }
+
+    #[test]
+    fn verify_dst_reader() {
+        let mut dst = chunk(b"FRTE", b"\x00\x00\x00\x02\x00\x4b");
+        dst.extend(chunk(b"DSTF", &[1, 2, 3]));
+        dst.extend(chunk(b"DSTC", &[0; 4]));
+        dst.extend(chunk(b"DSTF", &[4, 5]));
+
+        let dff = make_dff(b"DST ", chunk(b"DST ", &dst));
+
+        let mut reader = open(dff.clone());
+
+        let params = &reader.tracks()[0].codec_params;
+        assert_eq!(params.codec, CODEC_TYPE_DST);
+        assert_eq!(params.n_frames, Some(2 * 37632));
+
+        let packet = reader.next_packet().unwrap();
+        assert_eq!((packet.ts, packet.dur), (0, 37632));
+        assert_eq!(&packet.data[..], &[1, 2, 3]);
+
+        let packet = reader.next_packet().unwrap();
+        assert_eq!((packet.ts, packet.dur), (37632, 37632));
+        assert_eq!(&packet.data[..], &[4, 5]);
+        assert!(reader.next_packet().is_err());
+
+        assert_eq!(seek(&mut reader, 100), 0);
+        assert_eq!(&reader.next_packet().unwrap().data[..], &[1, 2, 3]);
+
+        // Frames that were not yet located are found by skipping frames.
+        let mut reader = open(dff);
+
+        assert_eq!(seek(&mut reader, 40000), 37632);
+        assert_eq!(&reader.next_packet().unwrap().data[..], &[4, 5]);
+    }
+}
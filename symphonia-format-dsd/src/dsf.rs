@@ -0,0 +1,388 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::io::{Seek, SeekFrom};
+
+use symphonia_core::audio::Channels;
+use symphonia_core::codecs::CODEC_TYPE_DSD_MSBF_PLANAR;
+use symphonia_core::codecs::{CodecParameters, CODEC_TYPE_DSD_LSBF_PLANAR};
+use symphonia_core::errors::{decode_error, end_of_stream_error, seek_error, unsupported_error};
+use symphonia_core::errors::{Result, SeekErrorKind};
+use symphonia_core::formats::prelude::*;
+use symphonia_core::io::*;
+use symphonia_core::meta::{Metadata, MetadataBuilder, MetadataLog};
+use symphonia_core::probe::{Descriptor, Instantiate, QueryDescriptor};
+use symphonia_core::support_format;
+
+use symphonia_metadata::id3v2;
+
+use log::{debug, warn};
+
+/// The DSD chunk identifier that starts every DSF file.
+const DSF_STREAM_MARKER: [u8; 4] = *b"DSD ";
+
+/// The length of the DSD chunk.
+const DSD_CHUNK_LEN: u64 = 28;
+/// The minimum length of the format chunk.
+const FMT_CHUNK_LEN: u64 = 52;
+/// The length of the data chunk header.
+const DATA_CHUNK_HEADER_LEN: u64 = 12;
+
+/// The maximum number of bytes per channel in a block.
+const MAX_BLOCK_LEN: u32 = 1 << 16;
+
+/// Gets the channels for a DSF channel type.
+fn channels_from_type(channel_type: u32) -> Option<Channels> {
+    let channels = match channel_type {
+        1 => Channels::FRONT_LEFT,
+        2 => Channels::FRONT_LEFT | Channels::FRONT_RIGHT,
+        3 => Channels::FRONT_LEFT | Channels::FRONT_RIGHT | Channels::FRONT_CENTRE,
+        4 => {
+            Channels::FRONT_LEFT
+                | Channels::FRONT_RIGHT
+                | Channels::REAR_LEFT
+                | Channels::REAR_RIGHT
+        }
+        5 => Channels::FRONT_LEFT | Channels::FRONT_RIGHT | Channels::FRONT_CENTRE | Channels::LFE1,
+        6 => {
+            Channels::FRONT_LEFT
+                | Channels::FRONT_RIGHT
+                | Channels::FRONT_CENTRE
+                | Channels::REAR_LEFT
+                | Channels::REAR_RIGHT
+        }
+        7 => {
+            Channels::FRONT_LEFT
+                | Channels::FRONT_RIGHT
+                | Channels::FRONT_CENTRE
+                | Channels::LFE1
+                | Channels::REAR_LEFT
+                | Channels::REAR_RIGHT
+        }
+        _ => return None,
+    };
+
+    Some(channels)
+}
+
+/// DSD Stream File (DSF) format reader.
+///
+/// `DsfReader` implements a demuxer for the DSF format.
+///
+/// Each packet contains one block of DSD audio for every channel, one channel after another. The
+/// number of audio frames in a packet is the number of bits in a block.
+pub struct DsfReader {
+    reader: MediaSourceStream,
+    tracks: Vec<Track>,
+    cues: Vec<Cue>,
+    metadata: MetadataLog,
+    /// The length of a block of one channel in bytes.
+    block_len: u64,
+    num_channels: u64,
+    data_start_pos: u64,
+    data_end_pos: u64,
+    next_block: u64,
+}
+
+impl QueryDescriptor for DsfReader {
+    fn query() -> &'static [Descriptor] {
+        &[support_format!(
+            "dsf",
+            "DSD Stream File",
+            &["dsf"],
+            &["audio/dsf", "audio/x-dsf"],
+            &[b"DSD "]
+        )]
+    }
+
+    fn score(_context: &[u8]) -> u8 {
+        255
+    }
+}
+
+impl DsfReader {
+    /// Gets the number of audio frames in a block.
+    fn frames_per_block(&self) -> u64 {
+        8 * self.block_len
+    }
+}
+
+impl FormatReader for DsfReader {
+    fn try_new(mut source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
+        // DSD chunk.
+        if source.read_quad_bytes()? != DSF_STREAM_MARKER {
+            return unsupported_error("dsf: missing dsd chunk");
+        }
+
+        if source.read_u64()? != DSD_CHUNK_LEN {
+            return decode_error("dsf: invalid dsd chunk length");
+        }
+
+        let _file_len = source.read_u64()?;
+        let metadata_pos = source.read_u64()?;
+
+        // Format chunk.
+        if source.read_quad_bytes()? != *b"fmt " {
+            return decode_error("dsf: missing fmt chunk");
+        }
+
+        let fmt_len = source.read_u64()?;
+
+        if fmt_len < FMT_CHUNK_LEN {
+            return decode_error("dsf: invalid fmt chunk length");
+        }
+
+        let format_version = source.read_u32()?;
+        let format_id = source.read_u32()?;
+        let channel_type = source.read_u32()?;
+        let num_channels = source.read_u32()?;
+        let sample_rate = source.read_u32()?;
+        let bits_per_sample = source.read_u32()?;
+        let num_frames = source.read_u64()?;
+        let block_len = source.read_u32()?;
+        let _reserved = source.read_u32()?;
+
+        source.ignore_bytes(fmt_len - FMT_CHUNK_LEN)?;
+
+        if format_version != 1 {
+            return unsupported_error("dsf: unsupported format version");
+        }
+
+        // Only raw DSD is defined.
+        if format_id != 0 {
+            return unsupported_error("dsf: unsupported format");
+        }
+
+        // The number of bits per sample actually indicates the bit order of the sample data.
+        let codec = match bits_per_sample {
+            1 => CODEC_TYPE_DSD_LSBF_PLANAR,
+            8 => CODEC_TYPE_DSD_MSBF_PLANAR,
+            _ => return decode_error("dsf: invalid bits per sample"),
+        };
+
+        let channels = match channels_from_type(channel_type) {
+            Some(channels) if channels.count() == num_channels as usize => channels,
+            _ => return decode_error("dsf: invalid channel type"),
+        };
+
+        if sample_rate == 0 {
+            return decode_error("dsf: invalid sample rate");
+        }
+
+        if block_len == 0 || block_len > MAX_BLOCK_LEN {
+            return decode_error("dsf: invalid block size");
+        }
+
+        // Data chunk.
+        if source.read_quad_bytes()? != *b"data" {
+            return decode_error("dsf: missing data chunk");
+        }
+
+        let data_len = match source.read_u64()?.checked_sub(DATA_CHUNK_HEADER_LEN) {
+            Some(data_len) => data_len,
+            None => return decode_error("dsf: invalid data chunk length"),
+        };
+
+        let data_start_pos = source.pos();
+        let data_end_pos = data_start_pos + data_len;
+
+        let mut metadata: MetadataLog = Default::default();
+
+        // If the source is seekable, read the ID3v2 tag following the audio data, and then return
+        // to the audio data.
+        if metadata_pos != 0 && source.is_seekable() {
+            source.seek(SeekFrom::Start(metadata_pos))?;
+
+            let mut builder = MetadataBuilder::new();
+
+            match id3v2::read_id3v2(&mut source, &mut builder) {
+                Ok(_) => metadata.push(builder.metadata()),
+                Err(err) => warn!("dsf: failed to read id3v2 tag: {}", err),
+            }
+
+            source.seek(SeekFrom::Start(data_start_pos))?;
+        }
+
+        let mut params = CodecParameters::new();
+
+        params
+            .for_codec(codec)
+            .with_sample_rate(sample_rate)
+            .with_time_base(TimeBase::new(1, sample_rate))
+            .with_channels(channels)
+            .with_bits_per_sample(1)
+            .with_n_frames(num_frames)
+            .with_max_frames_per_packet(8 * u64::from(block_len));
+
+        Ok(DsfReader {
+            reader: source,
+            tracks: vec![Track::new(0, params)],
+            cues: Vec::new(),
+            metadata,
+            block_len: u64::from(block_len),
+            num_channels: u64::from(num_channels),
+            data_start_pos,
+            data_end_pos,
+            next_block: 0,
+        })
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        let len = self.num_channels * self.block_len;
+
+        let pos = self.reader.pos();
+
+        if pos + len > self.data_end_pos {
+            return end_of_stream_error();
+        }
+
+        let ts = self.next_block * self.frames_per_block();
+
+        // The last block is padded. Only the valid audio frames are included in the duration.
+        let n_frames = self.tracks[0].codec_params.n_frames.unwrap_or(0);
+
+        if ts >= n_frames {
+            return end_of_stream_error();
+        }
+
+        let dur = self.frames_per_block().min(n_frames - ts);
+
+        let buf = self.reader.read_boxed_slice_exact(len as usize)?;
+
+        self.next_block += 1;
+
+        Ok(Packet::new_from_boxed_slice(0, ts, dur, buf).with_pos(pos))
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        let params = &self.tracks[0].codec_params;
+
+        let required_ts = match to {
+            // Frame timestamp given.
+            SeekTo::TimeStamp { ts, .. } => ts,
+            // Time value given, calculate frame timestamp from sample rate.
+            SeekTo::Time { time, .. } => {
+                // The sample rate is always known.
+                TimeBase::new(1, params.sample_rate.unwrap()).calc_timestamp(time)
+            }
+        };
+
+        if required_ts > params.n_frames.unwrap_or(0) {
+            return seek_error(SeekErrorKind::OutOfRange);
+        }
+
+        let block = required_ts / self.frames_per_block();
+
+        debug!("seeking to frame_ts={} in block {}", required_ts, block);
+
+        let seek_pos = self.data_start_pos + block * self.num_channels * self.block_len;
+
+        // If the reader supports seeking, seek directly to the block. Otherwise, forward seeks
+        // are emulated by consuming bytes.
+        if self.reader.is_seekable() {
+            self.reader.seek(SeekFrom::Start(seek_pos))?;
+        }
+        else {
+            let current_pos = self.reader.pos();
+
+            if seek_pos >= current_pos {
+                self.reader.ignore_bytes(seek_pos - current_pos)?;
+            }
+            else {
+                return seek_error(SeekErrorKind::ForwardOnly);
+            }
+        }
+
+        self.next_block = block;
+
+        Ok(SeekedTo { track_id: 0, actual_ts: block * self.frames_per_block(), required_ts })
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.reader
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use symphonia_core::codecs::CODEC_TYPE_DSD_LSBF_PLANAR;
+    use symphonia_core::formats::{FormatReader, SeekMode, SeekTo};
+    use symphonia_core::io::MediaSourceStream;
+    use symphonia_core::meta::StandardTagKey;
+
+    use super::DsfReader;
+
+    /// Make a stereo DSD64 file with 2 blocks of 4 bytes per channel, 40 audio frames, and an
+    /// ID3v2 tag.
+    fn make_dsf() -> Vec<u8> {
+        let mut dsf = b"DSD ".to_vec();
+        dsf.extend_from_slice(&28u64.to_le_bytes());
+        dsf.extend_from_slice(&(108u64 + 26).to_le_bytes());
+        dsf.extend_from_slice(&108u64.to_le_bytes());
+
+        dsf.extend_from_slice(b"fmt ");
+        dsf.extend_from_slice(&52u64.to_le_bytes());
+        for value in &[1u32, 0, 2, 2, 2822400, 1] {
+            dsf.extend_from_slice(&value.to_le_bytes());
+        }
+        dsf.extend_from_slice(&40u64.to_le_bytes());
+        dsf.extend_from_slice(&4u32.to_le_bytes());
+        dsf.extend_from_slice(&0u32.to_le_bytes());
+
+        dsf.extend_from_slice(b"data");
+        dsf.extend_from_slice(&(12u64 + 16).to_le_bytes());
+        dsf.extend(0..16);
+
+        dsf.extend_from_slice(b"ID3\x03\x00\x00\x00\x00\x00\x10");
+        dsf.extend_from_slice(b"TIT2\x00\x00\x00\x06\x00\x00\x00Title");
+        dsf
+    }
+
+    #[test]
+    fn verify_dsf_reader() {
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(make_dsf())), Default::default());
+        let mut reader = DsfReader::try_new(mss, &Default::default()).unwrap();
+
+        let params = &reader.tracks()[0].codec_params;
+        assert_eq!(params.codec, CODEC_TYPE_DSD_LSBF_PLANAR);
+        assert_eq!(params.sample_rate, Some(2822400));
+        assert_eq!(params.channels.map(|channels| channels.count()), Some(2));
+        assert_eq!(params.n_frames, Some(40));
+
+        let metadata = reader.metadata();
+        let tags = metadata.current().unwrap().tags();
+        assert_eq!(tags[0].std_key, Some(StandardTagKey::TrackTitle));
+        assert_eq!(tags[0].value.to_string(), "Title");
+
+        let packet = reader.next_packet().unwrap();
+        assert_eq!((packet.ts, packet.dur), (0, 32));
+        assert_eq!(&packet.data[..], &[0, 1, 2, 3, 4, 5, 6, 7]);
+
+        // The last block is padded.
+        let packet = reader.next_packet().unwrap();
+        assert_eq!((packet.ts, packet.dur), (32, 8));
+        assert!(reader.next_packet().is_err());
+
+        let seeked = reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 35, track_id: 0 });
+        assert_eq!(seeked.unwrap().actual_ts, 32);
+        assert_eq!(&reader.next_packet().unwrap().data[..], &[8, 9, 10, 11, 12, 13, 14, 15]);
+    }
+}
@@ -0,0 +1,106 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::errors::Result;
+use symphonia_core::io::ReadBytes;
+
+use crate::atoms::{Atom, AtomHeader};
+
+/// The number of Nero chapter timestamp units per second (100 ns units).
+pub const NERO_CHAPTER_UNITS_PER_SEC: u64 = 10_000_000;
+
+/// A Nero chapter.
+#[derive(Debug)]
+pub struct NeroChapter {
+    /// The start time of the chapter in 100 ns units.
+    pub start: u64,
+    /// The chapter title.
+    pub title: String,
+}
+
+/// Nero chapter list atom.
+#[derive(Debug)]
+pub struct ChplAtom {
+    /// Atom header.
+    header: AtomHeader,
+    /// The chapters.
+    pub chapters: Vec<NeroChapter>,
+}
+
+impl Atom for ChplAtom {
+    fn header(&self) -> AtomHeader {
+        self.header
+    }
+
+    fn read<B: ReadBytes>(reader: &mut B, header: AtomHeader) -> Result<Self> {
+        let (version, _) = AtomHeader::read_extra(reader)?;
+
+        // Version 1 adds a reserved field.
+        if version > 0 {
+            let _ = reader.read_be_u32()?;
+        }
+
+        let n_chapters = reader.read_u8()?;
+
+        let mut chapters = Vec::with_capacity(usize::from(n_chapters));
+
+        for _ in 0..n_chapters {
+            let start = reader.read_be_u64()?;
+
+            let title_len = reader.read_u8()?;
+            let title_buf = reader.read_boxed_slice_exact(usize::from(title_len))?;
+
+            let title = String::from_utf8_lossy(&title_buf).to_string();
+
+            chapters.push(NeroChapter { start, title });
+        }
+
+        Ok(ChplAtom { header, chapters })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::io::BufReader;
+
+    use super::ChplAtom;
+    use crate::atoms::{Atom, AtomHeader, AtomType};
+
+    #[test]
+    fn verify_read_chpl() {
+        // Version 1, no flags, a reserved field, and two chapters.
+        let mut data = vec![0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02];
+
+        // The first chapter at 0 s.
+        data.extend_from_slice(&0u64.to_be_bytes());
+        data.push(5);
+        data.extend_from_slice(b"Intro");
+
+        // The second chapter at 90.5 s.
+        data.extend_from_slice(&905_000_000u64.to_be_bytes());
+        data.push(7);
+        data.extend_from_slice(b"Verse 1");
+
+        let header = AtomHeader {
+            atype: AtomType::NeroChapterList,
+            atom_len: 8 + data.len() as u64,
+            data_len: data.len() as u64,
+        };
+
+        let chpl = ChplAtom::read(&mut BufReader::new(&data), header).unwrap();
+
+        assert_eq!(chpl.chapters.len(), 2);
+        assert_eq!((chpl.chapters[0].start, chpl.chapters[0].title.as_str()), (0, "Intro"));
+        assert_eq!(
+            (chpl.chapters[1].start, chpl.chapters[1].title.as_str()),
+            (905_000_000, "Verse 1")
+        );
+
+        // A truncated chapter list is an error.
+        assert!(ChplAtom::read(&mut BufReader::new(&data[..data.len() - 1]), header).is_err());
+    }
+}
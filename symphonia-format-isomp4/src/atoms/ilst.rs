@@ -10,7 +10,7 @@ use symphonia_core::io::{BufReader, ReadBytes};
 use symphonia_core::meta::{
     MetadataBuilder, MetadataRevision, StandardTagKey, StandardVisualKey, Tag,
 };
-use symphonia_core::meta::{Value, Visual};
+use symphonia_core::meta::{Value, VendorData, Visual};
 use symphonia_core::util::bits;
 use symphonia_metadata::{id3v1, itunes};
 
@@ -341,14 +341,19 @@ fn add_m_of_n_tag<B: ReadBytes>(
 
     // There should only be 1 value.
     if let Some(value) = tag.values.first() {
-        // The trkn and disk atoms contains an 8 byte value buffer, where the 4th and 6th bytes
-        // indicate the track/disk number and total number of tracks/disks, respectively. Odd.
-        if value.data.len() == 8 {
-            let m = value.data[3];
-            let n = value.data[5];
+        // The trkn and disk atoms contain a reserved 16-bit integer followed by two 16-bit
+        // big-endian integers: the track/disk number and total number of tracks/disks,
+        // respectively. The trkn atom has an additional reserved 16-bit integer. A total of 0
+        // indicates the total is unknown.
+        if value.data.len() >= 6 {
+            let m = u16::from_be_bytes([value.data[2], value.data[3]]);
+            let n = u16::from_be_bytes([value.data[4], value.data[5]]);
 
             builder.add_tag(Tag::new(Some(m_key), "", Value::from(m)));
-            builder.add_tag(Tag::new(Some(n_key), "", Value::from(n)));
+
+            if n > 0 {
+                builder.add_tag(Tag::new(Some(n_key), "", Value::from(n)));
+            }
         }
     }
 
@@ -451,20 +456,22 @@ fn add_freeform_tag<B: ReadBytes>(
 ) -> Result<()> {
     let tag = iter.read_atom::<MetaTagAtom>()?;
 
+    // Gets the fully qualified tag name.
+    let full_name = tag.full_name();
+
     // A user-defined tag should only have 1 value.
-    for value_atom in tag.values.iter() {
+    for value_atom in tag.values {
         // Parse the value atom data into a string, if possible.
         if let Some(value) = parse_tag_value(value_atom.data_type, &value_atom.data) {
-            // Gets the fully qualified tag name.
-            let full_name = tag.full_name();
-
             // Try to map iTunes freeform tags to standard tag keys.
             let std_key = itunes::std_key_from_tag(&full_name);
 
             builder.add_tag(Tag::new(std_key, &full_name, value));
         }
         else {
-            warn!("unsupported data type {:?} for free-form tag", value_atom.data_type);
+            // Values that are not text or numbers, such as binary data, are proprietary to the
+            // application identified by the tag name.
+            builder.add_vendor_data(VendorData { ident: full_name.clone(), data: value_atom.data });
         }
     }
 
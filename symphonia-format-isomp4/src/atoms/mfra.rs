@@ -0,0 +1,96 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::errors::Result;
+use symphonia_core::io::ReadBytes;
+
+use crate::atoms::{Atom, AtomHeader, AtomIterator, AtomType, TfraAtom};
+
+/// Movie fragment random access atom.
+#[derive(Debug)]
+pub struct MfraAtom {
+    /// Atom header.
+    header: AtomHeader,
+    /// Track fragment random access atoms, at most one per track.
+    pub tfras: Vec<TfraAtom>,
+}
+
+impl MfraAtom {
+    /// Gets the track fragment random access atom for the track with the provided track ID.
+    pub fn tfra(&self, track_id: u32) -> Option<&TfraAtom> {
+        self.tfras.iter().find(|tfra| tfra.track_id == track_id)
+    }
+}
+
+impl Atom for MfraAtom {
+    fn header(&self) -> AtomHeader {
+        self.header
+    }
+
+    fn read<B: ReadBytes>(reader: &mut B, header: AtomHeader) -> Result<Self> {
+        let mut iter = AtomIterator::new(reader, header);
+
+        let mut tfras = Vec::new();
+
+        while let Some(header) = iter.next()? {
+            // The movie fragment random access offset (mfro) atom is only useful for locating the
+            // mfra atom from the end of the file, and is ignored.
+            if header.atype == AtomType::TrackFragmentRandomAccess {
+                tfras.push(iter.read_atom::<TfraAtom>()?);
+            }
+        }
+
+        Ok(MfraAtom { header, tfras })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::io::BufReader;
+
+    use super::MfraAtom;
+    use crate::atoms::{Atom, AtomHeader, AtomType};
+
+    /// Make a version 0 tfra atom with one random access point at the start of a fragment.
+    fn make_tfra(track_id: u32, time: u32, moof_offset: u32) -> Vec<u8> {
+        let mut tfra = 35u32.to_be_bytes().to_vec();
+        tfra.extend_from_slice(b"tfra");
+        for value in &[0, track_id, 0, 1, time, moof_offset] {
+            tfra.extend_from_slice(&value.to_be_bytes());
+        }
+        tfra.extend_from_slice(&[1, 1, 1]);
+        tfra
+    }
+
+    #[test]
+    fn verify_read_mfra() {
+        let mut data = make_tfra(1, 0, 100);
+        data.extend(make_tfra(2, 500, 200));
+
+        // The mfro atom is ignored.
+        data.extend_from_slice(&16u32.to_be_bytes());
+        data.extend_from_slice(b"mfro");
+        data.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 94]);
+
+        let header = AtomHeader {
+            atype: AtomType::MovieFragmentRandomAccess,
+            atom_len: 8 + data.len() as u64,
+            data_len: data.len() as u64,
+        };
+
+        let mfra = MfraAtom::read(&mut BufReader::new(&data), header).unwrap();
+
+        assert_eq!(mfra.tfras.len(), 2);
+
+        let entries = &mfra.tfra(2).unwrap().entries;
+        assert_eq!(entries.len(), 1);
+        assert_eq!((entries[0].time, entries[0].moof_offset), (500, 200));
+
+        assert_eq!(mfra.tfra(1).unwrap().entries[0].moof_offset, 100);
+        assert!(mfra.tfra(3).is_none());
+    }
+}
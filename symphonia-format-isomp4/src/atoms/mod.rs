@@ -9,6 +9,7 @@ use symphonia_core::errors::{decode_error, Result};
 use symphonia_core::io::ReadBytes;
 
 pub(crate) mod alac;
+pub(crate) mod chpl;
 pub(crate) mod co64;
 pub(crate) mod ctts;
 pub(crate) mod edts;
@@ -23,6 +24,7 @@ pub(crate) mod mdia;
 pub(crate) mod mehd;
 pub(crate) mod meta;
 pub(crate) mod mfhd;
+pub(crate) mod mfra;
 pub(crate) mod minf;
 pub(crate) mod moof;
 pub(crate) mod moov;
@@ -38,10 +40,13 @@ pub(crate) mod stsd;
 pub(crate) mod stss;
 pub(crate) mod stsz;
 pub(crate) mod stts;
+pub(crate) mod tfdt;
 pub(crate) mod tfhd;
+pub(crate) mod tfra;
 pub(crate) mod tkhd;
 pub(crate) mod traf;
 pub(crate) mod trak;
+pub(crate) mod tref;
 pub(crate) mod trex;
 pub(crate) mod trun;
 pub(crate) mod udta;
@@ -49,6 +54,7 @@ pub(crate) mod wave;
 
 pub use self::meta::MetaAtom;
 pub use alac::AlacAtom;
+pub use chpl::{ChplAtom, NERO_CHAPTER_UNITS_PER_SEC};
 pub use co64::Co64Atom;
 pub use ctts::CttsAtom;
 pub use edts::EdtsAtom;
@@ -62,6 +68,7 @@ pub use mdhd::MdhdAtom;
 pub use mdia::MdiaAtom;
 pub use mehd::MehdAtom;
 pub use mfhd::MfhdAtom;
+pub use mfra::MfraAtom;
 pub use minf::MinfAtom;
 pub use moof::MoofAtom;
 pub use moov::MoovAtom;
@@ -77,10 +84,13 @@ pub use stsd::StsdAtom;
 pub use stss::StssAtom;
 pub use stsz::StszAtom;
 pub use stts::SttsAtom;
+pub use tfdt::TfdtAtom;
 pub use tfhd::TfhdAtom;
+pub use tfra::TfraAtom;
 pub use tkhd::TkhdAtom;
 pub use traf::TrafAtom;
 pub use trak::TrakAtom;
+pub use tref::TrefAtom;
 pub use trex::TrexAtom;
 pub use trun::TrunAtom;
 pub use udta::UdtaAtom;
@@ -98,6 +108,7 @@ pub enum AtomType {
     ArtistLowerTag,
     ArtistTag,
     CategoryTag,
+    ChapterReference,
     ChunkOffset,
     ChunkOffset64,
     CommentTag,
@@ -147,10 +158,12 @@ pub enum AtomType {
     MovieExtendsHeader,
     MovieFragment,
     MovieFragmentHeader,
+    MovieFragmentRandomAccess,
     MovieHeader,
     Mp3,
     Mp4a,
     MuLaw,
+    NeroChapterList,
     Opus,
     OpusDsConfig,
     OwnerTag,
@@ -180,10 +193,13 @@ pub enum AtomType {
     Track,
     TrackExtends,
     TrackFragment,
+    TrackFragmentBaseMediaDecodeTime,
     TrackFragmentHeader,
+    TrackFragmentRandomAccess,
     TrackFragmentRun,
     TrackHeader,
     TrackNumberTag,
+    TrackReference,
     TrackTitleTag,
     TvEpisodeNameTag,
     TvEpisodeNumberTag,
@@ -203,6 +219,8 @@ impl From<[u8; 4]> for AtomType {
             b"ac-3" => AtomType::Ac3,
             b"alac" => AtomType::Alac,
             b"alaw" => AtomType::ALaw,
+            b"chpl" => AtomType::NeroChapterList,
+            b"chap" => AtomType::ChapterReference,
             b"co64" => AtomType::ChunkOffset64,
             b"ctts" => AtomType::CompositionTimeToSample,
             b"data" => AtomType::MetaTagData,
@@ -228,6 +246,7 @@ impl From<[u8; 4]> for AtomType {
             b"mehd" => AtomType::MovieExtendsHeader,
             b"meta" => AtomType::Meta,
             b"mfhd" => AtomType::MovieFragmentHeader,
+            b"mfra" => AtomType::MovieFragmentRandomAccess,
             b"minf" => AtomType::MediaInfo,
             b"moof" => AtomType::MovieFragment,
             b"moov" => AtomType::Movie,
@@ -248,10 +267,13 @@ impl From<[u8; 4]> for AtomType {
             b"stss" => AtomType::SyncSample,
             b"stsz" => AtomType::SampleSize,
             b"stts" => AtomType::TimeToSample,
+            b"tfdt" => AtomType::TrackFragmentBaseMediaDecodeTime,
             b"tfhd" => AtomType::TrackFragmentHeader,
+            b"tfra" => AtomType::TrackFragmentRandomAccess,
             b"tkhd" => AtomType::TrackHeader,
             b"traf" => AtomType::TrackFragment,
             b"trak" => AtomType::Track,
+            b"tref" => AtomType::TrackReference,
             b"trex" => AtomType::TrackExtends,
             b"trun" => AtomType::TrackFragmentRun,
             b"twos" => AtomType::S16BeSampleEntry,
@@ -467,6 +489,18 @@ impl<B: ReadBytes> AtomIterator<B> {
         A::read(&mut self.reader, self.cur_atom.take().unwrap())
     }
 
+    /// Repositions the iterator such that the next atom is read from the provided position. The
+    /// position must be the first byte of an atom within the container.
+    pub fn seek_to_atom(&mut self, pos: u64) -> Result<()>
+    where
+        B: std::io::Seek,
+    {
+        self.reader.seek(std::io::SeekFrom::Start(pos))?;
+        self.cur_atom = None;
+        self.next_atom_pos = pos;
+        Ok(())
+    }
+
     pub fn consume_atom(&mut self) {
         assert!(self.cur_atom.take().is_some());
     }
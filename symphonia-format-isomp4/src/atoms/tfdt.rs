@@ -0,0 +1,67 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::errors::Result;
+use symphonia_core::io::ReadBytes;
+
+use crate::atoms::{Atom, AtomHeader};
+
+/// Track fragment base media decode time atom.
+#[derive(Debug)]
+pub struct TfdtAtom {
+    /// Atom header.
+    header: AtomHeader,
+    /// The decode timestamp of the first sample of the track fragment, in the timescale of the
+    /// track.
+    pub base_media_decode_time: u64,
+}
+
+impl Atom for TfdtAtom {
+    fn header(&self) -> AtomHeader {
+        self.header
+    }
+
+    fn read<B: ReadBytes>(reader: &mut B, header: AtomHeader) -> Result<Self> {
+        let (version, _) = AtomHeader::read_extra(reader)?;
+
+        let base_media_decode_time = match version {
+            1 => reader.read_be_u64()?,
+            _ => u64::from(reader.read_be_u32()?),
+        };
+
+        Ok(TfdtAtom { header, base_media_decode_time })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::io::BufReader;
+
+    use super::TfdtAtom;
+    use crate::atoms::{Atom, AtomHeader, AtomType};
+
+    fn read_tfdt(data: &[u8]) -> TfdtAtom {
+        let header = AtomHeader {
+            atype: AtomType::TrackFragmentBaseMediaDecodeTime,
+            atom_len: 8 + data.len() as u64,
+            data_len: data.len() as u64,
+        };
+
+        TfdtAtom::read(&mut BufReader::new(data), header).unwrap()
+    }
+
+    #[test]
+    fn verify_read_tfdt() {
+        // Version 0 uses a 32-bit decode time.
+        let tfdt = read_tfdt(&[0x00, 0x00, 0x00, 0x00, 0x12, 0x34, 0x56, 0x78]);
+        assert_eq!(tfdt.base_media_decode_time, 0x1234_5678);
+
+        // Version 1 uses a 64-bit decode time.
+        let tfdt = read_tfdt(&[0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0, 0, 0, 0x02]);
+        assert_eq!(tfdt.base_media_decode_time, 0x1_0000_0002);
+    }
+}
@@ -0,0 +1,179 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::errors::{decode_error, Result};
+use symphonia_core::io::ReadBytes;
+
+use crate::atoms::{Atom, AtomHeader};
+
+/// A random access point of a track.
+#[derive(Debug)]
+pub struct TfraEntry {
+    /// The presentation time of the sample, in the timescale of the track.
+    pub time: u64,
+    /// The position of the first byte of the moof atom containing the sample.
+    pub moof_offset: u64,
+    /// The 1-based index of the track run, within the track fragment, containing the sample.
+    pub trun_num: u32,
+    /// The 1-based index of the sample within the track run.
+    pub sample_num: u32,
+}
+
+impl TfraEntry {
+    /// Returns true if the random access point is the first sample of its track fragment.
+    pub fn is_first_sample(&self) -> bool {
+        self.trun_num == 1 && self.sample_num == 1
+    }
+}
+
+/// Track fragment random access atom.
+#[derive(Debug)]
+pub struct TfraAtom {
+    /// Atom header.
+    header: AtomHeader,
+    /// The track ID.
+    pub track_id: u32,
+    /// The random access points of the track, in ascending order of time.
+    pub entries: Vec<TfraEntry>,
+}
+
+/// Reads an unsigned big-endian integer of 1 to 4 bytes.
+fn read_var_uint<B: ReadBytes>(reader: &mut B, len: u32) -> Result<u32> {
+    match len {
+        1 => Ok(u32::from(reader.read_u8()?)),
+        2 => Ok(u32::from(reader.read_be_u16()?)),
+        3 => Ok(reader.read_be_u24()?),
+        _ => Ok(reader.read_be_u32()?),
+    }
+}
+
+impl Atom for TfraAtom {
+    fn header(&self) -> AtomHeader {
+        self.header
+    }
+
+    fn read<B: ReadBytes>(reader: &mut B, header: AtomHeader) -> Result<Self> {
+        let (version, _) = AtomHeader::read_extra(reader)?;
+
+        let track_id = reader.read_be_u32()?;
+
+        // The lengths, less one, of the traf, trun, and sample number fields of each entry.
+        let lens = reader.read_be_u32()?;
+
+        let traf_num_len = ((lens >> 4) & 0x3) + 1;
+        let trun_num_len = ((lens >> 2) & 0x3) + 1;
+        let sample_num_len = (lens & 0x3) + 1;
+
+        let num_entries = reader.read_be_u32()?;
+
+        // Each entry is atleast 11 bytes long. Do not trust the entry count if the atom is
+        // too small.
+        if u64::from(num_entries) * 11 > header.data_len {
+            return decode_error("isomp4: tfra entry count exceeds atom size");
+        }
+
+        let mut entries = Vec::with_capacity(num_entries as usize);
+
+        for _ in 0..num_entries {
+            let (time, moof_offset) = match version {
+                1 => (reader.read_be_u64()?, reader.read_be_u64()?),
+                _ => (u64::from(reader.read_be_u32()?), u64::from(reader.read_be_u32()?)),
+            };
+
+            // The index of the track fragment within the moof atom is not required since there is
+            // only ever one track fragment per track.
+            let _traf_num = read_var_uint(reader, traf_num_len)?;
+
+            entries.push(TfraEntry {
+                time,
+                moof_offset,
+                trun_num: read_var_uint(reader, trun_num_len)?,
+                sample_num: read_var_uint(reader, sample_num_len)?,
+            });
+        }
+
+        Ok(TfraAtom { header, track_id, entries })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::io::BufReader;
+
+    use super::TfraAtom;
+    use crate::atoms::{Atom, AtomHeader, AtomType};
+
+    fn make_header(data: &[u8]) -> AtomHeader {
+        AtomHeader {
+            atype: AtomType::TrackFragmentRandomAccess,
+            atom_len: 8 + data.len() as u64,
+            data_len: data.len() as u64,
+        }
+    }
+
+    #[test]
+    fn verify_read_tfra() {
+        // Version 0, track 2, with 1 byte traf, 2 byte trun, and 3 byte sample numbers.
+        let mut data = vec![0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02];
+        data.extend_from_slice(&0x06u32.to_be_bytes());
+        data.extend_from_slice(&2u32.to_be_bytes());
+
+        data.extend_from_slice(&1000u32.to_be_bytes());
+        data.extend_from_slice(&0x1234u32.to_be_bytes());
+        data.extend_from_slice(&[1, 0, 1, 0, 0, 1]);
+
+        data.extend_from_slice(&3000u32.to_be_bytes());
+        data.extend_from_slice(&0x5678u32.to_be_bytes());
+        data.extend_from_slice(&[1, 0x01, 0x02, 0x03, 0x04, 0x05]);
+
+        let tfra = TfraAtom::read(&mut BufReader::new(&data), make_header(&data)).unwrap();
+
+        assert_eq!(tfra.track_id, 2);
+        assert_eq!(tfra.entries.len(), 2);
+
+        let entry = &tfra.entries[0];
+        assert_eq!((entry.time, entry.moof_offset), (1000, 0x1234));
+        assert!(entry.is_first_sample());
+
+        let entry = &tfra.entries[1];
+        assert_eq!((entry.time, entry.moof_offset), (3000, 0x5678));
+        assert_eq!((entry.trun_num, entry.sample_num), (0x0102, 0x030405));
+        assert!(!entry.is_first_sample());
+    }
+
+    #[test]
+    fn verify_read_tfra_v1() {
+        // Version 1, track 1, with 4 byte traf, trun, and sample numbers.
+        let mut data = vec![0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01];
+        data.extend_from_slice(&0x3fu32.to_be_bytes());
+        data.extend_from_slice(&1u32.to_be_bytes());
+
+        data.extend_from_slice(&0x1_0000_0000u64.to_be_bytes());
+        data.extend_from_slice(&0x2_0000_0000u64.to_be_bytes());
+        data.extend_from_slice(&[0, 0, 0, 1, 0, 0, 0, 3, 0, 0, 0, 7]);
+
+        let tfra = TfraAtom::read(&mut BufReader::new(&data), make_header(&data)).unwrap();
+
+        assert_eq!(tfra.track_id, 1);
+        assert_eq!(tfra.entries.len(), 1);
+
+        let entry = &tfra.entries[0];
+        assert_eq!((entry.time, entry.moof_offset), (0x1_0000_0000, 0x2_0000_0000));
+        assert_eq!((entry.trun_num, entry.sample_num), (3, 7));
+    }
+
+    #[test]
+    fn verify_read_tfra_invalid_count() {
+        // An entry count that cannot fit within the atom is an error.
+        let mut data = vec![0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01];
+        data.extend_from_slice(&0u32.to_be_bytes());
+        data.extend_from_slice(&0x1000_0000u32.to_be_bytes());
+        data.extend_from_slice(&[0; 11]);
+
+        assert!(TfraAtom::read(&mut BufReader::new(&data), make_header(&data)).is_err());
+    }
+}
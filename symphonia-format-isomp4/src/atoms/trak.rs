@@ -8,7 +8,9 @@
 use symphonia_core::errors::{decode_error, Result};
 use symphonia_core::io::ReadBytes;
 
-use crate::atoms::{Atom, AtomHeader, AtomIterator, AtomType, EdtsAtom, MdiaAtom, TkhdAtom};
+use crate::atoms::{
+    Atom, AtomHeader, AtomIterator, AtomType, EdtsAtom, MdiaAtom, TkhdAtom, TrefAtom,
+};
 
 /// Track atom.
 #[derive(Debug)]
@@ -21,6 +23,8 @@ pub struct TrakAtom {
     pub edts: Option<EdtsAtom>,
     /// Media atom.
     pub mdia: MdiaAtom,
+    /// Optional, track reference atom.
+    pub tref: Option<TrefAtom>,
 }
 
 impl Atom for TrakAtom {
@@ -34,6 +38,7 @@ impl Atom for TrakAtom {
         let mut tkhd = None;
         let mut edts = None;
         let mut mdia = None;
+        let mut tref = None;
 
         while let Some(header) = iter.next()? {
             match header.atype {
@@ -46,6 +51,9 @@ impl Atom for TrakAtom {
                 AtomType::Media => {
                     mdia = Some(iter.read_atom::<MdiaAtom>()?);
                 }
+                AtomType::TrackReference => {
+                    tref = Some(iter.read_atom::<TrefAtom>()?);
+                }
                 _ => (),
             }
         }
@@ -58,6 +66,6 @@ impl Atom for TrakAtom {
             return decode_error("isomp4: missing mdia atom");
         }
 
-        Ok(TrakAtom { header, tkhd: tkhd.unwrap(), edts, mdia: mdia.unwrap() })
+        Ok(TrakAtom { header, tkhd: tkhd.unwrap(), edts, mdia: mdia.unwrap(), tref })
     }
 }
@@ -0,0 +1,78 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::errors::Result;
+use symphonia_core::io::ReadBytes;
+
+use crate::atoms::{Atom, AtomHeader, AtomIterator, AtomType};
+
+/// Track reference atom.
+#[derive(Debug)]
+pub struct TrefAtom {
+    /// Atom header.
+    header: AtomHeader,
+    /// The track IDs of the chapter tracks referenced by the track.
+    pub chap: Vec<u32>,
+}
+
+impl Atom for TrefAtom {
+    fn header(&self) -> AtomHeader {
+        self.header
+    }
+
+    #[allow(clippy::single_match)]
+    fn read<B: ReadBytes>(reader: &mut B, header: AtomHeader) -> Result<Self> {
+        let mut iter = AtomIterator::new(reader, header);
+
+        let mut chap = Vec::new();
+
+        // Each child atom is a reference type, and contains a list of referenced track IDs.
+        while let Some(header) = iter.next()? {
+            match header.atype {
+                AtomType::ChapterReference => {
+                    for _ in 0..header.data_len / 4 {
+                        chap.push(iter.inner_mut().read_be_u32()?);
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        Ok(TrefAtom { header, chap })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::io::BufReader;
+
+    use super::TrefAtom;
+    use crate::atoms::{Atom, AtomHeader, AtomType};
+
+    #[test]
+    fn verify_read_tref() {
+        // A hint reference to track 4, and a chapter reference to tracks 2 and 3.
+        let mut data = Vec::new();
+        data.extend_from_slice(&[0, 0, 0, 12]);
+        data.extend_from_slice(b"hint");
+        data.extend_from_slice(&4u32.to_be_bytes());
+        data.extend_from_slice(&[0, 0, 0, 16]);
+        data.extend_from_slice(b"chap");
+        data.extend_from_slice(&2u32.to_be_bytes());
+        data.extend_from_slice(&3u32.to_be_bytes());
+
+        let header = AtomHeader {
+            atype: AtomType::TrackReference,
+            atom_len: 8 + data.len() as u64,
+            data_len: data.len() as u64,
+        };
+
+        let tref = TrefAtom::read(&mut BufReader::new(&data), header).unwrap();
+
+        assert_eq!(tref.chap, [2, 3]);
+    }
+}
@@ -11,15 +11,17 @@ use symphonia_core::codecs::CodecParameters;
 use symphonia_core::errors::{decode_error, seek_error, unsupported_error, Result, SeekErrorKind};
 use symphonia_core::formats::prelude::*;
 use symphonia_core::io::{MediaSource, MediaSourceStream, ReadBytes, SeekBuffered};
-use symphonia_core::meta::{Metadata, MetadataLog};
+use symphonia_core::meta::{Metadata, MetadataLog, StandardTagKey, Tag, Value};
 use symphonia_core::probe::{Descriptor, Instantiate, QueryDescriptor};
 use symphonia_core::units::Time;
 
 use std::io::{Seek, SeekFrom};
 use std::sync::Arc;
 
-use crate::atoms::{AtomIterator, AtomType};
-use crate::atoms::{FtypAtom, MetaAtom, MoofAtom, MoovAtom, MvexAtom, SidxAtom, TrakAtom};
+use crate::atoms::{AtomIterator, AtomType, NERO_CHAPTER_UNITS_PER_SEC};
+use crate::atoms::{
+    ChplAtom, FtypAtom, MetaAtom, MfraAtom, MoofAtom, MoovAtom, MvexAtom, SidxAtom, TrakAtom,
+};
 use crate::stream::*;
 
 use log::{debug, info, trace, warn};
@@ -80,6 +82,98 @@ struct SampleDataInfo {
     len: u32,
 }
 
+/// Convert a timestamp from one timescale to another.
+fn rescale_ts(ts: u64, from: u32, to: u32) -> u64 {
+    (u128::from(ts) * u128::from(to) / u128::from(from.max(1))) as u64
+}
+
+/// Make a chapter `Cue` with a title tag.
+fn make_chapter_cue(index: u32, start_ts: u64, title: &str) -> Cue {
+    Cue {
+        index,
+        start_ts,
+        tags: vec![Tag::new(Some(StandardTagKey::TrackTitle), "TITLE", Value::from(title))],
+        points: Vec::new(),
+    }
+}
+
+/// Convert the chapters of a Nero chapter list into `Cue`s with timestamps in the given timescale.
+fn nero_chapters_to_cues(chpl: &ChplAtom, timescale: u32) -> Vec<Cue> {
+    chpl.chapters
+        .iter()
+        .enumerate()
+        .map(|(i, chapter)| {
+            let start_ts = (u128::from(chapter.start) * u128::from(timescale)
+                / u128::from(NERO_CHAPTER_UNITS_PER_SEC)) as u64;
+
+            make_chapter_cue(i as u32 + 1, start_ts, &chapter.title)
+        })
+        .collect()
+}
+
+/// Decode the text of a text track sample.
+fn decode_text_sample(buf: &[u8]) -> String {
+    // A text sample starts with the 16-bit length of the text. The text may be followed by
+    // modifier atoms, which are ignored.
+    let text: &[u8] = match buf {
+        [a, b, rest @ ..] => &rest[..usize::from(u16::from_be_bytes([*a, *b])).min(rest.len())],
+        _ => &[],
+    };
+
+    // The text is UTF-8 unless it starts with a UTF-16 byte order mark.
+    let is_utf16_be = text.starts_with(&[0xfe, 0xff]);
+    let is_utf16_le = text.starts_with(&[0xff, 0xfe]);
+
+    if is_utf16_be || is_utf16_le {
+        let chars: Vec<u16> = text[2..]
+            .chunks_exact(2)
+            .map(|c| {
+                if is_utf16_be {
+                    u16::from_be_bytes([c[0], c[1]])
+                }
+                else {
+                    u16::from_le_bytes([c[0], c[1]])
+                }
+            })
+            .collect();
+
+        String::from_utf16_lossy(&chars)
+    }
+    else {
+        String::from_utf8_lossy(text).into_owned()
+    }
+}
+
+/// Read the samples of a chapter text track into `Cue`s with timestamps in the given timescale.
+fn read_chapter_track(
+    reader: &mut MediaSourceStream,
+    seg: &dyn StreamSegment,
+    track_num: usize,
+    track_timescale: u32,
+    timescale: u32,
+) -> Result<Vec<Cue>> {
+    let mut cues = Vec::new();
+
+    for sample_num in seg.track_sample_range(track_num) {
+        let timing = match seg.sample_timing(track_num, sample_num)? {
+            Some(timing) => timing,
+            None => break,
+        };
+
+        let data_desc = seg.sample_data(track_num, sample_num, true)?;
+
+        reader.seek(SeekFrom::Start(data_desc.base_pos + data_desc.offset.unwrap_or(0)))?;
+
+        let buf = reader.read_boxed_slice_exact(data_desc.size as usize)?;
+
+        let start_ts = rescale_ts(timing.ts, track_timescale, timescale);
+
+        cues.push(make_chapter_cue(sample_num + 1, start_ts, &decode_text_sample(&buf)));
+    }
+
+    Ok(cues)
+}
+
 /// ISO Base Media File Format (MP4, M4A, MOV, etc.) demultiplexer.
 ///
 /// `IsoMp4Reader` implements a demuxer for the ISO Base Media File Format.
@@ -94,6 +188,8 @@ pub struct IsoMp4Reader {
     track_states: Vec<TrackState>,
     /// Optional, movie extends atom used for fragmented streams.
     mvex: Option<Arc<MvexAtom>>,
+    /// Optional, movie fragment random access atom used to seek fragmented streams.
+    mfra: Option<MfraAtom>,
 }
 
 impl IsoMp4Reader {
@@ -229,6 +325,88 @@ impl IsoMp4Reader {
         end_of_stream_error()
     }
 
+    /// If no segment read thus far contains the timestamp for the track `track_num`, use the
+    /// movie fragment random access atom, if present, to jump directly to the fragment containing
+    /// it instead of reading all fragments in-between.
+    ///
+    /// After jumping, only the segment of the moov atom, and the segment of the fragment jumped
+    /// to, are retained.
+    fn try_seek_fragment(&mut self, track_num: usize, ts: u64) -> Result<()> {
+        let (mfra, mvex) = match (&self.mfra, &self.mvex) {
+            (Some(mfra), Some(mvex)) if self.iter.inner_mut().is_seekable() => (mfra, mvex),
+            _ => return Ok(()),
+        };
+
+        for seg in self.segs.iter() {
+            if seg.ts_sample(track_num, ts)?.is_some() {
+                return Ok(());
+            }
+        }
+
+        // Find the last random access point of the track at, or before, the timestamp.
+        let moof_pos = match mfra
+            .tfra(mvex.trexs[track_num].track_id)
+            .and_then(|tfra| tfra.entries.iter().take_while(|entry| entry.time <= ts).last())
+        {
+            Some(entry) => entry.moof_offset,
+            None => return Ok(()),
+        };
+
+        // The random access points at the start of the fragment give the timestamp of the first
+        // sample of each track if the fragment does not.
+        let first_ts = mvex
+            .trexs
+            .iter()
+            .map(|trex| {
+                mfra.tfra(trex.track_id)?
+                    .entries
+                    .iter()
+                    .find(|entry| entry.moof_offset == moof_pos && entry.is_first_sample())
+                    .map(|entry| entry.time)
+            })
+            .collect::<Vec<Option<u64>>>();
+
+        let mvex = mvex.clone();
+
+        debug!("seeking to moof atom at {} using mfra", moof_pos);
+
+        self.iter.seek_to_atom(moof_pos)?;
+
+        match self.iter.next()? {
+            Some(header) if header.atype == AtomType::MovieFragment => (),
+            _ => return decode_error("isomp4: mfra entry does not reference a moof atom"),
+        }
+
+        let moof = self.iter.read_atom::<MoofAtom>()?;
+
+        // Consume the mdat atom following the moof atom, as when reading segments, such that the
+        // samples within it may be read.
+        if let Some(header) = self.iter.next()? {
+            if header.atype == AtomType::MediaData {
+                self.iter.consume_atom();
+            }
+        }
+
+        // Discard all fragment segments since the fragment jumped to will follow the moov segment.
+        self.segs.truncate(1);
+
+        let mut seg = MoofSegment::new(moof, mvex, self.segs[0].as_ref());
+
+        for (track_num, ts) in first_ts.into_iter().enumerate() {
+            if let Some(ts) = ts {
+                seg.set_first_ts(track_num, ts);
+            }
+        }
+
+        self.segs.push(Box::new(seg));
+
+        for state in self.track_states.iter_mut() {
+            state.cur_seg = 0;
+        }
+
+        Ok(())
+    }
+
     fn seek_track_by_time(&mut self, track_num: usize, time: Time) -> Result<SeekedTo> {
         // Convert time to timestamp for the track.
         if let Some(track) = self.tracks.get(track_num) {
@@ -331,6 +509,7 @@ impl FormatReader for IsoMp4Reader {
         let mut ftyp = None;
         let mut moov = None;
         let mut sidx = None;
+        let mut mfra = None;
 
         // Get the total length of the stream, if possible.
         let total_len = if is_seekable {
@@ -396,6 +575,11 @@ impl FormatReader for IsoMp4Reader {
                         break;
                     }
                 }
+                AtomType::MovieFragmentRandomAccess => {
+                    // The mfra atom is typically the last atom of a fragmented stream, and can
+                    // only be read if the stream is seekable.
+                    mfra = Some(iter.read_atom::<MfraAtom>()?);
+                }
                 AtomType::Meta => {
                     // Read the metadata atom and append it to the log.
                     let mut meta = iter.read_atom::<MetaAtom>()?;
@@ -465,13 +649,44 @@ impl FormatReader for IsoMp4Reader {
         // Instantiate a Tracks for all tracks above.
         let tracks = track_states
             .iter()
-            .map(|track| Track::new(track.track_num as u32, track.codec_params()))
+            .zip(moov.traks.iter())
+            .map(|(track, trak)| {
+                let mut track = Track::new(track.track_num as u32, track.codec_params());
+
+                // An undetermined language is the same as an unknown language.
+                let language = &trak.mdia.mdhd.language;
+
+                if !language.is_empty() && language != "und" {
+                    track.language = Some(language.clone());
+                }
+
+                track
+            })
             .collect();
 
         // A Movie Extends (mvex) atom is required to support segmented streams. If the mvex atom is
         // present, wrap it in an Arc so it can be shared amongst all segments.
         let mvex = moov.mvex.take().map(Arc::new);
 
+        // Nero chapters are timestamped in the timebase of the first track.
+        let chpl = moov.udta.as_ref().and_then(|udta| udta.chpl.as_ref());
+
+        let mut cues = match (chpl, moov.traks.first()) {
+            (Some(chpl), Some(trak)) => nero_chapters_to_cues(chpl, trak.mdia.mdhd.timescale),
+            _ => Default::default(),
+        };
+
+        // Find the first track that references a chapter track. The chapters are timestamped in
+        // the timebase of the referencing track.
+        let chapter_track = moov.traks.iter().find_map(|trak| {
+            let id = *trak.tref.as_ref()?.chap.first()?;
+            let track_num = moov.traks.iter().position(|chap| chap.tkhd.id == id)?;
+
+            let chap_timescale = moov.traks[track_num].mdia.mdhd.timescale;
+
+            Some((track_num, chap_timescale, trak.mdia.mdhd.timescale))
+        });
+
         // The number of tracks specified in the moov atom must match the number in the mvex atom.
         if let Some(mvex) = &mvex {
             if mvex.trexs.len() != moov.traks.len() {
@@ -481,15 +696,25 @@ impl FormatReader for IsoMp4Reader {
 
         let segs: Vec<Box<dyn StreamSegment>> = vec![Box::new(MoovSegment::new(moov))];
 
-        Ok(IsoMp4Reader {
-            iter,
-            tracks,
-            cues: Default::default(),
-            metadata,
-            track_states,
-            segs,
-            mvex,
-        })
+        // A chapter track supersedes Nero chapters. Reading the chapter track requires seeking
+        // to its samples, after which the stream is returned to its current position.
+        if let Some((track_num, chap_timescale, timescale)) = chapter_track {
+            if is_seekable {
+                let reader = iter.inner_mut();
+                let pos = reader.pos();
+
+                let seg = &*segs[0];
+
+                match read_chapter_track(reader, seg, track_num, chap_timescale, timescale) {
+                    Ok(chapters) => cues = chapters,
+                    Err(err) => warn!("failed to read chapter track: {}", err),
+                }
+
+                reader.seek(SeekFrom::Start(pos))?;
+            }
+        }
+
+        Ok(IsoMp4Reader { iter, tracks, cues, metadata, track_states, segs, mvex, mfra })
     }
 
     fn next_packet(&mut self) -> Result<Packet> {
@@ -567,6 +792,8 @@ impl FormatReader for IsoMp4Reader {
                     // Convert to time units.
                     let time = selected_track.codec_params.time_base.unwrap().calc_time(ts);
 
+                    self.try_seek_fragment(selected_track_id, ts)?;
+
                     // Seek all tracks excluding the primary track to the desired time.
                     for t in 0..self.track_states.len() {
                         if t != selected_track_id {
@@ -585,6 +812,11 @@ impl FormatReader for IsoMp4Reader {
                 // Select the first track if a selected track was not provided.
                 let selected_track_id = track_id.unwrap_or(0) as usize;
 
+                if let Some(selected_track) = self.tracks.get(selected_track_id) {
+                    let ts = selected_track.codec_params.time_base.unwrap().calc_timestamp(time);
+                    self.try_seek_fragment(selected_track_id, ts)?;
+                }
+
                 // Seek all tracks excluding the selected track and discard the result.
                 for t in 0..self.track_states.len() {
                     if t != selected_track_id {
@@ -602,3 +834,291 @@ impl FormatReader for IsoMp4Reader {
         self.iter.into_inner()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use symphonia_core::formats::{FormatReader, SeekMode, SeekTo};
+    use symphonia_core::io::{MediaSourceStream, ReadBytes};
+
+    use super::{decode_text_sample, IsoMp4Reader};
+
+    /// The number of fragments, and the number of samples in each fragment.
+    const NUM_FRAGMENTS: u8 = 4;
+    const FRAGMENT_SAMPLES: u8 = 10;
+
+    fn atom(atype: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut buf = (8 + body.len() as u32).to_be_bytes().to_vec();
+        buf.extend_from_slice(atype);
+        buf.extend_from_slice(body);
+        buf
+    }
+
+    fn full_atom(atype: &[u8; 4], version: u8, flags: u32, body: &[u8]) -> Vec<u8> {
+        let mut buf = flags.to_be_bytes().to_vec();
+        buf[0] = version;
+        buf.extend_from_slice(body);
+        atom(atype, &buf)
+    }
+
+    fn be_u32s(values: &[u32]) -> Vec<u8> {
+        values.iter().flat_map(|value| value.to_be_bytes().to_vec()).collect()
+    }
+
+    /// Make a trak atom with a single sample description, and the given sample tables, followed
+    /// by any other atoms of the track.
+    fn make_trak(
+        track_id: u32,
+        timescale: u32,
+        handler: &[u8; 4],
+        sample_entry: &[u8],
+        tables: &[u8],
+        other: &[u8],
+    ) -> Vec<u8> {
+        let tkhd =
+            full_atom(b"tkhd", 0, 0, &be_u32s(&[0, 0, track_id, 0, 0, 0, 0, 0, 0x0100_0000]));
+        let mdhd = full_atom(b"mdhd", 0, 0, &be_u32s(&[0, 0, timescale, 0, 0x55c4_0000]));
+        let hdlr = full_atom(b"hdlr", 0, 0, &[&be_u32s(&[0])[..], handler, &[0; 12]].concat());
+
+        let stsd = full_atom(b"stsd", 0, 0, &[&be_u32s(&[1])[..], sample_entry].concat());
+        let stbl = atom(b"stbl", &[&stsd[..], tables].concat());
+
+        let minf = atom(b"minf", &[full_atom(b"smhd", 0, 0, &[0; 4]), stbl].concat());
+        let mdia = atom(b"mdia", &[mdhd, hdlr, minf].concat());
+
+        atom(b"trak", &[&tkhd[..], other, &mdia].concat())
+    }
+
+    /// Make the sample tables of a track with the given sample durations and sizes, with all
+    /// samples in a single chunk at the given position.
+    fn make_sample_tables(durations: &[u32], sizes: &[u32], chunk_pos: u32) -> Vec<u8> {
+        let num_samples = sizes.len() as u32;
+
+        let stts: Vec<u32> = durations.iter().flat_map(|&dur| vec![1, dur]).collect();
+
+        [
+            full_atom(b"stts", 0, 0, &be_u32s(&[&[durations.len() as u32][..], &stts].concat())),
+            full_atom(b"stsc", 0, 0, &be_u32s(&[1, 1, num_samples, 1])),
+            full_atom(b"stsz", 0, 0, &be_u32s(&[&[0, num_samples][..], sizes].concat())),
+            full_atom(b"stco", 0, 0, &be_u32s(&[1, chunk_pos])),
+        ]
+        .concat()
+    }
+
+    /// Make a version 0 audio sample entry of 16-bit mono samples at 1 kHz.
+    fn make_pcm_sample_entry() -> Vec<u8> {
+        let mut sowt = vec![0, 0, 0, 0, 0, 0, 0, 1];
+        sowt.extend_from_slice(&be_u32s(&[0, 0, 0x0001_0010, 0, 1000 << 16]));
+        atom(b"sowt", &sowt)
+    }
+
+    /// Make the moov atom of a fragmented stream with a single track of 16-bit mono PCM at 1 kHz.
+    /// Each sample is 100 ms long, and 4 bytes in size.
+    fn make_moov() -> Vec<u8> {
+        let mvhd = full_atom(b"mvhd", 0, 0, &be_u32s(&[0, 0, 1000, 0, 0x1_0000, 0x0100_0000]));
+
+        // The moov atom contains no samples.
+        let tables = [
+            full_atom(b"stts", 0, 0, &be_u32s(&[0])),
+            full_atom(b"stsc", 0, 0, &be_u32s(&[0])),
+            full_atom(b"stsz", 0, 0, &be_u32s(&[0, 0])),
+            full_atom(b"stco", 0, 0, &be_u32s(&[0])),
+        ]
+        .concat();
+
+        let trak = make_trak(1, 1000, b"soun", &make_pcm_sample_entry(), &tables, &[]);
+
+        let trex = full_atom(b"trex", 0, 0, &be_u32s(&[1, 1, 100, 4, 0]));
+        let mvex = atom(b"mvex", &trex);
+
+        atom(b"moov", &[mvhd, trak, mvex].concat())
+    }
+
+    /// Make a moof and mdat atom pair for a fragment. The data of each sample is the fragment
+    /// number, followed by the sample number, and two zero bytes.
+    fn make_fragment(frag: u8, tfdt: Option<u64>) -> Vec<u8> {
+        let mfhd = full_atom(b"mfhd", 0, 0, &be_u32s(&[u32::from(frag) + 1]));
+
+        // The sample data is relative to the start of the moof atom.
+        let tfhd = full_atom(b"tfhd", 0, 0x2_0000, &be_u32s(&[1]));
+
+        let tfdt = match tfdt {
+            Some(ts) => full_atom(b"tfdt", 1, 0, &ts.to_be_bytes()),
+            None => Vec::new(),
+        };
+
+        // The trun data offset is the length of the moof atom, plus the mdat atom header.
+        let moof_len = 8 + mfhd.len() + 8 + tfhd.len() + tfdt.len() + 20;
+
+        let trun = full_atom(
+            b"trun",
+            0,
+            0x1,
+            &be_u32s(&[u32::from(FRAGMENT_SAMPLES), moof_len as u32 + 8]),
+        );
+
+        let traf = atom(b"traf", &[tfhd, tfdt, trun].concat());
+
+        let data: Vec<u8> = (0..FRAGMENT_SAMPLES).flat_map(|i| vec![frag, i, 0, 0]).collect();
+
+        [atom(b"moof", &[mfhd, traf].concat()), atom(b"mdat", &data)].concat()
+    }
+
+    /// Make a fragmented stream with random access points at the start of each fragment. Each
+    /// fragment is 1 s long, and is followed by a 1 s gap. The third fragment does not have a base
+    /// media decode time, and is only correctly timestamped by its random access point.
+    fn make_fragmented_mp4() -> Vec<u8> {
+        let mut buf = atom(b"ftyp", b"isom\0\0\0\0isom");
+        buf.extend(make_moov());
+
+        // A version 1 tfra atom of track 1, with 1 byte traf, trun, and sample numbers.
+        let mut tfra = be_u32s(&[1, 0, u32::from(NUM_FRAGMENTS)]);
+
+        for frag in 0..NUM_FRAGMENTS {
+            let ts = 2000 * u64::from(frag);
+
+            tfra.extend_from_slice(&ts.to_be_bytes());
+            tfra.extend_from_slice(&(buf.len() as u64).to_be_bytes());
+            tfra.extend_from_slice(&[1, 1, 1]);
+
+            buf.extend(make_fragment(frag, if frag == 2 { None } else { Some(ts) }));
+        }
+
+        let tfra = full_atom(b"tfra", 1, 0, &tfra);
+        let mfro = full_atom(b"mfro", 0, 0, &be_u32s(&[8 + tfra.len() as u32 + 16]));
+
+        buf.extend(atom(b"mfra", &[tfra, mfro].concat()));
+        buf
+    }
+
+    fn make_reader(buf: Vec<u8>) -> IsoMp4Reader {
+        let mut mss = MediaSourceStream::new(Box::new(Cursor::new(buf)), Default::default());
+
+        // The reader is instantiated after the ftyp marker is found by the probe.
+        mss.ignore_bytes(4).unwrap();
+
+        IsoMp4Reader::try_new(mss, &Default::default()).unwrap()
+    }
+
+    #[test]
+    fn verify_seek_fragment() {
+        let mut reader = make_reader(make_fragmented_mp4());
+
+        // Seek into the third fragment.
+        let seeked = reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 4550, track_id: 0 });
+        assert_eq!(seeked.unwrap().actual_ts, 4500);
+
+        for i in 5..FRAGMENT_SAMPLES {
+            let packet = reader.next_packet().unwrap();
+            assert_eq!(packet.ts, 4000 + 100 * u64::from(i));
+            assert_eq!(packet.dur, 100);
+            assert_eq!(&packet.data[..], &[2, i, 0, 0]);
+        }
+
+        // The following fragment is read sequentially.
+        let packet = reader.next_packet().unwrap();
+        assert_eq!(packet.ts, 6000);
+        assert_eq!(&packet.data[..], &[3, 0, 0, 0]);
+
+        // Seek backward into the first fragment, which is no longer read.
+        let seeked = reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 999, track_id: 0 });
+        assert_eq!(seeked.unwrap().actual_ts, 900);
+
+        let packet = reader.next_packet().unwrap();
+        assert_eq!(packet.ts, 900);
+        assert_eq!(&packet.data[..], &[0, 9, 0, 0]);
+
+        let packet = reader.next_packet().unwrap();
+        assert_eq!(packet.ts, 2000);
+        assert_eq!(&packet.data[..], &[1, 0, 0, 0]);
+
+        // A seek into a fragment already read does not use the random access points.
+        let seeked = reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 2200, track_id: 0 });
+        assert_eq!(seeked.unwrap().actual_ts, 2200);
+        assert_eq!(&reader.next_packet().unwrap().data[..], &[1, 2, 0, 0]);
+    }
+
+    /// Make a text sample of the text, followed by the modifier atoms.
+    fn make_text_sample(text: &[u8], modifiers: &[u8]) -> Vec<u8> {
+        [&(text.len() as u16).to_be_bytes()[..], text, modifiers].concat()
+    }
+
+    #[test]
+    fn verify_decode_text_sample() {
+        assert_eq!(decode_text_sample(&make_text_sample(b"Intro", &[])), "Intro");
+
+        // Modifier atoms following the text are ignored.
+        let encd = atom(b"encd", &be_u32s(&[0x100]));
+        assert_eq!(decode_text_sample(&make_text_sample(b"Intro", &encd)), "Intro");
+
+        // UTF-16 text starts with a byte order mark.
+        let text = [0xfe, 0xff, 0x00, 0x56, 0x00, 0xe9];
+        assert_eq!(decode_text_sample(&make_text_sample(&text, &[])), "V\u{e9}");
+
+        let text = [0xff, 0xfe, 0x56, 0x00, 0xe9, 0x00];
+        assert_eq!(decode_text_sample(&make_text_sample(&text, &[])), "V\u{e9}");
+
+        // The text length is limited to the length of the sample.
+        assert_eq!(decode_text_sample(&[0x00, 0x10, b'A', b'B']), "AB");
+        assert_eq!(decode_text_sample(&[0x00]), "");
+    }
+
+    #[test]
+    fn verify_chapter_track() {
+        let ftyp = atom(b"ftyp", b"M4A \0\0\0\0M4A isom");
+
+        // The chapter samples, timestamped at 0 s, 1 s, and 3 s in a 600 Hz timebase.
+        let samples = [
+            make_text_sample(b"Intro", &[]),
+            make_text_sample(&[0xfe, 0xff, 0x00, 0x56, 0x00, 0x31], &[]),
+            make_text_sample(b"Outro", &atom(b"encd", &be_u32s(&[0x100]))),
+        ];
+
+        let sizes: Vec<u32> = samples.iter().map(|sample| sample.len() as u32).collect();
+        let mdat = atom(b"mdat", &samples.concat());
+
+        let chunk_pos = (ftyp.len() + 8) as u32;
+
+        // The audio track references the chapter track, and contains no samples.
+        let audio_tables = [
+            full_atom(b"stts", 0, 0, &be_u32s(&[0])),
+            full_atom(b"stsc", 0, 0, &be_u32s(&[0])),
+            full_atom(b"stsz", 0, 0, &be_u32s(&[0, 0])),
+            full_atom(b"stco", 0, 0, &be_u32s(&[0])),
+        ]
+        .concat();
+
+        let tref = atom(b"tref", &atom(b"chap", &be_u32s(&[2])));
+
+        let audio = make_trak(1, 1000, b"soun", &make_pcm_sample_entry(), &audio_tables, &tref);
+
+        let text_tables = make_sample_tables(&[600, 1200, 300], &sizes, chunk_pos);
+        let text = make_trak(2, 600, b"text", &atom(b"text", &[0; 8]), &text_tables, &[]);
+
+        // A Nero chapter list is superseded by the chapter track.
+        let chpl = full_atom(b"chpl", 1, 0, &[0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 1, b'X']);
+        let udta = atom(b"udta", &chpl);
+
+        let mvhd = full_atom(b"mvhd", 0, 0, &be_u32s(&[0, 0, 1000, 0, 0x1_0000, 0x0100_0000]));
+        let moov = atom(b"moov", &[mvhd, audio, text, udta].concat());
+
+        let reader = make_reader([ftyp, mdat, moov].concat());
+
+        // The chapters are timestamped in the timebase of the audio track.
+        let cues: Vec<(u32, u64, String)> = reader
+            .cues()
+            .iter()
+            .map(|cue| (cue.index, cue.start_ts, cue.tags[0].value.to_string()))
+            .collect();
+
+        assert_eq!(
+            cues,
+            [
+                (1, 0, "Intro".to_string()),
+                (2, 1000, "V1".to_string()),
+                (3, 3000, "Outro".to_string())
+            ]
+        );
+    }
+}
@@ -110,6 +110,12 @@ impl MoofSegment {
                     info.total_sample_duration += trun.total_duration(default_dur);
                 }
 
+                // The decode time of the first sample, if provided, is authoritative. This allows
+                // fragments to be read after a discontinuity, such as a seek.
+                if let Some(tfdt) = &traf.tfdt {
+                    info.first_ts = tfdt.base_media_decode_time;
+                }
+
                 info.total_sample_count = traf.total_sample_count;
                 info.traf_idx = Some(traf_idx);
             }
@@ -120,6 +126,16 @@ impl MoofSegment {
         MoofSegment { moof, mvex, seq }
     }
 
+    /// Sets the timestamp of the first sample of the track identified by `track_num`, unless the
+    /// track fragment provides the decode time of its first sample.
+    pub fn set_first_ts(&mut self, track_num: usize, ts: u64) {
+        let has_tfdt = self.try_get_traf(track_num).map_or(false, |traf| traf.tfdt.is_some());
+
+        if !has_tfdt {
+            self.seq[track_num].first_ts = ts;
+        }
+    }
+
     /// Try to get the Track Fragment atom associated with the track identified by `track_num`.
     fn try_get_traf(&self, track_num: usize) -> Option<&TrafAtom> {
         debug_assert!(track_num < self.seq.len());
@@ -174,6 +190,11 @@ impl StreamSegment for MoofSegment {
         let mut sample_num = self.seq[track_num].first_sample;
         let mut ts_accum = self.seq[track_num].first_ts;
 
+        // The timestamp precedes this fragment.
+        if ts < ts_accum {
+            return Ok(None);
+        }
+
         let default_dur = traf
             .tfhd
             .default_sample_duration
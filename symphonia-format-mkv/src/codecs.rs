@@ -23,6 +23,7 @@ pub(crate) fn codec_id_to_type(track: &TrackElement) -> Option<CodecType> {
         "A_AAC/MPEG2/MAIN" | "A_AAC/MPEG2/LC" | "A_AAC/MPEG2/LC/SBR" | "A_AAC/MPEG2/SSR"
         | "A_AAC/MPEG4/MAIN" | "A_AAC/MPEG4/LC" | "A_AAC/MPEG4/LC/SBR" | "A_AAC/MPEG4/SSR"
         | "A_AAC/MPEG4/LTP" | "A_AAC" => Some(codecs::CODEC_TYPE_AAC),
+        "A_AC3" => Some(codecs::CODEC_TYPE_EAC3),
         "A_PCM/INT/BIG" => match bit_depth? {
             16 => Some(codecs::CODEC_TYPE_PCM_S16BE),
             24 => Some(codecs::CODEC_TYPE_PCM_S24BE),
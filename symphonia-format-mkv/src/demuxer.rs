@@ -15,7 +15,8 @@ use symphonia_core::errors::{
     decode_error, end_of_stream_error, seek_error, unsupported_error, Error, Result, SeekErrorKind,
 };
 use symphonia_core::formats::{
-    Cue, FormatOptions, FormatReader, Packet, SeekMode, SeekTo, SeekedTo, Track,
+    Cue, FormatOptions, FormatReader, Packet, SeekMode, SeekTo, SeekedTo, Track, TrackFilter,
+    TrackFlags,
 };
 use symphonia_core::io::{BufReader, MediaSource, MediaSourceStream, ReadBytes};
 use symphonia_core::meta::{Metadata, MetadataLog};
@@ -31,8 +32,8 @@ use crate::ebml::{EbmlElement, ElementHeader, ElementIterator};
 use crate::element_ids::{ElementType, ELEMENTS};
 use crate::lacing::{extract_frames, read_xiph_sizes, Frame};
 use crate::segment::{
-    BlockGroupElement, ClusterElement, CuesElement, InfoElement, SeekHeadElement, TagsElement,
-    TracksElement,
+    AttachmentsElement, BlockGroupElement, ClusterElement, CuesElement, InfoElement,
+    SeekHeadElement, TagsElement, TracksElement,
 };
 
 #[allow(dead_code)]
@@ -59,6 +60,10 @@ pub struct MkvReader {
     frames: VecDeque<Frame>,
     timestamp_scale: u64,
     clusters: Vec<ClusterElement>,
+    /// If true, the padding signalled by a block group is trimmed from its frames.
+    gapless: bool,
+    /// If set, the frames of tracks not selected by the filter are discarded.
+    track_filter: Option<TrackFilter>,
 }
 
 #[derive(Debug)]
@@ -195,6 +200,46 @@ impl MkvReader {
         }
     }
 
+    /// Trim the discard padding, in nanoseconds, of a block from the frames extracted from it,
+    /// starting at the frame with index `first_frame`. Positive padding is trimmed from the end of
+    /// the last frame, and negative padding from the start of the first frame.
+    fn trim_discard_padding(&mut self, first_frame: usize, padding: i64) {
+        let frame = match padding {
+            p if p > 0 => self.frames.back_mut(),
+            p if p < 0 => self.frames.get_mut(first_frame),
+            _ => None,
+        };
+
+        let frame = match frame {
+            Some(frame) => frame,
+            None => return,
+        };
+
+        let sample_rate = match self.track_states.get(&frame.track) {
+            Some(state) => match state.codec_params.sample_rate {
+                Some(sample_rate) => sample_rate,
+                None => return,
+            },
+            None => return,
+        };
+
+        let nanos = padding.unsigned_abs();
+
+        // Convert the padding to the number of decoded frames, and to the time base of the track.
+        let trim = (u128::from(nanos) * u128::from(sample_rate) / 1_000_000_000) as u32;
+        let dur = nanos / self.timestamp_scale;
+
+        if padding > 0 {
+            frame.trim_end = trim;
+        }
+        else {
+            frame.trim_start = trim;
+            frame.timestamp += dur.min(frame.duration);
+        }
+
+        frame.duration = frame.duration.saturating_sub(dur);
+    }
+
     fn next_element(&mut self) -> Result<()> {
         if let Some(ClusterState { end: Some(end), .. }) = &self.current_cluster {
             // Make sure we don't read past the current cluster if its size is known.
@@ -270,6 +315,9 @@ impl MkvReader {
                 };
 
                 let group = self.iter.read_element_data::<BlockGroupElement>()?;
+
+                let first_frame = self.frames.len();
+
                 extract_frames(
                     &group.data,
                     group.duration,
@@ -278,6 +326,10 @@ impl MkvReader {
                     self.timestamp_scale,
                     &mut self.frames,
                 )?;
+
+                if let (true, Some(padding)) = (self.gapless, group.discard_padding) {
+                    self.trim_discard_padding(first_frame, padding);
+                }
             }
             ElementType::Tags => {
                 let tags = self.iter.read_element_data::<TagsElement>()?;
@@ -298,7 +350,7 @@ impl MkvReader {
 }
 
 impl FormatReader for MkvReader {
-    fn try_new(mut reader: MediaSourceStream, _options: &FormatOptions) -> Result<Self>
+    fn try_new(mut reader: MediaSourceStream, options: &FormatOptions) -> Result<Self>
     where
         Self: Sized,
     {
@@ -333,6 +385,7 @@ impl FormatReader for MkvReader {
         let mut clusters = Vec::new();
         let mut metadata = MetadataLog::default();
         let mut current_cluster = None;
+        let mut has_attachments = false;
 
         let mut seek_positions = Vec::new();
         while let Ok(Some(header)) = it.read_child_header() {
@@ -369,6 +422,11 @@ impl FormatReader for MkvReader {
                     let tags = it.read_element_data::<TagsElement>()?;
                     metadata.push(tags.to_metadata());
                 }
+                ElementType::Attachments => {
+                    let attachments = it.read_element_data::<AttachmentsElement>()?;
+                    metadata.push(attachments.to_metadata());
+                    has_attachments = true;
+                }
                 ElementType::Cluster => {
                     // Set state for current cluster for the first call of `next_element`.
                     current_cluster = Some(ClusterState { timestamp: None, end: header.end() });
@@ -401,6 +459,12 @@ impl FormatReader for MkvReader {
                         let tags = it.read_element::<TagsElement>()?;
                         metadata.push(tags.to_metadata());
                     }
+                    // The attachments may have been read already if they precede the first
+                    // cluster.
+                    ElementType::Attachments if !has_attachments => {
+                        let attachments = it.read_element::<AttachmentsElement>()?;
+                        metadata.push(attachments.to_metadata());
+                    }
                     ElementType::Cues => {
                         let cues = it.read_element::<CuesElement>()?;
                         for cue in cues.points.into_vec() {
@@ -496,11 +560,17 @@ impl FormatReader for MkvReader {
                 }
             }
 
+            let mut flags = TrackFlags::empty();
+            flags.set(TrackFlags::DEFAULT, track.flag_default);
+            flags.set(TrackFlags::FORCED, track.flag_forced);
+
             let track_id = track.number as u32;
             tracks.push(Track {
                 id: track_id,
                 codec_params: codec_params.clone(),
                 language: track.language,
+                name: track.name,
+                flags,
             });
 
             states.insert(
@@ -513,6 +583,12 @@ impl FormatReader for MkvReader {
             );
         }
 
+        if let Some(filter) = options.track_filter {
+            if !tracks.iter().any(|track| filter.is_selected(track.id, &[])) {
+                return unsupported_error("mkv: selected track not found");
+            }
+        }
+
         Ok(Self {
             iter: it,
             tracks,
@@ -523,6 +599,8 @@ impl FormatReader for MkvReader {
             frames: VecDeque::new(),
             timestamp_scale: info.timestamp_scale,
             clusters,
+            gapless: options.enable_gapless,
+            track_filter: options.track_filter,
         })
     }
 
@@ -567,10 +645,18 @@ impl FormatReader for MkvReader {
     fn next_packet(&mut self) -> Result<Packet> {
         loop {
             if let Some(frame) = self.frames.pop_front() {
-                return Ok(Packet::new_from_boxed_slice(
+                if let Some(filter) = &self.track_filter {
+                    if !filter.is_selected(frame.track, &[]) {
+                        continue;
+                    }
+                }
+
+                return Ok(Packet::new_trimmed_from_boxed_slice(
                     frame.track,
                     frame.timestamp,
                     frame.duration,
+                    frame.trim_start,
+                    frame.trim_end,
                     frame.data,
                 ));
             }
@@ -154,6 +154,13 @@ pub enum ElementType {
     ChapLanguage,
     ChapLanguageIetf,
     ChapCountry,
+    Attachments,
+    AttachedFile,
+    FileDescription,
+    FileName,
+    FileMimeType,
+    FileData,
+    FileUid,
     Tags,
     Tag,
     Targets,
@@ -174,7 +181,8 @@ impl ElementType {
     pub(crate) fn is_top_level(&self) -> bool {
         matches!(
             self,
-            ElementType::Cluster
+            ElementType::Attachments
+                | ElementType::Cluster
                 | ElementType::Cues
                 | ElementType::Info
                 | ElementType::SeekHead
@@ -319,6 +327,13 @@ lazy_static! {
         elems.insert(0x437C, (Type::String, ElementType::ChapLanguage));
         elems.insert(0x437D, (Type::String, ElementType::ChapLanguageIetf));
         elems.insert(0x437E, (Type::String, ElementType::ChapCountry));
+        elems.insert(0x1941A469, (Type::Master, ElementType::Attachments));
+        elems.insert(0x61A7, (Type::Master, ElementType::AttachedFile));
+        elems.insert(0x467E, (Type::String, ElementType::FileDescription));
+        elems.insert(0x466E, (Type::String, ElementType::FileName));
+        elems.insert(0x4660, (Type::String, ElementType::FileMimeType));
+        elems.insert(0x465C, (Type::Binary, ElementType::FileData));
+        elems.insert(0x46AE, (Type::Unsigned, ElementType::FileUid));
         elems.insert(0x1254C367, (Type::Master, ElementType::Tags));
         elems.insert(0x7373, (Type::Master, ElementType::Tag));
         elems.insert(0x63C0, (Type::Master, ElementType::Targets));
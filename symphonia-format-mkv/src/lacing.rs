@@ -69,9 +69,19 @@ pub(crate) struct Frame {
     /// Absolute frame timestamp.
     pub(crate) timestamp: u64,
     pub(crate) duration: u64,
+    /// The number of decoded frames to trim from the start of the frame.
+    pub(crate) trim_start: u32,
+    /// The number of decoded frames to trim from the end of the frame.
+    pub(crate) trim_end: u32,
     pub(crate) data: Box<[u8]>,
 }
 
+impl Frame {
+    fn new(track: u32, timestamp: u64, duration: u64, data: Box<[u8]>) -> Self {
+        Frame { track, timestamp, duration, trim_start: 0, trim_end: 0, data }
+    }
+}
+
 pub(crate) fn calc_abs_block_timestamp(cluster_ts: u64, rel_block_ts: i16) -> u64 {
     if rel_block_ts < 0 {
         cluster_ts - (-rel_block_ts) as u64
@@ -104,7 +114,7 @@ pub(crate) fn extract_frames(
         Lacing::None => {
             let data = reader.read_boxed_slice_exact(block.len() - reader.pos() as usize)?;
             let duration = block_duration.or(default_frame_duration).unwrap_or(0);
-            buffer.push_back(Frame { track, timestamp, data, duration });
+            buffer.push_back(Frame::new(track, timestamp, duration, data));
         }
         Lacing::Xiph | Lacing::Ebml => {
             // Read number of stored sizes which is actually `number of frames` - 1
@@ -123,14 +133,14 @@ pub(crate) fn extract_frames(
 
             for frame_size in sizes {
                 let data = reader.read_boxed_slice_exact(frame_size as usize)?;
-                buffer.push_back(Frame { track, timestamp, data, duration: frame_duration });
+                buffer.push_back(Frame::new(track, timestamp, frame_duration, data));
                 timestamp += frame_duration;
             }
 
             // Size of last frame is not provided so we read to the end of the block.
             let size = block.len() - reader.pos() as usize;
             let data = reader.read_boxed_slice_exact(size)?;
-            buffer.push_back(Frame { track, timestamp, data, duration: frame_duration });
+            buffer.push_back(Frame::new(track, timestamp, frame_duration, data));
         }
         Lacing::FixedSize => {
             let frames = reader.read_byte()? as usize + 1;
@@ -145,7 +155,7 @@ pub(crate) fn extract_frames(
             let frame_size = total_size / frames;
             for _ in 0..frames {
                 let data = reader.read_boxed_slice_exact(frame_size)?;
-                buffer.push_back(Frame { track, timestamp, data, duration: frame_duration });
+                buffer.push_back(Frame::new(track, timestamp, frame_duration, data));
                 timestamp += frame_duration;
             }
         }
@@ -153,3 +163,40 @@ pub(crate) fn extract_frames(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, VecDeque};
+
+    use super::extract_frames;
+
+    fn extract(block: &[u8]) -> Vec<(u64, u64, Vec<u8>)> {
+        let mut frames = VecDeque::new();
+        extract_frames(block, Some(30), &HashMap::new(), 100, 1, &mut frames).unwrap();
+        frames.into_iter().map(|f| (f.timestamp, f.duration, f.data.into_vec())).collect()
+    }
+
+    #[test]
+    fn verify_lacing() {
+        let expected = vec![(100, 10, vec![1, 2]), (110, 10, vec![3, 4, 5]), (120, 10, vec![6])];
+
+        // Xiph lacing.
+        let block = [0x81, 0, 0, 0x02, 2, 2, 3, 1, 2, 3, 4, 5, 6];
+        assert_eq!(extract(&block), expected);
+
+        // EBML lacing, the size of the second frame is stored as a signed difference of +1.
+        let block = [0x81, 0, 0, 0x06, 2, 0x82, 0xc0, 1, 2, 3, 4, 5, 6];
+        assert_eq!(extract(&block), expected);
+
+        // Fixed-size lacing.
+        let block = [0x81, 0, 0, 0x04, 2, 1, 2, 3, 4, 5, 6];
+        assert_eq!(
+            extract(&block),
+            vec![(100, 10, vec![1, 2]), (110, 10, vec![3, 4]), (120, 10, vec![5, 6])]
+        );
+
+        // No lacing.
+        let block = [0x81, 0, 0, 0x00, 1, 2, 3];
+        assert_eq!(extract(&block), vec![(100, 30, vec![1, 2, 3])]);
+    }
+}
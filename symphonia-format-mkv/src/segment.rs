@@ -7,7 +7,8 @@
 
 use symphonia_core::errors::{Error, Result};
 use symphonia_core::io::{BufReader, ReadBytes};
-use symphonia_core::meta::{MetadataBuilder, MetadataRevision, Tag, Value};
+use symphonia_core::meta::{MetadataBuilder, MetadataRevision, StandardTagKey, StandardVisualKey};
+use symphonia_core::meta::{Tag, Value, VendorData, Visual};
 
 use crate::ebml::{read_unsigned_vint, Element, ElementData, ElementHeader};
 use crate::element_ids::ElementType;
@@ -18,11 +19,14 @@ use crate::lacing::calc_abs_block_timestamp;
 pub(crate) struct TrackElement {
     pub(crate) number: u64,
     pub(crate) uid: u64,
+    pub(crate) name: Option<String>,
     pub(crate) language: Option<String>,
     pub(crate) codec_id: String,
     pub(crate) codec_private: Option<Box<[u8]>>,
     pub(crate) audio: Option<AudioElement>,
     pub(crate) default_duration: Option<u64>,
+    pub(crate) flag_default: bool,
+    pub(crate) flag_forced: bool,
 }
 
 impl Element for TrackElement {
@@ -31,11 +35,14 @@ impl Element for TrackElement {
     fn read<B: ReadBytes>(reader: &mut B, header: ElementHeader) -> Result<Self> {
         let mut number = None;
         let mut uid = None;
+        let mut name = None;
         let mut language = None;
         let mut audio = None;
         let mut codec_private = None;
         let mut codec_id = None;
         let mut default_duration = None;
+        let mut flag_default = true;
+        let mut flag_forced = false;
 
         let mut it = header.children(reader);
         while let Some(header) = it.read_header()? {
@@ -46,6 +53,9 @@ impl Element for TrackElement {
                 ElementType::TrackUid => {
                     uid = Some(it.read_u64()?);
                 }
+                ElementType::Name => {
+                    name = Some(it.read_string()?);
+                }
                 ElementType::Language => {
                     language = Some(it.read_string()?);
                 }
@@ -61,6 +71,12 @@ impl Element for TrackElement {
                 ElementType::DefaultDuration => {
                     default_duration = Some(it.read_u64()?);
                 }
+                ElementType::FlagDefault => {
+                    flag_default = it.read_u64()? != 0;
+                }
+                ElementType::FlagForced => {
+                    flag_forced = it.read_u64()? != 0;
+                }
                 other => {
                     log::debug!("ignored element {:?}", other);
                 }
@@ -70,11 +86,14 @@ impl Element for TrackElement {
         Ok(Self {
             number: number.ok_or(Error::DecodeError("mkv: missing track number"))?,
             uid: uid.ok_or(Error::DecodeError("mkv: missing track UID"))?,
+            name,
             language,
             codec_id: codec_id.ok_or(Error::DecodeError("mkv: missing codec id"))?,
             codec_private,
             audio,
             default_duration,
+            flag_default,
+            flag_forced,
         })
     }
 }
@@ -412,6 +431,9 @@ impl Element for CueTrackPositionsElement {
 pub(crate) struct BlockGroupElement {
     pub(crate) data: Box<[u8]>,
     pub(crate) duration: Option<u64>,
+    /// The duration, in nanoseconds, of padding added to the end of the block if positive, or to
+    /// the start of the block if negative.
+    pub(crate) discard_padding: Option<i64>,
 }
 
 impl Element for BlockGroupElement {
@@ -422,10 +444,14 @@ impl Element for BlockGroupElement {
 
         let mut data = None;
         let mut block_duration = None;
+        let mut discard_padding = None;
         while let Some(header) = it.read_header()? {
             match header.etype {
                 ElementType::DiscardPadding => {
-                    let _nanos = it.read_data()?;
+                    discard_padding = match it.read_data()? {
+                        ElementData::SignedInt(nanos) => Some(nanos),
+                        _ => return Err(Error::DecodeError("mkv: expected a signed int")),
+                    };
                 }
                 ElementType::Block => {
                     data = Some(it.read_boxed_slice()?);
@@ -441,6 +467,7 @@ impl Element for BlockGroupElement {
         Ok(Self {
             data: data.ok_or(Error::DecodeError("mkv: missing block inside block group"))?,
             duration: block_duration,
+            discard_padding,
         })
     }
 }
@@ -511,6 +538,132 @@ impl Element for ClusterElement {
     }
 }
 
+#[derive(Debug)]
+pub(crate) struct AttachmentsElement {
+    pub(crate) files: Box<[AttachedFileElement]>,
+}
+
+impl Element for AttachmentsElement {
+    const ID: ElementType = ElementType::Attachments;
+
+    fn read<B: ReadBytes>(reader: &mut B, header: ElementHeader) -> Result<Self> {
+        let mut files = Vec::new();
+
+        let mut it = header.children(reader);
+        while let Some(header) = it.read_header()? {
+            match header.etype {
+                ElementType::AttachedFile => {
+                    files.push(it.read_element_data::<AttachedFileElement>()?);
+                }
+                other => {
+                    log::debug!("ignored element {:?}", other);
+                }
+            }
+        }
+
+        Ok(Self { files: files.into_boxed_slice() })
+    }
+}
+
+impl AttachmentsElement {
+    /// Attached images are exposed as visuals, and all other files as vendor data identified by
+    /// their file name.
+    pub(crate) fn to_metadata(&self) -> MetadataRevision {
+        let mut metadata = MetadataBuilder::new();
+        for file in self.files.iter() {
+            if file.mime_type.starts_with("image/") {
+                let mut tags = vec![Tag::new(None, "FILENAME", Value::from(file.name.as_str()))];
+
+                if let Some(description) = &file.description {
+                    tags.push(Tag::new(
+                        Some(StandardTagKey::Description),
+                        "",
+                        Value::from(description.as_str()),
+                    ));
+                }
+
+                metadata.add_visual(Visual {
+                    media_type: file.mime_type.clone(),
+                    dimensions: None,
+                    bits_per_pixel: None,
+                    color_mode: None,
+                    usage: attachment_visual_key(&file.name),
+                    tags,
+                    data: file.data.clone(),
+                });
+            }
+            else {
+                metadata.add_vendor_data(VendorData {
+                    ident: file.name.clone(),
+                    data: file.data.clone(),
+                });
+            }
+        }
+        metadata.metadata()
+    }
+}
+
+/// Get the usage of an attached image from its file name. As per the Matroska cover art
+/// guidelines, the cover is named "cover", "small_cover", "cover_land", or "small_cover_land",
+/// followed by the extension.
+fn attachment_visual_key(name: &str) -> Option<StandardVisualKey> {
+    let stem = name.rsplit_once('.').map_or(name, |(stem, _)| stem).to_ascii_lowercase();
+
+    match stem.as_str() {
+        "cover" | "small_cover" | "cover_land" | "small_cover_land" => {
+            Some(StandardVisualKey::FrontCover)
+        }
+        _ => None,
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct AttachedFileElement {
+    pub(crate) name: String,
+    pub(crate) mime_type: String,
+    pub(crate) description: Option<String>,
+    pub(crate) data: Box<[u8]>,
+}
+
+impl Element for AttachedFileElement {
+    const ID: ElementType = ElementType::AttachedFile;
+
+    fn read<B: ReadBytes>(reader: &mut B, header: ElementHeader) -> Result<Self> {
+        let mut name = None;
+        let mut mime_type = None;
+        let mut description = None;
+        let mut data = None;
+
+        let mut it = header.children(reader);
+        while let Some(header) = it.read_header()? {
+            match header.etype {
+                ElementType::FileName => {
+                    name = Some(it.read_string()?);
+                }
+                ElementType::FileMimeType => {
+                    mime_type = Some(it.read_string()?);
+                }
+                ElementType::FileDescription => {
+                    description = Some(it.read_string()?);
+                }
+                ElementType::FileData => {
+                    data = Some(it.read_boxed_slice()?);
+                }
+                other => {
+                    log::debug!("ignored element {:?}", other);
+                }
+            }
+        }
+
+        Ok(Self {
+            name: name.ok_or(Error::DecodeError("mkv: missing attachment file name"))?,
+            mime_type: mime_type.ok_or(Error::DecodeError("mkv: missing attachment mime type"))?,
+            description,
+            data: data.ok_or(Error::DecodeError("mkv: missing attachment data"))?,
+        })
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct TagsElement {
     pub(crate) tags: Box<[TagElement]>,
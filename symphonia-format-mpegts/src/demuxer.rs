@@ -0,0 +1,1085 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::collections::VecDeque;
+use std::io::{Seek, SeekFrom};
+
+use symphonia_core::errors::{seek_error, unsupported_error, Error, Result, SeekErrorKind};
+use symphonia_core::formats::prelude::*;
+use symphonia_core::formats::util::{Discontinuity, TimestampNormalizer};
+use symphonia_core::io::{MediaSource, MediaSourceStream, ReadBytes, SeekBuffered};
+use symphonia_core::meta::{Metadata, MetadataLog};
+use symphonia_core::probe::{Descriptor, Instantiate, QueryDescriptor};
+use symphonia_core::support_format;
+
+use log::{debug, info, warn};
+
+use crate::es::{ElementaryStream, EsCodec};
+use crate::packet::{TsPacketHeader, PAT_PID, TS_PACKET_LEN, TS_SYNC_BYTE};
+use crate::pes::{pes_header_len, pes_pts, PTS_BITS, PTS_CLOCK_RATE};
+use crate::psi::{read_pat, read_pmt, SectionBuffer};
+
+/// The maximum number of bytes to search for the first transport stream packet.
+const MAX_SYNC_SEARCH_LEN: u64 = 64 * 1024;
+
+/// The maximum number of transport stream packets to read while searching for the audio
+/// elementary streams and their first frames.
+const MAX_PROBE_PACKETS: usize = 16 * 1024;
+
+/// The maximum difference between the presentation timestamps of consecutive PES packets of a
+/// stream that is not a discontinuity. The presentation timestamps of an audio stream must be at
+/// most 700 ms apart, and some margin is allowed for lost packets.
+const MAX_PTS_GAP: u64 = 2 * PTS_CLOCK_RATE;
+
+/// The maximum number of discontinuities that are queued until they are taken. Older
+/// discontinuities are dropped.
+const MAX_QUEUED_DISCONTINUITIES: usize = 64;
+
+/// The state of an audio elementary stream.
+struct StreamState {
+    /// The PID of the packets carrying the elementary stream. Also used as the track ID.
+    pid: u16,
+    es: ElementaryStream,
+    /// The continuity counter of the last packet with a payload.
+    last_cc: Option<u8>,
+    /// The PES header bytes read thus far, if the PES header is incomplete.
+    pes_header: Option<Vec<u8>>,
+    /// If true, the payload of the current PES packet is being read.
+    in_pes: bool,
+    /// Maps the presentation timestamps onto a continuous timeline.
+    normalizer: TimestampNormalizer,
+    /// A normalized presentation timestamp, and the timestamp of the frame it was the
+    /// presentation timestamp of. Later presentation timestamps are relative to it.
+    anchor: Option<(u64, u64)>,
+    /// The timestamp of the next frame.
+    next_ts: u64,
+}
+
+impl StreamState {
+    fn new(pid: u16, codec: EsCodec) -> Self {
+        StreamState {
+            pid,
+            es: ElementaryStream::new(codec),
+            last_cc: None,
+            pes_header: None,
+            in_pes: false,
+            normalizer: TimestampNormalizer::with_wrap_bits(PTS_BITS, MAX_PTS_GAP),
+            anchor: None,
+            next_ts: 0,
+        }
+    }
+
+    /// Discard the PES packet being read, and any buffered elementary stream data.
+    fn discard(&mut self) {
+        self.es.clear();
+        self.pes_header = None;
+        self.in_pes = false;
+    }
+
+    /// Reset the stream to its initial state.
+    fn reset(&mut self) {
+        self.discard();
+        self.last_cc = None;
+        self.normalizer.reset();
+        self.anchor = None;
+        self.next_ts = 0;
+    }
+
+    /// Get the timestamp of a frame with the provided presentation timestamp and duration, and
+    /// the discontinuity of the presentation timestamps, if any. Timestamps are in units of the
+    /// sample rate of the stream. Returns `None` if the sample rate of the stream is not known.
+    fn pts_to_ts(&mut self, pts: u64, dur: u64) -> Option<(u64, Option<Discontinuity>)> {
+        let sample_rate = match self.es.codec_params().and_then(|params| params.sample_rate) {
+            Some(sample_rate) if sample_rate > 0 => u64::from(sample_rate),
+            _ => return None,
+        };
+
+        let (norm_pts, discontinuity) =
+            self.normalizer.normalize(pts, dur * PTS_CLOCK_RATE / sample_rate);
+
+        // After a jump, the timeline continues from the end of the previous frame.
+        if let Some(Discontinuity::Jump { .. }) = discontinuity {
+            self.anchor = None;
+        }
+
+        let (anchor_pts, anchor_ts) = *self.anchor.get_or_insert((norm_pts, self.next_ts));
+
+        // Round to the nearest timestamp.
+        let delta = norm_pts.saturating_sub(anchor_pts);
+
+        let ts = anchor_ts + (delta * sample_rate + PTS_CLOCK_RATE / 2) / PTS_CLOCK_RATE;
+
+        Some((ts, discontinuity))
+    }
+
+    /// Read the payload of a transport stream packet, and queue any complete frames.
+    fn read_payload(
+        &mut self,
+        header: &TsPacketHeader,
+        payload: &[u8],
+        packets: &mut VecDeque<Packet>,
+        discontinuities: &mut VecDeque<TimestampDiscontinuity>,
+    ) {
+        if let Some(last_cc) = self.last_cc {
+            if header.continuity_counter == last_cc && !header.discontinuity {
+                // A duplicate packet may be sent once, and is ignored.
+                return;
+            }
+            else if header.continuity_counter != (last_cc + 1) & 0xf && !header.discontinuity {
+                warn!("lost packets of pid={}, discarding pes packet", self.pid);
+                self.discard();
+            }
+        }
+
+        self.last_cc = Some(header.continuity_counter);
+
+        if header.payload_unit_start {
+            self.pes_header = Some(Vec::new());
+            self.in_pes = false;
+        }
+
+        if let Some(mut pes_header) = self.pes_header.take() {
+            pes_header.extend_from_slice(payload);
+
+            match pes_header_len(&pes_header) {
+                Ok(Some(len)) => {
+                    self.es.push(&pes_header[len..], pes_pts(&pes_header[..len]));
+                    self.in_pes = true;
+                }
+                Ok(None) => self.pes_header = Some(pes_header),
+                Err(err) => warn!("invalid pes packet of pid={}: {}", self.pid, err),
+            }
+        }
+        else if self.in_pes {
+            self.es.push(payload, None);
+        }
+
+        while let Some(frame) = self.es.next_frame() {
+            // The timestamp of a frame is derived from its presentation timestamp. Frames without
+            // a presentation timestamp, or a known sample rate, follow the previous frame.
+            let ts = match frame.pts.and_then(|pts| self.pts_to_ts(pts, frame.dur)) {
+                Some((ts, discontinuity)) => {
+                    if let Some(discontinuity) = discontinuity {
+                        info!("pts discontinuity of pid={}: {:?}", self.pid, discontinuity);
+
+                        if discontinuities.len() >= MAX_QUEUED_DISCONTINUITIES {
+                            discontinuities.pop_front();
+                        }
+
+                        discontinuities.push_back(TimestampDiscontinuity {
+                            track_id: u32::from(self.pid),
+                            ts,
+                            discontinuity,
+                        });
+                    }
+
+                    ts
+                }
+                None => self.next_ts + frame.discarded_dur,
+            };
+
+            self.next_ts = ts + frame.dur;
+
+            packets.push_back(Packet::new_from_boxed_slice(
+                u32::from(self.pid),
+                ts,
+                frame.dur,
+                frame.data,
+            ));
+        }
+    }
+}
+
+/// MPEG transport stream (MPEG-TS) demultiplexer.
+///
+/// `TsReader` implements a demuxer for the audio elementary streams of the first program of an
+/// MPEG transport stream, such as a television capture or a HLS segment. AAC in ADTS framing, and
+/// MPEG-1 and MPEG-2 audio are supported.
+///
+/// A different program, or a subset of the audio elementary streams of the program, may be
+/// selected using `FormatOptions::track_filter`. The packets of all other elementary streams are
+/// discarded without being reassembled.
+///
+/// Timestamps are derived from the presentation timestamps (PTS) of the PES packets of each
+/// elementary stream, relative to the first presentation timestamp of the elementary stream. Frames
+/// that do not start a PES packet follow the previous frame.
+///
+/// Timestamps are normalized onto a continuous and monotonic timeline. When the presentation
+/// timestamps wrap-around, jump, or a discontinuity is flagged in the packets carrying the program
+/// clock reference, the timeline continues from the end of the previous frame. The corrected
+/// discontinuities may be retrieved with [`FormatReader::take_discontinuities`].
+pub struct TsReader {
+    reader: MediaSourceStream,
+    tracks: Vec<Track>,
+    cues: Vec<Cue>,
+    metadata: MetadataLog,
+    programs: Vec<Program>,
+    streams: Vec<StreamState>,
+    /// The PID of the packets carrying the program clock reference of the program.
+    pcr_pid: Option<u16>,
+    /// Frames extracted from the elementary streams that have not been returned yet.
+    packets: VecDeque<Packet>,
+    /// Discontinuities detected since they were last taken.
+    discontinuities: VecDeque<TimestampDiscontinuity>,
+    first_packet_pos: u64,
+}
+
+impl TsReader {
+    /// Signal a discontinuity to all streams if the packet flags a discontinuity of the program
+    /// clock reference. The presentation timestamps that follow refer to a new time base.
+    fn signal_discontinuity(
+        streams: &mut [StreamState],
+        pcr_pid: Option<u16>,
+        header: &TsPacketHeader,
+    ) {
+        if header.discontinuity && Some(header.pid) == pcr_pid {
+            debug!("pcr discontinuity of pid={}", header.pid);
+
+            for stream in streams.iter_mut() {
+                stream.normalizer.signal_discontinuity();
+            }
+        }
+    }
+
+    /// Read the next transport stream packet, re-synchronizing to the packet sync byte if
+    /// required.
+    fn read_ts_packet(reader: &mut MediaSourceStream) -> Result<[u8; TS_PACKET_LEN]> {
+        let mut buf = [0u8; TS_PACKET_LEN];
+
+        reader.read_buf_exact(&mut buf)?;
+
+        if buf[0] != TS_SYNC_BYTE {
+            warn!("lost packet sync at {}", reader.pos() - TS_PACKET_LEN as u64);
+
+            reader.seek_buffered_rev(TS_PACKET_LEN - 1);
+            TsReader::sync(reader)?;
+            reader.read_buf_exact(&mut buf)?;
+        }
+
+        Ok(buf)
+    }
+
+    /// Search for the start of a transport stream packet. The start of a packet is a sync byte
+    /// that is followed by another sync byte one packet length later.
+    fn sync(reader: &mut MediaSourceStream) -> Result<()> {
+        let start = reader.pos();
+
+        loop {
+            if reader.pos() - start > MAX_SYNC_SEARCH_LEN {
+                return unsupported_error("mpegts: could not find packet sync");
+            }
+
+            if reader.read_byte()? != TS_SYNC_BYTE {
+                continue;
+            }
+
+            reader.ignore_bytes(TS_PACKET_LEN as u64 - 1)?;
+
+            let is_synced = reader.read_byte()? == TS_SYNC_BYTE;
+
+            if is_synced {
+                reader.seek_buffered_rev(TS_PACKET_LEN + 1);
+                return Ok(());
+            }
+
+            reader.seek_buffered_rev(TS_PACKET_LEN);
+        }
+    }
+
+    /// Read transport stream packets until at least one complete frame is queued.
+    fn read_more_packets(&mut self) -> Result<()> {
+        while self.packets.is_empty() {
+            let buf = TsReader::read_ts_packet(&mut self.reader)?;
+            let header = TsPacketHeader::parse(&buf)?;
+
+            if header.transport_error {
+                continue;
+            }
+
+            TsReader::signal_discontinuity(&mut self.streams, self.pcr_pid, &header);
+
+            let offset = match header.payload_offset {
+                Some(offset) => offset,
+                None => continue,
+            };
+
+            if let Some(stream) = self.streams.iter_mut().find(|s| s.pid == header.pid) {
+                stream.read_payload(
+                    &header,
+                    &buf[offset..],
+                    &mut self.packets,
+                    &mut self.discontinuities,
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl QueryDescriptor for TsReader {
+    fn query() -> &'static [Descriptor] {
+        &[support_format!(
+            "mpegts",
+            "MPEG Transport Stream",
+            &["ts", "m2t", "mts"],
+            &["video/mp2t"],
+            // The first packet of a transport stream typically starts a program association or
+            // service description section.
+            &[&[TS_SYNC_BYTE, 0x40, 0x00], &[TS_SYNC_BYTE, 0x40, 0x11]]
+        )]
+    }
+
+    fn score(_context: &[u8]) -> u8 {
+        255
+    }
+}
+
+impl FormatReader for TsReader {
+    fn try_new(mut source: MediaSourceStream, options: &FormatOptions) -> Result<Self> {
+        TsReader::sync(&mut source)?;
+
+        let first_packet_pos = source.pos();
+
+        let mut pat = SectionBuffer::default();
+        let mut pmt = SectionBuffer::default();
+
+        let mut pmt_pid = None;
+        let mut pcr_pid = None;
+        let mut program_number = 0;
+        let mut streams: Vec<StreamState> = Vec::new();
+        let mut packets = VecDeque::new();
+        let mut discontinuities = VecDeque::new();
+
+        // Read packets until the program map of the selected program is found, and the first frame
+        // of each selected audio elementary stream of the program has been read.
+        for _ in 0..MAX_PROBE_PACKETS {
+            let buf = match TsReader::read_ts_packet(&mut source) {
+                Ok(buf) => buf,
+                Err(Error::IoError(err)) if err.kind() == std::io::ErrorKind::UnexpectedEof => {
+                    break
+                }
+                Err(err) => return Err(err),
+            };
+
+            let header = TsPacketHeader::parse(&buf)?;
+
+            if header.transport_error {
+                continue;
+            }
+
+            TsReader::signal_discontinuity(&mut streams, pcr_pid, &header);
+
+            let offset = match header.payload_offset {
+                Some(offset) => offset,
+                None => continue,
+            };
+
+            let payload = &buf[offset..];
+
+            if header.pid == PAT_PID && pmt_pid.is_none() {
+                if let Some(section) = pat.push(payload, header.payload_unit_start) {
+                    let pat_programs = read_pat(section)?;
+
+                    if pat_programs.is_empty() {
+                        return unsupported_error("mpegts: stream has no programs");
+                    }
+
+                    // Select the first program, unless a program was selected by the filter.
+                    let program = match options.track_filter {
+                        Some(TrackFilter::Program(number)) => {
+                            pat_programs.iter().find(|p| u32::from(p.number) == number)
+                        }
+                        _ => pat_programs.first(),
+                    };
+
+                    match program {
+                        Some(program) => {
+                            pmt_pid = Some(program.pmt_pid);
+                            program_number = u32::from(program.number);
+                        }
+                        None => return unsupported_error("mpegts: selected program not found"),
+                    }
+                }
+            }
+            else if Some(header.pid) == pmt_pid && streams.is_empty() {
+                if let Some(section) = pmt.push(payload, header.payload_unit_start) {
+                    let program_map = read_pmt(section)?;
+
+                    pcr_pid = Some(program_map.pcr_pid);
+
+                    for entry in program_map.streams {
+                        match EsCodec::from_stream_type(entry.stream_type) {
+                            Some(codec) => {
+                                info!(
+                                    "found stream type={:#x} pid={}",
+                                    entry.stream_type, entry.pid
+                                );
+                                streams.push(StreamState::new(entry.pid, codec));
+                            }
+                            None => {
+                                debug!(
+                                    "ignoring stream type={:#x} pid={}",
+                                    entry.stream_type, entry.pid
+                                );
+                            }
+                        }
+                    }
+
+                    if streams.is_empty() {
+                        return unsupported_error("mpegts: program has no supported audio streams");
+                    }
+
+                    // Discard the streams not selected by the filter.
+                    if let Some(filter) = options.track_filter {
+                        let program = [Program {
+                            number: program_number,
+                            track_ids: streams.iter().map(|s| u32::from(s.pid)).collect(),
+                        }];
+
+                        streams.retain(|s| filter.is_selected(u32::from(s.pid), &program));
+
+                        if streams.is_empty() {
+                            return unsupported_error("mpegts: selected track not found");
+                        }
+                    }
+                }
+            }
+            else if let Some(stream) = streams.iter_mut().find(|s| s.pid == header.pid) {
+                stream.read_payload(&header, payload, &mut packets, &mut discontinuities);
+
+                if streams.iter().all(|s| s.es.codec_params().is_some()) {
+                    break;
+                }
+            }
+        }
+
+        // Only streams with a known codec configuration are exposed as tracks.
+        streams.retain(|s| s.es.codec_params().is_some());
+
+        if streams.is_empty() {
+            return unsupported_error("mpegts: no audio frames found");
+        }
+
+        let tracks: Vec<Track> = streams
+            .iter()
+            .map(|s| Track::new(u32::from(s.pid), s.es.codec_params().unwrap().clone()))
+            .collect();
+
+        let programs = vec![Program {
+            number: program_number,
+            track_ids: tracks.iter().map(|track| track.id).collect(),
+        }];
+
+        Ok(TsReader {
+            reader: source,
+            tracks,
+            cues: Vec::new(),
+            metadata: Default::default(),
+            programs,
+            streams,
+            pcr_pid,
+            packets,
+            discontinuities,
+            first_packet_pos,
+        })
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        self.read_more_packets()?;
+        Ok(self.packets.pop_front().unwrap())
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn programs(&self) -> &[Program] {
+        &self.programs
+    }
+
+    fn take_discontinuities(&mut self) -> Vec<TimestampDiscontinuity> {
+        // Discontinuities are detected when the frames of a track are read from the stream, which
+        // may be before the packets of the track are returned by `next_packet`. The timestamp of a
+        // discontinuity is that of the first packet after it.
+        self.discontinuities.drain(..).collect()
+    }
+
+    fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        // Select the track to seek, or the first track if one was not provided.
+        let track = match to {
+            SeekTo::TimeStamp { track_id, .. } => self.tracks.iter().find(|t| t.id == track_id),
+            SeekTo::Time { track_id: Some(track_id), .. } => {
+                self.tracks.iter().find(|t| t.id == track_id)
+            }
+            SeekTo::Time { track_id: None, .. } => self.tracks.first(),
+        };
+
+        let track = match track {
+            Some(track) => track,
+            None => return seek_error(SeekErrorKind::InvalidTrack),
+        };
+
+        let track_id = track.id;
+
+        let required_ts = match to {
+            SeekTo::TimeStamp { ts, .. } => ts,
+            SeekTo::Time { time, .. } => track.codec_params.time_base.unwrap().calc_timestamp(time),
+        };
+
+        debug!("seeking to ts={} of track={}", required_ts, track_id);
+
+        let stream = self.streams.iter().find(|s| u32::from(s.pid) == track_id).unwrap();
+
+        // The next timestamp of the track is either that of the next queued packet of the track,
+        // or the next frame to be read.
+        let next_ts =
+            self.packets.iter().find(|p| p.track_id() == track_id).map_or(stream.next_ts, |p| p.ts);
+
+        // There is no index of the transport stream, therefore seeking backwards requires reading
+        // the stream from the start.
+        if required_ts < next_ts {
+            if !self.reader.is_seekable() {
+                return seek_error(SeekErrorKind::ForwardOnly);
+            }
+
+            self.reader.seek(SeekFrom::Start(self.first_packet_pos))?;
+            self.packets.clear();
+
+            for stream in self.streams.iter_mut() {
+                stream.reset();
+            }
+        }
+
+        // Discard all packets until the packet of the track containing the required timestamp.
+        loop {
+            self.read_more_packets()?;
+
+            let packet = self.packets.front().unwrap();
+
+            if packet.track_id() == track_id && packet.ts + packet.dur > required_ts {
+                break;
+            }
+
+            self.packets.pop_front();
+        }
+
+        let actual_ts = self.packets.front().unwrap().ts;
+
+        debug!("seeked to ts={} (delta={})", actual_ts, required_ts as i64 - actual_ts as i64);
+
+        Ok(SeekedTo { track_id, required_ts, actual_ts })
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.reader
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use symphonia_core::checksum::Crc32;
+    use symphonia_core::codecs::CODEC_TYPE_AAC;
+    use symphonia_core::formats::TimestampDiscontinuity;
+    use symphonia_core::formats::TrackFilter;
+    use symphonia_core::formats::{FormatOptions, FormatReader, Program, SeekMode, SeekTo};
+    use symphonia_core::io::{MediaSourceStream, Monitor};
+    use symphonia_core::units::Time;
+
+    use symphonia_core::formats::util::Discontinuity;
+
+    use super::{TsReader, MAX_QUEUED_DISCONTINUITIES};
+    use crate::packet::TS_PACKET_LEN;
+
+    const PMT_PID: u16 = 0x1000;
+    const AUDIO_PID: u16 = 0x0100;
+
+    /// The presentation timestamp of the first PES packet of the test streams.
+    const FIRST_PTS: u64 = 126_000;
+
+    /// Write transport stream packets carrying the payload to the stream. The last packet is
+    /// padded using the adaptation field.
+    fn write_packets(ts: &mut Vec<u8>, pid: u16, cc: &mut u8, mut payload: &[u8]) {
+        let mut start = true;
+
+        while !payload.is_empty() {
+            let len = payload.len().min(TS_PACKET_LEN - 4);
+
+            ts.push(0x47);
+            ts.push(if start { 0x40 } else { 0x00 } | (pid >> 8) as u8);
+            ts.push(pid as u8);
+
+            if len < TS_PACKET_LEN - 4 {
+                // Adaptation field with stuffing, followed by the payload.
+                let af_len = TS_PACKET_LEN - 4 - len - 1;
+                ts.push(0x30 | *cc);
+                ts.push(af_len as u8);
+                if af_len > 0 {
+                    ts.push(0x00);
+                    ts.extend(std::iter::repeat(0xff).take(af_len - 1));
+                }
+            }
+            else {
+                ts.push(0x10 | *cc);
+            }
+
+            ts.extend_from_slice(&payload[..len]);
+            payload = &payload[len..];
+
+            *cc = (*cc + 1) & 0xf;
+            start = false;
+        }
+    }
+
+    /// Make a long-form section with the given table id and body.
+    fn make_section(table_id: u8, body: &[u8]) -> Vec<u8> {
+        let len = 5 + body.len() + 4;
+
+        let mut section = vec![0x00, table_id, 0xb0 | (len >> 8) as u8, len as u8];
+        section.extend_from_slice(&[0x00, 0x01, 0xc1, 0x00, 0x00]);
+        section.extend_from_slice(body);
+
+        let mut crc = Crc32::new(0xffff_ffff);
+        crc.process_buf_bytes(&section[1..]);
+        section.extend_from_slice(&crc.crc().to_be_bytes());
+
+        section
+    }
+
+    /// Make an ADTS frame of AAC-LC, 44.1kHz, stereo audio with a payload of the given length.
+    fn make_adts_frame(payload_len: usize, fill: u8) -> Vec<u8> {
+        let len = 7 + payload_len;
+
+        let mut frame = vec![
+            0xff,
+            0xf1,
+            0x50,
+            0x80 | (len >> 11) as u8,
+            (len >> 3) as u8,
+            ((len & 0x7) << 5) as u8 | 0x1f,
+            0xfc,
+        ];
+        frame.resize(len, fill);
+        frame
+    }
+
+    /// Make an audio PES packet carrying the data, with an optional presentation timestamp.
+    fn make_pes(pts: Option<u64>, data: &[u8]) -> Vec<u8> {
+        let mut pes = vec![0x00, 0x00, 0x01, 0xc0, 0x00, 0x00, 0x80];
+
+        match pts {
+            Some(pts) => {
+                pes.extend_from_slice(&[0x80, 0x05]);
+                pes.extend_from_slice(&[
+                    0x21 | ((pts >> 29) & 0xe) as u8,
+                    (pts >> 22) as u8,
+                    ((pts >> 14) & 0xfe) as u8 | 0x1,
+                    (pts >> 7) as u8,
+                    ((pts << 1) & 0xfe) as u8 | 0x1,
+                ]);
+            }
+            None => pes.extend_from_slice(&[0x00, 0x00]),
+        }
+
+        pes.extend_from_slice(data);
+        pes
+    }
+
+    /// Get the presentation timestamp of the audio frame at the timestamp `ts` of a 44.1kHz
+    /// stream.
+    fn pts_at(ts: u64) -> u64 {
+        FIRST_PTS + (ts * 90_000 + 22_050) / 44_100
+    }
+
+    /// Write the program association and program map sections of a program with one video stream
+    /// and one ADTS audio stream.
+    fn write_psi(ts: &mut Vec<u8>) {
+        let (mut pat_cc, mut pmt_cc) = (0, 0);
+
+        let pat = make_section(0x00, &[0x00, 0x01, 0xe0 | (PMT_PID >> 8) as u8, PMT_PID as u8]);
+        write_packets(ts, 0x0000, &mut pat_cc, &pat);
+
+        let pmt = make_section(
+            0x02,
+            &[
+                0xe0 | (AUDIO_PID >> 8) as u8,
+                AUDIO_PID as u8,
+                0xf0,
+                0x00,
+                // An unsupported video stream, followed by an ADTS audio stream.
+                0x1b,
+                0xe1,
+                0x01,
+                0xf0,
+                0x00,
+                0x0f,
+                0xe0 | (AUDIO_PID >> 8) as u8,
+                AUDIO_PID as u8,
+                0xf0,
+                0x00,
+            ],
+        );
+        write_packets(ts, PMT_PID, &mut pmt_cc, &pmt);
+    }
+
+    fn make_stream() -> Vec<u8> {
+        let mut ts = Vec::new();
+
+        write_psi(&mut ts);
+
+        let mut audio_cc = 0;
+
+        // Five PES packets, each with two ADTS frames.
+        for pes in 0..5u8 {
+            let mut data = make_adts_frame(150, 2 * pes);
+            data.extend(make_adts_frame(150, 2 * pes + 1));
+
+            let pes_data = make_pes(Some(pts_at(2048 * u64::from(pes))), &data);
+            write_packets(&mut ts, AUDIO_PID, &mut audio_cc, &pes_data);
+
+            // Duplicate the last packet of the first PES packet.
+            if pes == 0 {
+                let dup = ts[ts.len() - TS_PACKET_LEN..].to_vec();
+                ts.extend(dup);
+            }
+        }
+
+        ts
+    }
+
+    /// Make a stream of PES packets with the given presentation timestamps, each carrying one
+    /// ADTS frame. If `discontinuity_at` is set, the transport stream packet carrying that PES
+    /// packet flags a discontinuity.
+    fn make_pts_stream(pts: &[u64], discontinuity_at: Option<usize>) -> Vec<u8> {
+        let mut ts = Vec::new();
+
+        write_psi(&mut ts);
+
+        let mut audio_cc = 0;
+
+        for (i, &pts) in pts.iter().enumerate() {
+            let start = ts.len();
+
+            let pes_data = make_pes(Some(pts), &make_adts_frame(150, i as u8));
+            write_packets(&mut ts, AUDIO_PID, &mut audio_cc, &pes_data);
+
+            // The audio stream carries the program clock reference, and the PES packet fits in a
+            // single transport stream packet with an adaptation field.
+            if discontinuity_at == Some(i) {
+                ts[start + 5] |= 0x80;
+            }
+        }
+
+        ts
+    }
+
+    fn read_all_ts(reader: &mut TsReader) -> Vec<u64> {
+        let mut ts = Vec::new();
+
+        while let Ok(packet) = reader.next_packet() {
+            assert_eq!(&packet.data[..], &[ts.len() as u8; 150][..]);
+            ts.push(packet.ts);
+        }
+
+        ts
+    }
+
+    fn open(ts: Vec<u8>) -> TsReader {
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(ts)), Default::default());
+        TsReader::try_new(mss, &Default::default()).unwrap()
+    }
+
+    #[test]
+    fn verify_ts_reader() {
+        let mut reader = open(make_stream());
+
+        assert_eq!(reader.tracks().len(), 1);
+
+        let track = &reader.tracks()[0];
+        assert_eq!(track.id, u32::from(AUDIO_PID));
+        assert_eq!(track.codec_params.codec, CODEC_TYPE_AAC);
+        assert_eq!(track.codec_params.sample_rate, Some(44100));
+        assert_eq!(track.codec_params.channels.map(|c| c.count()), Some(2));
+
+        // Timestamps are relative to the first presentation timestamp.
+        for i in 0..10u8 {
+            let packet = reader.next_packet().unwrap();
+            assert_eq!(packet.ts, 1024 * u64::from(i));
+            assert_eq!(packet.dur, 1024);
+            assert_eq!(&packet.data[..], &[i; 150][..]);
+        }
+
+        assert!(reader.next_packet().is_err());
+
+        // Seek backwards.
+        let seeked = reader
+            .seek(
+                SeekMode::Accurate,
+                SeekTo::TimeStamp { ts: 5000, track_id: u32::from(AUDIO_PID) },
+            )
+            .unwrap();
+        assert_eq!(seeked.actual_ts, 4096);
+
+        let packet = reader.next_packet().unwrap();
+        assert_eq!(packet.ts, 4096);
+        assert_eq!(&packet.data[..], &[4; 150][..]);
+    }
+
+    #[test]
+    fn verify_ts_pes_continuation() {
+        let mut ts = Vec::new();
+
+        write_psi(&mut ts);
+
+        let mut audio_cc = 0;
+
+        // A PES packet spanning many transport stream packets, that ends part-way through a frame.
+        let frames: Vec<Vec<u8>> = (0..6).map(|i| make_adts_frame(400, i)).collect();
+
+        let mut data = frames[..5].concat();
+        data.extend_from_slice(&frames[5][..200]);
+
+        write_packets(&mut ts, AUDIO_PID, &mut audio_cc, &make_pes(Some(pts_at(0)), &data));
+
+        // The next PES packet continues the frame, and is followed by a frame whose presentation
+        // timestamp is one frame later than expected.
+        let mut data = frames[5][200..].to_vec();
+        data.extend(make_adts_frame(400, 6));
+
+        write_packets(&mut ts, AUDIO_PID, &mut audio_cc, &make_pes(Some(pts_at(7168)), &data));
+
+        // A PES packet without a presentation timestamp. The frames follow the previous frame.
+        let data = make_adts_frame(400, 7);
+
+        write_packets(&mut ts, AUDIO_PID, &mut audio_cc, &make_pes(None, &data));
+
+        let mut reader = open(ts);
+
+        let expected_ts = [0, 1024, 2048, 3072, 4096, 5120, 7168, 8192];
+
+        for (i, &ts) in expected_ts.iter().enumerate() {
+            let packet = reader.next_packet().unwrap();
+            assert_eq!(packet.ts, ts);
+            assert_eq!(&packet.data[..], &[i as u8; 400][..]);
+        }
+
+        assert!(reader.next_packet().is_err());
+    }
+
+    #[test]
+    fn verify_ts_multiple_raw_data_blocks() {
+        let mut ts = Vec::new();
+
+        write_psi(&mut ts);
+
+        let mut audio_cc = 0;
+
+        // An ADTS frame with 2 raw data blocks cannot be split into packets, and is discarded.
+        let mut multiple = make_adts_frame(300, 0xff);
+        multiple[6] |= 0x1;
+
+        let mut data = make_adts_frame(150, 0);
+        data.extend(multiple);
+        data.extend(make_adts_frame(150, 1));
+
+        write_packets(&mut ts, AUDIO_PID, &mut audio_cc, &make_pes(Some(pts_at(0)), &data));
+
+        // The first frame of a PES packet is discarded, and its presentation timestamp is moved to
+        // the next frame.
+        let mut multiple = make_adts_frame(300, 0xff);
+        multiple[6] |= 0x1;
+
+        let mut data = multiple;
+        data.extend(make_adts_frame(150, 2));
+
+        write_packets(&mut ts, AUDIO_PID, &mut audio_cc, &make_pes(Some(pts_at(4096)), &data));
+
+        let mut reader = open(ts);
+
+        for &(ts, fill) in &[(0, 0), (3072, 1), (6144, 2)] {
+            let packet = reader.next_packet().unwrap();
+            assert_eq!(packet.ts, ts);
+            assert_eq!(packet.dur, 1024);
+            assert_eq!(&packet.data[..], &[fill; 150][..]);
+        }
+
+        assert!(reader.next_packet().is_err());
+    }
+
+    #[test]
+    fn verify_ts_seek() {
+        let mut reader = open(make_stream());
+
+        let track_id = u32::from(AUDIO_PID);
+
+        // Seek forwards to a time.
+        let seeked = reader
+            .seek(SeekMode::Accurate, SeekTo::Time { time: Time::from(0.1), track_id: None })
+            .unwrap();
+        assert_eq!(seeked.track_id, track_id);
+        assert_eq!(seeked.required_ts, 4410);
+        assert_eq!(seeked.actual_ts, 4096);
+        assert_eq!(reader.next_packet().unwrap().ts, 4096);
+
+        // Seek forwards to the timestamp of the next packet.
+        let seeked =
+            reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 6144, track_id }).unwrap();
+        assert_eq!(seeked.actual_ts, 6144);
+        assert_eq!(&reader.next_packet().unwrap().data[..], &[6; 150][..]);
+
+        // Seek backwards to the start.
+        let seeked =
+            reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 0, track_id }).unwrap();
+        assert_eq!(seeked.actual_ts, 0);
+        assert_eq!(&reader.next_packet().unwrap().data[..], &[0; 150][..]);
+
+        // Seeking past the end, or an unknown track, is an error.
+        assert!(reader
+            .seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 20_000, track_id })
+            .is_err());
+        assert!(reader
+            .seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 0, track_id: 0x0101 })
+            .is_err());
+    }
+
+    #[test]
+    fn verify_ts_track_filter() {
+        let open = |track_filter| {
+            let mss =
+                MediaSourceStream::new(Box::new(Cursor::new(make_stream())), Default::default());
+            let options = FormatOptions::default().with_track_filter(Some(track_filter));
+            TsReader::try_new(mss, &options)
+        };
+
+        let reader = open(TrackFilter::Program(1)).unwrap();
+        assert_eq!(reader.programs(), &[Program { number: 1, track_ids: vec![0x0100] }]);
+
+        assert_eq!(open(TrackFilter::Track(u32::from(AUDIO_PID))).unwrap().tracks().len(), 1);
+
+        assert!(open(TrackFilter::Program(2)).is_err());
+        assert!(open(TrackFilter::Track(0x0101)).is_err());
+        assert!(open(TrackFilter::ExcludeTrack(u32::from(AUDIO_PID))).is_err());
+    }
+
+    #[test]
+    fn verify_ts_pts_wrap() {
+        let wrap = 1u64 << 33;
+
+        // The presentation timestamps wrap-around between the third and fourth frames.
+        let first_pts = wrap - 5000;
+
+        let pts: Vec<u64> =
+            (0..5).map(|i| (first_pts + (i * 1024 * 90_000 + 22_050) / 44_100) % wrap).collect();
+
+        let mut reader = open(make_pts_stream(&pts, None));
+
+        assert_eq!(read_all_ts(&mut reader), [0, 1024, 2048, 3072, 4096]);
+
+        assert_eq!(
+            reader.take_discontinuities(),
+            [TimestampDiscontinuity {
+                track_id: u32::from(AUDIO_PID),
+                ts: 3072,
+                discontinuity: Discontinuity::Wrapped,
+            }]
+        );
+        assert!(reader.take_discontinuities().is_empty());
+    }
+
+    #[test]
+    fn verify_ts_pts_jump() {
+        // The presentation timestamps jump forwards by 10 seconds, and then backwards. The
+        // timestamps continue from the end of the previous frame.
+        let pts = [pts_at(0), pts_at(1024), 900_000, 902_090, 50_000];
+
+        let mut reader = open(make_pts_stream(&pts, None));
+
+        assert_eq!(read_all_ts(&mut reader), [0, 1024, 2048, 3072, 4096]);
+
+        let track_id = u32::from(AUDIO_PID);
+
+        assert_eq!(
+            reader.take_discontinuities(),
+            [
+                TimestampDiscontinuity {
+                    track_id,
+                    ts: 2048,
+                    discontinuity: Discontinuity::Jump { from: pts_at(1024), to: 900_000 },
+                },
+                TimestampDiscontinuity {
+                    track_id,
+                    ts: 4096,
+                    discontinuity: Discontinuity::Jump { from: 902_090, to: 50_000 },
+                },
+            ]
+        );
+
+        // Seeking restarts the timeline.
+        reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 0, track_id }).unwrap();
+        assert_eq!(reader.next_packet().unwrap().ts, 0);
+    }
+
+    #[test]
+    fn verify_ts_discontinuity_queue() {
+        // The presentation timestamps of every frame jump forwards by 10 seconds.
+        let pts: Vec<u64> = (0..100).map(|i| FIRST_PTS + i * 900_000).collect();
+
+        let mss = MediaSourceStream::new(
+            Box::new(Cursor::new(make_pts_stream(&pts, None))),
+            Default::default(),
+        );
+        let mut reader: Box<dyn FormatReader> =
+            Box::new(TsReader::try_new(mss, &Default::default()).unwrap());
+
+        while reader.next_packet().is_ok() {}
+
+        // Only the most recent discontinuities are kept.
+        let discontinuities = reader.take_discontinuities();
+        assert_eq!(discontinuities.len(), MAX_QUEUED_DISCONTINUITIES);
+        assert_eq!(discontinuities[0].ts, 36 * 1024);
+        assert_eq!(discontinuities.last().unwrap().ts, 99 * 1024);
+    }
+
+    #[test]
+    fn verify_ts_pcr_discontinuity() {
+        // A gap of 1 second in the presentation timestamps is not a discontinuity by itself.
+        let pts = [pts_at(0), pts_at(1024), pts_at(2048) + 90_000, pts_at(3072) + 90_000];
+
+        let mut reader = open(make_pts_stream(&pts, None));
+
+        assert_eq!(read_all_ts(&mut reader), [0, 1024, 46148, 47172]);
+        assert!(reader.take_discontinuities().is_empty());
+
+        // If the discontinuity indicator is set in the packets carrying the program clock
+        // reference, the gap is a discontinuity.
+        let mut reader = open(make_pts_stream(&pts, Some(2)));
+
+        assert_eq!(read_all_ts(&mut reader), [0, 1024, 2048, 3072]);
+
+        assert_eq!(
+            reader.take_discontinuities(),
+            [TimestampDiscontinuity {
+                track_id: u32::from(AUDIO_PID),
+                ts: 2048,
+                discontinuity: Discontinuity::Jump {
+                    from: pts_at(1024),
+                    to: pts_at(2048) + 90_000,
+                },
+            }]
+        );
+    }
+}
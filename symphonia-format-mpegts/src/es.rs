@@ -0,0 +1,386 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::audio::Channels;
+use symphonia_core::codecs::{CodecParameters, CodecType};
+use symphonia_core::codecs::{CODEC_TYPE_AAC, CODEC_TYPE_MP1, CODEC_TYPE_MP2, CODEC_TYPE_MP3};
+use symphonia_core::units::TimeBase;
+
+use log::warn;
+
+use crate::pes::PTS_CLOCK_RATE;
+
+/// The codec, and framing, of an audio elementary stream.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EsCodec {
+    /// AAC in ADTS framing.
+    Adts,
+    /// MPEG-1 or MPEG-2 audio layer I, II, or III.
+    Mpa,
+}
+
+impl EsCodec {
+    /// Get the codec of an elementary stream from its program map stream type, if supported.
+    pub fn from_stream_type(stream_type: u8) -> Option<EsCodec> {
+        match stream_type {
+            // ISO/IEC 11172-3 and ISO/IEC 13818-3 audio.
+            0x03 | 0x04 => Some(EsCodec::Mpa),
+            // ISO/IEC 13818-7 audio with ADTS transport syntax.
+            0x0f => Some(EsCodec::Adts),
+            _ => None,
+        }
+    }
+
+    /// The minimum number of bytes required to parse a frame header.
+    fn header_len(&self) -> usize {
+        match self {
+            EsCodec::Adts => 7,
+            EsCodec::Mpa => 4,
+        }
+    }
+}
+
+/// Information about an audio frame parsed from its header.
+struct FrameInfo {
+    codec: CodecType,
+    sample_rate: u32,
+    channels: Option<Channels>,
+    /// The number of bytes at the start of the frame that are not passed on to the decoder.
+    skip: usize,
+    /// The length of the frame in bytes, including the header.
+    len: usize,
+    /// The number of audio frames (samples per channel) in the frame.
+    dur: u64,
+    /// The number of raw data blocks, each of which is a complete packet for the decoder, in the
+    /// frame.
+    num_blocks: u32,
+}
+
+const AAC_SAMPLE_RATES: [u32; 13] =
+    [96000, 88200, 64000, 48000, 44100, 32000, 24000, 22050, 16000, 12000, 11025, 8000, 7350];
+
+fn aac_channels(channel_config: u8) -> Option<Channels> {
+    let channels = match channel_config {
+        1 => Channels::FRONT_LEFT,
+        2 => Channels::FRONT_LEFT | Channels::FRONT_RIGHT,
+        3 => Channels::FRONT_CENTRE | Channels::FRONT_LEFT | Channels::FRONT_RIGHT,
+        4 => {
+            Channels::FRONT_CENTRE
+                | Channels::FRONT_LEFT
+                | Channels::FRONT_RIGHT
+                | Channels::REAR_CENTRE
+        }
+        5 => {
+            Channels::FRONT_CENTRE
+                | Channels::FRONT_LEFT
+                | Channels::FRONT_RIGHT
+                | Channels::SIDE_LEFT
+                | Channels::SIDE_RIGHT
+        }
+        6 => {
+            Channels::FRONT_CENTRE
+                | Channels::FRONT_LEFT
+                | Channels::FRONT_RIGHT
+                | Channels::SIDE_LEFT
+                | Channels::SIDE_RIGHT
+                | Channels::LFE1
+        }
+        7 => {
+            Channels::FRONT_CENTRE
+                | Channels::FRONT_LEFT
+                | Channels::FRONT_RIGHT
+                | Channels::SIDE_LEFT
+                | Channels::SIDE_RIGHT
+                | Channels::FRONT_LEFT_WIDE
+                | Channels::FRONT_RIGHT_WIDE
+                | Channels::LFE1
+        }
+        // The channel configuration is provided in-band.
+        _ => return None,
+    };
+
+    Some(channels)
+}
+
+/// Parse an ADTS frame header.
+fn parse_adts_header(buf: &[u8]) -> Option<FrameInfo> {
+    // Check the 12-bit sync word, and that the layer is 0.
+    if buf[0] != 0xff || buf[1] & 0xf6 != 0xf0 {
+        return None;
+    }
+
+    let protection_absent = buf[1] & 0x1 != 0;
+
+    let sample_rate = *AAC_SAMPLE_RATES.get(usize::from((buf[2] >> 2) & 0xf))?;
+
+    let channel_config = ((buf[2] & 0x1) << 2) | (buf[3] >> 6);
+
+    let len =
+        (usize::from(buf[3] & 0x3) << 11) | (usize::from(buf[4]) << 3) | usize::from(buf[5] >> 5);
+
+    // The header is followed by a CRC if protection is not absent.
+    let skip = if protection_absent { 7 } else { 9 };
+
+    if len <= skip {
+        return None;
+    }
+
+    let num_blocks = u32::from(buf[6] & 0x3) + 1;
+
+    Some(FrameInfo {
+        codec: CODEC_TYPE_AAC,
+        sample_rate,
+        channels: aac_channels(channel_config),
+        skip,
+        len,
+        dur: 1024 * u64::from(num_blocks),
+        num_blocks,
+    })
+}
+
+/// Bit-rate lookup tables in kilobits per second, indexed by the bit-rate index.
+const MPA_BIT_RATES_MPEG1: [[u32; 15]; 3] = [
+    [0, 32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448],
+    [0, 32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384],
+    [0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320],
+];
+
+const MPA_BIT_RATES_MPEG2: [[u32; 15]; 3] = [
+    [0, 32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256],
+    [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160],
+    [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160],
+];
+
+/// Parse a MPEG audio frame header.
+fn parse_mpa_header(buf: &[u8]) -> Option<FrameInfo> {
+    let header = u32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]);
+
+    // Check the 11-bit sync word.
+    if header & 0xffe0_0000 != 0xffe0_0000 {
+        return None;
+    }
+
+    // 0 = MPEG 2.5, 2 = MPEG 2, 3 = MPEG 1.
+    let version = (header >> 19) & 0x3;
+
+    // 1 = Layer III, 2 = Layer II, 3 = Layer I.
+    let layer = (header >> 17) & 0x3;
+
+    if version == 1 || layer == 0 {
+        return None;
+    }
+
+    let layer_idx = (3 - layer) as usize;
+
+    let bit_rate_idx = ((header >> 12) & 0xf) as usize;
+
+    // Free-format streams (bit-rate index 0) cannot be framed without searching for the next
+    // frame, and are not supported.
+    if bit_rate_idx == 0 || bit_rate_idx == 15 {
+        return None;
+    }
+
+    let bit_rate = 1000
+        * if version == 3 {
+            MPA_BIT_RATES_MPEG1[layer_idx][bit_rate_idx]
+        }
+        else {
+            MPA_BIT_RATES_MPEG2[layer_idx][bit_rate_idx]
+        };
+
+    let sample_rate = match (header >> 10) & 0x3 {
+        0 => 44_100,
+        1 => 48_000,
+        2 => 32_000,
+        _ => return None,
+    };
+
+    // MPEG 2 and MPEG 2.5 halve and quarter the MPEG 1 sample rates, respectively.
+    let sample_rate = match version {
+        3 => sample_rate,
+        2 => sample_rate / 2,
+        _ => sample_rate / 4,
+    };
+
+    let padding = (header >> 9) & 0x1;
+
+    let (codec, len, dur) = match layer {
+        3 => (CODEC_TYPE_MP1, 4 * (12 * bit_rate / sample_rate + padding), 384),
+        2 => (CODEC_TYPE_MP2, 144 * bit_rate / sample_rate + padding, 1152),
+        _ if version == 3 => (CODEC_TYPE_MP3, 144 * bit_rate / sample_rate + padding, 1152),
+        _ => (CODEC_TYPE_MP3, 72 * bit_rate / sample_rate + padding, 576),
+    };
+
+    let channels = if (header >> 6) & 0x3 == 0x3 {
+        Channels::FRONT_LEFT
+    }
+    else {
+        Channels::FRONT_LEFT | Channels::FRONT_RIGHT
+    };
+
+    Some(FrameInfo {
+        codec,
+        sample_rate,
+        channels: Some(channels),
+        skip: 0,
+        len: len as usize,
+        dur,
+        num_blocks: 1,
+    })
+}
+
+/// An audio frame extracted from an elementary stream.
+pub struct EsFrame {
+    /// The frame data to be passed to the decoder.
+    pub data: Box<[u8]>,
+    /// The number of audio frames (samples per channel) in the frame.
+    pub dur: u64,
+    /// The presentation timestamp of the PES packet the frame started in, if the frame is the
+    /// first frame to start in that PES packet.
+    pub pts: Option<u64>,
+    /// The total duration of the unsupported frames discarded immediately before this frame.
+    pub discarded_dur: u64,
+}
+
+/// Extracts audio frames from the payloads of the PES packets of an elementary stream.
+pub struct ElementaryStream {
+    codec: EsCodec,
+    buf: Vec<u8>,
+    pos: usize,
+    params: Option<CodecParameters>,
+    /// The offset in the buffer where the payload of the last PES packet with a presentation
+    /// timestamp started, and that timestamp. Cleared once a frame starts at or after the offset.
+    pts: Option<(usize, u64)>,
+    /// The total duration of the unsupported frames discarded since the last frame.
+    discarded_dur: u64,
+}
+
+impl ElementaryStream {
+    pub fn new(codec: EsCodec) -> Self {
+        ElementaryStream {
+            codec,
+            buf: Vec::new(),
+            pos: 0,
+            params: None,
+            pts: None,
+            discarded_dur: 0,
+        }
+    }
+
+    /// Get the codec parameters of the elementary stream. Available once the first frame has
+    /// been extracted.
+    pub fn codec_params(&self) -> Option<&CodecParameters> {
+        self.params.as_ref()
+    }
+
+    /// Push data from the payload of a PES packet. If the data starts the payload of a PES packet,
+    /// the presentation timestamp of the PES packet, if any, should be provided.
+    pub fn push(&mut self, data: &[u8], pts: Option<u64>) {
+        self.buf.drain(..self.pos);
+
+        if let Some((offset, _)) = &mut self.pts {
+            *offset = offset.saturating_sub(self.pos);
+        }
+
+        self.pos = 0;
+
+        if let Some(pts) = pts {
+            self.pts = Some((self.buf.len(), pts));
+        }
+
+        self.buf.extend_from_slice(data);
+    }
+
+    /// Discard all buffered data, such as after data was lost.
+    pub fn clear(&mut self) {
+        self.buf.clear();
+        self.pos = 0;
+        self.pts = None;
+        self.discarded_dur = 0;
+    }
+
+    /// Extract the next complete frame, if available. Data preceding the next frame header is
+    /// discarded.
+    pub fn next_frame(&mut self) -> Option<EsFrame> {
+        loop {
+            let avail = &self.buf[self.pos..];
+
+            if avail.len() < self.codec.header_len() {
+                return None;
+            }
+
+            let info = match self.codec {
+                EsCodec::Adts => parse_adts_header(avail),
+                EsCodec::Mpa => parse_mpa_header(avail),
+            };
+
+            let info = match info {
+                Some(info) => info,
+                None => {
+                    // Not a frame header, try to sync at the next byte.
+                    self.pos += 1;
+                    continue;
+                }
+            };
+
+            // Wait for the rest of the frame.
+            if avail.len() < info.len {
+                return None;
+            }
+
+            let data = Box::from(&avail[info.skip..info.len]);
+
+            // The presentation timestamp applies to the first frame that starts in the payload of
+            // its PES packet.
+            let pts = match self.pts {
+                Some((offset, pts)) if offset <= self.pos => {
+                    self.pts = None;
+                    Some(pts)
+                }
+                _ => None,
+            };
+
+            self.pos += info.len;
+
+            // An ADTS frame with multiple raw data blocks cannot be split into packets without
+            // parsing the raw data blocks, and is not supported.
+            if info.num_blocks > 1 {
+                warn!("discarding adts frame with {} raw data blocks", info.num_blocks);
+
+                // The presentation timestamp moves to the next frame.
+                if let Some(pts) = pts {
+                    let pts_dur = info.dur * PTS_CLOCK_RATE / u64::from(info.sample_rate);
+                    self.pts = Some((self.pos, pts + pts_dur));
+                }
+                else {
+                    self.discarded_dur += info.dur;
+                }
+
+                continue;
+            }
+
+            if self.params.is_none() {
+                let mut params = CodecParameters::new();
+
+                params
+                    .for_codec(info.codec)
+                    .with_sample_rate(info.sample_rate)
+                    .with_time_base(TimeBase::new(1, info.sample_rate));
+
+                if let Some(channels) = info.channels {
+                    params.with_channels(channels);
+                }
+
+                self.params = Some(params);
+            }
+
+            let discarded_dur = std::mem::take(&mut self.discarded_dur);
+
+            return Some(EsFrame { data, dur: info.dur, pts, discarded_dur });
+        }
+    }
+}
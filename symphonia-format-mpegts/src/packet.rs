@@ -0,0 +1,85 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::errors::{decode_error, Result};
+
+/// The length of a transport stream packet in bytes.
+pub const TS_PACKET_LEN: usize = 188;
+
+/// The sync byte that starts every transport stream packet.
+pub const TS_SYNC_BYTE: u8 = 0x47;
+
+/// The packet identifier of the program association table.
+pub const PAT_PID: u16 = 0x0000;
+
+/// The header of a transport stream packet.
+#[derive(Debug)]
+pub struct TsPacketHeader {
+    /// The packet contains uncorrectable errors.
+    pub transport_error: bool,
+    /// The payload starts a new PES packet or PSI section.
+    pub payload_unit_start: bool,
+    /// The packet identifier.
+    pub pid: u16,
+    /// The continuity counter. Only incremented for packets with a payload.
+    pub continuity_counter: u8,
+    /// The adaptation field signals a discontinuity in the continuity counter. In the packets
+    /// carrying the program clock reference, it also signals a discontinuity of the time base.
+    pub discontinuity: bool,
+    /// The offset of the payload within the packet, if the packet has a payload.
+    pub payload_offset: Option<usize>,
+}
+
+impl TsPacketHeader {
+    /// Parse the header of a transport stream packet.
+    pub fn parse(buf: &[u8; TS_PACKET_LEN]) -> Result<TsPacketHeader> {
+        if buf[0] != TS_SYNC_BYTE {
+            return decode_error("mpegts: missing packet sync byte");
+        }
+
+        let transport_error = buf[1] & 0x80 != 0;
+        let payload_unit_start = buf[1] & 0x40 != 0;
+        let pid = (u16::from(buf[1] & 0x1f) << 8) | u16::from(buf[2]);
+        let adaptation_field_control = (buf[3] >> 4) & 0x3;
+        let continuity_counter = buf[3] & 0xf;
+
+        let mut offset = 4;
+        let mut discontinuity = false;
+
+        // An adaptation field is present.
+        if adaptation_field_control & 0x2 != 0 {
+            let len = usize::from(buf[4]);
+
+            if len > 0 {
+                discontinuity = buf[5] & 0x80 != 0;
+            }
+
+            offset += 1 + len;
+
+            if offset > TS_PACKET_LEN {
+                return decode_error("mpegts: adaptation field exceeds packet length");
+            }
+        }
+
+        // A payload is present.
+        let payload_offset = if adaptation_field_control & 0x1 != 0 && offset < TS_PACKET_LEN {
+            Some(offset)
+        }
+        else {
+            None
+        };
+
+        Ok(TsPacketHeader {
+            transport_error,
+            payload_unit_start,
+            pid,
+            continuity_counter,
+            discontinuity,
+            payload_offset,
+        })
+    }
+}
@@ -0,0 +1,113 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::errors::{decode_error, Result};
+
+/// The rate of the clock of presentation timestamps in Hz.
+pub const PTS_CLOCK_RATE: u64 = 90_000;
+
+/// The number of bits of a presentation timestamp.
+pub const PTS_BITS: u32 = 33;
+
+/// The length of the fixed part of a PES packet header: the start code prefix, stream id, and
+/// PES packet length.
+const PES_FIXED_HEADER_LEN: usize = 6;
+
+/// Returns true if PES packets with the given stream id have the optional PES header.
+fn has_optional_header(stream_id: u8) -> bool {
+    !matches!(
+        stream_id,
+        // Program stream map, padding stream, private stream 2, ECM, EMM, program stream
+        // directory, DSMCC stream, and H.222.1 type E streams.
+        0xbc | 0xbe | 0xbf | 0xf0 | 0xf1 | 0xf2 | 0xf8 | 0xff
+    )
+}
+
+/// Get the length of the header of a PES packet from the start of a PES packet. Returns `None` if
+/// more data is required to determine the length.
+pub fn pes_header_len(buf: &[u8]) -> Result<Option<usize>> {
+    if buf.len() < PES_FIXED_HEADER_LEN {
+        return Ok(None);
+    }
+
+    if buf[0..3] != [0x00, 0x00, 0x01] {
+        return decode_error("mpegts: missing pes start code prefix");
+    }
+
+    if !has_optional_header(buf[3]) {
+        return Ok(Some(PES_FIXED_HEADER_LEN));
+    }
+
+    // The optional header is made up of two bytes of flags, the header data length, and the
+    // header data.
+    if buf.len() < PES_FIXED_HEADER_LEN + 3 {
+        return Ok(None);
+    }
+
+    if buf[6] >> 6 != 0x2 {
+        return decode_error("mpegts: invalid pes header marker bits");
+    }
+
+    let len = PES_FIXED_HEADER_LEN + 3 + usize::from(buf[8]);
+
+    Ok(if buf.len() >= len { Some(len) } else { None })
+}
+
+/// Get the presentation timestamp (PTS) of a PES packet from its complete header, if present. The
+/// PTS is a 33-bit timestamp in units of a 90 kHz clock.
+pub fn pes_pts(header: &[u8]) -> Option<u64> {
+    if header.len() < PES_FIXED_HEADER_LEN + 3 + 5 || !has_optional_header(header[3]) {
+        return None;
+    }
+
+    // The PTS is present if the first bit of the PTS DTS flags is set.
+    if header[7] & 0x80 == 0 {
+        return None;
+    }
+
+    // The PTS is split into 3, 15, and 15 bit parts, each followed by a marker bit.
+    let buf = &header[9..14];
+
+    let pts = (u64::from(buf[0] >> 1) & 0x7) << 30
+        | u64::from(buf[1]) << 22
+        | u64::from(buf[2] >> 1) << 15
+        | u64::from(buf[3]) << 7
+        | u64::from(buf[4] >> 1);
+
+    Some(pts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{pes_header_len, pes_pts};
+
+    #[test]
+    fn verify_pes_header() {
+        // An audio PES packet with a PTS of 0x1_2345_6789.
+        let header =
+            [0x00, 0x00, 0x01, 0xc0, 0x01, 0x00, 0x80, 0x80, 0x05, 0x29, 0x8d, 0x15, 0xcf, 0x13];
+
+        for len in 0..header.len() {
+            assert_eq!(pes_header_len(&header[..len]).unwrap(), None);
+        }
+
+        assert_eq!(pes_header_len(&header).unwrap(), Some(14));
+        assert_eq!(pes_pts(&header), Some(0x1_2345_6789));
+
+        // Without a PTS.
+        let header = [0x00, 0x00, 0x01, 0xc0, 0x01, 0x00, 0x80, 0x00, 0x00];
+        assert_eq!(pes_header_len(&header).unwrap(), Some(9));
+        assert_eq!(pes_pts(&header), None);
+
+        // A padding stream has no optional header.
+        let header = [0x00, 0x00, 0x01, 0xbe, 0x00, 0x10];
+        assert_eq!(pes_header_len(&header).unwrap(), Some(6));
+        assert_eq!(pes_pts(&header), None);
+
+        assert!(pes_header_len(&[0x00, 0x00, 0x02, 0xc0, 0x00, 0x00]).is_err());
+    }
+}
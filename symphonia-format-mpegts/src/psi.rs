@@ -0,0 +1,270 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::checksum::Crc32;
+use symphonia_core::errors::{decode_error, Result};
+use symphonia_core::io::Monitor;
+
+/// The table identifier of a program association section.
+const PAT_TABLE_ID: u8 = 0x00;
+
+/// The table identifier of a program map section.
+const PMT_TABLE_ID: u8 = 0x02;
+
+/// The length of the common header of a long-form section, up-to and including the last section
+/// number field.
+const SECTION_HEADER_LEN: usize = 8;
+
+/// The length of the CRC-32 that terminates a long-form section.
+const SECTION_CRC_LEN: usize = 4;
+
+/// Reassembles a program specific information (PSI) section from the payloads of transport
+/// stream packets.
+#[derive(Default)]
+pub struct SectionBuffer {
+    buf: Vec<u8>,
+    started: bool,
+}
+
+impl SectionBuffer {
+    /// Push the payload of a transport stream packet. Returns a complete section, if one is
+    /// available.
+    pub fn push(&mut self, payload: &[u8], payload_unit_start: bool) -> Option<&[u8]> {
+        if payload_unit_start {
+            // The pointer field gives the offset of the start of the section.
+            let pointer = usize::from(*payload.first()?);
+
+            self.buf.clear();
+            self.buf.extend_from_slice(payload.get(1 + pointer..)?);
+            self.started = true;
+        }
+        else if self.started {
+            self.buf.extend_from_slice(payload);
+        }
+        else {
+            return None;
+        }
+
+        if self.buf.len() < 3 {
+            return None;
+        }
+
+        let len = 3 + ((usize::from(self.buf[1] & 0x0f) << 8) | usize::from(self.buf[2]));
+
+        if self.buf.len() < len {
+            return None;
+        }
+
+        self.started = false;
+
+        Some(&self.buf[..len])
+    }
+}
+
+/// Validate the header and CRC of a long-form section, and return the section body.
+fn read_section(section: &[u8], table_id: u8) -> Result<&[u8]> {
+    if section.len() < SECTION_HEADER_LEN + SECTION_CRC_LEN {
+        return decode_error("mpegts: section is too short");
+    }
+
+    if section[0] != table_id {
+        return decode_error("mpegts: unexpected section table id");
+    }
+
+    // The CRC-32 of a section, including the CRC, is always 0.
+    let mut crc32 = Crc32::new(0xffff_ffff);
+    crc32.process_buf_bytes(section);
+
+    if crc32.crc() != 0 {
+        return decode_error("mpegts: section crc mismatch");
+    }
+
+    Ok(&section[SECTION_HEADER_LEN..section.len() - SECTION_CRC_LEN])
+}
+
+/// A program of a program association section.
+#[derive(Debug)]
+pub struct PatProgram {
+    /// The program number.
+    pub number: u16,
+    /// The PID of the packets carrying the program map section of the program.
+    pub pmt_pid: u16,
+}
+
+/// Read a program association section, and return the programs.
+pub fn read_pat(section: &[u8]) -> Result<Vec<PatProgram>> {
+    let body = read_section(section, PAT_TABLE_ID)?;
+
+    // Program number 0 is the network information table, and is not a program.
+    let programs = body
+        .chunks_exact(4)
+        .map(|entry| PatProgram {
+            number: (u16::from(entry[0]) << 8) | u16::from(entry[1]),
+            pmt_pid: (u16::from(entry[2] & 0x1f) << 8) | u16::from(entry[3]),
+        })
+        .filter(|program| program.number != 0)
+        .collect();
+
+    Ok(programs)
+}
+
+/// An elementary stream of a program.
+#[derive(Debug)]
+pub struct PmtStream {
+    /// The stream type.
+    pub stream_type: u8,
+    /// The PID of the packets carrying the elementary stream.
+    pub pid: u16,
+}
+
+/// A program map section.
+#[derive(Debug)]
+pub struct Pmt {
+    /// The PID of the packets carrying the program clock reference (PCR) of the program.
+    pub pcr_pid: u16,
+    /// The elementary streams of the program.
+    pub streams: Vec<PmtStream>,
+}
+
+/// Read a program map section.
+pub fn read_pmt(section: &[u8]) -> Result<Pmt> {
+    let body = read_section(section, PMT_TABLE_ID)?;
+
+    if body.len() < 4 {
+        return decode_error("mpegts: program map section is too short");
+    }
+
+    let pcr_pid = (u16::from(body[0] & 0x1f) << 8) | u16::from(body[1]);
+
+    // Skip the program descriptors.
+    let program_info_len = (usize::from(body[2] & 0x0f) << 8) | usize::from(body[3]);
+
+    let mut entries = match body.get(4 + program_info_len..) {
+        Some(entries) => entries,
+        None => return decode_error("mpegts: program info length exceeds section length"),
+    };
+
+    let mut streams = Vec::new();
+
+    while entries.len() >= 5 {
+        let stream_type = entries[0];
+        let pid = (u16::from(entries[1] & 0x1f) << 8) | u16::from(entries[2]);
+        let es_info_len = (usize::from(entries[3] & 0x0f) << 8) | usize::from(entries[4]);
+
+        streams.push(PmtStream { stream_type, pid });
+
+        entries = match entries.get(5 + es_info_len..) {
+            Some(entries) => entries,
+            None => return decode_error("mpegts: es info length exceeds section length"),
+        };
+    }
+
+    Ok(Pmt { pcr_pid, streams })
+}
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::checksum::Crc32;
+    use symphonia_core::io::Monitor;
+
+    use super::{read_pat, read_pmt, SectionBuffer};
+
+    /// Make a long-form section with the given table id and body.
+    fn make_section(table_id: u8, body: &[u8]) -> Vec<u8> {
+        let len = 5 + body.len() + 4;
+
+        let mut section = vec![table_id, 0xb0 | (len >> 8) as u8, len as u8];
+        section.extend_from_slice(&[0x00, 0x01, 0xc1, 0x00, 0x00]);
+        section.extend_from_slice(body);
+
+        let mut crc = Crc32::new(0xffff_ffff);
+        crc.process_buf_bytes(&section);
+        section.extend_from_slice(&crc.crc().to_be_bytes());
+
+        section
+    }
+
+    #[test]
+    fn verify_read_pat() {
+        // The network information table, followed by programs 1 and 2.
+        let section = make_section(
+            0x00,
+            &[0x00, 0x00, 0xe0, 0x10, 0x00, 0x01, 0xf0, 0x00, 0x00, 0x02, 0xe1, 0x00],
+        );
+
+        let programs = read_pat(&section).unwrap();
+
+        assert_eq!(programs.len(), 2);
+        assert_eq!((programs[0].number, programs[0].pmt_pid), (1, 0x1000));
+        assert_eq!((programs[1].number, programs[1].pmt_pid), (2, 0x0100));
+
+        // A corrupt section.
+        let mut corrupt = section.clone();
+        corrupt[10] ^= 0x01;
+        assert!(read_pat(&corrupt).is_err());
+
+        // A program map section is not a program association section.
+        assert!(read_pat(&make_section(0x02, &[])).is_err());
+    }
+
+    #[test]
+    fn verify_read_pmt() {
+        let section = make_section(
+            0x02,
+            &[
+                // The PCR PID, and a program descriptor.
+                0xe1, 0x00, 0xf0, 0x03, 0x0e, 0x01, 0xff,
+                // A video stream without descriptors.
+                0x1b, 0xe1, 0x00, 0xf0, 0x00,
+                // An ADTS audio stream with a language descriptor.
+                0x0f, 0xe1, 0x01, 0xf0, 0x06, 0x0a, 0x04, b'e', b'n', b'g', 0x00,
+                // A MPEG audio stream.
+                0x04, 0xe1, 0x02, 0xf0, 0x00,
+            ],
+        );
+
+        let pmt = read_pmt(&section).unwrap();
+
+        assert_eq!(pmt.pcr_pid, 0x0100);
+
+        let streams: Vec<(u8, u16)> = pmt.streams.iter().map(|s| (s.stream_type, s.pid)).collect();
+        assert_eq!(streams, [(0x1b, 0x0100), (0x0f, 0x0101), (0x04, 0x0102)]);
+
+        // The program info length exceeds the section.
+        assert!(read_pmt(&make_section(0x02, &[0xe1, 0x00, 0xf0, 0x08, 0x00])).is_err());
+
+        // An elementary stream info length exceeds the section.
+        let section = make_section(0x02, &[0xe1, 0x00, 0xf0, 0x00, 0x0f, 0xe1, 0x01, 0xf0, 0x04]);
+        assert!(read_pmt(&section).is_err());
+    }
+
+    #[test]
+    fn verify_section_buffer() {
+        let section = make_section(0x02, &[0xe1, 0x00, 0xf0, 0x00, 0x0f, 0xe1, 0x01, 0xf0, 0x00]);
+
+        let mut buf = SectionBuffer::default();
+
+        // Payloads are ignored until the start of a section.
+        assert!(buf.push(&section[4..], false).is_none());
+
+        // The section starts after the bytes skipped by the pointer field, and continues in the
+        // next payload.
+        let mut start = vec![0x02, 0xaa, 0xbb];
+        start.extend_from_slice(&section[..6]);
+
+        assert!(buf.push(&start, true).is_none());
+
+        // The bytes following the section are stuffing.
+        let mut end = section[6..].to_vec();
+        end.extend_from_slice(&[0xff; 8]);
+
+        assert_eq!(buf.push(&end, false), Some(&section[..]));
+
+        // A new section is not started by a continuation.
+        assert!(buf.push(&end, false).is_none());
+    }
+}
@@ -10,4 +10,17 @@ use symphonia_core::meta::MetadataRevision;
 /// Side data variants.
 pub enum SideData {
     Metadata(MetadataRevision),
+    StreamInfo(StreamInfo),
+}
+
+/// Information about a logical stream provided by another logical stream, such as a Skeleton.
+pub struct StreamInfo {
+    /// The serial of the described logical stream.
+    pub serial: u32,
+    /// The name of the logical stream.
+    pub name: Option<String>,
+    /// The role of the logical stream (e.g., "audio/main").
+    pub role: Option<String>,
+    /// The language of the logical stream.
+    pub language: Option<String>,
 }
@@ -18,7 +18,7 @@ use symphonia_core::support_format;
 
 use log::{debug, info, warn};
 
-use super::common::SideData;
+use super::common::{SideData, StreamInfo};
 use super::logical::LogicalStream;
 use super::mappings;
 use super::page::*;
@@ -221,6 +221,9 @@ impl OggReader {
         // The start of page position.
         let mut byte_range_start = self.reader.pos();
 
+        // Information about the logical streams provided by a Skeleton stream.
+        let mut stream_infos = Vec::<StreamInfo>::new();
+
         // Pre-condition: This function is only called when the current page is marked as a
         // first page.
         assert!(self.pages.header().is_first_page);
@@ -275,6 +278,7 @@ impl OggReader {
                 for data in side_data {
                     match data {
                         SideData::Metadata(rev) => self.metadata.push(rev),
+                        SideData::StreamInfo(info) => stream_infos.push(info),
                     }
                 }
 
@@ -306,6 +310,12 @@ impl OggReader {
                     byte_range_start,
                     total_len,
                 )?;
+
+                // Probing the end of the physical stream reads pages ahead, and, if the stream is
+                // chained, may stop on the first page of the next physical stream. Re-read the
+                // current page so that the packets buffered from it are not skipped.
+                self.reader.seek(SeekFrom::Start(byte_range_start))?;
+                self.pages.try_next_page(&mut self.reader)?;
             }
         }
 
@@ -314,15 +324,31 @@ impl OggReader {
         // First, clear the existing track listing.
         self.tracks.clear();
 
-        // Second, add a track for all streams.
+        // Second, add a track for all streams, except those that only describe other streams.
         for (&serial, stream) in streams.iter() {
+            if stream.is_meta_stream() {
+                continue;
+            }
+
             // Warn if the track is not ready. This should not happen if the physical stream was
             // muxed properly.
             if !stream.is_ready() {
                 warn!("track for serial={:#x} may not be ready", serial);
             }
 
-            self.tracks.push(Track::new(serial, stream.codec_params().clone()));
+            let mut track = Track::new(serial, stream.codec_params().clone());
+
+            // Apply the name, language, and role of the stream, if described.
+            if let Some(info) = stream_infos.iter().find(|info| info.serial == serial) {
+                track.name = info.name.clone();
+                track.language = info.language.clone();
+
+                if info.role.as_deref() == Some("audio/main") {
+                    track.flags |= TrackFlags::DEFAULT;
+                }
+            }
+
+            self.tracks.push(track);
         }
 
         // Third, replace all logical streams with the new set.
@@ -482,3 +508,126 @@ impl FormatReader for OggReader {
         self.reader
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use symphonia_core::checksum::Crc32;
+    use symphonia_core::errors::Error;
+    use symphonia_core::formats::{FormatReader, TrackFlags};
+    use symphonia_core::io::{MediaSourceStream, Monitor};
+
+    use super::OggReader;
+
+    /// Builds an OGG page containing the provided packets.
+    fn make_page(serial: u32, seq: u32, absgp: u64, flags: u8, packets: &[&[u8]]) -> Vec<u8> {
+        let mut page = Vec::new();
+        page.extend_from_slice(b"OggS");
+        page.extend_from_slice(&[0, flags]);
+        page.extend_from_slice(&absgp.to_le_bytes());
+        page.extend_from_slice(&serial.to_le_bytes());
+        page.extend_from_slice(&seq.to_le_bytes());
+        page.extend_from_slice(&[0; 4]);
+        page.push(packets.len() as u8);
+        page.extend(packets.iter().map(|packet| packet.len() as u8));
+
+        for packet in packets {
+            page.extend_from_slice(packet);
+        }
+
+        let mut crc32 = Crc32::new(0);
+        crc32.process_buf_bytes(&page);
+        page[22..26].copy_from_slice(&crc32.crc().to_le_bytes());
+        page
+    }
+
+    /// Builds a complete logical Opus stream with the provided serial and title.
+    fn make_opus_link(serial: u32, title: &str) -> Vec<u8> {
+        let mut head = b"OpusHead".to_vec();
+        head.extend_from_slice(&[1, 2, 0x38, 0x01, 0x80, 0xbb, 0, 0, 0, 0, 0]);
+
+        let comment = format!("TITLE={}", title);
+
+        let mut tags = b"OpusTags".to_vec();
+        tags.extend_from_slice(&4u32.to_le_bytes());
+        tags.extend_from_slice(b"test");
+        tags.extend_from_slice(&1u32.to_le_bytes());
+        tags.extend_from_slice(&(comment.len() as u32).to_le_bytes());
+        tags.extend_from_slice(comment.as_bytes());
+
+        let mut link = make_page(serial, 0, 0, 0x02, &[&head]);
+        link.extend(make_page(serial, 1, 0, 0x00, &[&tags]));
+        link.extend(make_page(serial, 2, 1920, 0x04, &[&[0xfc, 0xff, 0xfe], &[0xfc, 0xff, 0xfe]]));
+        link
+    }
+
+    fn latest_title(ogg: &mut OggReader) -> String {
+        let mut metadata = ogg.metadata();
+        metadata.skip_to_latest().unwrap().tags()[0].value.to_string()
+    }
+
+    #[test]
+    fn verify_chained_streams() {
+        let mut buf = make_opus_link(1, "One");
+        buf.extend(make_opus_link(2, "Two"));
+
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(buf)), Default::default());
+        let mut ogg = OggReader::try_new(mss, &Default::default()).unwrap();
+
+        assert_eq!(ogg.tracks().len(), 1);
+        assert_eq!(ogg.tracks()[0].id, 1);
+        assert_eq!(latest_title(&mut ogg), "One");
+
+        assert_eq!(ogg.next_packet().unwrap().track_id(), 1);
+        assert_eq!(ogg.next_packet().unwrap().track_id(), 1);
+
+        // The start of the next link replaces the tracks and metadata.
+        assert!(matches!(ogg.next_packet(), Err(Error::ResetRequired)));
+        assert_eq!(ogg.tracks().len(), 1);
+        assert_eq!(ogg.tracks()[0].id, 2);
+        assert_eq!(latest_title(&mut ogg), "Two");
+
+        assert_eq!(ogg.next_packet().unwrap().track_id(), 2);
+        assert_eq!(ogg.next_packet().unwrap().track_id(), 2);
+        assert!(matches!(ogg.next_packet(), Err(Error::IoError(_))));
+    }
+    #[test]
+    fn verify_skeleton_stream() {
+        let mut fishead = b"fishead\0".to_vec();
+        fishead.extend_from_slice(&[3, 0, 0, 0]);
+        fishead.resize(64, 0);
+
+        let mut fisbone = b"fisbone\0".to_vec();
+        fisbone.extend_from_slice(&44u32.to_le_bytes());
+        fisbone.extend_from_slice(&2u32.to_le_bytes());
+        fisbone.extend_from_slice(&2u32.to_le_bytes());
+        fisbone.resize(52, 0);
+        fisbone.extend_from_slice(b"Content-Type: audio/opus\r\nRole: audio/main\r\n");
+        fisbone.extend_from_slice(b"Name: opus\r\nTitle: Main Audio\r\nLanguage: en\r\n");
+
+        // The first pages of all logical streams precede all other pages.
+        let opus = make_opus_link(2, "One");
+        let opus_head_len = 27 + 1 + usize::from(opus[27]);
+
+        let mut buf = make_page(1, 0, 0, 0x02, &[&fishead]);
+        buf.extend_from_slice(&opus[..opus_head_len]);
+        buf.extend(make_page(1, 1, 0, 0x00, &[&fisbone]));
+        buf.extend(make_page(1, 2, 0, 0x04, &[&[]]));
+        buf.extend_from_slice(&opus[opus_head_len..]);
+
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(buf)), Default::default());
+        let mut ogg = OggReader::try_new(mss, &Default::default()).unwrap();
+
+        // The Skeleton stream is not a track, but describes the Opus track.
+        assert_eq!(ogg.tracks().len(), 1);
+
+        let track = &ogg.tracks()[0];
+        assert_eq!(track.id, 2);
+        assert_eq!(track.name.as_deref(), Some("Main Audio"));
+        assert_eq!(track.language.as_deref(), Some("en"));
+        assert!(track.flags.contains(TrackFlags::DEFAULT));
+
+        assert_eq!(ogg.next_packet().unwrap().track_id(), 2);
+    }
+}
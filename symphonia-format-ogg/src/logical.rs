@@ -79,6 +79,11 @@ impl LogicalStream {
         self.mapper.is_ready()
     }
 
+    /// Returns true if the stream only carries information about the other streams.
+    pub fn is_meta_stream(&self) -> bool {
+        self.mapper.is_meta_stream()
+    }
+
     /// Get the `CodecParameters` for the logical stream.
     pub fn codec_params(&self) -> &CodecParameters {
         self.mapper.codec_params()
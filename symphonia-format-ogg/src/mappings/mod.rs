@@ -12,6 +12,7 @@ use symphonia_core::errors::Result;
 
 mod flac;
 mod opus;
+mod skeleton;
 mod vorbis;
 
 /// Detect a `Mapper` for a logical stream given the identification packet of the stream.
@@ -19,6 +20,7 @@ pub fn detect(buf: &[u8]) -> Result<Option<Box<dyn Mapper>>> {
     let mapper = flac::detect(buf)?
         .or(vorbis::detect(buf)?)
         .or(opus::detect(buf)?)
+        .or(skeleton::detect(buf)?)
         .or_else(make_null_mapper);
 
     Ok(mapper)
@@ -74,6 +76,12 @@ pub trait Mapper: Send + Sync {
     fn is_ready(&self) -> bool {
         true
     }
+
+    /// Returns `true` if the stream only carries information about the other streams, such as a
+    /// Skeleton stream, and should not be exposed as a track.
+    fn is_meta_stream(&self) -> bool {
+        false
+    }
 }
 
 fn make_null_mapper() -> Option<Box<dyn Mapper>> {
@@ -147,12 +147,48 @@ pub fn detect(buf: &[u8]) -> Result<Option<Box<dyn Mapper>>> {
     Ok(Some(mapper))
 }
 
+/// Get the duration of an Opus packet in samples at 48 kHz, as described by the table-of-contents
+/// byte and, if present, the frame count byte (RFC 6716 section 3.1). Returns 0 for an invalid
+/// packet.
+fn opus_packet_dur(packet: &[u8]) -> u64 {
+    let toc = match packet.first() {
+        Some(&toc) => toc,
+        None => return 0,
+    };
+
+    let config = usize::from(toc >> 3);
+
+    // The duration of each frame in the packet.
+    let frame_dur = match config {
+        // SILK-only: 10, 20, 40, or 60 ms.
+        0..=11 => [480, 960, 1920, 2880][config & 0x3],
+        // Hybrid: 10 or 20 ms.
+        12..=15 => [480, 960][config & 0x1],
+        // CELT-only: 2.5, 5, 10, or 20 ms.
+        _ => [120, 240, 480, 960][config & 0x3],
+    };
+
+    let n_frames = match toc & 0x3 {
+        0 => 1,
+        1 | 2 => 2,
+        _ => match packet.get(1) {
+            Some(&count) => u64::from(count & 0x3f),
+            None => return 0,
+        },
+    };
+
+    // A packet may not exceed 120 ms.
+    match n_frames * frame_dur {
+        dur if dur <= 5760 => dur,
+        _ => 0,
+    }
+}
+
 pub struct OpusPacketParser {}
 
 impl PacketParser for OpusPacketParser {
-    fn parse_next_packet_dur(&mut self, _packet: &[u8]) -> u64 {
-        // TODO: Implement.
-        0
+    fn parse_next_packet_dur(&mut self, packet: &[u8]) -> u64 {
+        opus_packet_dur(packet)
     }
 }
 
@@ -184,7 +220,7 @@ impl Mapper for OpusMapper {
 
     fn map_packet(&mut self, packet: &[u8]) -> Result<MapResult> {
         if !self.need_comment {
-            Ok(MapResult::StreamData { dur: 0 })
+            Ok(MapResult::StreamData { dur: opus_packet_dur(packet) })
         }
         else {
             let mut reader = BufReader::new(packet);
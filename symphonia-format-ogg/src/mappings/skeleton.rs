@@ -0,0 +1,128 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use super::{MapResult, Mapper, NullPacketParser, PacketParser};
+use crate::common::{SideData, StreamInfo};
+
+use symphonia_core::codecs::CodecParameters;
+use symphonia_core::errors::{decode_error, Result};
+use symphonia_core::io::{BufReader, ReadBytes};
+
+use log::debug;
+
+/// The signature of the Skeleton header packet.
+const SKELETON_FISHEAD_SIGNATURE: &[u8] = b"fishead\0";
+
+/// The signature of a Skeleton bone packet.
+const SKELETON_FISBONE_SIGNATURE: &[u8] = b"fisbone\0";
+
+/// The minimum size of the Skeleton header packet (version 3).
+const SKELETON_FISHEAD_MIN_SIZE: usize = 64;
+
+/// The minimum size of a Skeleton bone packet, excluding the message header fields.
+const SKELETON_FISBONE_MIN_SIZE: usize = 52;
+
+/// Detect a Skeleton logical stream given its header packet.
+pub fn detect(buf: &[u8]) -> Result<Option<Box<dyn Mapper>>> {
+    if buf.len() < SKELETON_FISHEAD_MIN_SIZE || !buf.starts_with(SKELETON_FISHEAD_SIGNATURE) {
+        return Ok(None);
+    }
+
+    let mut reader = BufReader::new(buf);
+    reader.ignore_bytes(SKELETON_FISHEAD_SIGNATURE.len() as u64)?;
+
+    let major = reader.read_u16()?;
+    let minor = reader.read_u16()?;
+
+    debug!("skeleton version {}.{}", major, minor);
+
+    Ok(Some(Box::new(SkeletonMapper { params: CodecParameters::new() })))
+}
+
+/// Read a Skeleton bone packet describing another logical stream of the physical stream.
+fn read_fisbone(buf: &[u8]) -> Result<StreamInfo> {
+    if buf.len() < SKELETON_FISBONE_MIN_SIZE {
+        return decode_error("ogg: skeleton fisbone packet too small");
+    }
+
+    let mut reader = BufReader::new(&buf[SKELETON_FISBONE_SIGNATURE.len()..]);
+
+    // The offset of the message header fields is relative to the offset field itself.
+    let offset = reader.read_u32()? as usize;
+    let serial = reader.read_u32()?;
+
+    let fields = match buf.get(SKELETON_FISBONE_SIGNATURE.len() + offset..) {
+        Some(fields) => String::from_utf8_lossy(fields),
+        None => return decode_error("ogg: skeleton invalid message header offset"),
+    };
+
+    let mut name = None;
+    let mut title = None;
+    let mut role = None;
+    let mut language = None;
+
+    // The message header fields are formatted like HTTP headers: one "Name: value" pair per line.
+    for line in fields.lines() {
+        if let Some((key, value)) = line.split_once(':') {
+            let value = Some(value.trim().to_string());
+
+            match key.trim().to_ascii_lowercase().as_str() {
+                "name" => name = value,
+                "title" => title = value,
+                "role" => role = value,
+                "language" => language = value,
+                _ => (),
+            }
+        }
+    }
+
+    // Prefer the human-readable title over the unique name, if both are provided.
+    Ok(StreamInfo { serial, name: title.or(name), role, language })
+}
+
+/// A mapper for the Skeleton logical stream. The Skeleton stream only carries information about
+/// the other logical streams, and is never exposed as a track.
+struct SkeletonMapper {
+    params: CodecParameters,
+}
+
+impl Mapper for SkeletonMapper {
+    fn name(&self) -> &'static str {
+        "skeleton"
+    }
+
+    fn codec_params(&self) -> &CodecParameters {
+        &self.params
+    }
+
+    fn codec_params_mut(&mut self) -> &mut CodecParameters {
+        &mut self.params
+    }
+
+    fn reset(&mut self) {
+        // Nothing to do!
+    }
+
+    fn make_parser(&self) -> Option<Box<dyn PacketParser>> {
+        Some(Box::new(NullPacketParser {}))
+    }
+
+    fn map_packet(&mut self, packet: &[u8]) -> Result<MapResult> {
+        if packet.starts_with(SKELETON_FISBONE_SIGNATURE) {
+            let info = read_fisbone(packet)?;
+            Ok(MapResult::SideData { data: SideData::StreamInfo(info) })
+        }
+        else {
+            // The Skeleton header, index, and end-of-stream packets.
+            Ok(MapResult::Setup)
+        }
+    }
+
+    fn is_meta_stream(&self) -> bool {
+        true
+    }
+}
@@ -11,13 +11,13 @@ use std::marker::PhantomData;
 use symphonia_core::audio::Channels;
 use symphonia_core::codecs::CodecType;
 use symphonia_core::codecs::{
-    CODEC_TYPE_ADPCM_IMA_WAV, CODEC_TYPE_ADPCM_MS, CODEC_TYPE_PCM_ALAW, CODEC_TYPE_PCM_F32LE,
-    CODEC_TYPE_PCM_F64LE, CODEC_TYPE_PCM_MULAW, CODEC_TYPE_PCM_S16LE, CODEC_TYPE_PCM_S24LE,
-    CODEC_TYPE_PCM_S32LE, CODEC_TYPE_PCM_U8,
+    CODEC_TYPE_ADPCM_G726, CODEC_TYPE_ADPCM_IMA_WAV, CODEC_TYPE_ADPCM_MS, CODEC_TYPE_PCM_ALAW,
+    CODEC_TYPE_PCM_F32LE, CODEC_TYPE_PCM_F64LE, CODEC_TYPE_PCM_MULAW, CODEC_TYPE_PCM_S16LE,
+    CODEC_TYPE_PCM_S24LE, CODEC_TYPE_PCM_S32LE, CODEC_TYPE_PCM_U8,
 };
 use symphonia_core::errors::{decode_error, unsupported_error, Result};
 use symphonia_core::io::ReadBytes;
-use symphonia_core::meta::Tag;
+use symphonia_core::meta::{StandardTagKey, Tag, Value};
 use symphonia_metadata::riff;
 
 use log::info;
@@ -27,13 +27,13 @@ use crate::PacketInfo;
 /// `ParseChunkTag` implements `parse_tag` to map between the 4-byte chunk identifier and the
 /// enumeration
 pub trait ParseChunkTag: Sized {
-    fn parse_tag(tag: [u8; 4], len: u32) -> Option<Self>;
+    fn parse_tag(tag: [u8; 4], len: u64) -> Option<Self>;
 }
 
 enum NullChunks {}
 
 impl ParseChunkTag for NullChunks {
-    fn parse_tag(_tag: [u8; 4], _len: u32) -> Option<Self> {
+    fn parse_tag(_tag: [u8; 4], _len: u64) -> Option<Self> {
         None
     }
 }
@@ -74,40 +74,113 @@ fn test_fix_channel_mask() {
     assert_eq!(fix_channel_mask(0xFFFFFFFF, 8), 0b11111111);
 }
 
+/// The suffix shared by the Wave64 GUIDs of all chunks that have an equivalent RIFF chunk. The
+/// first 4 bytes of these GUIDs are the RIFF chunk identifier.
+const WAVE64_GUID_SUFFIX: [u8; 12] =
+    [0xf3, 0xac, 0xd3, 0x11, 0x8c, 0xd1, 0x00, 0xc0, 0x4f, 0x8e, 0xdb, 0x8a];
+
+/// Get the RIFF chunk identifier equivalent to a Wave64 chunk GUID. A GUID without an equivalent
+/// RIFF chunk maps to an all-zero identifier, which is never recognized.
+fn wave64_chunk_tag(guid: &[u8; 16]) -> [u8; 4] {
+    if guid[4..] == WAVE64_GUID_SUFFIX {
+        [guid[0], guid[1], guid[2], guid[3]]
+    }
+    else {
+        [0; 4]
+    }
+}
+
+/// The layout of the chunks read by a `ChunksReader`.
+#[derive(Copy, Clone)]
+pub enum ChunkLayout {
+    /// RIFF chunks have a 4-byte identifier, a 32-bit length, and are aligned to 2 bytes.
+    Riff,
+    /// RF64 chunks are RIFF chunks, except the length of the data chunk is provided by the ds64
+    /// chunk if it does not fit in 32 bits.
+    Rf64 { data_len: u64 },
+    /// Wave64 chunks have a 16-byte GUID, a 64-bit length that includes the chunk header, and are
+    /// aligned to 8 bytes.
+    Wave64,
+}
+
+impl ChunkLayout {
+    fn header_len(&self) -> u64 {
+        match self {
+            ChunkLayout::Wave64 => 24,
+            _ => 8,
+        }
+    }
+
+    fn alignment(&self) -> u64 {
+        match self {
+            ChunkLayout::Wave64 => 8,
+            _ => 2,
+        }
+    }
+}
+
 /// `ChunksReader` reads chunks from a `ByteStream`. It is generic across a type, usually an enum,
 /// implementing the `ParseChunkTag` trait. When a new chunk is encountered in the stream,
 /// `parse_tag` on T is called to return an object capable of parsing/reading that chunk or `None`.
 /// This makes reading the actual chunk data lazy in that the  chunk is not read until the object is
 /// consumed.
 pub struct ChunksReader<T: ParseChunkTag> {
-    len: u32,
-    consumed: u32,
+    len: u64,
+    consumed: u64,
+    layout: ChunkLayout,
     phantom: PhantomData<T>,
 }
 
 impl<T: ParseChunkTag> ChunksReader<T> {
-    pub fn new(len: u32) -> Self {
-        ChunksReader { len, consumed: 0, phantom: PhantomData }
+    pub fn new(len: u64) -> Self {
+        ChunksReader::with_layout(len, ChunkLayout::Riff)
+    }
+
+    pub fn with_layout(len: u64, layout: ChunkLayout) -> Self {
+        ChunksReader { len, consumed: 0, layout, phantom: PhantomData }
     }
 
     pub fn next<B: ReadBytes>(&mut self, reader: &mut B) -> Result<Option<T>> {
         // Loop until a chunk is recognized and returned, or the end of stream is reached.
         loop {
-            // Align to the next 2-byte boundary if not currently aligned.
-            if self.consumed & 0x1 == 1 {
-                reader.read_u8()?;
-                self.consumed += 1;
+            // Align to the next chunk boundary if not currently aligned.
+            let misalignment = self.consumed % self.layout.alignment();
+
+            if misalignment != 0 {
+                let padding = self.layout.alignment() - misalignment;
+                reader.ignore_bytes(padding)?;
+                self.consumed += padding;
             }
 
             // Check if there are enough bytes for another chunk, if not, there are no more chunks.
-            if self.consumed + 8 > self.len {
+            if self.consumed + self.layout.header_len() > self.len {
                 return Ok(None);
             }
 
             // Read tag and len, the chunk header.
-            let tag = reader.read_quad_bytes()?;
-            let len = reader.read_u32()?;
-            self.consumed += 8;
+            let (tag, mut len) = match self.layout {
+                ChunkLayout::Wave64 => {
+                    let mut guid = [0; 16];
+                    reader.read_buf_exact(&mut guid)?;
+
+                    // The length of a Wave64 chunk includes the chunk header.
+                    match reader.read_u64()?.checked_sub(self.layout.header_len()) {
+                        Some(len) => (wave64_chunk_tag(&guid), len),
+                        None => return decode_error("wav: invalid wave64 chunk length"),
+                    }
+                }
+                _ => (reader.read_quad_bytes()?, u64::from(reader.read_u32()?)),
+            };
+
+            self.consumed += self.layout.header_len();
+
+            // An RF64 data chunk with a length that does not fit in 32 bits has its length
+            // stored in the ds64 chunk.
+            if let ChunkLayout::Rf64 { data_len } = self.layout {
+                if &tag == b"data" && len == u64::from(u32::MAX) {
+                    len = data_len;
+                }
+            }
 
             // Check if the ChunkReader has enough unread bytes to fully read the chunk.
             //
@@ -116,7 +189,7 @@ impl<T: ParseChunkTag> ChunksReader<T> {
             if self.len - self.consumed < len {
                 // When ffmpeg encodes wave to stdout the riff (parent) and data chunk lengths are
                 // (2^32)-1 since the size can't be known ahead of time.
-                if !(self.len == len && len == u32::MAX) {
+                if !(self.len == len && len == u64::from(u32::MAX)) {
                     return decode_error("wav: chunk length exceeds parent (list) chunk length");
                 }
             }
@@ -134,7 +207,7 @@ impl<T: ParseChunkTag> ChunksReader<T> {
                         len
                     );
 
-                    reader.ignore_bytes(u64::from(len))?
+                    reader.ignore_bytes(len)?
                 }
             }
         }
@@ -144,13 +217,15 @@ impl<T: ParseChunkTag> ChunksReader<T> {
         // If data is remaining in this chunk, skip it.
         if self.consumed < self.len {
             let remaining = self.len - self.consumed;
-            reader.ignore_bytes(u64::from(remaining))?;
+            reader.ignore_bytes(remaining)?;
             self.consumed += remaining;
         }
 
-        // Pad the chunk to the next 2-byte boundary.
-        if self.len & 0x1 == 1 {
-            reader.read_u8()?;
+        // Pad the chunk to the next chunk boundary.
+        let misalignment = self.len % self.layout.alignment();
+
+        if misalignment != 0 {
+            reader.ignore_bytes(self.layout.alignment() - misalignment)?;
         }
 
         Ok(())
@@ -159,18 +234,18 @@ impl<T: ParseChunkTag> ChunksReader<T> {
 
 /// Common trait implemented for all chunks that are parsed by a `ChunkParser`.
 pub trait ParseChunk: Sized {
-    fn parse<B: ReadBytes>(reader: &mut B, tag: [u8; 4], len: u32) -> Result<Self>;
+    fn parse<B: ReadBytes>(reader: &mut B, tag: [u8; 4], len: u64) -> Result<Self>;
 }
 
 /// `ChunkParser` is a utility struct for unifying the parsing of chunks.
 pub struct ChunkParser<P: ParseChunk> {
     tag: [u8; 4],
-    len: u32,
+    len: u64,
     phantom: PhantomData<P>,
 }
 
 impl<P: ParseChunk> ChunkParser<P> {
-    fn new(tag: [u8; 4], len: u32) -> Self {
+    fn new(tag: [u8; 4], len: u64) -> Self {
         ChunkParser { tag, len, phantom: PhantomData }
     }
 
@@ -198,7 +273,8 @@ pub struct WaveFormatPcm {
 }
 
 pub struct WaveFormatAdpcm {
-    /// The number of bits per sample. At the moment only 4bit is supported.
+    /// The number of bits per coded sample. Microsoft and IMA ADPCM only support 4 bits, while
+    /// G.726 supports 2 to 5 bits.
     pub bits_per_sample: u16,
     /// Channel bitmask.
     pub channels: Channels,
@@ -264,7 +340,7 @@ impl WaveFormatChunk {
         reader: &mut B,
         bits_per_sample: u16,
         n_channels: u16,
-        len: u32,
+        len: u64,
     ) -> Result<WaveFormatData> {
         // WaveFormat for a PCM format may be extended with an extra data length field followed by
         // the extension data itself. Use the chunk length to determine if the format chunk is
@@ -320,7 +396,7 @@ impl WaveFormatChunk {
         reader: &mut B,
         bits_per_sample: u16,
         n_channels: u16,
-        len: u32,
+        len: u64,
         codec: CodecType,
     ) -> Result<WaveFormatData> {
         if bits_per_sample != 4 {
@@ -355,11 +431,50 @@ impl WaveFormatChunk {
         Ok(WaveFormatData::Adpcm(WaveFormatAdpcm { bits_per_sample, channels, codec }))
     }
 
+    fn read_g726_fmt<B: ReadBytes>(
+        reader: &mut B,
+        bits_per_sample: u16,
+        n_channels: u16,
+        len: u64,
+    ) -> Result<WaveFormatData> {
+        // The bit rate, 16, 24, 32, or 40 kbit/s at 8 kHz, is selected by the bits per sample.
+        if bits_per_sample < 2 || bits_per_sample > 5 {
+            return decode_error("wav: bits per sample for fmt_g726 must be 2 to 5 bits");
+        }
+
+        // The extension data, if present, is not required.
+        if len > 16 {
+            if len < 18 {
+                return decode_error("wav: malformed fmt_g726 chunk");
+            }
+
+            let extra_size = reader.read_u16()? as u64;
+
+            if extra_size > len - 18 {
+                return decode_error("wav: malformed fmt_g726 chunk");
+            }
+
+            reader.ignore_bytes(extra_size)?;
+        }
+
+        // G.726 is only supported for mono.
+        let channels = match n_channels {
+            1 => Channels::FRONT_LEFT,
+            _ => return decode_error("wav: channel layout is not mono for fmt_g726"),
+        };
+
+        Ok(WaveFormatData::Adpcm(WaveFormatAdpcm {
+            bits_per_sample,
+            channels,
+            codec: CODEC_TYPE_ADPCM_G726,
+        }))
+    }
+
     fn read_ieee_fmt<B: ReadBytes>(
         reader: &mut B,
         bits_per_sample: u16,
         n_channels: u16,
-        len: u32,
+        len: u64,
     ) -> Result<WaveFormatData> {
         // WaveFormat for a IEEE format should not be extended, but it may still have an extra data
         // length parameter.
@@ -399,7 +514,7 @@ impl WaveFormatChunk {
         reader: &mut B,
         bits_per_coded_sample: u16,
         n_channels: u16,
-        len: u32,
+        len: u64,
     ) -> Result<WaveFormatData> {
         // WaveFormat for the extensible format must be extended to 40 bytes in length.
         if len < 40 {
@@ -525,16 +640,16 @@ impl WaveFormatChunk {
     fn read_alaw_pcm_fmt<B: ReadBytes>(
         reader: &mut B,
         n_channels: u16,
-        len: u32,
+        len: u64,
     ) -> Result<WaveFormatData> {
-        if len != 18 {
-            return decode_error("wav: malformed fmt_alaw chunk");
+        // WaveFormat for a companded format should have an extra data length parameter, but some
+        // writers omit it. Any extra data is not used.
+        if len >= 18 {
+            let _extra_size = reader.read_u16()?;
+            reader.ignore_bytes(len - 18)?;
         }
-
-        let extra_size = reader.read_u16()?;
-
-        if extra_size > 0 {
-            reader.ignore_bytes(u64::from(extra_size))?;
+        else if len > 16 {
+            return decode_error("wav: malformed fmt_alaw chunk");
         }
 
         let channels = match n_channels {
@@ -549,16 +664,16 @@ impl WaveFormatChunk {
     fn read_mulaw_pcm_fmt<B: ReadBytes>(
         reader: &mut B,
         n_channels: u16,
-        len: u32,
+        len: u64,
     ) -> Result<WaveFormatData> {
-        if len != 18 {
-            return decode_error("wav: malformed fmt_mulaw chunk");
+        // WaveFormat for a companded format should have an extra data length parameter, but some
+        // writers omit it. Any extra data is not used.
+        if len >= 18 {
+            let _extra_size = reader.read_u16()?;
+            reader.ignore_bytes(len - 18)?;
         }
-
-        let extra_size = reader.read_u16()?;
-
-        if extra_size > 0 {
-            reader.ignore_bytes(u64::from(extra_size))?;
+        else if len > 16 {
+            return decode_error("wav: malformed fmt_mulaw chunk");
         }
 
         let channels = match n_channels {
@@ -589,13 +704,19 @@ impl WaveFormatChunk {
                     + 1) as u64;
                 PacketInfo::with_blocks(self.block_align, frames_per_block)
             }
+            WaveFormatData::Adpcm(WaveFormatAdpcm { codec, bits_per_sample, .. })
+                if codec == CODEC_TYPE_ADPCM_G726 =>
+            {
+                // Every 8 samples are packed into a whole number of bytes.
+                PacketInfo::with_blocks(bits_per_sample, 8)
+            }
             _ => Ok(PacketInfo::without_blocks(self.block_align)),
         }
     }
 }
 
 impl ParseChunk for WaveFormatChunk {
-    fn parse<B: ReadBytes>(reader: &mut B, _tag: [u8; 4], len: u32) -> Result<WaveFormatChunk> {
+    fn parse<B: ReadBytes>(reader: &mut B, _tag: [u8; 4], len: u64) -> Result<WaveFormatChunk> {
         // WaveFormat has a minimal length of 16 bytes. This may be extended with format specific
         // data later.
         if len < 16 {
@@ -617,6 +738,8 @@ impl ParseChunk for WaveFormatChunk {
         const WAVE_FORMAT_ALAW: u16 = 0x0006;
         const WAVE_FORMAT_MULAW: u16 = 0x0007;
         const WAVE_FORMAT_ADPCM_IMA: u16 = 0x0011;
+        const WAVE_FORMAT_G726ADPCM: u16 = 0x0045;
+        const WAVE_FORMAT_G726_ADPCM: u16 = 0x0064;
         const WAVE_FORMAT_EXTENSIBLE: u16 = 0xfffe;
 
         let format_data = match format {
@@ -642,6 +765,10 @@ impl ParseChunk for WaveFormatChunk {
                 len,
                 CODEC_TYPE_ADPCM_IMA_WAV,
             ),
+            // The G.726 ADPCM Formats
+            WAVE_FORMAT_G726ADPCM | WAVE_FORMAT_G726_ADPCM => {
+                Self::read_g726_fmt(reader, bits_per_sample, n_channels, len)
+            }
             // Unsupported format.
             _ => return unsupported_error("wav: unsupported wave format"),
         }?;
@@ -701,12 +828,38 @@ impl fmt::Display for WaveFormatChunk {
     }
 }
 
+/// An RF64 data size chunk, containing the 64-bit lengths of the RIFF and data chunks.
+pub struct Ds64Chunk {
+    pub riff_len: u64,
+    pub data_len: u64,
+    pub n_frames: u64,
+}
+
+impl ParseChunk for Ds64Chunk {
+    fn parse<B: ReadBytes>(reader: &mut B, _tag: [u8; 4], len: u64) -> Result<Self> {
+        // The ds64 chunk is atleast 28 bytes long.
+        if len < 28 {
+            return decode_error("wav: malformed ds64 chunk");
+        }
+
+        let riff_len = reader.read_u64()?;
+        let data_len = reader.read_u64()?;
+        let n_frames = reader.read_u64()?;
+
+        // Ignore the table of lengths for other chunks, since only the data chunk may be larger
+        // than 4 GiB in practice.
+        reader.ignore_bytes(len - 24)?;
+
+        Ok(Ds64Chunk { riff_len, data_len, n_frames })
+    }
+}
+
 pub struct FactChunk {
     pub n_frames: u32,
 }
 
 impl ParseChunk for FactChunk {
-    fn parse<B: ReadBytes>(reader: &mut B, _tag: [u8; 4], len: u32) -> Result<Self> {
+    fn parse<B: ReadBytes>(reader: &mut B, _tag: [u8; 4], len: u64) -> Result<Self> {
         // A Fact chunk is exactly 4 bytes long, though there is some mystery as to whether there
         // can be more fields in the chunk.
         if len != 4 {
@@ -727,7 +880,7 @@ impl fmt::Display for FactChunk {
 
 pub struct ListChunk {
     pub form: [u8; 4],
-    pub len: u32,
+    pub len: u64,
 }
 
 impl ListChunk {
@@ -737,7 +890,7 @@ impl ListChunk {
 }
 
 impl ParseChunk for ListChunk {
-    fn parse<B: ReadBytes>(reader: &mut B, _tag: [u8; 4], len: u32) -> Result<Self> {
+    fn parse<B: ReadBytes>(reader: &mut B, _tag: [u8; 4], len: u64) -> Result<Self> {
         // A List chunk must contain atleast the list/form identifier. However, an empty list
         // (len == 4) is permissible.
         if len < 4 {
@@ -762,7 +915,7 @@ pub struct InfoChunk {
 }
 
 impl ParseChunk for InfoChunk {
-    fn parse<B: ReadBytes>(reader: &mut B, tag: [u8; 4], len: u32) -> Result<InfoChunk> {
+    fn parse<B: ReadBytes>(reader: &mut B, tag: [u8; 4], len: u64) -> Result<InfoChunk> {
         // TODO: Apply limit.
         let mut value_buf = vec![0u8; len as usize];
         reader.read_buf_exact(&mut value_buf)?;
@@ -771,12 +924,284 @@ impl ParseChunk for InfoChunk {
     }
 }
 
+/// A loop within a `SmplChunk`.
+pub struct SampleLoop {
+    /// The loop type. 0 is a forward loop, 1 is an alternating loop, 2 is a backward loop.
+    pub loop_type: u32,
+    /// The frame offset of the start of the loop.
+    pub start: u32,
+    /// The frame offset of the last frame of the loop.
+    pub end: u32,
+    /// The number of times to play the loop, or 0 for an infinite loop.
+    pub play_count: u32,
+}
+
+pub struct SmplChunk {
+    pub loops: Vec<SampleLoop>,
+}
+
+impl ParseChunk for SmplChunk {
+    fn parse<B: ReadBytes>(reader: &mut B, _tag: [u8; 4], len: u64) -> Result<SmplChunk> {
+        // The sampler chunk header is 36 bytes long.
+        if len < 36 {
+            return decode_error("wav: malformed smpl chunk");
+        }
+
+        // Skip the manufacturer, product, sample period, MIDI, and SMPTE fields.
+        reader.ignore_bytes(28)?;
+
+        let n_loops = reader.read_u32()?;
+        let sampler_data_len = reader.read_u32()?;
+
+        // Each loop is 24 bytes long.
+        if u64::from(n_loops) * 24 > len - 36 {
+            return decode_error("wav: smpl chunk loop count exceeds chunk length");
+        }
+
+        let mut loops = Vec::with_capacity(n_loops as usize);
+
+        for _ in 0..n_loops {
+            let _cue_point_id = reader.read_u32()?;
+            let loop_type = reader.read_u32()?;
+            let start = reader.read_u32()?;
+            let end = reader.read_u32()?;
+            let _fraction = reader.read_u32()?;
+            let play_count = reader.read_u32()?;
+
+            loops.push(SampleLoop { loop_type, start, end, play_count });
+        }
+
+        // Ignore the sampler specific data, and anything else remaining in the chunk.
+        let remaining = len - 36 - 24 * u64::from(n_loops);
+
+        if u64::from(sampler_data_len) > remaining {
+            info!("smpl chunk sampler data length exceeds chunk length");
+        }
+
+        reader.ignore_bytes(remaining)?;
+
+        Ok(SmplChunk { loops })
+    }
+}
+
+impl SmplChunk {
+    /// Gets the loop tags for the first forward loop of the sampler chunk.
+    pub fn loop_tags(&self) -> Vec<Tag> {
+        let mut tags = Vec::new();
+
+        if let Some(lp) = self.loops.iter().find(|lp| lp.loop_type == 0 && lp.end >= lp.start) {
+            tags.push(Tag::new(None, "LOOPSTART", Value::from(lp.start.to_string())));
+            tags.push(Tag::new(None, "LOOPEND", Value::from(lp.end.to_string())));
+
+            if lp.play_count > 0 {
+                tags.push(Tag::new(None, "LOOPCOUNT", Value::from(lp.play_count.to_string())));
+            }
+        }
+
+        tags
+    }
+}
+
+/// The length of the fixed-size fields of a broadcast extension chunk.
+const BEXT_FIXED_LEN: u64 = 602;
+
+/// A loudness value of the broadcast extension chunk that is not set.
+const BEXT_LOUDNESS_UNSET: i16 = 0x7fff;
+
+/// Decode a fixed-length, null-padded, ASCII string field.
+fn decode_fixed_str(buf: &[u8]) -> String {
+    let end = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    String::from_utf8_lossy(&buf[..end]).trim().to_string()
+}
+
+/// A Broadcast Wave Format (BWF) broadcast extension chunk, as defined by EBU Tech 3285.
+pub struct BextChunk {
+    pub description: String,
+    pub originator: String,
+    pub originator_ref: String,
+    /// The origination date formatted as "yyyy:mm:dd".
+    pub origination_date: String,
+    /// The origination time formatted as "hh:mm:ss".
+    pub origination_time: String,
+    /// The timestamp of the first sample in samples since midnight.
+    pub time_ref: u64,
+    pub version: u16,
+    /// The SMPTE UMID. Only valid for version 1 and later.
+    pub umid: [u8; 64],
+    /// The loudness values in 1/100ths of a unit. Only valid for version 2 and later.
+    pub loudness_value: i16,
+    pub loudness_range: i16,
+    pub max_true_peak_level: i16,
+    pub max_momentary_loudness: i16,
+    pub max_short_term_loudness: i16,
+    pub coding_history: String,
+}
+
+impl ParseChunk for BextChunk {
+    fn parse<B: ReadBytes>(reader: &mut B, _tag: [u8; 4], len: u64) -> Result<BextChunk> {
+        if len < BEXT_FIXED_LEN {
+            return decode_error("wav: malformed bext chunk");
+        }
+
+        let mut description = [0; 256];
+        reader.read_buf_exact(&mut description)?;
+
+        let mut originator = [0; 32];
+        reader.read_buf_exact(&mut originator)?;
+
+        let mut originator_ref = [0; 32];
+        reader.read_buf_exact(&mut originator_ref)?;
+
+        let mut origination_date = [0; 10];
+        reader.read_buf_exact(&mut origination_date)?;
+
+        let mut origination_time = [0; 8];
+        reader.read_buf_exact(&mut origination_time)?;
+
+        let time_ref = reader.read_u64()?;
+        let version = reader.read_u16()?;
+
+        let mut umid = [0; 64];
+        reader.read_buf_exact(&mut umid)?;
+
+        let loudness_value = reader.read_i16()?;
+        let loudness_range = reader.read_i16()?;
+        let max_true_peak_level = reader.read_i16()?;
+        let max_momentary_loudness = reader.read_i16()?;
+        let max_short_term_loudness = reader.read_i16()?;
+
+        // Reserved.
+        reader.ignore_bytes(180)?;
+
+        // The coding history is a series of CR/LF terminated strings filling the rest of the chunk.
+        let coding_history = reader.read_boxed_slice_exact((len - BEXT_FIXED_LEN) as usize)?;
+
+        Ok(BextChunk {
+            description: decode_fixed_str(&description),
+            originator: decode_fixed_str(&originator),
+            originator_ref: decode_fixed_str(&originator_ref),
+            origination_date: decode_fixed_str(&origination_date),
+            origination_time: decode_fixed_str(&origination_time),
+            time_ref,
+            version,
+            umid,
+            loudness_value,
+            loudness_range,
+            max_true_peak_level,
+            max_momentary_loudness,
+            max_short_term_loudness,
+            coding_history: decode_fixed_str(&coding_history),
+        })
+    }
+}
+
+impl BextChunk {
+    /// Gets the tags for all fields of the broadcast extension chunk that are set.
+    pub fn tags(&self) -> Vec<Tag> {
+        let mut tags = Vec::new();
+
+        let strings = [
+            (Some(StandardTagKey::Description), "DESCRIPTION", &self.description),
+            (None, "ORIGINATOR", &self.originator),
+            (None, "ORIGINATOR_REFERENCE", &self.originator_ref),
+            (None, "ORIGINATION_DATE", &self.origination_date),
+            (None, "ORIGINATION_TIME", &self.origination_time),
+            (None, "CODING_HISTORY", &self.coding_history),
+        ];
+
+        for (std_key, key, value) in strings.iter() {
+            if !value.is_empty() {
+                tags.push(Tag::new(*std_key, key, Value::from(value.as_str())));
+            }
+        }
+
+        tags.push(Tag::new(None, "TIME_REFERENCE", Value::from(self.time_ref)));
+
+        if self.version >= 1 && self.umid.iter().any(|&b| b != 0) {
+            let umid: String = self.umid.iter().map(|b| format!("{:02x}", b)).collect();
+            tags.push(Tag::new(None, "UMID", Value::from(umid)));
+        }
+
+        if self.version >= 2 {
+            let loudness = [
+                ("LOUDNESS_VALUE", self.loudness_value),
+                ("LOUDNESS_RANGE", self.loudness_range),
+                ("MAX_TRUE_PEAK_LEVEL", self.max_true_peak_level),
+                ("MAX_MOMENTARY_LOUDNESS", self.max_momentary_loudness),
+                ("MAX_SHORT_TERM_LOUDNESS", self.max_short_term_loudness),
+            ];
+
+            for &(key, value) in loudness.iter() {
+                if value != BEXT_LOUDNESS_UNSET {
+                    tags.push(Tag::new(None, key, Value::from(f64::from(value) / 100.0)));
+                }
+            }
+        }
+
+        tags
+    }
+}
+
+#[test]
+fn test_bext_chunk() {
+    use symphonia_core::io::BufReader;
+
+    let mut buf = vec![0; BEXT_FIXED_LEN as usize];
+    buf[..9].copy_from_slice(b"Interview");
+    buf[256..260].copy_from_slice(b"Desk");
+    buf[320..338].copy_from_slice(b"2022:05:0112:30:00");
+    buf[338..346].copy_from_slice(&48000u64.to_le_bytes());
+    buf[346..348].copy_from_slice(&2u16.to_le_bytes());
+    buf[412..414].copy_from_slice(&(-2300i16).to_le_bytes());
+    buf[414..416].copy_from_slice(&BEXT_LOUDNESS_UNSET.to_le_bytes());
+    buf[416..422].copy_from_slice(&[0xff, 0x7f, 0xff, 0x7f, 0xff, 0x7f]);
+    buf.extend_from_slice(b"A=PCM,F=48000\r\n");
+
+    let len = buf.len() as u64;
+    let bext = BextChunk::parse(&mut BufReader::new(&buf), *b"bext", len).unwrap();
+
+    assert_eq!(bext.description, "Interview");
+    assert_eq!(bext.originator, "Desk");
+    assert_eq!(bext.origination_date, "2022:05:01");
+    assert_eq!(bext.origination_time, "12:30:00");
+    assert_eq!(bext.time_ref, 48000);
+    assert_eq!(bext.coding_history, "A=PCM,F=48000");
+
+    let tags = bext.tags();
+    let keys: Vec<&str> = tags.iter().map(|tag| tag.key.as_str()).collect();
+
+    assert_eq!(
+        keys,
+        [
+            "DESCRIPTION",
+            "ORIGINATOR",
+            "ORIGINATION_DATE",
+            "ORIGINATION_TIME",
+            "CODING_HISTORY",
+            "TIME_REFERENCE",
+            "LOUDNESS_VALUE",
+        ]
+    );
+    assert!(matches!(tags[6].value, Value::Float(v) if (v + 23.0).abs() < 1e-9));
+}
+
+/// A non-standard chunk containing an ID3v2 tag.
+pub struct Id3Chunk {
+    pub data: Box<[u8]>,
+}
+
+impl ParseChunk for Id3Chunk {
+    fn parse<B: ReadBytes>(reader: &mut B, _tag: [u8; 4], len: u64) -> Result<Id3Chunk> {
+        Ok(Id3Chunk { data: reader.read_boxed_slice_exact(len as usize)? })
+    }
+}
+
 pub struct DataChunk {
-    pub len: u32,
+    pub len: u64,
 }
 
 impl ParseChunk for DataChunk {
-    fn parse<B: ReadBytes>(_: &mut B, _: [u8; 4], len: u32) -> Result<DataChunk> {
+    fn parse<B: ReadBytes>(_: &mut B, _: [u8; 4], len: u64) -> Result<DataChunk> {
         Ok(DataChunk { len })
     }
 }
@@ -786,6 +1211,9 @@ pub enum RiffWaveChunks {
     List(ChunkParser<ListChunk>),
     Fact(ChunkParser<FactChunk>),
     Data(ChunkParser<DataChunk>),
+    Smpl(ChunkParser<SmplChunk>),
+    Id3(ChunkParser<Id3Chunk>),
+    Bext(ChunkParser<BextChunk>),
 }
 
 macro_rules! parser {
@@ -795,12 +1223,15 @@ macro_rules! parser {
 }
 
 impl ParseChunkTag for RiffWaveChunks {
-    fn parse_tag(tag: [u8; 4], len: u32) -> Option<Self> {
+    fn parse_tag(tag: [u8; 4], len: u64) -> Option<Self> {
         match &tag {
             b"fmt " => parser!(RiffWaveChunks::Format, WaveFormatChunk, tag, len),
             b"LIST" => parser!(RiffWaveChunks::List, ListChunk, tag, len),
             b"fact" => parser!(RiffWaveChunks::Fact, FactChunk, tag, len),
             b"data" => parser!(RiffWaveChunks::Data, DataChunk, tag, len),
+            b"smpl" => parser!(RiffWaveChunks::Smpl, SmplChunk, tag, len),
+            b"bext" => parser!(RiffWaveChunks::Bext, BextChunk, tag, len),
+            b"id3 " | b"ID3 " => parser!(RiffWaveChunks::Id3, Id3Chunk, tag, len),
             _ => None,
         }
     }
@@ -811,7 +1242,7 @@ pub enum RiffInfoListChunks {
 }
 
 impl ParseChunkTag for RiffInfoListChunks {
-    fn parse_tag(tag: [u8; 4], len: u32) -> Option<Self> {
+    fn parse_tag(tag: [u8; 4], len: u64) -> Option<Self> {
         // Right now it is assumed all list chunks are INFO chunks, but that's not really
         // guaranteed.
         //
@@ -819,3 +1250,92 @@ impl ParseChunkTag for RiffInfoListChunks {
         parser!(RiffInfoListChunks::Info, InfoChunk, tag, len)
     }
 }
+
+#[test]
+fn test_wave64_chunks() {
+    use symphonia_core::io::BufReader;
+
+    let mut buf = Vec::new();
+
+    for (tag, body) in [(b"fact", &[0x10, 0, 0, 0][..]), (b"data", &[1, 2][..])].iter() {
+        buf.extend_from_slice(&tag[..]);
+        buf.extend_from_slice(&WAVE64_GUID_SUFFIX);
+        buf.extend_from_slice(&(24 + body.len() as u64).to_le_bytes());
+        buf.extend_from_slice(body);
+        buf.resize((buf.len() + 7) & !7, 0);
+    }
+
+    let mut reader = BufReader::new(&buf);
+    let mut chunks = ChunksReader::<RiffWaveChunks>::with_layout(64, ChunkLayout::Wave64);
+
+    match chunks.next(&mut reader).unwrap() {
+        Some(RiffWaveChunks::Fact(fact)) => {
+            assert_eq!(fact.parse(&mut reader).unwrap().n_frames, 16);
+        }
+        _ => panic!("expected a fact chunk"),
+    }
+
+    match chunks.next(&mut reader).unwrap() {
+        Some(RiffWaveChunks::Data(data)) => {
+            assert_eq!(data.parse(&mut reader).unwrap().len, 2);
+        }
+        _ => panic!("expected a data chunk"),
+    }
+
+    reader.ignore_bytes(2).unwrap();
+
+    assert!(chunks.next(&mut reader).unwrap().is_none());
+}
+
+#[test]
+fn test_companded_fmt_chunk() {
+    use symphonia_core::io::BufReader;
+
+    // A-law, 1 channel, 8 kHz, 8 bits per sample, without an extra data length parameter.
+    let mut buf = vec![0x06, 0x00, 0x01, 0x00, 0x40, 0x1f, 0x00, 0x00];
+    buf.extend_from_slice(&[0x40, 0x1f, 0x00, 0x00, 0x01, 0x00, 0x08, 0x00]);
+
+    let fmt = WaveFormatChunk::parse(&mut BufReader::new(&buf), *b"fmt ", 16).unwrap();
+    assert!(matches!(fmt.format_data, WaveFormatData::ALaw(_)));
+
+    // The same chunk as µ-law, with an extra data length parameter and trailing extra data.
+    buf[0] = 0x07;
+    buf.extend_from_slice(&[0x02, 0x00, 0xff, 0xff, 0x01]);
+
+    let mut reader = BufReader::new(&buf);
+    let fmt = WaveFormatChunk::parse(&mut reader, *b"fmt ", 20).unwrap();
+    assert!(matches!(fmt.format_data, WaveFormatData::MuLaw(_)));
+    assert_eq!(reader.read_u8().unwrap(), 0x01);
+
+    assert!(WaveFormatChunk::parse(&mut BufReader::new(&buf), *b"fmt ", 17).is_err());
+}
+
+#[test]
+fn test_g726_fmt_chunk() {
+    use symphonia_core::io::BufReader;
+
+    // G.726, 1 channel, 8 kHz, 4 bits per sample, with 2 bytes of extra data.
+    let mut buf = vec![0x64, 0x00, 0x01, 0x00, 0x40, 0x1f, 0x00, 0x00];
+    buf.extend_from_slice(&[0xa0, 0x0f, 0x00, 0x00, 0x01, 0x00, 0x04, 0x00]);
+    buf.extend_from_slice(&[0x02, 0x00, 0x00, 0x00]);
+
+    let fmt = WaveFormatChunk::parse(&mut BufReader::new(&buf), *b"fmt ", 20).unwrap();
+    assert!(matches!(
+        fmt.format_data,
+        WaveFormatData::Adpcm(WaveFormatAdpcm { codec: CODEC_TYPE_ADPCM_G726, .. })
+    ));
+
+    // Every 8 samples are packed into 4 bytes.
+    let packet_info = fmt.packet_info().unwrap();
+    assert_eq!(packet_info.block_size, 4);
+    assert_eq!(packet_info.frames_per_block, 8);
+
+    // Only 2 to 5 bits per sample are valid.
+    buf[14] = 0x06;
+    assert!(WaveFormatChunk::parse(&mut BufReader::new(&buf), *b"fmt ", 20).is_err());
+
+    // Stereo is not supported.
+    buf[14] = 0x04;
+    buf[2] = 0x02;
+    assert!(WaveFormatChunk::parse(&mut BufReader::new(&buf), *b"fmt ", 20).is_err());
+}
@@ -20,12 +20,14 @@ use symphonia_core::codecs::CodecParameters;
 use symphonia_core::errors::{decode_error, end_of_stream_error, seek_error, unsupported_error};
 use symphonia_core::errors::{Result, SeekErrorKind};
 use symphonia_core::formats::prelude::*;
+use symphonia_core::formats::util::trim_packet;
 use symphonia_core::io::*;
 use symphonia_core::meta::{Metadata, MetadataBuilder, MetadataLog, MetadataRevision};
 use symphonia_core::probe::{Descriptor, Instantiate, QueryDescriptor};
 use symphonia_core::support_format;
+use symphonia_metadata::id3v2;
 
-use log::{debug, error};
+use log::{debug, error, warn};
 
 mod chunks;
 
@@ -34,9 +36,26 @@ use chunks::*;
 /// WAVE is actually a RIFF stream, with a "RIFF" ASCII stream marker.
 const WAVE_STREAM_MARKER: [u8; 4] = *b"RIFF";
 
+/// RF64 is a RIFF stream with 64-bit chunk lengths, with a "RF64" ASCII stream marker.
+const RF64_STREAM_MARKER: [u8; 4] = *b"RF64";
+
+/// BW64 is identical to RF64, but with a "BW64" ASCII stream marker.
+const BW64_STREAM_MARKER: [u8; 4] = *b"BW64";
+
 /// The RIFF form is "wave".
 const WAVE_RIFF_FORM: [u8; 4] = *b"WAVE";
 
+/// Wave64 is a RIFF-like stream that uses GUIDs instead of ASCII chunk identifiers, with the
+/// "riff" GUID as its stream marker.
+const WAVE64_STREAM_MARKER: [u8; 16] = [
+    0x72, 0x69, 0x66, 0x66, 0x2e, 0x91, 0xcf, 0x11, 0xa5, 0xd6, 0x28, 0xdb, 0x04, 0xc1, 0x00, 0x00,
+];
+
+/// The Wave64 RIFF form is the "wave" GUID.
+const WAVE64_RIFF_FORM: [u8; 16] = [
+    0x77, 0x61, 0x76, 0x65, 0xf3, 0xac, 0xd3, 0x11, 0x8c, 0xd1, 0x00, 0xc0, 0x4f, 0x8e, 0xdb, 0x8a,
+];
+
 /// The maximum number of frames that will be in a packet.
 const WAVE_MAX_FRAMES_PER_PACKET: u64 = 1152;
 
@@ -69,10 +88,22 @@ impl PacketInfo {
         }
     }
 
+    /// Limits the number of frames per packet, rounded down to a whole number of blocks. A
+    /// packet always contains at least one block.
+    fn limit_frames_per_packet(&mut self, max_frames: u64) {
+        let max_blocks = (max_frames / self.frames_per_block).max(1);
+        self.max_blocks_per_packet = self.max_blocks_per_packet.min(max_blocks);
+    }
+
     fn is_empty(&self) -> bool {
         self.block_size == 0
     }
 
+    /// Returns true if the codec codes multiple frames per block.
+    fn is_blocked(&self) -> bool {
+        self.frames_per_block > 1
+    }
+
     fn get_max_frames_per_packet(&self) -> u64 {
         self.max_blocks_per_packet * self.frames_per_block
     }
@@ -109,7 +140,15 @@ impl QueryDescriptor for WavReader {
                 "Waveform Audio File Format",
                 &["wav", "wave"],
                 &["audio/vnd.wave", "audio/x-wav", "audio/wav", "audio/wave"],
-                &[b"RIFF"]
+                &[b"RIFF", b"RF64", b"BW64"]
+            ),
+            // Wave64
+            support_format!(
+                "wave64",
+                "Sony Wave64",
+                &["w64"],
+                &["audio/x-w64"],
+                &[&WAVE64_STREAM_MARKER]
             ),
         ]
     }
@@ -120,28 +159,8 @@ impl QueryDescriptor for WavReader {
 }
 
 impl FormatReader for WavReader {
-    fn try_new(mut source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
-        // The RIFF marker should be present.
-        let marker = source.read_quad_bytes()?;
-
-        if marker != WAVE_STREAM_MARKER {
-            return unsupported_error("wav: missing riff stream marker");
-        }
-
-        // A Wave file is one large RIFF chunk, with the actual meta and audio data as sub-chunks.
-        // Therefore, the header was the chunk ID, and the next 4 bytes is the length of the RIFF
-        // chunk.
-        let riff_len = source.read_u32()?;
-        let riff_form = source.read_quad_bytes()?;
-
-        // The RIFF chunk contains WAVE data.
-        if riff_form != WAVE_RIFF_FORM {
-            error!("riff form is not wave ({})", String::from_utf8_lossy(&riff_form));
-
-            return unsupported_error("wav: riff form is not wave");
-        }
-
-        let mut riff_chunks = ChunksReader::<RiffWaveChunks>::new(riff_len);
+    fn try_new(mut source: MediaSourceStream, options: &FormatOptions) -> Result<Self> {
+        let (mut riff_chunks, ds64) = read_riff_header(&mut source)?;
 
         let mut codec_params = CodecParameters::new();
         let mut metadata: MetadataLog = Default::default();
@@ -163,6 +182,13 @@ impl FormatReader for WavReader {
                     // The Format chunk contains the block_align field and possible additional information
                     // to handle packetization and seeking.
                     packet_info = format.packet_info()?;
+
+                    if let Some(max_dur) = options.max_packet_duration {
+                        let max_frames =
+                            max_dur.as_micros() as u64 * u64::from(format.sample_rate) / 1_000_000;
+                        packet_info.limit_frames_per_packet(max_frames);
+                    }
+
                     codec_params
                         .with_max_frames_per_packet(packet_info.get_max_frames_per_packet())
                         .with_frames_per_block(packet_info.frames_per_block);
@@ -175,6 +201,14 @@ impl FormatReader for WavReader {
 
                     // Append Fact chunk fields to codec parameters.
                     append_fact_params(&mut codec_params, &fact);
+
+                    // The frame count of an RF64 stream is stored in the ds64 chunk if it does not
+                    // fit in 32 bits.
+                    if let Some(ds64) = &ds64 {
+                        if fact.n_frames == u32::MAX {
+                            codec_params.with_n_frames(ds64.n_frames);
+                        }
+                    }
                 }
                 RiffWaveChunks::List(lst) => {
                     let list = lst.parse(&mut source)?;
@@ -186,12 +220,44 @@ impl FormatReader for WavReader {
                         _ => list.skip(&mut source)?,
                     }
                 }
+                RiffWaveChunks::Smpl(smp) => {
+                    let smpl = smp.parse(&mut source)?;
+
+                    read_smpl_chunk(&smpl, &mut metadata);
+                }
+                RiffWaveChunks::Bext(bxt) => {
+                    let bext = bxt.parse(&mut source)?;
+
+                    read_bext_chunk(&bext, &mut metadata);
+                }
+                RiffWaveChunks::Id3(id3) => {
+                    let id3 = id3.parse(&mut source)?;
+
+                    read_id3_chunk(&id3, &mut metadata);
+                }
                 RiffWaveChunks::Data(dat) => {
                     let data = dat.parse(&mut source)?;
 
                     // Record the bounds of the data chunk.
                     let data_start_pos = source.pos();
-                    let data_end_pos = data_start_pos + u64::from(data.len);
+                    let data_end_pos = data_start_pos + data.len;
+
+                    // Metadata chunks, such as the sampler chunk, are often written after the data
+                    // chunk. If the source is seekable, and the data chunk is not truncated, read
+                    // them and then return to the start of the data chunk.
+                    let is_complete = source.byte_len().map_or(false, |len| data_end_pos < len);
+
+                    if source.is_seekable() && is_complete {
+                        source.seek(SeekFrom::Start(data_end_pos))?;
+
+                        if let Err(err) =
+                            read_trailing_chunks(&mut source, riff_chunks, &mut metadata)
+                        {
+                            warn!("wav: failed to read chunks after the data chunk: {}", err);
+                        }
+
+                        source.seek(SeekFrom::Start(data_start_pos))?;
+                    }
 
                     // Append Data chunk fields to codec parameters.
                     append_data_params(&mut codec_params, &data, &packet_info);
@@ -246,7 +312,14 @@ impl FormatReader for WavReader {
         // packet relative to the start of the data chunk divided by the length per frame.
         let pts = self.packet_info.get_frames(pos - self.data_start_pos);
 
-        Ok(Packet::new_from_boxed_slice(0, pts, dur, packet_buf))
+        let mut packet = Packet::new_from_boxed_slice(0, pts, dur, packet_buf).with_pos(pos);
+
+        // The last block of a block-based codec may be padded. Trim the padding frames.
+        if self.packet_info.is_blocked() {
+            trim_packet(&mut packet, 0, self.tracks[0].codec_params.n_frames);
+        }
+
+        Ok(packet)
     }
 
     fn metadata(&mut self) -> Metadata<'_> {
@@ -303,7 +376,8 @@ impl FormatReader for WavReader {
         let actual_ts = self.packet_info.get_actual_ts(ts);
 
         // Calculate the absolute byte offset of the desired audio frame.
-        let seek_pos = self.data_start_pos + (actual_ts * self.packet_info.block_size);
+        let seek_pos = self.data_start_pos
+            + (actual_ts / self.packet_info.frames_per_block * self.packet_info.block_size);
 
         // If the reader supports seeking we can seek directly to the frame's offset wherever it may
         // be.
@@ -332,7 +406,135 @@ impl FormatReader for WavReader {
     }
 }
 
-fn read_info_chunk(source: &mut MediaSourceStream, len: u32) -> Result<MetadataRevision> {
+/// Reads the header of a RIFF, RF64, or Wave64 stream, and returns a reader for the chunks of the
+/// stream. For RF64 streams, the ds64 chunk is also returned.
+fn read_riff_header(
+    source: &mut MediaSourceStream,
+) -> Result<(ChunksReader<RiffWaveChunks>, Option<Ds64Chunk>)> {
+    let marker = source.read_quad_bytes()?;
+
+    match marker {
+        WAVE_STREAM_MARKER | RF64_STREAM_MARKER | BW64_STREAM_MARKER => {
+            // A Wave file is one large RIFF chunk, with the actual meta and audio data as
+            // sub-chunks. Therefore, the header was the chunk ID, and the next 4 bytes is the
+            // length of the RIFF chunk.
+            let riff_len = source.read_u32()?;
+            let riff_form = source.read_quad_bytes()?;
+
+            // The RIFF chunk contains WAVE data.
+            if riff_form != WAVE_RIFF_FORM {
+                error!("riff form is not wave ({})", String::from_utf8_lossy(&riff_form));
+
+                return unsupported_error("wav: riff form is not wave");
+            }
+
+            if marker == WAVE_STREAM_MARKER {
+                return Ok((ChunksReader::new(u64::from(riff_len)), None));
+            }
+
+            // The ds64 chunk must be the first chunk of an RF64 stream.
+            let tag = source.read_quad_bytes()?;
+            let len = source.read_u32()?;
+
+            if tag != *b"ds64" {
+                return decode_error("wav: missing ds64 chunk");
+            }
+
+            let ds64 = Ds64Chunk::parse(source, tag, u64::from(len))?;
+
+            // The length of the RIFF chunk includes the RIFF form and the ds64 chunk.
+            let len = ds64.riff_len.saturating_sub(12 + u64::from(len));
+            let layout = ChunkLayout::Rf64 { data_len: ds64.data_len };
+
+            Ok((ChunksReader::with_layout(len, layout), Some(ds64)))
+        }
+        _ if marker == WAVE64_STREAM_MARKER[..4] => {
+            let mut guid = [0; 12];
+            source.read_buf_exact(&mut guid)?;
+
+            if guid != WAVE64_STREAM_MARKER[4..] {
+                return unsupported_error("wav: missing riff stream marker");
+            }
+
+            // The length of the Wave64 RIFF chunk includes the 24-byte chunk header.
+            let riff_len = source.read_u64()?;
+
+            let mut riff_form = [0; 16];
+            source.read_buf_exact(&mut riff_form)?;
+
+            if riff_form != WAVE64_RIFF_FORM {
+                return unsupported_error("wav: riff form is not wave");
+            }
+
+            let len = riff_len.saturating_sub(40);
+
+            Ok((ChunksReader::with_layout(len, ChunkLayout::Wave64), None))
+        }
+        _ => unsupported_error("wav: missing riff stream marker"),
+    }
+}
+
+fn read_smpl_chunk(smpl: &SmplChunk, metadata: &mut MetadataLog) {
+    let tags = smpl.loop_tags();
+
+    if !tags.is_empty() {
+        let mut metadata_builder = MetadataBuilder::new();
+
+        for tag in tags {
+            metadata_builder.add_tag(tag);
+        }
+
+        metadata.push(metadata_builder.metadata());
+    }
+}
+
+fn read_bext_chunk(bext: &BextChunk, metadata: &mut MetadataLog) {
+    let mut metadata_builder = MetadataBuilder::new();
+
+    for tag in bext.tags() {
+        metadata_builder.add_tag(tag);
+    }
+
+    metadata.push(metadata_builder.metadata());
+}
+
+fn read_id3_chunk(id3: &Id3Chunk, metadata: &mut MetadataLog) {
+    let mut metadata_builder = MetadataBuilder::new();
+
+    match id3v2::read_id3v2(&mut BufReader::new(&id3.data), &mut metadata_builder) {
+        Ok(_) => metadata.push(metadata_builder.metadata()),
+        Err(err) => warn!("wav: failed to read id3v2 tag: {}", err),
+    }
+}
+
+/// Reads the metadata chunks that follow the data chunk.
+fn read_trailing_chunks(
+    source: &mut MediaSourceStream,
+    mut riff_chunks: ChunksReader<RiffWaveChunks>,
+    metadata: &mut MetadataLog,
+) -> Result<()> {
+    while let Some(chunk) = riff_chunks.next(source)? {
+        match chunk {
+            RiffWaveChunks::List(lst) => {
+                let list = lst.parse(source)?;
+
+                match &list.form {
+                    b"INFO" => metadata.push(read_info_chunk(source, list.len)?),
+                    _ => list.skip(source)?,
+                }
+            }
+            RiffWaveChunks::Smpl(smp) => read_smpl_chunk(&smp.parse(source)?, metadata),
+            RiffWaveChunks::Bext(bxt) => read_bext_chunk(&bxt.parse(source)?, metadata),
+            RiffWaveChunks::Id3(id3) => read_id3_chunk(&id3.parse(source)?, metadata),
+            // A second format, fact, or data chunk is unexpected. Stop reading.
+            _ => break,
+        }
+    }
+
+    Ok(())
+}
+
+fn read_info_chunk(source: &mut MediaSourceStream, len: u64) -> Result<MetadataRevision> {
     let mut info_list = ChunksReader::<RiffInfoListChunks>::new(len);
 
     let mut metadata_builder = MetadataBuilder::new();
@@ -368,7 +570,10 @@ fn append_format_params(codec_params: &mut CodecParameters, format: WaveFormatCh
                 .with_channels(pcm.channels);
         }
         WaveFormatData::Adpcm(adpcm) => {
-            codec_params.for_codec(adpcm.codec).with_channels(adpcm.channels);
+            codec_params
+                .for_codec(adpcm.codec)
+                .with_bits_per_coded_sample(u32::from(adpcm.bits_per_sample))
+                .with_channels(adpcm.channels);
         }
         WaveFormatData::IeeeFloat(ieee) => {
             codec_params.for_codec(ieee.codec).with_channels(ieee.channels);
@@ -399,7 +604,90 @@ fn append_data_params(
     packet_info: &PacketInfo,
 ) {
     if !packet_info.is_empty() {
-        let n_frames = packet_info.get_frames(u64::from(data.len));
-        codec_params.with_n_frames(n_frames);
+        let n_frames = packet_info.get_frames(data.len);
+
+        // For block-based codecs, the Fact chunk records the exact number of frames, excluding the
+        // padding of the last block. Prefer it unless it exceeds the length of the Data chunk.
+        match codec_params.n_frames {
+            Some(fact_n_frames) if packet_info.is_blocked() && fact_n_frames <= n_frames => (),
+            _ => {
+                codec_params.with_n_frames(n_frames);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use symphonia_core::codecs::CODEC_TYPE_ADPCM_IMA_WAV;
+    use symphonia_core::formats::FormatReader;
+    use symphonia_core::io::MediaSourceStream;
+
+    use super::WavReader;
+
+    fn chunk(id: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut buf = id.to_vec();
+        buf.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        buf.extend_from_slice(data);
+        buf
+    }
+
+    /// Make a mono IMA ADPCM file with 256 byte blocks of 505 frames each, and an optional Fact
+    /// chunk.
+    fn make_ima_adpcm_wav(n_blocks: usize, fact_n_frames: Option<u32>) -> Vec<u8> {
+        let mut fmt = vec![0x11, 0, 1, 0];
+        fmt.extend_from_slice(&8000u32.to_le_bytes());
+        fmt.extend_from_slice(&4055u32.to_le_bytes());
+        fmt.extend_from_slice(&[0, 1, 4, 0]);
+        // Extension data size, and frames per block.
+        fmt.extend_from_slice(&[2, 0]);
+        fmt.extend_from_slice(&505u16.to_le_bytes());
+
+        let mut body = b"WAVE".to_vec();
+        body.extend(chunk(b"fmt ", &fmt));
+        if let Some(n_frames) = fact_n_frames {
+            body.extend(chunk(b"fact", &n_frames.to_le_bytes()));
+        }
+        body.extend(chunk(b"data", &vec![0; 256 * n_blocks]));
+
+        let mut wav = b"RIFF".to_vec();
+        wav.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        wav.extend(body);
+        wav
+    }
+
+    fn open(wav: Vec<u8>) -> WavReader {
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(wav)), Default::default());
+        WavReader::try_new(mss, &Default::default()).unwrap()
+    }
+
+    #[test]
+    fn verify_adpcm_padding() {
+        // The last block is padded with 315 frames.
+        let mut reader = open(make_ima_adpcm_wav(3, Some(1200)));
+
+        let params = &reader.tracks()[0].codec_params;
+        assert_eq!(params.codec, CODEC_TYPE_ADPCM_IMA_WAV);
+        assert_eq!(params.n_frames, Some(1200));
+
+        let packet = reader.next_packet().unwrap();
+        assert_eq!((packet.ts, packet.dur, packet.trim_end), (0, 1010, 0));
+
+        let packet = reader.next_packet().unwrap();
+        assert_eq!((packet.ts, packet.dur, packet.trim_end), (1010, 190, 315));
+        assert!(reader.next_packet().is_err());
+
+        // A frame count exceeding the Data chunk, or a missing Fact chunk, is ignored.
+        let reader = open(make_ima_adpcm_wav(3, Some(2000)));
+        assert_eq!(reader.tracks()[0].codec_params.n_frames, Some(1515));
+
+        let mut reader = open(make_ima_adpcm_wav(3, None));
+        assert_eq!(reader.tracks()[0].codec_params.n_frames, Some(1515));
+
+        reader.next_packet().unwrap();
+        let packet = reader.next_packet().unwrap();
+        assert_eq!((packet.ts, packet.dur, packet.trim_end), (1010, 505, 0));
     }
 }
@@ -0,0 +1,417 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! An APEv1 and APEv2 metadata reader.
+
+use std::collections::HashMap;
+
+use symphonia_core::errors::{decode_error, unsupported_error, Result};
+use symphonia_core::io::ReadBytes;
+use symphonia_core::meta::{
+    MetadataBuilder, StandardTagKey, StandardVisualKey, Tag, Value, Visual,
+};
+
+use lazy_static::lazy_static;
+use log::warn;
+
+lazy_static! {
+    static ref APE_TAG_MAP: HashMap<&'static str, StandardTagKey> = {
+        let mut m = HashMap::new();
+        m.insert("acoustid_fingerprint", StandardTagKey::AcoustidFingerprint);
+        m.insert("acoustid_id", StandardTagKey::AcoustidId);
+        m.insert("album artist", StandardTagKey::AlbumArtist);
+        m.insert("album", StandardTagKey::Album);
+        m.insert("albumartist", StandardTagKey::AlbumArtist);
+        m.insert("albumartistsort", StandardTagKey::SortAlbumArtist);
+        m.insert("albumsort", StandardTagKey::SortAlbum);
+        m.insert("arranger", StandardTagKey::Arranger);
+        m.insert("artist", StandardTagKey::Artist);
+        m.insert("artistsort", StandardTagKey::SortArtist);
+        m.insert("asin", StandardTagKey::IdentAsin);
+        m.insert("barcode", StandardTagKey::IdentBarcode);
+        m.insert("bpm", StandardTagKey::Bpm);
+        m.insert("catalognumber", StandardTagKey::IdentCatalogNumber);
+        m.insert("comment", StandardTagKey::Comment);
+        m.insert("compilation", StandardTagKey::Compilation);
+        m.insert("composer", StandardTagKey::Composer);
+        m.insert("composersort", StandardTagKey::SortComposer);
+        m.insert("conductor", StandardTagKey::Conductor);
+        m.insert("copyright", StandardTagKey::Copyright);
+        m.insert("disc", StandardTagKey::DiscNumber);
+        m.insert("discsubtitle", StandardTagKey::DiscSubtitle);
+        m.insert("djmixer", StandardTagKey::MixDj);
+        m.insert("ean/upc", StandardTagKey::IdentEanUpn);
+        m.insert("encodedby", StandardTagKey::EncodedBy);
+        m.insert("encoder", StandardTagKey::Encoder);
+        m.insert("engineer", StandardTagKey::Engineer);
+        m.insert("genre", StandardTagKey::Genre);
+        m.insert("isrc", StandardTagKey::IdentIsrc);
+        m.insert("label", StandardTagKey::Label);
+        m.insert("language", StandardTagKey::Language);
+        m.insert("lyricist", StandardTagKey::Lyricist);
+        m.insert("lyrics", StandardTagKey::Lyrics);
+        m.insert("media", StandardTagKey::MediaFormat);
+        m.insert("mixer", StandardTagKey::MixEngineer);
+        m.insert("mood", StandardTagKey::Mood);
+        m.insert("musicbrainz_albumartistid", StandardTagKey::MusicBrainzAlbumArtistId);
+        m.insert("musicbrainz_albumid", StandardTagKey::MusicBrainzAlbumId);
+        m.insert("musicbrainz_albumstatus", StandardTagKey::MusicBrainzReleaseStatus);
+        m.insert("musicbrainz_albumtype", StandardTagKey::MusicBrainzReleaseType);
+        m.insert("musicbrainz_artistid", StandardTagKey::MusicBrainzArtistId);
+        m.insert("musicbrainz_discid", StandardTagKey::MusicBrainzDiscId);
+        m.insert("musicbrainz_genreid", StandardTagKey::MusicBrainzGenreId);
+        m.insert("musicbrainz_labelid", StandardTagKey::MusicBrainzLabelId);
+        m.insert("musicbrainz_originalalbumid", StandardTagKey::MusicBrainzOriginalAlbumId);
+        m.insert("musicbrainz_originalartistid", StandardTagKey::MusicBrainzOriginalArtistId);
+        m.insert("musicbrainz_recordingid", StandardTagKey::MusicBrainzRecordingId);
+        m.insert("musicbrainz_releasegroupid", StandardTagKey::MusicBrainzReleaseGroupId);
+        m.insert("musicbrainz_releasetrackid", StandardTagKey::MusicBrainzReleaseTrackId);
+        m.insert("musicbrainz_trackid", StandardTagKey::MusicBrainzTrackId);
+        m.insert("musicbrainz_workid", StandardTagKey::MusicBrainzWorkId);
+        m.insert("originaldate", StandardTagKey::OriginalDate);
+        m.insert("performer", StandardTagKey::Performer);
+        m.insert("producer", StandardTagKey::Producer);
+        m.insert("publisher", StandardTagKey::Label);
+        m.insert("releasecountry", StandardTagKey::ReleaseCountry);
+        m.insert("remixer", StandardTagKey::Remixer);
+        m.insert("replaygain_album_gain", StandardTagKey::ReplayGainAlbumGain);
+        m.insert("replaygain_album_peak", StandardTagKey::ReplayGainAlbumPeak);
+        m.insert("replaygain_track_gain", StandardTagKey::ReplayGainTrackGain);
+        m.insert("replaygain_track_peak", StandardTagKey::ReplayGainTrackPeak);
+        m.insert("script", StandardTagKey::Script);
+        m.insert("subtitle", StandardTagKey::TrackSubtitle);
+        m.insert("title", StandardTagKey::TrackTitle);
+        m.insert("titlesort", StandardTagKey::SortTrackTitle);
+        m.insert("track", StandardTagKey::TrackNumber);
+        m.insert("writer", StandardTagKey::Writer);
+        m.insert("year", StandardTagKey::Date);
+        m
+    };
+}
+
+/// The 8-byte preamble of both an APE tag header and footer.
+pub const APE_PREAMBLE: [u8; 8] = *b"APETAGEX";
+
+/// The length of an APE tag header or footer, including the preamble.
+pub const APE_HEADER_LEN: u64 = 32;
+
+/// An APE tag header or footer.
+#[derive(Copy, Clone, Debug)]
+pub struct ApeHeader {
+    /// The version of the tag. Either 1000 (APEv1) or 2000 (APEv2).
+    pub version: u32,
+    /// The length of the tag in bytes including all items and the footer, but excluding the header.
+    pub tag_size: u32,
+    /// The number of items in the tag.
+    pub num_items: u32,
+    /// Global tag flags.
+    pub flags: u32,
+}
+
+impl ApeHeader {
+    /// Returns `true` if the tag has a header.
+    pub fn has_header(&self) -> bool {
+        self.flags & 0x8000_0000 != 0
+    }
+
+    /// Returns `true` if the tag has a footer.
+    pub fn has_footer(&self) -> bool {
+        // Only APEv2 tags may omit the footer.
+        self.version < 2000 || self.flags & 0x4000_0000 == 0
+    }
+
+    /// Returns `true` if this is the header, or `false` if it is the footer.
+    pub fn is_header(&self) -> bool {
+        self.flags & 0x2000_0000 != 0
+    }
+
+    /// Gets the total length of the tag in bytes including the header and footer.
+    pub fn total_len(&self) -> u64 {
+        let header_len = if self.has_header() { APE_HEADER_LEN } else { 0 };
+        u64::from(self.tag_size) + header_len
+    }
+
+    /// Gets the length of the items in bytes.
+    pub fn items_len(&self) -> u64 {
+        let footer_len = if self.has_footer() { APE_HEADER_LEN } else { 0 };
+        u64::from(self.tag_size).saturating_sub(footer_len)
+    }
+}
+
+/// Reads an APE tag header or footer, including the preamble.
+pub fn read_ape_header<B: ReadBytes>(reader: &mut B) -> Result<ApeHeader> {
+    let mut preamble = [0; 8];
+    reader.read_buf_exact(&mut preamble)?;
+
+    if preamble != APE_PREAMBLE {
+        return unsupported_error("ape: not an ape tag");
+    }
+
+    read_ape_header_no_preamble(reader)
+}
+
+/// Reads an APE tag header or footer after the preamble has already been consumed.
+pub fn read_ape_header_no_preamble<B: ReadBytes>(reader: &mut B) -> Result<ApeHeader> {
+    let version = reader.read_u32()?;
+    let tag_size = reader.read_u32()?;
+    let num_items = reader.read_u32()?;
+    let flags = reader.read_u32()?;
+
+    // Reserved, must be 0.
+    reader.ignore_bytes(8)?;
+
+    if version != 1000 && version != 2000 {
+        return unsupported_error("ape: unsupported tag version");
+    }
+
+    // The tag size includes the footer.
+    if u64::from(tag_size) < APE_HEADER_LEN {
+        return decode_error("ape: invalid tag size");
+    }
+
+    Ok(ApeHeader { version, tag_size, num_items, flags })
+}
+
+/// The type of an APE tag item value.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum ItemType {
+    Text,
+    Binary,
+    Locator,
+}
+
+/// Maps the APE cover art item key to a `StandardVisualKey`.
+fn cover_art_key_to_visual_key(key: &str) -> Option<StandardVisualKey> {
+    let key = match key.strip_prefix("cover art ") {
+        Some(key) => key,
+        _ => return None,
+    };
+
+    match key {
+        "(other)" => None,
+        "(png icon)" => Some(StandardVisualKey::FileIcon),
+        "(icon)" => Some(StandardVisualKey::OtherIcon),
+        "(front)" => Some(StandardVisualKey::FrontCover),
+        "(back)" => Some(StandardVisualKey::BackCover),
+        "(leaflet)" => Some(StandardVisualKey::Leaflet),
+        "(media)" => Some(StandardVisualKey::Media),
+        "(lead artist)" => Some(StandardVisualKey::LeadArtistPerformerSoloist),
+        "(artist)" => Some(StandardVisualKey::ArtistPerformer),
+        "(conductor)" => Some(StandardVisualKey::Conductor),
+        "(band)" => Some(StandardVisualKey::BandOrchestra),
+        "(composer)" => Some(StandardVisualKey::Composer),
+        "(lyricist)" => Some(StandardVisualKey::Lyricist),
+        "(recording location)" => Some(StandardVisualKey::RecordingLocation),
+        "(during recording)" => Some(StandardVisualKey::RecordingSession),
+        "(during performance)" => Some(StandardVisualKey::Performance),
+        "(video capture)" => Some(StandardVisualKey::ScreenCapture),
+        "(illustration)" => Some(StandardVisualKey::Illustration),
+        "(band logotype)" => Some(StandardVisualKey::BandArtistLogo),
+        "(publisher logotype)" => Some(StandardVisualKey::PublisherStudioLogo),
+        _ => None,
+    }
+}
+
+/// Guess the media type of an image from its signature.
+fn guess_image_media_type(data: &[u8]) -> &'static str {
+    if data.starts_with(&[0xff, 0xd8, 0xff]) {
+        "image/jpeg"
+    }
+    else if data.starts_with(b"\x89PNG") {
+        "image/png"
+    }
+    else if data.starts_with(b"GIF8") {
+        "image/gif"
+    }
+    else if data.starts_with(b"BM") {
+        "image/bmp"
+    }
+    else {
+        ""
+    }
+}
+
+/// Reads all the items of an APE tag described by `header`. The reader must be positioned at the
+/// first item (i.e., immediately after the header, or `ApeHeader::items_len` bytes before the
+/// footer).
+pub fn read_ape_items<B: ReadBytes>(
+    reader: &mut B,
+    header: &ApeHeader,
+    metadata: &mut MetadataBuilder,
+) -> Result<()> {
+    let mut remaining = header.items_len();
+
+    for _ in 0..header.num_items {
+        // Each item has atleast an 8 byte header, and a 2 byte null-terminated key.
+        if remaining < 10 {
+            return decode_error("ape: item exceeds tag size");
+        }
+
+        let value_len = reader.read_u32()?;
+        let flags = reader.read_u32()?;
+
+        // The key is a null-terminated ASCII string of 2 to 255 characters.
+        let mut key_buf = Vec::with_capacity(16);
+
+        loop {
+            match reader.read_byte()? {
+                0 => break,
+                byte if key_buf.len() < 255 => key_buf.push(byte),
+                _ => return decode_error("ape: item key too long"),
+            }
+        }
+
+        let item_len = 8 + key_buf.len() as u64 + 1 + u64::from(value_len);
+
+        if item_len > remaining {
+            return decode_error("ape: item exceeds tag size");
+        }
+
+        remaining -= item_len;
+
+        let value = reader.read_boxed_slice_exact(value_len as usize)?;
+
+        // APEv1 tags only contain text items.
+        let item_type = if header.version < 2000 {
+            ItemType::Text
+        }
+        else {
+            match (flags >> 1) & 0x3 {
+                0 => ItemType::Text,
+                1 => ItemType::Binary,
+                2 => ItemType::Locator,
+                _ => {
+                    warn!("ape: reserved item type");
+                    continue;
+                }
+            }
+        };
+
+        let key = String::from_utf8_lossy(&key_buf);
+        let lower_key = key.to_ascii_lowercase();
+
+        // Cover art is stored as a binary item containing a null-terminated file name, followed by
+        // the image data.
+        if item_type == ItemType::Binary && lower_key.starts_with("cover art") {
+            let (desc, data) = match value.iter().position(|&b| b == 0) {
+                Some(pos) => (&value[..pos], &value[pos + 1..]),
+                None => (&value[..0], &value[..]),
+            };
+
+            let tags = vec![Tag::new(
+                Some(StandardTagKey::Description),
+                "",
+                Value::from(String::from_utf8_lossy(desc)),
+            )];
+
+            metadata.add_visual(Visual {
+                media_type: guess_image_media_type(data).to_string(),
+                dimensions: None,
+                bits_per_pixel: None,
+                color_mode: None,
+                usage: cover_art_key_to_visual_key(&lower_key),
+                tags,
+                data: Box::from(data),
+            });
+
+            continue;
+        }
+
+        let std_key = APE_TAG_MAP.get(lower_key.as_str()).copied();
+
+        match item_type {
+            ItemType::Text | ItemType::Locator => {
+                // Text items may contain a list of null-separated values. Create one tag per
+                // value.
+                for text in value.split(|&b| b == 0) {
+                    let text = String::from_utf8_lossy(text);
+                    metadata.add_tag(Tag::new(std_key, &key, Value::from(text)));
+                }
+            }
+            ItemType::Binary => {
+                metadata.add_tag(Tag::new(std_key, &key, Value::from(&value[..])));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads an entire APE tag starting from the header. The reader must be positioned at the start
+/// of the header's preamble. Tags without a header must be read with `read_ape_items` after
+/// reading the footer.
+pub fn read_ape_tag<B: ReadBytes>(reader: &mut B, metadata: &mut MetadataBuilder) -> Result<()> {
+    let header = read_ape_header(reader)?;
+
+    if !header.is_header() {
+        return decode_error("ape: expected tag header");
+    }
+
+    read_ape_items(reader, &header, metadata)?;
+
+    // Consume the footer.
+    if header.has_footer() {
+        reader.ignore_bytes(APE_HEADER_LEN)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use symphonia_core::io::BufReader;
+
+    fn make_item(key: &str, value: &[u8], flags: u32) -> Vec<u8> {
+        let mut item = Vec::new();
+        item.extend_from_slice(&(value.len() as u32).to_le_bytes());
+        item.extend_from_slice(&flags.to_le_bytes());
+        item.extend_from_slice(key.as_bytes());
+        item.push(0);
+        item.extend_from_slice(value);
+        item
+    }
+
+    fn make_header(num_items: u32, items_len: usize, flags: u32) -> Vec<u8> {
+        let mut buf = APE_PREAMBLE.to_vec();
+        buf.extend_from_slice(&2000u32.to_le_bytes());
+        buf.extend_from_slice(&(items_len as u32 + 32).to_le_bytes());
+        buf.extend_from_slice(&num_items.to_le_bytes());
+        buf.extend_from_slice(&flags.to_le_bytes());
+        buf.extend_from_slice(&[0; 8]);
+        buf
+    }
+
+    #[test]
+    fn verify_read_ape_tag() {
+        let mut items = Vec::new();
+        items.extend(make_item("Title", b"Song", 0));
+        items.extend(make_item("Artist", b"A\0B", 0));
+        items.extend(make_item("MUSICBRAINZ_ALBUMID", b"abc", 0));
+        items.extend(make_item("Cover Art (Front)", b"front.jpg\0\xff\xd8\xff\xe0", 2));
+
+        let mut buf = make_header(4, items.len(), 0xa000_0000);
+        buf.extend(&items);
+        buf.extend(make_header(4, items.len(), 0x8000_0000));
+
+        let mut builder = MetadataBuilder::new();
+        read_ape_tag(&mut BufReader::new(&buf), &mut builder).unwrap();
+
+        let rev = builder.metadata();
+        let tags = rev.tags();
+
+        assert_eq!(tags.len(), 4);
+        assert_eq!(tags[0].std_key, Some(StandardTagKey::TrackTitle));
+        assert_eq!(tags[1].value.to_string(), "A");
+        assert_eq!(tags[2].value.to_string(), "B");
+        assert_eq!(tags[3].std_key, Some(StandardTagKey::MusicBrainzAlbumId));
+
+        assert_eq!(rev.visuals().len(), 1);
+        assert_eq!(rev.visuals()[0].usage, Some(StandardVisualKey::FrontCover));
+        assert_eq!(rev.visuals()[0].media_type, "image/jpeg");
+    }
+}
@@ -0,0 +1,163 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! An Advanced Systems Format (ASF) content description metadata reader.
+
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use symphonia_core::errors::{decode_error, Result};
+use symphonia_core::io::{BufReader, FiniteStream, ReadBytes};
+use symphonia_core::meta::{StandardTagKey, Tag, Value, Visual};
+
+use crate::id3v2::util::apic_picture_type_to_visual_key;
+
+lazy_static! {
+    static ref ASF_TAG_MAP: HashMap<&'static str, StandardTagKey> = {
+        let mut m = HashMap::new();
+        // Content description object fields.
+        m.insert("author", StandardTagKey::Artist);
+        m.insert("copyright", StandardTagKey::Copyright);
+        m.insert("description", StandardTagKey::Comment);
+        m.insert("rating", StandardTagKey::Rating);
+        m.insert("title", StandardTagKey::TrackTitle);
+        // Extended content description object descriptors.
+        m.insert("musicbrainz/album artist id", StandardTagKey::MusicBrainzAlbumArtistId);
+        m.insert("musicbrainz/album id", StandardTagKey::MusicBrainzAlbumId);
+        m.insert("musicbrainz/artist id", StandardTagKey::MusicBrainzArtistId);
+        m.insert("musicbrainz/release group id", StandardTagKey::MusicBrainzReleaseGroupId);
+        m.insert("musicbrainz/track id", StandardTagKey::MusicBrainzRecordingId);
+        m.insert("musicbrainz/work id", StandardTagKey::MusicBrainzWorkId);
+        m.insert("replaygain_album_gain", StandardTagKey::ReplayGainAlbumGain);
+        m.insert("replaygain_album_peak", StandardTagKey::ReplayGainAlbumPeak);
+        m.insert("replaygain_track_gain", StandardTagKey::ReplayGainTrackGain);
+        m.insert("replaygain_track_peak", StandardTagKey::ReplayGainTrackPeak);
+        m.insert("wm/albumartist", StandardTagKey::AlbumArtist);
+        m.insert("wm/albumartistsortorder", StandardTagKey::SortAlbumArtist);
+        m.insert("wm/albumsortorder", StandardTagKey::SortAlbum);
+        m.insert("wm/albumtitle", StandardTagKey::Album);
+        m.insert("wm/artistsortorder", StandardTagKey::SortArtist);
+        m.insert("wm/beatsperminute", StandardTagKey::Bpm);
+        m.insert("wm/composer", StandardTagKey::Composer);
+        m.insert("wm/composersortorder", StandardTagKey::SortComposer);
+        m.insert("wm/conductor", StandardTagKey::Conductor);
+        m.insert("wm/contentgroupdescription", StandardTagKey::ContentGroup);
+        m.insert("wm/encodedby", StandardTagKey::EncodedBy);
+        m.insert("wm/encodingsettings", StandardTagKey::EncoderSettings);
+        m.insert("wm/genre", StandardTagKey::Genre);
+        m.insert("wm/isrc", StandardTagKey::IdentIsrc);
+        m.insert("wm/language", StandardTagKey::Language);
+        m.insert("wm/lyrics", StandardTagKey::Lyrics);
+        m.insert("wm/mood", StandardTagKey::Mood);
+        m.insert("wm/originalalbumtitle", StandardTagKey::OriginalAlbum);
+        m.insert("wm/originalartist", StandardTagKey::OriginalArtist);
+        m.insert("wm/originalreleaseyear", StandardTagKey::OriginalDate);
+        m.insert("wm/partofset", StandardTagKey::DiscNumber);
+        m.insert("wm/producer", StandardTagKey::Producer);
+        m.insert("wm/publisher", StandardTagKey::Label);
+        m.insert("wm/subtitle", StandardTagKey::TrackSubtitle);
+        m.insert("wm/titlesortorder", StandardTagKey::SortTrackTitle);
+        m.insert("wm/toolname", StandardTagKey::Encoder);
+        m.insert("wm/tracknumber", StandardTagKey::TrackNumber);
+        m.insert("wm/writer", StandardTagKey::Lyricist);
+        m.insert("wm/year", StandardTagKey::Date);
+        m
+    };
+}
+
+/// Decode a UTF-16LE string. The string ends at the first null character, if any.
+pub fn decode_utf16_string(buf: &[u8]) -> String {
+    let chars: Vec<u16> = buf
+        .chunks_exact(2)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .take_while(|&c| c != 0)
+        .collect();
+
+    String::from_utf16_lossy(&chars)
+}
+
+/// Parse a content descriptor, or a field of the content description object, into a `Tag`.
+pub fn parse(key: &str, value: Value) -> Tag {
+    // Attempt to assign a standardized tag key.
+    let std_tag = ASF_TAG_MAP.get(key.to_lowercase().as_str()).copied();
+
+    Tag::new(std_tag, key, value)
+}
+
+/// Read a null-terminated UTF-16LE string.
+fn read_null_terminated_utf16(reader: &mut BufReader<'_>) -> Result<String> {
+    let mut chars = Vec::new();
+
+    loop {
+        match reader.read_u16()? {
+            0 => break,
+            c => chars.push(c),
+        }
+    }
+
+    Ok(String::from_utf16_lossy(&chars))
+}
+
+/// Parse the value of a `WM/Picture` content descriptor into a `Visual`.
+pub fn parse_picture(buf: &[u8]) -> Result<Visual> {
+    let mut reader = BufReader::new(buf);
+
+    let usage = apic_picture_type_to_visual_key(u32::from(reader.read_u8()?));
+
+    let data_len = reader.read_u32()? as usize;
+
+    let media_type = read_null_terminated_utf16(&mut reader)?;
+    let desc = read_null_terminated_utf16(&mut reader)?;
+
+    if data_len > reader.bytes_available() as usize {
+        return decode_error("asf: invalid picture data length");
+    }
+
+    let data = Box::from(reader.read_buf_bytes_ref(data_len)?);
+
+    let tags = vec![Tag::new(Some(StandardTagKey::Description), "", Value::from(desc))];
+
+    Ok(Visual {
+        media_type,
+        dimensions: None,
+        bits_per_pixel: None,
+        color_mode: None,
+        usage,
+        tags,
+        data,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::meta::{StandardTagKey, StandardVisualKey, Value};
+
+    use super::{decode_utf16_string, parse, parse_picture};
+
+    fn utf16(s: &str) -> Vec<u8> {
+        s.encode_utf16().chain(Some(0)).flat_map(|c| c.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn verify_asf_tags() {
+        assert_eq!(decode_utf16_string(&utf16("Album")), "Album");
+
+        let tag = parse("WM/AlbumTitle", Value::from("Album"));
+        assert_eq!(tag.std_key, Some(StandardTagKey::Album));
+
+        let mut buf = vec![0x03];
+        buf.extend_from_slice(&3u32.to_le_bytes());
+        buf.extend_from_slice(&utf16("image/png"));
+        buf.extend_from_slice(&utf16("Cover"));
+        buf.extend_from_slice(&[1, 2, 3]);
+
+        let visual = parse_picture(&buf).unwrap();
+        assert_eq!(visual.media_type, "image/png");
+        assert_eq!(visual.usage, Some(StandardVisualKey::FrontCover));
+        assert_eq!(visual.tags[0].value.to_string(), "Cover");
+        assert_eq!(&visual.data[..], &[1, 2, 3]);
+    }
+}
@@ -0,0 +1,40 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! A Core Audio Format (CAF) information chunk metadata reader.
+
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use symphonia_core::meta::{StandardTagKey, Tag, Value};
+
+lazy_static! {
+    static ref CAF_INFO_MAP: HashMap<&'static str, StandardTagKey> = {
+        let mut m = HashMap::new();
+        m.insert("album", StandardTagKey::Album);
+        m.insert("artist", StandardTagKey::Artist);
+        m.insert("comments", StandardTagKey::Comment);
+        m.insert("composer", StandardTagKey::Composer);
+        m.insert("copyright", StandardTagKey::Copyright);
+        m.insert("encoding application", StandardTagKey::Encoder);
+        m.insert("genre", StandardTagKey::Genre);
+        m.insert("lyricist", StandardTagKey::Lyricist);
+        m.insert("recorded date", StandardTagKey::Date);
+        m.insert("tempo", StandardTagKey::Bpm);
+        m.insert("title", StandardTagKey::TrackTitle);
+        m.insert("track number", StandardTagKey::TrackNumber);
+        m.insert("year", StandardTagKey::Date);
+        m
+    };
+}
+
+/// Parse a key-value pair of a CAF information chunk into a `Tag`.
+pub fn parse(key: &str, value: &str) -> Tag {
+    // Attempt to assign a standardized tag key.
+    let std_tag = CAF_INFO_MAP.get(key.to_lowercase().as_str()).copied();
+
+    Tag::new(std_tag, key, Value::from(value))
+}
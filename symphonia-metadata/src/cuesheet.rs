@@ -0,0 +1,294 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! An external cuesheet (.cue) file parser.
+
+use std::borrow::Cow;
+
+use symphonia_core::errors::{decode_error, Result};
+use symphonia_core::formats::{Cuesheet, CuesheetIndex, CuesheetTrack, CUESHEET_FRAMES_PER_SEC};
+use symphonia_core::io::ReadBytes;
+use symphonia_core::meta::{StandardTagKey, Tag, Value};
+
+use log::{info, warn};
+
+use crate::text::decode_windows_1252;
+
+/// The maximum size of a cuesheet file that will be read.
+const MAX_CUESHEET_LEN: u64 = 1024 * 1024;
+
+/// Splits a cuesheet line into tokens. Tokens are separated by whitespace, or may be enclosed in
+/// double quotes to include whitespace.
+fn tokenize(line: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut rest = line.trim_start();
+
+    while !rest.is_empty() {
+        let (token, remainder) = if let Some(quoted) = rest.strip_prefix('"') {
+            // A quoted token ends at the next quote, or the end of the line if unterminated.
+            match quoted.find('"') {
+                Some(end) => (&quoted[..end], &quoted[end + 1..]),
+                None => (quoted, ""),
+            }
+        }
+        else {
+            match rest.find(char::is_whitespace) {
+                Some(end) => rest.split_at(end),
+                None => (rest, ""),
+            }
+        };
+
+        tokens.push(token);
+        rest = remainder.trim_start();
+    }
+
+    tokens
+}
+
+/// Parses a cuesheet timestamp in the form of MM:SS:FF into a number of CD-DA frames.
+fn parse_msf(msf: &str) -> Option<u64> {
+    let mut parts = msf.split(':');
+
+    let m = parts.next()?.parse::<u64>().ok()?;
+    let s = parts.next()?.parse::<u64>().ok()?;
+    let f = parts.next()?.parse::<u64>().ok()?;
+
+    if parts.next().is_some() || s >= 60 || f >= CUESHEET_FRAMES_PER_SEC {
+        return None;
+    }
+
+    Some(((m * 60) + s) * CUESHEET_FRAMES_PER_SEC + f)
+}
+
+/// Gets the standard tag key for a cuesheet command or remark, if one exists.
+fn std_key_from_command(command: &str, is_track: bool) -> Option<StandardTagKey> {
+    match command {
+        "TITLE" if is_track => Some(StandardTagKey::TrackTitle),
+        "TITLE" => Some(StandardTagKey::Album),
+        "PERFORMER" if is_track => Some(StandardTagKey::Artist),
+        "PERFORMER" => Some(StandardTagKey::AlbumArtist),
+        "SONGWRITER" => Some(StandardTagKey::Composer),
+        "COMMENT" => Some(StandardTagKey::Comment),
+        "COMPOSER" => Some(StandardTagKey::Composer),
+        "DATE" => Some(StandardTagKey::Date),
+        "DISCNUMBER" => Some(StandardTagKey::DiscNumber),
+        "TOTALDISCS" => Some(StandardTagKey::DiscTotal),
+        "GENRE" => Some(StandardTagKey::Genre),
+        "REPLAYGAIN_ALBUM_GAIN" => Some(StandardTagKey::ReplayGainAlbumGain),
+        "REPLAYGAIN_ALBUM_PEAK" => Some(StandardTagKey::ReplayGainAlbumPeak),
+        "REPLAYGAIN_TRACK_GAIN" => Some(StandardTagKey::ReplayGainTrackGain),
+        "REPLAYGAIN_TRACK_PEAK" => Some(StandardTagKey::ReplayGainTrackPeak),
+        _ => None,
+    }
+}
+
+/// Parses the text of a cuesheet into a `Cuesheet`.
+pub fn parse_cuesheet(text: &str) -> Result<Cuesheet> {
+    let mut cuesheet = Cuesheet::default();
+
+    let mut file: Option<String> = None;
+    let mut track: Option<CuesheetTrack> = None;
+
+    // Skip the byte-order mark, if present.
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+
+    for line in text.lines() {
+        let tokens = tokenize(line);
+
+        let (command, args) = match tokens.split_first() {
+            Some((command, args)) => (command.to_ascii_uppercase(), args),
+            None => continue,
+        };
+
+        match command.as_str() {
+            "FILE" => {
+                if args.is_empty() {
+                    return decode_error("cue: file command is missing a file name");
+                }
+                file = Some(args[0].to_string());
+            }
+            "TRACK" => {
+                let number = match args.first().and_then(|n| n.parse::<u32>().ok()) {
+                    Some(number) => number,
+                    None => return decode_error("cue: invalid track number"),
+                };
+
+                if let Some(track) = track.take() {
+                    cuesheet.tracks.push(track);
+                }
+
+                let is_audio = args.get(1).map_or(true, |t| t.eq_ignore_ascii_case("AUDIO"));
+
+                track = Some(CuesheetTrack {
+                    number,
+                    file: file.clone(),
+                    is_audio,
+                    ..Default::default()
+                });
+            }
+            "INDEX" => {
+                let track = match track.as_mut() {
+                    Some(track) => track,
+                    None => return decode_error("cue: index command outside of a track"),
+                };
+
+                let number = args.first().and_then(|n| n.parse::<u32>().ok());
+                let start = args.get(1).and_then(|msf| parse_msf(msf));
+
+                match (number, start) {
+                    (Some(number), Some(start)) => {
+                        track.indices.push(CuesheetIndex { number, start })
+                    }
+                    _ => return decode_error("cue: invalid index"),
+                }
+            }
+            "PREGAP" | "POSTGAP" => {
+                let track = match track.as_mut() {
+                    Some(track) => track,
+                    None => return decode_error("cue: gap command outside of a track"),
+                };
+
+                let gap = match args.first().and_then(|msf| parse_msf(msf)) {
+                    Some(gap) => gap,
+                    None => return decode_error("cue: invalid gap length"),
+                };
+
+                if command == "PREGAP" {
+                    track.pregap = Some(gap);
+                }
+                else {
+                    track.postgap = Some(gap);
+                }
+            }
+            "ISRC" => match (track.as_mut(), args.first()) {
+                (Some(track), Some(isrc)) => track.isrc = Some(isrc.to_string()),
+                _ => warn!("cue: ignoring isrc command outside of a track"),
+            },
+            "CATALOG" => cuesheet.catalog = args.first().map(|catalog| catalog.to_string()),
+            "FLAGS" => {
+                if let Some(track) = track.as_mut() {
+                    track.pre_emphasis = args.iter().any(|flag| flag.eq_ignore_ascii_case("PRE"));
+                }
+            }
+            "TITLE" | "PERFORMER" | "SONGWRITER" | "REM" => {
+                // Remarks are a key followed by a value. Otherwise, the command is the key.
+                let (key, value) = if command == "REM" {
+                    match args.split_first() {
+                        Some((key, value)) => (key.to_ascii_uppercase(), value),
+                        None => continue,
+                    }
+                }
+                else {
+                    (command.clone(), args)
+                };
+
+                if value.is_empty() {
+                    continue;
+                }
+
+                let std_key = std_key_from_command(&key, track.is_some());
+                let tag = Tag::new(std_key, &key, Value::from(value.join(" ")));
+
+                match track.as_mut() {
+                    Some(track) => track.tags.push(tag),
+                    None => cuesheet.tags.push(tag),
+                }
+            }
+            "CDTEXTFILE" => (),
+            _ => info!("cue: ignoring unknown command {}", command),
+        }
+    }
+
+    if let Some(track) = track.take() {
+        cuesheet.tracks.push(track);
+    }
+
+    if cuesheet.tracks.is_empty() {
+        return decode_error("cue: cuesheet has no tracks");
+    }
+
+    Ok(cuesheet)
+}
+
+/// Reads a cuesheet file into a `Cuesheet`.
+///
+/// Cuesheets are commonly encoded as either UTF-8 or Windows-1252. If the cuesheet is not valid
+/// UTF-8, it is decoded as Windows-1252.
+pub fn read_cuesheet<B: ReadBytes>(reader: &mut B) -> Result<Cuesheet> {
+    let mut buf = Vec::new();
+
+    loop {
+        match reader.read_u8() {
+            Ok(byte) => buf.push(byte),
+            Err(ref err) if err.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(err) => return Err(err.into()),
+        }
+
+        if buf.len() as u64 > MAX_CUESHEET_LEN {
+            return decode_error("cue: cuesheet is too large");
+        }
+    }
+
+    let text = match std::str::from_utf8(&buf) {
+        Ok(text) => Cow::Borrowed(text),
+        Err(_) => decode_windows_1252(&buf),
+    };
+
+    parse_cuesheet(&text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verify_parse_cuesheet() {
+        let text = "\u{feff}REM GENRE Rock\r\n\
+                    REM DATE 1999\r\n\
+                    CATALOG 0123456789012\r\n\
+                    PERFORMER \"Some Band\"\r\n\
+                    TITLE \"Some Album\"\r\n\
+                    FILE \"Some Album.flac\" WAVE\r\n\
+                    \x20 TRACK 01 AUDIO\r\n\
+                    \x20   TITLE \"First Song\"\r\n\
+                    \x20   ISRC USABC9900001\r\n\
+                    \x20   INDEX 01 00:00:00\r\n\
+                    \x20 TRACK 02 AUDIO\r\n\
+                    \x20   TITLE \"Second Song\"\r\n\
+                    \x20   FLAGS DCP PRE\r\n\
+                    \x20   PREGAP 00:01:00\r\n\
+                    \x20   INDEX 00 03:58:50\r\n\
+                    \x20   INDEX 01 04:00:00\r\n";
+
+        let cuesheet = parse_cuesheet(text).unwrap();
+
+        assert_eq!(cuesheet.catalog.as_deref(), Some("0123456789012"));
+        assert_eq!(cuesheet.tags.len(), 4);
+        assert_eq!(cuesheet.tags[0].std_key, Some(StandardTagKey::Genre));
+        assert_eq!(cuesheet.tags[3].std_key, Some(StandardTagKey::Album));
+        assert_eq!(cuesheet.tags[3].value.to_string(), "Some Album");
+        assert_eq!(cuesheet.tracks.len(), 2);
+
+        let track = &cuesheet.tracks[0];
+        assert_eq!(track.number, 1);
+        assert_eq!(track.file.as_deref(), Some("Some Album.flac"));
+        assert_eq!(track.isrc.as_deref(), Some("USABC9900001"));
+        assert_eq!(track.tags[0].std_key, Some(StandardTagKey::TrackTitle));
+        assert_eq!(track.start(), Some(0));
+
+        let track = &cuesheet.tracks[1];
+        assert!(track.pre_emphasis);
+        assert_eq!(track.pregap, Some(75));
+        assert_eq!(track.indices.len(), 2);
+        assert_eq!(track.start(), Some(240 * 75));
+
+        let cues = cuesheet.to_cues(44100);
+        assert_eq!(cues[1].index, 2);
+        assert_eq!(cues[1].start_ts, (238 * 75 + 50) * 588);
+        assert_eq!(cues[1].points[1].start_offset_ts, 100 * 588);
+    }
+}
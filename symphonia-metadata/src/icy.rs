@@ -0,0 +1,153 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! A SHOUTcast/Icecast (ICY) metadata reader.
+
+use symphonia_core::meta::{MetadataBuilder, MetadataRevision, StandardTagKey, Tag, Value};
+
+use crate::text::decode_latin1;
+
+/// Decodes ICY text. ICY metadata has no defined encoding, but is either UTF-8 or, commonly,
+/// ISO-8859-1.
+fn decode_text(buf: &[u8]) -> String {
+    match std::str::from_utf8(buf) {
+        Ok(text) => text.to_string(),
+        Err(_) => decode_latin1(buf).into_owned(),
+    }
+}
+
+/// The response headers describing the station. There is no standard tag key for the station
+/// name, therefore `icy-name` is read as an unmapped tag.
+const STATION_HEADERS: [&str; 4] = ["icy-name", "icy-genre", "icy-description", "icy-url"];
+
+fn map_key(key: &str) -> Option<StandardTagKey> {
+    match key.to_ascii_lowercase().as_str() {
+        "streamtitle" => Some(StandardTagKey::TrackTitle),
+        "streamurl" => Some(StandardTagKey::Url),
+        "icy-genre" => Some(StandardTagKey::Genre),
+        "icy-description" => Some(StandardTagKey::Description),
+        "icy-url" => Some(StandardTagKey::UrlInternetRadio),
+        _ => None,
+    }
+}
+
+/// Parses the fields of an interleaved metadata block, such as
+/// `StreamTitle='Artist - Title';StreamUrl='';`, into a list of key-value pairs.
+///
+/// Values are terminated by `';`, and may therefore contain both `'` and `;`. Trailing null
+/// padding is ignored.
+pub fn parse_icy_fields(block: &[u8]) -> Vec<(String, String)> {
+    // Remove the null padding.
+    let len = block.iter().rposition(|&b| b != 0).map_or(0, |pos| pos + 1);
+    let text = decode_text(&block[..len]);
+
+    let mut fields = Vec::new();
+    let mut rest = text.as_str();
+
+    while let Some(eq) = rest.find('=') {
+        let key = rest[..eq].trim();
+        let value_start = &rest[eq + 1..];
+
+        let (value, next) = if let Some(quoted) = value_start.strip_prefix('\'') {
+            match quoted.find("';") {
+                Some(end) => (&quoted[..end], &quoted[end + 2..]),
+                None => (quoted.trim_end_matches('\''), ""),
+            }
+        }
+        else {
+            match value_start.find(';') {
+                Some(end) => (&value_start[..end], &value_start[end + 1..]),
+                None => (value_start, ""),
+            }
+        };
+
+        if !key.is_empty() {
+            fields.push((key.to_string(), value.to_string()));
+        }
+
+        rest = next;
+    }
+
+    fields
+}
+
+/// Reads an interleaved metadata block into a new `MetadataRevision`. Returns `None` if the block
+/// contains no fields.
+pub fn read_icy_metadata(block: &[u8]) -> Option<MetadataRevision> {
+    let fields = parse_icy_fields(block);
+
+    if fields.is_empty() {
+        return None;
+    }
+
+    let mut builder = MetadataBuilder::new();
+
+    for (key, value) in fields {
+        builder.add_tag(Tag::new(map_key(&key), &key, Value::from(value)));
+    }
+
+    Some(builder.metadata())
+}
+
+/// Reads the `icy-*` response headers of a stream, such as the station name and genre, into a new
+/// `MetadataRevision`. Returns `None` if there are no such headers.
+pub fn read_icy_headers<'a, I>(headers: I) -> Option<MetadataRevision>
+where
+    I: IntoIterator<Item = (&'a str, &'a str)>,
+{
+    let mut builder = MetadataBuilder::new();
+    let mut is_empty = true;
+
+    for (key, value) in headers {
+        let is_station_header = STATION_HEADERS.iter().any(|name| key.eq_ignore_ascii_case(name));
+
+        if is_station_header && !value.is_empty() {
+            builder.add_tag(Tag::new(map_key(key), key, Value::from(value)));
+            is_empty = false;
+        }
+    }
+
+    if is_empty {
+        None
+    }
+    else {
+        Some(builder.metadata())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::meta::StandardTagKey;
+
+    use super::{parse_icy_fields, read_icy_metadata};
+
+    #[test]
+    fn verify_parse_icy_fields() {
+        let mut block = b"StreamTitle='Guns N' Roses - Paradise; City';StreamUrl='';".to_vec();
+        block.resize(64, 0);
+
+        assert_eq!(
+            parse_icy_fields(&block),
+            vec![
+                ("StreamTitle".to_string(), "Guns N' Roses - Paradise; City".to_string()),
+                ("StreamUrl".to_string(), "".to_string()),
+            ]
+        );
+
+        // Unterminated values, and ISO-8859-1 text.
+        assert_eq!(
+            parse_icy_fields(b"StreamTitle='Caf\xe9'"),
+            vec![("StreamTitle".to_string(), "Caf\u{e9}".to_string())]
+        );
+
+        assert!(read_icy_metadata(&[0; 16]).is_none());
+
+        let rev = read_icy_metadata(b"StreamTitle='Song';").unwrap();
+        assert_eq!(rev.tags()[0].std_key, Some(StandardTagKey::TrackTitle));
+        assert_eq!(rev.tags()[0].value.to_string(), "Song");
+    }
+}
@@ -13,12 +13,12 @@ use symphonia_core::errors::{decode_error, unsupported_error, Result};
 use symphonia_core::io::{BufReader, FiniteStream, ReadBytes};
 use symphonia_core::meta::{StandardTagKey, Tag, Value, Visual};
 
-use encoding_rs::UTF_16BE;
 use lazy_static::lazy_static;
 use log::warn;
 
 use super::unsync::{decode_unsynchronisation, read_syncsafe_leq32};
 use super::util;
+use crate::text::decode_utf16;
 
 // The following is a list of all standardized ID3v2.x frames for all ID3v2 major versions and their
 // implementation status ("S" column) in Symphonia.
@@ -112,7 +112,7 @@ use super::util;
 //   x                  TSST                        Set subtitle
 //   x   TYE    TYER    n/a      Date               Year
 //   x   TXX    TXXX                                User defined text information frame
-//       UFI    UFID                                Unique file identifier
+//   x   UFI    UFID                                Unique file identifier
 //              USER                                Terms of use
 //   x   ULT    USLT             Lyrics             Unsychronized lyric/text transcription
 //   x   WCM    WCOM             UrlPurchase        Commercial information
@@ -138,6 +138,9 @@ use super::util;
 //   x   TSA                     SortAlbum          (Apple iTunes) Album sort order
 //   x   TS2    TSO2             SortAlbumArtist    (Apple iTunes) Album artist sort order
 //   x   TSC    TSOC             SortComposer       (Apple iTunes) Composer sort order
+//   x          XSOA             SortAlbum          (Draft ID3v2.4) Album sort order
+//   x          XSOP             SortArtist         (Draft ID3v2.4) Performer sort order
+//   x          XSOT             SortTrackTitle     (Draft ID3v2.4) Title sort order
 //
 // Information on these frames can be found at:
 //
@@ -335,7 +338,7 @@ lazy_static! {
             m.insert(b"TSST", (read_text_frame, None));
             m.insert(b"TXXX", (read_txxx_frame, None));
             m.insert(b"TYER", (read_text_frame, Some(StandardTagKey::Date)));
-            // m.insert(b"UFID", read_null_frame);
+            m.insert(b"UFID", (read_ufid_frame, None));
             // m.insert(b"USER", read_null_frame);
             m.insert(b"USLT", (read_comm_uslt_frame, Some(StandardTagKey::Lyrics)));
             m.insert(b"WCOM", (read_url_frame, Some(StandardTagKey::UrlPurchase)));
@@ -359,6 +362,10 @@ lazy_static! {
             m.insert(b"TSO2", (read_text_frame, Some(StandardTagKey::SortAlbumArtist)));
             m.insert(b"TSOC", (read_text_frame, Some(StandardTagKey::SortComposer)));
             m.insert(b"WFED", (read_text_frame, Some(StandardTagKey::UrlPodcast)));
+            // Draft ID3v2.4 sort order frames written into ID3v2.3 tags by some taggers.
+            m.insert(b"XSOA", (read_text_frame, Some(StandardTagKey::SortAlbum)));
+            m.insert(b"XSOP", (read_text_frame, Some(StandardTagKey::SortArtist)));
+            m.insert(b"XSOT", (read_text_frame, Some(StandardTagKey::SortTrackTitle)));
             m
         };
 }
@@ -368,13 +375,26 @@ lazy_static! {
         let mut m = HashMap::new();
         m.insert("ACOUSTID FINGERPRINT", StandardTagKey::AcoustidFingerprint);
         m.insert("ACOUSTID ID", StandardTagKey::AcoustidId);
+        m.insert("ALBUMARTISTSORT", StandardTagKey::SortAlbumArtist);
         m.insert("BARCODE", StandardTagKey::IdentBarcode);
         m.insert("CATALOGNUMBER", StandardTagKey::IdentCatalogNumber);
+        m.insert("COMPOSERSORT", StandardTagKey::SortComposer);
         m.insert("LICENSE", StandardTagKey::License);
         m.insert("MUSICBRAINZ ALBUM ARTIST ID", StandardTagKey::MusicBrainzAlbumArtistId);
         m.insert("MUSICBRAINZ ALBUM ID", StandardTagKey::MusicBrainzAlbumId);
+        m.insert("MUSICBRAINZ ALBUM RELEASE COUNTRY", StandardTagKey::ReleaseCountry);
+        m.insert("MUSICBRAINZ ALBUM STATUS", StandardTagKey::MusicBrainzReleaseStatus);
+        m.insert("MUSICBRAINZ ALBUM TYPE", StandardTagKey::MusicBrainzReleaseType);
         m.insert("MUSICBRAINZ ARTIST ID", StandardTagKey::MusicBrainzArtistId);
+        m.insert("MUSICBRAINZ DISC ID", StandardTagKey::MusicBrainzDiscId);
+        m.insert("MUSICBRAINZ GENRE ID", StandardTagKey::MusicBrainzGenreId);
+        m.insert("MUSICBRAINZ LABEL ID", StandardTagKey::MusicBrainzLabelId);
+        m.insert("MUSICBRAINZ ORIGINAL ALBUM ID", StandardTagKey::MusicBrainzOriginalAlbumId);
+        m.insert("MUSICBRAINZ ORIGINAL ARTIST ID", StandardTagKey::MusicBrainzOriginalArtistId);
+        m.insert("MUSICBRAINZ RECORDING ID", StandardTagKey::MusicBrainzRecordingId);
         m.insert("MUSICBRAINZ RELEASE GROUP ID", StandardTagKey::MusicBrainzReleaseGroupId);
+        m.insert("MUSICBRAINZ RELEASE TRACK ID", StandardTagKey::MusicBrainzReleaseTrackId);
+        m.insert("MUSICBRAINZ TRACK ID", StandardTagKey::MusicBrainzTrackId);
         m.insert("MUSICBRAINZ WORK ID", StandardTagKey::MusicBrainzWorkId);
         m.insert("REPLAYGAIN_ALBUM_GAIN", StandardTagKey::ReplayGainAlbumGain);
         m.insert("REPLAYGAIN_ALBUM_PEAK", StandardTagKey::ReplayGainAlbumPeak);
@@ -682,8 +702,9 @@ fn read_txxx_frame(
     let desc = scan_text(reader, encoding, reader.bytes_available() as usize)?;
 
     // Some TXXX frames may be mapped to standard keys. Check if a standard key exists for the
-    // description.
-    let std_key = TXXX_FRAME_STD_KEYS.get(desc.as_ref()).copied();
+    // description. Taggers are inconsistent with the case of the description, therefore the
+    // comparison is case-insensitive.
+    let std_key = TXXX_FRAME_STD_KEYS.get(desc.to_ascii_uppercase().as_str()).copied();
 
     // Generate a key name using the description.
     let key = format!("TXXX:{}", desc);
@@ -744,6 +765,37 @@ fn read_wxxx_frame(
     Ok(FrameResult::Tag(tag))
 }
 
+/// Reads a `UFID` (unique file identifier) frame.
+fn read_ufid_frame(
+    reader: &mut BufReader<'_>,
+    _: Option<StandardTagKey>,
+    _: &str,
+) -> Result<FrameResult> {
+    // Scan for a ISO-8859-1 owner identifier.
+    let owner =
+        scan_text(reader, Encoding::Iso8859_1, reader.bytes_available() as usize)?.into_owned();
+
+    // The remainder of the frame is the identifier. It may be up-to 64 bytes long.
+    let ident = reader.read_buf_bytes_available_ref();
+
+    if ident.len() > 64 {
+        return decode_error("id3v2: UFID identifier exceeds 64 bytes");
+    }
+
+    let key = format!("UFID:{}", owner);
+
+    // MusicBrainz stores the recording identifier as an ASCII string in a UFID frame.
+    let tag = if owner == "http://musicbrainz.org" {
+        let value = Value::from(String::from_utf8_lossy(ident));
+        Tag::new(Some(StandardTagKey::MusicBrainzRecordingId), &key, value)
+    }
+    else {
+        Tag::new(None, &key, Value::from(ident))
+    };
+
+    Ok(FrameResult::Tag(tag))
+}
+
 /// Reads a `PRIV` (private) frame.
 fn read_priv_frame(
     reader: &mut BufReader<'_>,
@@ -996,9 +1048,9 @@ fn decode_text(encoding: Encoding, data: &[u8]) -> Cow<'_, str> {
                 }
                 end -= 2;
             }
-            // Decode UTF-16 to UTF-8. If a byte-order-mark is present, UTF_16BE.decode() will use
+            // Decode UTF-16 to UTF-8. If a byte-order-mark is present, decode_utf16() will use
             // the indicated endianness. Otherwise, big endian is assumed.
-            UTF_16BE.decode(&data[..end]).0
+            decode_utf16(&data[..end])
         }
     }
 }
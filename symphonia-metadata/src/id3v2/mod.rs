@@ -344,27 +344,28 @@ pub fn read_id3v2<B: ReadBytes>(reader: &mut B, metadata: &mut MetadataBuilder)
 
     // If the unsynchronisation flag is set in the header, all tag data must be passed through the
     // unsynchronisation decoder before being read for verions < 4 of ID3v2.
-    let mut scoped = if header.unsynchronisation && header.major_version < 4 {
+    let (scoped, result) = if header.unsynchronisation && header.major_version < 4 {
         let mut unsync = UnsyncStream::new(ScopedStream::new(reader, u64::from(header.size)));
 
-        read_id3v2_body(&mut unsync, &header, metadata)?;
+        let result = read_id3v2_body(&mut unsync, &header, metadata);
 
-        unsync.into_inner()
+        (unsync.into_inner(), result)
     }
     // Otherwise, read the data as-is. Individual frames may be unsynchronised for major versions
     // >= 4.
     else {
         let mut scoped = ScopedStream::new(reader, u64::from(header.size));
 
-        read_id3v2_body(&mut scoped, &header, metadata)?;
+        let result = read_id3v2_body(&mut scoped, &header, metadata);
 
-        scoped
+        (scoped, result)
     };
 
-    // Ignore any remaining data in the tag.
-    scoped.ignore()?;
+    // Ignore any remaining data in the tag such that the reader is positioned at the end of the
+    // tag, even if the body could not be read.
+    scoped.finish()?;
 
-    Ok(())
+    result
 }
 
 pub mod util {
@@ -28,38 +28,52 @@ pub fn read_syncsafe_leq32<B: ReadBytes>(reader: &mut B, bit_width: u8) -> Resul
     Ok(result)
 }
 
-pub fn decode_unsynchronisation(buf: &mut [u8]) -> &mut [u8] {
+/// Decodes the unsynchronisation scheme in-place, and returns the length of the decoded data.
+/// `last` is the byte preceding `buf` in the unsynchronised stream, or 0 if there is none.
+fn decode_unsync_in_place(buf: &mut [u8], last: u8) -> usize {
     let len = buf.len();
-    let mut src = 0;
+
+    // If the preceding byte was 0xff, and the first byte is 0x00, skip the first byte.
+    let mut src = usize::from(last == 0xff && buf.first() == Some(&0x00));
     let mut dst = 0;
 
-    // Decode the unsynchronisation scheme in-place.
-    while src < len - 1 {
-        buf[dst] = buf[src];
-        dst += 1;
-        src += 1;
+    while src < len {
+        // Copy everything up-to and including the next 0xff byte in one run.
+        let (run, is_ff) = match buf[src..].iter().position(|&b| b == 0xff) {
+            Some(pos) => (pos + 1, true),
+            None => (len - src, false),
+        };
+
+        buf.copy_within(src..src + run, dst);
+        src += run;
+        dst += run;
 
-        if buf[src - 1] == 0xff && buf[src] == 0x00 {
+        // A 0x00 byte following a 0xff byte is dropped.
+        if is_ff && src < len && buf[src] == 0x00 {
             src += 1;
         }
     }
 
-    if src < len {
-        buf[dst] = buf[src];
-        dst += 1;
-    }
+    dst
+}
 
-    &mut buf[..dst]
+pub fn decode_unsynchronisation(buf: &mut [u8]) -> &mut [u8] {
+    let len = decode_unsync_in_place(buf, 0);
+    &mut buf[..len]
 }
 
+/// `UnsyncStream` transparently decodes the unsynchronisation scheme of an inner stream.
 pub struct UnsyncStream<B: ReadBytes + FiniteStream> {
     inner: B,
+    /// The last byte read from the inner stream, including dropped bytes.
     byte: u8,
+    /// The number of decoded bytes read.
+    pos: u64,
 }
 
 impl<B: ReadBytes + FiniteStream> UnsyncStream<B> {
     pub fn new(inner: B) -> Self {
-        UnsyncStream { inner, byte: 0 }
+        UnsyncStream { inner, byte: 0, pos: 0 }
     }
 
     /// Convert the `UnsyncStream` to the inner stream.
@@ -97,6 +111,8 @@ impl<B: ReadBytes + FiniteStream> ReadBytes for UnsyncStream<B> {
             self.byte = self.inner.read_byte()?;
         }
 
+        self.pos += 1;
+
         Ok(self.byte)
     }
 
@@ -112,82 +128,102 @@ impl<B: ReadBytes + FiniteStream> ReadBytes for UnsyncStream<B> {
         Ok([self.read_byte()?, self.read_byte()?, self.read_byte()?, self.read_byte()?])
     }
 
-    fn read_buf(&mut self, _: &mut [u8]) -> io::Result<usize> {
-        // Not required.
-        unimplemented!();
-    }
-
-    fn read_buf_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
-        let len = buf.len();
+    fn read_buf(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
 
-        if len > 0 {
-            // Fill the provided buffer directly from the underlying reader.
-            self.inner.read_buf_exact(buf)?;
+        loop {
+            let len = self.inner.read_buf(buf)?;
 
-            // If the last seen byte was 0xff, and the first byte in buf is 0x00, skip the first
-            // byte of buf.
-            let mut src = usize::from(self.byte == 0xff && buf[0] == 0x00);
-            let mut dst = 0;
+            if len == 0 {
+                return Ok(0);
+            }
 
-            // Record the last byte in buf to continue unsychronisation streaming later.
+            let last = self.byte;
             self.byte = buf[len - 1];
 
-            // Decode the unsynchronisation scheme in-place.
-            while src < len - 1 {
-                buf[dst] = buf[src];
-                dst += 1;
-                src += 1;
+            // A read of a single dropped byte decodes to nothing. Read again rather than signal the
+            // end of the stream.
+            let decoded = decode_unsync_in_place(&mut buf[..len], last);
 
-                if buf[src - 1] == 0xff && buf[src] == 0x00 {
-                    src += 1;
-                }
+            if decoded > 0 {
+                self.pos += decoded as u64;
+                return Ok(decoded);
             }
+        }
+    }
 
-            // When the final two src bytes are [ 0xff, 0x00 ], src will always equal len.
-            // Therefore, if src < len, then the final byte should always be copied to dst.
-            if src < len {
-                buf[dst] = buf[src];
-                dst += 1;
-            }
+    fn read_buf_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        let mut dst = 0;
 
-            // If dst < len, then buf is not full. Read the remaining bytes manually to completely
-            // fill buf.
-            while dst < len {
-                buf[dst] = self.read_byte()?;
-                dst += 1;
-            }
+        // Fill the provided buffer directly from the underlying reader, and decode it in-place.
+        // Decoding may drop bytes, so read again for the remainder until the buffer is full. Since
+        // no more bytes are read than are required, the underlying reader is never over-read.
+        while dst < buf.len() {
+            let chunk = &mut buf[dst..];
+
+            self.inner.read_buf_exact(chunk)?;
+
+            // Record the last byte in the chunk to continue unsychronisation streaming later.
+            let last = self.byte;
+            self.byte = chunk[chunk.len() - 1];
+
+            dst += decode_unsync_in_place(chunk, last);
         }
 
+        self.pos += buf.len() as u64;
+
         Ok(())
     }
 
     fn scan_bytes_aligned<'a>(
         &mut self,
-        _: &[u8],
-        _: usize,
-        _: &'a mut [u8],
+        pattern: &[u8],
+        align: usize,
+        buf: &'a mut [u8],
     ) -> io::Result<&'a mut [u8]> {
-        // Not required.
-        unimplemented!();
+        // The pattern must be matched against the decoded bytes, therefore scan byte-by-byte.
+        let mut len = 0;
+
+        while len < buf.len() {
+            buf[len] = self.read_byte()?;
+            len += 1;
+
+            if len >= pattern.len()
+                && (len - pattern.len()) % align == 0
+                && buf[len - pattern.len()..len] == *pattern
+            {
+                break;
+            }
+        }
+
+        Ok(&mut buf[..len])
     }
 
-    fn ignore_bytes(&mut self, count: u64) -> io::Result<()> {
-        for _ in 0..count {
-            self.inner.read_byte()?;
+    fn ignore_bytes(&mut self, mut count: u64) -> io::Result<()> {
+        // Decoded bytes are ignored, therefore the data must still be decoded, but it may be done
+        // in bulk.
+        let mut scratch = [0; 4096];
+
+        while count > 0 {
+            let len = count.min(scratch.len() as u64) as usize;
+            self.read_buf_exact(&mut scratch[..len])?;
+            count -= len as u64;
         }
+
         Ok(())
     }
 
     fn pos(&self) -> u64 {
-        // Not required.
-        unimplemented!();
+        self.pos
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::read_syncsafe_leq32;
-    use symphonia_core::io::BufReader;
+    use super::{decode_unsynchronisation, read_syncsafe_leq32, UnsyncStream};
+    use symphonia_core::io::{BufReader, ReadBytes, ScopedStream};
 
     #[test]
     fn verify_read_syncsafe_leq32() {
@@ -207,4 +243,182 @@ mod tests {
         let mut stream = BufReader::new(&[3, 4, 80, 1]);
         assert_eq!(0, read_syncsafe_leq32(&mut stream, 0).unwrap());
     }
+
+    #[test]
+    fn verify_unsync_stream() {
+        let raw = [0x01, 0xff, 0x00, 0x00, 0xff, 0xff, 0x00, 0xe0, 0xff, 0x00, 0xff, 0x00, 0x02];
+        let decoded = [0x01, 0xff, 0x00, 0xff, 0xff, 0xe0, 0xff, 0xff, 0x02];
+
+        assert_eq!(decode_unsynchronisation(&mut raw.clone()), &decoded);
+
+        // Split the reads such that unsynchronisation pairs straddle the reads.
+        for split in 0..decoded.len() {
+            let scoped = ScopedStream::new(BufReader::new(&raw), raw.len() as u64);
+            let mut stream = UnsyncStream::new(scoped);
+
+            let mut buf = vec![0; decoded.len()];
+            let (first, second) = buf.split_at_mut(split);
+            stream.read_buf_exact(first).unwrap();
+            stream.read_buf_exact(second).unwrap();
+            assert_eq!(buf, decoded);
+        }
+
+        for (split, &byte) in decoded.iter().enumerate() {
+            let scoped = ScopedStream::new(BufReader::new(&raw), raw.len() as u64);
+            let mut stream = UnsyncStream::new(scoped);
+
+            stream.ignore_bytes(split as u64).unwrap();
+            assert_eq!(stream.read_byte().unwrap(), byte);
+        }
+    }
+
+    /// Apply the unsynchronisation scheme as an encoder following the specification would. A 0x00
+    /// byte is inserted after every 0xff byte that is followed by 0x00, a byte >= 0xe0, or the end
+    /// of the data.
+    fn encode_unsynchronisation(data: &[u8]) -> Vec<u8> {
+        let mut encoded = Vec::new();
+
+        for (i, &byte) in data.iter().enumerate() {
+            encoded.push(byte);
+
+            if byte == 0xff {
+                match data.get(i + 1) {
+                    Some(&next) if next != 0x00 && next < 0xe0 => (),
+                    _ => encoded.push(0x00),
+                }
+            }
+        }
+
+        encoded
+    }
+
+    /// A xorshift pseudo-random number generator.
+    struct Rng(u32);
+
+    impl Rng {
+        fn next(&mut self) -> u32 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 17;
+            self.0 ^= self.0 << 5;
+            self.0
+        }
+
+        /// Generates a byte biased towards the bytes significant to unsynchronisation.
+        fn next_byte(&mut self) -> u8 {
+            match self.next() % 4 {
+                0 => 0xff,
+                1 => 0x00,
+                2 => 0xe0 | (self.next() as u8 & 0x1f),
+                _ => self.next() as u8,
+            }
+        }
+    }
+
+    fn unsync_stream(raw: &[u8]) -> UnsyncStream<ScopedStream<BufReader<'_>>> {
+        UnsyncStream::new(ScopedStream::new(BufReader::new(raw), raw.len() as u64))
+    }
+
+    #[test]
+    fn verify_unsync_edge_cases() {
+        // Empty and single byte buffers.
+        assert_eq!(decode_unsynchronisation(&mut []), &[]);
+        assert_eq!(decode_unsynchronisation(&mut [0xff]), &[0xff]);
+        assert_eq!(decode_unsynchronisation(&mut [0x00]), &[0x00]);
+
+        // Trailing 0xff, with and without the trailing 0x00 byte.
+        assert_eq!(decode_unsynchronisation(&mut [0x01, 0xff, 0x00]), &[0x01, 0xff]);
+        assert_eq!(decode_unsynchronisation(&mut [0x01, 0xff]), &[0x01, 0xff]);
+
+        // A 0x00 byte following a dropped 0x00 byte is not dropped.
+        assert_eq!(decode_unsynchronisation(&mut [0xff, 0x00, 0x00]), &[0xff, 0x00]);
+
+        // An empty stream.
+        let mut stream = unsync_stream(&[]);
+        assert!(stream.read_buf_exact(&mut []).is_ok());
+        assert_eq!(stream.read_buf(&mut [0; 4]).unwrap(), 0);
+        assert!(stream.read_byte().is_err());
+        assert_eq!(stream.pos(), 0);
+
+        // A stream that decodes to a single byte.
+        let mut stream = unsync_stream(&[0xff, 0x00]);
+        let mut buf = [0; 4];
+        assert_eq!(stream.read_buf(&mut buf).unwrap(), 1);
+        assert_eq!(buf[0], 0xff);
+        assert_eq!(stream.read_buf(&mut buf).unwrap(), 0);
+        assert_eq!(stream.pos(), 1);
+
+        // A single-byte read of a dropped byte continues to the next byte.
+        let mut stream = unsync_stream(&[0xff, 0x00, 0xe0]);
+        let mut buf = [0; 1];
+        assert_eq!(stream.read_buf(&mut buf).unwrap(), 1);
+        assert_eq!(stream.read_buf(&mut buf).unwrap(), 1);
+        assert_eq!(buf[0], 0xe0);
+    }
+
+    #[test]
+    fn verify_unsync_scan_bytes() {
+        // The pattern is matched against the decoded bytes.
+        let raw = [0x01, 0xff, 0x00, 0xe0, 0x02, 0xff, 0x00, 0x00, 0x03];
+
+        let mut stream = unsync_stream(&raw);
+        let mut buf = [0; 8];
+        assert_eq!(stream.scan_bytes(&[0xff, 0xe0], &mut buf).unwrap(), &[0x01, 0xff, 0xe0]);
+        assert_eq!(stream.scan_bytes(&[0xff, 0x00], &mut buf).unwrap(), &[0x02, 0xff, 0x00]);
+        assert_eq!(stream.pos(), 6);
+
+        // The pattern is only matched on an aligned boundary.
+        let mut stream = unsync_stream(&[0x01, 0x02, 0x03, 0x02, 0x03]);
+        assert_eq!(
+            stream.scan_bytes_aligned(&[0x02, 0x03], 3, &mut buf).unwrap(),
+            &[0x01, 0x02, 0x03, 0x02, 0x03]
+        );
+    }
+
+    #[test]
+    fn verify_unsync_roundtrip() {
+        let mut rng = Rng(0x1234_5678);
+
+        for _ in 0..1000 {
+            let len = (rng.next() % 48) as usize;
+            let data: Vec<u8> = (0..len).map(|_| rng.next_byte()).collect();
+
+            let raw = encode_unsynchronisation(&data);
+
+            assert_eq!(decode_unsynchronisation(&mut raw.clone()), &data[..]);
+
+            // Decode the stream using a random mix of reads, carrying the unsynchronisation state
+            // across each read.
+            let mut stream = unsync_stream(&raw);
+            let mut decoded = Vec::new();
+
+            while decoded.len() < data.len() {
+                let remaining = data.len() - decoded.len();
+                let count = 1 + (rng.next() as usize % remaining);
+
+                match rng.next() % 4 {
+                    0 => decoded.push(stream.read_byte().unwrap()),
+                    1 => {
+                        let mut buf = vec![0; count];
+                        stream.read_buf_exact(&mut buf).unwrap();
+                        decoded.extend_from_slice(&buf);
+                    }
+                    2 => {
+                        let mut buf = vec![0; count];
+                        let read = stream.read_buf(&mut buf).unwrap();
+                        assert!(read > 0);
+                        decoded.extend_from_slice(&buf[..read]);
+                    }
+                    _ => {
+                        stream.ignore_bytes(count as u64).unwrap();
+                        decoded.extend_from_slice(&data[decoded.len()..decoded.len() + count]);
+                    }
+                }
+
+                assert_eq!(stream.pos(), decoded.len() as u64);
+            }
+
+            assert_eq!(decoded, data);
+            assert!(stream.read_byte().is_err());
+        }
+    }
 }
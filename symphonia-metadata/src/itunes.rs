@@ -47,6 +47,21 @@ lazy_static! {
         );
         m.insert("com.apple.iTunes:MusicBrainz Album Type", StandardTagKey::MusicBrainzReleaseType);
         m.insert("com.apple.iTunes:MusicBrainz Artist Id", StandardTagKey::MusicBrainzArtistId);
+        m.insert("com.apple.iTunes:MusicBrainz Disc Id", StandardTagKey::MusicBrainzDiscId);
+        m.insert("com.apple.iTunes:MusicBrainz Genre Id", StandardTagKey::MusicBrainzGenreId);
+        m.insert("com.apple.iTunes:MusicBrainz Label Id", StandardTagKey::MusicBrainzLabelId);
+        m.insert(
+            "com.apple.iTunes:MusicBrainz Original Album Id",
+            StandardTagKey::MusicBrainzOriginalAlbumId,
+        );
+        m.insert(
+            "com.apple.iTunes:MusicBrainz Original Artist Id",
+            StandardTagKey::MusicBrainzOriginalArtistId,
+        );
+        m.insert(
+            "com.apple.iTunes:MusicBrainz Recording Id",
+            StandardTagKey::MusicBrainzRecordingId,
+        );
         m.insert(
             "com.apple.iTunes:MusicBrainz Release Group Id",
             StandardTagKey::MusicBrainzReleaseGroupId,
@@ -14,8 +14,16 @@
 #![allow(clippy::identity_op)]
 #![allow(clippy::manual_range_contains)]
 
+pub mod ape;
+pub mod asf;
+pub mod caf;
+pub mod cuesheet;
+pub mod icy;
 pub mod id3v1;
 pub mod id3v2;
 pub mod itunes;
+pub mod lrc;
 pub mod riff;
+pub mod text;
+pub mod trailing;
 pub mod vorbis;
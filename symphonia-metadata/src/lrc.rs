@@ -0,0 +1,236 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! An LRC (.lrc) synchronized lyrics file parser.
+
+use std::borrow::Cow;
+
+use symphonia_core::errors::{decode_error, Result};
+use symphonia_core::formats::Cue;
+use symphonia_core::io::ReadBytes;
+use symphonia_core::meta::{StandardTagKey, Tag, Value};
+
+use log::info;
+
+use crate::text::decode_windows_1252;
+
+/// The maximum size of a LRC file that will be read.
+const MAX_LRC_LEN: u64 = 1024 * 1024;
+
+/// A single line of synchronized lyrics.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LyricLine {
+    /// The time, in milliseconds from the start of the track, at which the line begins.
+    pub start_ms: u64,
+    /// The text of the line.
+    pub text: String,
+}
+
+/// The contents of an LRC file.
+#[derive(Clone, Debug, Default)]
+pub struct Lyrics {
+    /// The ID tags of the file (e.g., title, artist).
+    pub tags: Vec<Tag>,
+    /// The lines of lyrics in ascending order of time.
+    pub lines: Vec<LyricLine>,
+}
+
+impl Lyrics {
+    /// Converts the `Lyrics` into a list of `Cue`s given the sample rate of the media.
+    ///
+    /// Each line becomes a `Cue`, numbered from 1, with a single `Lyrics` tag containing the text
+    /// of the line.
+    pub fn to_cues(&self, sample_rate: u32) -> Vec<Cue> {
+        self.lines
+            .iter()
+            .enumerate()
+            .map(|(i, line)| Cue {
+                index: i as u32 + 1,
+                start_ts: line.start_ms * u64::from(sample_rate) / 1000,
+                tags: vec![Tag::new(
+                    Some(StandardTagKey::Lyrics),
+                    "LYRICS",
+                    Value::from(line.text.as_str()),
+                )],
+                points: Vec::new(),
+            })
+            .collect()
+    }
+}
+
+/// Parses a LRC timestamp in the form of MM:SS, MM:SS.FF, or MM:SS.FFF into milliseconds.
+fn parse_timestamp(ts: &str) -> Option<u64> {
+    let (m, rest) = ts.split_at(ts.find(':')?);
+    let rest = &rest[1..];
+
+    // Some files separate the fraction with a colon instead of a period.
+    let (s, frac) = match rest.find(|c| c == '.' || c == ':') {
+        Some(pos) => (&rest[..pos], &rest[pos + 1..]),
+        None => (rest, ""),
+    };
+
+    let m = m.trim().parse::<u64>().ok()?;
+    let s = s.parse::<u64>().ok()?;
+
+    if s >= 60 || frac.len() > 3 || !frac.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    // Scale the fraction to milliseconds.
+    let ms = match frac.len() {
+        0 => 0,
+        len => frac.parse::<u64>().ok()? * 10u64.pow(3 - len as u32),
+    };
+
+    Some((m * 60 + s) * 1000 + ms)
+}
+
+/// Gets the standard tag key for a LRC ID tag, if one exists.
+fn std_key_from_id(id: &str) -> Option<StandardTagKey> {
+    match id {
+        "ar" => Some(StandardTagKey::Artist),
+        "al" => Some(StandardTagKey::Album),
+        "ti" => Some(StandardTagKey::TrackTitle),
+        "au" => Some(StandardTagKey::Lyricist),
+        "by" => Some(StandardTagKey::EncodedBy),
+        "re" | "tool" => Some(StandardTagKey::Encoder),
+        _ => None,
+    }
+}
+
+/// Parses the text of a LRC file into `Lyrics`.
+pub fn parse_lrc(text: &str) -> Result<Lyrics> {
+    let mut lyrics = Lyrics::default();
+
+    // The offset, in milliseconds, to subtract from all timestamps.
+    let mut offset = 0i64;
+
+    // Skip the byte-order mark, if present.
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+
+    for line in text.lines() {
+        let mut rest = line.trim();
+        let mut times = Vec::new();
+
+        // A line starts with one or more bracketed timestamps or, for ID tags, a single bracketed
+        // key-value pair.
+        while let Some(field) = rest.strip_prefix('[') {
+            let end = match field.find(']') {
+                Some(end) => end,
+                None => break,
+            };
+
+            let content = &field[..end];
+            rest = &field[end + 1..];
+
+            if let Some(ms) = parse_timestamp(content) {
+                times.push(ms);
+            }
+            else if let Some(colon) = content.find(':') {
+                let id = content[..colon].trim().to_ascii_lowercase();
+                let value = content[colon + 1..].trim();
+
+                if id == "offset" {
+                    offset = value.trim_start_matches('+').parse().unwrap_or(0);
+                }
+                else if id == "length" || id == "ve" || id == "#" {
+                    // The track length, and player version, are not tags of the media.
+                }
+                else if !value.is_empty() {
+                    lyrics.tags.push(Tag::new(std_key_from_id(&id), &id, Value::from(value)));
+                }
+            }
+            else {
+                info!("lrc: ignoring unknown field {}", content);
+            }
+        }
+
+        let text = rest.trim();
+
+        for &time in &times {
+            lyrics.lines.push(LyricLine { start_ms: time, text: text.to_string() });
+        }
+    }
+
+    if lyrics.lines.is_empty() {
+        return decode_error("lrc: file has no lyrics");
+    }
+
+    // A positive offset causes lyrics to be shown sooner.
+    for line in lyrics.lines.iter_mut() {
+        line.start_ms = (line.start_ms as i64 - offset).max(0) as u64;
+    }
+
+    // Lines with multiple timestamps are repeated, therefore they must be sorted. The sort is
+    // stable such that lines at the same time remain in file order.
+    lyrics.lines.sort_by_key(|line| line.start_ms);
+
+    Ok(lyrics)
+}
+
+/// Reads a LRC file into `Lyrics`.
+///
+/// LRC files are commonly encoded as either UTF-8 or Windows-1252. If the file is not valid UTF-8,
+/// it is decoded as Windows-1252.
+pub fn read_lrc<B: ReadBytes>(reader: &mut B) -> Result<Lyrics> {
+    let mut buf = Vec::new();
+
+    loop {
+        match reader.read_u8() {
+            Ok(byte) => buf.push(byte),
+            Err(ref err) if err.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(err) => return Err(err.into()),
+        }
+
+        if buf.len() as u64 > MAX_LRC_LEN {
+            return decode_error("lrc: file is too large");
+        }
+    }
+
+    let text = match std::str::from_utf8(&buf) {
+        Ok(text) => Cow::Borrowed(text),
+        Err(_) => decode_windows_1252(&buf),
+    };
+
+    parse_lrc(&text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verify_parse_lrc() {
+        let text = "[ti:Some Song]\r\n\
+                    [ar: Some Band ]\r\n\
+                    [length: 03:20]\r\n\
+                    [offset:+500]\r\n\
+                    \r\n\
+                    [00:12.00]First line\r\n\
+                    [00:20.5][01:02.250]Chorus\r\n\
+                    [00:30:10]Second line\r\n\
+                    [00:00.10]Intro\r\n";
+
+        let lyrics = parse_lrc(text).unwrap();
+
+        assert_eq!(lyrics.tags.len(), 2);
+        assert_eq!(lyrics.tags[0].std_key, Some(StandardTagKey::TrackTitle));
+        assert_eq!(lyrics.tags[1].value.to_string(), "Some Band");
+
+        let times: Vec<u64> = lyrics.lines.iter().map(|line| line.start_ms).collect();
+        assert_eq!(times, vec![0, 11_500, 20_000, 29_600, 61_750]);
+        assert_eq!(lyrics.lines[2].text, "Chorus");
+        assert_eq!(lyrics.lines[4].text, "Chorus");
+
+        let cues = lyrics.to_cues(48000);
+        assert_eq!(cues[1].index, 2);
+        assert_eq!(cues[1].start_ts, 11_500 * 48);
+        assert_eq!(cues[1].tags[0].value.to_string(), "First line");
+
+        assert!(parse_lrc("[ti:No Lyrics]").is_err());
+    }
+}
@@ -0,0 +1,85 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Text decoding shared by the tag readers.
+//!
+//! The decoding of UTF-16 and legacy codepages is provided by `encoding_rs` if the `encoding`
+//! feature is enabled (the default). Otherwise, UTF-16 is decoded with the standard library, and
+//! legacy codepages are decoded as ISO-8859-1 (Latin-1), which differs only in the rarely used
+//! 0x80 to 0x9F range. The API is the same in both cases.
+
+use std::borrow::Cow;
+
+/// Decodes ISO-8859-1 (Latin-1) text into a UTF-8 `str`. No copy is made if the text is ASCII.
+pub fn decode_latin1(data: &[u8]) -> Cow<'_, str> {
+    match std::str::from_utf8(data) {
+        Ok(text) if data.is_ascii() => Cow::Borrowed(text),
+        _ => Cow::Owned(data.iter().map(|&b| char::from(b)).collect()),
+    }
+}
+
+/// Decodes Windows-1252 text into a UTF-8 `str`.
+#[cfg(feature = "encoding")]
+pub fn decode_windows_1252(data: &[u8]) -> Cow<'_, str> {
+    encoding_rs::WINDOWS_1252.decode_without_bom_handling(data).0
+}
+
+/// Decodes Windows-1252 text into a UTF-8 `str`.
+#[cfg(not(feature = "encoding"))]
+pub fn decode_windows_1252(data: &[u8]) -> Cow<'_, str> {
+    decode_latin1(data)
+}
+
+/// Decodes UTF-16 text into a UTF-8 `str`. If a byte-order-mark is present, the indicated
+/// endianness is used and the byte-order-mark is removed. Otherwise, big-endian is assumed. Any
+/// invalid characters are replaced with the U+FFFD REPLACEMENT CHARACTER.
+#[cfg(feature = "encoding")]
+pub fn decode_utf16(data: &[u8]) -> Cow<'_, str> {
+    encoding_rs::UTF_16BE.decode(data).0
+}
+
+/// Decodes UTF-16 text into a UTF-8 `str`. If a byte-order-mark is present, the indicated
+/// endianness is used and the byte-order-mark is removed. Otherwise, big-endian is assumed. Any
+/// invalid characters are replaced with the U+FFFD REPLACEMENT CHARACTER.
+#[cfg(not(feature = "encoding"))]
+pub fn decode_utf16(data: &[u8]) -> Cow<'_, str> {
+    let (data, is_le) = match data {
+        [0xff, 0xfe, rest @ ..] => (rest, true),
+        [0xfe, 0xff, rest @ ..] => (rest, false),
+        _ => (data, false),
+    };
+
+    let units = data.chunks(2).map(|pair| match *pair {
+        [a, b] if is_le => u16::from_le_bytes([a, b]),
+        [a, b] => u16::from_be_bytes([a, b]),
+        // A trailing odd byte is invalid.
+        _ => 0xdc00,
+    });
+
+    Cow::Owned(
+        std::char::decode_utf16(units)
+            .map(|c| c.unwrap_or(std::char::REPLACEMENT_CHARACTER))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_latin1, decode_utf16, decode_windows_1252};
+
+    #[test]
+    fn verify_decode_text() {
+        assert_eq!(decode_latin1(b"Caf\xe9"), "Caf\u{e9}");
+        assert_eq!(decode_windows_1252(b"na\xefve"), "na\u{ef}ve");
+
+        // Big-endian, with and without a byte-order-mark, and little-endian.
+        assert_eq!(decode_utf16(&[0x00, 0x41, 0x00, 0xe9]), "A\u{e9}");
+        assert_eq!(decode_utf16(&[0xfe, 0xff, 0x00, 0x41]), "A");
+        assert_eq!(decode_utf16(&[0xff, 0xfe, 0x41, 0x00, 0x3d, 0xd8, 0x00, 0xde]), "A\u{1f600}");
+        assert_eq!(decode_utf16(&[0x00, 0x41, 0x00]), "A\u{fffd}");
+    }
+}
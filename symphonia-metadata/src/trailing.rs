@@ -0,0 +1,170 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! A reader for the ID3v1 and APEv2 tags appended to the end of a stream.
+
+use std::io::{Seek, SeekFrom};
+
+use symphonia_core::errors::Result;
+use symphonia_core::io::{MediaSource, MediaSourceStream, ReadBytes, SeekBuffered};
+use symphonia_core::meta::{MetadataBuilder, MetadataLog};
+
+use log::warn;
+
+use crate::{ape, id3v1};
+
+/// The length of an ID3v1 tag.
+const ID3V1_TAG_LEN: u64 = 128;
+
+/// Reads the APEv2 and ID3v1 tags at the end of the stream, and pushes them to `metadata` as a
+/// single revision.
+///
+/// Returns the position of the first tag, or the end of the stream if there are no tags. If the
+/// length of the stream is unknown, `None` is returned and no tags are read. The position of the
+/// stream afterwards is unspecified.
+pub fn read_trailing_tags(
+    reader: &mut MediaSourceStream,
+    metadata: &mut MetadataLog,
+) -> Result<Option<u64>> {
+    let mut end = match reader.byte_len() {
+        Some(len) => len,
+        None => return Ok(None),
+    };
+
+    let mut builder = MetadataBuilder::new();
+
+    // An ID3v1 tag is always the last tag.
+    if end >= ID3V1_TAG_LEN {
+        reader.seek(SeekFrom::Start(end - ID3V1_TAG_LEN))?;
+
+        if reader.read_triple_bytes()? == *b"TAG" {
+            reader.seek_buffered_rel(-3);
+            id3v1::read_id3v1(reader, &mut builder)?;
+            end -= ID3V1_TAG_LEN;
+        }
+    }
+
+    // An APEv2 tag, with a footer, precedes the ID3v1 tag.
+    if end >= ape::APE_HEADER_LEN {
+        let footer_pos = end - ape::APE_HEADER_LEN;
+
+        reader.seek(SeekFrom::Start(footer_pos))?;
+
+        if let Ok(footer) = ape::read_ape_header(reader) {
+            // The items immediately precede the footer. If the footer describes a tag that does
+            // not fit before it, the tag is invalid and skipped.
+            let items_pos = footer_pos.checked_sub(footer.items_len());
+
+            match items_pos {
+                Some(items_pos) if !footer.is_header() && footer.total_len() <= end => {
+                    reader.seek(SeekFrom::Start(items_pos))?;
+                    ape::read_ape_items(reader, &footer, &mut builder)?;
+                    end -= footer.total_len();
+                }
+                _ => warn!("ape: skipping invalid trailing tag"),
+            }
+        }
+    }
+
+    let revision = builder.metadata();
+
+    if !revision.tags().is_empty() {
+        metadata.push(revision);
+    }
+
+    Ok(Some(end))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use symphonia_core::io::MediaSourceStream;
+    use symphonia_core::meta::{MetadataLog, StandardTagKey};
+
+    use super::read_trailing_tags;
+
+    fn make_id3v1(title: &str) -> Vec<u8> {
+        let mut buf = b"TAG".to_vec();
+        buf.extend_from_slice(title.as_bytes());
+        buf.resize(127, 0);
+        // Genre: Other.
+        buf.push(12);
+        buf
+    }
+
+    fn make_ape_footer(tag_size: u32, num_items: u32, flags: u32) -> Vec<u8> {
+        let mut buf = b"APETAGEX".to_vec();
+        buf.extend_from_slice(&2000u32.to_le_bytes());
+        buf.extend_from_slice(&tag_size.to_le_bytes());
+        buf.extend_from_slice(&num_items.to_le_bytes());
+        buf.extend_from_slice(&flags.to_le_bytes());
+        buf.extend_from_slice(&[0; 8]);
+        buf
+    }
+
+    fn make_ape_tag(key: &str, value: &str) -> Vec<u8> {
+        let mut buf = (value.len() as u32).to_le_bytes().to_vec();
+        buf.extend_from_slice(&0u32.to_le_bytes());
+        buf.extend_from_slice(key.as_bytes());
+        buf.push(0);
+        buf.extend_from_slice(value.as_bytes());
+
+        let footer = make_ape_footer(buf.len() as u32 + 32, 1, 0);
+        buf.extend(footer);
+        buf
+    }
+
+    fn read(buf: Vec<u8>) -> (Option<u64>, MetadataLog) {
+        let mut mss = MediaSourceStream::new(Box::new(Cursor::new(buf)), Default::default());
+        let mut metadata = MetadataLog::default();
+        let end = read_trailing_tags(&mut mss, &mut metadata).unwrap();
+        (end, metadata)
+    }
+
+    #[test]
+    fn verify_read_trailing_tags() {
+        let mut buf = vec![0; 1000];
+        buf.extend(make_ape_tag("Artist", "Someone"));
+        buf.extend(make_id3v1("Title"));
+
+        let (end, mut metadata) = read(buf);
+        assert_eq!(end, Some(1000));
+
+        let revision = metadata.metadata().skip_to_latest().unwrap().clone();
+        let keys: Vec<_> = revision.tags().iter().map(|tag| tag.std_key).collect();
+        assert!(keys.contains(&Some(StandardTagKey::TrackTitle)));
+        assert!(keys.contains(&Some(StandardTagKey::Artist)));
+
+        // No tags.
+        let (end, mut metadata) = read(vec![0; 1000]);
+        assert_eq!(end, Some(1000));
+        assert!(metadata.metadata().current().is_none());
+    }
+
+    #[test]
+    fn verify_read_invalid_trailing_tags() {
+        // A footer flagged as having no footer, with a tag size equal to the stream length, places
+        // the items before the start of the stream.
+        let mut buf = vec![0; 1000];
+        let len = buf.len() as u32 + 32;
+        buf.extend(make_ape_footer(len, 1, 0x4000_0000));
+
+        let (end, _) = read(buf);
+        assert_eq!(end, Some(1032));
+
+        // A footer describing a tag longer than the stream.
+        let mut buf = vec![0; 1000];
+        buf.extend(make_ape_footer(2000, 1, 0));
+        buf.extend(make_id3v1("Title"));
+
+        let (end, mut metadata) = read(buf);
+        assert_eq!(end, Some(1032));
+        let revision = metadata.metadata().current().unwrap().clone();
+        assert_eq!(revision.tags()[0].std_key, Some(StandardTagKey::TrackTitle));
+    }
+}
@@ -35,6 +35,7 @@ lazy_static! {
         m.insert("comment"                     , StandardTagKey::Comment);
         m.insert("compileation"                , StandardTagKey::Compilation);
         m.insert("composer"                    , StandardTagKey::Composer);
+        m.insert("composersort"                , StandardTagKey::SortComposer);
         m.insert("conductor"                   , StandardTagKey::Conductor);
         m.insert("copyright"                   , StandardTagKey::Copyright);
         m.insert("date"                        , StandardTagKey::Date);
@@ -67,8 +68,12 @@ lazy_static! {
         m.insert("mood"                        , StandardTagKey::Mood);
         m.insert("musicbrainz_albumartistid"   , StandardTagKey::MusicBrainzAlbumArtistId);
         m.insert("musicbrainz_albumid"         , StandardTagKey::MusicBrainzAlbumId);
+        m.insert("musicbrainz_albumstatus"     , StandardTagKey::MusicBrainzReleaseStatus);
+        m.insert("musicbrainz_albumtype"       , StandardTagKey::MusicBrainzReleaseType);
         m.insert("musicbrainz_artistid"        , StandardTagKey::MusicBrainzArtistId);
         m.insert("musicbrainz_discid"          , StandardTagKey::MusicBrainzDiscId);
+        m.insert("musicbrainz_genreid"         , StandardTagKey::MusicBrainzGenreId);
+        m.insert("musicbrainz_labelid"         , StandardTagKey::MusicBrainzLabelId);
         m.insert("musicbrainz_originalalbumid" , StandardTagKey::MusicBrainzOriginalAlbumId);
         m.insert("musicbrainz_originalartistid", StandardTagKey::MusicBrainzOriginalArtistId);
         m.insert("musicbrainz_recordingid"     , StandardTagKey::MusicBrainzRecordingId);
@@ -87,6 +92,8 @@ lazy_static! {
         m.insert("publisher"                   , StandardTagKey::Label);
         m.insert("rating"                      , StandardTagKey::Rating);
         m.insert("releasecountry"              , StandardTagKey::ReleaseCountry);
+        m.insert("releasestatus"               , StandardTagKey::MusicBrainzReleaseStatus);
+        m.insert("releasetype"                 , StandardTagKey::MusicBrainzReleaseType);
         m.insert("remixer"                     , StandardTagKey::Remixer);
         m.insert("replaygain_album_gain"       , StandardTagKey::ReplayGainAlbumGain);
         m.insert("replaygain_album_peak"       , StandardTagKey::ReplayGainAlbumPeak);
@@ -18,12 +18,14 @@ use std::path::Path;
 
 use lazy_static::lazy_static;
 use symphonia::core::codecs::{DecoderOptions, FinalizeResult, CODEC_TYPE_NULL};
+use symphonia::core::dsp::channel_map::ChannelMap;
 use symphonia::core::errors::{Error, Result};
 use symphonia::core::formats::{Cue, FormatOptions, FormatReader, SeekMode, SeekTo, Track};
 use symphonia::core::io::{MediaSource, MediaSourceStream, ReadOnlySource};
 use symphonia::core::meta::{ColorMode, MetadataOptions, MetadataRevision, Tag, Value, Visual};
 use symphonia::core::probe::{Hint, ProbeResult};
 use symphonia::core::units::{Time, TimeBase};
+use symphonia::dump::dump_packets;
 
 use clap::{Arg, ArgMatches};
 use log::{error, info, warn};
@@ -75,10 +77,34 @@ fn main() {
                 .short('v')
                 .help("Verify the decoded audio is valid during playback"),
         )
+        .arg(
+            Arg::new("dump-packets")
+                .long("dump-packets")
+                .help("Dump the position, size, timing, and header fields of each packet")
+                .conflicts_with_all(&[
+                    "decode-only",
+                    "probe-only",
+                    "verify-only",
+                    "verify",
+                    "seek",
+                ]),
+        )
+        .arg(
+            Arg::new("channel-map")
+                .long("channel-map")
+                .value_name("MAP")
+                .help("Reorder or downmix the output channels (e.g., \"FR,FL\" or \"2.0\")"),
+        )
         .arg(Arg::new("no-progress").long("no-progress").help("Do not display playback progress"))
         .arg(
             Arg::new("no-gapless").long("no-gapless").help("Disable gapless decoding and playback"),
         )
+        .arg(
+            Arg::new("estimate-gapless")
+                .long("estimate-gapless")
+                .conflicts_with("no-gapless")
+                .help("Assume the typical encoder delay if the file has no gapless information"),
+        )
         .arg(
             Arg::new("INPUT")
                 .help("The input file path, or - to use standard input")
@@ -127,8 +153,9 @@ fn run(args: &ArgMatches) -> Result<i32> {
     let mss = MediaSourceStream::new(source, Default::default());
 
     // Use the default options for format readers other than for gapless playback.
-    let format_opts =
-        FormatOptions { enable_gapless: !args.is_present("no-gapless"), ..Default::default() };
+    let format_opts = FormatOptions::default()
+        .with_enable_gapless(!args.is_present("no-gapless"))
+        .with_estimate_gapless(args.is_present("estimate-gapless"));
 
     // Use the default options for metadata readers.
     let metadata_opts: MetadataOptions = Default::default();
@@ -141,6 +168,16 @@ fn run(args: &ArgMatches) -> Result<i32> {
 
     let no_progress = args.is_present("no-progress");
 
+    // Parse the channel map, if provided.
+    let channel_map = match args.value_of("channel-map").map(str::parse::<ChannelMap>) {
+        Some(Ok(map)) => Some(map),
+        Some(Err(err)) => {
+            error!("invalid channel map: {}", err);
+            return Ok(-1);
+        }
+        None => None,
+    };
+
     // Probe the media source stream for metadata and get the format reader.
     match symphonia::default::get_probe().format(&hint, mss, &format_opts, &metadata_opts) {
         Ok(mut probed) => {
@@ -152,6 +189,13 @@ fn run(args: &ArgMatches) -> Result<i32> {
                 // Decode-only mode decodes the audio, but does not play or verify it.
                 decode_only(probed.format, &DecoderOptions { verify: false, ..Default::default() })
             }
+            else if args.is_present("dump-packets") {
+                // Dump-packets mode prints a description of each packet, but does not decode it.
+                let stdout = std::io::stdout();
+                let options = Default::default();
+                dump_packets(probed.format.as_mut(), &mut stdout.lock(), &options)?;
+                Ok(0)
+            }
             else if args.is_present("probe-only") {
                 // Probe-only mode only prints information about the format, tracks, metadata, etc.
                 print_format(path_str, &mut probed);
@@ -169,7 +213,14 @@ fn run(args: &ArgMatches) -> Result<i32> {
                     DecoderOptions { verify: args.is_present("verify"), ..Default::default() };
 
                 // Play it!
-                play(probed.format, track, seek_time, &decode_opts, no_progress)
+                play(
+                    probed.format,
+                    track,
+                    seek_time,
+                    &decode_opts,
+                    channel_map.as_ref(),
+                    no_progress,
+                )
             }
         }
         Err(err) => {
@@ -227,6 +278,7 @@ fn play(
     track_num: Option<usize>,
     seek_time: Option<f64>,
     decode_opts: &DecoderOptions,
+    channel_map: Option<&ChannelMap>,
     no_progress: bool,
 ) -> Result<i32> {
     // If the user provided a track number, select that track if it exists, otherwise, select the
@@ -276,7 +328,14 @@ fn play(
     let mut track_info = PlayTrackOptions { track_id, seek_ts };
 
     let result = loop {
-        match play_track(&mut reader, &mut audio_output, track_info, decode_opts, no_progress) {
+        match play_track(
+            &mut reader,
+            &mut audio_output,
+            track_info,
+            decode_opts,
+            channel_map,
+            no_progress,
+        ) {
             Err(Error::ResetRequired) => {
                 // The demuxer indicated that a reset is required. This is sometimes seen with
                 // streaming OGG (e.g., Icecast) wherein the entire contents of the container change
@@ -306,6 +365,7 @@ fn play_track(
     audio_output: &mut Option<Box<dyn output::AudioOutput>>,
     play_opts: PlayTrackOptions,
     decode_opts: &DecoderOptions,
+    channel_map: Option<&ChannelMap>,
     no_progress: bool,
 ) -> Result<i32> {
     // Get the selected track using the track ID.
@@ -357,8 +417,20 @@ fn play_track(
                     // decoder, but the length is not.
                     let duration = decoded.capacity() as u64;
 
-                    // Try to open the audio output.
-                    audio_output.replace(output::try_open(spec, duration).unwrap());
+                    // Try to open the audio output. If there is a channel map, mix the decoded
+                    // audio to the output channels.
+                    let output = match channel_map.map(|map| map.mixer(spec.channels)) {
+                        Some(Ok(mixer)) => {
+                            output::MixedAudioOutput::try_open(spec, duration, mixer)
+                        }
+                        Some(Err(err)) => {
+                            error!("channel map can not be applied: {}", err);
+                            break Err(Error::Unsupported("channel map can not be applied"));
+                        }
+                        None => output::try_open(spec, duration),
+                    };
+
+                    audio_output.replace(output.unwrap());
                 }
                 else {
                     // TODO: Check the audio spec. and duration hasn't changed.
@@ -526,6 +598,9 @@ fn print_tracks(tracks: &[Track]) {
             if let Some(channel_layout) = params.channel_layout {
                 println!("|          Channel Layout:  {:?}", channel_layout);
             }
+            if let Some(name) = &track.name {
+                println!("|          Name:            {}", name);
+            }
             if let Some(language) = &track.language {
                 println!("|          Language:        {}", language);
             }
@@ -9,7 +9,8 @@
 
 use std::result;
 
-use symphonia::core::audio::{AudioBufferRef, SignalSpec};
+use symphonia::core::audio::{AsAudioBufferRef, AudioBuffer, AudioBufferRef, SignalSpec};
+use symphonia::core::dsp::channel_map::ChannelMixer;
 use symphonia::core::units::Duration;
 
 pub trait AudioOutput {
@@ -366,3 +367,41 @@ pub fn try_open(spec: SignalSpec, duration: Duration) -> Result<Box<dyn AudioOut
 pub fn try_open(spec: SignalSpec, duration: Duration) -> Result<Box<dyn AudioOutput>> {
     cpal::CpalAudioOutput::try_open(spec, duration)
 }
+
+/// An `AudioOutput` that mixes the decoded audio with a `ChannelMixer` before writing it to the
+/// platform audio output.
+pub struct MixedAudioOutput {
+    mixer: ChannelMixer,
+    mixed: AudioBuffer<f32>,
+    inner: Box<dyn AudioOutput>,
+}
+
+impl MixedAudioOutput {
+    pub fn try_open(
+        spec: SignalSpec,
+        duration: Duration,
+        mixer: ChannelMixer,
+    ) -> Result<Box<dyn AudioOutput>> {
+        let spec = SignalSpec::new(spec.rate, mixer.output_channels());
+
+        let inner = try_open(spec, duration)?;
+
+        Ok(Box::new(MixedAudioOutput { mixer, mixed: AudioBuffer::new(duration, spec), inner }))
+    }
+}
+
+impl AudioOutput for MixedAudioOutput {
+    fn write(&mut self, decoded: AudioBufferRef<'_>) -> Result<()> {
+        // Do nothing if there are no audio frames.
+        if decoded.frames() == 0 {
+            return Ok(());
+        }
+
+        self.mixer.mix(decoded, &mut self.mixed);
+        self.inner.write(self.mixed.as_audio_buffer_ref())
+    }
+
+    fn flush(&mut self) {
+        self.inner.flush()
+    }
+}
@@ -0,0 +1,259 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The plugin side of the plugin interface.
+//!
+//! Plugins should use the [`export_decoders`][crate::export_decoders] macro rather than the items
+//! in this module directly.
+
+use std::ffi::CString;
+use std::os::raw::c_void;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, Ordering};
+use std::sync::Once;
+
+use symphonia_core::audio::{Channels, SampleBuffer};
+use symphonia_core::codecs::{CodecParameters, Decoder, DecoderOptions};
+use symphonia_core::errors::{Error, Result};
+use symphonia_core::formats::Packet;
+
+use crate::{status, PluginAudio, PluginCodecParams, PluginPacket};
+use crate::{PluginDecoderVTable, PluginEntry, ABI_VERSION};
+
+/// Exports one or more `Decoder`s from a plugin by defining the plugin entry point.
+///
+/// ```ignore
+/// symphonia_plugin::export_decoders!(MyDecoder, MyOtherDecoder);
+/// ```
+#[macro_export]
+macro_rules! export_decoders {
+    ($($decoder:ty),+ $(,)?) => {
+        #[no_mangle]
+        pub extern "C" fn symphonia_plugin_entry() -> *const $crate::PluginEntry {
+            static ENTRY: $crate::export::EntryCell = $crate::export::EntryCell::new();
+
+            ENTRY.get_or_init(|| {
+                let mut vtables = Vec::new();
+                $(vtables.extend($crate::export::decoder_vtables::<$decoder>());)+
+                vtables
+            })
+        }
+    };
+}
+
+/// A lazily initialized, immutable, `PluginEntry`.
+pub struct EntryCell {
+    once: Once,
+    entry: AtomicPtr<PluginEntry>,
+}
+
+impl EntryCell {
+    /// Instantiate a new, uninitialized, `EntryCell`.
+    pub const fn new() -> Self {
+        EntryCell { once: Once::new(), entry: AtomicPtr::new(ptr::null_mut()) }
+    }
+
+    /// Gets the `PluginEntry`, initializing it with the function tables returned by `init` if
+    /// this is the first call.
+    ///
+    /// The entry and its function tables are never freed.
+    pub fn get_or_init<F>(&self, init: F) -> *const PluginEntry
+    where
+        F: FnOnce() -> Vec<PluginDecoderVTable>,
+    {
+        self.once.call_once(|| {
+            let decoders = Box::leak(init().into_boxed_slice());
+
+            let entry = Box::new(PluginEntry {
+                abi_version: ABI_VERSION,
+                n_decoders: decoders.len(),
+                decoders: decoders.as_ptr(),
+            });
+
+            self.entry.store(Box::into_raw(entry), Ordering::Release);
+        });
+
+        self.entry.load(Ordering::Acquire)
+    }
+}
+
+impl Default for EntryCell {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Gets a function table for each codec supported by the `Decoder`.
+///
+/// The names of the codecs are leaked since the function tables must live for the lifetime of the
+/// plugin.
+pub fn decoder_vtables<D: Decoder + 'static>() -> Vec<PluginDecoderVTable> {
+    let to_c_str = |name: &str| {
+        // Names should never contain a null character, but simply drop them if they do.
+        let name = CString::new(name.replace('\0', "")).unwrap_or_default();
+        name.into_raw() as *const _
+    };
+
+    D::supported_codecs()
+        .iter()
+        .map(|desc| PluginDecoderVTable {
+            codec: u32::from(desc.codec),
+            short_name: to_c_str(desc.short_name),
+            long_name: to_c_str(desc.long_name),
+            create: create::<D>,
+            destroy: destroy::<D>,
+            reset: reset::<D>,
+            decode: decode::<D>,
+            finalize: finalize::<D>,
+        })
+        .collect()
+}
+
+/// A decoder instance owned by the plugin.
+struct Instance<D: Decoder> {
+    decoder: D,
+    buf: Option<SampleBuffer<f32>>,
+}
+
+fn error_to_status(err: &Error) -> i32 {
+    match err {
+        Error::DecodeError(_) => status::DECODE_ERROR,
+        Error::Unsupported(_) => status::UNSUPPORTED,
+        Error::ResetRequired => status::RESET_REQUIRED,
+        Error::IoError(_) => status::IO_ERROR,
+        _ => status::OTHER,
+    }
+}
+
+/// Converts plugin codec parameters into `CodecParameters`.
+///
+/// # Safety
+///
+/// The extra data pointer must be valid for the length provided, or null.
+unsafe fn to_codec_params(params: &PluginCodecParams) -> CodecParameters {
+    let mut codec_params = CodecParameters::new();
+
+    codec_params.for_codec(params.codec.into());
+
+    if params.sample_rate != 0 {
+        codec_params.with_sample_rate(params.sample_rate);
+    }
+    if params.channels != 0 {
+        codec_params.with_channels(Channels::from_bits_truncate(params.channels));
+    }
+    if params.bits_per_sample != 0 {
+        codec_params.with_bits_per_sample(params.bits_per_sample);
+    }
+    if params.bits_per_coded_sample != 0 {
+        codec_params.with_bits_per_coded_sample(params.bits_per_coded_sample);
+    }
+    if params.max_frames_per_packet != 0 {
+        codec_params.with_max_frames_per_packet(params.max_frames_per_packet);
+    }
+    if params.frames_per_block != 0 {
+        codec_params.with_frames_per_block(params.frames_per_block);
+    }
+    if !params.extra_data.is_null() {
+        let extra_data = std::slice::from_raw_parts(params.extra_data, params.extra_data_len);
+        codec_params.with_extra_data(Box::from(extra_data));
+    }
+
+    codec_params
+}
+
+unsafe extern "C" fn create<D: Decoder>(
+    params: *const PluginCodecParams,
+    instance: *mut *mut c_void,
+) -> i32 {
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        let params = &*params;
+
+        let options = DecoderOptions { verify: params.verify != 0 };
+
+        D::try_new(&to_codec_params(params), &options)
+    }));
+
+    match result {
+        Ok(Ok(decoder)) => {
+            let boxed = Box::new(Instance { decoder, buf: None });
+            *instance = Box::into_raw(boxed) as *mut c_void;
+            status::OK
+        }
+        Ok(Err(err)) => error_to_status(&err),
+        Err(_) => status::OTHER,
+    }
+}
+
+unsafe extern "C" fn destroy<D: Decoder>(instance: *mut c_void) {
+    drop(Box::from_raw(instance as *mut Instance<D>));
+}
+
+unsafe extern "C" fn reset<D: Decoder>(instance: *mut c_void) {
+    let instance = &mut *(instance as *mut Instance<D>);
+
+    let _ = catch_unwind(AssertUnwindSafe(|| instance.decoder.reset()));
+}
+
+unsafe extern "C" fn decode<D: Decoder>(
+    instance: *mut c_void,
+    packet: *const PluginPacket,
+    audio: *mut PluginAudio,
+) -> i32 {
+    let instance = &mut *(instance as *mut Instance<D>);
+    let packet = &*packet;
+
+    let data = std::slice::from_raw_parts(packet.data, packet.len);
+
+    let result = catch_unwind(AssertUnwindSafe(|| -> Result<PluginAudio> {
+        let packet = Packet::new_from_slice(packet.track_id, packet.ts, packet.dur, data);
+
+        let decoded = instance.decoder.decode(&packet)?;
+
+        let spec = *decoded.spec();
+        let n_frames = decoded.frames();
+        let n_samples = decoded.capacity() * spec.channels.count();
+
+        // Reallocate the sample buffer if it is too small for the decoded audio.
+        if instance.buf.as_ref().map_or(true, |buf| buf.capacity() < n_samples) {
+            instance.buf = Some(SampleBuffer::new(decoded.capacity() as u64, spec));
+        }
+
+        let buf = instance.buf.as_mut().unwrap();
+
+        buf.copy_planar_ref(decoded);
+
+        Ok(PluginAudio {
+            rate: spec.rate,
+            channels: spec.channels.bits(),
+            frames: n_frames,
+            samples: buf.samples().as_ptr(),
+        })
+    }));
+
+    match result {
+        Ok(Ok(decoded)) => {
+            *audio = decoded;
+            status::OK
+        }
+        Ok(Err(err)) => error_to_status(&err),
+        Err(_) => status::OTHER,
+    }
+}
+
+unsafe extern "C" fn finalize<D: Decoder>(instance: *mut c_void) -> i32 {
+    let instance = &mut *(instance as *mut Instance<D>);
+
+    match catch_unwind(AssertUnwindSafe(|| instance.decoder.finalize())) {
+        Ok(result) => match result.verify_ok {
+            Some(true) => 1,
+            Some(false) => 0,
+            None => -1,
+        },
+        Err(_) => -1,
+    }
+}
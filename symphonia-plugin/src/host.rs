@@ -0,0 +1,281 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_void};
+use std::ptr;
+use std::sync::RwLock;
+
+use symphonia_core::audio::{AsAudioBufferRef, AudioBuffer, AudioBufferRef};
+use symphonia_core::audio::{Channels, Signal, SignalSpec};
+use symphonia_core::codecs::{CodecDescriptor, CodecParameters, CodecRegistry, CodecType};
+use symphonia_core::codecs::{Decoder, DecoderOptions, FinalizeResult};
+use symphonia_core::errors::{decode_error, unsupported_error, Error, Result};
+use symphonia_core::formats::Packet;
+
+use lazy_static::lazy_static;
+use log::info;
+
+use crate::{status, PluginAudio, PluginCodecParams, PluginPacket};
+use crate::{PluginDecoderVTable, PluginEntry, ABI_VERSION};
+
+lazy_static! {
+    /// The function tables of all registered plugin codecs.
+    static ref PLUGIN_CODECS: RwLock<HashMap<CodecType, &'static PluginDecoderVTable>> =
+        RwLock::new(HashMap::new());
+}
+
+fn status_to_result(status: i32) -> Result<()> {
+    match status {
+        status::OK => Ok(()),
+        status::DECODE_ERROR => decode_error("plugin: malformed data"),
+        status::UNSUPPORTED => unsupported_error("plugin: unsupported stream"),
+        status::RESET_REQUIRED => Err(Error::ResetRequired),
+        status::IO_ERROR => {
+            Err(Error::IoError(std::io::Error::new(std::io::ErrorKind::Other, "plugin: io error")))
+        }
+        _ => decode_error("plugin: decoder failed"),
+    }
+}
+
+/// Converts a null-terminated string from a plugin into a `'static` string.
+///
+/// # Safety
+///
+/// The string must be null-terminated, and must live for the lifetime of the plugin.
+unsafe fn to_static_str(name: *const c_char) -> &'static str {
+    if name.is_null() {
+        return "";
+    }
+
+    // The plugin is never unloaded, therefore the leaked string lives as long as the descriptor.
+    Box::leak(CStr::from_ptr(name).to_string_lossy().into_owned().into_boxed_str())
+}
+
+/// Registers all the decoders of a plugin on the provided `CodecRegistry`.
+///
+/// Registered decoders are made by the `CodecRegistry` the same as any other decoder.
+///
+/// # Safety
+///
+/// The entry must have been returned by the entry point of a Symphonia plugin, and the plugin
+/// must not be unloaded for the lifetime of the process.
+pub unsafe fn register_entry(
+    entry: *const PluginEntry,
+    registry: &mut CodecRegistry,
+) -> Result<()> {
+    let entry = match entry.as_ref() {
+        Some(entry) => entry,
+        None => return unsupported_error("plugin: invalid plugin entry"),
+    };
+
+    if entry.abi_version != ABI_VERSION {
+        return unsupported_error("plugin: unsupported plugin abi version");
+    }
+
+    let vtables: &'static [PluginDecoderVTable] = if entry.n_decoders > 0 {
+        std::slice::from_raw_parts(entry.decoders, entry.n_decoders)
+    }
+    else {
+        &[]
+    };
+
+    let mut codecs = PLUGIN_CODECS.write().unwrap();
+
+    for vtable in vtables {
+        let descriptor = CodecDescriptor {
+            codec: CodecType::from(vtable.codec),
+            short_name: to_static_str(vtable.short_name),
+            long_name: to_static_str(vtable.long_name),
+            inst_func: |params, opts| Ok(Box::new(PluginDecoder::try_new(params, opts)?)),
+        };
+
+        info!("registering plugin codec {} ({})", descriptor.short_name, descriptor.codec);
+
+        codecs.insert(descriptor.codec, vtable);
+        registry.register(&descriptor);
+    }
+
+    Ok(())
+}
+
+/// Loads a plugin from a dynamic library, and registers all its decoders on the provided
+/// `CodecRegistry`.
+///
+/// Once loaded, the plugin is never unloaded.
+///
+/// # Safety
+///
+/// Loading a dynamic library runs arbitrary initialization code, and the library must be a
+/// Symphonia plugin built for the same plugin ABI version.
+#[cfg(feature = "dylib")]
+pub unsafe fn load_plugin<P: AsRef<std::ffi::OsStr>>(
+    path: P,
+    registry: &mut CodecRegistry,
+) -> Result<()> {
+    use std::io;
+
+    let to_io_error =
+        |err: libloading::Error| Error::IoError(io::Error::new(io::ErrorKind::Other, err));
+
+    let library = libloading::Library::new(path).map_err(to_io_error)?;
+
+    let entry = {
+        let entry_fn: libloading::Symbol<'_, crate::PluginEntryFn> =
+            library.get(crate::ENTRY_POINT_SYMBOL).map_err(to_io_error)?;
+
+        entry_fn()
+    };
+
+    register_entry(entry, registry)?;
+
+    // The registered decoders reference code and data within the library. Never unload it.
+    std::mem::forget(library);
+
+    Ok(())
+}
+
+/// A `Decoder` implemented by a plugin.
+pub struct PluginDecoder {
+    vtable: &'static PluginDecoderVTable,
+    instance: *mut c_void,
+    params: CodecParameters,
+    buf: AudioBuffer<f32>,
+}
+
+// Plugin decoders are implemented from a Rust `Decoder`, which is `Send + Sync`. An instance is
+// only ever accessed through `&mut self`.
+unsafe impl Send for PluginDecoder {}
+unsafe impl Sync for PluginDecoder {}
+
+impl Decoder for PluginDecoder {
+    fn try_new(params: &CodecParameters, options: &DecoderOptions) -> Result<Self> {
+        let vtable = match PLUGIN_CODECS.read().unwrap().get(&params.codec) {
+            Some(vtable) => *vtable,
+            None => return unsupported_error("plugin: no plugin registered for codec"),
+        };
+
+        let channels = params
+            .channels
+            .or_else(|| params.channel_layout.map(|layout| layout.into_channels()))
+            .unwrap_or_else(Channels::empty);
+
+        let (extra_data, extra_data_len) = match &params.extra_data {
+            Some(data) => (data.as_ptr(), data.len()),
+            None => (ptr::null(), 0),
+        };
+
+        let plugin_params = PluginCodecParams {
+            codec: u32::from(params.codec),
+            sample_rate: params.sample_rate.unwrap_or(0),
+            channels: channels.bits(),
+            bits_per_sample: params.bits_per_sample.unwrap_or(0),
+            bits_per_coded_sample: params.bits_per_coded_sample.unwrap_or(0),
+            max_frames_per_packet: params.max_frames_per_packet.unwrap_or(0),
+            frames_per_block: params.frames_per_block.unwrap_or(0),
+            extra_data,
+            extra_data_len,
+            verify: u8::from(options.verify),
+        };
+
+        let mut instance = ptr::null_mut();
+
+        status_to_result(unsafe { (vtable.create)(&plugin_params, &mut instance) })?;
+
+        if instance.is_null() {
+            return decode_error("plugin: decoder instance is null");
+        }
+
+        Ok(PluginDecoder { vtable, instance, params: params.clone(), buf: AudioBuffer::unused() })
+    }
+
+    fn supported_codecs() -> &'static [CodecDescriptor] {
+        // Plugin codecs are only known at runtime, and are registered by `register_entry`.
+        &[]
+    }
+
+    fn reset(&mut self) {
+        unsafe { (self.vtable.reset)(self.instance) }
+    }
+
+    fn codec_params(&self) -> &CodecParameters {
+        &self.params
+    }
+
+    fn decode(&mut self, packet: &Packet) -> Result<AudioBufferRef<'_>> {
+        let data = packet.buf();
+
+        let plugin_packet = PluginPacket {
+            track_id: packet.track_id(),
+            ts: packet.ts(),
+            dur: packet.dur(),
+            data: data.as_ptr(),
+            len: data.len(),
+        };
+
+        let mut audio = PluginAudio { rate: 0, channels: 0, frames: 0, samples: ptr::null() };
+
+        let result = status_to_result(unsafe {
+            (self.vtable.decode)(self.instance, &plugin_packet, &mut audio)
+        });
+
+        if let Err(err) = result {
+            self.buf.clear();
+            return Err(err);
+        }
+
+        let spec = SignalSpec::new(audio.rate, Channels::from_bits_truncate(audio.channels));
+        let n_channels = spec.channels.count();
+
+        if audio.frames > 0 && (audio.samples.is_null() || n_channels == 0) {
+            self.buf.clear();
+            return decode_error("plugin: decoder returned invalid audio");
+        }
+
+        // Reallocate the audio buffer if the signal specification changed, or the decoded audio
+        // would not fit.
+        if *self.buf.spec() != spec || self.buf.capacity() < audio.frames {
+            self.buf = AudioBuffer::new(audio.frames as u64, spec);
+        }
+
+        self.buf.clear();
+        self.buf.render_reserved(Some(audio.frames));
+
+        if audio.frames > 0 {
+            // The plugin guarantees the samples remain valid until the next call on the instance.
+            let samples =
+                unsafe { std::slice::from_raw_parts(audio.samples, audio.frames * n_channels) };
+
+            for (ch, plane) in samples.chunks_exact(audio.frames).enumerate() {
+                self.buf.chan_mut(ch).copy_from_slice(plane);
+            }
+        }
+
+        Ok(self.buf.as_audio_buffer_ref())
+    }
+
+    fn finalize(&mut self) -> FinalizeResult {
+        let verify_ok = match unsafe { (self.vtable.finalize)(self.instance) } {
+            1 => Some(true),
+            0 => Some(false),
+            _ => None,
+        };
+
+        FinalizeResult { verify_ok }
+    }
+
+    fn last_decoded(&self) -> AudioBufferRef<'_> {
+        self.buf.as_audio_buffer_ref()
+    }
+}
+
+impl Drop for PluginDecoder {
+    fn drop(&mut self) {
+        unsafe { (self.vtable.destroy)(self.instance) }
+    }
+}
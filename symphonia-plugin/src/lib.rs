@@ -0,0 +1,207 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+#![warn(rust_2018_idioms)]
+// The following lints are allowed in all Symphonia crates. Please see clippy.toml for their
+// justification.
+#![allow(clippy::comparison_chain)]
+#![allow(clippy::excessive_precision)]
+#![allow(clippy::identity_op)]
+#![allow(clippy::manual_range_contains)]
+
+//! An experimental plugin interface for out-of-tree Symphonia decoders.
+//!
+//! Decoders that cannot be compiled into an application, for example due to licensing
+//! restrictions, may instead be compiled into a dynamic library and loaded at runtime. The
+//! interface between the host and a plugin is a C-ABI function table per codec, so the plugin and
+//! host need not be built with the same compiler version.
+//!
+//! A plugin exports its decoders with the [`export_decoders`] macro. A host registers a plugin's
+//! decoders into a [`CodecRegistry`][symphonia_core::codecs::CodecRegistry] with [`load_plugin`]
+//! (requires the `dylib` feature), or with [`register_entry`] if the plugin entry point was
+//! obtained by other means.
+//!
+//! Decoded audio always crosses the plugin boundary as planar 32-bit floating point samples.
+
+use std::os::raw::{c_char, c_void};
+
+pub mod export;
+mod host;
+
+pub use host::{register_entry, PluginDecoder};
+
+#[cfg(feature = "dylib")]
+pub use host::load_plugin;
+
+/// The version of the plugin ABI. A host will refuse to load a plugin built for a different
+/// version.
+pub const ABI_VERSION: u32 = 1;
+
+/// The name of the symbol of the plugin entry point.
+pub const ENTRY_POINT_SYMBOL: &[u8] = b"symphonia_plugin_entry\0";
+
+/// The signature of the plugin entry point.
+pub type PluginEntryFn = unsafe extern "C" fn() -> *const PluginEntry;
+
+/// Status codes returned by plugin functions.
+pub mod status {
+    /// The operation completed successfully.
+    pub const OK: i32 = 0;
+    /// The data was malformed, see `Error::DecodeError`.
+    pub const DECODE_ERROR: i32 = 1;
+    /// The codec or stream is not supported, see `Error::Unsupported`.
+    pub const UNSUPPORTED: i32 = 2;
+    /// The decoder must be reset, see `Error::ResetRequired`.
+    pub const RESET_REQUIRED: i32 = 3;
+    /// An IO error occurred, see `Error::IoError`.
+    pub const IO_ERROR: i32 = 4;
+    /// Any other error, including a panic within the plugin.
+    pub const OTHER: i32 = 5;
+}
+
+/// The codec parameters provided to a plugin decoder upon instantiation.
+///
+/// Unknown values are represented by 0.
+#[repr(C)]
+pub struct PluginCodecParams {
+    /// The codec type identifier.
+    pub codec: u32,
+    /// The sample rate in hertz.
+    pub sample_rate: u32,
+    /// The channel bitmask, see `Channels`.
+    pub channels: u32,
+    /// The number of bits per decoded sample.
+    pub bits_per_sample: u32,
+    /// The number of bits per encoded sample.
+    pub bits_per_coded_sample: u32,
+    /// The maximum number of frames a packet will contain.
+    pub max_frames_per_packet: u64,
+    /// The number of frames per block.
+    pub frames_per_block: u64,
+    /// A pointer to the codec-specific extra data, or null if there is none.
+    pub extra_data: *const u8,
+    /// The length of the extra data in bytes.
+    pub extra_data_len: usize,
+    /// Non-zero if the decoded audio should be verified, see `DecoderOptions::verify`.
+    pub verify: u8,
+}
+
+/// A packet provided to a plugin decoder.
+#[repr(C)]
+pub struct PluginPacket {
+    /// The track identifier.
+    pub track_id: u32,
+    /// The timestamp of the packet.
+    pub ts: u64,
+    /// The duration of the packet.
+    pub dur: u64,
+    /// A pointer to the packet data.
+    pub data: *const u8,
+    /// The length of the packet data in bytes.
+    pub len: usize,
+}
+
+/// Decoded audio returned by a plugin decoder.
+///
+/// The samples are owned by the plugin decoder instance, and remain valid until the next call to
+/// any function of the instance.
+#[repr(C)]
+pub struct PluginAudio {
+    /// The sample rate in hertz.
+    pub rate: u32,
+    /// The channel bitmask, see `Channels`.
+    pub channels: u32,
+    /// The number of frames decoded.
+    pub frames: usize,
+    /// A pointer to `frames` samples per channel, stored in planar channel order.
+    pub samples: *const f32,
+}
+
+/// The function table of a single codec provided by a plugin.
+#[repr(C)]
+pub struct PluginDecoderVTable {
+    /// The codec type identifier.
+    pub codec: u32,
+    /// A null-terminated short name of the codec.
+    pub short_name: *const c_char,
+    /// A null-terminated long name of the codec.
+    pub long_name: *const c_char,
+    /// Instantiates a decoder and writes a pointer to the instance into the out parameter.
+    pub create: unsafe extern "C" fn(*const PluginCodecParams, *mut *mut c_void) -> i32,
+    /// Destroys a decoder instance.
+    pub destroy: unsafe extern "C" fn(*mut c_void),
+    /// Resets a decoder instance.
+    pub reset: unsafe extern "C" fn(*mut c_void),
+    /// Decodes a packet and writes the decoded audio into the out parameter.
+    pub decode: unsafe extern "C" fn(*mut c_void, *const PluginPacket, *mut PluginAudio) -> i32,
+    /// Finalizes a decoder instance. Returns 1 if verification passed, 0 if verification failed,
+    /// or -1 if verification was not performed.
+    pub finalize: unsafe extern "C" fn(*mut c_void) -> i32,
+}
+
+/// The table returned by the entry point of a plugin.
+#[repr(C)]
+pub struct PluginEntry {
+    /// The plugin ABI version the plugin was built for.
+    pub abi_version: u32,
+    /// The number of function tables.
+    pub n_decoders: usize,
+    /// A pointer to `n_decoders` function tables.
+    pub decoders: *const PluginDecoderVTable,
+}
+
+// The tables are immutable once constructed, and the strings and functions they point to live for
+// the lifetime of the plugin.
+unsafe impl Send for PluginDecoderVTable {}
+unsafe impl Sync for PluginDecoderVTable {}
+unsafe impl Send for PluginEntry {}
+unsafe impl Sync for PluginEntry {}
+
+#[cfg(test)]
+mod tests {
+    use symphonia_codec_pcm::PcmDecoder;
+    use symphonia_core::audio::{AudioBufferRef, Channels, Signal};
+    use symphonia_core::codecs::{CodecParameters, CodecRegistry, CODEC_TYPE_PCM_S16LE};
+    use symphonia_core::formats::Packet;
+
+    use super::export::EntryCell;
+    use super::register_entry;
+
+    static ENTRY: EntryCell = EntryCell::new();
+
+    #[test]
+    fn verify_plugin_decoder_round_trip() {
+        let entry = ENTRY.get_or_init(super::export::decoder_vtables::<PcmDecoder>);
+
+        let mut registry = CodecRegistry::new();
+        unsafe { register_entry(entry, &mut registry) }.unwrap();
+
+        let mut params = CodecParameters::new();
+        params
+            .for_codec(CODEC_TYPE_PCM_S16LE)
+            .with_sample_rate(8000)
+            .with_bits_per_coded_sample(16)
+            .with_bits_per_sample(16)
+            .with_max_frames_per_packet(4)
+            .with_channels(Channels::FRONT_LEFT | Channels::FRONT_RIGHT);
+
+        let mut decoder = registry.make(&params, &Default::default()).unwrap();
+
+        let data = [0x00, 0x40, 0x00, 0xc0, 0x00, 0x00, 0xff, 0x7f];
+        let packet = Packet::new_from_slice(0, 0, 2, &data);
+
+        match decoder.decode(&packet).unwrap() {
+            AudioBufferRef::F32(buf) => {
+                assert_eq!(buf.frames(), 2);
+                assert_eq!(buf.spec().rate, 8000);
+                assert_eq!(buf.chan(0), &[0.5, 0.0]);
+                assert_eq!(buf.chan(1), &[-0.5, 32767.0 / 32768.0]);
+            }
+            _ => panic!("plugin decoders should produce f32 audio"),
+        }
+    }
+}
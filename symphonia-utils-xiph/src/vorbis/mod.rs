@@ -8,6 +8,10 @@
 use symphonia_core::audio::Channels;
 
 /// Get the mapping 0 channel listing for the given number of channels.
+///
+/// Vorbis leaves the channel order application-defined for more than 8 channels, in which case
+/// the first `num_channels` channel positions are assigned in order. Streams with more channels
+/// than there are channel positions are not supported.
 pub fn vorbis_channels_to_channels(num_channels: u8) -> Option<Channels> {
     let channels = match num_channels {
         1 => Channels::FRONT_LEFT,
@@ -53,8 +57,46 @@ pub fn vorbis_channels_to_channels(num_channels: u8) -> Option<Channels> {
                 | Channels::REAR_RIGHT
                 | Channels::LFE1
         }
+        9..=26 => return Channels::from_bits(u32::MAX >> (32 - u32::from(num_channels))),
         _ => return None,
     };
 
     Some(channels)
 }
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::audio::Channels;
+
+    use super::vorbis_channels_to_channels;
+
+    #[test]
+    fn verify_vorbis_channels_to_channels() {
+        for n in 1..=26 {
+            let channels = vorbis_channels_to_channels(n).unwrap();
+            assert_eq!(channels.count(), usize::from(n));
+        }
+
+        assert_eq!(
+            vorbis_channels_to_channels(6),
+            Some(
+                Channels::FRONT_LEFT
+                    | Channels::FRONT_CENTRE
+                    | Channels::FRONT_RIGHT
+                    | Channels::REAR_LEFT
+                    | Channels::REAR_RIGHT
+                    | Channels::LFE1
+            )
+        );
+
+        // More than 8 channels are assigned the first channel positions.
+        assert_eq!(
+            vorbis_channels_to_channels(9),
+            Some(Channels::from_bits((1 << 9) - 1).unwrap())
+        );
+        assert_eq!(vorbis_channels_to_channels(26), Channels::from_bits((1 << 26) - 1));
+
+        assert_eq!(vorbis_channels_to_channels(0), None);
+        assert_eq!(vorbis_channels_to_channels(27), None);
+    }
+}
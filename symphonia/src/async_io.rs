@@ -0,0 +1,293 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The `async_io` module provides an asynchronous interface to Symphonia for the Tokio runtime.
+//!
+//! Symphonia's demuxers and decoders are synchronous. Rather than blocking an executor thread,
+//! [`AsyncMedia`] runs them on Tokio's blocking thread pool. Tokio's asynchronous readers may be
+//! used as a media source with [`AsyncSource`] and [`AsyncReadOnlySource`], which wait on the
+//! reader from the blocking thread.
+
+use std::io;
+use std::io::SeekFrom;
+
+use symphonia_core::audio::AudioBuffer;
+use symphonia_core::errors::{Error, Result};
+use symphonia_core::formats::{Packet, SeekMode, SeekTo, SeekedTo};
+use symphonia_core::io::MediaSource;
+
+use tokio_dep::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt};
+use tokio_dep::runtime::Handle;
+
+use crate::opener::{MediaOpener, OpenedMedia};
+
+/// `AsyncSource` wraps a seekable Tokio reader and implements `MediaSource`.
+///
+/// An `AsyncSource` blocks the calling thread while waiting on the reader. It must therefore only
+/// be read from outside of the runtime's executor threads, such as by [`AsyncMedia`].
+pub struct AsyncSource<R> {
+    inner: R,
+    byte_len: Option<u64>,
+    handle: Handle,
+}
+
+impl<R: AsyncRead + AsyncSeek + Unpin + Send + Sync> AsyncSource<R> {
+    /// Instantiates a new `AsyncSource<R>` by taking ownership of the provided reader. The length
+    /// of the reader is determined by seeking to its end.
+    ///
+    /// Panics if not called from within a Tokio runtime.
+    pub async fn new(mut inner: R) -> io::Result<Self> {
+        let pos = inner.stream_position().await?;
+        let byte_len = inner.seek(SeekFrom::End(0)).await?;
+        inner.seek(SeekFrom::Start(pos)).await?;
+
+        Ok(AsyncSource { inner, byte_len: Some(byte_len), handle: Handle::current() })
+    }
+
+    /// Unwraps this `AsyncSource<R>`, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: AsyncRead + AsyncSeek + Unpin + Send + Sync> MediaSource for AsyncSource<R> {
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
+    fn byte_len(&self) -> Option<u64> {
+        self.byte_len
+    }
+}
+
+impl<R: AsyncRead + Unpin> io::Read for AsyncSource<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let inner = &mut self.inner;
+        self.handle.block_on(inner.read(buf))
+    }
+}
+
+impl<R: AsyncSeek + Unpin> io::Seek for AsyncSource<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let inner = &mut self.inner;
+        self.handle.block_on(inner.seek(pos))
+    }
+}
+
+/// `AsyncReadOnlySource` wraps a Tokio reader and implements `MediaSource` for sources that
+/// cannot be seeked, such as network streams.
+///
+/// Like [`AsyncSource`], an `AsyncReadOnlySource` must only be read from outside of the runtime's
+/// executor threads.
+pub struct AsyncReadOnlySource<R> {
+    inner: R,
+    handle: Handle,
+}
+
+impl<R: AsyncRead + Unpin + Send + Sync> AsyncReadOnlySource<R> {
+    /// Instantiates a new `AsyncReadOnlySource<R>` by taking ownership of the provided reader.
+    ///
+    /// Panics if not called from within a Tokio runtime.
+    pub fn new(inner: R) -> Self {
+        AsyncReadOnlySource { inner, handle: Handle::current() }
+    }
+
+    /// Unwraps this `AsyncReadOnlySource<R>`, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: AsyncRead + Unpin + Send + Sync> MediaSource for AsyncReadOnlySource<R> {
+    fn is_seekable(&self) -> bool {
+        false
+    }
+
+    fn byte_len(&self) -> Option<u64> {
+        None
+    }
+}
+
+impl<R: AsyncRead + Unpin> io::Read for AsyncReadOnlySource<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let inner = &mut self.inner;
+        self.handle.block_on(inner.read(buf))
+    }
+}
+
+impl<R> io::Seek for AsyncReadOnlySource<R> {
+    fn seek(&mut self, _: SeekFrom) -> io::Result<u64> {
+        Err(io::Error::new(io::ErrorKind::Other, "source does not support seeking"))
+    }
+}
+
+/// `AsyncMedia` is an asynchronous interface to media opened with a [`MediaOpener`].
+///
+/// Each operation is run to completion on Tokio's blocking thread pool. If the future of an
+/// operation is dropped before it completes, the media is lost once the operation finishes, and
+/// all further operations will return an error.
+pub struct AsyncMedia {
+    media: Option<OpenedMedia>,
+}
+
+fn join_error(err: tokio_dep::task::JoinError) -> Error {
+    Error::IoError(io::Error::new(io::ErrorKind::Other, err))
+}
+
+impl AsyncMedia {
+    /// Open the provided `MediaSource` with the provided `MediaOpener`.
+    pub async fn open<S>(opener: MediaOpener<'static>, source: S) -> Result<AsyncMedia>
+    where
+        S: MediaSource + 'static,
+    {
+        let media = tokio_dep::task::spawn_blocking(move || opener.open(source))
+            .await
+            .map_err(join_error)??;
+
+        Ok(AsyncMedia::new(media))
+    }
+
+    /// Instantiate a new `AsyncMedia` for media that has already been opened.
+    pub fn new(media: OpenedMedia) -> Self {
+        AsyncMedia { media: Some(media) }
+    }
+
+    /// Gets a reference to the opened media.
+    ///
+    /// Panics if a previous operation was cancelled.
+    pub fn media(&self) -> &OpenedMedia {
+        self.media.as_ref().expect("async media was lost by a cancelled operation")
+    }
+
+    /// Unwraps this `AsyncMedia`, returning the opened media, or `None` if a previous operation
+    /// was cancelled.
+    pub fn into_inner(self) -> Option<OpenedMedia> {
+        self.media
+    }
+
+    async fn run_blocking<T, F>(&mut self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut OpenedMedia) -> Result<T> + Send + 'static,
+    {
+        let mut media = match self.media.take() {
+            Some(media) => media,
+            None => {
+                return Err(Error::IoError(io::Error::new(
+                    io::ErrorKind::Other,
+                    "async media was lost by a cancelled operation",
+                )))
+            }
+        };
+
+        let (media, result) = tokio_dep::task::spawn_blocking(move || {
+            let result = f(&mut media);
+            (media, result)
+        })
+        .await
+        .map_err(join_error)?;
+
+        self.media = Some(media);
+
+        result
+    }
+
+    /// Get the next packet from the container. See `FormatReader::next_packet`.
+    pub async fn next_packet(&mut self) -> Result<Packet> {
+        self.run_blocking(|media| media.format.next_packet()).await
+    }
+
+    /// Seek, as precisely as possible depending on the mode, to the `Time` or track `TimeStamp`
+    /// requested. The decoder is reset after a successful seek. See `FormatReader::seek`.
+    pub async fn seek(&mut self, mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        self.run_blocking(move |media| {
+            let seeked_to = media.format.seek(mode, to)?;
+            media.decoder.reset();
+            Ok(seeked_to)
+        })
+        .await
+    }
+
+    /// Read and decode packets of the selected track until audio is decoded, and return a copy
+    /// of the decoded audio. Returns an end-of-stream IO error once the end of the track is
+    /// reached.
+    ///
+    /// Malformed packets are skipped.
+    pub async fn next_buffer(&mut self) -> Result<AudioBuffer<f32>> {
+        self.run_blocking(|media| loop {
+            let packet = media.format.next_packet()?;
+
+            if packet.track_id() != media.track_id {
+                continue;
+            }
+
+            let decoded = match media.decoder.decode(&packet) {
+                Ok(decoded) => decoded,
+                Err(Error::DecodeError(_)) => continue,
+                Err(err) => return Err(err),
+            };
+
+            let mut buf = decoded.make_equivalent();
+            decoded.convert(&mut buf);
+
+            return Ok(buf);
+        })
+        .await
+    }
+}
+
+#[cfg(all(test, feature = "wav", feature = "pcm"))]
+mod tests {
+    use std::io::Cursor;
+
+    use symphonia_core::audio::Signal;
+
+    use super::{AsyncMedia, AsyncReadOnlySource, AsyncSource};
+    use crate::opener::MediaOpener;
+
+    /// Makes a mono, 8-bit, WAV file.
+    fn make_wav(samples: &[u8]) -> Vec<u8> {
+        let mut wav = b"RIFF".to_vec();
+        wav.extend_from_slice(&(36 + samples.len() as u32).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        for field in &[16u32, 0x0001_0001, 8000, 8000, 0x0008_0001] {
+            wav.extend_from_slice(&field.to_le_bytes());
+        }
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&(samples.len() as u32).to_le_bytes());
+        wav.extend_from_slice(samples);
+        wav
+    }
+
+    #[test]
+    fn verify_async_media() {
+        let rt = tokio_dep::runtime::Builder::new_multi_thread().worker_threads(1).build().unwrap();
+
+        rt.block_on(async {
+            let wav = make_wav(&[128, 192, 64]);
+
+            // Seekable source.
+            let source = AsyncSource::new(Cursor::new(wav.clone())).await.unwrap();
+
+            let mut media = AsyncMedia::open(MediaOpener::new(), source).await.unwrap();
+            assert!(media.media().is_seekable);
+
+            let buf = media.next_buffer().await.unwrap();
+            assert_eq!(buf.chan(0), &[0.0, 0.5, -0.5]);
+            assert!(media.next_buffer().await.is_err());
+
+            // Unseekable source.
+            let source = AsyncReadOnlySource::new(Cursor::new(wav));
+
+            let mut media = AsyncMedia::open(MediaOpener::new(), source).await.unwrap();
+            assert!(!media.media().is_seekable);
+
+            let packet = media.next_packet().await.unwrap();
+            assert_eq!(packet.dur(), 3);
+        });
+    }
+}
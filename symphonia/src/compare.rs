@@ -0,0 +1,357 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The `compare` module summarizes the properties of opened media, and compares two summaries.
+//!
+//! This may be used, for example, to check if a transcode preserved the audio properties,
+//! metadata, and chapters of the original.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use symphonia_core::codecs::CodecType;
+use symphonia_core::formats::FormatReader;
+use symphonia_core::meta::{MetadataRevision, Tag};
+use symphonia_core::probe::ProbedMetadata;
+
+use crate::opener::OpenedMedia;
+
+/// A summary of the properties of a track.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrackSummary {
+    /// The codec of the track.
+    pub codec: CodecType,
+    /// The sample rate in hertz, if known.
+    pub sample_rate: Option<u32>,
+    /// The number of channels, if known.
+    pub n_channels: Option<usize>,
+    /// The number of bits per decoded sample, if known.
+    pub bits_per_sample: Option<u32>,
+    /// The duration of the track in seconds, if known.
+    pub duration: Option<f64>,
+    /// The language of the track, if known.
+    pub language: Option<String>,
+}
+
+/// A summary of a chapter, or cue.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ChapterSummary {
+    /// The start time of the chapter in seconds, if it could be determined.
+    pub start: Option<f64>,
+    /// The title of the chapter, if any.
+    pub title: Option<String>,
+}
+
+/// A summary of the properties of opened media.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MediaSummary {
+    /// The summaries of all tracks.
+    pub tracks: Vec<TrackSummary>,
+    /// The tags of the latest metadata revisions. Tags are keyed by their standard key if they
+    /// have one, or otherwise by their key. The values of repeated tags are joined with `"; "`.
+    pub tags: BTreeMap<String, String>,
+    /// The summaries of all chapters.
+    pub chapters: Vec<ChapterSummary>,
+}
+
+fn tag_key(tag: &Tag) -> String {
+    match tag.std_key {
+        Some(std_key) => format!("{:?}", std_key),
+        None => tag.key.clone(),
+    }
+}
+
+fn add_tags(tags: &mut BTreeMap<String, String>, rev: &MetadataRevision) {
+    // Tags of later revisions replace those of earlier revisions, but repeated tags within a
+    // revision are joined.
+    let mut rev_tags: BTreeMap<String, String> = BTreeMap::new();
+
+    for tag in rev.tags() {
+        let value = tag.value.to_string();
+
+        rev_tags
+            .entry(tag_key(tag))
+            .and_modify(|values| {
+                values.push_str("; ");
+                values.push_str(&value);
+            })
+            .or_insert(value);
+    }
+
+    tags.extend(rev_tags);
+}
+
+impl MediaSummary {
+    /// Summarize the provided `FormatReader`, and the metadata read while probing, if provided.
+    pub fn new(format: &mut dyn FormatReader, probed: Option<&mut ProbedMetadata>) -> Self {
+        let mut summary: MediaSummary = Default::default();
+
+        for track in format.tracks() {
+            let params = &track.codec_params;
+
+            let duration = match (params.time_base, params.n_frames) {
+                (Some(tb), Some(n_frames)) => {
+                    let time = tb.calc_time(n_frames);
+                    Some(time.seconds as f64 + time.frac)
+                }
+                _ => None,
+            };
+
+            summary.tracks.push(TrackSummary {
+                codec: params.codec,
+                sample_rate: params.sample_rate,
+                n_channels: params.channels.map(|channels| channels.count()),
+                bits_per_sample: params.bits_per_sample,
+                duration,
+                language: track.language.clone(),
+            });
+        }
+
+        // Cue timestamps are in frames from the start of the stream.
+        let sample_rate = format.default_track().and_then(|track| track.codec_params.sample_rate);
+
+        for cue in format.cues() {
+            let title = cue
+                .tags
+                .iter()
+                .find(|tag| tag_key(tag) == "TrackTitle" || tag.key.eq_ignore_ascii_case("title"))
+                .map(|tag| tag.value.to_string());
+
+            summary.chapters.push(ChapterSummary {
+                start: sample_rate.map(|rate| cue.start_ts as f64 / f64::from(rate)),
+                title,
+            });
+        }
+
+        if let Some(probed) = probed {
+            if let Some(mut metadata) = probed.get() {
+                if let Some(rev) = metadata.skip_to_latest() {
+                    add_tags(&mut summary.tags, rev);
+                }
+            }
+        }
+
+        if let Some(rev) = format.metadata().skip_to_latest() {
+            add_tags(&mut summary.tags, rev);
+        }
+
+        summary
+    }
+
+    /// Summarize opened media.
+    pub fn from_media(media: &mut OpenedMedia) -> Self {
+        MediaSummary::new(media.format.as_mut(), Some(&mut media.metadata))
+    }
+}
+
+/// A difference between two `MediaSummary`s.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Difference {
+    /// The number of tracks differs.
+    TrackCount { left: usize, right: usize },
+    /// A property of the track at the provided index differs.
+    Track { index: usize, property: &'static str, left: String, right: String },
+    /// A tag differs, or is only present on one side.
+    Tag { key: String, left: Option<String>, right: Option<String> },
+    /// The number of chapters differs.
+    ChapterCount { left: usize, right: usize },
+    /// A property of the chapter at the provided index differs.
+    Chapter { index: usize, property: &'static str, left: String, right: String },
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let opt = |value: &Option<String>| value.clone().unwrap_or_else(|| "<none>".to_string());
+
+        match self {
+            Difference::TrackCount { left, right } => {
+                write!(f, "track count: {} != {}", left, right)
+            }
+            Difference::Track { index, property, left, right } => {
+                write!(f, "track {} {}: {} != {}", index, property, left, right)
+            }
+            Difference::Tag { key, left, right } => {
+                write!(f, "tag {}: {} != {}", key, opt(left), opt(right))
+            }
+            Difference::ChapterCount { left, right } => {
+                write!(f, "chapter count: {} != {}", left, right)
+            }
+            Difference::Chapter { index, property, left, right } => {
+                write!(f, "chapter {} {}: {} != {}", index, property, left, right)
+            }
+        }
+    }
+}
+
+/// `CompareOptions` configures the comparison of two `MediaSummary`s.
+#[derive(Copy, Clone, Debug)]
+pub struct CompareOptions {
+    /// Compare the codecs of tracks. Disable this when comparing a transcode to its original.
+    /// Default: `true`.
+    pub compare_codec: bool,
+    /// The maximum difference, in seconds, between two durations or chapter start times for them
+    /// to be considered equal. Lossy codecs commonly pad the end of a track. Default: `0.05`.
+    pub time_tolerance: f64,
+}
+
+impl Default for CompareOptions {
+    fn default() -> Self {
+        CompareOptions { compare_codec: true, time_tolerance: 0.05 }
+    }
+}
+
+fn fmt_opt<T: fmt::Debug>(value: &Option<T>) -> String {
+    match value {
+        Some(value) => format!("{:?}", value),
+        None => "<unknown>".to_string(),
+    }
+}
+
+fn is_time_equal(left: Option<f64>, right: Option<f64>, tolerance: f64) -> bool {
+    match (left, right) {
+        (Some(left), Some(right)) => (left - right).abs() <= tolerance,
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+/// Compare two `MediaSummary`s and return all differences.
+pub fn compare(
+    left: &MediaSummary,
+    right: &MediaSummary,
+    options: &CompareOptions,
+) -> Vec<Difference> {
+    let mut diffs = Vec::new();
+
+    if left.tracks.len() != right.tracks.len() {
+        diffs.push(Difference::TrackCount { left: left.tracks.len(), right: right.tracks.len() });
+    }
+
+    for (index, (l, r)) in left.tracks.iter().zip(&right.tracks).enumerate() {
+        let mut track_diff = |property, left: String, right: String| {
+            if left != right {
+                diffs.push(Difference::Track { index, property, left, right });
+            }
+        };
+
+        if options.compare_codec {
+            track_diff("codec", l.codec.to_string(), r.codec.to_string());
+        }
+
+        track_diff("sample rate", fmt_opt(&l.sample_rate), fmt_opt(&r.sample_rate));
+        track_diff("channels", fmt_opt(&l.n_channels), fmt_opt(&r.n_channels));
+        track_diff("bits per sample", fmt_opt(&l.bits_per_sample), fmt_opt(&r.bits_per_sample));
+        track_diff("language", fmt_opt(&l.language), fmt_opt(&r.language));
+
+        if !is_time_equal(l.duration, r.duration, options.time_tolerance) {
+            diffs.push(Difference::Track {
+                index,
+                property: "duration",
+                left: fmt_opt(&l.duration),
+                right: fmt_opt(&r.duration),
+            });
+        }
+    }
+
+    let keys = left.tags.keys().chain(right.tags.keys()).collect::<BTreeSet<_>>();
+
+    for key in keys {
+        let l = left.tags.get(key);
+        let r = right.tags.get(key);
+
+        if l != r {
+            diffs.push(Difference::Tag { key: key.clone(), left: l.cloned(), right: r.cloned() });
+        }
+    }
+
+    if left.chapters.len() != right.chapters.len() {
+        diffs.push(Difference::ChapterCount {
+            left: left.chapters.len(),
+            right: right.chapters.len(),
+        });
+    }
+
+    for (index, (l, r)) in left.chapters.iter().zip(&right.chapters).enumerate() {
+        if !is_time_equal(l.start, r.start, options.time_tolerance) {
+            diffs.push(Difference::Chapter {
+                index,
+                property: "start",
+                left: fmt_opt(&l.start),
+                right: fmt_opt(&r.start),
+            });
+        }
+
+        if l.title != r.title {
+            diffs.push(Difference::Chapter {
+                index,
+                property: "title",
+                left: fmt_opt(&l.title),
+                right: fmt_opt(&r.title),
+            });
+        }
+    }
+
+    diffs
+}
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::codecs::{CODEC_TYPE_FLAC, CODEC_TYPE_VORBIS};
+
+    use super::{compare, ChapterSummary, CompareOptions, Difference, MediaSummary, TrackSummary};
+
+    #[test]
+    fn verify_compare() {
+        let mut original = MediaSummary::default();
+
+        original.tracks.push(TrackSummary {
+            codec: CODEC_TYPE_FLAC,
+            sample_rate: Some(44100),
+            n_channels: Some(2),
+            bits_per_sample: Some(16),
+            duration: Some(180.0),
+            language: None,
+        });
+        original.tags.insert("TrackTitle".to_string(), "Song".to_string());
+        original.tags.insert("Artist".to_string(), "Band".to_string());
+        original.chapters.push(ChapterSummary { start: Some(0.0), title: Some("One".into()) });
+
+        let mut transcode = original.clone();
+        transcode.tracks[0].codec = CODEC_TYPE_VORBIS;
+        transcode.tracks[0].bits_per_sample = None;
+        transcode.tracks[0].duration = Some(180.02);
+        transcode.tags.remove("Artist");
+        transcode.chapters[0].start = Some(1.0);
+
+        let options = CompareOptions { compare_codec: false, ..Default::default() };
+
+        assert_eq!(
+            compare(&original, &transcode, &options),
+            vec![
+                Difference::Track {
+                    index: 0,
+                    property: "bits per sample",
+                    left: "16".to_string(),
+                    right: "<unknown>".to_string(),
+                },
+                Difference::Tag {
+                    key: "Artist".to_string(),
+                    left: Some("Band".into()),
+                    right: None
+                },
+                Difference::Chapter {
+                    index: 0,
+                    property: "start",
+                    left: "0.0".to_string(),
+                    right: "1.0".to_string(),
+                },
+            ]
+        );
+
+        assert!(compare(&original, &original, &Default::default()).is_empty());
+    }
+}
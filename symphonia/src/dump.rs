@@ -0,0 +1,313 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The `dump` module describes the packets read by a `FormatReader` for debugging.
+//!
+//! Each packet is described by its position, size, and timing, the leading bytes of its data in
+//! hexadecimal, and the fields of its header if the codec is known. This is most useful when
+//! debugging interoperability issues with files written by other muxers.
+
+use std::fmt;
+use std::io;
+
+use symphonia_core::codecs::{
+    CodecType, CODEC_TYPE_FLAC, CODEC_TYPE_MP1, CODEC_TYPE_MP2, CODEC_TYPE_MP3, CODEC_TYPE_NULL,
+};
+use symphonia_core::errors::{Error, Result};
+use symphonia_core::formats::{FormatReader, Packet};
+
+/// `DumpOptions` configures how packets are described.
+#[derive(Copy, Clone, Debug)]
+pub struct DumpOptions {
+    /// The maximum number of leading bytes of each packet to dump as hexadecimal. Default: `16`.
+    pub hex_len: usize,
+    /// Parse the header fields of packets of known codecs. Default: `true`.
+    pub parse_headers: bool,
+}
+
+impl Default for DumpOptions {
+    fn default() -> Self {
+        DumpOptions { hex_len: 16, parse_headers: true }
+    }
+}
+
+/// A description of a single packet.
+#[derive(Clone, Debug, Default)]
+pub struct PacketDump {
+    /// The index of the packet in the order it was read.
+    pub index: u64,
+    /// The track ID of the packet.
+    pub track_id: u32,
+    /// The byte position of the packet in the media source stream, if known.
+    pub pos: Option<u64>,
+    /// The size of the packet in bytes.
+    pub size: usize,
+    /// The timestamp of the packet in `TimeBase` units.
+    pub ts: u64,
+    /// The duration of the packet in `TimeBase` units.
+    pub dur: u64,
+    /// The number of frames trimmed from the start of the packet.
+    pub trim_start: u32,
+    /// The number of frames trimmed from the end of the packet.
+    pub trim_end: u32,
+    /// The leading bytes of the packet.
+    pub head: Vec<u8>,
+    /// The parsed header fields of the packet as name and value pairs.
+    pub fields: Vec<(&'static str, String)>,
+}
+
+impl PacketDump {
+    /// Describe a packet of the provided codec.
+    pub fn new(index: u64, packet: &Packet, codec: CodecType, options: &DumpOptions) -> Self {
+        let buf = packet.buf();
+
+        let fields = if options.parse_headers {
+            match codec {
+                CODEC_TYPE_MP1 | CODEC_TYPE_MP2 | CODEC_TYPE_MP3 => mpeg_audio_fields(buf),
+                CODEC_TYPE_FLAC => flac_fields(buf),
+                _ => Vec::new(),
+            }
+        }
+        else {
+            Vec::new()
+        };
+
+        PacketDump {
+            index,
+            track_id: packet.track_id(),
+            pos: packet.pos(),
+            size: buf.len(),
+            ts: packet.ts(),
+            dur: packet.dur(),
+            trim_start: packet.trim_start(),
+            trim_end: packet.trim_end(),
+            head: buf[..buf.len().min(options.hex_len)].to_vec(),
+            fields,
+        }
+    }
+}
+
+impl fmt::Display for PacketDump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{} track={}", self.index, self.track_id)?;
+
+        match self.pos {
+            Some(pos) => write!(f, " pos={:#x}", pos)?,
+            None => write!(f, " pos=?")?,
+        }
+
+        write!(f, " size={} ts={} dur={}", self.size, self.ts, self.dur)?;
+
+        if self.trim_start > 0 || self.trim_end > 0 {
+            write!(f, " trim={}/{}", self.trim_start, self.trim_end)?;
+        }
+
+        for (name, value) in &self.fields {
+            write!(f, " {}={}", name, value)?;
+        }
+
+        if !self.head.is_empty() {
+            write!(f, " |")?;
+
+            for byte in &self.head {
+                write!(f, " {:02x}", byte)?;
+            }
+
+            if self.head.len() < self.size {
+                write!(f, " ..")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads all packets from the `FormatReader` and writes a description of each, one per line, to
+/// the provided writer. Returns the number of packets read at the end of the stream.
+pub fn dump_packets<W: io::Write>(
+    format: &mut dyn FormatReader,
+    out: &mut W,
+    options: &DumpOptions,
+) -> Result<u64> {
+    let mut index = 0;
+
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(Error::IoError(err)) if err.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(err) => return Err(err),
+        };
+
+        let codec = format
+            .tracks()
+            .iter()
+            .find(|track| track.id == packet.track_id())
+            .map_or(CODEC_TYPE_NULL, |track| track.codec_params.codec);
+
+        writeln!(out, "{}", PacketDump::new(index, &packet, codec, options))?;
+
+        index += 1;
+    }
+
+    Ok(index)
+}
+
+/// Parses the fields of a MPEG audio frame header.
+fn mpeg_audio_fields(buf: &[u8]) -> Vec<(&'static str, String)> {
+    const BITRATES: [[u16; 15]; 5] = [
+        // MPEG1 layer 1, 2, and 3.
+        [0, 32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448],
+        [0, 32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384],
+        [0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320],
+        // MPEG2 and 2.5 layer 1, and layers 2 and 3.
+        [0, 32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256],
+        [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160],
+    ];
+
+    const SAMPLE_RATES: [u32; 3] = [44100, 48000, 32000];
+
+    if buf.len() < 4 || buf[0] != 0xff || buf[1] & 0xe0 != 0xe0 {
+        return vec![("header", "invalid".to_string())];
+    }
+
+    let header = u32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]);
+
+    let (version, sample_rate_div) = match (header >> 19) & 0x3 {
+        0b00 => ("2.5", 4),
+        0b10 => ("2", 2),
+        0b11 => ("1", 1),
+        _ => return vec![("version", "reserved".to_string())],
+    };
+
+    let layer = match (header >> 17) & 0x3 {
+        0b01 => 3,
+        0b10 => 2,
+        0b11 => 1,
+        _ => return vec![("layer", "reserved".to_string())],
+    };
+
+    let bitrate_idx = ((header >> 12) & 0xf) as usize;
+    let sample_rate_idx = ((header >> 10) & 0x3) as usize;
+
+    let bitrate = match bitrate_idx {
+        0 => "free".to_string(),
+        15 => "bad".to_string(),
+        idx => {
+            let table = match (version, layer) {
+                ("1", layer) => layer - 1,
+                (_, 1) => 3,
+                _ => 4,
+            };
+            format!("{}k", BITRATES[table][idx])
+        }
+    };
+
+    let sample_rate = match SAMPLE_RATES.get(sample_rate_idx) {
+        Some(rate) => (rate / sample_rate_div).to_string(),
+        None => "reserved".to_string(),
+    };
+
+    let mode = match (header >> 6) & 0x3 {
+        0b00 => "stereo",
+        0b01 => "joint-stereo",
+        0b10 => "dual-mono",
+        _ => "mono",
+    };
+
+    vec![
+        ("mpeg", version.to_string()),
+        ("layer", layer.to_string()),
+        ("bitrate", bitrate),
+        ("rate", sample_rate),
+        ("mode", mode.to_string()),
+        ("crc", ((header >> 16) & 0x1 == 0).to_string()),
+        ("padding", ((header >> 9) & 0x1 == 1).to_string()),
+    ]
+}
+
+/// Parses the fixed fields of a FLAC frame header.
+fn flac_fields(buf: &[u8]) -> Vec<(&'static str, String)> {
+    const SAMPLE_RATES: [u32; 12] =
+        [0, 88200, 176400, 192000, 8000, 16000, 22050, 24000, 32000, 44100, 48000, 96000];
+
+    const BITS_PER_SAMPLE: [u32; 8] = [0, 8, 12, 0, 16, 20, 24, 32];
+
+    if buf.len() < 4 || buf[0] != 0xff || buf[1] & 0xfe != 0xf8 {
+        return vec![("header", "invalid".to_string())];
+    }
+
+    let blocking = if buf[1] & 0x1 == 0 { "fixed" } else { "variable" };
+
+    let block_size = match buf[2] >> 4 {
+        0 => "reserved".to_string(),
+        1 => "192".to_string(),
+        n @ 2..=5 => (576 << (n - 2)).to_string(),
+        6 | 7 => "trailing".to_string(),
+        n => (256 << (n - 8)).to_string(),
+    };
+
+    let sample_rate = match usize::from(buf[2] & 0xf) {
+        0 => "streaminfo".to_string(),
+        idx @ 1..=11 => SAMPLE_RATES[idx].to_string(),
+        15 => "invalid".to_string(),
+        _ => "trailing".to_string(),
+    };
+
+    let channels = match buf[3] >> 4 {
+        n @ 0..=7 => format!("{}", n + 1),
+        8 => "left-side".to_string(),
+        9 => "right-side".to_string(),
+        10 => "mid-side".to_string(),
+        _ => "reserved".to_string(),
+    };
+
+    let bits_per_sample = match usize::from((buf[3] >> 1) & 0x7) {
+        0 => "streaminfo".to_string(),
+        3 => "reserved".to_string(),
+        idx => BITS_PER_SAMPLE[idx].to_string(),
+    };
+
+    vec![
+        ("blocking", blocking.to_string()),
+        ("block", block_size),
+        ("rate", sample_rate),
+        ("channels", channels),
+        ("bps", bits_per_sample),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::codecs::{CODEC_TYPE_FLAC, CODEC_TYPE_MP3};
+    use symphonia_core::formats::Packet;
+
+    use super::{DumpOptions, PacketDump};
+
+    #[test]
+    fn verify_packet_dump() {
+        let options = DumpOptions { hex_len: 4, ..Default::default() };
+
+        // MPEG1 layer 3, 128 kbps, 44.1 kHz, joint stereo, no CRC.
+        let mp3 =
+            Packet::new_from_slice(0, 1152, 1152, &[0xff, 0xfb, 0x90, 0x64, 0x00]).with_pos(0x1a2);
+
+        assert_eq!(
+            PacketDump::new(1, &mp3, CODEC_TYPE_MP3, &options).to_string(),
+            "#1 track=0 pos=0x1a2 size=5 ts=1152 dur=1152 mpeg=1 layer=3 bitrate=128k rate=44100 \
+             mode=joint-stereo crc=false padding=false | ff fb 90 64 .."
+        );
+
+        // Fixed blocking, 4096 frames, 44.1 kHz, mid-side stereo, 16 bits per sample.
+        let flac = Packet::new_from_slice(0, 0, 4096, &[0xff, 0xf8, 0xc9, 0xa8]);
+
+        assert_eq!(
+            PacketDump::new(0, &flac, CODEC_TYPE_FLAC, &options).to_string(),
+            "#0 track=0 pos=? size=4 ts=0 dur=4096 blocking=fixed block=4096 rate=44100 \
+             channels=mid-side bps=16 | ff f8 c9 a8"
+        );
+    }
+}
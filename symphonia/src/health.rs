@@ -0,0 +1,302 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The `health` module provides a watchdog that reports the health of a streamed media source.
+//!
+//! A [`Watchdog`] measures how quickly a media source delivers data, and how quickly the decoded
+//! audio is consumed. A player of a live or network stream may poll the watchdog for
+//! [`HealthEvent`]s to raise an alarm before the stream audibly drops out.
+
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use symphonia_core::io::MediaSource;
+
+/// An event reported by a [`Watchdog`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum HealthEvent {
+    /// Less audio was decoded than the wall-clock time that elapsed. If the audio is played in
+    /// real-time, the playback buffer is draining and will eventually underrun.
+    UnderrunRisk {
+        /// The seconds of audio decoded per second of wall-clock time.
+        realtime_factor: f64,
+    },
+    /// The rate data is read from the source dropped significantly below its average rate.
+    BitrateDrop {
+        /// The number of bytes read per second in the last window.
+        bytes_per_sec: u64,
+        /// The average number of bytes read per second over all previous windows.
+        average_bytes_per_sec: u64,
+    },
+    /// A single read of the source blocked for longer than the stall threshold.
+    Stalled {
+        /// The duration the read blocked for.
+        duration: Duration,
+    },
+    /// More resynchronizations, or decode errors, occurred in the last window than the limit.
+    ResyncSpike {
+        /// The number of resynchronizations in the last window.
+        count: u32,
+    },
+}
+
+/// `WatchdogOptions` configures the thresholds of a [`Watchdog`].
+#[derive(Copy, Clone, Debug)]
+pub struct WatchdogOptions {
+    /// The duration of the window over which rates are measured. The default is 5 seconds.
+    pub window: Duration,
+    /// A read of the source blocking for longer than this duration is reported as a stall. The
+    /// default is 1 second.
+    pub stall_threshold: Duration,
+    /// An underrun risk is reported if fewer seconds of audio than this factor, per second of
+    /// wall-clock time, are decoded in a window. The default is 0.95.
+    pub min_realtime_factor: f64,
+    /// A bitrate drop is reported if the read rate of a window falls below this fraction of the
+    /// average read rate. The default is 0.5.
+    pub min_bitrate_fraction: f64,
+    /// A resync spike is reported if there are more resynchronizations than this in a window.
+    /// The default is 3.
+    pub max_resyncs: u32,
+}
+
+impl Default for WatchdogOptions {
+    fn default() -> Self {
+        WatchdogOptions {
+            window: Duration::from_secs(5),
+            stall_threshold: Duration::from_secs(1),
+            min_realtime_factor: 0.95,
+            min_bitrate_fraction: 0.5,
+            max_resyncs: 3,
+        }
+    }
+}
+
+struct State {
+    options: WatchdogOptions,
+    window_start: Option<Instant>,
+    /// The number of bytes read in the current window.
+    bytes: u64,
+    /// The seconds of audio decoded in the current window.
+    decoded: f64,
+    /// The number of resynchronizations in the current window.
+    resyncs: u32,
+    /// The total number of bytes read, and the total duration, of all previous windows.
+    total_bytes: u64,
+    total_time: Duration,
+    events: Vec<HealthEvent>,
+}
+
+impl State {
+    fn update(&mut self, now: Instant) {
+        let start = *self.window_start.get_or_insert(now);
+        let elapsed = now.saturating_duration_since(start);
+
+        if elapsed < self.options.window {
+            return;
+        }
+
+        let secs = elapsed.as_secs_f64();
+
+        // Only measure consumption if the consumer decoded audio during the window, otherwise the
+        // stream may simply be paused.
+        if self.decoded > 0.0 {
+            let realtime_factor = self.decoded / secs;
+
+            if realtime_factor < self.options.min_realtime_factor {
+                self.events.push(HealthEvent::UnderrunRisk { realtime_factor });
+            }
+        }
+
+        let bytes_per_sec = (self.bytes as f64 / secs) as u64;
+
+        if self.total_time > Duration::from_secs(0) {
+            let average = self.total_bytes as f64 / self.total_time.as_secs_f64();
+
+            if (bytes_per_sec as f64) < self.options.min_bitrate_fraction * average {
+                self.events.push(HealthEvent::BitrateDrop {
+                    bytes_per_sec,
+                    average_bytes_per_sec: average as u64,
+                });
+            }
+        }
+
+        if self.resyncs > self.options.max_resyncs {
+            self.events.push(HealthEvent::ResyncSpike { count: self.resyncs });
+        }
+
+        self.total_bytes += self.bytes;
+        self.total_time += elapsed;
+
+        self.window_start = Some(now);
+        self.bytes = 0;
+        self.decoded = 0.0;
+        self.resyncs = 0;
+    }
+
+    fn record_read(&mut self, n_bytes: usize, started: Instant, now: Instant) {
+        let duration = now.saturating_duration_since(started);
+
+        if duration > self.options.stall_threshold {
+            self.events.push(HealthEvent::Stalled { duration });
+        }
+
+        self.update(now);
+        self.bytes += n_bytes as u64;
+    }
+
+    fn record_decoded(&mut self, n_frames: usize, sample_rate: u32, now: Instant) {
+        self.update(now);
+
+        if sample_rate > 0 {
+            self.decoded += n_frames as f64 / f64::from(sample_rate);
+        }
+    }
+
+    fn record_resync(&mut self, now: Instant) {
+        self.update(now);
+        self.resyncs += 1;
+    }
+}
+
+/// `Watchdog` monitors the health of a media source and the consumption of its decoded audio.
+///
+/// Wrap the media source with [`Watchdog::watch`], and report decoded audio and errors to the
+/// watchdog as they occur. Rates are measured over a window, and events are reported at the end
+/// of each window, except for stalls which are reported once the stalled read returns.
+///
+/// A `Watchdog` is a cheaply cloneable handle, and may be shared between threads.
+#[derive(Clone)]
+pub struct Watchdog {
+    state: Arc<Mutex<State>>,
+}
+
+impl Watchdog {
+    /// Instantiate a new `Watchdog` with the provided options.
+    pub fn new(options: WatchdogOptions) -> Self {
+        let state = State {
+            options,
+            window_start: None,
+            bytes: 0,
+            decoded: 0.0,
+            resyncs: 0,
+            total_bytes: 0,
+            total_time: Duration::from_secs(0),
+            events: Vec::new(),
+        };
+
+        Watchdog { state: Arc::new(Mutex::new(state)) }
+    }
+
+    /// Wrap the provided `MediaSource` such that all reads are measured by this watchdog.
+    pub fn watch<S: MediaSource>(&self, source: S) -> WatchedSource<S> {
+        WatchedSource { inner: source, watchdog: self.clone() }
+    }
+
+    /// Report that `n_frames` of audio at the provided sample rate were decoded.
+    pub fn record_decoded(&self, n_frames: usize, sample_rate: u32) {
+        self.state.lock().unwrap().record_decoded(n_frames, sample_rate, Instant::now());
+    }
+
+    /// Report a resynchronization of the stream. Decode errors should be reported with this
+    /// function.
+    pub fn record_resync(&self) {
+        self.state.lock().unwrap().record_resync(Instant::now());
+    }
+
+    /// Take all events reported since the last call.
+    pub fn take_events(&self) -> Vec<HealthEvent> {
+        std::mem::take(&mut self.state.lock().unwrap().events)
+    }
+}
+
+impl Default for Watchdog {
+    fn default() -> Self {
+        Watchdog::new(Default::default())
+    }
+}
+
+/// `WatchedSource` is a `MediaSource` that reports all reads to a [`Watchdog`].
+pub struct WatchedSource<S> {
+    inner: S,
+    watchdog: Watchdog,
+}
+
+impl<S> WatchedSource<S> {
+    /// Unwraps this `WatchedSource<S>`, returning the underlying source.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: MediaSource> MediaSource for WatchedSource<S> {
+    fn is_seekable(&self) -> bool {
+        self.inner.is_seekable()
+    }
+
+    fn byte_len(&self) -> Option<u64> {
+        self.inner.byte_len()
+    }
+}
+
+impl<S: io::Read> io::Read for WatchedSource<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let started = Instant::now();
+        let n_bytes = self.inner.read(buf)?;
+
+        self.watchdog.state.lock().unwrap().record_read(n_bytes, started, Instant::now());
+
+        Ok(n_bytes)
+    }
+}
+
+impl<S: io::Seek> io::Seek for WatchedSource<S> {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{HealthEvent, Watchdog, WatchdogOptions};
+
+    #[test]
+    fn verify_watchdog_events() {
+        let watchdog = Watchdog::new(WatchdogOptions::default());
+        let mut state = watchdog.state.lock().unwrap();
+
+        let t0 = Instant::now();
+        let at = |secs: u64| t0 + Duration::from_secs(secs);
+
+        // A healthy window: 5 seconds of audio decoded in 5 seconds, at 16000 bytes per second.
+        state.record_read(80_000, at(0), at(0));
+        state.record_decoded(5 * 44100, 44100, at(1));
+        state.record_read(0, at(5), at(5));
+        assert!(state.events.is_empty());
+
+        // An unhealthy window: a stalled read, a lower bitrate, 2.5 seconds of audio decoded, and
+        // 4 resyncs.
+        state.record_read(30_000, at(6), at(8));
+        state.record_decoded(2 * 44100 + 22050, 44100, at(8));
+        for _ in 0..4 {
+            state.record_resync(at(9));
+        }
+        state.record_read(0, at(10), at(10));
+
+        assert_eq!(
+            state.events,
+            vec![
+                HealthEvent::Stalled { duration: Duration::from_secs(2) },
+                HealthEvent::UnderrunRisk { realtime_factor: 0.5 },
+                HealthEvent::BitrateDrop { bytes_per_sec: 6000, average_bytes_per_sec: 16000 },
+                HealthEvent::ResyncSpike { count: 4 },
+            ]
+        );
+    }
+}
@@ -0,0 +1,672 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The `icy` module provides a `MediaSource` for SHOUTcast/Icecast internet radio streams.
+//!
+//! These servers interleave a metadata block, containing the title of the current song, into the
+//! audio stream every `icy-metaint` bytes. [`IcySource`] strips the metadata blocks from the
+//! stream, and [`IcyReader`] pushes each update onto the metadata log of the format reader as a
+//! new `MetadataRevision`.
+
+use std::io;
+use std::io::{BufRead, Read};
+#[cfg(feature = "icy-http")]
+use std::net::{Ipv6Addr, TcpStream};
+use std::sync::{Arc, Mutex};
+
+use symphonia_core::errors::{unsupported_error, Result};
+use symphonia_core::formats::prelude::*;
+use symphonia_core::io::{MediaSource, MediaSourceStream};
+use symphonia_core::meta::{Metadata, MetadataLog, MetadataRevision};
+use symphonia_metadata::icy::{parse_icy_fields, read_icy_headers, read_icy_metadata};
+
+/// The maximum number of redirects followed by `IcySource::connect`.
+#[cfg(feature = "icy-http")]
+const MAX_REDIRECTS: u32 = 5;
+
+/// The maximum length of the status line and headers of a response, in bytes.
+const MAX_HEAD_LEN: usize = 8 * 1024;
+
+/// The maximum number of header lines in a response.
+const MAX_HEADER_LINES: usize = 100;
+
+/// A handle to the metadata revisions read by an [`IcySource`] that have not yet been pushed onto
+/// the metadata log of an [`IcyReader`].
+#[derive(Clone, Default)]
+pub struct IcyMetadata {
+    pending: Arc<Mutex<Vec<MetadataRevision>>>,
+}
+
+impl IcyMetadata {
+    fn push(&self, rev: MetadataRevision) {
+        self.pending.lock().unwrap().push(rev);
+    }
+
+    fn take(&self) -> Vec<MetadataRevision> {
+        std::mem::take(&mut *self.pending.lock().unwrap())
+    }
+}
+
+/// The status and headers of an HTTP, or ICY, response.
+#[derive(Debug, Default)]
+pub struct ResponseHead {
+    /// The status code.
+    pub status: u16,
+    /// The headers in the order received. Header names are lowercase.
+    pub headers: Vec<(String, String)>,
+}
+
+impl ResponseHead {
+    /// Gets the value of the first header with the provided lowercase name.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+
+    /// Reads the status line, and headers, of a response. An error is returned if the response
+    /// head is longer than 8 KiB, or has more than 100 header lines.
+    pub fn read<R: BufRead>(reader: &mut R) -> io::Result<ResponseHead> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "icy: malformed response");
+        let too_long = || io::Error::new(io::ErrorKind::InvalidData, "icy: response head too long");
+
+        let mut remaining = MAX_HEAD_LEN;
+
+        // Reads a line without reading past the end of the maximum response head length.
+        let mut read_line = |line: &mut String| -> io::Result<usize> {
+            line.clear();
+
+            let len = reader.by_ref().take(remaining as u64).read_line(line)?;
+            remaining -= len;
+
+            if remaining == 0 && !line.ends_with('\n') {
+                return Err(too_long());
+            }
+
+            Ok(len)
+        };
+
+        let mut line = String::new();
+
+        read_line(&mut line)?;
+
+        // The status line is "HTTP/1.x <code> <reason>", or "ICY <code> <reason>" for SHOUTcast v1
+        // servers.
+        let status = line.split_whitespace().nth(1).and_then(|code| code.parse().ok());
+
+        let mut head = ResponseHead { status: status.ok_or_else(invalid)?, headers: Vec::new() };
+
+        for _ in 0..=MAX_HEADER_LINES {
+            if read_line(&mut line)? == 0 {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "icy: truncated headers"));
+            }
+
+            let line = line.trim_end();
+
+            if line.is_empty() {
+                return Ok(head);
+            }
+
+            if let Some(colon) = line.find(':') {
+                let key = line[..colon].trim().to_ascii_lowercase();
+                let value = line[colon + 1..].trim().to_string();
+                head.headers.push((key, value));
+            }
+        }
+
+        Err(io::Error::new(io::ErrorKind::InvalidData, "icy: too many headers"))
+    }
+}
+
+/// `IcySource` reads an ICY stream, strips the interleaved metadata blocks, and implements
+/// `MediaSource` for the remaining audio data.
+///
+/// An `IcySource` may wrap the body of a response from any HTTP client, including those that
+/// support HTTPS, or connect to an `http://` stream with [`IcySource::connect`]. The request must
+/// include the header `Icy-MetaData: 1` for the server to interleave metadata.
+///
+/// The metadata read from the stream is pushed onto the metadata log of an [`IcyReader`] created
+/// with the handle returned by [`IcySource::metadata`].
+pub struct IcySource<R: Read> {
+    inner: R,
+    metaint: usize,
+    /// The number of audio bytes remaining before the next metadata block.
+    remaining: usize,
+    /// The stream title of the last metadata block, used to ignore repeated updates.
+    last_title: Option<String>,
+    content_type: Option<String>,
+    metadata: IcyMetadata,
+}
+
+impl<R: Read> IcySource<R> {
+    /// Instantiate a new `IcySource` over the body of a response. The metadata interval is the
+    /// value of the `icy-metaint` response header, or 0 if it was not present.
+    pub fn new(inner: R, metaint: usize) -> Self {
+        IcySource {
+            inner,
+            metaint,
+            remaining: metaint,
+            last_title: None,
+            content_type: None,
+            metadata: Default::default(),
+        }
+    }
+
+    /// Instantiate a new `IcySource` over the body of a response, using the response headers to
+    /// find the metadata interval and content type. The station information in the headers is
+    /// the first metadata revision.
+    pub fn with_head(inner: R, head: &ResponseHead) -> Self {
+        let metaint = head.header("icy-metaint").and_then(|value| value.parse().ok()).unwrap_or(0);
+
+        let mut source = IcySource::new(inner, metaint);
+
+        source.content_type = head.header("content-type").map(|value| value.to_string());
+
+        let headers = head.headers.iter().map(|(key, value)| (key.as_str(), value.as_str()));
+
+        if let Some(rev) = read_icy_headers(headers) {
+            source.metadata.push(rev);
+        }
+
+        source
+    }
+
+    /// Gets the content type of the stream, if known. This may be used as a hint to the probe.
+    pub fn content_type(&self) -> Option<&str> {
+        self.content_type.as_deref()
+    }
+
+    /// Gets a handle to the metadata revisions read from the stream, to be passed to
+    /// [`IcyReader::new`].
+    pub fn metadata(&self) -> IcyMetadata {
+        self.metadata.clone()
+    }
+
+    /// Unwraps this `IcySource<R>`, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Reads a metadata block. Returns false if the end of the stream was reached instead.
+    fn read_metadata_block(&mut self) -> io::Result<bool> {
+        let mut len = [0];
+
+        if self.inner.read(&mut len)? == 0 {
+            return Ok(false);
+        }
+
+        // The length of the block is in units of 16 bytes. A length of 0 means that the metadata
+        // is unchanged.
+        if len[0] > 0 {
+            let mut block = vec![0; 16 * usize::from(len[0])];
+            self.inner.read_exact(&mut block)?;
+
+            let title = parse_icy_fields(&block)
+                .into_iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case("streamtitle"))
+                .map(|(_, value)| value);
+
+            // Servers commonly resend the same metadata. Only push changes.
+            if title.is_none() || title != self.last_title {
+                if let Some(rev) = read_icy_metadata(&block) {
+                    self.metadata.push(rev);
+                }
+                self.last_title = title;
+            }
+        }
+
+        self.remaining = self.metaint;
+
+        Ok(true)
+    }
+}
+
+#[cfg(feature = "icy-http")]
+impl IcySource<io::BufReader<TcpStream>> {
+    /// Connect to the `http://` stream at the provided URL, requesting interleaved metadata.
+    /// Redirects, including relative redirects, are followed.
+    ///
+    /// Requires the `icy-http` feature flag.
+    pub fn connect(url: &str) -> io::Result<Self> {
+        let mut url = url.to_string();
+
+        for _ in 0..=MAX_REDIRECTS {
+            let (host, port, path) = parse_http_url(&url)?;
+
+            let mut stream = TcpStream::connect((host.as_str(), port))?;
+
+            // IPv6 addresses are bracketed in the host header.
+            let host = if host.contains(':') { format!("[{}]", host) } else { host };
+
+            let request = format!(
+                "GET {} HTTP/1.0\r\nHost: {}\r\nUser-Agent: symphonia\r\nAccept: */*\r\n\
+                 Icy-MetaData: 1\r\nConnection: close\r\n\r\n",
+                path, host
+            );
+
+            io::Write::write_all(&mut stream, request.as_bytes())?;
+
+            let mut reader = io::BufReader::new(stream);
+            let head = ResponseHead::read(&mut reader)?;
+
+            match head.status {
+                200..=299 => return Ok(IcySource::with_head(reader, &head)),
+                301 | 302 | 303 | 307 | 308 => match head.header("location") {
+                    Some(location) => url = resolve_url(&url, location),
+                    None => break,
+                },
+                status => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::Other,
+                        format!("icy: server responded with status {}", status),
+                    ))
+                }
+            }
+        }
+
+        Err(io::Error::new(io::ErrorKind::Other, "icy: too many redirects"))
+    }
+}
+
+/// Resolves the URL of a redirect location against the URL of the request. The location may be an
+/// absolute URL, a network-path reference (`//host/path`), an absolute path, or a relative path.
+#[cfg(feature = "icy-http")]
+fn resolve_url(base: &str, location: &str) -> String {
+    if location.contains("://") {
+        return location.to_string();
+    }
+
+    if let Some(rest) = location.strip_prefix("//") {
+        return format!("http://{}", rest);
+    }
+
+    // The scheme and authority of the base URL.
+    let authority_start = base.find("://").map_or(0, |pos| pos + 3);
+    let path_start =
+        base[authority_start..].find('/').map_or(base.len(), |pos| authority_start + pos);
+
+    if location.starts_with('/') {
+        return format!("{}{}", &base[..path_start], location);
+    }
+
+    // A relative path replaces the last segment of the base path, ignoring any query.
+    let path = &base[path_start..];
+    let path = &path[..path.find(|c| c == '?' || c == '#').unwrap_or(path.len())];
+    let dir = &path[..path.rfind('/').map_or(0, |pos| pos + 1)];
+
+    if dir.is_empty() {
+        format!("{}/{}", &base[..path_start], location)
+    }
+    else {
+        format!("{}{}{}", &base[..path_start], dir, location)
+    }
+}
+
+/// Splits an `http://` URL into its host, port, and path. The host may be an IPv6 address in
+/// brackets, which are removed.
+#[cfg(feature = "icy-http")]
+fn parse_http_url(url: &str) -> io::Result<(String, u16, String)> {
+    let invalid =
+        || io::Error::new(io::ErrorKind::InvalidInput, "icy: only http urls are supported");
+
+    let rest = url.strip_prefix("http://").ok_or_else(invalid)?;
+
+    let (authority, path) = match rest.find('/') {
+        Some(slash) => (&rest[..slash], &rest[slash..]),
+        None => (rest, "/"),
+    };
+
+    let (host, port) = match authority.strip_prefix('[') {
+        Some(rest) => {
+            let end = rest.find(']').ok_or_else(invalid)?;
+
+            if rest[..end].parse::<Ipv6Addr>().is_err() {
+                return Err(invalid());
+            }
+
+            (&rest[..end], &rest[end + 1..])
+        }
+        None => match authority.find(':') {
+            Some(colon) => (&authority[..colon], &authority[colon..]),
+            None => (authority, ""),
+        },
+    };
+
+    let port = match port {
+        "" => 80,
+        port => port.strip_prefix(':').and_then(|port| port.parse().ok()).ok_or_else(invalid)?,
+    };
+
+    if host.is_empty() {
+        return Err(invalid());
+    }
+
+    Ok((host.to_string(), port, path.to_string()))
+}
+
+impl<R: Read> Read for IcySource<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.metaint == 0 {
+            return self.inner.read(buf);
+        }
+
+        if self.remaining == 0 && !self.read_metadata_block()? {
+            return Ok(0);
+        }
+
+        let len = buf.len().min(self.remaining);
+        let n_read = self.inner.read(&mut buf[..len])?;
+
+        self.remaining -= n_read;
+
+        Ok(n_read)
+    }
+}
+
+impl<R: Read> io::Seek for IcySource<R> {
+    fn seek(&mut self, _: io::SeekFrom) -> io::Result<u64> {
+        Err(io::Error::new(io::ErrorKind::Other, "source does not support seeking"))
+    }
+}
+
+impl<R: Read + Send + Sync> MediaSource for IcySource<R> {
+    fn is_seekable(&self) -> bool {
+        false
+    }
+
+    fn byte_len(&self) -> Option<u64> {
+        None
+    }
+}
+
+/// `IcyReader` wraps the `FormatReader` of an ICY stream, and pushes the metadata read by the
+/// [`IcySource`] of the stream onto its metadata log.
+///
+/// The metadata log contains the metadata revisions of the wrapped reader, and of the stream, in
+/// the order that they were read.
+pub struct IcyReader {
+    format: Box<dyn FormatReader>,
+    icy: IcyMetadata,
+    metadata: MetadataLog,
+    /// If true, the current revision of the wrapped reader was already pushed onto the log.
+    pushed_current: bool,
+}
+
+impl IcyReader {
+    /// Instantiate a new `IcyReader` over the format reader of an `IcySource`, using the handle
+    /// returned by [`IcySource::metadata`].
+    pub fn new(format: Box<dyn FormatReader>, icy: IcyMetadata) -> Self {
+        let mut reader =
+            IcyReader { format, icy, metadata: Default::default(), pushed_current: false };
+        reader.update_metadata();
+        reader
+    }
+
+    /// Pushes the new metadata revisions of the wrapped reader, and of the stream, onto the log.
+    fn update_metadata(&mut self) {
+        let mut inner = self.format.metadata();
+
+        // The log of the wrapped reader always retains its current revision, so track if it was
+        // already pushed.
+        while let Some(rev) = inner.pop() {
+            if !self.pushed_current {
+                self.metadata.push(rev);
+            }
+            self.pushed_current = false;
+        }
+
+        if !self.pushed_current {
+            if let Some(rev) = inner.current() {
+                self.metadata.push(rev.clone());
+                self.pushed_current = true;
+            }
+        }
+
+        for rev in self.icy.take() {
+            self.metadata.push(rev);
+        }
+    }
+}
+
+impl FormatReader for IcyReader {
+    fn try_new(_: MediaSourceStream, _: &FormatOptions) -> Result<Self> {
+        unsupported_error("icy: an IcyReader must wrap an existing format reader")
+    }
+
+    fn cues(&self) -> &[Cue] {
+        self.format.cues()
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.update_metadata();
+        self.metadata.metadata()
+    }
+
+    fn seek(&mut self, mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        self.format.seek(mode, to)
+    }
+
+    fn tracks(&self) -> &[Track] {
+        self.format.tracks()
+    }
+
+    fn programs(&self) -> &[Program] {
+        self.format.programs()
+    }
+
+    fn take_discontinuities(&mut self) -> Vec<TimestampDiscontinuity> {
+        self.format.take_discontinuities()
+    }
+
+    fn default_track(&self) -> Option<&Track> {
+        self.format.default_track()
+    }
+
+    fn default_track_for_language(&self, language: &str) -> Option<&Track> {
+        self.format.default_track_for_language(language)
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        let packet = self.format.next_packet()?;
+        self.update_metadata();
+        Ok(packet)
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.format.into_inner()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, ErrorKind, Read};
+
+    use symphonia_core::meta::StandardTagKey;
+
+    use super::{IcySource, ResponseHead};
+
+    fn make_block(title: &str) -> Vec<u8> {
+        let mut block = format!("StreamTitle='{}';", title).into_bytes();
+        block.resize(32, 0);
+        block
+    }
+
+    #[test]
+    fn verify_icy_source() {
+        let block = make_block("Hello");
+
+        let mut stream = Vec::new();
+        stream.extend_from_slice(b"abcd");
+        stream.push(2);
+        stream.extend_from_slice(&block);
+        stream.extend_from_slice(b"efgh");
+        stream.push(0);
+        stream.extend_from_slice(b"ijkl");
+        stream.push(2);
+        stream.extend_from_slice(&block);
+        stream.extend_from_slice(b"m");
+
+        let response = b"ICY 200 OK\r\nicy-name: Radio\r\nicy-metaint: 4\r\n\
+                         icy-url: http://radio.example/\r\ncontent-type: audio/mpeg\r\n\r\n";
+
+        let mut reader = Cursor::new(&response[..]);
+        let head = ResponseHead::read(&mut reader).unwrap();
+
+        let mut source = IcySource::with_head(Cursor::new(stream), &head);
+        let metadata = source.metadata();
+
+        assert_eq!(source.content_type(), Some("audio/mpeg"));
+
+        let mut data = Vec::new();
+        source.read_to_end(&mut data).unwrap();
+        assert_eq!(data, b"abcdefghijklm");
+
+        let revs = metadata.take();
+        assert_eq!(revs.len(), 2);
+
+        // The station name has no standard tag key, and the station URL is the web address of
+        // the station.
+        let station = &revs[0];
+        assert_eq!(station.tags()[0].key, "icy-name");
+        assert_eq!(station.tags()[0].std_key, None);
+        assert_eq!(station.tags()[0].value.to_string(), "Radio");
+        assert_eq!(station.tags()[1].std_key, Some(StandardTagKey::UrlInternetRadio));
+        assert_eq!(station.tags()[1].value.to_string(), "http://radio.example/");
+
+        // The repeated title is only pushed once.
+        assert_eq!(revs[1].tags()[0].value.to_string(), "Hello");
+    }
+
+    #[cfg(feature = "wav")]
+    #[test]
+    fn verify_icy_reader() {
+        use symphonia_core::formats::FormatReader;
+        use symphonia_core::io::MediaSourceStream;
+
+        use crate::default::formats::WavReader;
+
+        use super::IcyReader;
+
+        // A WAVE file with 16-bit mono PCM audio, a title tag, and 1024 frames.
+        let mut wav = Vec::new();
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(36u32 + 24 + 2048).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        wav.extend_from_slice(&[1, 0, 1, 0]);
+        wav.extend_from_slice(&8000u32.to_le_bytes());
+        wav.extend_from_slice(&16000u32.to_le_bytes());
+        wav.extend_from_slice(&[2, 0, 16, 0]);
+        wav.extend_from_slice(b"LIST");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        wav.extend_from_slice(b"INFOINAM");
+        wav.extend_from_slice(&4u32.to_le_bytes());
+        wav.extend_from_slice(b"Song");
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&2048u32.to_le_bytes());
+        wav.resize(44 + 24 + 2048, 0);
+
+        // Interleave a metadata block every 512 bytes, changing the title once.
+        let mut stream = Vec::new();
+        for (i, chunk) in wav.chunks(512).enumerate() {
+            stream.extend_from_slice(chunk);
+            stream.push(2);
+            stream.extend(make_block(if i < 2 { "First" } else { "Second" }));
+        }
+
+        let source = IcySource::new(Cursor::new(stream), 512);
+        let metadata = source.metadata();
+
+        let mss = MediaSourceStream::new(Box::new(source), Default::default());
+        let format = WavReader::try_new(mss, &Default::default()).unwrap();
+        let mut reader = IcyReader::new(Box::new(format), metadata);
+
+        let mut titles = Vec::new();
+
+        while reader.next_packet().is_ok() {
+            while let Some(rev) = reader.metadata().pop() {
+                titles.push(rev.tags()[0].value.to_string());
+            }
+        }
+
+        titles.extend(reader.metadata().current().map(|rev| rev.tags()[0].value.to_string()));
+
+        // The metadata of the wrapped reader is first.
+        assert_eq!(titles, vec!["Song", "First", "Second"]);
+    }
+
+    #[test]
+    fn verify_response_head_limits() {
+        // A header longer than the maximum response head length.
+        let mut response = b"HTTP/1.0 200 OK\r\nicy-name: ".to_vec();
+        response.resize(16 * 1024, b'a');
+        response.extend_from_slice(b"\r\n\r\n");
+
+        let err = ResponseHead::read(&mut Cursor::new(response)).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        // Too many headers.
+        let mut response = b"HTTP/1.0 200 OK\r\n".to_vec();
+        for _ in 0..200 {
+            response.extend_from_slice(b"a: b\r\n");
+        }
+        response.extend_from_slice(b"\r\n");
+
+        let err = ResponseHead::read(&mut Cursor::new(response)).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        // The maximum number of headers.
+        let mut response = b"HTTP/1.0 200 OK\r\n".to_vec();
+        for _ in 0..100 {
+            response.extend_from_slice(b"a: b\r\n");
+        }
+        response.extend_from_slice(b"\r\n");
+
+        let head = ResponseHead::read(&mut Cursor::new(response)).unwrap();
+        assert_eq!(head.headers.len(), 100);
+    }
+
+    #[cfg(feature = "icy-http")]
+    #[test]
+    fn verify_parse_http_url() {
+        use super::parse_http_url;
+
+        assert_eq!(
+            parse_http_url("http://radio.example:8000/stream").unwrap(),
+            ("radio.example".to_string(), 8000, "/stream".to_string())
+        );
+        assert_eq!(
+            parse_http_url("http://radio.example").unwrap(),
+            ("radio.example".to_string(), 80, "/".to_string())
+        );
+        assert_eq!(
+            parse_http_url("http://[::1]:8000/stream").unwrap(),
+            ("::1".to_string(), 8000, "/stream".to_string())
+        );
+        assert_eq!(
+            parse_http_url("http://[2001:db8::1]").unwrap(),
+            ("2001:db8::1".to_string(), 80, "/".to_string())
+        );
+        assert!(parse_http_url("https://radio.example/").is_err());
+        assert!(parse_http_url("http://::1:8000/").is_err());
+        assert!(parse_http_url("http://[::1/").is_err());
+        assert!(parse_http_url("http://[::1]8000/").is_err());
+        assert!(parse_http_url("http://[radio.example]/").is_err());
+    }
+
+    #[cfg(feature = "icy-http")]
+    #[test]
+    fn verify_resolve_url() {
+        use super::resolve_url;
+
+        let base = "http://radio.example:8000/live/stream.mp3?id=1";
+
+        assert_eq!(resolve_url(base, "http://other.example/a"), "http://other.example/a");
+        assert_eq!(resolve_url(base, "//other.example/a"), "http://other.example/a");
+        assert_eq!(resolve_url(base, "/relay/b"), "http://radio.example:8000/relay/b");
+        assert_eq!(resolve_url(base, "hq.mp3"), "http://radio.example:8000/live/hq.mp3");
+        assert_eq!(resolve_url("http://radio.example", "hq.mp3"), "http://radio.example/hq.mp3");
+    }
+}
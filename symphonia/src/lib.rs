@@ -6,7 +6,8 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 #![warn(rust_2018_idioms)]
-#![forbid(unsafe_code)]
+// Unsafe code is only permitted where it is explicitly allowed, and only for memory mapping files.
+#![deny(unsafe_code)]
 
 //! # Project Symphonia
 //!
@@ -26,8 +27,15 @@
 //!
 //! | Format   | Feature Flag | Gapless* | Default |
 //! |----------|--------------|----------|---------|
+//! | AIFF     | `aiff`       | No       | No      |
+//! | AMR      | `amr`        | No       | No      |
+//! | ASF/WMA  | `asf`        | No       | No      |
+//! | AU       | `au`         | No       | No      |
+//! | CAF      | `caf`        | No       | No      |
+//! | DSF/DFF  | `dsd`        | No       | No      |
 //! | ISO/MP4  | `isomp4`     | No       | No      |
 //! | MKV/WebM | `mkv`        | No       | Yes     |
+//! | MPEG-TS  | `mpegts`     | No       | No      |
 //! | OGG      | `ogg`        | Yes      | Yes     |
 //! | Wave     | `wav`        | Yes      | Yes     |
 //!
@@ -42,14 +50,17 @@
 //! | Codec    | Feature Flag | Gapless | Default |
 //! |----------|--------------|---------|---------|
 //! | AAC-LC   | `aac`        | No      | No      |
+//! | AC-3     | `ac3`        | No      | No      |
 //! | ADPCM    | `adpcm`      | Yes     | Yes     |
 //! | ALAC     | `alac`       | Yes     | No      |
+//! | APE      | `ape`        | No      | No      |
 //! | FLAC     | `flac`       | Yes     | Yes     |
 //! | MP1      | `mp1`, `mpa` | No      | No      |
 //! | MP2      | `mp2`, `mpa` | No      | No      |
 //! | MP3      | `mp3`, `mpa` | Yes     | No      |
 //! | PCM      | `pcm`        | Yes     | Yes     |
 //! | Vorbis   | `vorbis`     | Yes     | Yes     |
+//! | WavPack  | `wavpack`    | No      | No      |
 //!
 //! **Tip:** All codecs can be enabled with the `all-codecs` feature flag. Similarly, all MPEG
 //! audio codecs can be enabled with the `mpa` feature flag.
@@ -58,9 +69,12 @@
 //!
 //! The following metadata tagging formats are supported. These are always enabled.
 //!
+//! * APEv1 & APEv2
+//! * Cuesheet (external .cue files)
 //! * ID3v1
 //! * ID3v2
 //! * ISO/MP4
+//! * LRC synchronized lyrics (external .lrc files)
 //! * RIFF
 //! * Vorbis Comment (in OGG & FLAC)
 //!
@@ -79,6 +93,8 @@
 //! 3.  Make sure the [`MediaSource`][core::io::MediaSource] trait is implemented for whatever
 //!     source you are using. This trait is already implemented for `std::fs::File` and
 //!     `std::io::Cursor`.
+//!     Large local files may instead be memory mapped with
+//!     [`MmapSource`][mmap::MmapSource] by enabling the `mmap` feature flag.
 //! 4.  Instantiate a [`MediaSourceStream`][core::io::MediaSourceStream] with the `MediaSource`
 //!     above.
 //! 5.  Using the `Probe`, call [`format`][core::probe::Probe::format] and pass it the
@@ -104,14 +120,33 @@
 //!     [`RawSampleBuffer`][core::audio::RawSampleBuffer] to export the audio out of Symphonia.
 //! 11. Repeat step 9 and 10 until the end-of-stream error is returned.
 //!
+//! Steps 1 through 8 may be replaced by a single call to
+//! [`MediaOpener::open`][opener::MediaOpener::open] for the common case of decoding a single track
+//! of a media source.
+//!
+//! Applications using the Tokio runtime may enable the `tokio` feature flag and use
+//! [`AsyncMedia`][async_io::AsyncMedia] to demux and decode without blocking an executor thread.
+//!
 //! An example implementation of a simple audio player (symphonia-play) can be found in the
 //! Project Symphonia git repository.
 //!
+//! # Thread Safety
+//!
+//! All `FormatReader`s, `Decoder`s, and `MediaSourceStream`s are `Send` and `Sync`. A decoding
+//! pipeline may therefore be moved between threads, for example, by a media server that
+//! schedules work on a thread pool.
+//!
 //! # Gapless Playback
 //!
 //! Gapless playback is disabled by default. To enable gapless playback, set
 //! [`FormatOptions::enable_gapless`][core::formats::FormatOptions::enable_gapless] to `true`.
 //!
+//! # Serialization
+//!
+//! Metadata types such as [`Tag`][core::meta::Tag], [`Visual`][core::meta::Visual],
+//! [`MetadataRevision`][core::meta::MetadataRevision], and [`Cue`][core::formats::Cue] may be
+//! serialized and deserialized with `serde` by enabling the `serde` feature flag.
+//!
 //! # Adding new formats and codecs
 //!
 //! Simply implement the [`Decoder`][core::codecs::Decoder] trait for a decoder or the
@@ -132,14 +167,20 @@ pub mod default {
         pub use symphonia_bundle_mp3::MpaDecoder;
         #[cfg(feature = "aac")]
         pub use symphonia_codec_aac::AacDecoder;
+        #[cfg(feature = "ac3")]
+        pub use symphonia_codec_ac3::Ac3Decoder;
         #[cfg(feature = "adpcm")]
         pub use symphonia_codec_adpcm::AdpcmDecoder;
         #[cfg(feature = "alac")]
         pub use symphonia_codec_alac::AlacDecoder;
+        #[cfg(feature = "ape")]
+        pub use symphonia_codec_ape::ApeDecoder;
         #[cfg(feature = "pcm")]
         pub use symphonia_codec_pcm::PcmDecoder;
         #[cfg(feature = "vorbis")]
         pub use symphonia_codec_vorbis::VorbisDecoder;
+        #[cfg(feature = "wavpack")]
+        pub use symphonia_codec_wavpack::WavPackDecoder;
 
         #[deprecated = "use `default::codecs::MpaDecoder` instead"]
         #[cfg(any(feature = "mp1", feature = "mp2", feature = "mp3"))]
@@ -155,10 +196,34 @@ pub mod default {
         pub use symphonia_bundle_mp3::MpaReader;
         #[cfg(feature = "aac")]
         pub use symphonia_codec_aac::AdtsReader;
+        #[cfg(feature = "ac3")]
+        pub use symphonia_codec_ac3::Ac3Reader;
+        #[cfg(feature = "ape")]
+        pub use symphonia_codec_ape::ApeReader;
+        #[cfg(feature = "musepack")]
+        pub use symphonia_codec_musepack::MpcReader;
+        #[cfg(feature = "tta")]
+        pub use symphonia_codec_tta::TtaReader;
+        #[cfg(feature = "wavpack")]
+        pub use symphonia_codec_wavpack::WavPackReader;
+        #[cfg(feature = "aiff")]
+        pub use symphonia_format_aiff::AiffReader;
+        #[cfg(feature = "amr")]
+        pub use symphonia_format_amr::AmrReader;
+        #[cfg(feature = "asf")]
+        pub use symphonia_format_asf::AsfReader;
+        #[cfg(feature = "au")]
+        pub use symphonia_format_au::AuReader;
+        #[cfg(feature = "caf")]
+        pub use symphonia_format_caf::CafReader;
+        #[cfg(feature = "dsd")]
+        pub use symphonia_format_dsd::{DffReader, DsfReader};
         #[cfg(feature = "isomp4")]
         pub use symphonia_format_isomp4::IsoMp4Reader;
         #[cfg(feature = "mkv")]
         pub use symphonia_format_mkv::MkvReader;
+        #[cfg(feature = "mpegts")]
+        pub use symphonia_format_mpegts::TsReader;
         #[cfg(feature = "ogg")]
         pub use symphonia_format_ogg::OggReader;
         #[cfg(feature = "wav")]
@@ -219,12 +284,18 @@ pub mod default {
         #[cfg(feature = "aac")]
         registry.register_all::<codecs::AacDecoder>();
 
+        #[cfg(feature = "ac3")]
+        registry.register_all::<codecs::Ac3Decoder>();
+
         #[cfg(feature = "adpcm")]
         registry.register_all::<codecs::AdpcmDecoder>();
 
         #[cfg(feature = "alac")]
         registry.register_all::<codecs::AlacDecoder>();
 
+        #[cfg(feature = "ape")]
+        registry.register_all::<codecs::ApeDecoder>();
+
         #[cfg(feature = "flac")]
         registry.register_all::<codecs::FlacDecoder>();
 
@@ -236,6 +307,9 @@ pub mod default {
 
         #[cfg(feature = "vorbis")]
         registry.register_all::<codecs::VorbisDecoder>();
+
+        #[cfg(feature = "wavpack")]
+        registry.register_all::<codecs::WavPackDecoder>();
     }
 
     /// Registers all the formats selected by the `feature` flags in the includer's `Cargo.toml` on
@@ -250,6 +324,9 @@ pub mod default {
         #[cfg(feature = "aac")]
         probe.register_all::<formats::AdtsReader>();
 
+        #[cfg(feature = "ac3")]
+        probe.register_all::<formats::Ac3Reader>();
+
         #[cfg(feature = "flac")]
         probe.register_all::<formats::FlacReader>();
 
@@ -268,9 +345,152 @@ pub mod default {
         #[cfg(feature = "mkv")]
         probe.register_all::<formats::MkvReader>();
 
+        #[cfg(feature = "mpegts")]
+        probe.register_all::<formats::TsReader>();
+
+        #[cfg(feature = "caf")]
+        probe.register_all::<formats::CafReader>();
+
+        #[cfg(feature = "dsd")]
+        probe.register_all::<formats::DsfReader>();
+
+        #[cfg(feature = "dsd")]
+        probe.register_all::<formats::DffReader>();
+
+        #[cfg(feature = "amr")]
+        probe.register_all::<formats::AmrReader>();
+
+        #[cfg(feature = "asf")]
+        probe.register_all::<formats::AsfReader>();
+
+        #[cfg(feature = "au")]
+        probe.register_all::<formats::AuReader>();
+
+        #[cfg(feature = "aiff")]
+        probe.register_all::<formats::AiffReader>();
+
+        #[cfg(feature = "wavpack")]
+        probe.register_all::<formats::WavPackReader>();
+
+        #[cfg(feature = "ape")]
+        probe.register_all::<formats::ApeReader>();
+
+        #[cfg(feature = "musepack")]
+        probe.register_all::<formats::MpcReader>();
+
+        #[cfg(feature = "tta")]
+        probe.register_all::<formats::TtaReader>();
+
         // Metadata
         probe.register_all::<Id3v2Reader>();
     }
 }
 
+#[cfg(feature = "tokio")]
+pub mod async_io;
+pub mod compare;
+pub mod dump;
+pub mod health;
+pub mod icy;
+pub mod looping;
+#[cfg(feature = "mmap")]
+pub mod mmap;
+pub mod negotiate;
+pub mod opener;
+pub mod pacing;
+pub mod sidecar;
+
 pub use symphonia_core as core;
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::codecs::Decoder;
+    use symphonia_core::formats::FormatReader;
+    use symphonia_core::io::MediaSourceStream;
+
+    /// Fails to compile if `T` is not `Send` and `Sync`.
+    fn assert_send_sync<T: Send + Sync + ?Sized>() {}
+
+    #[test]
+    fn verify_send_sync() {
+        use crate::default::codecs::*;
+        use crate::default::formats::*;
+
+        assert_send_sync::<dyn Decoder>();
+        assert_send_sync::<dyn FormatReader>();
+        assert_send_sync::<MediaSourceStream>();
+
+        #[cfg(feature = "aac")]
+        {
+            assert_send_sync::<AacDecoder>();
+            assert_send_sync::<AdtsReader>();
+        }
+        #[cfg(feature = "ac3")]
+        {
+            assert_send_sync::<Ac3Decoder>();
+            assert_send_sync::<Ac3Reader>();
+        }
+        #[cfg(feature = "adpcm")]
+        assert_send_sync::<AdpcmDecoder>();
+        #[cfg(feature = "ape")]
+        {
+            assert_send_sync::<ApeDecoder>();
+            assert_send_sync::<ApeReader>();
+        }
+        #[cfg(feature = "alac")]
+        assert_send_sync::<AlacDecoder>();
+        #[cfg(feature = "flac")]
+        {
+            assert_send_sync::<FlacDecoder>();
+            assert_send_sync::<FlacReader>();
+        }
+        #[cfg(any(feature = "mp1", feature = "mp2", feature = "mp3"))]
+        {
+            assert_send_sync::<MpaDecoder>();
+            assert_send_sync::<MpaReader>();
+        }
+        #[cfg(feature = "musepack")]
+        assert_send_sync::<MpcReader>();
+        #[cfg(feature = "pcm")]
+        assert_send_sync::<PcmDecoder>();
+        #[cfg(feature = "tta")]
+        assert_send_sync::<TtaReader>();
+        #[cfg(feature = "vorbis")]
+        assert_send_sync::<VorbisDecoder>();
+        #[cfg(feature = "aiff")]
+        assert_send_sync::<AiffReader>();
+        #[cfg(feature = "amr")]
+        assert_send_sync::<AmrReader>();
+        #[cfg(feature = "asf")]
+        assert_send_sync::<AsfReader>();
+        #[cfg(feature = "au")]
+        assert_send_sync::<AuReader>();
+        #[cfg(feature = "caf")]
+        assert_send_sync::<CafReader>();
+        #[cfg(feature = "dsd")]
+        {
+            assert_send_sync::<DffReader>();
+            assert_send_sync::<DsfReader>();
+        }
+        #[cfg(feature = "isomp4")]
+        assert_send_sync::<IsoMp4Reader>();
+        #[cfg(feature = "mkv")]
+        assert_send_sync::<MkvReader>();
+        #[cfg(feature = "mpegts")]
+        assert_send_sync::<TsReader>();
+        #[cfg(feature = "ogg")]
+        assert_send_sync::<OggReader>();
+        #[cfg(feature = "wav")]
+        assert_send_sync::<WavReader>();
+        #[cfg(feature = "wavpack")]
+        {
+            assert_send_sync::<WavPackDecoder>();
+            assert_send_sync::<WavPackReader>();
+        }
+
+        assert_send_sync::<crate::opener::OpenedMedia>();
+        assert_send_sync::<crate::looping::Looper>();
+        assert_send_sync::<crate::pacing::PacedPackets>();
+        assert_send_sync::<crate::health::Watchdog>();
+    }
+}
@@ -0,0 +1,277 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The `looping` module provides an opt-in decode mode that plays the loop region of a track.
+
+use symphonia_core::audio::{AudioBuffer, Signal};
+use symphonia_core::codecs::Decoder;
+use symphonia_core::errors::{decode_error, Error, Result};
+use symphonia_core::formats::util::loop_region_from_tags;
+use symphonia_core::formats::{FormatReader, LoopRegion, SeekMode, SeekTo};
+
+use crate::opener::OpenedMedia;
+
+/// `Looper` decodes a track and seamlessly jumps back to the start of the loop region of the track
+/// when the end of the loop region is reached.
+///
+/// After the loop region has been played the number of times specified by the region, the rest of
+/// the track is played normally. If the region should be played indefinitely, the end of the
+/// track is never reached.
+///
+/// Jumps are frame-accurate: the format reader is seeked in accurate mode, the decoder is reset,
+/// and any pre-roll frames decoded before the start of the loop region are discarded.
+pub struct Looper {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    track_id: u32,
+    region: Option<LoopRegion>,
+    /// The number of jumps remaining, or `None` if the loop region is played indefinitely.
+    jumps_left: Option<u32>,
+    /// Frames with a timestamp before this timestamp are discarded.
+    skip_until: u64,
+    /// If true, the end of the loop region was reached, and a jump must be performed.
+    at_loop_end: bool,
+    /// If true, at least one jump was performed.
+    has_jumped: bool,
+    /// If true, frames were returned since the last jump.
+    has_played: bool,
+    buf: AudioBuffer<f32>,
+}
+
+impl Looper {
+    /// Instantiate a new `Looper` that plays the provided loop region of a track. If the region is
+    /// `None`, the track is played without looping.
+    pub fn new(
+        format: Box<dyn FormatReader>,
+        decoder: Box<dyn Decoder>,
+        track_id: u32,
+        region: Option<LoopRegion>,
+    ) -> Self {
+        let jumps_left = match region {
+            Some(region) if region.play_count > 0 => Some(region.play_count - 1),
+            Some(_) => None,
+            None => Some(0),
+        };
+
+        Looper {
+            format,
+            decoder,
+            track_id,
+            region,
+            jumps_left,
+            skip_until: 0,
+            at_loop_end: false,
+            has_jumped: false,
+            has_played: false,
+            buf: AudioBuffer::unused(),
+        }
+    }
+
+    /// Instantiate a new `Looper` for opened media, using the loop region described by the loop
+    /// tags of the media, if any. See `loop_region_from_tags` for the supported tags.
+    pub fn from_media(mut media: OpenedMedia) -> Self {
+        let mut region = media
+            .format
+            .metadata()
+            .skip_to_latest()
+            .and_then(|rev| loop_region_from_tags(rev.tags()));
+
+        if region.is_none() {
+            region = media.metadata.get().and_then(|mut metadata| {
+                metadata.skip_to_latest().and_then(|rev| loop_region_from_tags(rev.tags()))
+            });
+        }
+
+        Looper::new(media.format, media.decoder, media.track_id, region)
+    }
+
+    /// Gets the loop region, if any.
+    pub fn region(&self) -> Option<LoopRegion> {
+        self.region
+    }
+
+    /// Stop looping. The rest of the track will be played normally.
+    pub fn stop_looping(&mut self) {
+        self.jumps_left = Some(0);
+    }
+
+    fn is_looping(&self) -> bool {
+        self.region.is_some() && self.jumps_left != Some(0)
+    }
+
+    fn jump(&mut self) -> Result<()> {
+        // If nothing was played since the last jump, then the loop region does not overlap the
+        // track and looping would never produce any audio.
+        if self.has_jumped && !self.has_played {
+            return decode_error("symphonia: loop region is outside of the track");
+        }
+
+        let region = self.region.unwrap();
+
+        self.format.seek(
+            SeekMode::Accurate,
+            SeekTo::TimeStamp { ts: region.start_ts, track_id: self.track_id },
+        )?;
+
+        self.decoder.reset();
+
+        if let Some(jumps_left) = &mut self.jumps_left {
+            *jumps_left -= 1;
+        }
+
+        self.skip_until = region.start_ts;
+        self.at_loop_end = false;
+        self.has_jumped = true;
+        self.has_played = false;
+
+        Ok(())
+    }
+
+    /// Decode the next buffer of audio. Returns an end-of-stream IO error once the end of the
+    /// track is reached.
+    pub fn next_buffer(&mut self) -> Result<&AudioBuffer<f32>> {
+        loop {
+            if self.at_loop_end {
+                self.jump()?;
+            }
+
+            let packet = match self.format.next_packet() {
+                Ok(packet) => packet,
+                Err(Error::IoError(err))
+                    if err.kind() == std::io::ErrorKind::UnexpectedEof && self.is_looping() =>
+                {
+                    // The loop region extends to, or past, the end of the track.
+                    self.at_loop_end = true;
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+
+            if packet.track_id() != self.track_id {
+                continue;
+            }
+
+            let decoded = match self.decoder.decode(&packet) {
+                Ok(decoded) => decoded,
+                // Malformed packets are skipped.
+                Err(Error::DecodeError(_)) => continue,
+                Err(err) => return Err(err),
+            };
+
+            // Reallocate the buffer if the signal specification changed, or the decoded audio
+            // would not fit.
+            if *self.buf.spec() != *decoded.spec() || self.buf.capacity() < decoded.capacity() {
+                self.buf = decoded.make_equivalent();
+            }
+
+            decoded.convert(&mut self.buf);
+
+            let ts = packet.ts();
+            let n_frames = self.buf.frames() as u64;
+
+            // Discard pre-roll frames before the start of the loop region.
+            let start = self.skip_until.saturating_sub(ts).min(n_frames);
+
+            // Discard frames past the end of the loop region while looping.
+            let mut end = n_frames;
+
+            if let Some(region) = self.region.filter(|_| self.is_looping()) {
+                if ts + n_frames >= region.end_ts {
+                    end = region.end_ts.saturating_sub(ts).min(n_frames);
+                    self.at_loop_end = true;
+                }
+            }
+
+            if start >= end {
+                continue;
+            }
+
+            self.buf.trim(start as usize, (n_frames - end) as usize);
+            self.has_played = true;
+
+            return Ok(&self.buf);
+        }
+    }
+
+    /// Consume the `Looper` and return the format reader and decoder.
+    pub fn into_inner(self) -> (Box<dyn FormatReader>, Box<dyn Decoder>) {
+        (self.format, self.decoder)
+    }
+}
+
+#[cfg(all(test, feature = "wav", feature = "pcm"))]
+mod tests {
+    use std::io::Cursor;
+
+    use symphonia_core::audio::Signal;
+    use symphonia_core::formats::LoopRegion;
+
+    use super::Looper;
+    use crate::opener::MediaOpener;
+
+    /// Makes a mono, 8-bit, WAV file with a sampler chunk following the data chunk.
+    fn make_wav(samples: &[u8], loop_start: u32, loop_end: u32, play_count: u32) -> Vec<u8> {
+        let mut fmt = Vec::new();
+        fmt.extend_from_slice(&1u16.to_le_bytes());
+        fmt.extend_from_slice(&1u16.to_le_bytes());
+        fmt.extend_from_slice(&8000u32.to_le_bytes());
+        fmt.extend_from_slice(&8000u32.to_le_bytes());
+        fmt.extend_from_slice(&1u16.to_le_bytes());
+        fmt.extend_from_slice(&8u16.to_le_bytes());
+
+        let mut smpl = vec![0; 28];
+        smpl.extend_from_slice(&1u32.to_le_bytes());
+        smpl.extend_from_slice(&0u32.to_le_bytes());
+        for field in &[0, 0, loop_start, loop_end, 0, play_count] {
+            smpl.extend_from_slice(&field.to_le_bytes());
+        }
+
+        let mut body = b"WAVE".to_vec();
+        for (tag, chunk) in [(b"fmt ", &fmt[..]), (b"data", samples), (b"smpl", &smpl[..])] {
+            body.extend_from_slice(tag);
+            body.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+            body.extend_from_slice(chunk);
+            if chunk.len() & 1 == 1 {
+                body.push(0);
+            }
+        }
+
+        let mut wav = b"RIFF".to_vec();
+        wav.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        wav.extend_from_slice(&body);
+        wav
+    }
+
+    fn play(looper: &mut Looper) -> Vec<f32> {
+        let mut out = Vec::new();
+
+        while let Ok(buf) = looper.next_buffer() {
+            out.extend_from_slice(buf.chan(0));
+        }
+
+        out
+    }
+
+    #[test]
+    fn verify_looper_plays_smpl_loop() {
+        // Frames 2 to 4 (inclusive) are looped 3 times.
+        let samples = [128, 129, 130, 131, 132, 133, 134];
+        let wav = make_wav(&samples, 2, 4, 3);
+
+        let media = MediaOpener::new().with_hint("wav").open(Cursor::new(wav)).unwrap();
+        let mut looper = Looper::from_media(media);
+
+        assert_eq!(looper.region(), Some(LoopRegion { start_ts: 2, end_ts: 5, play_count: 3 }));
+
+        let expected: Vec<f32> = [0u8, 1, 2, 3, 4, 2, 3, 4, 2, 3, 4, 5, 6]
+            .iter()
+            .map(|&i| f32::from(i) / 128.0)
+            .collect();
+
+        assert_eq!(play(&mut looper), expected);
+    }
+}
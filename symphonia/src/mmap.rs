@@ -0,0 +1,108 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The `mmap` module provides a `MediaSource` backed by a memory-mapped file.
+
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use memmap2::Mmap;
+
+use symphonia_core::io::MediaSource;
+
+/// `MmapSource` is a `MediaSource` backed by a read-only memory map of a file.
+///
+/// Reading from a memory-mapped file is a copy out of the page cache rather than a system call,
+/// and seeking is free. This reduces the overhead of reading large local files, and of the random
+/// access performed while seeking.
+pub struct MmapSource {
+    inner: io::Cursor<Mmap>,
+}
+
+impl MmapSource {
+    /// Memory map the provided file.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified, or truncated, by this or any other process while it is
+    /// mapped. Doing so is undefined behaviour, and truncation will usually terminate the process.
+    #[allow(unsafe_code)]
+    pub unsafe fn map(file: &File) -> io::Result<Self> {
+        let mmap = Mmap::map(file)?;
+
+        Ok(MmapSource { inner: io::Cursor::new(mmap) })
+    }
+
+    /// Open and memory map the file at the provided path.
+    ///
+    /// # Safety
+    ///
+    /// See [`MmapSource::map`].
+    #[allow(unsafe_code)]
+    pub unsafe fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        MmapSource::map(&File::open(path)?)
+    }
+
+    /// Gets the entire contents of the file as a byte slice.
+    pub fn as_slice(&self) -> &[u8] {
+        self.inner.get_ref()
+    }
+}
+
+impl MediaSource for MmapSource {
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
+    fn byte_len(&self) -> Option<u64> {
+        Some(self.inner.get_ref().len() as u64)
+    }
+}
+
+impl io::Read for MmapSource {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl io::Seek for MmapSource {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Seek, SeekFrom};
+
+    use symphonia_core::io::MediaSource;
+
+    use super::MmapSource;
+
+    #[test]
+    #[allow(unsafe_code)]
+    fn verify_mmap_source() {
+        let path = std::env::temp_dir().join(format!("symphonia-mmap-{}", std::process::id()));
+        std::fs::write(&path, b"0123456789").unwrap();
+
+        let mut source = unsafe { MmapSource::open(&path) }.unwrap();
+
+        assert!(source.is_seekable());
+        assert_eq!(source.byte_len(), Some(10));
+
+        let mut buf = [0; 4];
+        source.seek(SeekFrom::Start(6)).unwrap();
+        source.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"6789");
+        assert_eq!(source.read(&mut buf).unwrap(), 0);
+        assert_eq!(source.as_slice(), b"0123456789");
+
+        drop(source);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,394 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The `negotiate` module selects the output format of an audio device for decoded audio.
+//!
+//! Audio backends typically support a small set of sample formats and sample rates. Given the
+//! configurations supported by a device, [`negotiate`] picks the one requiring the least
+//! conversion, and [`Converter`] performs that conversion on decoded audio buffers.
+//!
+//! The order of preference is:
+//!
+//! 1. The sample format of the decoded audio, passed through unchanged.
+//! 2. 32-bit floating point.
+//! 3. Signed 16-bit integer, dithered if the decoded audio has a greater bit depth.
+//! 4. Any other sample format, preferring those with a greater bit depth.
+//!
+//! Configurations supporting the sample rate of the decoded audio are always preferred over those
+//! requiring resampling.
+
+use std::cmp::Reverse;
+
+use symphonia_core::audio::{AudioBuffer, AudioBufferRef, RawSampleBuffer, SignalSpec};
+use symphonia_core::conv::dither::{Dither, Triangular};
+use symphonia_core::conv::{ConvertibleSample, FromSample};
+use symphonia_core::sample::{i24, u24, Sample, SampleFormat};
+
+/// An `OutputConfig` is a sample format, and range of sample rates, supported by an audio device.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct OutputConfig {
+    /// The sample format.
+    pub format: SampleFormat,
+    /// The minimum supported sample rate.
+    pub min_rate: u32,
+    /// The maximum supported sample rate.
+    pub max_rate: u32,
+}
+
+impl OutputConfig {
+    /// Instantiate a new `OutputConfig` supporting a range of sample rates.
+    pub fn new(format: SampleFormat, min_rate: u32, max_rate: u32) -> Self {
+        OutputConfig { format, min_rate, max_rate }
+    }
+
+    /// Instantiate a new `OutputConfig` supporting a single sample rate.
+    pub fn with_rate(format: SampleFormat, rate: u32) -> Self {
+        OutputConfig::new(format, rate, rate)
+    }
+
+    /// Gets the supported sample rate nearest to the provided sample rate.
+    fn nearest_rate(&self, rate: u32) -> u32 {
+        rate.max(self.min_rate).min(self.max_rate)
+    }
+}
+
+/// The conversion applied to each sample.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Conversion {
+    /// The samples are passed through unchanged.
+    Passthrough,
+    /// The samples are converted to the output sample format.
+    Convert,
+    /// The samples are dithered, and then converted to the output sample format.
+    Dither,
+}
+
+/// The outcome of a negotiation.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Negotiated {
+    /// The output sample format.
+    pub format: SampleFormat,
+    /// The output sample rate.
+    pub rate: u32,
+    /// The sample format of the decoded audio.
+    pub source_format: SampleFormat,
+    /// The sample rate of the decoded audio.
+    pub source_rate: u32,
+    /// The conversion applied to each sample.
+    pub conversion: Conversion,
+}
+
+impl Negotiated {
+    /// Returns `true` if the decoded audio must be resampled to the output sample rate.
+    ///
+    /// Resampling is not performed by [`Converter`], and must be done before conversion.
+    pub fn needs_resampling(&self) -> bool {
+        self.rate != self.source_rate
+    }
+
+    /// Instantiate a `Converter` performing the negotiated conversion.
+    pub fn converter(&self) -> Converter {
+        Converter::new(self)
+    }
+}
+
+/// Gets the effective number of bits of a sample format.
+fn eff_bits(format: SampleFormat) -> u32 {
+    match format {
+        SampleFormat::U8 => u8::EFF_BITS,
+        SampleFormat::U16 => u16::EFF_BITS,
+        SampleFormat::U24 => u24::EFF_BITS,
+        SampleFormat::U32 => u32::EFF_BITS,
+        SampleFormat::S8 => i8::EFF_BITS,
+        SampleFormat::S16 => i16::EFF_BITS,
+        SampleFormat::S24 => i24::EFF_BITS,
+        SampleFormat::S32 => i32::EFF_BITS,
+        SampleFormat::F32 => f32::EFF_BITS,
+        SampleFormat::F64 => f64::EFF_BITS,
+    }
+}
+
+/// Gets the conversion from the source sample format to the output sample format.
+fn conversion(source: SampleFormat, output: SampleFormat) -> Conversion {
+    let is_ditherable = matches!(
+        output,
+        SampleFormat::U8 | SampleFormat::S8 | SampleFormat::U16 | SampleFormat::S16
+    );
+
+    if source == output {
+        Conversion::Passthrough
+    }
+    else if is_ditherable && eff_bits(source) > eff_bits(output) {
+        Conversion::Dither
+    }
+    else {
+        Conversion::Convert
+    }
+}
+
+/// Picks the output configuration requiring the least conversion of decoded audio with the
+/// provided sample format and sample rate. Returns `None` if no configurations were provided.
+pub fn negotiate(
+    source_format: SampleFormat,
+    source_rate: u32,
+    configs: &[OutputConfig],
+) -> Option<Negotiated> {
+    let tier = |format: SampleFormat| {
+        if format == source_format {
+            0
+        }
+        else if format == SampleFormat::F32 {
+            1
+        }
+        else if format == SampleFormat::S16 {
+            2
+        }
+        else {
+            3
+        }
+    };
+
+    configs
+        .iter()
+        .min_by_key(|config| {
+            let rate = config.nearest_rate(source_rate);
+            (
+                rate != source_rate,
+                tier(config.format),
+                Reverse(eff_bits(config.format)),
+                (i64::from(rate) - i64::from(source_rate)).abs(),
+            )
+        })
+        .map(|config| Negotiated {
+            format: config.format,
+            rate: config.nearest_rate(source_rate),
+            source_format,
+            source_rate,
+            conversion: conversion(source_format, config.format),
+        })
+}
+
+/// The interleaved output buffer of a `Converter`.
+enum Output {
+    U8(RawSampleBuffer<u8>),
+    U16(RawSampleBuffer<u16>),
+    U24(RawSampleBuffer<u24>),
+    U32(RawSampleBuffer<u32>),
+    S8(RawSampleBuffer<i8>),
+    S16(RawSampleBuffer<i16>),
+    S24(RawSampleBuffer<i24>),
+    S32(RawSampleBuffer<i32>),
+    F32(RawSampleBuffer<f32>),
+    F64(RawSampleBuffer<f64>),
+}
+
+macro_rules! impl_output_func {
+    ($var:expr, $buf:ident, $expr:expr) => {
+        match $var {
+            Output::U8($buf) => $expr,
+            Output::U16($buf) => $expr,
+            Output::U24($buf) => $expr,
+            Output::U32($buf) => $expr,
+            Output::S8($buf) => $expr,
+            Output::S16($buf) => $expr,
+            Output::S24($buf) => $expr,
+            Output::S32($buf) => $expr,
+            Output::F32($buf) => $expr,
+            Output::F64($buf) => $expr,
+        }
+    };
+}
+
+impl Output {
+    fn new(format: SampleFormat, n_frames: usize, spec: SignalSpec) -> Self {
+        let duration = n_frames as u64;
+
+        match format {
+            SampleFormat::U8 => Output::U8(RawSampleBuffer::new(duration, spec)),
+            SampleFormat::U16 => Output::U16(RawSampleBuffer::new(duration, spec)),
+            SampleFormat::U24 => Output::U24(RawSampleBuffer::new(duration, spec)),
+            SampleFormat::U32 => Output::U32(RawSampleBuffer::new(duration, spec)),
+            SampleFormat::S8 => Output::S8(RawSampleBuffer::new(duration, spec)),
+            SampleFormat::S16 => Output::S16(RawSampleBuffer::new(duration, spec)),
+            SampleFormat::S24 => Output::S24(RawSampleBuffer::new(duration, spec)),
+            SampleFormat::S32 => Output::S32(RawSampleBuffer::new(duration, spec)),
+            SampleFormat::F32 => Output::F32(RawSampleBuffer::new(duration, spec)),
+            SampleFormat::F64 => Output::F64(RawSampleBuffer::new(duration, spec)),
+        }
+    }
+
+    fn capacity(&self) -> usize {
+        impl_output_func!(self, buf, buf.capacity())
+    }
+
+    fn copy_ref(&mut self, src: AudioBufferRef<'_>) {
+        impl_output_func!(self, buf, buf.copy_interleaved_ref(src))
+    }
+
+    fn copy_f32(&mut self, src: &AudioBuffer<f32>) {
+        impl_output_func!(self, buf, buf.copy_interleaved_typed(src))
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        impl_output_func!(self, buf, buf.as_bytes())
+    }
+}
+
+/// A dither for 32-bit floating point samples.
+type DitherFn = Box<dyn FnMut(f32) -> f32 + Send + Sync>;
+
+fn make_dither<T: ConvertibleSample + FromSample<f32> + Send + Sync + 'static>() -> DitherFn {
+    let mut dither = Triangular::<f32, T>::new();
+    Box::new(move |sample| dither.dither(sample))
+}
+
+/// A `Converter` converts decoded audio buffers into interleaved samples of the negotiated
+/// output sample format.
+pub struct Converter {
+    format: SampleFormat,
+    output: Option<Output>,
+    /// The dither, and the intermediate buffer of samples being dithered, if dithering.
+    dither: Option<(DitherFn, Option<AudioBuffer<f32>>)>,
+}
+
+impl Converter {
+    /// Instantiate a new `Converter` performing the negotiated conversion.
+    pub fn new(negotiated: &Negotiated) -> Self {
+        let dither = match negotiated.conversion {
+            Conversion::Dither => Some(match negotiated.format {
+                SampleFormat::U8 => make_dither::<u8>(),
+                SampleFormat::S8 => make_dither::<i8>(),
+                SampleFormat::U16 => make_dither::<u16>(),
+                _ => make_dither::<i16>(),
+            }),
+            _ => None,
+        };
+
+        Converter {
+            format: negotiated.format,
+            output: None,
+            dither: dither.map(|dither| (dither, None)),
+        }
+    }
+
+    /// Gets the output sample format.
+    pub fn format(&self) -> SampleFormat {
+        self.format
+    }
+
+    /// Converts the audio buffer, returning the interleaved samples, in native byte order, of the
+    /// output sample format.
+    pub fn convert(&mut self, src: AudioBufferRef<'_>) -> &[u8] {
+        let spec = *src.spec();
+        let n_samples = src.capacity() * spec.channels.count();
+
+        // Reallocate the output buffer if the audio buffer may not fit.
+        if self.output.as_ref().map_or(true, |output| output.capacity() < n_samples) {
+            self.output = Some(Output::new(self.format, src.capacity(), spec));
+        }
+
+        let output = self.output.as_mut().unwrap();
+
+        match &mut self.dither {
+            Some((dither, buf)) => {
+                let is_reusable = buf
+                    .as_ref()
+                    .map_or(false, |buf| buf.spec() == &spec && buf.capacity() >= src.capacity());
+
+                if !is_reusable {
+                    *buf = Some(src.make_equivalent::<f32>());
+                }
+
+                let buf = buf.as_mut().unwrap();
+
+                src.convert(buf);
+
+                for plane in buf.planes_mut().planes() {
+                    for sample in plane.iter_mut() {
+                        *sample = dither(*sample);
+                    }
+                }
+
+                output.copy_f32(buf);
+            }
+            None => output.copy_ref(src),
+        }
+
+        output.as_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::audio::{AsAudioBufferRef, AudioBuffer, Channels, Signal, SignalSpec};
+    use symphonia_core::sample::SampleFormat;
+
+    use super::{negotiate, Conversion, OutputConfig};
+
+    #[test]
+    fn verify_negotiate() {
+        let configs = [
+            OutputConfig::new(SampleFormat::S16, 8000, 96000),
+            OutputConfig::new(SampleFormat::F32, 44100, 48000),
+            OutputConfig::with_rate(SampleFormat::S24, 44100),
+        ];
+
+        // The native format is passed through.
+        let negotiated = negotiate(SampleFormat::S24, 44100, &configs).unwrap();
+        assert_eq!(negotiated.format, SampleFormat::S24);
+        assert_eq!(negotiated.conversion, Conversion::Passthrough);
+
+        // 32-bit floating point is preferred otherwise.
+        let negotiated = negotiate(SampleFormat::S32, 48000, &configs).unwrap();
+        assert_eq!(negotiated.format, SampleFormat::F32);
+        assert_eq!(negotiated.conversion, Conversion::Convert);
+        assert!(!negotiated.needs_resampling());
+
+        // Only S16 supports 96kHz without resampling, and must be dithered.
+        let negotiated = negotiate(SampleFormat::F32, 96000, &configs).unwrap();
+        assert_eq!(negotiated.format, SampleFormat::S16);
+        assert_eq!(negotiated.conversion, Conversion::Dither);
+
+        // Resampling to the nearest supported sample rate when no configuration supports the rate.
+        let negotiated = negotiate(SampleFormat::F32, 192000, &configs[1..]).unwrap();
+        assert_eq!(negotiated.format, SampleFormat::F32);
+        assert_eq!(negotiated.rate, 48000);
+        assert!(negotiated.needs_resampling());
+
+        assert!(negotiate(SampleFormat::F32, 44100, &[]).is_none());
+    }
+
+    #[test]
+    fn verify_converter() {
+        let spec = SignalSpec::new(44100, Channels::FRONT_LEFT | Channels::FRONT_RIGHT);
+
+        let mut buf = AudioBuffer::<f32>::new(4, spec);
+        buf.render_reserved(Some(4));
+        buf.chan_mut(0).copy_from_slice(&[0.0, 0.5, -0.5, 1.0]);
+
+        // Dithering adds less than 1 LSB of noise.
+        let configs = [OutputConfig::with_rate(SampleFormat::S16, 44100)];
+        let mut converter = negotiate(SampleFormat::F32, 44100, &configs).unwrap().converter();
+
+        let bytes = converter.convert(buf.as_audio_buffer_ref());
+        assert_eq!(bytes.len(), 16);
+
+        let samples: Vec<i16> =
+            bytes.chunks_exact(2).map(|b| i16::from_ne_bytes([b[0], b[1]])).collect();
+
+        for (sample, expected) in samples.iter().step_by(2).zip(&[0, 16384, -16384, 32767]) {
+            assert!((i32::from(*sample) - expected).abs() <= 1);
+        }
+
+        // Passthrough is a copy.
+        let configs = [OutputConfig::with_rate(SampleFormat::F32, 44100)];
+        let mut converter = negotiate(SampleFormat::F32, 44100, &configs).unwrap().converter();
+
+        let bytes = converter.convert(buf.as_audio_buffer_ref());
+        assert_eq!(&bytes[8..12], &0.5f32.to_ne_bytes());
+    }
+}
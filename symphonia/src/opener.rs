@@ -0,0 +1,257 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The `opener` module provides a single entry point to open a media source and assemble a
+//! playback chain.
+
+use symphonia_core::codecs::{CodecRegistry, Decoder, DecoderOptions, CODEC_TYPE_NULL};
+use symphonia_core::errors::{unsupported_error, Result};
+use symphonia_core::formats::{FormatOptions, FormatReader};
+use symphonia_core::io::{MediaSource, MediaSourceStream, MediaSourceStreamOptions};
+use symphonia_core::meta::MetadataOptions;
+use symphonia_core::probe::{Hint, Probe, ProbeOptions, ProbedMetadata};
+
+/// `MediaOpener` is a builder that probes a media source, selects the default track, and
+/// instantiates a decoder for it.
+///
+/// Unless overridden, the default probe and codec registry from the [`default`][crate::default]
+/// module are used.
+///
+/// ```no_run
+/// use symphonia::opener::MediaOpener;
+///
+/// let file = std::fs::File::open("music.flac").unwrap();
+///
+/// let mut media = MediaOpener::new().with_hint("flac").with_gapless(true).open(file).unwrap();
+///
+/// while let Ok(packet) = media.format.next_packet() {
+///     if packet.track_id() == media.track_id {
+///         let _decoded = media.decoder.decode(&packet);
+///     }
+/// }
+/// ```
+pub struct MediaOpener<'a> {
+    hint: Hint,
+    mss_opts: MediaSourceStreamOptions,
+    format_opts: FormatOptions,
+    metadata_opts: MetadataOptions,
+    decoder_opts: DecoderOptions,
+    probe_opts: ProbeOptions,
+    language: Option<String>,
+    probe: Option<&'a Probe>,
+    codecs: Option<&'a CodecRegistry>,
+}
+
+/// The result of opening a media source with `MediaOpener`.
+pub struct OpenedMedia {
+    /// The `FormatReader` for the media source.
+    pub format: Box<dyn FormatReader>,
+    /// A log of `Metadata` revisions read while probing the media source.
+    pub metadata: ProbedMetadata,
+    /// The identifier of the selected track.
+    pub track_id: u32,
+    /// The `Decoder` for the selected track.
+    pub decoder: Box<dyn Decoder>,
+    /// If true, the media source is seekable. Otherwise, only forward seeks may succeed, and the
+    /// duration of the track may be unknown.
+    pub is_seekable: bool,
+}
+
+impl<'a> MediaOpener<'a> {
+    /// Instantiate a new `MediaOpener` with the default options.
+    pub fn new() -> Self {
+        MediaOpener {
+            hint: Hint::new(),
+            mss_opts: Default::default(),
+            format_opts: Default::default(),
+            metadata_opts: Default::default(),
+            decoder_opts: Default::default(),
+            probe_opts: Default::default(),
+            language: None,
+            probe: None,
+            codecs: None,
+        }
+    }
+
+    /// Provide a file extension hint to the probe.
+    pub fn with_hint(mut self, extension: &str) -> Self {
+        self.hint.with_extension(extension);
+        self
+    }
+
+    /// Provide a mime-type hint to the probe.
+    pub fn with_mime_type(mut self, mime_type: &str) -> Self {
+        self.hint.mime_type(mime_type);
+        self
+    }
+
+    /// Enable or disable gapless playback. See `FormatOptions::enable_gapless`.
+    pub fn with_gapless(mut self, enable: bool) -> Self {
+        self.format_opts.enable_gapless = enable;
+        self
+    }
+
+    /// Enable or disable estimating the encoder delay of media without gapless information. See
+    /// `FormatOptions::estimate_gapless`.
+    pub fn with_estimated_gapless(mut self, enable: bool) -> Self {
+        self.format_opts.estimate_gapless = enable;
+        self
+    }
+
+    /// Enable or disable verification of the decoded audio. See `DecoderOptions::verify`.
+    pub fn with_verify(mut self, verify: bool) -> Self {
+        self.decoder_opts.verify = verify;
+        self
+    }
+
+    /// Use the provided `MediaSourceStreamOptions`.
+    pub fn with_stream_options(mut self, options: MediaSourceStreamOptions) -> Self {
+        self.mss_opts = options;
+        self
+    }
+
+    /// Use the provided `FormatOptions`.
+    pub fn with_format_options(mut self, options: FormatOptions) -> Self {
+        self.format_opts = options;
+        self
+    }
+
+    /// Minimize the buffering added by the source stream, demuxer, and decoder, for
+    /// low-latency consumers such as intercom or VoIP. This replaces the stream options with
+    /// `MediaSourceStreamOptions::low_latency`, and limits the duration of packets of unframed
+    /// audio, such as PCM, G.711, or ADPCM in WAVE, to 10 ms. The decoder allocates its output
+    /// buffer to fit the largest packet, therefore decoded blocks are limited likewise.
+    ///
+    /// Packets of framed codecs, such as Opus, are always demuxed whole.
+    pub fn with_low_latency(mut self) -> Self {
+        self.mss_opts = MediaSourceStreamOptions::low_latency();
+        self.format_opts.max_packet_duration = FormatOptions::low_latency().max_packet_duration;
+        self
+    }
+
+    /// Use the provided `MetadataOptions`.
+    pub fn with_metadata_options(mut self, options: MetadataOptions) -> Self {
+        self.metadata_opts = options;
+        self
+    }
+
+    /// Use the provided `DecoderOptions`.
+    pub fn with_decoder_options(mut self, options: DecoderOptions) -> Self {
+        self.decoder_opts = options;
+        self
+    }
+
+    /// Use the provided `ProbeOptions` to bound the probe.
+    pub fn with_probe_options(mut self, options: ProbeOptions) -> Self {
+        self.probe_opts = options;
+        self
+    }
+
+    /// Prefer tracks in the provided language when selecting the default track. See
+    /// `FormatReader::default_track_for_language`.
+    pub fn with_language(mut self, language: &str) -> Self {
+        self.language = Some(language.to_string());
+        self
+    }
+
+    /// Use the provided `Probe` instead of the default probe.
+    pub fn with_probe(mut self, probe: &'a Probe) -> Self {
+        self.probe = Some(probe);
+        self
+    }
+
+    /// Use the provided `CodecRegistry` instead of the default codec registry.
+    pub fn with_codecs(mut self, codecs: &'a CodecRegistry) -> Self {
+        self.codecs = Some(codecs);
+        self
+    }
+
+    /// Open the provided `MediaSource`.
+    pub fn open<S: MediaSource + 'static>(mut self, source: S) -> Result<OpenedMedia> {
+        let mss_opts = std::mem::take(&mut self.mss_opts);
+        let mss = MediaSourceStream::new(Box::new(source), mss_opts);
+        self.open_stream(mss)
+    }
+
+    /// Open the provided `MediaSourceStream`. The stream options of the `MediaOpener` are
+    /// ignored.
+    pub fn open_stream(self, mss: MediaSourceStream) -> Result<OpenedMedia> {
+        let is_seekable = mss.is_seekable();
+
+        let probe = self.probe.unwrap_or_else(|| crate::default::get_probe());
+        let codecs = self.codecs.unwrap_or_else(|| crate::default::get_codecs());
+
+        let probed = probe.format_with_options(
+            &self.hint,
+            mss,
+            &self.format_opts,
+            &self.metadata_opts,
+            &self.probe_opts,
+        )?;
+
+        let track = match &self.language {
+            Some(language) => probed.format.default_track_for_language(language),
+            None => probed.format.default_track(),
+        };
+
+        // The default track may still have an unknown codec if no track has a known codec.
+        let track = match track {
+            Some(track) if track.codec_params.codec != CODEC_TYPE_NULL => track,
+            _ => return unsupported_error("symphonia: no supported audio track"),
+        };
+
+        let track_id = track.id;
+        let decoder = codecs.make(&track.codec_params, &self.decoder_opts)?;
+
+        Ok(OpenedMedia {
+            format: probed.format,
+            metadata: probed.metadata,
+            track_id,
+            decoder,
+            is_seekable,
+        })
+    }
+}
+
+impl Default for MediaOpener<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(all(test, feature = "wav", feature = "pcm"))]
+mod tests {
+    use std::io::Cursor;
+
+    use super::MediaOpener;
+
+    #[test]
+    fn verify_low_latency() {
+        // One second of mono 16-bit PCM at 8kHz.
+        let mut wav = Vec::new();
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(36u32 + 16000).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&[16, 0, 0, 0, 1, 0, 1, 0]);
+        wav.extend_from_slice(&8000u32.to_le_bytes());
+        wav.extend_from_slice(&16000u32.to_le_bytes());
+        wav.extend_from_slice(&[2, 0, 16, 0]);
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&16000u32.to_le_bytes());
+        wav.resize(wav.len() + 16000, 0);
+
+        let mut media = MediaOpener::new().with_low_latency().open(Cursor::new(wav)).unwrap();
+
+        // 10 ms at 8kHz is 80 frames.
+        let packet = media.format.next_packet().unwrap();
+        assert_eq!(packet.dur(), 80);
+
+        let decoded = media.decoder.decode(&packet).unwrap();
+        assert_eq!(decoded.frames(), 80);
+        assert_eq!(decoded.capacity(), 80);
+    }
+}
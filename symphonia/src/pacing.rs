@@ -0,0 +1,224 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The `pacing` module provides an iterator that yields packets at the rate they would be
+//! played.
+//!
+//! [`PacedPackets`] turns a media file into a realistic stream of packets, which may be used to
+//! simulate a live source when testing downstream streaming components. The packet rate may be
+//! accelerated, and a [`ManualClock`] may be used to run the simulation deterministically without
+//! waiting.
+
+use std::io;
+use std::time::{Duration, Instant};
+
+use symphonia_core::errors::{Error, Result};
+use symphonia_core::formats::{FormatReader, Packet};
+
+/// A `Clock` provides the time to a [`PacedPackets`] iterator.
+pub trait Clock {
+    /// Gets the time elapsed since an arbitrary, but fixed, epoch.
+    fn now(&self) -> Duration;
+
+    /// Waits until `now()` is at least the provided time.
+    fn sleep_until(&mut self, time: Duration);
+}
+
+/// A `Clock` that follows wall-clock time.
+pub struct SystemClock {
+    epoch: Instant,
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        SystemClock { epoch: Instant::now() }
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.epoch.elapsed()
+    }
+
+    fn sleep_until(&mut self, time: Duration) {
+        let now = self.now();
+
+        if time > now {
+            std::thread::sleep(time - now);
+        }
+    }
+}
+
+/// A `Clock` that only advances when it is slept on, or advanced manually. Sleeping returns
+/// immediately, making paced iteration deterministic.
+#[derive(Clone, Debug, Default)]
+pub struct ManualClock {
+    now: Duration,
+}
+
+impl ManualClock {
+    /// Advances the clock by the provided duration.
+    pub fn advance(&mut self, duration: Duration) {
+        self.now += duration;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        self.now
+    }
+
+    fn sleep_until(&mut self, time: Duration) {
+        self.now = self.now.max(time);
+    }
+}
+
+/// `PacedPackets` is an iterator over the packets of a `FormatReader` that yields each packet no
+/// sooner than its timestamp, relative to the first packet, divided by the speed.
+///
+/// Packets of tracks without a known time base are yielded immediately. Iteration ends at the end
+/// of the stream, and all other errors are yielded.
+pub struct PacedPackets<C: Clock = SystemClock> {
+    format: Box<dyn FormatReader>,
+    clock: C,
+    speed: f64,
+    track_id: Option<u32>,
+    /// The clock time, and the media time in seconds, of the first packet.
+    origin: Option<(Duration, f64)>,
+}
+
+impl PacedPackets<SystemClock> {
+    /// Instantiate a new `PacedPackets` iterator over all packets of the `FormatReader`, paced in
+    /// real-time.
+    pub fn new(format: Box<dyn FormatReader>) -> Self {
+        PacedPackets::with_clock(format, Default::default())
+    }
+}
+
+impl<C: Clock> PacedPackets<C> {
+    /// Instantiate a new `PacedPackets` iterator over all packets of the `FormatReader`, paced in
+    /// real-time by the provided clock.
+    pub fn with_clock(format: Box<dyn FormatReader>, clock: C) -> Self {
+        PacedPackets { format, clock, speed: 1.0, track_id: None, origin: None }
+    }
+
+    /// Accelerate, or slow, the pace by the provided factor. For example, a speed of 2.0 yields
+    /// packets twice as fast as real-time. The speed must be positive.
+    pub fn with_speed(mut self, speed: f64) -> Self {
+        assert!(speed > 0.0, "speed must be positive");
+        self.speed = speed;
+        self
+    }
+
+    /// Only yield the packets of the track with the provided ID.
+    pub fn with_track(mut self, track_id: u32) -> Self {
+        self.track_id = Some(track_id);
+        self
+    }
+
+    /// Gets a reference to the clock.
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+
+    /// Unwraps this `PacedPackets`, returning the `FormatReader`.
+    pub fn into_inner(self) -> Box<dyn FormatReader> {
+        self.format
+    }
+
+    /// Gets the timestamp of the packet in seconds, if the time base of its track is known.
+    fn packet_time(&self, packet: &Packet) -> Option<f64> {
+        let track = self.format.tracks().iter().find(|track| track.id == packet.track_id())?;
+        let time = track.codec_params.time_base?.calc_time(packet.ts());
+        Some(time.seconds as f64 + time.frac)
+    }
+}
+
+impl<C: Clock> Iterator for PacedPackets<C> {
+    type Item = Result<Packet>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let packet = loop {
+            match self.format.next_packet() {
+                Ok(packet) if self.track_id.map_or(true, |id| id == packet.track_id()) => {
+                    break packet
+                }
+                Ok(_) => (),
+                Err(Error::IoError(err)) if err.kind() == io::ErrorKind::UnexpectedEof => {
+                    return None
+                }
+                Err(err) => return Some(Err(err)),
+            }
+        };
+
+        if let Some(time) = self.packet_time(&packet) {
+            match self.origin {
+                Some((clock_origin, media_origin)) => {
+                    // Packets may be out-of-order, or precede the first packet. Never wait for
+                    // those.
+                    let delay = ((time - media_origin) / self.speed).max(0.0);
+                    self.clock.sleep_until(clock_origin + Duration::from_secs_f64(delay));
+                }
+                None => self.origin = Some((self.clock.now(), time)),
+            }
+        }
+
+        Some(Ok(packet))
+    }
+}
+
+#[cfg(all(test, feature = "wav", feature = "pcm"))]
+mod tests {
+    use std::io::Cursor;
+    use std::time::Duration;
+
+    use symphonia_core::io::MediaSourceStream;
+
+    use super::{ManualClock, PacedPackets};
+
+    /// Builds a mono 8-bit WAV file with the provided number of frames at 8kHz.
+    fn make_wav(n_frames: u32) -> Vec<u8> {
+        let mut wav = Vec::new();
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(36 + n_frames).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        wav.extend_from_slice(&[1, 0, 1, 0]);
+        wav.extend_from_slice(&8000u32.to_le_bytes());
+        wav.extend_from_slice(&8000u32.to_le_bytes());
+        wav.extend_from_slice(&[1, 0, 8, 0]);
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&n_frames.to_le_bytes());
+        wav.resize(wav.len() + n_frames as usize, 0x80);
+        wav
+    }
+
+    #[test]
+    fn verify_paced_packets() {
+        let mss =
+            MediaSourceStream::new(Box::new(Cursor::new(make_wav(16000))), Default::default());
+
+        let probed = crate::default::get_probe()
+            .format(&Default::default(), mss, &Default::default(), &Default::default())
+            .unwrap();
+
+        // Two seconds of audio at double speed takes one second, less the duration of the last
+        // packet.
+        let mut paced =
+            PacedPackets::with_clock(probed.format, ManualClock::default()).with_speed(2.0);
+
+        let mut last_ts = 0;
+
+        for packet in &mut paced {
+            last_ts = packet.unwrap().ts();
+        }
+
+        let expected = Duration::from_secs_f64(last_ts as f64 / 8000.0 / 2.0);
+        assert_eq!(paced.clock().now, expected);
+        assert!(expected < Duration::from_secs(1));
+    }
+}
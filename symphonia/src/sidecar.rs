@@ -0,0 +1,293 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The `sidecar` module loads metadata from sidecar files stored next to a media file.
+//!
+//! Cuesheets (`.cue`) and synchronized lyrics (`.lrc`) are commonly distributed as separate files
+//! alongside the media they describe. [`Sidecars::load`] finds and reads these files, and
+//! [`Sidecars::merge`] combines them with the embedded metadata of opened media into a single
+//! list of tags and cues.
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use symphonia_core::errors::{Error, Result};
+use symphonia_core::formats::{Cue, Cuesheet};
+use symphonia_core::io::MediaSourceStream;
+use symphonia_core::meta::Tag;
+use symphonia_metadata::cuesheet::read_cuesheet;
+use symphonia_metadata::lrc::{read_lrc, Lyrics};
+
+use crate::opener::OpenedMedia;
+
+/// `SidecarOptions` selects the kinds of sidecar files to load.
+#[derive(Copy, Clone, Debug)]
+pub struct SidecarOptions {
+    /// Load a cuesheet (`.cue`). Default: `true`.
+    pub cuesheet: bool,
+    /// Load synchronized lyrics (`.lrc`). Default: `true`.
+    pub lyrics: bool,
+}
+
+impl Default for SidecarOptions {
+    fn default() -> Self {
+        SidecarOptions { cuesheet: true, lyrics: true }
+    }
+}
+
+/// The sidecar files of a media file.
+#[derive(Default)]
+pub struct Sidecars {
+    /// The file name of the media file.
+    media_file_name: Option<String>,
+    /// The path of the cuesheet, if one was found.
+    pub cuesheet_path: Option<PathBuf>,
+    /// The cuesheet, if one was found and could be read.
+    pub cuesheet: Option<Cuesheet>,
+    /// The path of the lyrics file, if one was found.
+    pub lyrics_path: Option<PathBuf>,
+    /// The lyrics, if a lyrics file was found and could be read.
+    pub lyrics: Option<Lyrics>,
+    /// The errors encountered reading the sidecar files that were found.
+    pub errors: Vec<(PathBuf, Error)>,
+}
+
+/// The merged embedded and sidecar metadata of a media file.
+#[derive(Clone, Debug, Default)]
+pub struct MergedMetadata {
+    /// The tags of the media. Embedded tags are listed first, followed by any sidecar tags that
+    /// are not also embedded.
+    pub tags: Vec<Tag>,
+    /// The cues of the media. These are the embedded cues if there are any, otherwise they are
+    /// read from the cuesheet.
+    pub cues: Vec<Cue>,
+    /// The synchronized lyrics of the media. Each line of lyrics is a cue with a `Lyrics` tag.
+    pub lyrics: Vec<Cue>,
+}
+
+/// Gets the paths a sidecar with the provided extension may have for a media file. Both
+/// replacing the extension of the media file (`song.cue`), and appending to it (`song.flac.cue`),
+/// are common.
+fn candidate_paths(media_path: &Path, extension: &str) -> Vec<PathBuf> {
+    let mut paths = vec![media_path.with_extension(extension)];
+
+    if media_path.extension().is_some() {
+        let mut name = media_path.as_os_str().to_owned();
+        name.push(".");
+        name.push(extension);
+        paths.push(PathBuf::from(name));
+    }
+
+    paths
+}
+
+fn open_sidecar(path: &Path) -> Result<MediaSourceStream> {
+    let file = File::open(path)?;
+    Ok(MediaSourceStream::new(Box::new(file), Default::default()))
+}
+
+/// Returns true if two tags describe the same property.
+fn is_same_tag(a: &Tag, b: &Tag) -> bool {
+    match (a.std_key, b.std_key) {
+        (Some(a), Some(b)) => a == b,
+        _ => a.key.eq_ignore_ascii_case(&b.key),
+    }
+}
+
+impl Sidecars {
+    /// Finds and reads the sidecar files of the media file at the provided path.
+    ///
+    /// Sidecar files that do not exist are skipped. A sidecar file that exists, but could not be
+    /// read, is recorded in `errors`.
+    pub fn load(media_path: &Path, options: &SidecarOptions) -> Sidecars {
+        let mut sidecars = Sidecars {
+            media_file_name: media_path.file_name().map(|name| name.to_string_lossy().into_owned()),
+            ..Default::default()
+        };
+
+        let find = |extension| {
+            candidate_paths(media_path, extension).into_iter().find(|path| path.is_file())
+        };
+
+        if options.cuesheet {
+            if let Some(path) = find("cue") {
+                match open_sidecar(&path).and_then(|mut mss| read_cuesheet(&mut mss)) {
+                    Ok(cuesheet) => sidecars.cuesheet = Some(cuesheet),
+                    Err(err) => sidecars.errors.push((path.clone(), err)),
+                }
+                sidecars.cuesheet_path = Some(path);
+            }
+        }
+
+        if options.lyrics {
+            if let Some(path) = find("lrc") {
+                match open_sidecar(&path).and_then(|mut mss| read_lrc(&mut mss)) {
+                    Ok(lyrics) => sidecars.lyrics = Some(lyrics),
+                    Err(err) => sidecars.errors.push((path.clone(), err)),
+                }
+                sidecars.lyrics_path = Some(path);
+            }
+        }
+
+        sidecars
+    }
+
+    /// Gets the tags of all sidecar files.
+    ///
+    /// The disc-level tags of a cuesheet are included. If the cuesheet describes a single track,
+    /// the tags of that track are also included.
+    pub fn tags(&self) -> Vec<Tag> {
+        let mut tags = Vec::new();
+
+        if let Some(cuesheet) = &self.cuesheet {
+            tags.extend(cuesheet.tags.iter().cloned());
+
+            if let [track] = cuesheet.tracks.as_slice() {
+                tags.extend(track.tags.iter().cloned());
+            }
+        }
+
+        if let Some(lyrics) = &self.lyrics {
+            tags.extend(lyrics.tags.iter().cloned());
+        }
+
+        tags
+    }
+
+    /// Gets the cues of the cuesheet given the sample rate of the media.
+    ///
+    /// Only the tracks of the cuesheet that are stored in the media file are included.
+    pub fn cues(&self, sample_rate: u32) -> Vec<Cue> {
+        let cuesheet = match &self.cuesheet {
+            Some(cuesheet) => cuesheet,
+            None => return Vec::new(),
+        };
+
+        // The file of a track is a path relative to the cuesheet. Only compare the file names.
+        let is_media_file = |file: &Option<String>| match (file, &self.media_file_name) {
+            (Some(file), Some(media)) => {
+                let name = Path::new(file).file_name().map(|name| name.to_string_lossy());
+                name.map_or(false, |name| name.eq_ignore_ascii_case(media))
+            }
+            _ => true,
+        };
+
+        let tracks = cuesheet.tracks.iter().filter(|track| is_media_file(&track.file)).cloned();
+
+        let cuesheet = Cuesheet {
+            catalog: cuesheet.catalog.clone(),
+            tags: cuesheet.tags.clone(),
+            tracks: tracks.collect(),
+        };
+
+        cuesheet.to_cues(sample_rate)
+    }
+
+    /// Gets the synchronized lyrics, as cues, given the sample rate of the media.
+    pub fn lyric_cues(&self, sample_rate: u32) -> Vec<Cue> {
+        self.lyrics.as_ref().map_or_else(Vec::new, |lyrics| lyrics.to_cues(sample_rate))
+    }
+
+    /// Merges the embedded metadata of the opened media with the metadata of the sidecar files.
+    ///
+    /// Embedded tags and cues take precedence over those of the sidecar files.
+    pub fn merge(&self, media: &mut OpenedMedia) -> MergedMetadata {
+        let mut merged = MergedMetadata::default();
+
+        if let Some(mut metadata) = media.metadata.get() {
+            if let Some(rev) = metadata.skip_to_latest() {
+                merged.tags.extend(rev.tags().iter().cloned());
+            }
+        }
+
+        if let Some(rev) = media.format.metadata().skip_to_latest() {
+            merged.tags.extend(rev.tags().iter().cloned());
+        }
+
+        for tag in self.tags() {
+            if !merged.tags.iter().any(|embedded| is_same_tag(embedded, &tag)) {
+                merged.tags.push(tag);
+            }
+        }
+
+        let sample_rate = media
+            .format
+            .tracks()
+            .iter()
+            .find(|track| track.id == media.track_id)
+            .and_then(|track| track.codec_params.sample_rate);
+
+        merged.cues = media.format.cues().to_vec();
+
+        if let Some(sample_rate) = sample_rate {
+            if merged.cues.is_empty() {
+                merged.cues = self.cues(sample_rate);
+            }
+
+            merged.lyrics = self.lyric_cues(sample_rate);
+        }
+
+        merged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use symphonia_core::meta::StandardTagKey;
+
+    use super::{candidate_paths, SidecarOptions, Sidecars};
+
+    #[test]
+    fn verify_load_sidecars() {
+        let dir = std::env::temp_dir().join(format!("symphonia-sidecar-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let media_path = dir.join("Song.flac");
+
+        std::fs::write(
+            dir.join("Song.flac.cue"),
+            "PERFORMER \"Band\"\nFILE \"Song.flac\" WAVE\n  TRACK 01 AUDIO\n    \
+             TITLE \"Song\"\n    INDEX 01 00:00:00\nFILE \"Other.flac\" WAVE\n  \
+             TRACK 02 AUDIO\n    \
+             INDEX 01 00:00:00\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("Song.lrc"), "[ar:Band]\n[al:Album]\n[00:01.00]Hello\n").unwrap();
+
+        let sidecars = Sidecars::load(&media_path, &SidecarOptions::default());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(sidecars.errors.is_empty());
+        assert_eq!(sidecars.cuesheet_path, Some(dir.join("Song.flac.cue")));
+        assert_eq!(sidecars.lyrics_path, Some(dir.join("Song.lrc")));
+
+        // Only the track stored in the media file is a cue.
+        let cues = sidecars.cues(44100);
+        assert_eq!(cues.len(), 1);
+        assert_eq!(cues[0].index, 1);
+
+        let lyrics = sidecars.lyric_cues(44100);
+        assert_eq!(lyrics[0].start_ts, 44100);
+        assert_eq!(lyrics[0].tags[0].value.to_string(), "Hello");
+
+        let tags = sidecars.tags();
+        assert_eq!(tags[0].std_key, Some(StandardTagKey::AlbumArtist));
+        assert!(tags.iter().any(|tag| tag.std_key == Some(StandardTagKey::Album)));
+    }
+
+    #[test]
+    fn verify_candidate_paths() {
+        assert_eq!(
+            candidate_paths(Path::new("dir/a.mp3"), "lrc"),
+            vec![Path::new("dir/a.lrc"), Path::new("dir/a.mp3.lrc")]
+        );
+        assert_eq!(candidate_paths(Path::new("a"), "cue"), vec![Path::new("a.cue")]);
+    }
+}